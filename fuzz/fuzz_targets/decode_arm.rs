@@ -0,0 +1,7 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|op: u32| {
+    rgba::fuzz::fuzz_decode_arm(op);
+});
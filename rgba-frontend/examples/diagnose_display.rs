@@ -0,0 +1,58 @@
+//! Print the decoded display registers after running a ROM, to see why a
+//! screen comes out blank or wrong
+//!
+//! Run with: `cargo run -p rgba-frontend --example diagnose_display -- game.gba [frames]`
+
+use rgba::{io, Gba};
+
+const DISPLAY_REGISTERS: [&str; 12] = [
+    "DISPCNT", "DISPSTAT", "VCOUNT", "BG0CNT", "BG1CNT", "BG2CNT", "BG3CNT", "WININ", "WINOUT",
+    "MOSAIC", "BLDCNT", "BLDALPHA",
+];
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let Some(rom_path) = args.get(1) else {
+        eprintln!("Usage: diagnose_display <rom_path> [frames]");
+        std::process::exit(1);
+    };
+    let frames: u32 = args.get(2).and_then(|n| n.parse().ok()).unwrap_or(60);
+
+    let mut gba = Gba::new();
+    if let Err(e) = gba.load_rom_path(rom_path) {
+        eprintln!("Error reading ROM '{}': {}", rom_path, e);
+        std::process::exit(1);
+    }
+    for _ in 0..frames {
+        gba.run_frame();
+    }
+
+    println!("After {} frames:", frames);
+    for name in DISPLAY_REGISTERS {
+        let reg = io::by_name(name).and_then(|info| gba.io_register(info.addr()));
+        if let Some(reg) = reg {
+            println!("  {}", reg);
+        }
+    }
+
+    let Some(dispcnt) = gba.io_register(0x0400_0000) else {
+        return;
+    };
+    let enabled = |field: &str| dispcnt.field(field) == Some(1);
+    let mode = dispcnt.field("BG Mode").unwrap_or(0);
+    let layers: Vec<&str> = ["BG0", "BG1", "BG2", "BG3", "OBJ"]
+        .into_iter()
+        .filter(|layer| enabled(&format!("Screen Display {}", layer)))
+        .collect();
+
+    println!();
+    println!("Mode {}, layers: {}", mode, layers.join(" "));
+    if enabled("Forced Blank") {
+        println!("Forced blank is set: the screen is white");
+    } else if layers.is_empty() {
+        println!("No layer is enabled: the screen shows the backdrop color");
+    }
+    if mode > 5 {
+        println!("Mode {} is prohibited", mode);
+    }
+}
@@ -0,0 +1,213 @@
+//! Reference minifb frontend
+//!
+//! Run with: `cargo run -p rgba-frontend --release --features gui --example gui_emulator -- game.gba [bios.bin]`
+//!
+//! | Key        | Action                       |
+//! |------------|------------------------------|
+//! | Arrows     | D-Pad                        |
+//! | Z / X      | A / B                        |
+//! | Enter      | Start                        |
+//! | Backspace  | Select                       |
+//! | A / S      | L / R                        |
+//! | P          | Pause / resume               |
+//! | N          | Step one scanline (paused)   |
+//! | - / =      | Slow motion down / up        |
+//! | 1-5        | Toggle BG0-BG3 / OBJ layer   |
+//! | R          | Reset                        |
+//! | F5 / F7    | Save / load state            |
+//! | Tab (hold) | Fast-forward                 |
+//! | Esc        | Quit                         |
+//!
+//! minifb has no drag-and-drop events, so ROMs are passed on the command line;
+//! pass a different path to switch games.
+
+use minifb::{Key, KeyRepeat, Window, WindowOptions};
+use rgba::frontends::scale::{self, Filter, FitMode};
+use rgba::{Gba, KeyState, Layer, Throttle};
+use std::time::{Duration, Instant};
+
+const WIDTH: usize = 240;
+const HEIGHT: usize = 160;
+const FRAME_TIME: Duration = Duration::from_nanos(16_742_706); // 280896 cycles @ 16.78 MHz
+const FAST_FORWARD_FRAMES: u32 = 4;
+const MIN_SPEED: f64 = 1.0 / 64.0;
+const LAYER_KEYS: [(Key, Layer); 5] = [
+    (Key::Key1, Layer::Bg0),
+    (Key::Key2, Layer::Bg1),
+    (Key::Key3, Layer::Bg2),
+    (Key::Key4, Layer::Bg3),
+    (Key::Key5, Layer::Obj),
+];
+
+const KEY_MAP: [(Key, KeyState); 10] = [
+    (Key::Up, KeyState::UP),
+    (Key::Down, KeyState::DOWN),
+    (Key::Left, KeyState::LEFT),
+    (Key::Right, KeyState::RIGHT),
+    (Key::Z, KeyState::A),
+    (Key::X, KeyState::B),
+    (Key::Enter, KeyState::START),
+    (Key::Backspace, KeyState::SELECT),
+    (Key::A, KeyState::L),
+    (Key::S, KeyState::R),
+];
+
+fn load_gba(rom_path: &str, bios_path: Option<&str>) -> Gba {
+    let mut gba = Gba::new();
+    if let Some(bios) = bios_path {
+        if let Err(e) = gba.load_bios_path(bios) {
+            eprintln!("Warning: failed to load BIOS '{}': {}", bios, e);
+        }
+    }
+    if let Err(e) = gba.load_rom_path(rom_path) {
+        eprintln!("Error reading ROM '{}': {}", rom_path, e);
+        std::process::exit(1);
+    }
+    gba
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let Some(rom_path) = args.get(1) else {
+        eprintln!("Usage: gui_emulator <rom_path> [bios_path]");
+        std::process::exit(1);
+    };
+    let bios_path = args.get(2).map(String::as_str);
+    let state_path = format!("{}.state", rom_path);
+
+    let mut gba = load_gba(rom_path, bios_path);
+
+    let mut window = Window::new(
+        "RGBA",
+        WIDTH * 3,
+        HEIGHT * 3,
+        WindowOptions {
+            resize: true,
+            ..WindowOptions::default()
+        },
+    )
+    .unwrap_or_else(|e| {
+        eprintln!("Failed to create window: {}", e);
+        std::process::exit(1);
+    });
+
+    let mut frame = vec![0u32; WIDTH * HEIGHT];
+    let mut buffer = Vec::new();
+    let mut paused = false;
+    let mut status = String::new();
+    let mut frame_count = 0u32;
+    let mut fps = 0.0;
+    let mut fps_timer = Instant::now();
+    let mut throttle = Throttle::new(1.0);
+    let mut speed: f64 = 1.0;
+
+    while window.is_open() && !window.is_key_down(Key::Escape) {
+        // Edge-triggered hotkeys
+        if window.is_key_pressed(Key::P, KeyRepeat::No) {
+            paused = !paused;
+        }
+        if window.is_key_pressed(Key::Minus, KeyRepeat::No) {
+            speed = (speed / 2.0).max(MIN_SPEED);
+            throttle.set_speed(speed, gba.cycles_elapsed());
+        }
+        if window.is_key_pressed(Key::Equal, KeyRepeat::No) {
+            speed = (speed * 2.0).min(1.0);
+            throttle.set_speed(speed, gba.cycles_elapsed());
+        }
+        for (key, layer) in LAYER_KEYS {
+            if window.is_key_pressed(key, KeyRepeat::No) {
+                let enabled = !gba.layer_enabled(layer);
+                gba.set_layer_enabled(layer, enabled);
+                status = format!("{:?} {}", layer, if enabled { "shown" } else { "hidden" });
+            }
+        }
+        if window.is_key_pressed(Key::R, KeyRepeat::No) {
+            gba.reset();
+            status = "reset".to_string();
+        }
+        if window.is_key_pressed(Key::F5, KeyRepeat::No) {
+            status = match gba.save_state_path(&state_path) {
+                Ok(()) => format!("saved {}", state_path),
+                Err(e) => format!("save failed: {}", e),
+            };
+        }
+        if window.is_key_pressed(Key::F7, KeyRepeat::No) {
+            status = match gba.load_state_path(&state_path) {
+                Ok(()) => format!("loaded {}", state_path),
+                Err(e) => format!("load failed: {}", e),
+            };
+        }
+
+        // Level-triggered GBA buttons
+        let pressed = KEY_MAP
+            .iter()
+            .filter(|(kb_key, _)| window.is_key_down(*kb_key))
+            .fold(KeyState::empty(), |keys, (_, gba_key)| keys | *gba_key);
+        gba.input_mut().set_all_keys(pressed.bits());
+
+        let fast_forward = window.is_key_down(Key::Tab);
+        // Slow motion and single-stepping show each line as it is drawn
+        let line_by_line = paused || speed < 1.0;
+        gba.set_scanline_capture(line_by_line);
+        if paused {
+            if window.is_key_pressed(Key::N, KeyRepeat::Yes) {
+                gba.run_scanlines(1);
+            }
+        } else if line_by_line {
+            gba.run_scanlines(((228.0 * speed) as u32).max(1));
+            frame_count += 1;
+        } else {
+            let frames = if fast_forward { FAST_FORWARD_FRAMES } else { 1 };
+            for _ in 0..frames {
+                gba.run_frame();
+            }
+            gba.framebuffer_xrgb8888(&mut frame);
+            frame_count += frames;
+        }
+        if let Some(capture) = gba.scanline_capture() {
+            frame.copy_from_slice(capture);
+        }
+
+        let (win_w, win_h) = window.get_size();
+        let (win_w, win_h) = (win_w.max(1), win_h.max(1));
+        buffer.resize(win_w * win_h, 0);
+        scale::scale_into(
+            &frame,
+            WIDTH as u32,
+            HEIGHT as u32,
+            &mut buffer,
+            win_w as u32,
+            win_h as u32,
+            FitMode::Integer,
+            Filter::Nearest,
+        );
+        if window.update_with_buffer(&buffer, win_w, win_h).is_err() {
+            break;
+        }
+
+        if fps_timer.elapsed() >= Duration::from_secs(1) {
+            fps = frame_count as f64 / fps_timer.elapsed().as_secs_f64();
+            frame_count = 0;
+            fps_timer = Instant::now();
+        }
+        let mode = if paused {
+            " [paused]".to_string()
+        } else if fast_forward {
+            " [fast]".to_string()
+        } else if speed < 1.0 {
+            format!(" [{}%]", speed * 100.0)
+        } else {
+            String::new()
+        };
+        window.set_title(&format!("RGBA ({:.1} FPS){} {}", fps, mode, status));
+
+        if paused || fast_forward {
+            throttle.reset(gba.cycles_elapsed());
+        }
+        if paused {
+            std::thread::sleep(FRAME_TIME);
+        } else {
+            throttle.wait(gba.cycles_elapsed());
+        }
+    }
+}
@@ -0,0 +1,335 @@
+//! Terminal debugger built on the breakpoint, disassembler and step APIs
+//!
+//! Run with: `cargo run -p rgba-frontend --features tui --example tui_debugger -- game.gba [bios.bin]`
+//!
+//! Keys: `s` step, `c` continue/pause, `r` reset, `↑/↓` move the disassembly
+//! cursor, `b` toggle a breakpoint at the cursor, `PgUp/PgDn` scroll memory,
+//! `:` enter a command, `q` quit.
+//!
+//! Commands: `b ADDR` add breakpoint, `d ADDR` delete breakpoint,
+//! `m ADDR` show memory, `io REG` decode an I/O register (name or address),
+//! `run N` run N frames.
+
+use std::io as stdio;
+use std::time::Duration;
+
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::execute;
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen};
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Paragraph};
+use ratatui::{Frame, Terminal};
+use rgba::{io, Gba, Mode};
+
+const FRAME_CYCLES: u32 = 280896;
+const DISASM_LINES: u32 = 24;
+const MEM_ROWS: u32 = 12;
+
+struct Debugger {
+    gba: Gba,
+    running: bool,
+    cursor: Option<u32>,
+    mem_addr: u32,
+    command: Option<String>,
+    status: String,
+}
+
+impl Debugger {
+    fn thumb(&self) -> bool {
+        self.gba.cpu_get_cpsr() & (1 << 5) != 0
+    }
+
+    fn pc(&self) -> u32 {
+        self.gba.cpu().next_instruction_pc()
+    }
+
+    fn step_size(&self) -> u32 {
+        if self.thumb() {
+            2
+        } else {
+            4
+        }
+    }
+
+    fn cursor(&self) -> u32 {
+        self.cursor.unwrap_or_else(|| self.pc())
+    }
+
+    fn run_command(&mut self, line: &str) {
+        let mut parts = line.split_whitespace();
+        let cmd = parts.next().unwrap_or("");
+        let arg = parts.next().map(|a| a.trim_start_matches("0x"));
+        let addr = arg.and_then(|a| u32::from_str_radix(a, 16).ok());
+
+        self.status = match (cmd, addr) {
+            ("b", Some(a)) => {
+                self.gba.add_breakpoint(a);
+                format!("breakpoint at {:08X}", a)
+            }
+            ("d", Some(a)) => {
+                if self.gba.remove_breakpoint(a) {
+                    format!("removed breakpoint {:08X}", a)
+                } else {
+                    format!("no breakpoint at {:08X}", a)
+                }
+            }
+            ("m", Some(a)) => {
+                self.mem_addr = a & !0xF;
+                format!("memory at {:08X}", self.mem_addr)
+            }
+            ("io", _) => self.describe_io(arg.unwrap_or("")),
+            ("run", _) => {
+                let frames = arg.and_then(|a| a.parse().ok()).unwrap_or(1);
+                self.run_frames(frames)
+            }
+            _ => format!("unknown command '{}'", line),
+        };
+    }
+
+    fn describe_io(&self, reg: &str) -> String {
+        // A name, a bus address or an offset into I/O space
+        let info = io::by_name(reg).or_else(|| {
+            let n = u32::from_str_radix(reg, 16).ok()?;
+            io::describe(n).or_else(|| io::describe_offset(n))
+        });
+        match info.and_then(|info| self.gba.io_register(info.addr())) {
+            Some(value) => value.to_string(),
+            None => format!("unknown I/O register '{}'", reg),
+        }
+    }
+
+    fn run_frames(&mut self, frames: u32) -> String {
+        for _ in 0..frames {
+            if let Some(bp) = self.gba.run_until_breakpoint(FRAME_CYCLES) {
+                self.running = false;
+                return format!("hit breakpoint {:08X}", bp);
+            }
+        }
+        format!("ran {} frame(s)", frames)
+    }
+
+    fn handle_key(&mut self, code: KeyCode) -> bool {
+        if let Some(ref mut input) = self.command {
+            match code {
+                KeyCode::Enter => {
+                    let line = input.clone();
+                    self.command = None;
+                    self.run_command(&line);
+                }
+                KeyCode::Esc => self.command = None,
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Char(c) => input.push(c),
+                _ => {}
+            }
+            return true;
+        }
+
+        match code {
+            KeyCode::Char('q') => return false,
+            KeyCode::Char('s') => {
+                self.running = false;
+                self.gba.step();
+                self.cursor = None;
+            }
+            KeyCode::Char('c') => {
+                self.running = !self.running;
+                self.cursor = None;
+                self.status = if self.running { "running" } else { "paused" }.to_string();
+            }
+            KeyCode::Char('r') => {
+                self.gba.reset();
+                self.cursor = None;
+                self.status = "reset".to_string();
+            }
+            KeyCode::Char('b') => {
+                let addr = self.cursor();
+                if !self.gba.remove_breakpoint(addr) {
+                    self.gba.add_breakpoint(addr);
+                }
+            }
+            KeyCode::Char(':') => self.command = Some(String::new()),
+            KeyCode::Up => self.cursor = Some(self.cursor().wrapping_sub(self.step_size())),
+            KeyCode::Down => self.cursor = Some(self.cursor().wrapping_add(self.step_size())),
+            KeyCode::PageUp => self.mem_addr = self.mem_addr.wrapping_sub(MEM_ROWS * 16),
+            KeyCode::PageDown => self.mem_addr = self.mem_addr.wrapping_add(MEM_ROWS * 16),
+            _ => {}
+        }
+        true
+    }
+
+    fn draw(&mut self, f: &mut Frame) {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(10), Constraint::Length(3)])
+            .split(f.area());
+        let cols = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(55), Constraint::Percentage(45)])
+            .split(rows[0]);
+        let right = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(12), Constraint::Min(5)])
+            .split(cols[1]);
+
+        f.render_widget(self.disassembly(), cols[0]);
+        f.render_widget(self.registers(), right[0]);
+        f.render_widget(self.memory(), right[1]);
+
+        let footer = match self.command {
+            Some(ref input) => format!(":{}", input),
+            None => format!(
+                "{}  |  s step  c run/pause  b breakpoint  : command  q quit",
+                self.status
+            ),
+        };
+        f.render_widget(
+            Paragraph::new(footer).block(Block::default().borders(Borders::ALL)),
+            rows[1],
+        );
+    }
+
+    fn disassembly(&mut self) -> Paragraph<'static> {
+        let thumb = self.thumb();
+        let size = self.step_size();
+        let pc = self.pc();
+        let cursor = self.cursor();
+        let start = cursor.wrapping_sub(size * (DISASM_LINES / 2));
+
+        let lines: Vec<Line> = (0..DISASM_LINES)
+            .map(|i| {
+                let addr = start.wrapping_add(i * size);
+                let (op, text) = self.gba.disassemble_at(addr, thumb);
+                let bp = if self.gba.breakpoints().contains(&addr) { '*' } else { ' ' };
+                let marker = if addr == pc { '>' } else { ' ' };
+                let raw = if thumb {
+                    format!("    {:04X}", op)
+                } else {
+                    format!("{:08X}", op)
+                };
+                let mut style = Style::default();
+                if addr == pc {
+                    style = style.fg(Color::Yellow).add_modifier(Modifier::BOLD);
+                }
+                if addr == cursor {
+                    style = style.bg(Color::DarkGray);
+                }
+                Line::styled(format!("{}{} {:08X}  {}  {}", bp, marker, addr, raw, text), style)
+            })
+            .collect();
+
+        let title = if thumb { " Disassembly (Thumb) " } else { " Disassembly (ARM) " };
+        Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(title))
+    }
+
+    fn registers(&self) -> Paragraph<'static> {
+        let mut lines = Vec::new();
+        for row in 0..4 {
+            let regs: Vec<String> = (0..4)
+                .map(|col| {
+                    let n = row * 4 + col;
+                    format!("r{:<2} {:08X}", n, self.gba.cpu_reg(n))
+                })
+                .collect();
+            lines.push(Line::from(regs.join("  ")));
+        }
+
+        let cpsr = self.gba.cpu_get_cpsr();
+        let flag = |bit: u32, c: char| if cpsr & (1 << bit) != 0 { c } else { '-' };
+        lines.push(Line::from(""));
+        lines.push(Line::from(format!(
+            "CPSR {:08X}  {}{}{}{}  {}{}{}  {:?}",
+            cpsr,
+            flag(31, 'N'),
+            flag(30, 'Z'),
+            flag(29, 'C'),
+            flag(28, 'V'),
+            flag(7, 'I'),
+            flag(6, 'F'),
+            flag(5, 'T'),
+            Mode::from_bits(cpsr)
+        )));
+        let bps: Vec<String> = self.gba.breakpoints().iter().map(|a| format!("{:08X}", a)).collect();
+        lines.push(Line::from(format!("Breakpoints: {}", bps.join(" "))));
+
+        Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(" Registers "))
+    }
+
+    fn memory(&mut self) -> Paragraph<'static> {
+        let lines: Vec<Line> = (0..MEM_ROWS)
+            .map(|row| {
+                let base = self.mem_addr.wrapping_add(row * 16);
+                let bytes: Vec<u8> = (0..16).map(|i| self.gba.peek_byte(base.wrapping_add(i))).collect();
+                let hex: Vec<String> = bytes.iter().map(|b| format!("{:02X}", b)).collect();
+                let ascii: String = bytes
+                    .iter()
+                    .map(|&b| if b.is_ascii_graphic() { b as char } else { '.' })
+                    .collect();
+                Line::from(format!("{:08X}  {}  {}", base, hex.join(" "), ascii))
+            })
+            .collect();
+
+        Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(" Memory "))
+    }
+}
+
+fn main() -> stdio::Result<()> {
+    let args: Vec<String> = std::env::args().collect();
+    let Some(rom_path) = args.get(1) else {
+        eprintln!("Usage: tui_debugger <rom_path> [bios_path]");
+        std::process::exit(1);
+    };
+
+    let mut gba = Gba::new();
+    if let Some(bios) = args.get(2) {
+        if let Err(e) = gba.load_bios_path(bios) {
+            eprintln!("Warning: failed to load BIOS '{}': {}", bios, e);
+        }
+    }
+    if let Err(e) = gba.load_rom_path(rom_path) {
+        eprintln!("Error reading ROM '{}': {}", rom_path, e);
+        std::process::exit(1);
+    }
+
+    let mut dbg = Debugger {
+        gba,
+        running: false,
+        cursor: None,
+        mem_addr: 0x0300_0000,
+        command: None,
+        status: "paused".to_string(),
+    };
+
+    enable_raw_mode()?;
+    execute!(stdio::stdout(), EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdio::stdout()))?;
+
+    let result = (|| -> stdio::Result<()> {
+        loop {
+            terminal.draw(|f| dbg.draw(f))?;
+
+            if event::poll(Duration::from_millis(16))? {
+                if let Event::Key(key) = event::read()? {
+                    if key.kind == KeyEventKind::Press && !dbg.handle_key(key.code) {
+                        return Ok(());
+                    }
+                }
+            }
+
+            if dbg.running {
+                let msg = dbg.run_frames(1);
+                if !dbg.running {
+                    dbg.status = msg;
+                }
+            }
+        }
+    })();
+
+    disable_raw_mode()?;
+    execute!(stdio::stdout(), LeaveAlternateScreen)?;
+    result
+}
@@ -0,0 +1,13 @@
+//! Frontends for the rgba emulator core
+//!
+//! Everything that needs a window, a terminal or a GPU lives here so that
+//! `rgba-core` stays embeddable in WASM, libretro or embedded hosts. The
+//! `rgba` binary and the GUI and terminal debugger examples build on this
+//! crate; [`WgpuPresenter`] is available to other wgpu frontends with the
+//! `wgpu` feature.
+
+#[cfg(feature = "wgpu")]
+mod present;
+
+#[cfg(feature = "wgpu")]
+pub use present::{integer_viewport, PresentOptions, WgpuPresenter};
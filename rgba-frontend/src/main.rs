@@ -1,4 +1,4 @@
-use rgba::Gba;
+use rgba::{is_multiboot_path, Gba};
 use std::io::Write;
 
 fn rgb555_to_u8(color: u16) -> (u8, u8, u8) {
@@ -51,6 +51,7 @@ fn print_usage() {
     eprintln!("  --save-type <type>  Save type: sram, flash64, flash128, eeprom512, eeprom8k");
     #[cfg(feature = "gui")]
     eprintln!("  --gui               Run with graphical window");
+    eprintln!("  --version           Show core version and features");
     eprintln!("  --help              Show this help");
 }
 
@@ -68,6 +69,10 @@ fn parse_args(args: &[String]) -> Option<(String, Option<String>, u32, String, O
                 print_usage();
                 std::process::exit(0);
             }
+            "--version" | "-V" => {
+                println!("{}", rgba::build_info());
+                std::process::exit(0);
+            }
             "--bios" => {
                 i += 1;
                 bios_path = args.get(i).cloned();
@@ -126,7 +131,11 @@ fn run_headless(args: &[String]) {
         apply_save_type(&mut gba, st);
     }
 
-    gba.load_rom(rom_data);
+    if is_multiboot_path(&rom_path) {
+        gba.load_multiboot(rom_data);
+    } else {
+        gba.load_rom(rom_data);
+    }
 
     println!("Running {} frames...", frames);
     for i in 0..frames {
@@ -143,7 +152,7 @@ fn run_headless(args: &[String]) {
     let mut pixels = Vec::with_capacity((width * height) as usize);
     for y in 0..height {
         for x in 0..width {
-            let color = gba.get_pixel_tile_mode(x as u16, y as u16);
+            let color = gba.render_debug_pixel(x as u16, y as u16).color;
             pixels.push(rgb555_to_u8(color));
         }
     }
@@ -178,8 +187,10 @@ fn apply_save_type(gba: &mut Gba, st: &str) {
 
 #[cfg(feature = "gui")]
 fn run_gui(args: &[String]) {
-    use minifb::{Key, Scale, Window, WindowOptions};
+    use minifb::{Key, Window, WindowOptions};
+    use rgba::frontends::scale;
     use rgba::KeyState;
+    use rgba::Throttle;
 
     let (rom_path, bios_path, _frames, _output, save_type) = match parse_args(args) {
         Some(v) => v,
@@ -207,17 +218,21 @@ fn run_gui(args: &[String]) {
         apply_save_type(&mut gba, st);
     }
 
-    gba.load_rom(rom_data);
+    if is_multiboot_path(&rom_path) {
+        gba.load_multiboot(rom_data);
+    } else {
+        gba.load_rom(rom_data);
+    }
 
-    let width = 240;
-    let height = 160;
+    let width = 240usize;
+    let height = 160usize;
     let scale = 2;
     let mut window = Window::new(
         "RGBA - GBA Emulator",
-        width * scale as usize,
-        height * scale as usize,
+        width * scale,
+        height * scale,
         WindowOptions {
-            scale: Scale::X2,
+            resize: true,
             ..WindowOptions::default()
         },
     )
@@ -226,10 +241,12 @@ fn run_gui(args: &[String]) {
         std::process::exit(1);
     });
 
-    let mut buffer = vec![0u32; (width * height * scale * scale) as usize];
+    let mut frame = vec![0u32; width * height];
+    let mut buffer = vec![0u32; width * height * scale * scale];
 
     let mut frame_count = 0u64;
     let mut fps_timer = std::time::Instant::now();
+    let mut throttle = Throttle::new(gba.speed() as f64);
 
     while window.is_open() && !window.is_key_down(Key::Escape) {
         // Handle input
@@ -246,13 +263,11 @@ fn run_gui(args: &[String]) {
             (Key::S, KeyState::R),
         ];
 
-        for (kb_key, gba_key) in &key_map {
-            if window.is_key_down(*kb_key) {
-                gba.input_mut().press_key(*gba_key);
-            } else {
-                gba.input_mut().release_key(*gba_key);
-            }
-        }
+        let pressed = key_map
+            .iter()
+            .filter(|(kb_key, _)| window.is_key_down(*kb_key))
+            .fold(KeyState::empty(), |keys, (_, gba_key)| keys | *gba_key);
+        gba.input_mut().set_all_keys(pressed.bits());
 
         // Reset
         if window.is_key_pressed(Key::R, minifb::KeyRepeat::No) {
@@ -263,30 +278,24 @@ fn run_gui(args: &[String]) {
         gba.run_frame();
 
         // Render
-        gba.sync_ppu_full();
-        let mut idx = 0;
-        for y in 0..height as usize {
-            for x in 0..width as usize {
-                let color = gba.get_pixel_tile_mode(x as u16, y as u16);
-                let r = ((color & 0x1F) * 255 / 31) as u32;
-                let g = (((color >> 5) & 0x1F) * 255 / 31) as u32;
-                let b = (((color >> 10) & 0x1F) * 255 / 31) as u32;
-                let rgb = (r << 16) | (g << 8) | b;
-
-                // Scale up
-                let sy = y * scale as usize;
-                let sx = x * scale as usize;
-                let screen_w = width as usize * scale as usize;
-                for dy in 0..scale as usize {
-                    for dx in 0..scale as usize {
-                        buffer[(sy + dy) * screen_w + sx + dx] = rgb;
-                    }
-                }
-                idx += 1;
-            }
-        }
-
-        window.update_with_buffer(&buffer, width as usize * scale as usize, height as usize * scale as usize).unwrap();
+        gba.framebuffer_xrgb8888(&mut frame);
+
+        // Letterboxed integer scaling into the current window size
+        let (win_w, win_h) = window.get_size();
+        let (win_w, win_h) = (win_w.max(1), win_h.max(1));
+        buffer.resize(win_w * win_h, 0);
+        scale::scale_into(
+            &frame,
+            width as u32,
+            height as u32,
+            &mut buffer,
+            win_w as u32,
+            win_h as u32,
+            scale::FitMode::Integer,
+            scale::Filter::Nearest,
+        );
+
+        window.update_with_buffer(&buffer, win_w, win_h).unwrap();
 
         // FPS counter
         frame_count += 1;
@@ -297,8 +306,8 @@ fn run_gui(args: &[String]) {
             fps_timer = std::time::Instant::now();
         }
 
-        // Frame rate limiting (~60 FPS)
-        std::thread::sleep(std::time::Duration::from_micros(16000));
+        // Keep emulated time in step with real time (~59.7 FPS)
+        throttle.wait(gba.cycles_elapsed());
     }
 }
 
@@ -0,0 +1,322 @@
+//! GPU Presentation (wgpu)
+//!
+//! Uploads a 240x160 RGB555 frame to a texture and draws it with integer
+//! scaling. Decoding, the optional LCD grid and color correction all run in
+//! the fragment shader so frontends skip the per-pixel CPU conversion; the
+//! correction uses the core's [`ColorCurve`] uploaded as a uniform.
+
+use std::borrow::Cow;
+
+use rgba::frontends::scale::{self, FitMode};
+use rgba::{ColorCorrection, ColorCurve};
+
+const FRAME_WIDTH: u32 = 240;
+const FRAME_HEIGHT: u32 = 160;
+
+/// Size of the shader's `Params` uniform
+const PARAMS_SIZE: usize = 192;
+
+const SHADER: &str = r#"
+struct Params {
+    flags: u32,
+    scale: u32,
+    luminance: f32,
+    pad0: u32,
+    // Output R, G, B rows of the color mix (w unused)
+    mix: array<vec4<f32>, 3>,
+    // Linear light of channel levels 0-31, four per vector
+    levels: array<vec4<f32>, 8>,
+};
+
+@group(0) @binding(0) var frame: texture_2d<u32>;
+@group(0) @binding(1) var<uniform> params: Params;
+
+struct VsOut {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+fn level(c: u32) -> f32 {
+    return params.levels[c >> 2u][c & 3u];
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) i: u32) -> VsOut {
+    // One triangle covering the viewport
+    let x = f32((i << 1u) & 2u);
+    let y = f32(i & 2u);
+    var out: VsOut;
+    out.pos = vec4<f32>(x * 2.0 - 1.0, 1.0 - y * 2.0, 0.0, 1.0);
+    out.uv = vec2<f32>(x, y);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VsOut) -> @location(0) vec4<f32> {
+    let size = vec2<f32>(240.0, 160.0);
+    let p = clamp(in.uv * size, vec2<f32>(0.0), size - vec2<f32>(1.0));
+    let raw = textureLoad(frame, vec2<i32>(p), 0).r;
+    var rgb = vec3<f32>(
+        f32(raw & 31u),
+        f32((raw >> 5u) & 31u),
+        f32((raw >> 10u) & 31u),
+    ) / 31.0;
+
+    if ((params.flags & 2u) != 0u) {
+        // Same steps as ColorCurve::apply
+        let l = vec3<f32>(level(raw & 31u), level((raw >> 5u) & 31u), level((raw >> 10u) & 31u));
+        let c = vec3<f32>(
+            dot(params.mix[0].xyz, l),
+            dot(params.mix[1].xyz, l),
+            dot(params.mix[2].xyz, l),
+        ) * params.luminance;
+        rgb = pow(clamp(c, vec3<f32>(0.0), vec3<f32>(1.0)), vec3<f32>(1.0 / 2.2));
+    }
+
+    if ((params.flags & 1u) != 0u && params.scale >= 3u) {
+        // Darken the first row/column of every scaled pixel
+        let cell = fract(in.uv * size) * f32(params.scale);
+        if (cell.x < 1.0 || cell.y < 1.0) {
+            rgb = rgb * 0.75;
+        }
+    }
+
+    return vec4<f32>(rgb, 1.0);
+}
+"#;
+
+/// Post-processing applied by the presentation shader
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PresentOptions {
+    pub lcd_grid: bool,
+    pub color_correction: ColorCorrection,
+}
+
+impl PresentOptions {
+    fn flags(&self) -> u32 {
+        let corrected = self.color_correction != ColorCorrection::None;
+        (self.lcd_grid as u32) | ((corrected as u32) << 1)
+    }
+
+    /// Contents of the shader's `Params` uniform
+    fn params(&self, scale: u32) -> [u8; PARAMS_SIZE] {
+        let ColorCurve {
+            levels,
+            mix,
+            luminance,
+        } = self.color_correction.curve();
+        let mut words = vec![self.flags(), scale, luminance.to_bits(), 0];
+        for row in mix {
+            words.extend(row.map(f32::to_bits));
+            words.push(0);
+        }
+        words.extend(levels.map(f32::to_bits));
+
+        let mut params = [0u8; PARAMS_SIZE];
+        for (bytes, word) in params.chunks_exact_mut(4).zip(words) {
+            bytes.copy_from_slice(&word.to_le_bytes());
+        }
+        params
+    }
+}
+
+/// Largest integer-scaled 240x160 viewport centered in the target,
+/// returned as (x, y, width, height, scale)
+pub fn integer_viewport(target_width: u32, target_height: u32) -> (u32, u32, u32, u32, u32) {
+    let rect = scale::fit(FRAME_WIDTH, FRAME_HEIGHT, target_width, target_height, FitMode::Integer);
+    let scale = scale::integer_scale(FRAME_WIDTH, FRAME_HEIGHT, target_width, target_height);
+    (rect.x, rect.y, rect.width, rect.height, scale)
+}
+
+/// Reusable wgpu pipeline presenting RGB555 frames
+pub struct WgpuPresenter {
+    texture: wgpu::Texture,
+    params: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    pipeline: wgpu::RenderPipeline,
+    options: PresentOptions,
+}
+
+impl WgpuPresenter {
+    /// Build the frame texture and pipeline for a surface of `target_format`
+    pub fn new(device: &wgpu::Device, target_format: wgpu::TextureFormat) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("rgba frame"),
+            size: Self::frame_extent(),
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R16Uint,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let params = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("rgba present params"),
+            size: PARAMS_SIZE as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("rgba present layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Uint,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("rgba present bind group"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: params.as_entire_binding(),
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("rgba present shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(SHADER)),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("rgba present pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("rgba present pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: target_format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        Self {
+            texture,
+            params,
+            bind_group,
+            pipeline,
+            options: PresentOptions::default(),
+        }
+    }
+
+    pub fn options(&self) -> PresentOptions {
+        self.options
+    }
+
+    pub fn set_options(&mut self, options: PresentOptions) {
+        self.options = options;
+    }
+
+    /// Upload one 240x160 frame of RGB555 pixels (row-major)
+    pub fn upload(&self, queue: &wgpu::Queue, frame: &[u16]) {
+        let pixels = (FRAME_WIDTH * FRAME_HEIGHT) as usize;
+        if frame.len() < pixels {
+            return;
+        }
+
+        let mut bytes = Vec::with_capacity(pixels * 2);
+        for &color in &frame[..pixels] {
+            bytes.extend_from_slice(&color.to_le_bytes());
+        }
+
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &bytes,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(FRAME_WIDTH * 2),
+                rows_per_image: Some(FRAME_HEIGHT),
+            },
+            Self::frame_extent(),
+        );
+    }
+
+    /// Record a pass drawing the last uploaded frame into `target`
+    pub fn present(
+        &self,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+        target_size: (u32, u32),
+    ) {
+        let (x, y, width, height, scale) = integer_viewport(target_size.0, target_size.1);
+
+        queue.write_buffer(&self.params, 0, &self.options.params(scale));
+
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("rgba present pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        pass.set_viewport(x as f32, y as f32, width as f32, height as f32, 0.0, 1.0);
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+
+    fn frame_extent() -> wgpu::Extent3d {
+        wgpu::Extent3d {
+            width: FRAME_WIDTH,
+            height: FRAME_HEIGHT,
+            depth_or_array_layers: 1,
+        }
+    }
+}
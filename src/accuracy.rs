@@ -0,0 +1,87 @@
+//! Accuracy Settings
+//!
+//! Several hardware quirks cost emulation time and only matter to a handful
+//! of games or test ROMs. Each one can be toggled individually through
+//! [`AccuracySettings`], or picked as a group with an [`AccuracyProfile`].
+
+/// Preset trade-off between emulation speed and hardware fidelity
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "config",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum AccuracyProfile {
+    /// Skip every optional quirk
+    #[default]
+    Fast,
+    /// Emulate quirks that games visibly depend on
+    Balanced,
+    /// Emulate every supported quirk
+    Cycle,
+}
+
+/// Individual accuracy options applied by [`crate::Gba::set_accuracy_settings`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AccuracySettings {
+    /// Drop sprites that exceed the per-scanline OBJ cycle budget
+    pub sprite_cycle_limit: bool,
+    /// Let the first opaque sprite win a pixel regardless of priority
+    pub obj_priority_bug: bool,
+    /// Unmapped reads return the last prefetched opcode
+    pub open_bus: bool,
+    /// Honor the WAITCNT game pak prefetch buffer in access timing
+    pub prefetch: bool,
+    /// Channel 1 sweep: leaving subtract mode after a subtraction mutes the channel
+    pub sweep_negate_quirk: bool,
+    /// Halt the CPU for the waitstate-timed length of every DMA transfer
+    pub dma_bus_timing: bool,
+    /// CPU palette/VRAM/OAM accesses wait a cycle while the PPU draws, and
+    /// OAM is locked outside VBlank unless DISPCNT frees it during HBlank
+    pub video_access_timing: bool,
+}
+
+impl AccuracyProfile {
+    pub const ALL: [AccuracyProfile; 3] = [
+        AccuracyProfile::Fast,
+        AccuracyProfile::Balanced,
+        AccuracyProfile::Cycle,
+    ];
+
+    /// Options enabled by this profile
+    pub fn settings(self) -> AccuracySettings {
+        match self {
+            AccuracyProfile::Fast => AccuracySettings::default(),
+            AccuracyProfile::Balanced => AccuracySettings {
+                sprite_cycle_limit: true,
+                open_bus: true,
+                dma_bus_timing: true,
+                ..AccuracySettings::default()
+            },
+            AccuracyProfile::Cycle => AccuracySettings {
+                sprite_cycle_limit: true,
+                obj_priority_bug: true,
+                open_bus: true,
+                prefetch: true,
+                sweep_negate_quirk: true,
+                dma_bus_timing: true,
+                video_access_timing: true,
+            },
+        }
+    }
+}
+
+impl AccuracySettings {
+    /// The profile these settings correspond to, if any
+    pub fn profile(&self) -> Option<AccuracyProfile> {
+        AccuracyProfile::ALL
+            .into_iter()
+            .find(|p| p.settings() == *self)
+    }
+}
+
+impl From<AccuracyProfile> for AccuracySettings {
+    fn from(profile: AccuracyProfile) -> Self {
+        profile.settings()
+    }
+}
@@ -5,6 +5,38 @@
 //! - Direct Sound A/B (sample playback)
 //! - FIFO DMA for audio streaming
 
+use crate::audio::CYCLES_PER_SAMPLE;
+use crate::savestate::{StateError, StateReader, StateWriter};
+use crate::view::{ByteView, ByteViewMut};
+
+/// CPU cycles per frame sequencer step (512 Hz)
+const FRAME_SEQUENCER_PERIOD: u32 = 32768;
+
+/// Per-channel outputs kept for [`Apu::recent_samples`], at 32768 Hz
+pub const SCOPE_LEN: usize = 1024;
+
+/// A sound channel, as selected by [`Apu::recent_samples`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Channel {
+    Square1,
+    Square2,
+    Wave,
+    Noise,
+    DirectSoundA,
+    DirectSoundB,
+}
+
+impl Channel {
+    pub const ALL: [Channel; 6] = [
+        Channel::Square1,
+        Channel::Square2,
+        Channel::Wave,
+        Channel::Noise,
+        Channel::DirectSoundA,
+        Channel::DirectSoundB,
+    ];
+}
+
 /// PSG Square Wave Channel (Channel 1-2)
 #[derive(Debug)]
 #[allow(dead_code)]
@@ -24,6 +56,10 @@ pub struct SquareChannel {
     sweep_direction: bool, // true = addition, false = subtraction
     sweep_time: u8,        // 0-7
     sweep_counter: u8,
+    sweep_shadow: u16,   // frequency the sweep unit calculates from
+    sweep_negated: bool, // a subtraction was calculated since the last trigger
+    // Accuracy option: leaving subtract mode after a subtraction silences the channel
+    sweep_negate_quirk: bool,
     frequency: u16, // 0-2047
     frequency_counter: u16,
     duty_position: u8,
@@ -48,6 +84,9 @@ impl SquareChannel {
             sweep_direction: false,
             sweep_time: 0,
             sweep_counter: 0,
+            sweep_shadow: 0,
+            sweep_negated: false,
+            sweep_negate_quirk: false,
             frequency: 0,
             frequency_counter: 0,
             duty_position: 0,
@@ -56,7 +95,9 @@ impl SquareChannel {
     }
 
     pub fn reset(&mut self) {
+        let quirk = self.sweep_negate_quirk;
         *self = Self::new();
+        self.sweep_negate_quirk = quirk;
     }
 
     pub fn step(&mut self, cycles: u32) {
@@ -65,9 +106,9 @@ impl SquareChannel {
         }
 
         // Frequency timer (one cycle = 4 cycles)
-        let freq_period = (2048 - self.frequency) as u32 * 4;
+        let freq_period = 2048u32.wrapping_sub(self.frequency as u32).wrapping_mul(4);
         if cycles >= freq_period {
-            self.duty_position = (self.duty_position + 1) % 8;
+            self.duty_position = self.duty_position.wrapping_add(1) % 8;
         }
 
         // Update output based on duty cycle and position
@@ -104,15 +145,133 @@ impl SquareChannel {
         self.frequency = freq & 0x7FF;
     }
 
+    pub fn frequency(&self) -> u16 {
+        self.frequency
+    }
+
+    /// SOUND1CNT_L write: shift (bits 0-2), decrease (bit 3), sweep time (bits 4-6)
+    pub fn write_sweep(&mut self, val: u8) {
+        self.sweep_shift = val & 0x7;
+        self.sweep_direction = val & 0x8 == 0;
+        self.sweep_time = (val >> 4) & 0x7;
+
+        // DMG quirk kept by the GBA: switching to addition after a subtraction
+        // was calculated disables the channel until the next trigger
+        if self.sweep_negate_quirk && self.sweep_direction && self.sweep_negated {
+            self.enabled = false;
+        }
+    }
+
+    pub fn set_sweep_negate_quirk(&mut self, enabled: bool) {
+        self.sweep_negate_quirk = enabled;
+    }
+
+    pub fn sweep_negate_quirk(&self) -> bool {
+        self.sweep_negate_quirk
+    }
+
+    /// Next sweep frequency from the shadow register; overflowing 2047 disables the channel
+    fn sweep_calculate(&mut self) -> u16 {
+        let delta = self.sweep_shadow >> self.sweep_shift;
+        let freq = if self.sweep_direction {
+            self.sweep_shadow.wrapping_add(delta)
+        } else {
+            self.sweep_negated = true;
+            self.sweep_shadow.wrapping_sub(delta)
+        };
+        if freq > 2047 {
+            self.enabled = false;
+        }
+        freq
+    }
+
+    /// Sweep tick, clocked by the frame sequencer at 128 Hz
+    pub fn clock_sweep(&mut self) {
+        self.sweep_counter = self.sweep_counter.saturating_sub(1);
+        if self.sweep_counter != 0 {
+            return;
+        }
+        self.sweep_counter = if self.sweep_time == 0 { 8 } else { self.sweep_time };
+
+        if self.sweep_enabled && self.sweep_time != 0 {
+            let freq = self.sweep_calculate();
+            if freq <= 2047 && self.sweep_shift != 0 {
+                self.sweep_shadow = freq;
+                self.frequency = freq;
+                // The new frequency is immediately checked for overflow again
+                self.sweep_calculate();
+            }
+        }
+    }
+
     pub fn set_duty_cycle(&mut self, duty: u8) {
         self.duty_cycle = duty & 0x3;
     }
 
     pub fn trigger(&mut self) {
         self.enabled = true;
-        self.length_counter = 64 - self.length_load as u8;
+        self.length_counter = 64u8.wrapping_sub(self.length_load);
         self.envelope_counter = self.envelope_step;
         self.frequency_counter = 0;
+
+        self.sweep_shadow = self.frequency;
+        self.sweep_counter = if self.sweep_time == 0 { 8 } else { self.sweep_time };
+        self.sweep_enabled = self.sweep_time != 0 || self.sweep_shift != 0;
+        self.sweep_negated = false;
+        if self.sweep_shift != 0 {
+            self.sweep_calculate();
+        }
+    }
+}
+
+impl SquareChannel {
+    pub(crate) fn save_state(&self, w: &mut StateWriter) {
+        w.write_bool(self.enabled);
+        w.write_u8(self.duty_cycle);
+        w.write_bool(self.length_enabled);
+        w.write_u8(self.length_load);
+        w.write_u8(self.length_counter);
+        w.write_bool(self.envelope_enabled);
+        w.write_bool(self.envelope_direction);
+        w.write_u8(self.envelope_step);
+        w.write_u8(self.envelope_volume);
+        w.write_u8(self.envelope_counter);
+        w.write_bool(self.sweep_enabled);
+        w.write_u8(self.sweep_shift);
+        w.write_bool(self.sweep_direction);
+        w.write_u8(self.sweep_time);
+        w.write_u8(self.sweep_counter);
+        w.write_u16(self.sweep_shadow);
+        w.write_bool(self.sweep_negated);
+        w.write_u16(self.frequency);
+        w.write_u16(self.frequency_counter);
+        w.write_u8(self.duty_position);
+        w.write_u8(self.output_volume);
+    }
+
+    pub(crate) fn load_state(&mut self, r: &mut StateReader) -> Result<(), StateError> {
+        self.enabled = r.read_bool()?;
+        self.duty_cycle = r.read_u8()?;
+        self.length_enabled = r.read_bool()?;
+        self.length_load = r.read_u8()?;
+        self.length_counter = r.read_u8()?;
+        self.envelope_enabled = r.read_bool()?;
+        self.envelope_direction = r.read_bool()?;
+        self.envelope_step = r.read_u8()?;
+        self.envelope_volume = r.read_u8()?;
+        self.envelope_counter = r.read_u8()?;
+        self.sweep_enabled = r.read_bool()?;
+        self.sweep_shift = r.read_u8()?;
+        self.sweep_direction = r.read_bool()?;
+        self.sweep_time = r.read_u8()?;
+        self.sweep_counter = r.read_u8()?;
+        self.sweep_shadow = r.read_u16()?;
+        self.sweep_negated = r.read_bool()?;
+        self.frequency = r.read_u16()?;
+        self.frequency_counter = r.read_u16()?;
+        self.duty_position = r.read_u8()?;
+        self.output_volume = r.read_u8()?;
+        Ok(())
     }
 }
 
@@ -158,14 +317,14 @@ impl WaveChannel {
         }
 
         // Frequency timer (one cycle = 2 cycles)
-        let freq_period = (2048 - self.frequency) as u32 * 2;
+        let freq_period = 2048u32.wrapping_sub(self.frequency as u32).wrapping_mul(2);
         if cycles >= freq_period {
-            self.wave_position = (self.wave_position + 1) % 32;
+            self.wave_position = self.wave_position.wrapping_add(1) % 32;
         }
 
         // Get sample and apply volume
         let idx = (self.wave_position / 2) as usize;
-        let sample = self.wave_ram[idx];
+        let sample = self.wave_ram.byte_at(idx);
         let nibble = if self.wave_position % 2 == 0 {
             sample >> 4
         } else {
@@ -198,9 +357,36 @@ impl WaveChannel {
     }
 
     pub fn set_wave_ram(&mut self, index: usize, value: u8) {
-        if index < 32 {
-            self.wave_ram[index] = value;
-        }
+        self.wave_ram.set_byte(index, value);
+    }
+}
+
+impl WaveChannel {
+    pub(crate) fn save_state(&self, w: &mut StateWriter) {
+        w.write_bool(self.enabled);
+        w.write_bool(self.length_enabled);
+        w.write_u8(self.length_load);
+        w.write_u8(self.length_counter);
+        w.write_u8(self.volume_code);
+        w.write_u16(self.frequency);
+        w.write_u16(self.frequency_counter);
+        w.write_u8(self.wave_position);
+        w.write_bytes(&self.wave_ram);
+        w.write_u8(self.output_volume);
+    }
+
+    pub(crate) fn load_state(&mut self, r: &mut StateReader) -> Result<(), StateError> {
+        self.enabled = r.read_bool()?;
+        self.length_enabled = r.read_bool()?;
+        self.length_load = r.read_u8()?;
+        self.length_counter = r.read_u8()?;
+        self.volume_code = r.read_u8()?;
+        self.frequency = r.read_u16()?;
+        self.frequency_counter = r.read_u16()?;
+        self.wave_position = r.read_u8()?;
+        r.read_bytes_into(&mut self.wave_ram)?;
+        self.output_volume = r.read_u8()?;
+        Ok(())
     }
 }
 
@@ -284,12 +470,66 @@ impl NoiseChannel {
     }
 }
 
+impl NoiseChannel {
+    pub(crate) fn save_state(&self, w: &mut StateWriter) {
+        w.write_bool(self.enabled);
+        w.write_bool(self.length_enabled);
+        w.write_u8(self.length_load);
+        w.write_u8(self.length_counter);
+        w.write_bool(self.envelope_enabled);
+        w.write_bool(self.envelope_direction);
+        w.write_u8(self.envelope_step);
+        w.write_u8(self.envelope_volume);
+        w.write_u8(self.envelope_counter);
+        w.write_u8(self.clock_shift);
+        w.write_bool(self.width_mode);
+        w.write_u16(self.lfsr);
+        w.write_u8(self.output_volume);
+    }
+
+    pub(crate) fn load_state(&mut self, r: &mut StateReader) -> Result<(), StateError> {
+        self.enabled = r.read_bool()?;
+        self.length_enabled = r.read_bool()?;
+        self.length_load = r.read_u8()?;
+        self.length_counter = r.read_u8()?;
+        self.envelope_enabled = r.read_bool()?;
+        self.envelope_direction = r.read_bool()?;
+        self.envelope_step = r.read_u8()?;
+        self.envelope_volume = r.read_u8()?;
+        self.envelope_counter = r.read_u8()?;
+        self.clock_shift = r.read_u8()?;
+        self.width_mode = r.read_bool()?;
+        self.lfsr = r.read_u16()?;
+        self.output_volume = r.read_u8()?;
+        Ok(())
+    }
+}
+
+/// Bytes a Direct Sound FIFO holds (8 words)
+pub const FIFO_CAPACITY: usize = 32;
+
+// The read and write cursors wrap at the FIFO size
+const FIFO_WRAP: u8 = FIFO_CAPACITY as u8;
+
+/// A FIFO at or below this many bytes requests a sound DMA refill
+pub const FIFO_REFILL_LEVEL: usize = 16;
+
+/// Direct Sound FIFO health counters, for diagnosing crackling audio
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FifoStats {
+    /// Timer ticks that found the FIFO of an audible channel empty, so the
+    /// last sample played again
+    pub underruns: u32,
+    /// Bytes dropped because they were written to a full FIFO
+    pub overruns: u32,
+}
+
 /// Direct Sound Channel (FIFO DMA)
 #[derive(Debug)]
 #[allow(dead_code)]
 pub struct DirectSoundChannel {
     enabled: bool,
-    fifo: [u8; 32], // 8 words * 4 bytes
+    fifo: [u8; FIFO_CAPACITY], // 8 words * 4 bytes
     fifo_read: u8,
     fifo_write: u8,
     fifo_count: u8,
@@ -298,13 +538,14 @@ pub struct DirectSoundChannel {
     output_right: bool,
     output_left: bool,
     current_sample: i16,
+    stats: FifoStats,
 }
 
 impl DirectSoundChannel {
     pub fn new() -> Self {
         Self {
             enabled: false,
-            fifo: [0; 32],
+            fifo: [0; FIFO_CAPACITY],
             fifo_read: 0,
             fifo_write: 0,
             fifo_count: 0,
@@ -313,6 +554,7 @@ impl DirectSoundChannel {
             output_right: false,
             output_left: false,
             current_sample: 0,
+            stats: FifoStats::default(),
         }
     }
 
@@ -320,33 +562,71 @@ impl DirectSoundChannel {
         *self = Self::new();
     }
 
+    /// Queue one sample byte; a full FIFO drops it
+    pub fn push_byte(&mut self, val: u8) {
+        if self.fifo_count as usize == FIFO_CAPACITY {
+            self.stats.overruns = self.stats.overruns.wrapping_add(1);
+            return;
+        }
+        self.fifo.set_byte(self.fifo_write as usize, val);
+        self.fifo_write = self.fifo_write.wrapping_add(1) % FIFO_WRAP;
+        self.fifo_count = self.fifo_count.wrapping_add(1);
+    }
+
+    /// Queue a word as four samples, lowest byte first
     pub fn write_fifo(&mut self, data: u32) {
-        // Write 4 bytes (one word) to FIFO
-        for i in 0..4 {
-            self.fifo[self.fifo_write as usize] = ((data >> (i * 8)) & 0xFF) as u8;
-            self.fifo_write = (self.fifo_write + 1) % 32;
-            if self.fifo_count < 8 {
-                self.fifo_count += 1;
-            }
+        for byte in data.to_le_bytes() {
+            self.push_byte(byte);
         }
     }
 
+    /// Play the next signed 8-bit sample, on a timer overflow. An empty FIFO
+    /// keeps the last sample playing.
     pub fn read_sample(&mut self) -> i16 {
         if self.fifo_count == 0 {
-            return 0;
+            if self.output_left || self.output_right {
+                self.stats.underruns = self.stats.underruns.wrapping_add(1);
+            }
+            return self.current_sample;
         }
 
-        // Read 2 bytes (one halfword) from FIFO
-        let low = self.fifo[self.fifo_read as usize] as i16;
-        self.fifo_read = (self.fifo_read + 1) % 32;
-        let high = self.fifo[self.fifo_read as usize] as i16;
-        self.fifo_read = (self.fifo_read + 1) % 32;
-        self.fifo_count -= 2;
+        let byte = self.fifo.byte_at(self.fifo_read as usize);
+        self.fifo_read = self.fifo_read.wrapping_add(1) % FIFO_WRAP;
+        self.fifo_count = self.fifo_count.wrapping_sub(1);
+
+        self.current_sample = (byte as i8 as i16) << 8;
+        self.current_sample
+    }
 
-        let sample = (high << 8) | low;
-        self.current_sample = sample;
+    /// Bytes queued in the FIFO
+    pub fn fifo_len(&self) -> usize {
+        self.fifo_count as usize
+    }
 
-        sample
+    /// True once the FIFO has drained far enough to request a DMA refill
+    pub fn needs_refill(&self) -> bool {
+        self.fifo_len() <= FIFO_REFILL_LEVEL
+    }
+
+    pub fn stats(&self) -> FifoStats {
+        self.stats
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.stats = FifoStats::default();
+    }
+
+    /// Empty the FIFO and silence the channel (SOUNDCNT_H FIFO reset)
+    pub fn clear_fifo(&mut self) {
+        self.fifo_read = 0;
+        self.fifo_write = 0;
+        self.fifo_count = 0;
+        self.current_sample = 0;
+    }
+
+    /// Timer (0 or 1) whose overflow consumes a sample
+    pub fn timer(&self) -> u8 {
+        self.timer
     }
 
     pub fn get_output(&self) -> i16 {
@@ -369,6 +649,35 @@ impl DirectSoundChannel {
     }
 }
 
+impl DirectSoundChannel {
+    pub(crate) fn save_state(&self, w: &mut StateWriter) {
+        w.write_bool(self.enabled);
+        w.write_bytes(&self.fifo);
+        w.write_u8(self.fifo_read);
+        w.write_u8(self.fifo_write);
+        w.write_u8(self.fifo_count);
+        w.write_u8(self.volume);
+        w.write_u8(self.timer);
+        w.write_bool(self.output_right);
+        w.write_bool(self.output_left);
+        w.write_i16(self.current_sample);
+    }
+
+    pub(crate) fn load_state(&mut self, r: &mut StateReader) -> Result<(), StateError> {
+        self.enabled = r.read_bool()?;
+        r.read_bytes_into(&mut self.fifo)?;
+        self.fifo_read = r.read_u8()?;
+        self.fifo_write = r.read_u8()?;
+        self.fifo_count = r.read_u8()?;
+        self.volume = r.read_u8()?;
+        self.timer = r.read_u8()?;
+        self.output_right = r.read_bool()?;
+        self.output_left = r.read_bool()?;
+        self.current_sample = r.read_i16()?;
+        Ok(())
+    }
+}
+
 /// GBA Audio Processing Unit
 pub struct Apu {
     // PSG channels
@@ -389,10 +698,22 @@ pub struct Apu {
     // Mixing
     left_enabled: [bool; 8],  // Enable each channel on left
     right_enabled: [bool; 8], // Enable each channel on right
+    /// SOUNDCNT_H bits 0-1: PSG mix at 25%, 50% or 100%
+    psg_ratio: u8,
 
     // Output
     output_left: i16,
     output_right: i16,
+
+    // Frame sequencer: 512 Hz clock for the sweep unit
+    sequencer_cycles: u32,
+    sequencer_step: u8,
+
+    // Oscilloscope: one ring of recent outputs per channel, sharing one
+    // write position
+    scope: Box<[[i16; SCOPE_LEN]; 6]>,
+    scope_pos: usize,
+    scope_countdown: u32,
 }
 
 impl Apu {
@@ -409,8 +730,14 @@ impl Apu {
             volume_right: 0,
             left_enabled: [false; 8],
             right_enabled: [false; 8],
+            psg_ratio: 0,
             output_left: 0,
             output_right: 0,
+            sequencer_cycles: 0,
+            sequencer_step: 0,
+            scope: Box::new([[0; SCOPE_LEN]; 6]),
+            scope_pos: 0,
+            scope_countdown: CYCLES_PER_SAMPLE,
         }
     }
 
@@ -426,8 +753,14 @@ impl Apu {
         self.volume_right = 0;
         self.left_enabled = [false; 8];
         self.right_enabled = [false; 8];
+        self.psg_ratio = 0;
         self.output_left = 0;
         self.output_right = 0;
+        self.sequencer_cycles = 0;
+        self.sequencer_step = 0;
+        self.scope.iter_mut().for_each(|ring| ring.fill(0));
+        self.scope_pos = 0;
+        self.scope_countdown = CYCLES_PER_SAMPLE;
     }
 
     /// Step the APU forward by given number of cycles
@@ -436,59 +769,108 @@ impl Apu {
             return;
         }
 
+        self.sequencer_cycles = self.sequencer_cycles.wrapping_add(cycles);
+        while self.sequencer_cycles >= FRAME_SEQUENCER_PERIOD {
+            self.sequencer_cycles = self.sequencer_cycles.wrapping_sub(FRAME_SEQUENCER_PERIOD);
+            self.clock_frame_sequencer();
+        }
+
         // Step PSG channels
         self.square1.step(cycles);
         self.square2.step(cycles);
         self.wave.step(cycles);
         self.noise.step(cycles);
 
-        // Mix all channels
-        let mut left_mixed = 0i32;
-        let mut right_mixed = 0i32;
-
-        // PSG channels
-        if self.left_enabled[0] {
-            left_mixed += self.square1.get_output() as i32;
-        }
-        if self.right_enabled[0] {
-            right_mixed += self.square1.get_output() as i32;
-        }
-        if self.left_enabled[1] {
-            left_mixed += self.square2.get_output() as i32;
-        }
-        if self.right_enabled[1] {
-            right_mixed += self.square2.get_output() as i32;
-        }
-        if self.left_enabled[2] {
-            left_mixed += self.wave.get_output() as i32;
-        }
-        if self.right_enabled[2] {
-            right_mixed += self.wave.get_output() as i32;
-        }
-        if self.left_enabled[3] {
-            left_mixed += self.noise.get_output() as i32;
+        // PSG channels, routed by SOUNDCNT_L and scaled by its master volume
+        let psg = [
+            self.square1.get_output() as i32,
+            self.square2.get_output() as i32,
+            self.wave.get_output() as i32,
+            self.noise.get_output() as i32,
+        ];
+        let mut psg_left = 0i32;
+        let mut psg_right = 0i32;
+        for (ch, &out) in psg.iter().enumerate() {
+            let (left, right) = self.channel_routing(ch);
+            if left {
+                psg_left = psg_left.wrapping_add(out);
+            }
+            if right {
+                psg_right = psg_right.wrapping_add(out);
+            }
         }
-        if self.right_enabled[3] {
-            right_mixed += self.noise.get_output() as i32;
+        let psg_shift = match self.psg_ratio {
+            0 => 2,
+            1 => 1,
+            _ => 0,
+        };
+        let mut left_mixed = (psg_left.wrapping_mul(self.volume_left as i32) / 7) >> psg_shift;
+        let mut right_mixed = (psg_right.wrapping_mul(self.volume_right as i32) / 7) >> psg_shift;
+
+        // Direct Sound channels, routed by SOUNDCNT_H; the master volume
+        // only applies to the PSG
+        for (ch, ds) in [(4, &self.ds_a), (5, &self.ds_b)] {
+            let (left, right) = self.channel_routing(ch);
+            if left {
+                left_mixed = left_mixed.wrapping_add(ds.get_output() as i32);
+            }
+            if right {
+                right_mixed = right_mixed.wrapping_add(ds.get_output() as i32);
+            }
         }
 
-        // Direct Sound channels
-        if self.left_enabled[4] {
-            left_mixed += self.ds_a.get_output() as i32;
-        }
-        if self.right_enabled[4] {
-            right_mixed += self.ds_a.get_output() as i32;
-        }
-        if self.left_enabled[5] {
-            left_mixed += self.ds_b.get_output() as i32;
+        self.output_left = left_mixed.clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+        self.output_right = right_mixed.clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+
+        let mut cycles = cycles;
+        while cycles >= self.scope_countdown {
+            cycles = cycles.wrapping_sub(self.scope_countdown);
+            self.scope_countdown = CYCLES_PER_SAMPLE;
+            self.record_scope();
         }
-        if self.right_enabled[5] {
-            right_mixed += self.ds_b.get_output() as i32;
+        self.scope_countdown = self.scope_countdown.wrapping_sub(cycles);
+    }
+
+    fn record_scope(&mut self) {
+        let outputs = [
+            self.square1.get_output() as i16,
+            self.square2.get_output() as i16,
+            self.wave.get_output() as i16,
+            self.noise.get_output() as i16,
+            self.ds_a.get_output(),
+            self.ds_b.get_output(),
+        ];
+        for (ring, out) in self.scope.iter_mut().zip(outputs) {
+            if let Some(slot) = ring.get_mut(self.scope_pos) {
+                *slot = out;
+            }
         }
+        self.scope_pos = self.scope_pos.wrapping_add(1) % SCOPE_LEN;
+    }
 
-        // Apply master volume
-        self.output_left = ((left_mixed * self.volume_left as i32) / 7) as i16;
-        self.output_right = ((right_mixed * self.volume_right as i32) / 7) as i16;
+    /// The last `n` outputs of `channel` (at most [`SCOPE_LEN`]), oldest
+    /// first, sampled at 32768 Hz while the sound circuit is powered. PSG
+    /// channels range 0-15, Direct Sound channels span the i16 range.
+    pub fn recent_samples(&self, channel: Channel, n: usize) -> Vec<i16> {
+        let Some(ring) = self.scope.get(channel as usize) else {
+            return Vec::new();
+        };
+        // The slot at scope_pos holds the oldest sample
+        let (newer, older) = ring.split_at(self.scope_pos.min(SCOPE_LEN));
+        older
+            .iter()
+            .chain(newer)
+            .skip(SCOPE_LEN.saturating_sub(n))
+            .copied()
+            .collect()
+    }
+
+    /// One 512 Hz frame sequencer step; steps 2 and 6 clock the channel 1 sweep
+    fn clock_frame_sequencer(&mut self) {
+        if self.sequencer_step == 2 || self.sequencer_step == 6 {
+            self.square1.clock_sweep();
+        }
+        self.sequencer_step = self.sequencer_step.wrapping_add(1) % 8;
     }
 
     pub fn get_output_left(&self) -> i16 {
@@ -499,7 +881,18 @@ impl Apu {
         self.output_right
     }
 
+    /// SOUNDCNT_X bit 7. Powering off resets every PSG channel; wave RAM survives.
     pub fn set_master_enabled(&mut self, enabled: bool) {
+        if self.master_enabled && !enabled {
+            self.square1.reset();
+            self.square2.reset();
+            self.noise.reset();
+            let wave_ram = self.wave.wave_ram;
+            self.wave.reset();
+            self.wave.wave_ram = wave_ram;
+            self.sequencer_cycles = 0;
+            self.sequencer_step = 0;
+        }
         self.master_enabled = enabled;
     }
 
@@ -515,15 +908,68 @@ impl Apu {
         self.volume_right = volume & 0x7;
     }
 
+    /// SOUNDCNT_L: PSG master volume (bits 0-2 right, 4-6 left) and the
+    /// channel 1-4 enables (bits 8-11 right, 12-15 left)
+    pub fn write_soundcnt_l(&mut self, val: u16) {
+        self.set_volume_right(val as u8);
+        self.set_volume_left((val >> 4) as u8);
+        let enables = self
+            .right_enabled
+            .iter_mut()
+            .zip(self.left_enabled.iter_mut());
+        for (ch, (right, left)) in enables.take(4).enumerate() {
+            *right = val & (0x100 << ch) != 0;
+            *left = val & (0x1000 << ch) != 0;
+        }
+    }
+
+    /// SOUNDCNT_H: PSG ratio (bits 0-1), Direct Sound A/B volume (bits
+    /// 2-3), and per FIFO the right/left enables, timer select and reset
+    /// (bits 8-11 for A, 12-15 for B)
+    pub fn write_soundcnt_h(&mut self, val: u16) {
+        self.psg_ratio = (val & 0x3) as u8;
+        let enables = self
+            .right_enabled
+            .iter_mut()
+            .zip(self.left_enabled.iter_mut());
+        let fifos = [&mut self.ds_a, &mut self.ds_b]
+            .into_iter()
+            .zip(enables.skip(4));
+        for (i, (ds, (right, left))) in fifos.enumerate() {
+            let bits = val >> (8 | i << 2);
+            ds.volume = ((val >> (2 | i)) & 1) as u8;
+            ds.output_right = bits & 0x1 != 0;
+            ds.output_left = bits & 0x2 != 0;
+            ds.timer = ((bits >> 2) & 1) as u8;
+            if bits & 0x8 != 0 {
+                ds.clear_fifo();
+            }
+            *right = ds.output_right;
+            *left = ds.output_left;
+        }
+    }
+
+    /// Mixed output of `channel` (0-3 PSG, 4-5 Direct Sound A/B) is sent to
+    /// the (left, right) speaker
+    pub fn channel_routing(&self, channel: usize) -> (bool, bool) {
+        match (
+            self.left_enabled.get(channel),
+            self.right_enabled.get(channel),
+        ) {
+            (Some(&left), Some(&right)) => (left, right),
+            _ => (false, false),
+        }
+    }
+
     pub fn set_channel_enabled_left(&mut self, channel: usize, enabled: bool) {
-        if channel < 8 {
-            self.left_enabled[channel] = enabled;
+        if let Some(left) = self.left_enabled.get_mut(channel) {
+            *left = enabled;
         }
     }
 
     pub fn set_channel_enabled_right(&mut self, channel: usize, enabled: bool) {
-        if channel < 8 {
-            self.right_enabled[channel] = enabled;
+        if let Some(right) = self.right_enabled.get_mut(channel) {
+            *right = enabled;
         }
     }
 
@@ -532,6 +978,10 @@ impl Apu {
         &mut self.square1
     }
 
+    pub fn square1(&self) -> &SquareChannel {
+        &self.square1
+    }
+
     pub fn get_square2(&mut self) -> &mut SquareChannel {
         &mut self.square2
     }
@@ -552,6 +1002,54 @@ impl Apu {
     pub fn get_ds_b(&mut self) -> &mut DirectSoundChannel {
         &mut self.ds_b
     }
+
+    /// FIFO health counters of Direct Sound A and B
+    pub fn fifo_stats(&self) -> [FifoStats; 2] {
+        [self.ds_a.stats(), self.ds_b.stats()]
+    }
+}
+
+impl Apu {
+    pub(crate) fn save_state(&self, w: &mut StateWriter) {
+        self.square1.save_state(w);
+        self.square2.save_state(w);
+        self.wave.save_state(w);
+        self.noise.save_state(w);
+        self.ds_a.save_state(w);
+        self.ds_b.save_state(w);
+        w.write_bool(self.master_enabled);
+        w.write_u8(self.volume_left);
+        w.write_u8(self.volume_right);
+        for &on in self.left_enabled.iter().chain(&self.right_enabled) {
+            w.write_bool(on);
+        }
+        w.write_u8(self.psg_ratio);
+        w.write_i16(self.output_left);
+        w.write_i16(self.output_right);
+        w.write_u32(self.sequencer_cycles);
+        w.write_u8(self.sequencer_step);
+    }
+
+    pub(crate) fn load_state(&mut self, r: &mut StateReader) -> Result<(), StateError> {
+        self.square1.load_state(r)?;
+        self.square2.load_state(r)?;
+        self.wave.load_state(r)?;
+        self.noise.load_state(r)?;
+        self.ds_a.load_state(r)?;
+        self.ds_b.load_state(r)?;
+        self.master_enabled = r.read_bool()?;
+        self.volume_left = r.read_u8()?;
+        self.volume_right = r.read_u8()?;
+        for on in self.left_enabled.iter_mut().chain(self.right_enabled.iter_mut()) {
+            *on = r.read_bool()?;
+        }
+        self.psg_ratio = r.read_u8()?;
+        self.output_left = r.read_i16()?;
+        self.output_right = r.read_i16()?;
+        self.sequencer_cycles = r.read_u32()?;
+        self.sequencer_step = r.read_u8()?;
+        Ok(())
+    }
 }
 
 impl Default for Apu {
@@ -0,0 +1,1173 @@
+//! Assembler
+//!
+//! Assembles a practical subset of ARM and Thumb to machine code at run
+//! time, so tests and examples can write `add r0, r1, r2` instead of
+//! hand-encoding `0xE081_0002`. The syntax is the GNU style that
+//! [`crate::disassemble_arm`] and [`crate::disassemble_thumb`] print, and
+//! their output assembles back to the same opcode:
+//!
+//! ```
+//! let code = rgba::asm::assemble(0x0800_0000, "
+//!         mov r0, #0
+//!     loop:
+//!         add r0, r0, #1
+//!         b loop
+//! ").unwrap();
+//! assert_eq!(code[4..8], 0xE280_0001u32.to_le_bytes());
+//! ```
+//!
+//! Besides ARMv4T instructions (no coprocessor ones) it understands labels,
+//! `;`, `@` and `//` comments, `ldr rd, =value` with a literal pool placed at
+//! `.pool` or the end of the source, and the directives `.arm`, `.thumb`,
+//! `.word`, `.hword`, `.byte`, `.align` and `.space`. Source starts in ARM
+//! state. Values may be numbers, labels, or sums and differences of them.
+
+use std::collections::HashMap;
+use std::fmt;
+
+/// Why the source could not be assembled
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    /// 1-based line of the offending statement
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+/// Assemble `source` as if loaded at `origin`; branch offsets and label
+/// values are computed from there
+pub fn assemble(origin: u32, source: &str) -> Result<Vec<u8>, AsmError> {
+    let mut layout = Layout {
+        line: 0,
+        addr: origin,
+        thumb: false,
+        items: Vec::new(),
+        labels: HashMap::new(),
+        pool: Vec::new(),
+    };
+    for (i, text) in source.lines().enumerate() {
+        layout.line = i + 1;
+        layout.statement(text).map_err(|message| AsmError {
+            line: i + 1,
+            message,
+        })?;
+    }
+    layout.flush_pool();
+
+    let encoder = Encoder {
+        labels: &layout.labels,
+    };
+    let mut out = vec![0u8; layout.addr.wrapping_sub(origin) as usize];
+    for item in &layout.items {
+        let bytes = encoder.item(item).map_err(|message| AsmError {
+            line: item.line,
+            message,
+        })?;
+        let at = item.addr.wrapping_sub(origin) as usize;
+        out[at..at + bytes.len()].copy_from_slice(&bytes);
+    }
+    Ok(out)
+}
+
+const COND: [&str; 15] = [
+    "eq", "ne", "cs", "cc", "mi", "pl", "vs", "vc", "hi", "ls", "ge", "lt", "gt", "le", "al",
+];
+
+/// Condition code for `s`, including the `hs`/`lo` aliases
+fn cond_code(s: &str) -> Option<u32> {
+    match s {
+        "hs" => Some(2),
+        "lo" => Some(3),
+        _ => COND.iter().position(|&c| c == s).map(|i| i as u32),
+    }
+}
+
+enum Kind {
+    Instruction {
+        thumb: bool,
+        mnemonic: String,
+        operands: String,
+        /// Address of this `ldr rd, =value`'s pool entry
+        literal: Option<u32>,
+    },
+    Data {
+        width: u32,
+        expr: String,
+    },
+}
+
+struct Item {
+    line: usize,
+    addr: u32,
+    kind: Kind,
+}
+
+/// First pass: places every statement and records label addresses
+struct Layout {
+    line: usize,
+    addr: u32,
+    thumb: bool,
+    items: Vec<Item>,
+    labels: HashMap<String, u32>,
+    /// `ldr rd, =value` items waiting for the next literal pool
+    pool: Vec<(usize, String)>,
+}
+
+impl Layout {
+    fn statement(&mut self, text: &str) -> Result<(), String> {
+        let mut text = strip_comment(text).trim();
+        while let Some((name, rest)) = text.split_once(':') {
+            let name = name.trim();
+            if !is_label(name) {
+                break;
+            }
+            if self.labels.insert(name.to_string(), self.addr).is_some() {
+                return Err(format!("label `{}` defined twice", name));
+            }
+            text = rest.trim();
+        }
+        if text.is_empty() {
+            return Ok(());
+        }
+
+        let (mnemonic, operands) = match text.split_once(char::is_whitespace) {
+            Some((m, rest)) => (m.to_ascii_lowercase(), rest.trim().to_string()),
+            None => (text.to_ascii_lowercase(), String::new()),
+        };
+        if mnemonic.starts_with('.') {
+            return self.directive(&mnemonic, &operands);
+        }
+
+        let (size, alignment) = match (self.thumb, mnemonic.as_str()) {
+            (false, _) => (4, 4),
+            (true, "bl") => (4, 2),
+            (true, _) => (2, 2),
+        };
+        if !self.addr.is_multiple_of(alignment) {
+            return Err(format!(
+                "instruction at unaligned address 0x{:08X}",
+                self.addr
+            ));
+        }
+        if let Some((_, value)) = operands.split_once('=') {
+            if !mnemonic.starts_with("ldr") {
+                return Err("only ldr takes an =value operand".to_string());
+            }
+            self.pool.push((self.items.len(), value.trim().to_string()));
+        }
+        self.push(
+            size,
+            Kind::Instruction {
+                thumb: self.thumb,
+                mnemonic,
+                operands,
+                literal: None,
+            },
+        );
+        Ok(())
+    }
+
+    fn directive(&mut self, name: &str, operands: &str) -> Result<(), String> {
+        let amount = || parse_number(operands).ok_or_else(|| format!("{} needs a number", name));
+        let width = match name {
+            ".arm" => {
+                self.thumb = false;
+                return Ok(());
+            }
+            ".thumb" => {
+                self.thumb = true;
+                return Ok(());
+            }
+            ".pool" | ".ltorg" => {
+                self.flush_pool();
+                return Ok(());
+            }
+            ".align" => {
+                let n = if operands.is_empty() { 2 } else { amount()? };
+                if n >= 32 {
+                    return Err(format!("alignment 2^{} is too large", n));
+                }
+                self.addr = align(self.addr, 1 << n);
+                return Ok(());
+            }
+            ".space" | ".skip" => {
+                self.addr = self.addr.wrapping_add(amount()? as u32);
+                return Ok(());
+            }
+            ".word" | ".long" => 4,
+            ".hword" | ".short" => 2,
+            ".byte" => 1,
+            _ => return Err(format!("unknown directive `{}`", name)),
+        };
+        for expr in split_operands(operands) {
+            self.push(
+                width,
+                Kind::Data {
+                    width,
+                    expr: expr.to_string(),
+                },
+            );
+        }
+        Ok(())
+    }
+
+    /// Place waiting `=value` literals, word aligned, at the current address
+    fn flush_pool(&mut self) {
+        if self.pool.is_empty() {
+            return;
+        }
+        self.addr = align(self.addr, 4);
+        for (index, expr) in std::mem::take(&mut self.pool) {
+            if let Kind::Instruction { literal, .. } = &mut self.items[index].kind {
+                *literal = Some(self.addr);
+            }
+            self.push(4, Kind::Data { width: 4, expr });
+        }
+    }
+
+    fn push(&mut self, size: u32, kind: Kind) {
+        self.items.push(Item {
+            line: self.line,
+            addr: self.addr,
+            kind,
+        });
+        self.addr = self.addr.wrapping_add(size);
+    }
+}
+
+fn strip_comment(text: &str) -> &str {
+    let end = [text.find(';'), text.find('@'), text.find("//")]
+        .into_iter()
+        .flatten()
+        .min()
+        .unwrap_or(text.len());
+    &text[..end]
+}
+
+fn is_label(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '.')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+fn align(addr: u32, to: u32) -> u32 {
+    addr.wrapping_add(to - 1) & !(to - 1)
+}
+
+/// Decimal, `0x` hex or `0b` binary, with optional `-` and `_` separators
+fn parse_number(s: &str) -> Option<i64> {
+    let s = s.trim().replace('_', "");
+    let (negative, digits) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest.to_string()),
+        None => (false, s),
+    };
+    let lower = digits.to_ascii_lowercase();
+    let value = if let Some(hex) = lower.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16).ok()?
+    } else if let Some(bin) = lower.strip_prefix("0b") {
+        i64::from_str_radix(bin, 2).ok()?
+    } else {
+        lower.parse().ok()?
+    };
+    Some(if negative { -value } else { value })
+}
+
+/// Split at commas that are not inside `[]` or `{}`
+fn split_operands(s: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '[' | '{' => depth += 1,
+            ']' | '}' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(s[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if !s[start..].trim().is_empty() {
+        parts.push(s[start..].trim());
+    }
+    parts
+}
+
+fn reg(s: &str) -> Result<u32, String> {
+    let name = s.trim().to_ascii_lowercase();
+    let r = match name.as_str() {
+        "sp" => Some(13),
+        "lr" => Some(14),
+        "pc" => Some(15),
+        "ip" => Some(12),
+        "fp" => Some(11),
+        _ => name
+            .strip_prefix('r')
+            .and_then(|n| n.parse().ok())
+            .filter(|&n| n < 16),
+    };
+    r.ok_or_else(|| format!("`{}` is not a register", s.trim()))
+}
+
+fn low_reg(s: &str) -> Result<u32, String> {
+    low(reg(s)?)
+}
+
+fn low(r: u32) -> Result<u32, String> {
+    if r > 7 {
+        return Err(format!("Thumb needs a low register here, not r{}", r));
+    }
+    Ok(r)
+}
+
+/// `{r0-r3, lr}` as a bit mask
+fn reg_list(s: &str) -> Result<u32, String> {
+    let inner = s
+        .trim()
+        .strip_prefix('{')
+        .and_then(|rest| rest.strip_suffix('}'))
+        .ok_or_else(|| format!("`{}` is not a register list", s))?;
+    let mut mask = 0;
+    for part in inner.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        match part.split_once('-') {
+            Some((first, last)) => {
+                for r in reg(first)?..=reg(last)? {
+                    mask |= 1 << r;
+                }
+            }
+            None => mask |= 1 << reg(part)?,
+        }
+    }
+    Ok(mask)
+}
+
+fn expect_count(ops: &[&str], count: usize) -> Result<(), String> {
+    if ops.len() != count {
+        return Err(format!("expected {} operands, found {}", count, ops.len()));
+    }
+    Ok(())
+}
+
+/// Position of `name` in `names`; callers have already matched it
+fn index_of(names: &[&str], name: &str) -> u32 {
+    names.iter().position(|&n| n == name).unwrap_or(0) as u32
+}
+
+/// Check that `value` is a multiple of `scale` that fits in `bits` once
+/// divided by it, and return the scaled field
+fn field(value: i64, bits: u32, scale: i64, what: &str) -> Result<u32, String> {
+    if value < 0 || value % scale != 0 || value / scale >= 1 << bits {
+        return Err(format!("{} {} out of range", what, value));
+    }
+    Ok((value / scale) as u32)
+}
+
+/// Signed branch or load offset, range-checked and scaled like [`field`]
+fn signed_field(value: i64, bits: u32, scale: i64, what: &str) -> Result<u32, String> {
+    let limit = 1i64 << (bits - 1);
+    if value % scale != 0 || value / scale < -limit || value / scale >= limit {
+        return Err(format!("{} {} out of range", what, value));
+    }
+    Ok(((value / scale) as u32) & ((1 << bits) - 1))
+}
+
+/// Immediate as an ARM 8-bit value rotated right by an even amount
+fn arm_immediate(value: u32) -> Option<u32> {
+    (0..16).find_map(|rot| {
+        let byte = value.rotate_left(rot * 2);
+        (byte <= 0xFF).then_some((rot << 8) | byte)
+    })
+}
+
+/// Data processing opcode with the same effect on an adjusted immediate
+fn dp_alternative(opcode: u32, value: u32) -> Option<(u32, u32)> {
+    match opcode {
+        0x0 => Some((0xE, !value)),
+        0xE => Some((0x0, !value)),
+        0x2 => Some((0x4, value.wrapping_neg())),
+        0x4 => Some((0x2, value.wrapping_neg())),
+        0xA => Some((0xB, value.wrapping_neg())),
+        0xB => Some((0xA, value.wrapping_neg())),
+        0xD => Some((0xF, !value)),
+        0xF => Some((0xD, !value)),
+        _ => None,
+    }
+}
+
+const DP_OPS: [&str; 16] = [
+    "and", "eor", "sub", "rsb", "add", "adc", "sbc", "rsc", "tst", "teq", "cmp", "cmn", "orr",
+    "mov", "bic", "mvn",
+];
+
+/// ARM mnemonic stems, longest first so `bl` is tried before `b`
+const ARM_STEMS: [&str; 42] = [
+    "umull", "umlal", "smull", "smlal", "push", "bkpt", "and", "eor", "sub", "rsb", "add", "adc",
+    "sbc", "rsc", "tst", "teq", "cmp", "cmn", "orr", "mov", "bic", "mvn", "mul", "mla", "ldr",
+    "str", "ldm", "stm", "swp", "swi", "svc", "mrs", "msr", "nop", "lsl", "lsr", "asr", "ror",
+    "pop", "bx", "bl", "b",
+];
+
+/// Suffixes besides the condition that each stem accepts
+fn arm_suffixes(stem: &str) -> &'static [&'static str] {
+    match stem {
+        "ldr" => &["", "b", "t", "bt", "h", "sb", "sh"],
+        "str" => &["", "b", "t", "bt", "h"],
+        "ldm" | "stm" => &["", "ia", "ib", "da", "db", "fd", "fa", "ed", "ea"],
+        "swp" => &["", "b"],
+        "tst" | "teq" | "cmp" | "cmn" => &[""],
+        "mul" | "mla" | "umull" | "umlal" | "smull" | "smlal" | "lsl" | "lsr" | "asr" | "ror" => {
+            &["", "s"]
+        }
+        _ if DP_OPS.contains(&stem) => &["", "s"],
+        _ => &[""],
+    }
+}
+
+/// Split an ARM mnemonic into stem, condition and suffix. The condition
+/// may come before the suffix (`ldreqb`) or after it (`ldrbeq`).
+fn split_arm_mnemonic(mnemonic: &str) -> Option<(&'static str, u32, &str)> {
+    ARM_STEMS.iter().find_map(|&stem| {
+        let rest = mnemonic.strip_prefix(stem)?;
+        let allowed = arm_suffixes(stem);
+        let mut candidates = vec![(14, rest)];
+        if rest.len() >= 2 {
+            if let Some(cond) = cond_code(&rest[..2]) {
+                candidates.push((cond, &rest[2..]));
+            }
+            if let Some(cond) = cond_code(&rest[rest.len() - 2..]) {
+                candidates.push((cond, &rest[..rest.len() - 2]));
+            }
+        }
+        candidates
+            .into_iter()
+            .find(|(_, suffix)| allowed.contains(suffix))
+            .map(|(cond, suffix)| (stem, cond, suffix))
+    })
+}
+
+/// A parsed `[rn, offset]` style memory operand
+struct Address<'a> {
+    rn: u32,
+    pre: bool,
+    writeback: bool,
+    up: bool,
+    offset: Offset<'a>,
+}
+
+enum Offset<'a> {
+    Imm(u32),
+    Reg { rm: u32, shift: Option<&'a str> },
+}
+
+/// Second pass: encodes statements once every label is known
+struct Encoder<'a> {
+    labels: &'a HashMap<String, u32>,
+}
+
+impl Encoder<'_> {
+    fn item(&self, item: &Item) -> Result<Vec<u8>, String> {
+        match &item.kind {
+            Kind::Data { width, expr } => {
+                let value = self.value(expr)?;
+                let limit = if *width == 4 {
+                    u32::MAX as i64
+                } else {
+                    (1i64 << (width * 8)) - 1
+                };
+                if value > limit || value < -(limit / 2 + 1) {
+                    return Err(format!("{} does not fit in {} bytes", value, width));
+                }
+                Ok((value as u32).to_le_bytes()[..*width as usize].to_vec())
+            }
+            Kind::Instruction {
+                thumb: false,
+                mnemonic,
+                operands,
+                literal,
+            } => Ok(self
+                .arm(item.addr, mnemonic, operands, *literal)?
+                .to_le_bytes()
+                .to_vec()),
+            Kind::Instruction {
+                thumb: true,
+                mnemonic,
+                operands,
+                literal,
+            } => Ok(self
+                .thumb(item.addr, mnemonic, operands, *literal)?
+                .iter()
+                .flat_map(|half| half.to_le_bytes())
+                .collect()),
+        }
+    }
+
+    /// Evaluate numbers and labels joined by `+` and `-`; a leading `#` is ignored
+    fn value(&self, expr: &str) -> Result<i64, String> {
+        let expr = expr.trim();
+        let expr = expr.strip_prefix('#').unwrap_or(expr);
+        let mut total = 0i64;
+        let mut sign = 1i64;
+        let mut term = String::new();
+        let mut terms = 0;
+        for c in expr.chars().chain(std::iter::once('+')) {
+            match c {
+                '+' | '-' if !term.trim().is_empty() => {
+                    total = total.wrapping_add(sign * self.term(term.trim())?);
+                    terms += 1;
+                    term.clear();
+                    sign = if c == '-' { -1 } else { 1 };
+                }
+                '-' => sign = -sign,
+                '+' => {}
+                _ => term.push(c),
+            }
+        }
+        if terms == 0 {
+            return Err("missing value".to_string());
+        }
+        Ok(total)
+    }
+
+    fn term(&self, term: &str) -> Result<i64, String> {
+        if let Some(n) = parse_number(term) {
+            return Ok(n);
+        }
+        match self.labels.get(term) {
+            Some(&addr) => Ok(addr as i64),
+            None if is_label(term) => Err(format!("unknown label `{}`", term)),
+            None => Err(format!("`{}` is not a number or label", term)),
+        }
+    }
+
+    /// `[rn, #imm]!`, `[rn], rm, lsl #2` and friends; `ops` starts at the
+    /// bracketed operand
+    fn address<'o>(&self, ops: &[&'o str]) -> Result<Address<'o>, String> {
+        let first = ops.first().ok_or("missing address")?;
+        let (inner, writeback) = match first.strip_suffix('!') {
+            Some(rest) => (rest.trim(), true),
+            None => (*first, false),
+        };
+        let inner = inner
+            .strip_prefix('[')
+            .and_then(|rest| rest.strip_suffix(']'))
+            .ok_or_else(|| format!("`{}` is not an address", first))?;
+        let inner = split_operands(inner);
+        let rn = reg(inner.first().ok_or("missing base register")?)?;
+
+        let (pre, offset) = match (&inner[1..], &ops[1..]) {
+            ([], []) => (true, &[][..]),
+            (offset, []) => (true, offset),
+            ([], offset) if !writeback => (false, offset),
+            _ => return Err(format!("`{}` is not an address", ops.join(", "))),
+        };
+        let mut address = Address {
+            rn,
+            pre,
+            writeback,
+            up: true,
+            offset: Offset::Imm(0),
+        };
+        let Some(&first) = offset.first() else {
+            return Ok(address);
+        };
+        if first.starts_with('#') {
+            if offset.len() > 1 {
+                return Err("an immediate offset takes no shift".to_string());
+            }
+            let value = self.value(first)?;
+            address.up = value >= 0;
+            address.offset = Offset::Imm(value.unsigned_abs() as u32);
+        } else {
+            let (up, rm) = match first.strip_prefix('-') {
+                Some(rm) => (false, rm),
+                None => (true, first.strip_prefix('+').unwrap_or(first)),
+            };
+            if offset.len() > 2 {
+                return Err("too many offset operands".to_string());
+            }
+            address.up = up;
+            address.offset = Offset::Reg {
+                rm: reg(rm)?,
+                shift: offset.get(1).copied(),
+            };
+        }
+        Ok(address)
+    }
+
+    /// Shift field of a shifted register operand: `lsl #2`, `asr r3`, `rrx`
+    fn shift(&self, text: &str, register_shift: bool) -> Result<u32, String> {
+        let text = text.trim();
+        if text.eq_ignore_ascii_case("rrx") {
+            return Ok(3 << 5);
+        }
+        let (name, amount) = text
+            .split_once(char::is_whitespace)
+            .ok_or_else(|| format!("`{}` is not a shift", text))?;
+        let kind = match name.to_ascii_lowercase().as_str() {
+            "lsl" | "asl" => 0,
+            "lsr" => 1,
+            "asr" => 2,
+            "ror" => 3,
+            _ => return Err(format!("`{}` is not a shift", name)),
+        };
+        let amount = amount.trim();
+        if !amount.starts_with('#') {
+            if !register_shift {
+                return Err("shift by register is not allowed here".to_string());
+            }
+            return Ok((reg(amount)? << 8) | (kind << 5) | 0x10);
+        }
+        let n = self.value(amount)?;
+        let valid = match kind {
+            0 => (0..=31).contains(&n),
+            3 => (1..=31).contains(&n),
+            _ => (1..=32).contains(&n),
+        };
+        if !valid {
+            return Err(format!("shift amount {} out of range", n));
+        }
+        Ok((((n as u32) & 0x1F) << 7) | (kind << 5))
+    }
+
+    /// Bits 0-11 and the I bit of a data processing operand
+    fn operand2(&self, ops: &[&str]) -> Result<Result<u32, u32>, String> {
+        match ops {
+            [imm] if imm.starts_with('#') => Ok(Err(self.value(imm)? as u32)),
+            [rm] => Ok(Ok(reg(rm)?)),
+            [rm, shift] => Ok(Ok(reg(rm)? | self.shift(shift, true)?)),
+            _ => Err("bad second operand".to_string()),
+        }
+    }
+
+    fn arm(
+        &self,
+        addr: u32,
+        mnemonic: &str,
+        operands: &str,
+        literal: Option<u32>,
+    ) -> Result<u32, String> {
+        let (stem, cond, suffix) = split_arm_mnemonic(mnemonic)
+            .ok_or_else(|| format!("unknown instruction `{}`", mnemonic))?;
+        let ops = split_operands(operands);
+        let c = cond << 28;
+        let s = if suffix == "s" { 1 << 20 } else { 0 };
+
+        if let Some(opcode) = DP_OPS.iter().position(|&op| op == stem) {
+            return self.arm_data_processing(c, opcode as u32, s, &ops);
+        }
+        match stem {
+            "lsl" | "lsr" | "asr" | "ror" => {
+                let (rd, rm, amount) = match ops[..] {
+                    [rd, amount] => (rd, rd, amount),
+                    [rd, rm, amount] => (rd, rm, amount),
+                    _ => return Err("expected rd, rm, amount".to_string()),
+                };
+                let shift = format!("{} {}", stem, amount);
+                self.arm_data_processing(c, 0xD, s, &[rd, rm, shift.as_str()])
+            }
+            "mul" => {
+                expect_count(&ops, 3)?;
+                Ok(c | s | (reg(ops[0])? << 16) | (reg(ops[2])? << 8) | 0x90 | reg(ops[1])?)
+            }
+            "mla" => {
+                expect_count(&ops, 4)?;
+                Ok(c | s
+                    | 1 << 21
+                    | (reg(ops[0])? << 16)
+                    | (reg(ops[3])? << 12)
+                    | (reg(ops[2])? << 8)
+                    | 0x90
+                    | reg(ops[1])?)
+            }
+            "umull" | "umlal" | "smull" | "smlal" => {
+                expect_count(&ops, 4)?;
+                let kind = index_of(&["umull", "umlal", "smull", "smlal"], stem);
+                Ok(c | 0x0080_0090
+                    | kind << 21
+                    | s
+                    | (reg(ops[1])? << 16)
+                    | (reg(ops[0])? << 12)
+                    | (reg(ops[3])? << 8)
+                    | reg(ops[2])?)
+            }
+            "ldr" | "str" => self.arm_transfer(addr, c, stem == "ldr", suffix, &ops, literal),
+            "ldm" | "stm" => {
+                expect_count(&ops, 2)?;
+                let load = stem == "ldm";
+                let (pre, up) = match (suffix, load) {
+                    ("" | "ia", _) | ("fd", true) | ("ea", false) => (false, true),
+                    ("ib", _) | ("ed", true) | ("fa", false) => (true, true),
+                    ("da", _) | ("fa", true) | ("ed", false) => (false, false),
+                    _ => (true, false),
+                };
+                let (rn, writeback) = match ops[0].strip_suffix('!') {
+                    Some(rn) => (rn, true),
+                    None => (ops[0], false),
+                };
+                let (list, user) = match ops[1].strip_suffix('^') {
+                    Some(list) => (list, true),
+                    None => (ops[1], false),
+                };
+                Ok(c | 0x0800_0000
+                    | (pre as u32) << 24
+                    | (up as u32) << 23
+                    | (user as u32) << 22
+                    | (writeback as u32) << 21
+                    | (load as u32) << 20
+                    | (reg(rn)? << 16)
+                    | reg_list(list)?)
+            }
+            "push" | "pop" => {
+                expect_count(&ops, 1)?;
+                let base = if stem == "push" {
+                    0x092D_0000
+                } else {
+                    0x08BD_0000
+                };
+                Ok(c | base | reg_list(ops[0])?)
+            }
+            "swp" => {
+                expect_count(&ops, 3)?;
+                let rn = ops[2]
+                    .strip_prefix('[')
+                    .and_then(|rest| rest.strip_suffix(']'))
+                    .ok_or("swp needs [rn]")?;
+                let byte = (suffix == "b") as u32;
+                Ok(c | 0x0100_0090
+                    | byte << 22
+                    | (reg(rn)? << 16)
+                    | (reg(ops[0])? << 12)
+                    | reg(ops[1])?)
+            }
+            "b" | "bl" => {
+                expect_count(&ops, 1)?;
+                let offset = self.value(ops[0])? - (addr as i64 + 8);
+                let link = (stem == "bl") as u32;
+                Ok(c | 0x0A00_0000 | link << 24 | signed_field(offset, 24, 4, "branch offset")?)
+            }
+            "bx" => {
+                expect_count(&ops, 1)?;
+                Ok(c | 0x012F_FF10 | reg(ops[0])?)
+            }
+            "swi" | "svc" => {
+                expect_count(&ops, 1)?;
+                Ok(c | 0x0F00_0000 | field(self.value(ops[0])?, 24, 1, "SWI number")?)
+            }
+            "mrs" => {
+                expect_count(&ops, 2)?;
+                let spsr = match ops[1].to_ascii_lowercase().as_str() {
+                    "cpsr" => 0,
+                    "spsr" => 1,
+                    other => return Err(format!("`{}` is not cpsr or spsr", other)),
+                };
+                Ok(c | 0x010F_0000 | spsr << 22 | (reg(ops[0])? << 12))
+            }
+            "msr" => {
+                expect_count(&ops, 2)?;
+                let target = ops[0].to_ascii_lowercase();
+                let (psr, fields) = target.split_once('_').unwrap_or((&target, "fc"));
+                let spsr = match psr {
+                    "cpsr" => 0,
+                    "spsr" => 1,
+                    _ => return Err(format!("`{}` is not cpsr or spsr", psr)),
+                };
+                let mut mask = 0;
+                for f in fields.chars() {
+                    mask |= match f {
+                        'c' => 1,
+                        'x' => 2,
+                        's' => 4,
+                        'f' => 8,
+                        _ => return Err(format!("`{}` is not a PSR field", f)),
+                    };
+                }
+                let source = match self.operand2(&ops[1..])? {
+                    Ok(rm) if rm < 16 => rm,
+                    Ok(_) => return Err("msr takes no shift".to_string()),
+                    Err(value) => {
+                        1 << 25
+                            | arm_immediate(value)
+                                .ok_or_else(|| format!("#0x{:X} cannot be encoded", value))?
+                    }
+                };
+                Ok(c | 0x0120_F000 | spsr << 22 | mask << 16 | source)
+            }
+            "nop" => Ok(c | 0x01A0_0000),
+            "bkpt" => {
+                expect_count(&ops, 1)?;
+                let imm = field(self.value(ops[0])?, 16, 1, "breakpoint number")?;
+                Ok(0xE120_0070 | (imm & 0xFFF0) << 4 | (imm & 0xF))
+            }
+            _ => Err(format!("unknown instruction `{}`", mnemonic)),
+        }
+    }
+
+    fn arm_data_processing(
+        &self,
+        c: u32,
+        opcode: u32,
+        s: u32,
+        ops: &[&str],
+    ) -> Result<u32, String> {
+        let (rd, rn, rest) = match opcode {
+            0x8..=0xB => (
+                0,
+                reg(ops.first().ok_or("missing operands")?)?,
+                ops.get(1..).unwrap_or(&[]),
+            ),
+            0xD | 0xF => (
+                reg(ops.first().ok_or("missing operands")?)?,
+                0,
+                ops.get(1..).unwrap_or(&[]),
+            ),
+            _ if ops.len() == 2 => (reg(ops[0])?, reg(ops[0])?, &ops[1..]),
+            _ if ops.len() >= 3 => (reg(ops[0])?, reg(ops[1])?, &ops[2..]),
+            _ => return Err("missing operands".to_string()),
+        };
+        let s = if (0x8..=0xB).contains(&opcode) {
+            1 << 20
+        } else {
+            s
+        };
+        let (opcode, operand) = match self.operand2(rest)? {
+            Ok(bits) => (opcode, bits),
+            Err(value) => match arm_immediate(value) {
+                Some(bits) => (opcode, 1 << 25 | bits),
+                None => dp_alternative(opcode, value)
+                    .and_then(|(alt, alt_value)| Some((alt, 1 << 25 | arm_immediate(alt_value)?)))
+                    .ok_or_else(|| format!("#0x{:X} cannot be encoded", value))?,
+            },
+        };
+        Ok(c | opcode << 21 | s | rn << 16 | rd << 12 | operand)
+    }
+
+    fn arm_transfer(
+        &self,
+        addr: u32,
+        c: u32,
+        load: bool,
+        suffix: &str,
+        ops: &[&str],
+        literal: Option<u32>,
+    ) -> Result<u32, String> {
+        let rd = reg(ops.first().ok_or("missing operands")?)?;
+        let address = match (literal, ops.get(1)) {
+            (Some(target), _) => self.pc_relative(addr + 8, target as i64)?,
+            (None, Some(op)) if !op.starts_with('[') => {
+                expect_count(ops, 2)?;
+                self.pc_relative(addr + 8, self.value(op)?)?
+            }
+            _ => self.address(&ops[1..])?,
+        };
+        let Address {
+            rn,
+            pre,
+            writeback,
+            up,
+            offset,
+        } = address;
+        let flags =
+            c | (pre as u32) << 24 | (up as u32) << 23 | (load as u32) << 20 | rn << 16 | rd << 12;
+
+        if matches!(suffix, "h" | "sb" | "sh") {
+            let sh = match suffix {
+                "h" => 0xB0,
+                "sb" => 0xD0,
+                _ => 0xF0,
+            };
+            let offset = match offset {
+                Offset::Imm(imm) => {
+                    let imm = field(imm as i64, 8, 1, "offset")?;
+                    1 << 22 | (imm & 0xF0) << 4 | (imm & 0xF)
+                }
+                Offset::Reg { rm, shift: None } => rm,
+                Offset::Reg { .. } => return Err("halfword transfers take no shift".to_string()),
+            };
+            return Ok(flags | (writeback as u32) << 21 | sh | offset);
+        }
+
+        let translate = suffix.contains('t');
+        if translate && pre {
+            return Err("the t suffix needs a post-indexed address".to_string());
+        }
+        let byte = suffix.contains('b') as u32;
+        let offset = match offset {
+            Offset::Imm(imm) => field(imm as i64, 12, 1, "offset")?,
+            Offset::Reg { rm, shift } => {
+                let shift = match shift {
+                    Some(shift) => self.shift(shift, false)?,
+                    None => 0,
+                };
+                1 << 25 | shift | rm
+            }
+        };
+        let w = (writeback || translate) as u32;
+        Ok(flags | 0x0400_0000 | byte << 22 | w << 21 | offset)
+    }
+
+    /// PC-relative address of `target`, where `pc` is the value PC reads as
+    fn pc_relative(&self, pc: u32, target: i64) -> Result<Address<'static>, String> {
+        let offset = target - pc as i64;
+        Ok(Address {
+            rn: 15,
+            pre: true,
+            writeback: false,
+            up: offset >= 0,
+            offset: Offset::Imm(offset.unsigned_abs() as u32),
+        })
+    }
+
+    fn thumb(
+        &self,
+        addr: u32,
+        mnemonic: &str,
+        operands: &str,
+        literal: Option<u32>,
+    ) -> Result<Vec<u16>, String> {
+        let ops = split_operands(operands);
+        let branch_offset = || -> Result<i64, String> {
+            expect_count(&ops, 1)?;
+            Ok(self.value(ops[0])? - (addr as i64 + 4))
+        };
+        match mnemonic {
+            "bl" => {
+                let offset = signed_field(branch_offset()?, 22, 2, "branch offset")?;
+                return Ok(vec![
+                    0xF000 | (offset >> 11) as u16,
+                    0xF800 | (offset & 0x7FF) as u16,
+                ]);
+            }
+            "b" => {
+                return Ok(vec![
+                    0xE000 | signed_field(branch_offset()?, 11, 2, "branch offset")? as u16,
+                ])
+            }
+            "bx" => {
+                expect_count(&ops, 1)?;
+                return Ok(vec![0x4700 | (reg(ops[0])? << 3) as u16]);
+            }
+            "swi" | "svc" | "bkpt" => {
+                expect_count(&ops, 1)?;
+                let base = if mnemonic == "bkpt" { 0xBE00 } else { 0xDF00 };
+                return Ok(vec![
+                    base | field(self.value(ops[0])?, 8, 1, "immediate")? as u16,
+                ]);
+            }
+            "nop" => return Ok(vec![0x46C0]),
+            _ => {}
+        }
+        if let Some(cond) = mnemonic
+            .strip_prefix('b')
+            .and_then(cond_code)
+            .filter(|&c| c < 14)
+        {
+            let offset = signed_field(branch_offset()?, 8, 2, "branch offset")?;
+            return Ok(vec![0xD000 | (cond << 8) as u16 | offset as u16]);
+        }
+
+        let name = match mnemonic.strip_suffix('s') {
+            Some(stem) if stem.len() >= 3 && !stem.starts_with("ld") && !stem.starts_with("st") => {
+                stem
+            }
+            _ => mnemonic,
+        };
+        let op = self.thumb_op(addr, name, &ops, literal)?;
+        Ok(vec![op as u16])
+    }
+
+    fn thumb_op(
+        &self,
+        addr: u32,
+        name: &str,
+        ops: &[&str],
+        literal: Option<u32>,
+    ) -> Result<u32, String> {
+        let imm = |s: &str| s.starts_with('#');
+        let hi = |rd: u32, rs: u32| (rd & 8) << 4 | rs << 3 | (rd & 7);
+        match (name, ops) {
+            ("lsl" | "lsr" | "asr", [rd, rs, amount]) if imm(amount) => {
+                let kind = index_of(&["lsl", "lsr", "asr"], name);
+                let n = self.value(amount)?;
+                let n = if kind != 0 && n == 32 {
+                    0
+                } else {
+                    field(n, 5, 1, "shift amount")? as i64
+                };
+                Ok(kind << 11 | (n as u32) << 6 | low_reg(rs)? << 3 | low_reg(rd)?)
+            }
+            ("add" | "sub", [rd, base, value])
+                if ["pc", "sp"].contains(&base.to_ascii_lowercase().as_str()) =>
+            {
+                let base = reg(base)?;
+                if reg(rd)? == 13 && base == 13 {
+                    return self.thumb_op(addr, name, &[*rd, *value], literal);
+                }
+                if name == "sub" || reg(rd)? == 13 {
+                    return Err(format!("{} {} cannot be encoded", name, ops.join(", ")));
+                }
+                let sp = (base == 13) as u32;
+                Ok(0xA000
+                    | sp << 11
+                    | low_reg(rd)? << 8
+                    | field(self.value(value)?, 8, 4, "offset")?)
+            }
+            ("add" | "sub", [sp, value]) if reg(sp) == Ok(13) && imm(value) => {
+                let n = self.value(value)?;
+                let n = if name == "sub" { -n } else { n };
+                let negative = (n < 0) as u32;
+                Ok(0xB000 | negative << 7 | field(n.abs(), 7, 4, "offset")?)
+            }
+            ("add" | "sub", [rd, rs, operand]) => {
+                let sub = (name == "sub") as u32;
+                if imm(operand) {
+                    let n = self.value(operand)?;
+                    if n > 7 && reg(rd)? == reg(rs)? {
+                        return self.thumb_op(addr, name, &[*rd, *operand], literal);
+                    }
+                    Ok(0x1C00
+                        | sub << 9
+                        | field(n, 3, 1, "immediate")? << 6
+                        | low_reg(rs)? << 3
+                        | low_reg(rd)?)
+                } else {
+                    Ok(0x1800
+                        | sub << 9
+                        | low_reg(operand)? << 6
+                        | low_reg(rs)? << 3
+                        | low_reg(rd)?)
+                }
+            }
+            ("mov" | "cmp" | "add" | "sub", [rd, value]) if imm(value) => {
+                let kind = index_of(&["mov", "cmp", "add", "sub"], name);
+                Ok(0x2000
+                    | kind << 11
+                    | low_reg(rd)? << 8
+                    | field(self.value(value)?, 8, 1, "immediate")?)
+            }
+            ("add", [rd, rs]) if reg(rd)? < 8 && reg(rs)? < 8 => {
+                Ok(0x1800 | reg(rs)? << 6 | reg(rd)? << 3 | reg(rd)?)
+            }
+            ("mov", [rd, rs]) if reg(rd)? < 8 && reg(rs)? < 8 => {
+                Ok(0x1C00 | reg(rs)? << 3 | reg(rd)?)
+            }
+            ("add", [rd, rs]) => Ok(0x4400 | hi(reg(rd)?, reg(rs)?)),
+            ("cmp", [rd, rs]) if reg(rd)? >= 8 || reg(rs)? >= 8 => {
+                Ok(0x4500 | hi(reg(rd)?, reg(rs)?))
+            }
+            ("mov", [rd, rs]) => Ok(0x4600 | hi(reg(rd)?, reg(rs)?)),
+            ("mul", [rd, rs, again]) if reg(rd)? == reg(again)? => {
+                self.thumb_op(addr, name, &[*rd, *rs], literal)
+            }
+            (_, [rd, rs]) if THUMB_ALU.contains(&name) && !imm(rs) => {
+                let kind = index_of(&THUMB_ALU, name);
+                Ok(0x4000 | kind << 6 | low_reg(rs)? << 3 | low_reg(rd)?)
+            }
+            ("push" | "pop", [list]) => {
+                let mask = reg_list(list)?;
+                let (load, extra) = if name == "pop" { (1, 15) } else { (0, 14) };
+                if mask & !(0xFF | 1 << extra) != 0 {
+                    return Err(format!("{} cannot transfer {}", name, list));
+                }
+                Ok(0xB400 | load << 11 | (mask >> extra & 1) << 8 | (mask & 0xFF))
+            }
+            ("ldmia" | "stmia" | "ldm" | "stm", [rb, list]) => {
+                let rb = rb
+                    .strip_suffix('!')
+                    .ok_or("Thumb ldm/stm always write back")?;
+                let load = name.starts_with("ld") as u32;
+                let mask = reg_list(list)?;
+                if mask > 0xFF {
+                    return Err("only low registers can be listed".to_string());
+                }
+                Ok(0xC000 | load << 11 | low_reg(rb)? << 8 | mask)
+            }
+            (
+                "ldr" | "str" | "ldrb" | "strb" | "ldrh" | "strh" | "ldsb" | "ldrsb" | "ldsh"
+                | "ldrsh",
+                [rd, ..],
+            ) => self.thumb_transfer(addr, name, reg(rd)?, &ops[1..], literal),
+            _ => Err(format!(
+                "unknown Thumb instruction `{} {}`",
+                name,
+                ops.join(", ")
+            )),
+        }
+    }
+
+    fn thumb_transfer(
+        &self,
+        addr: u32,
+        name: &str,
+        rd: u32,
+        ops: &[&str],
+        literal: Option<u32>,
+    ) -> Result<u32, String> {
+        let pc = (addr + 4) & !2;
+        let pc_relative = |target: i64| -> Result<u32, String> {
+            if name != "ldr" || rd > 7 {
+                return Err("only ldr with a low register can load PC-relative".to_string());
+            }
+            Ok(0x4800 | rd << 8 | field(target - pc as i64, 8, 4, "PC-relative offset")?)
+        };
+        if let Some(target) = literal {
+            return pc_relative(target as i64);
+        }
+        if let [label] = ops {
+            if !label.starts_with('[') {
+                return pc_relative(self.value(label)?);
+            }
+        }
+        let address = self.address(ops)?;
+        if !address.pre || address.writeback || !address.up {
+            return Err("Thumb addresses are [rb, #imm] or [rb, ro]".to_string());
+        }
+        let rd = low(rd)?;
+        match (address.rn, address.offset) {
+            (15, Offset::Imm(imm)) => pc_relative(pc as i64 + imm as i64),
+            (13, Offset::Imm(imm)) => {
+                let load = match name {
+                    "ldr" => 1,
+                    "str" => 0,
+                    _ => return Err(format!("{} cannot address sp", name)),
+                };
+                Ok(0x9000 | load << 11 | rd << 8 | field(imm as i64, 8, 4, "offset")?)
+            }
+            (rb, Offset::Reg { rm, shift: None }) => {
+                let kind = index_of(
+                    &["str", "strh", "strb", "ldsb", "ldr", "ldrh", "ldrb", "ldsh"],
+                    &name.replace("ldrs", "lds"),
+                );
+                Ok(0x5000 | kind << 9 | low(rm)? << 6 | low(rb)? << 3 | rd)
+            }
+            (rb, Offset::Imm(imm)) => {
+                let (base, scale) = match name {
+                    "str" => (0x6000, 4),
+                    "ldr" => (0x6800, 4),
+                    "strb" => (0x7000, 1),
+                    "ldrb" => (0x7800, 1),
+                    "strh" => (0x8000, 2),
+                    "ldrh" => (0x8800, 2),
+                    _ => return Err(format!("{} has no immediate offset form", name)),
+                };
+                Ok(base | field(imm as i64, 5, scale, "offset")? << 6 | low(rb)? << 3 | rd)
+            }
+            _ => Err("Thumb offsets take no shift".to_string()),
+        }
+    }
+}
+
+/// Thumb register-to-register ALU operations, by opcode
+const THUMB_ALU: [&str; 16] = [
+    "and", "eor", "lsl", "lsr", "asr", "adc", "sbc", "ror", "tst", "neg", "cmp", "cmn", "orr",
+    "mul", "bic", "mvn",
+];
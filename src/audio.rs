@@ -0,0 +1,383 @@
+//! Audio Output Resampling
+//!
+//! The APU mix is sampled every [`CYCLES_PER_SAMPLE`] cycles, the
+//! 32768 Hz rate of the default SOUNDBIAS resolution, and converted to the
+//! client's output rate. Nearest-neighbour conversion is the cheapest but
+//! aliases Direct Sound badly; linear interpolation and a windowed-sinc
+//! kernel trade CPU time for a cleaner signal.
+//!
+//! When emulation runs faster or slower than real time the output either
+//! follows the speed in pitch, like a tape, or keeps its pitch by trimming
+//! or repeating short grains of sound so its duration matches wall time.
+
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+
+use crate::throttle::CPU_FREQUENCY;
+
+/// Rate the APU mix is sampled at, in Hz
+pub const NATIVE_SAMPLE_RATE: u32 = 32768;
+
+/// CPU cycles between two native samples
+pub const CYCLES_PER_SAMPLE: u32 = (CPU_FREQUENCY / NATIVE_SAMPLE_RATE as u64) as u32;
+
+/// Output rate used until the client picks one
+pub const DEFAULT_OUTPUT_RATE: u32 = 48000;
+
+/// Sinc kernel half-width in native samples
+const SINC_TAPS: usize = 8;
+
+/// Buffered output is capped at this many seconds; older samples are dropped
+const MAX_BUFFERED_SECONDS: usize = 1;
+
+/// Grains per second when stretching audio to keep its pitch
+const GRAINS_PER_SECOND: u32 = 50;
+
+/// Interpolation used to convert native samples to the output rate
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AudioQuality {
+    /// Repeat or drop native samples
+    Nearest,
+    /// Interpolate between the two surrounding native samples
+    #[default]
+    Linear,
+    /// Hann-windowed sinc over 16 native samples
+    Sinc,
+}
+
+impl AudioQuality {
+    pub const ALL: [AudioQuality; 3] = [
+        AudioQuality::Nearest,
+        AudioQuality::Linear,
+        AudioQuality::Sinc,
+    ];
+
+    /// Native samples the kernel needs after an output sample's position
+    fn latency(self) -> usize {
+        match self {
+            AudioQuality::Nearest => 0,
+            AudioQuality::Linear => 1,
+            AudioQuality::Sinc => SINC_TAPS,
+        }
+    }
+}
+
+/// How the output follows an emulation speed other than 100%
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AudioPitch {
+    /// Resample all emulated audio into wall time, so pitch scales with speed
+    #[default]
+    Scaled,
+    /// Keep the original pitch; grains of output are trimmed when fast and
+    /// repeated when slow
+    Corrected,
+}
+
+/// Samples the APU and resamples to the output rate into a stereo buffer
+#[derive(Debug, Clone)]
+pub struct AudioOutput {
+    rate: u32,
+    quality: AudioQuality,
+    /// Cycles left until the next native sample
+    countdown: u32,
+    /// Most recent native samples, oldest first
+    history: VecDeque<[f32; 2]>,
+    /// Position of the next output sample, in native samples from
+    /// `history[0]`
+    pos: f64,
+    /// Interleaved left/right output samples
+    buffer: VecDeque<i16>,
+    /// Output samples produced in the current and the last complete frame
+    frame_count: usize,
+    last_frame_count: usize,
+    /// Emulated seconds per wall-clock second
+    speed: f64,
+    pitch: AudioPitch,
+    /// Pitch-corrected output waiting to be stretched
+    grain: Vec<[f32; 2]>,
+    /// Where the last stretched grain would have continued, faded into the
+    /// start of the next one
+    crossfade: Vec<[f32; 2]>,
+    /// Fraction of an output sample carried between grains
+    stretch_error: f64,
+    /// Copy of the output for video capture, independent of the buffer
+    tap: Option<Vec<i16>>,
+}
+
+impl AudioOutput {
+    pub fn new(rate: u32, quality: AudioQuality) -> Self {
+        Self {
+            rate: rate.max(1),
+            quality,
+            countdown: CYCLES_PER_SAMPLE,
+            history: VecDeque::with_capacity(2 * SINC_TAPS + 2),
+            pos: 0.0,
+            buffer: VecDeque::new(),
+            frame_count: 0,
+            last_frame_count: 0,
+            speed: 1.0,
+            pitch: AudioPitch::default(),
+            grain: Vec::new(),
+            crossfade: Vec::new(),
+            stretch_error: 0.0,
+            tap: None,
+        }
+    }
+
+    pub fn rate(&self) -> u32 {
+        self.rate
+    }
+
+    /// Change the output rate; buffered output is kept
+    pub fn set_rate(&mut self, rate: u32) {
+        self.flush_grain();
+        self.rate = rate.max(1);
+    }
+
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    /// Emulation speed relative to real hardware, used to fit the output to
+    /// wall time
+    pub fn set_speed(&mut self, speed: f64) {
+        self.flush_grain();
+        self.speed = speed;
+    }
+
+    pub fn pitch(&self) -> AudioPitch {
+        self.pitch
+    }
+
+    pub fn set_pitch(&mut self, pitch: AudioPitch) {
+        self.flush_grain();
+        self.pitch = pitch;
+    }
+
+    pub fn quality(&self) -> AudioQuality {
+        self.quality
+    }
+
+    pub fn set_quality(&mut self, quality: AudioQuality) {
+        self.quality = quality;
+    }
+
+    /// Cycles until the next native sample is taken
+    pub fn cycles_until_sample(&self) -> u32 {
+        self.countdown
+    }
+
+    /// Advance `cycles`, sampling the APU output `left`/`right` if a native
+    /// sample falls due. Callers split steps at [`Self::cycles_until_sample`].
+    pub fn step(&mut self, cycles: u32, left: i16, right: i16) {
+        let mut cycles = cycles;
+        while cycles >= self.countdown {
+            cycles = cycles.wrapping_sub(self.countdown);
+            self.countdown = CYCLES_PER_SAMPLE;
+            self.push_native([left as f32, right as f32]);
+        }
+        self.countdown = self.countdown.wrapping_sub(cycles);
+    }
+
+    /// Close the current video frame's sample count
+    pub fn end_frame(&mut self) {
+        self.last_frame_count = self.frame_count;
+        self.frame_count = 0;
+    }
+
+    /// Stereo output samples produced during the last complete video frame
+    pub fn frame_sample_count(&self) -> usize {
+        self.last_frame_count
+    }
+
+    /// Stereo output samples waiting in the buffer
+    pub fn buffered(&self) -> usize {
+        self.buffer.len() / 2
+    }
+
+    /// Move every buffered sample into `out`, interleaved left/right
+    pub fn drain_into(&mut self, out: &mut Vec<i16>) {
+        out.extend(self.buffer.drain(..));
+    }
+
+    /// Also collect every output sample for [`Self::take_tap`], so a
+    /// capture sees the audio whoever drains the buffer
+    pub(crate) fn set_tap(&mut self, enabled: bool) {
+        if enabled != self.tap.is_some() {
+            self.tap = enabled.then(Vec::new);
+        }
+    }
+
+    /// Move the samples collected since the last call into `out`
+    pub(crate) fn take_tap(&mut self, out: &mut Vec<i16>) {
+        if let Some(tap) = &mut self.tap {
+            out.append(tap);
+        }
+    }
+
+    /// Drop buffered output and resampler history
+    pub fn clear(&mut self) {
+        self.countdown = CYCLES_PER_SAMPLE;
+        self.history.clear();
+        self.pos = 0.0;
+        self.buffer.clear();
+        self.frame_count = 0;
+        self.last_frame_count = 0;
+        self.grain.clear();
+        self.crossfade.clear();
+        self.stretch_error = 0.0;
+        if let Some(tap) = &mut self.tap {
+            tap.clear();
+        }
+    }
+
+    fn push_native(&mut self, sample: [f32; 2]) {
+        if self.history.len() == 2 * SINC_TAPS + 2 {
+            self.history.pop_front();
+            self.pos -= 1.0;
+        }
+        self.history.push_back(sample);
+
+        let mut step = NATIVE_SAMPLE_RATE as f64 / self.rate as f64;
+        if self.pitch == AudioPitch::Scaled {
+            step *= self.speed;
+        }
+        let ready = self.history.len() as f64 - 1.0 - self.quality.latency() as f64;
+        while self.pos <= ready {
+            let [l, r] = self.interpolate(self.pos, step);
+            if self.pitch == AudioPitch::Corrected && self.speed != 1.0 {
+                self.stretch(l, r);
+            } else {
+                self.emit(l, r);
+            }
+            self.pos += step;
+        }
+    }
+
+    fn native(&self, index: isize) -> [f32; 2] {
+        usize::try_from(index)
+            .ok()
+            .and_then(|i| self.history.get(i))
+            .copied()
+            .unwrap_or([0.0; 2])
+    }
+
+    /// Output value at fractional native position `pos`
+    fn interpolate(&self, pos: f64, step: f64) -> [f32; 2] {
+        let base = pos.floor() as isize;
+        let frac = pos - pos.floor();
+        match self.quality {
+            AudioQuality::Nearest => self.native(pos.round() as isize),
+            AudioQuality::Linear => {
+                let (a, b) = (self.native(base), self.native(base.wrapping_add(1)));
+                let t = frac as f32;
+                [a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t]
+            }
+            AudioQuality::Sinc => {
+                // Lower the cutoff below the output Nyquist when downsampling
+                let cutoff = (1.0 / step).min(1.0);
+                let mut acc = [0.0f64; 2];
+                let mut weight_sum = 0.0;
+                const TAPS: isize = SINC_TAPS as isize;
+                for i in 1 - TAPS..=TAPS {
+                    let x = i as f64 - frac;
+                    let window = 0.5 + 0.5 * (PI * x / SINC_TAPS as f64).cos();
+                    let weight = sinc(x * cutoff) * window;
+                    let s = self.native(base.wrapping_add(i));
+                    acc[0] += s[0] as f64 * weight;
+                    acc[1] += s[1] as f64 * weight;
+                    weight_sum += weight;
+                }
+                // Normalize so a constant input passes through unchanged
+                let norm = if weight_sum.abs() > f64::EPSILON {
+                    weight_sum
+                } else {
+                    1.0
+                };
+                [(acc[0] / norm) as f32, (acc[1] / norm) as f32]
+            }
+        }
+    }
+
+    /// Collect pitch-corrected output and emit each full grain resized to
+    /// the wall time it covers
+    fn stretch(&mut self, left: f32, right: f32) {
+        self.grain.push([left, right]);
+        let len = (self.rate / GRAINS_PER_SECOND).max(1) as usize;
+        if self.grain.len() < len {
+            return;
+        }
+
+        let exact = len as f64 / self.speed + self.stretch_error;
+        let target = exact.round().max(0.0) as usize;
+        self.stretch_error = exact - target as f64;
+
+        let grain = std::mem::take(&mut self.grain);
+        let fade = std::mem::take(&mut self.crossfade);
+        for (i, &sample) in grain.iter().cycle().take(target).enumerate() {
+            let [mut l, mut r] = sample;
+            if let Some(&[pl, pr]) = fade.get(i) {
+                let t = i as f32 / fade.len() as f32;
+                l = pl + (l - pl) * t;
+                r = pr + (r - pr) * t;
+            }
+            self.emit(l, r);
+        }
+        // A grain played whole flows straight into the next one; otherwise
+        // blend from where this one would have gone on
+        if target != len {
+            self.crossfade = grain
+                .iter()
+                .cycle()
+                .skip(target)
+                .take(len / 4)
+                .copied()
+                .collect();
+        }
+        self.grain = grain;
+        self.grain.clear();
+    }
+
+    /// Emit a partial grain unstretched, before the stretch settings change
+    fn flush_grain(&mut self) {
+        let grain = std::mem::take(&mut self.grain);
+        for &[l, r] in &grain {
+            self.emit(l, r);
+        }
+        self.grain = grain;
+        self.grain.clear();
+        self.crossfade.clear();
+        self.stretch_error = 0.0;
+    }
+
+    fn emit(&mut self, left: f32, right: f32) {
+        let max = (self.rate as usize).saturating_mul(2 * MAX_BUFFERED_SECONDS);
+        if self.buffer.len() >= max {
+            self.buffer.drain(..2);
+        }
+        let sample = [clamp_i16(left), clamp_i16(right)];
+        self.buffer.extend(sample);
+        if let Some(tap) = &mut self.tap {
+            tap.extend(sample);
+        }
+        self.frame_count = self.frame_count.wrapping_add(1);
+    }
+}
+
+impl Default for AudioOutput {
+    fn default() -> Self {
+        Self::new(DEFAULT_OUTPUT_RATE, AudioQuality::default())
+    }
+}
+
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-9 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+fn clamp_i16(v: f32) -> i16 {
+    v.round().clamp(i16::MIN as f32, i16::MAX as f32) as i16
+}
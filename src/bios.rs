@@ -0,0 +1,92 @@
+//! Replacement BIOS
+//!
+//! A small open BIOS image generated at build time, for running games that
+//! depend on the real BIOS's exception layout without a dumped BIOS. SWIs
+//! are still emulated in Rust; the image supplies what games can observe
+//! directly:
+//! - The exception vectors at 0x00-0x1C, with IRQ branching to 0x128
+//! - The IRQ dispatcher at 0x128 that calls the handler pointer at
+//!   0x03007FFC through `LDR PC, [R0, #-4]` and returns through 0x138
+//! - A default user handler that acknowledges whatever is pending, so an
+//!   interrupt before the game installs its own does not jump to 0
+//! - `BX LR` everywhere else, for games that call BIOS routines directly
+
+/// Reset: jump to the cartridge entry point
+const RESET: u32 = 0x0E0;
+/// Undefined instruction, SWI and reserved vectors: `MOVS PC, LR`
+const RETURN: u32 = 0x0E8;
+/// Prefetch abort and FIQ: `SUBS PC, LR, #4`
+const RETURN_4: u32 = 0x0EC;
+/// Data abort: `SUBS PC, LR, #8`
+const RETURN_8: u32 = 0x0F0;
+/// Real BIOS address of the IRQ dispatcher
+const IRQ_DISPATCH: u32 = 0x128;
+
+/// Handler installed at 0x03007FFC on boot: acknowledges IE & IF
+pub(crate) const DEFAULT_IRQ_HANDLER: u32 = 0x0F4;
+
+const BX_LR: u32 = 0xE12F_FF1E;
+
+/// ARM `B target` placed at `at`
+const fn branch(at: u32, target: u32) -> u32 {
+    0xEA00_0000 | ((target.wrapping_sub(at.wrapping_add(8)) >> 2) & 0x00FF_FFFF)
+}
+
+/// The 16KB replacement BIOS image
+pub(crate) fn replacement_bios() -> Vec<u8> {
+    let mut words = vec![BX_LR; 0x1000];
+    let mut put = |addr: u32, code: &[u32]| {
+        let start = (addr / 4) as usize;
+        for (word, &op) in words.iter_mut().skip(start).zip(code) {
+            *word = op;
+        }
+    };
+
+    put(
+        0x00,
+        &[
+            branch(0x00, RESET),
+            branch(0x04, RETURN),
+            branch(0x08, RETURN),
+            branch(0x0C, RETURN_4),
+            branch(0x10, RETURN_8),
+            branch(0x14, RETURN),
+            branch(0x18, IRQ_DISPATCH),
+            branch(0x1C, RETURN_4),
+        ],
+    );
+    put(
+        RESET,
+        &[
+            0xE51F_F004, // LDR PC, [PC, #-4]
+            0x0800_0000, // .word ROM entry
+        ],
+    );
+    put(RETURN, &[0xE1B0_F00E]); // MOVS PC, LR
+    put(RETURN_4, &[0xE25E_F004]); // SUBS PC, LR, #4
+    put(RETURN_8, &[0xE25E_F008]); // SUBS PC, LR, #8
+    put(
+        DEFAULT_IRQ_HANDLER,
+        &[
+            0xE3A0_0301, // MOV R0, #0x04000000
+            0xE280_0C02, // ADD R0, R0, #0x200
+            0xE590_1000, // LDR R1, [R0]          ; IE | IF << 16
+            0xE001_1821, // AND R1, R1, R1, LSR #16
+            0xE1C0_10B2, // STRH R1, [R0, #2]     ; acknowledge in IF
+            BX_LR,
+        ],
+    );
+    put(
+        IRQ_DISPATCH,
+        &[
+            0xE92D_500F, // STMFD SP!, {R0-R3, R12, LR}
+            0xE3A0_0301, // MOV R0, #0x04000000
+            0xE28F_E000, // ADD LR, PC, #0        ; return to 0x138
+            0xE510_F004, // LDR PC, [R0, #-4]     ; [0x03FFFFFC] mirrors 0x03007FFC
+            0xE8BD_500F, // LDMFD SP!, {R0-R3, R12, LR}
+            0xE25E_F004, // SUBS PC, LR, #4
+        ],
+    );
+
+    words.iter().flat_map(|w| w.to_le_bytes()).collect()
+}
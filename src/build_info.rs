@@ -0,0 +1,74 @@
+//! Build Provenance
+//!
+//! Which version of the core is running and which optional parts were
+//! compiled in. Bug reports can paste [`build_info`]'s `Display` form, and
+//! save states record [`VERSION`] so a capture can be traced back to the
+//! build that wrote it.
+
+use std::fmt;
+
+use crate::savestate::STATE_VERSION;
+use crate::{AccuracyProfile, AccuracySettings};
+
+/// Crate version of the emulator core
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Optional Cargo features this build was compiled with
+const FEATURES: &[(&str, bool)] = &[
+    ("import", cfg!(feature = "import")),
+    ("replacement-bios", cfg!(feature = "replacement-bios")),
+    ("stats", cfg!(feature = "stats")),
+    ("frame-log", cfg!(feature = "frame-log")),
+    ("config", cfg!(feature = "config")),
+    ("no-panic", cfg!(feature = "no-panic")),
+];
+
+/// Version, compiled-in features and defaults of the running core
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildInfo {
+    pub version: &'static str,
+    /// Enabled optional features, in `Cargo.toml` order
+    pub features: Vec<&'static str>,
+    /// Save state layout written and accepted by this build
+    pub state_version: u32,
+    /// Accuracy profile a new [`crate::Gba`] starts with
+    pub accuracy_profile: AccuracyProfile,
+    pub accuracy: AccuracySettings,
+}
+
+impl BuildInfo {
+    pub fn has_feature(&self, name: &str) -> bool {
+        self.features.contains(&name)
+    }
+}
+
+impl fmt::Display for BuildInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let features = if self.features.is_empty() {
+            "none".to_string()
+        } else {
+            self.features.join(", ")
+        };
+        write!(
+            f,
+            "rgba {} (features: {}; save state v{}; accuracy: {:?})",
+            self.version, features, self.state_version, self.accuracy_profile
+        )
+    }
+}
+
+/// Describe the running build
+pub fn build_info() -> BuildInfo {
+    let accuracy_profile = AccuracyProfile::default();
+    BuildInfo {
+        version: VERSION,
+        features: FEATURES
+            .iter()
+            .filter(|(_, enabled)| *enabled)
+            .map(|(name, _)| *name)
+            .collect(),
+        state_version: STATE_VERSION,
+        accuracy_profile,
+        accuracy: accuracy_profile.settings(),
+    }
+}
@@ -0,0 +1,91 @@
+//! Gba Builder
+//!
+//! [`GbaBuilder`] gathers the settings a frontend chooses before a game
+//! starts and applies them to a fresh [`Gba`] in one place. Settings from a
+//! command line, a menu or a [`crate::Config`] file all go through it.
+
+use std::path::PathBuf;
+
+use crate::audio::DEFAULT_OUTPUT_RATE;
+use crate::{AccuracyProfile, ColorCorrection, Gba, SwiHandling};
+
+/// Emulator settings applied when the [`Gba`] is created
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GbaBuilder {
+    accuracy: AccuracyProfile,
+    color_correction: ColorCorrection,
+    audio_rate: u32,
+    bios: Option<PathBuf>,
+    swi_handling: SwiHandling,
+    state_dir: Option<PathBuf>,
+}
+
+impl GbaBuilder {
+    /// The settings [`Gba::new`] starts with
+    pub fn new() -> Self {
+        Self {
+            accuracy: AccuracyProfile::default(),
+            color_correction: ColorCorrection::default(),
+            audio_rate: DEFAULT_OUTPUT_RATE,
+            bios: None,
+            swi_handling: SwiHandling::Hle,
+            state_dir: None,
+        }
+    }
+
+    pub fn accuracy(mut self, profile: AccuracyProfile) -> Self {
+        self.accuracy = profile;
+        self
+    }
+
+    pub fn color_correction(mut self, mode: ColorCorrection) -> Self {
+        self.color_correction = mode;
+        self
+    }
+
+    /// Output rate of [`Gba::drain_audio`], in Hz
+    pub fn audio_rate(mut self, rate: u32) -> Self {
+        self.audio_rate = rate;
+        self
+    }
+
+    /// BIOS image to boot instead of the built-in one
+    pub fn bios(mut self, path: impl Into<PathBuf>) -> Self {
+        self.bios = Some(path.into());
+        self
+    }
+
+    /// Whether SWIs run through the BIOS image; see [`Gba::set_swi_handling`]
+    pub fn swi_handling(mut self, mode: SwiHandling) -> Self {
+        self.swi_handling = mode;
+        self
+    }
+
+    /// Where save state slots are kept; see [`Gba::set_state_dir`]
+    pub fn state_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.state_dir = Some(dir.into());
+        self
+    }
+
+    /// Create the [`Gba`]; fails if the BIOS image cannot be loaded
+    pub fn build(self) -> Result<Gba, Box<dyn std::error::Error>> {
+        let mut gba = Gba::new();
+        gba.set_accuracy(self.accuracy);
+        gba.set_color_correction(self.color_correction);
+        gba.set_audio_sample_rate(self.audio_rate);
+        gba.set_swi_handling(self.swi_handling);
+        if let Some(dir) = self.state_dir {
+            gba.set_state_dir(dir);
+        }
+        if let Some(path) = &self.bios {
+            gba.load_bios_path(&path.to_string_lossy())?;
+        }
+        Ok(gba)
+    }
+}
+
+impl Default for GbaBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
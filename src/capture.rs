@@ -0,0 +1,173 @@
+//! Video Capture
+//!
+//! Streams every frame the core finishes to a byte sink in a form ffmpeg
+//! reads straight from a pipe, for gameplay recordings or regression videos.
+//! A frame runs from one VBlank to the next and is taken from the scanline
+//! capture buffer, so mid-frame raster effects are recorded as displayed.
+//! The first VBlank after capture starts only opens the first frame.
+//!
+//! Audio goes to a second sink as interleaved stereo signed 16-bit
+//! little-endian samples at [`crate::Gba::audio_sample_rate`], written
+//! frame by frame alongside the video so the two stay in step:
+//!
+//! ```text
+//! ffmpeg -f rawvideo -pixel_format rgb24 -video_size 240x160 \
+//!        -framerate 262144/4389 -i video.rgb \
+//!        -f s16le -ar 48000 -ac 2 -i audio.pcm out.mp4
+//! ```
+
+use std::io::{self, Write};
+
+use crate::audio::AudioOutput;
+
+/// Frames per second as a fraction, 16777216 Hz / 280896 cycles per frame
+pub const CAPTURE_FRAME_RATE: (u32, u32) = (262144, 4389);
+
+/// Encoding of the captured video stream
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VideoFormat {
+    /// Headerless 240x160 RGB24 frames; ffmpeg needs the size and rate,
+    /// as in the example above
+    #[default]
+    RawRgb,
+    /// YUV4MPEG2 with 4:4:4 BT.601 frames; ffmpeg reads the size and rate
+    /// from the stream header
+    Y4m,
+}
+
+/// An active capture started by [`crate::Gba::start_video_capture`]
+pub struct VideoCapture {
+    format: VideoFormat,
+    video: Box<dyn Write + Send>,
+    audio: Option<Box<dyn Write + Send>>,
+    /// A VBlank has been seen, so the frame being drawn is complete
+    armed: bool,
+    frames: u64,
+    /// First write failure; nothing more is written after it
+    error: Option<io::Error>,
+    /// Scanline capture was turned on for this capture and goes off with it
+    pub(crate) owns_line_capture: bool,
+    bytes: Vec<u8>,
+    samples: Vec<i16>,
+}
+
+impl VideoCapture {
+    pub(crate) fn new(
+        video: Box<dyn Write + Send>,
+        format: VideoFormat,
+        audio: Option<Box<dyn Write + Send>>,
+    ) -> Self {
+        let mut capture = Self {
+            format,
+            video,
+            audio,
+            armed: false,
+            frames: 0,
+            error: None,
+            owns_line_capture: false,
+            bytes: Vec::new(),
+            samples: Vec::new(),
+        };
+        if format == VideoFormat::Y4m {
+            let (num, den) = CAPTURE_FRAME_RATE;
+            let header = format!("YUV4MPEG2 W240 H160 F{}:{} Ip A1:1 C444\n", num, den);
+            capture.write_video(header.into_bytes());
+        }
+        capture
+    }
+
+    pub fn format(&self) -> VideoFormat {
+        self.format
+    }
+
+    /// Frames written so far
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Whether audio is being captured
+    pub fn has_audio(&self) -> bool {
+        self.audio.is_some()
+    }
+
+    /// Write the 240x160 0x00RRGGBB `frame` that just finished and the
+    /// audio `output` produced while it was drawn
+    pub(crate) fn vblank(&mut self, frame: &[u32], output: &mut AudioOutput) {
+        self.samples.clear();
+        output.take_tap(&mut self.samples);
+        if !self.armed {
+            self.armed = true;
+            return;
+        }
+        if self.error.is_some() {
+            return;
+        }
+
+        let mut bytes = std::mem::take(&mut self.bytes);
+        bytes.clear();
+        match self.format {
+            VideoFormat::RawRgb => {
+                for &px in frame {
+                    bytes.extend_from_slice(&[(px >> 16) as u8, (px >> 8) as u8, px as u8]);
+                }
+            }
+            VideoFormat::Y4m => {
+                bytes.extend_from_slice(b"FRAME\n");
+                bytes.extend(frame.iter().map(|&px| ycbcr(px)[0]));
+                bytes.extend(frame.iter().map(|&px| ycbcr(px)[1]));
+                bytes.extend(frame.iter().map(|&px| ycbcr(px)[2]));
+            }
+        }
+        self.write_video(bytes);
+
+        if let Some(audio) = &mut self.audio {
+            let pcm: Vec<u8> = self.samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+            if let Err(e) = audio.write_all(&pcm) {
+                self.error.get_or_insert(e);
+            }
+        }
+        if self.error.is_none() {
+            self.frames = self.frames.wrapping_add(1);
+        }
+    }
+
+    fn write_video(&mut self, bytes: Vec<u8>) {
+        if let Err(e) = self.video.write_all(&bytes) {
+            self.error.get_or_insert(e);
+        }
+        self.bytes = bytes;
+    }
+
+    /// Flush both sinks; the frame count, or the first write error
+    pub(crate) fn finish(mut self) -> io::Result<u64> {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        self.video.flush()?;
+        if let Some(audio) = &mut self.audio {
+            audio.flush()?;
+        }
+        Ok(self.frames)
+    }
+}
+
+/// Studio-range BT.601 Y, Cb, Cr of a 0x00RRGGBB pixel
+fn ycbcr(px: u32) -> [u8; 3] {
+    let (r, g, b) = (
+        ((px >> 16) & 0xFF) as i32,
+        ((px >> 8) & 0xFF) as i32,
+        (px & 0xFF) as i32,
+    );
+    let row = |kr: i32, kg: i32, kb: i32, offset: i32| {
+        let sum = kr
+            .wrapping_mul(r)
+            .wrapping_add(kg.wrapping_mul(g))
+            .wrapping_add(kb.wrapping_mul(b));
+        (sum.wrapping_add(128) >> 8).wrapping_add(offset) as u8
+    };
+    [
+        row(66, 129, 25, 16),
+        row(-38, -74, 112, 128),
+        row(112, -94, -18, 128),
+    ]
+}
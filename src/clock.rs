@@ -0,0 +1,99 @@
+//! Host Clock
+//!
+//! Peripherals that report wall-clock time (cartridge RTC, the solar
+//! sensor's day cycle) never call `std::time` themselves. The emulator asks
+//! a [`HostClock`] for the date once, when the machine is created or the
+//! clock is swapped, and from then on the in-game clock advances with
+//! emulated frames. That keeps runs deterministic, and because the epoch and
+//! elapsed cycles are part of the save state, loading a state resumes the
+//! in-game clock where it was saved instead of jumping to the host's time.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::savestate::{StateError, StateReader, StateWriter};
+use crate::throttle::CPU_FREQUENCY;
+
+/// Source of the wall-clock time seeded into the emulated RTC
+pub trait HostClock: Send {
+    /// Seconds since the Unix epoch
+    fn now(&self) -> u64;
+}
+
+/// The host's system clock; the default for a new [`crate::Gba`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl HostClock for SystemClock {
+    fn now(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0)
+    }
+}
+
+/// Clock set by hand, for tests and replays. Clones share the same time, so
+/// a test can keep one handle after giving the other to the emulator.
+#[derive(Debug, Clone, Default)]
+pub struct ManualClock {
+    secs: Arc<AtomicU64>,
+}
+
+impl ManualClock {
+    pub fn new(secs: u64) -> Self {
+        Self {
+            secs: Arc::new(AtomicU64::new(secs)),
+        }
+    }
+
+    pub fn set(&self, secs: u64) {
+        self.secs.store(secs, Ordering::Relaxed);
+    }
+
+    pub fn advance(&self, secs: u64) {
+        self.secs.fetch_add(secs, Ordering::Relaxed);
+    }
+}
+
+impl HostClock for ManualClock {
+    fn now(&self) -> u64 {
+        self.secs.load(Ordering::Relaxed)
+    }
+}
+
+/// In-game wall clock: a host-seeded epoch plus emulated time since then
+pub(crate) struct GameClock {
+    epoch: u64,
+    cycles: u64,
+}
+
+impl GameClock {
+    pub(crate) fn new(host: &dyn HostClock) -> Self {
+        Self {
+            epoch: host.now(),
+            cycles: 0,
+        }
+    }
+
+    /// Seconds since the Unix epoch as seen by the game
+    pub(crate) fn now(&self) -> u64 {
+        self.epoch.wrapping_add(self.cycles / CPU_FREQUENCY)
+    }
+
+    pub(crate) fn advance(&mut self, cycles: u64) {
+        self.cycles = self.cycles.wrapping_add(cycles);
+    }
+
+    pub(crate) fn save_state(&self, w: &mut StateWriter) {
+        w.write_u64(self.epoch);
+        w.write_u64(self.cycles);
+    }
+
+    pub(crate) fn load_state(&mut self, r: &mut StateReader) -> Result<(), StateError> {
+        self.epoch = r.read_u64()?;
+        self.cycles = r.read_u64()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,118 @@
+//! Color Correction
+//!
+//! The original GBA LCD has no backlight and a narrow, washed-out gamut, so
+//! raw RGB555 values look oversaturated on a modern monitor. These modes
+//! approximate how each handheld model actually displayed the same colors.
+
+/// Display model whose color response is emulated when converting RGB555
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "config",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum ColorCorrection {
+    /// Plain linear 5-bit to 8-bit expansion
+    #[default]
+    None,
+    /// Original unlit GBA / GBA SP AGS-001 panel
+    GbaLcd,
+    /// Backlit GBA SP AGS-101 panel
+    Ags101,
+    /// Nintendo DS running in GBA mode
+    Nds,
+}
+
+impl ColorCorrection {
+    /// Panel gamma, output luminance and the 3x3 mix (rows are output R, G, B)
+    fn profile(self) -> (f32, f32, [[f32; 3]; 3]) {
+        match self {
+            ColorCorrection::None => (1.0, 1.0, [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]]),
+            ColorCorrection::GbaLcd => (
+                4.0,
+                255.0 / 280.0,
+                [
+                    [1.0, 50.0 / 255.0, 0.0],
+                    [10.0 / 255.0, 230.0 / 255.0, 30.0 / 255.0],
+                    [50.0 / 255.0, 10.0 / 255.0, 220.0 / 255.0],
+                ],
+            ),
+            ColorCorrection::Ags101 => (
+                2.2,
+                1.0,
+                [[0.86, 0.10, 0.04], [0.03, 0.90, 0.07], [0.04, 0.08, 0.88]],
+            ),
+            ColorCorrection::Nds => (
+                2.2,
+                0.95,
+                [[0.80, 0.15, 0.05], [0.11, 0.83, 0.06], [0.06, 0.15, 0.79]],
+            ),
+        }
+    }
+
+    /// Panel response of this mode, for converters outside the core such
+    /// as GPU shaders. `None` is a plain expansion and skips the curve
+    pub fn curve(self) -> ColorCurve {
+        let (gamma, luminance, mix) = self.profile();
+        ColorCurve {
+            levels: std::array::from_fn(|level| (level as f32 / 31.0).powf(gamma)),
+            mix,
+            luminance,
+        }
+    }
+
+    /// Convert one RGB555 color to 0x00RRGGBB
+    pub fn convert(self, color: u16) -> u32 {
+        if self == ColorCorrection::None {
+            let r = (color & 0x1F) as u32;
+            let g = ((color >> 5) & 0x1F) as u32;
+            let b = ((color >> 10) & 0x1F) as u32;
+            return (expand5(r) << 16) | (expand5(g) << 8) | expand5(b);
+        }
+        self.curve().apply(color)
+    }
+
+    /// Precompute every RGB555 color (indexed by the low 15 bits)
+    pub fn build_lut(self) -> Vec<u32> {
+        if self == ColorCorrection::None {
+            return (0..0x8000u16).map(|c| self.convert(c)).collect();
+        }
+        let curve = self.curve();
+        (0..0x8000u16).map(|c| curve.apply(c)).collect()
+    }
+}
+
+/// Linear light of each 5-bit channel level, mixed into the output channels
+/// and scaled by the panel luminance, then encoded with a 2.2 gamma
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorCurve {
+    /// Linear light of channel levels 0-31
+    pub levels: [f32; 32],
+    /// Rows are output R, G, B; columns are input R, G, B
+    pub mix: [[f32; 3]; 3],
+    pub luminance: f32,
+}
+
+impl ColorCurve {
+    /// Convert one RGB555 color to 0x00RRGGBB
+    pub fn apply(&self, color: u16) -> u32 {
+        let level = |shift: u16| {
+            let index = ((color >> shift) & 0x1F) as usize;
+            self.levels.get(index).copied().unwrap_or(0.0)
+        };
+        let lin = [level(0), level(5), level(10)];
+
+        let mut out = 0u32;
+        for (row, shift) in self.mix.iter().zip([16, 8, 0]) {
+            let v = (row[0] * lin[0] + row[1] * lin[1] + row[2] * lin[2]) * self.luminance;
+            let c = (v.clamp(0.0, 1.0).powf(1.0 / 2.2) * 255.0 + 0.5) as u32;
+            out |= c.min(255) << shift;
+        }
+        out
+    }
+}
+
+/// Scale a 5-bit channel to 0-255
+fn expand5(c: u32) -> u32 {
+    c.wrapping_mul(255) / 31
+}
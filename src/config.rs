@@ -0,0 +1,161 @@
+//! Settings File (`config` feature)
+//!
+//! [`Config`] holds the emulator-level settings a frontend would otherwise
+//! persist by hand, reads and writes them as TOML, and turns them into a
+//! [`GbaBuilder`]:
+//!
+//! ```toml
+//! accuracy = "balanced"
+//! color_correction = "gba-lcd"
+//! audio_rate = 48000
+//! bios = "gba_bios.bin"
+//! swi_handling = "lle"
+//! state_dir = "states"
+//!
+//! [keys]
+//! A = "Z"
+//! B = "X"
+//! ```
+//!
+//! Fields left out keep their defaults. Key bindings map GBA button names
+//! ([`KeyState`] flag names) to host key names, which mean whatever the
+//! frontend's input library calls its keys.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::audio::DEFAULT_OUTPUT_RATE;
+use crate::{AccuracyProfile, ColorCorrection, GbaBuilder, KeyState, SwiHandling};
+
+/// Host keys bound by default, the layout of the bundled GUI
+const DEFAULT_KEYS: [(&str, &str); 10] = [
+    ("UP", "Up"),
+    ("DOWN", "Down"),
+    ("LEFT", "Left"),
+    ("RIGHT", "Right"),
+    ("A", "Z"),
+    ("B", "X"),
+    ("START", "Enter"),
+    ("SELECT", "Backspace"),
+    ("L", "A"),
+    ("R", "S"),
+];
+
+/// Reasons a settings file could not be read or written
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    /// The file is not valid TOML or a field has the wrong type
+    Parse(String),
+    /// A `[keys]` entry names something other than a GBA button
+    UnknownButton(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "settings file: {}", e),
+            ConfigError::Parse(msg) => write!(f, "invalid settings: {}", msg),
+            ConfigError::UnknownButton(name) => write!(f, "unknown GBA button '{}'", name),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<std::io::Error> for ConfigError {
+    fn from(e: std::io::Error) -> Self {
+        ConfigError::Io(e)
+    }
+}
+
+/// Emulator settings as stored in a TOML file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub accuracy: AccuracyProfile,
+    pub color_correction: ColorCorrection,
+    /// Audio output rate in Hz
+    pub audio_rate: u32,
+    /// BIOS image to boot; the built-in BIOS when absent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bios: Option<PathBuf>,
+    /// Whether SWIs run through `bios` or are emulated
+    pub swi_handling: SwiHandling,
+    /// Root of the save state slot directories; `states` when absent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state_dir: Option<PathBuf>,
+    /// Host key name for each GBA button, by button name
+    pub keys: BTreeMap<String, String>,
+}
+
+impl Config {
+    pub fn from_toml(text: &str) -> Result<Self, ConfigError> {
+        let config: Config = toml::from_str(text).map_err(|e| ConfigError::Parse(e.to_string()))?;
+        if let Some(name) = config
+            .keys
+            .keys()
+            .find(|name| KeyState::from_name(name).is_none())
+        {
+            return Err(ConfigError::UnknownButton(name.clone()));
+        }
+        Ok(config)
+    }
+
+    pub fn to_toml(&self) -> Result<String, ConfigError> {
+        toml::to_string(self).map_err(|e| ConfigError::Parse(e.to_string()))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        Self::from_toml(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ConfigError> {
+        std::fs::write(path, self.to_toml()?)?;
+        Ok(())
+    }
+
+    /// A builder for a [`crate::Gba`] with these settings
+    pub fn builder(&self) -> GbaBuilder {
+        let mut builder = GbaBuilder::new()
+            .accuracy(self.accuracy)
+            .color_correction(self.color_correction)
+            .audio_rate(self.audio_rate)
+            .swi_handling(self.swi_handling);
+        if let Some(path) = &self.bios {
+            builder = builder.bios(path);
+        }
+        if let Some(dir) = &self.state_dir {
+            builder = builder.state_dir(dir);
+        }
+        builder
+    }
+
+    /// Each bound button with its host key name
+    pub fn bindings(&self) -> Vec<(KeyState, &str)> {
+        self.keys
+            .iter()
+            .filter_map(|(button, key)| Some((KeyState::from_name(button)?, key.as_str())))
+            .collect()
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            accuracy: AccuracyProfile::default(),
+            color_correction: ColorCorrection::default(),
+            audio_rate: DEFAULT_OUTPUT_RATE,
+            bios: None,
+            swi_handling: SwiHandling::default(),
+            state_dir: None,
+            keys: DEFAULT_KEYS
+                .iter()
+                .map(|&(button, key)| (button.to_string(), key.to_string()))
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,57 @@
+//! Basic Block Coverage
+//!
+//! Splits the executed instruction stream into basic blocks: runs of
+//! straight-line code that end wherever the next executed instruction is not
+//! the sequential one (a taken branch, a return, an exception). Each block
+//! is counted by its first and last instruction address, so a fuzzer can
+//! tell when an input reaches new code and a test run can measure how much
+//! of a ROM it exercised.
+
+use std::collections::HashMap;
+
+/// Straight-line code from `start` to the instruction at `end`, both inclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BasicBlock {
+    pub start: u32,
+    pub end: u32,
+    /// Times the block ran to its end
+    pub times: u64,
+}
+
+/// Open block: (start, last instruction, address that would continue it)
+type OpenBlock = (u32, u32, u32);
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct BlockTracer {
+    open: Option<OpenBlock>,
+    counts: HashMap<(u32, u32), u64>,
+}
+
+impl BlockTracer {
+    /// Note that the instruction at `pc` is about to run. A `pc` that does
+    /// not follow the previous instruction closes the block before it.
+    pub(crate) fn record(&mut self, pc: u32, thumb: bool) {
+        let next = pc.wrapping_add(if thumb { 2 } else { 4 });
+        self.open = match self.open {
+            Some((start, _, expected)) if expected == pc => Some((start, pc, next)),
+            Some((start, last, _)) => {
+                let count = self.counts.entry((start, last)).or_insert(0);
+                *count = count.saturating_add(1);
+                Some((pc, pc, next))
+            }
+            None => Some((pc, pc, next)),
+        };
+    }
+
+    /// Blocks closed since the last call, by address. The block still
+    /// running is reported once it ends.
+    pub(crate) fn take(&mut self) -> Vec<BasicBlock> {
+        let mut blocks: Vec<BasicBlock> = self
+            .counts
+            .drain()
+            .map(|((start, end), times)| BasicBlock { start, end, times })
+            .collect();
+        blocks.sort_by_key(|b| (b.start, b.end));
+        blocks
+    }
+}
@@ -5,6 +5,13 @@
 //! - Thumb mode (16-bit instructions)
 //! - Multiple processor modes (User, IRQ, FIQ, Supervisor, Abort, Undefined, System)
 
+mod bank;
+
+pub use bank::RegisterBank;
+
+use crate::savestate::{StateError, StateReader, StateWriter};
+use crate::view::ByteView;
+
 bitflags::bitflags! {
     /// CPU Status Register flags
     #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -46,29 +53,64 @@ impl Mode {
     }
 }
 
+/// Frames kept by the call-stack model before the oldest is dropped
+const MAX_CALL_DEPTH: usize = 1024;
+
+/// IWRAM offset of the BIOS IntrCheck word (0x03007FF8), where IRQ
+/// handlers acknowledge interrupts for IntrWait
+const INTR_CHECK: usize = 0x7FF8;
+
+fn intr_check(mem: &super::Memory) -> u16 {
+    mem.iwram().half_at(INTR_CHECK)
+}
+
+fn set_intr_check(mem: &mut super::Memory, flags: u16) {
+    if let Some(word) = mem.iwram_mut().get_mut(INTR_CHECK..INTR_CHECK + 2) {
+        word.copy_from_slice(&flags.to_le_bytes());
+    }
+}
+
+/// How a [`CallFrame`] was entered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallKind {
+    /// BL, or any branch that left LR pointing at the next instruction
+    Call,
+    /// IRQ exception entry
+    Irq,
+}
+
+/// One entry of the call-stack model, innermost last
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CallFrame {
+    pub kind: CallKind,
+    /// Address of the calling instruction, or the interrupted one
+    pub call_site: u32,
+    pub target: u32,
+    /// PC that ends this frame when branched to
+    pub return_addr: u32,
+    /// SP at entry
+    pub sp: u32,
+}
+
+/// An opcode that took the undefined instruction exception
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UndefinedInstruction {
+    pub address: u32,
+    /// The 32-bit ARM or 16-bit Thumb opcode
+    pub opcode: u32,
+    pub thumb: bool,
+}
+
 /// ARM7TDMI CPU
 pub struct Cpu {
-    // General purpose registers
+    // General purpose registers of the current mode
     // R0-R7 are unprivileged
     // R8-R12 have banked versions for FIQ
-    // R13 (SP), R14 (LR), R15 (PC/SPSR) are banked for most modes
+    // R13 (SP), R14 (LR) are banked for most modes
     r: [u32; 16],
 
-    // Banked registers for different modes
-    // We'll store these as separate arrays and swap based on mode
-    banked_r0_irq: u32,
-    banked_r1_irq: u32,
-    banked_r2_irq: u32,
-    banked_r3_irq: u32,
-    banked_r8_fiq: u32,
-    banked_r9_fiq: u32,
-    banked_r10_fiq: u32,
-    banked_r11_fiq: u32,
-    banked_r12_fiq: u32,
-
-    banked_sp: [u32; 6], // For FIQ, IRQ, Supervisor, Abort, Undefined, System
-    banked_lr: [u32; 6],
-    banked_spsr: [u32; 6], // Saved PSR for privileged modes
+    // Other modes' R8-R14, and every mode's SPSR
+    bank: RegisterBank,
 
     // Current program status register
     cpsr: u32,
@@ -79,6 +121,10 @@ pub struct Cpu {
     pipeline_loaded: bool,
     pc_written: bool,
     halted: bool,
+    /// Halted in STOP mode, with the system clocks off as well
+    stopped: bool,
+    /// IRQ flags an HLE IntrWait is waiting for in the BIOS IntrCheck word
+    intr_wait: Option<u16>,
 
     // Instruction cache (PC -> opcode) for hot loops
     // Simple direct-mapped cache with 1024 entries
@@ -95,30 +141,29 @@ pub struct Cpu {
     pub irq_save_count: u32,
     pub irq_save_stack: Vec<[u32; 4]>,
     current_arm_pc: u32,
+    /// Call-stack model; None while call tracing is off
+    call_stack: Option<Vec<CallFrame>>,
+    /// A debugger handles BKPT; otherwise it takes the prefetch abort
+    bkpt_trap: bool,
+    /// Address of a BKPT that trapped to the debugger, until taken
+    bkpt_hit: Option<u32>,
+    /// Told about every opcode that takes the undefined instruction exception
+    undefined_hook: Option<Box<dyn FnMut(UndefinedInstruction) + Send>>,
 }
 
 impl Cpu {
     pub fn new() -> Self {
         Self {
             r: [0; 16],
-            banked_r0_irq: 0,
-            banked_r1_irq: 0,
-            banked_r2_irq: 0,
-            banked_r3_irq: 0,
-            banked_r8_fiq: 0,
-            banked_r9_fiq: 0,
-            banked_r10_fiq: 0,
-            banked_r11_fiq: 0,
-            banked_r12_fiq: 0,
-            banked_sp: [0; 6],
-            banked_lr: [0; 6],
-            banked_spsr: [0; 6],
+            bank: RegisterBank::new(),
             cpsr: 0x0000001F, // System mode
             pipeline: [0; 3],
             pipeline_pc: [0; 3],
             pipeline_loaded: false,
             pc_written: false,
             halted: false,
+            stopped: false,
+            intr_wait: None,
             arm_cache: [(0, 0); 1024],
             thumb_cache: [(0, 0); 1024],
 
@@ -132,39 +177,42 @@ impl Cpu {
 
             decomp_trace: Vec::new(),
             decomp_trace_enabled: false,
+            call_stack: None,
+            bkpt_trap: false,
+            bkpt_hit: None,
+            undefined_hook: None,
         }
     }
 
     pub fn reset(&mut self) {
-        // On reset, CPU starts in SVC mode
+        // Start where the BIOS hands over to the cartridge: System mode, IRQs on
         self.r = [0; 16];
-        self.banked_r8_fiq = 0;
-        self.banked_r9_fiq = 0;
-        self.banked_r10_fiq = 0;
-        self.banked_r11_fiq = 0;
-        self.banked_r12_fiq = 0;
-        self.banked_sp = [
-            0x0300_7FA0, // FIQ
-            0x0300_7FA0, // IRQ
-            0x0300_7FE0, // SVC
-            0x0300_7FA0, // Abort
-            0x0300_7FA0, // Undefined
-            0x0300_7F00, // System/User
-        ];
-        self.banked_lr = [0; 6];
-        self.banked_spsr = [0; 6];
-        self.cpsr = 0x000000D3; // SVC mode, IRQ/FIQ disabled, ARM mode
+        self.bank = RegisterBank::new();
+        for (mode, sp) in [
+            (Mode::Fiq, 0x0300_7FA0),
+            (Mode::Irq, 0x0300_7FA0),
+            (Mode::Supervisor, 0x0300_7FE0),
+            (Mode::Abort, 0x0300_7FA0),
+            (Mode::Undefined, 0x0300_7FA0),
+            (Mode::System, 0x0300_7F00),
+        ] {
+            self.bank.write(mode, 13, sp);
+        }
+        self.cpsr = 0x0000001F; // System mode, IRQ/FIQ enabled, ARM mode
         self.r[13] = 0x0300_7F00; // SP (stack pointer) - points to IWRAM
         self.r[14] = 0x0800_0004; // LR (link register)
         self.r[15] = 0x0800_0000; // PC (program counter) - ROM entry point
-        self.banked_sp[1] = 0x0300_7FA0; // SP_irq
         self.pipeline = [0; 3];
         self.pipeline_pc = [0; 3];
         self.pipeline_loaded = false;
         self.pc_written = false;
         self.halted = false;
+        self.stopped = false;
+        self.intr_wait = None;
+        self.bkpt_hit = None;
     }
 
+    #[doc(hidden)]
     pub fn set_pc_bios(&mut self) {
         self.r[15] = 0x0000_0000;
         self.pipeline_loaded = false;
@@ -175,13 +223,20 @@ impl Cpu {
         self.halted
     }
 
+    /// In STOP mode: halted, and the PPU, APU and timers are not clocked
+    pub fn is_stopped(&self) -> bool {
+        self.stopped
+    }
+
     #[cfg(debug_assertions)]
+    #[doc(hidden)]
     pub fn enable_trace(&mut self) {
         self.trace_enabled = true;
         self.trace_buf.clear();
     }
 
     #[cfg(debug_assertions)]
+    #[doc(hidden)]
     pub fn get_trace(&self) -> &std::collections::VecDeque<(u32, u32, [u32; 16], u32)> {
         &self.trace_buf
     }
@@ -205,15 +260,66 @@ impl Cpu {
         self.trace_buf.push_back((pc, opcode, self.r, self.cpsr));
     }
 
+    #[doc(hidden)]
     pub fn trace_enabled_flag(&self) -> bool {
         self.trace_enabled
     }
 
+    #[doc(hidden)]
     pub fn set_trace_enabled(&mut self, enabled: bool) {
         self.trace_enabled = enabled;
     }
 
+    /// Track calls and returns in a call-stack model (see [`Cpu::call_stack`])
+    pub fn set_call_tracing(&mut self, enabled: bool) {
+        if enabled != self.call_stack.is_some() {
+            self.call_stack = enabled.then(Vec::new);
+        }
+    }
+
+    /// Active calls and interrupt handlers, outermost first. Empty while
+    /// call tracing is off.
+    pub fn call_stack(&self) -> &[CallFrame] {
+        self.call_stack.as_deref().unwrap_or(&[])
+    }
+
+    fn push_call_frame(&mut self, frame: CallFrame) {
+        if let Some(stack) = &mut self.call_stack {
+            if stack.len() >= MAX_CALL_DEPTH {
+                stack.remove(0);
+            }
+            stack.push(frame);
+        }
+    }
+
+    /// Update the call-stack model after the instruction at `pc` ran. A jump
+    /// to a frame's return address unwinds to it; any other jump that left
+    /// LR pointing at the following instruction is a call.
+    fn track_call(&mut self, pc: u32, thumb: bool) {
+        let size = if thumb { 2 } else { 4 };
+        let next = pc.wrapping_add(size);
+        let target = self.next_instruction_pc();
+        if target == next {
+            return;
+        }
+        let Some(stack) = &mut self.call_stack else {
+            return;
+        };
+        if let Some(i) = stack.iter().rposition(|f| f.return_addr == target) {
+            stack.truncate(i);
+        } else if self.r[14] & !1 == next {
+            self.push_call_frame(CallFrame {
+                kind: CallKind::Call,
+                call_site: pc,
+                target,
+                return_addr: next,
+                sp: self.r[13],
+            });
+        }
+    }
+
     #[cfg(not(debug_assertions))]
+    #[doc(hidden)]
     pub fn get_trace(&self) -> &std::collections::VecDeque<(u32, u32, [u32; 16], u32)> {
         &self.trace_buf
     }
@@ -222,8 +328,120 @@ impl Cpu {
         self.halted = true;
     }
 
+    pub fn set_stopped(&mut self) {
+        self.halted = true;
+        self.stopped = true;
+    }
+
+    /// Wake from HALT or STOP
     pub fn clear_halted(&mut self) {
         self.halted = false;
+        self.stopped = false;
+    }
+
+    /// IRQ flags an emulated IntrWait or VBlankIntrWait is waiting for
+    pub fn intr_wait_mask(&self) -> Option<u16> {
+        self.intr_wait
+    }
+
+    /// IntrWait (SWI 0x04) for the IRQ flags in `mask`, as the BIOS does
+    /// it: IME on, then halt until an IRQ handler sets one of them in the
+    /// IntrCheck word at 0x03007FF8. With `discard`, flags already set
+    /// there are cleared first so only new interrupts count.
+    fn begin_intr_wait(&mut self, discard: bool, mask: u16, mem: &mut super::Memory) {
+        mem.interrupt.ime = true;
+        if discard {
+            let flags = intr_check(mem) & !mask;
+            set_intr_check(mem, flags);
+        }
+        self.intr_wait = Some(mask);
+        self.poll_intr_wait(mem);
+    }
+
+    /// Resume an IntrWait once the CPU is back in the waiting code: it
+    /// ends, clearing the flags it saw, if one it waits for is set in
+    /// IntrCheck, and halts again otherwise
+    pub(crate) fn poll_intr_wait(&mut self, mem: &mut super::Memory) {
+        let Some(mask) = self.intr_wait else {
+            return;
+        };
+        let flags = intr_check(mem);
+        if flags & mask != 0 {
+            set_intr_check(mem, flags & !mask);
+            self.intr_wait = None;
+        } else {
+            self.halted = true;
+        }
+    }
+
+    /// Take the undefined instruction exception for the ARM or Thumb `opcode`
+    /// at `instruction_pc`: Undefined mode, LR pointing past the opcode, IRQs
+    /// off, ARM state, PC at vector 0x04
+    fn enter_undefined(&mut self, instruction_pc: u32, opcode: u32) -> u32 {
+        let thumb = self.is_thumb_mode();
+        if let Some(hook) = &mut self.undefined_hook {
+            hook(UndefinedInstruction {
+                address: instruction_pc,
+                opcode,
+                thumb,
+            });
+        }
+        let size = if thumb { 2 } else { 4 };
+        self.enter_exception(
+            Mode::Undefined,
+            0x0000_0004,
+            instruction_pc.wrapping_add(size),
+        )
+    }
+
+    /// Call `hook` with each opcode that takes the undefined instruction
+    /// exception, before the CPU enters the vector
+    pub fn set_undefined_hook(&mut self, hook: impl FnMut(UndefinedInstruction) + Send + 'static) {
+        self.undefined_hook = Some(Box::new(hook));
+    }
+
+    pub fn clear_undefined_hook(&mut self) {
+        self.undefined_hook = None;
+    }
+
+    /// Switch to `mode` with the old CPSR in its SPSR, `lr` in its LR, IRQs
+    /// off and ARM state, and continue at `vector`
+    fn enter_exception(&mut self, mode: Mode, vector: u32, lr: u32) -> u32 {
+        let old_cpsr = self.cpsr;
+        self.set_mode(mode);
+        self.bank.set_spsr(mode, old_cpsr);
+        self.r[14] = lr;
+        self.cpsr |= 0x80;
+        self.set_thumb_mode(false);
+        self.set_pc(vector);
+        3
+    }
+
+    /// BKPT (ARM 0xE12xxx7x, Thumb 0xBExx). The ARM7TDMI has no debug
+    /// unit to catch it, so without a debugger it raises a prefetch abort
+    /// with LR = opcode + 4, whichever state it came from. With one it
+    /// records the hit and carries on with the next instruction.
+    fn execute_bkpt(&mut self, instruction_pc: u32) -> u32 {
+        if self.bkpt_trap {
+            self.bkpt_hit = Some(instruction_pc);
+            let size = if self.is_thumb_mode() { 2 } else { 4 };
+            self.r[15] = self.r[15].wrapping_add(size);
+            1
+        } else {
+            self.enter_exception(Mode::Abort, 0x0000_000C, instruction_pc.wrapping_add(4))
+        }
+    }
+
+    /// Route BKPT to a debugger (see [`Cpu::take_bkpt_hit`]) instead of the
+    /// prefetch abort vector
+    pub fn set_bkpt_trap(&mut self, enabled: bool) {
+        self.bkpt_trap = enabled;
+        self.bkpt_hit = None;
+    }
+
+    /// Address of the BKPT that trapped since the last call, if any
+    pub fn take_bkpt_hit(&mut self) -> Option<u32> {
+        self.bkpt_hit.take()
     }
 
     pub fn take_interrupt(&mut self, mem: &mut super::Memory) -> bool {
@@ -235,11 +453,11 @@ impl Cpu {
             self.r[15]
         };
         let ret_addr = next_instruction.wrapping_add(4);
-        self.irq_save_count += 1;
+        self.irq_save_count = self.irq_save_count.wrapping_add(1);
 
         self.set_mode(Mode::Irq);
 
-        self.banked_spsr[self.mode_index(Mode::Irq)] = old_cpsr;
+        self.bank.set_spsr(Mode::Irq, old_cpsr);
 
         self.r[14] = ret_addr;
 
@@ -247,17 +465,26 @@ impl Cpu {
 
         self.set_thumb_mode(false);
         self.set_pc(0x0000_0018);
+        self.push_call_frame(CallFrame {
+            kind: CallKind::Irq,
+            call_site: next_instruction,
+            target: 0x0000_0018,
+            return_addr: next_instruction,
+            sp: self.r[13],
+        });
         self.pipeline_loaded = false;
         true
     }
 
     // Register access
     pub fn get_reg(&self, n: usize) -> u32 {
-        self.r[n]
+        self.r.get(n).copied().unwrap_or(0)
     }
 
     pub fn set_reg(&mut self, n: usize, val: u32) {
-        self.r[n] = val;
+        if let Some(r) = self.r.get_mut(n) {
+            *r = val;
+        }
     }
 
     pub fn get_sp(&self) -> u32 {
@@ -288,6 +515,15 @@ impl Cpu {
         self.pipeline_pc[0]
     }
 
+    /// Address of the next instruction `step` will execute
+    pub fn next_instruction_pc(&self) -> u32 {
+        if self.pipeline_loaded {
+            self.pipeline_pc[0]
+        } else {
+            self.r[15]
+        }
+    }
+
     pub fn get_cpsr(&self) -> u32 {
         self.cpsr
     }
@@ -305,14 +541,17 @@ impl Cpu {
         self.pc_written = true;
     }
 
+    #[doc(hidden)]
     pub fn get_pipeline_pc(&self, idx: usize) -> u32 {
-        self.pipeline_pc[idx]
+        self.pipeline_pc.get(idx).copied().unwrap_or(0)
     }
 
+    #[doc(hidden)]
     pub fn get_pipeline(&self, idx: usize) -> u32 {
-        self.pipeline[idx]
+        self.pipeline.get(idx).copied().unwrap_or(0)
     }
 
+    #[doc(hidden)]
     pub fn is_pipeline_loaded(&self) -> bool {
         self.pipeline_loaded
     }
@@ -327,94 +566,99 @@ impl Cpu {
         if current == mode {
             return;
         }
-
-        // Save current banked registers
-        let idx = self.mode_index(current);
-        if idx < 6 {
-            self.banked_sp[idx] = self.r[13];
-            self.banked_lr[idx] = self.r[14];
-            self.banked_spsr[idx] = self.get_spsr();
-        }
-
-        // Load new banked registers
-        let idx = self.mode_index(mode);
+        self.bank.switch(current, mode, &mut self.r);
         self.cpsr = (self.cpsr & !0x1F) | (mode as u32);
+    }
 
-        if idx < 6 {
-            self.r[13] = self.banked_sp[idx];
-            self.r[14] = self.banked_lr[idx];
-        }
+    /// Copy SPSR to CPSR (exception return), swapping banks if the mode changes
+    fn restore_cpsr_from_spsr(&mut self) {
+        let spsr = self.get_spsr();
+        self.bank
+            .switch(self.get_mode(), Mode::from_bits(spsr), &mut self.r);
+        self.cpsr = spsr;
+    }
 
-        // Handle FIQ banking (R8-R12)
-        if mode == Mode::Fiq {
-            self.banked_r8_fiq = self.r[8];
-            self.banked_r9_fiq = self.r[9];
-            self.banked_r10_fiq = self.r[10];
-            self.banked_r11_fiq = self.r[11];
-            self.banked_r12_fiq = self.r[12];
-        } else if current == Mode::Fiq {
-            self.r[8] = self.banked_r8_fiq;
-            self.r[9] = self.banked_r9_fiq;
-            self.r[10] = self.banked_r10_fiq;
-            self.r[11] = self.banked_r11_fiq;
-            self.r[12] = self.banked_r12_fiq;
+    /// Replace the whole register file, e.g. from another emulator's save state.
+    /// Banked arrays are in FIQ, IRQ, SVC, ABT, UND, SYS order; `other_r8_12`
+    /// is the inactive R8-R12 set (user registers while in FIQ mode).
+    #[cfg(feature = "import")]
+    pub(crate) fn import_registers(
+        &mut self,
+        r: [u32; 16],
+        cpsr: u32,
+        banked_sp: [u32; 6],
+        banked_lr: [u32; 6],
+        banked_spsr: [u32; 6],
+        other_r8_12: [u32; 5],
+    ) {
+        self.r = r;
+        self.cpsr = cpsr;
+        self.bank = RegisterBank::new();
+        let modes = [
+            Mode::Fiq,
+            Mode::Irq,
+            Mode::Supervisor,
+            Mode::Abort,
+            Mode::Undefined,
+            Mode::System,
+        ];
+        let banked = banked_sp.into_iter().zip(banked_lr).zip(banked_spsr);
+        for (mode, ((sp, lr), spsr)) in modes.into_iter().zip(banked) {
+            self.bank.write(mode, 13, sp);
+            self.bank.write(mode, 14, lr);
+            self.bank.set_spsr(mode, spsr);
+        }
+        let other = if self.get_mode() == Mode::Fiq {
+            Mode::User
+        } else {
+            Mode::Fiq
+        };
+        for (n, val) in (8..13).zip(other_r8_12) {
+            self.bank.write(other, n, val);
         }
+        self.pipeline_loaded = false;
+        self.pc_written = false;
+        self.halted = false;
+        self.stopped = false;
+        self.intr_wait = None;
     }
 
-    fn mode_index(&self, mode: Mode) -> usize {
-        match mode {
-            Mode::Fiq => 0,
-            Mode::Irq => 1,
-            Mode::Supervisor => 2,
-            Mode::Abort => 3,
-            Mode::Undefined => 4,
-            Mode::System => 5,
-            Mode::User => 5,
+    /// Register `n` as seen from `mode`, whether or not it is the current mode
+    pub fn banked_reg(&self, mode: Mode, n: usize) -> u32 {
+        if RegisterBank::shares(mode, self.get_mode(), n) {
+            self.get_reg(n)
+        } else {
+            self.bank.read(mode, n)
         }
     }
 
-    fn get_user_reg(&self, n: usize) -> u32 {
-        match n {
-            8 if self.get_mode() == Mode::Fiq => self.banked_r8_fiq,
-            9 if self.get_mode() == Mode::Fiq => self.banked_r9_fiq,
-            10 if self.get_mode() == Mode::Fiq => self.banked_r10_fiq,
-            11 if self.get_mode() == Mode::Fiq => self.banked_r11_fiq,
-            12 if self.get_mode() == Mode::Fiq => self.banked_r12_fiq,
-            13 => self.banked_sp[5],
-            14 => self.banked_lr[5],
-            _ => self.r[n],
+    /// Write register `n` of `mode`, whether or not it is the current mode
+    pub fn set_banked_reg(&mut self, mode: Mode, n: usize, val: u32) {
+        if RegisterBank::shares(mode, self.get_mode(), n) {
+            self.set_reg(n, val);
+        } else {
+            self.bank.write(mode, n, val);
         }
     }
 
-    fn set_user_reg(&mut self, n: usize, val: u32) {
-        match n {
-            8 if self.get_mode() == Mode::Fiq => self.banked_r8_fiq = val,
-            9 if self.get_mode() == Mode::Fiq => self.banked_r9_fiq = val,
-            10 if self.get_mode() == Mode::Fiq => self.banked_r10_fiq = val,
-            11 if self.get_mode() == Mode::Fiq => self.banked_r11_fiq = val,
-            12 if self.get_mode() == Mode::Fiq => self.banked_r12_fiq = val,
-            13 => self.banked_sp[5] = val,
-            14 => self.banked_lr[5] = val,
-            _ => self.r[n] = val,
-        }
+    /// Read the user-mode copy of register `n` from any mode, as LDM/STM
+    /// with the S bit (`^`) do
+    pub(crate) fn get_user_reg(&self, n: usize) -> u32 {
+        self.banked_reg(Mode::User, n)
     }
 
+    /// Write the user-mode copy of register `n` from any mode
+    pub(crate) fn set_user_reg(&mut self, n: usize, val: u32) {
+        self.set_banked_reg(Mode::User, n, val);
+    }
+
+    /// SPSR of the current mode; User and System read CPSR
     fn get_spsr(&self) -> u32 {
-        let mode = self.get_mode();
-        let idx = self.mode_index(mode);
-        if idx < 6 {
-            self.banked_spsr[idx]
-        } else {
-            self.cpsr
-        }
+        self.bank.spsr(self.get_mode()).unwrap_or(self.cpsr)
     }
 
     fn set_spsr(&mut self, val: u32) {
-        let mode = self.get_mode();
-        let idx = self.mode_index(mode);
-        if idx < 6 {
-            self.banked_spsr[idx] = val;
-        }
+        self.bank.set_spsr(self.get_mode(), val);
     }
 
     // Flag access
@@ -493,11 +737,19 @@ impl Cpu {
 
     /// Execute one instruction, return cycles taken
     pub fn step(&mut self, mem: &mut super::Memory) -> u32 {
-        if self.is_thumb_mode() {
+        let traced = self
+            .call_stack
+            .is_some()
+            .then(|| (self.next_instruction_pc(), self.is_thumb_mode()));
+        let cycles = if self.is_thumb_mode() {
             self.step_thumb(mem)
         } else {
             self.step_arm(mem)
+        };
+        if let Some((pc, thumb)) = traced {
+            self.track_call(pc, thumb);
         }
+        cycles
     }
 
     fn step_arm(&mut self, mem: &mut super::Memory) -> u32 {
@@ -523,6 +775,7 @@ impl Cpu {
 
         self.pc_written = false;
         self.r[15] = instruction_pc.wrapping_add(8);
+        mem.set_open_bus_latch(self.pipeline[2]);
 
         let cycles = self.execute_arm_with_pc(opcode, mem, instruction_pc, self.r[15]);
 
@@ -530,8 +783,8 @@ impl Cpu {
             if instruction_pc == 0x0038 {
                 mem.set_bios_read_return(0xE25EF004);
             } else {
-                let offset = (instruction_pc + 8) as usize;
-                if offset + 4 <= 0x4000 {
+                let offset = instruction_pc.wrapping_add(8) as usize;
+                if offset < 0x3FFD {
                     let val = mem.bios_read_word(offset);
                     mem.set_bios_read_return(val);
                 }
@@ -569,6 +822,15 @@ impl Cpu {
         // Extract condition field (bits 31-28)
         let cond = ((opcode >> 28) & 0xF) as usize;
 
+        // ARMv5 BLX immediate (0xFA/0xFB) does not exist on the ARM7TDMI.
+        // Trap it instead of reading it as a never-taken branch.
+        if cond == 0xF && (opcode >> 25) & 0x7 == 0b101 {
+            return self.enter_undefined(instruction_pc, opcode);
+        }
+        if opcode & 0xFFF0_00F0 == 0xE120_0070 {
+            return self.execute_bkpt(instruction_pc);
+        }
+
         // Check if condition is satisfied
         if !self.check_condition(cond) {
             // Condition not met, skip this instruction
@@ -594,10 +856,11 @@ impl Cpu {
                     let bit5 = (opcode & 0x0000_0020) != 0;
                     let bit6 = (opcode & 0x0000_0040) != 0;
                     let bit7 = (opcode & 0x0000_0080) != 0;
-                    let bit22 = (opcode & 0x0040_0000) != 0;
-                    if bit7 && (bit5 || bit6) && (bit4 || bit22) {
-                        // Load-store halfword or signed byte
-                        // ARM7TDMI: bit7=1 required, bits 6-5=SH, bit4=1(reg) or bit22=1(imm)
+                    if bit7 && bit4 && (bit5 || bit6) {
+                        // Load-store halfword or signed byte: bits 7 and 4
+                        // set, bits 6-5 = SH. Bit 22 picks immediate or
+                        // register offset; with bit 4 clear it is a data
+                        // processing op shifted by an odd immediate.
                         self.execute_arm_load_store_halfword(opcode, mem)
                     } else if (opcode & 0x0FB0_0FF0) == 0x0100_0090 {
                         // SWP/SWPB - atomic swap
@@ -625,7 +888,10 @@ impl Cpu {
                 }
             }
             0x1 => {
-                if (opcode >> 25) & 1 != 0 {
+                if opcode & 0x0200_0010 == 0x0200_0010 {
+                    // The architecturally undefined space, cond 011x xxxx xxx1
+                    self.enter_undefined(instruction_pc, opcode)
+                } else if (opcode >> 25) & 1 != 0 {
                     self.execute_arm_load_store_register(opcode, mem)
                 } else {
                     self.execute_arm_load_store(opcode, mem)
@@ -647,18 +913,16 @@ impl Cpu {
                     self.execute_arm_load_store_register(opcode, mem)
                 }
             }
-            0x3 => {
-                // Check for SWI first (bits 27-24 = 0b1111)
+            _ => {
+                // Category 3. Check for SWI first (bits 27-24 = 0b1111)
                 if (opcode & 0x0F00_0000) == 0x0F00_0000 {
-                    // SWI: set LR to return address, then handle
-                    self.r[14] = instruction_pc + 4;
-                    self.execute_arm_swi(opcode, mem)
+                    self.execute_arm_swi(opcode, instruction_pc, mem)
                 } else {
-                    // Branch / Branch with link
-                    self.execute_arm_branch(opcode, instruction_pc, mem)
+                    // LDC/STC/CDP/MCR/MRC: the GBA has no coprocessor to
+                    // answer, so they trap
+                    self.enter_undefined(instruction_pc, opcode)
                 }
             }
-            _ => 1, // Unknown, treat as NOP
         }
     }
 
@@ -672,17 +936,19 @@ impl Cpu {
         let i_bit = ((opcode >> 25) & 1) != 0;
         let register_shift = !i_bit && (operand2 & 0x10) != 0;
 
-        let mut rn_val = self.r[rn];
+        let mut rn_val = self.get_reg(rn);
         if register_shift && rn == 15 {
             rn_val = rn_val.wrapping_add(4);
         }
-        let (op2_val, shifter_carry) = self.decode_operand2_ex(operand2, i_bit, register_shift);
+        let (op2_val, shifter_carry) = self.decode_operand2(operand2, i_bit, register_shift);
+        // Shifting by a register costs an internal cycle to read Rs
+        let cycles = (register_shift as u32).wrapping_add(1);
 
         match op {
             0x0 => {
                 // AND
                 let result = rn_val & op2_val;
-                self.r[rd] = result;
+                self.set_reg(rd, result);
                 if s {
                     self.set_flag_n((result as i32) < 0);
                     self.set_flag_z(result == 0);
@@ -692,7 +958,7 @@ impl Cpu {
             0x1 => {
                 // EOR
                 let result = rn_val ^ op2_val;
-                self.r[rd] = result;
+                self.set_reg(rd, result);
                 if s {
                     self.set_flag_n((result as i32) < 0);
                     self.set_flag_z(result == 0);
@@ -702,7 +968,7 @@ impl Cpu {
             0x2 => {
                 // SUB
                 let (result, overflow) = rn_val.overflowing_sub(op2_val);
-                self.r[rd] = result;
+                self.set_reg(rd, result);
                 if s {
                     self.set_flag_n((result as i32) < 0);
                     self.set_flag_z(result == 0);
@@ -713,7 +979,7 @@ impl Cpu {
             0x3 => {
                 // RSB
                 let (result, overflow) = op2_val.overflowing_sub(rn_val);
-                self.r[rd] = result;
+                self.set_reg(rd, result);
                 if s {
                     self.set_flag_n((result as i32) < 0);
                     self.set_flag_z(result == 0);
@@ -724,7 +990,7 @@ impl Cpu {
             0x4 => {
                 // ADD
                 let (result, overflow) = rn_val.overflowing_add(op2_val);
-                self.r[rd] = result;
+                self.set_reg(rd, result);
                 if s {
                     self.set_flag_n((result as i32) < 0);
                     self.set_flag_z(result == 0);
@@ -740,7 +1006,7 @@ impl Cpu {
                 let c = if self.get_flag_c() { 1 } else { 0 };
                 let (result1, overflow1) = rn_val.overflowing_add(op2_val);
                 let (result, overflow2) = result1.overflowing_add(c);
-                self.r[rd] = result;
+                self.set_reg(rd, result);
                 if s {
                     self.set_flag_n((result as i32) < 0);
                     self.set_flag_z(result == 0);
@@ -756,7 +1022,7 @@ impl Cpu {
                 let c = if self.get_flag_c() { 0 } else { 1 }; // NOT carry (borrow)
                 let borrow = op2_val.wrapping_add(c);
                 let (result, overflow) = rn_val.overflowing_sub(borrow);
-                self.r[rd] = result;
+                self.set_reg(rd, result);
                 if s {
                     self.set_flag_n((result as i32) < 0);
                     self.set_flag_z(result == 0);
@@ -769,7 +1035,7 @@ impl Cpu {
                 let c = if self.get_flag_c() { 0 } else { 1 }; // NOT carry (borrow)
                 let borrow = rn_val.wrapping_add(c);
                 let (result, overflow) = op2_val.overflowing_sub(borrow);
-                self.r[rd] = result;
+                self.set_reg(rd, result);
                 if s {
                     self.set_flag_n((result as i32) < 0);
                     self.set_flag_z(result == 0);
@@ -813,7 +1079,7 @@ impl Cpu {
             0xC => {
                 // ORR
                 let result = rn_val | op2_val;
-                self.r[rd] = result;
+                self.set_reg(rd, result);
                 if s {
                     self.set_flag_n((result as i32) < 0);
                     self.set_flag_z(result == 0);
@@ -822,7 +1088,7 @@ impl Cpu {
             }
             0xD => {
                 // MOV
-                self.r[rd] = op2_val;
+                self.set_reg(rd, op2_val);
                 if s && rd != 15 {
                     self.set_flag_n((op2_val as i32) < 0);
                     self.set_flag_z(op2_val == 0);
@@ -832,7 +1098,7 @@ impl Cpu {
             0xE => {
                 // BIC
                 let result = rn_val & !op2_val;
-                self.r[rd] = result;
+                self.set_reg(rd, result);
                 if s {
                     self.set_flag_n((result as i32) < 0);
                     self.set_flag_z(result == 0);
@@ -842,7 +1108,7 @@ impl Cpu {
             0xF => {
                 // MVN
                 let result = !op2_val;
-                self.r[rd] = result;
+                self.set_reg(rd, result);
                 if s {
                     self.set_flag_n((result as i32) < 0);
                     self.set_flag_z(result == 0);
@@ -854,38 +1120,9 @@ impl Cpu {
 
         // CMP/CMN/TST/TEQ with Rd=15 and S=1: restore CPSR from SPSR (no PC write)
         if rd == 15 && s && (op == 0x8 || op == 0x9 || op == 0xA || op == 0xB) {
-            let old_mode = self.get_mode();
-            let spsr = self.get_spsr();
-            let new_mode = Mode::from_bits(spsr);
-            self.cpsr = spsr;
-            if old_mode != new_mode {
-                let old_idx = self.mode_index(old_mode);
-                if old_idx < 6 {
-                    self.banked_sp[old_idx] = self.r[13];
-                    self.banked_lr[old_idx] = self.r[14];
-                    self.banked_spsr[old_idx] = spsr;
-                }
-                let new_idx = self.mode_index(new_mode);
-                if new_idx < 6 {
-                    self.r[13] = self.banked_sp[new_idx];
-                    self.r[14] = self.banked_lr[new_idx];
-                }
-                if new_mode == Mode::Fiq {
-                    self.banked_r8_fiq = self.r[8];
-                    self.banked_r9_fiq = self.r[9];
-                    self.banked_r10_fiq = self.r[10];
-                    self.banked_r11_fiq = self.r[11];
-                    self.banked_r12_fiq = self.r[12];
-                } else if old_mode == Mode::Fiq {
-                    self.r[8] = self.banked_r8_fiq;
-                    self.r[9] = self.banked_r9_fiq;
-                    self.r[10] = self.banked_r10_fiq;
-                    self.r[11] = self.banked_r11_fiq;
-                    self.r[12] = self.banked_r12_fiq;
-                }
-            }
+            self.restore_cpsr_from_spsr();
             self.r[15] = self.r[15].wrapping_add(4);
-            return 1;
+            return cycles;
         }
 
         // Check if PC was the destination register (but not for test/comparison ops)
@@ -893,52 +1130,20 @@ impl Cpu {
         if rd == 15 && op != 0x8 && op != 0x9 && op != 0xA && op != 0xB {
             let result = self.r[15];
             if s {
-                let old_mode = self.get_mode();
-                let spsr = self.get_spsr();
-                let new_mode = Mode::from_bits(spsr);
-                self.cpsr = spsr;
-                if old_mode != new_mode {
-                    let old_idx = self.mode_index(old_mode);
-                    if old_idx < 6 {
-                        self.banked_sp[old_idx] = self.r[13];
-                        self.banked_lr[old_idx] = self.r[14];
-                        self.banked_spsr[old_idx] = spsr;
-                    }
-                    let new_idx = self.mode_index(new_mode);
-                    if new_idx < 6 {
-                        self.r[13] = self.banked_sp[new_idx];
-                        self.r[14] = self.banked_lr[new_idx];
-                    }
-                    if new_mode == Mode::Fiq {
-                        self.banked_r8_fiq = self.r[8];
-                        self.banked_r9_fiq = self.r[9];
-                        self.banked_r10_fiq = self.r[10];
-                        self.banked_r11_fiq = self.r[11];
-                        self.banked_r12_fiq = self.r[12];
-                    } else if old_mode == Mode::Fiq {
-                        self.r[8] = self.banked_r8_fiq;
-                        self.r[9] = self.banked_r9_fiq;
-                        self.r[10] = self.banked_r10_fiq;
-                        self.r[11] = self.banked_r11_fiq;
-                        self.r[12] = self.banked_r12_fiq;
-                    }
-                }
+                self.restore_cpsr_from_spsr();
             }
             self.set_pc(result);
-            return 1;
+            return cycles;
         }
 
         self.r[15] = self.r[15].wrapping_add(4);
 
-        1
-    }
-
-    #[allow(dead_code)]
-    fn decode_operand2(&self, operand2: u32, is_immediate: bool) -> (u32, bool) {
-        self.decode_operand2_ex(operand2, is_immediate, false)
+        cycles
     }
 
-    fn decode_operand2_ex(
+    /// Operand 2 and the barrel shifter's carry-out, which the logical
+    /// operations copy into C when the S bit is set
+    fn decode_operand2(
         &self,
         operand2: u32,
         is_immediate: bool,
@@ -948,7 +1153,7 @@ impl Cpu {
 
         if is_immediate {
             let imm8 = (operand2 & 0xFF) as u32;
-            let rotate = ((operand2 >> 8) & 0xF) * 2;
+            let rotate = ((operand2 >> 8) & 0xF) << 1;
             let result = if rotate == 0 {
                 imm8
             } else {
@@ -963,14 +1168,14 @@ impl Cpu {
         } else {
             let rm = (operand2 & 0xF) as usize;
             let is_register_shift = (operand2 & 0x10) != 0;
-            let mut val = self.r[rm];
+            let mut val = self.get_reg(rm);
             if is_register_shift && is_reg_shift_instr && rm == 15 {
                 val = val.wrapping_add(4);
             }
 
             if is_register_shift {
                 let rs = ((operand2 >> 8) & 0xF) as usize;
-                let amount = (self.r[rs] & 0xFF) as u32;
+                let amount = (self.get_reg(rs) & 0xFF) as u32;
                 let shift_type = (shift >> 1) & 0x3;
 
                 let (result, carry) = match shift_type {
@@ -978,7 +1183,7 @@ impl Cpu {
                         if amount == 0 {
                             (val, self.get_flag_c())
                         } else if amount < 32 {
-                            (val << amount, (val >> (32 - amount)) != 0)
+                            (val << amount, (val >> 32u32.wrapping_sub(amount)) & 1 != 0)
                         } else {
                             (0, amount == 32 && (val & 1) != 0)
                         }
@@ -987,7 +1192,7 @@ impl Cpu {
                         if amount == 0 {
                             (val, self.get_flag_c())
                         } else if amount < 32 {
-                            (val >> amount, (val >> (amount - 1)) & 1 != 0)
+                            (val >> amount, (val >> amount.wrapping_sub(1)) & 1 != 0)
                         } else {
                             (0, amount == 32 && (val >> 31) != 0)
                         }
@@ -998,7 +1203,7 @@ impl Cpu {
                         } else if amount < 32 {
                             (
                                 ((val as i32) >> amount) as u32,
-                                (val >> (amount - 1)) & 1 != 0,
+                                (val >> amount.wrapping_sub(1)) & 1 != 0,
                             )
                         } else {
                             let bit31 = (val >> 31) != 0;
@@ -1030,14 +1235,20 @@ impl Cpu {
                         if shift_imm == 0 {
                             (val, self.get_flag_c())
                         } else {
-                            ((val << shift_imm), (val >> (32 - shift_imm)) != 0)
+                            (
+                                val << shift_imm,
+                                (val >> 32u32.wrapping_sub(shift_imm)) & 1 != 0,
+                            )
                         }
                     }
                     1 => {
                         if shift_imm == 0 {
                             (0, (val >> 31) != 0)
                         } else {
-                            (val >> shift_imm, (val >> (shift_imm - 1)) & 1 != 0)
+                            (
+                                val >> shift_imm,
+                                (val >> shift_imm.wrapping_sub(1)) & 1 != 0,
+                            )
                         }
                     }
                     2 => {
@@ -1047,7 +1258,7 @@ impl Cpu {
                         } else {
                             (
                                 ((val as i32) >> shift_imm) as u32,
-                                (val >> (shift_imm - 1)) & 1 != 0,
+                                (val >> shift_imm.wrapping_sub(1)) & 1 != 0,
                             )
                         }
                     }
@@ -1057,7 +1268,7 @@ impl Cpu {
                             ((val >> 1) | if c { 0x80000000 } else { 0 }, val & 1 != 0)
                         } else {
                             let result = val.rotate_right(shift_imm);
-                            (result, (val >> (shift_imm - 1)) & 1 != 0)
+                            (result, (val >> shift_imm.wrapping_sub(1)) & 1 != 0)
                         }
                     }
                     _ => (val, self.get_flag_c()),
@@ -1067,11 +1278,17 @@ impl Cpu {
         }
     }
 
-    #[allow(dead_code)]
-    fn set_flags_from_result(&mut self, result: u32) {
-        self.set_flag_n((result as i32) < 0);
-        self.set_flag_z(result == 0);
-        // C and V depend on the operation
+    /// Internal cycles the multiplier spends on `rs`: it stops after the
+    /// first of bits 8-31, 16-31 or 24-31 that are all zero, or all one
+    /// when the operand is signed
+    fn multiply_cycles(rs: u32, signed: bool) -> u32 {
+        for (m, mask) in [(1, 0xFFFF_FF00), (2, 0xFFFF_0000), (3, 0xFF00_0000)] {
+            let top = rs & mask;
+            if top == 0 || (signed && top == mask) {
+                return m;
+            }
+        }
+        4
     }
 
     fn execute_arm_multiply(&mut self, opcode: u32) -> u32 {
@@ -1091,51 +1308,58 @@ impl Cpu {
 
             let result = if u {
                 // Signed long multiply
-                let rm_signed = self.r[rm] as i32 as i64;
-                let rs_signed = self.r[rs] as i32 as i64;
+                let rm_signed = self.get_reg(rm) as i32 as i64;
+                let rs_signed = self.get_reg(rs) as i32 as i64;
                 let product = rm_signed.wrapping_mul(rs_signed) as u64;
                 if a {
-                    let acc = ((self.r[rd_hi] as u64) << 32) | (self.r[rd_lo] as u64);
+                    let acc = ((self.get_reg(rd_hi) as u64) << 32) | (self.get_reg(rd_lo) as u64);
                     product.wrapping_add(acc)
                 } else {
                     product
                 }
             } else {
                 // Unsigned long multiply
-                let product = (self.r[rm] as u64).wrapping_mul(self.r[rs] as u64);
+                let product = (self.get_reg(rm) as u64).wrapping_mul(self.get_reg(rs) as u64);
                 if a {
-                    let acc = ((self.r[rd_hi] as u64) << 32) | (self.r[rd_lo] as u64);
+                    let acc = ((self.get_reg(rd_hi) as u64) << 32) | (self.get_reg(rd_lo) as u64);
                     product.wrapping_add(acc)
                 } else {
                     product
                 }
             };
 
-            self.r[rd_hi] = (result >> 32) as u32;
-            self.r[rd_lo] = (result & 0xFFFF_FFFF) as u32;
+            let cycles = Self::multiply_cycles(self.get_reg(rs), u)
+                .wrapping_add(2)
+                .wrapping_add(a as u32);
+            self.set_reg(rd_hi, (result >> 32) as u32);
+            self.set_reg(rd_lo, (result & 0xFFFF_FFFF) as u32);
 
             if s {
                 self.set_flag_n((result as i64) < 0);
                 self.set_flag_z(result == 0);
             }
+            self.r[15] = self.r[15].wrapping_add(4);
+            cycles
         } else {
             // 32-bit result: Rd = Rm * Rs [+ Rn]
-            let product = (self.r[rm] as u32).wrapping_mul(self.r[rs] as u32);
+            let product = (self.get_reg(rm) as u32).wrapping_mul(self.get_reg(rs) as u32);
             let result = if a {
-                product.wrapping_add(self.r[rn])
+                product.wrapping_add(self.get_reg(rn))
             } else {
                 product
             };
-            self.r[rd] = result;
+            let cycles = Self::multiply_cycles(self.get_reg(rs), true)
+                .wrapping_add(1)
+                .wrapping_add(a as u32);
+            self.set_reg(rd, result);
 
             if s {
                 self.set_flag_n((result as i32) < 0);
                 self.set_flag_z(result == 0);
             }
+            self.r[15] = self.r[15].wrapping_add(4);
+            cycles
         }
-
-        self.r[15] = self.r[15].wrapping_add(4);
-        1
     }
 
     fn execute_arm_swp(&mut self, opcode: u32, mem: &mut super::Memory) -> u32 {
@@ -1144,16 +1368,16 @@ impl Cpu {
         let rm = (opcode & 0xF) as usize;
         let byte = ((opcode >> 22) & 1) != 0;
 
-        let addr = self.r[rn];
+        let addr = self.get_reg(rn);
 
         if byte {
             let old_val = mem.read_byte(addr) as u32;
-            mem.write_byte(addr, self.r[rm] as u8);
-            self.r[rd] = old_val;
+            mem.write_byte(addr, self.get_reg(rm) as u8);
+            self.set_reg(rd, old_val);
         } else {
             let old_val = mem.read_word(addr);
-            mem.write_word(addr, self.r[rm]);
-            self.r[rd] = old_val;
+            mem.write_word(addr, self.get_reg(rm));
+            self.set_reg(rd, old_val);
         }
 
         self.r[15] = self.r[15].wrapping_add(4);
@@ -1170,9 +1394,9 @@ impl Cpu {
         if is_mrs {
             let rd = ((opcode >> 12) & 0xF) as usize;
             if psr {
-                self.r[rd] = self.get_spsr();
+                self.set_reg(rd, self.get_spsr());
             } else {
-                self.r[rd] = self.cpsr;
+                self.set_reg(rd, self.cpsr);
             }
         } else {
             let rm = (opcode & 0xF) as usize;
@@ -1180,10 +1404,10 @@ impl Cpu {
 
             let val = if immediate {
                 let imm = opcode & 0xFF;
-                let rotate = ((opcode >> 8) & 0xF) * 2;
+                let rotate = ((opcode >> 8) & 0xF) << 1;
                 imm.rotate_right(rotate) as u32
             } else {
-                self.r[rm]
+                self.get_reg(rm)
             };
 
             let apply_flags = (opcode & 0x80000) != 0;
@@ -1253,10 +1477,10 @@ impl Cpu {
             (imm4h << 4) | imm4l
         } else {
             let rm = (opcode & 0xF) as usize;
-            self.r[rm]
+            self.get_reg(rm)
         };
 
-        let base = self.r[rn];
+        let base = self.get_reg(rn);
         let offset_addr = if up {
             base.wrapping_add(offset)
         } else {
@@ -1283,16 +1507,27 @@ impl Cpu {
                 mem.read_half_rotated(addr)
             };
 
+            // Post-indexing always writes back; a loaded Rn wins over it
             if !pre_index || writeback {
-                self.r[rn] = offset_addr;
+                self.set_reg(rn, offset_addr);
             }
 
-            self.r[rd] = val;
+            if rd == 15 {
+                self.set_pc(val & !3);
+                return 2;
+            }
+            self.set_reg(rd, val);
         } else {
-            mem.write_half(addr, self.r[rd] as u16);
+            // A stored PC reads 12 ahead, like STR
+            let val = if rd == 15 {
+                self.get_reg(rd).wrapping_add(4)
+            } else {
+                self.get_reg(rd)
+            };
+            mem.write_half(addr, val as u16);
 
             if !pre_index || writeback {
-                self.r[rn] = offset_addr;
+                self.set_reg(rn, offset_addr);
             }
         }
 
@@ -1302,7 +1537,7 @@ impl Cpu {
 
     fn execute_arm_bx(&mut self, opcode: u32, _mem: &mut super::Memory) -> u32 {
         let rm = (opcode & 0xF) as usize;
-        let target = self.r[rm];
+        let target = self.get_reg(rm);
 
         let is_thumb = (target & 1) != 0;
         self.set_thumb_mode(is_thumb);
@@ -1317,7 +1552,7 @@ impl Cpu {
         let rd = ((_opcode >> 12) & 0xF) as usize;
         let offset = (_opcode & 0xFFF) as i32 as i64;
 
-        let base = self.r[rn] as i64;
+        let base = self.get_reg(rn) as i64;
 
         let load = (_opcode >> 20) & 1 != 0;
         let byte = (_opcode >> 22) & 1 != 0;
@@ -1326,7 +1561,7 @@ impl Cpu {
         let pre_index = (_opcode >> 24) & 1 != 0;
         let u = if add { 1i64 } else { -1i64 };
 
-        let offset_addr = (base + u * offset) as u32;
+        let offset_addr = base.wrapping_add(u.wrapping_mul(offset)) as u32;
         let addr = if pre_index { offset_addr } else { base as u32 };
 
         if load {
@@ -1337,20 +1572,20 @@ impl Cpu {
             };
 
             if !pre_index || writeback {
-                self.r[rn] = offset_addr;
+                self.set_reg(rn, offset_addr);
             }
 
             if rd == 15 {
                 self.set_pc(val & 0xFFFFFFFE);
                 return 2;
             } else {
-                self.r[rd] = val;
+                self.set_reg(rd, val);
             }
         } else {
             let val = if rd == 15 {
-                self.r[rd].wrapping_add(4)
+                self.get_reg(rd).wrapping_add(4)
             } else {
-                self.r[rd]
+                self.get_reg(rd)
             };
             if byte {
                 mem.write_byte(addr, val as u8);
@@ -1359,7 +1594,7 @@ impl Cpu {
             }
 
             if !pre_index || writeback {
-                self.r[rn] = offset_addr;
+                self.set_reg(rn, offset_addr);
             }
         }
 
@@ -1380,7 +1615,7 @@ impl Cpu {
         let shift_type = (opcode >> 5) & 0x3;
         let shift_amount = ((opcode >> 7) & 0x1F) as u32;
 
-        let mut offset = self.r[rm];
+        let mut offset = self.get_reg(rm);
         match shift_type {
             0 => offset <<= shift_amount,
             1 => {
@@ -1402,7 +1637,7 @@ impl Cpu {
             _ => {}
         }
 
-        let base = self.r[rn];
+        let base = self.get_reg(rn);
         let offset_addr = if add {
             base.wrapping_add(offset)
         } else {
@@ -1418,20 +1653,20 @@ impl Cpu {
             };
 
             if !pre_index || writeback {
-                self.r[rn] = offset_addr;
+                self.set_reg(rn, offset_addr);
             }
 
             if rd == 15 {
                 self.set_pc(val & 0xFFFFFFFE);
                 return 2;
             } else {
-                self.r[rd] = val;
+                self.set_reg(rd, val);
             }
         } else {
             let val = if rd == 15 {
-                self.r[rd].wrapping_add(4)
+                self.get_reg(rd).wrapping_add(4)
             } else {
-                self.r[rd]
+                self.get_reg(rd)
             };
             if byte {
                 mem.write_byte(addr, val as u8);
@@ -1440,7 +1675,7 @@ impl Cpu {
             }
 
             if !pre_index || writeback {
-                self.r[rn] = offset_addr;
+                self.set_reg(rn, offset_addr);
             }
         }
 
@@ -1465,7 +1700,7 @@ impl Cpu {
         let rn = ((opcode >> 16) & 0xF) as usize; // Base register
         let reg_list = opcode & 0xFFFF; // Bitmask of registers
 
-        let mut addr = self.r[rn] & !3;
+        let mut addr = self.get_reg(rn) & !3;
         if add_to_base {
             // Increment mode (U=1)
             if pre_index {
@@ -1478,12 +1713,12 @@ impl Cpu {
             if pre_index {
                 // DB: start at base - reg_count*4
                 let reg_count = reg_list.count_ones() as u32;
-                addr = addr.wrapping_sub(reg_count * 4);
+                addr = addr.wrapping_sub(reg_count << 2);
             } else {
                 // DA: start at base - (reg_count-1)*4
                 let reg_count = reg_list.count_ones() as u32;
                 if reg_count > 0 {
-                    addr = addr.wrapping_sub((reg_count - 1) * 4);
+                    addr = addr.wrapping_sub(reg_count.wrapping_sub(1) << 2);
                 }
             }
         }
@@ -1491,7 +1726,7 @@ impl Cpu {
         // Handle empty register list special case
         if reg_list == 0 {
             // Empty rlist: treat as 16 registers for address calc
-            let mut empty_addr = self.r[rn] & !3;
+            let mut empty_addr = self.get_reg(rn) & !3;
             if add_to_base {
                 if pre_index {
                     empty_addr = empty_addr.wrapping_add(4);
@@ -1514,42 +1749,44 @@ impl Cpu {
             }
             if writeback {
                 if add_to_base {
-                    self.r[rn] = self.r[rn].wrapping_add(0x40);
+                    self.set_reg(rn, self.get_reg(rn).wrapping_add(0x40));
                 } else {
-                    self.r[rn] = self.r[rn].wrapping_sub(0x40);
+                    self.set_reg(rn, self.get_reg(rn).wrapping_sub(0x40));
                 }
             }
             return 3;
         }
 
-        // Process each register
+        // Process each register. With the S bit, STM always and LDM without
+        // PC transfer the user bank; LDM with PC restores CPSR instead.
         let is_privileged = self.get_mode() != Mode::User;
+        let user_bank = force_user && is_privileged && !(load && reg_list & (1 << 15) != 0);
         let lowest_reg = reg_list.trailing_zeros() as usize;
         let reg_count = reg_list.count_ones() as u32;
         let wb_value = if add_to_base {
-            self.r[rn].wrapping_add(reg_count * 4)
+            self.get_reg(rn).wrapping_add(reg_count << 2)
         } else {
-            self.r[rn].wrapping_sub(reg_count * 4)
+            self.get_reg(rn).wrapping_sub(reg_count << 2)
         };
 
         for reg_idx in 0..16 {
             if reg_list & (1 << reg_idx) != 0 {
                 if load {
                     let val = mem.read_word(addr);
-                    if force_user && reg_idx != 15 && is_privileged {
+                    if user_bank {
                         self.set_user_reg(reg_idx, val);
                     } else {
-                        self.r[reg_idx] = val;
+                        self.set_reg(reg_idx, val);
                     }
                 } else {
                     let val = if reg_idx == 15 {
                         self.r[15].wrapping_add(4)
                     } else if reg_idx == rn && reg_idx != lowest_reg && writeback {
                         wb_value
-                    } else if force_user && is_privileged {
+                    } else if user_bank {
                         self.get_user_reg(reg_idx)
                     } else {
-                        self.r[reg_idx]
+                        self.get_reg(reg_idx)
                     };
                     mem.write_word(addr, val);
                 }
@@ -1560,22 +1797,21 @@ impl Cpu {
         if writeback && !(load && reg_list & (1 << rn) != 0) {
             let reg_count = reg_list.count_ones() as u32;
             if add_to_base {
-                self.r[rn] = self.r[rn].wrapping_add(reg_count * 4);
+                self.set_reg(rn, self.get_reg(rn).wrapping_add(reg_count << 2));
             } else {
-                self.r[rn] = self.r[rn].wrapping_sub(reg_count * 4);
+                self.set_reg(rn, self.get_reg(rn).wrapping_sub(reg_count << 2));
             }
         }
 
+        // LDM^ with PC is an exception return: CPSR comes back from SPSR
+        // after the loads and writeback used the exception mode's bank, and
+        // PC is aligned for the restored ARM or Thumb state
         if load && (reg_list & (1 << 15)) != 0 {
             let pc_value = self.r[15];
             if force_user && is_privileged {
-                let spsr = self.get_spsr();
-                self.cpsr = spsr;
-                self.set_mode(Mode::from_bits(spsr));
+                self.restore_cpsr_from_spsr();
             }
-            self.r[15] = pc_value;
-            self.pipeline_loaded = false;
-            self.pc_written = true;
+            self.set_pc(pc_value);
             return 3;
         }
 
@@ -1620,7 +1856,36 @@ impl Cpu {
         2 // Branch takes 2 cycles
     }
 
-    fn execute_arm_swi(&mut self, opcode: u32, mem: &mut super::Memory) -> u32 {
+    /// Run a semihosting call if it is enabled and `comment` requests one
+    fn try_semihost(&mut self, comment: u32, thumb: bool, mem: &mut super::Memory) -> bool {
+        let request = if thumb {
+            crate::semihost::SEMIHOST_SWI_THUMB
+        } else {
+            crate::semihost::SEMIHOST_SWI_ARM
+        };
+        if comment != request {
+            return false;
+        }
+        let Some(mut host) = mem.semihost.take() else {
+            return false;
+        };
+        self.r[0] = host.call(self.r[0], self.r[1], mem);
+        mem.semihost = Some(host);
+        true
+    }
+
+    fn execute_arm_swi(
+        &mut self,
+        opcode: u32,
+        instruction_pc: u32,
+        mem: &mut super::Memory,
+    ) -> u32 {
+        if self.try_semihost(opcode & 0x00FF_FFFF, false, mem) {
+            self.r[15] = self.current_arm_pc.wrapping_add(4);
+            self.pc_written = true;
+            self.pipeline_loaded = false;
+            return 3;
+        }
         let swi_num_upper = ((opcode >> 16) & 0xFF) as u32;
         let swi_num_lower = (opcode & 0xFF) as u32;
 
@@ -1629,27 +1894,17 @@ impl Cpu {
         if mem.swi_log_enabled && mem.swi_log.len() < 100_000 {
             mem.swi_log.push(swi_num);
         }
-        mem.arm_swi_count += 1;
+        mem.arm_swi_count = mem.arm_swi_count.wrapping_add(1);
 
         if mem.use_real_bios {
-            if swi_num == 0x04 || swi_num == 0x05 {
-                mem.interrupt.ie |= super::mem::Interrupt::VBLANK;
-                mem.interrupt.if_raw &= !super::mem::Interrupt::VBLANK;
-                mem.interrupt.ime = true;
-
-                mem.halt_pending = true;
-                return if self.cpsr & 0x20 != 0 { 2 + 2 } else { 3 };
-            }
-            let old_cpsr = self.cpsr;
-            let ret_addr = self.r[14];
-            self.set_mode(Mode::Supervisor);
-            self.banked_spsr[self.mode_index(Mode::Supervisor)] = old_cpsr;
-            self.r[14] = ret_addr;
-            self.cpsr |= 0x80;
-            self.set_thumb_mode(false);
-            self.set_pc(0x00000008);
-            return 3;
+            return self.enter_exception(
+                Mode::Supervisor,
+                0x0000_0008,
+                instruction_pc.wrapping_add(4),
+            );
         }
+        // Emulated calls return through LR like the BIOS's `MOVS PC, LR`
+        self.r[14] = instruction_pc.wrapping_add(4);
 
         let bios_return = match swi_num {
             0x08 => 0xE3A02004,
@@ -1659,8 +1914,9 @@ impl Cpu {
 
         match swi_num {
             0x00 => {
+                let entry = crate::multiboot::soft_reset_entry(mem.read_byte(0x0300_7FFA));
                 self.reset();
-                self.set_pc(0x08000000);
+                self.set_pc(entry);
                 return 3;
             }
             0x01 => {
@@ -1684,39 +1940,27 @@ impl Cpu {
                     mem.clear_io();
                 }
             }
-            0x02 | 0x03 => {
+            0x02 => {
                 mem.halt_pending = true;
             }
+            0x03 => {
+                mem.stop_pending = true;
+            }
             0x04 => {
-                mem.interrupt.ime = true;
-                mem.interrupt.ie |= super::mem::Interrupt::VBLANK;
-                mem.halt_pending = true;
+                self.begin_intr_wait(self.r[0] != 0, self.r[1] as u16, mem);
             }
             0x05 => {
-                mem.interrupt.ime = true;
-                mem.interrupt.ie |= super::mem::Interrupt::VBLANK;
-                // BIOS maintains VBlank counter at 0x03007FF8
-                {
-                    let iwram = mem.iwram_mut();
-                    let a = 0x7FF8;
-                    let lo = u16::from_le_bytes([iwram[a], iwram[a + 1]]);
-                    let hi = u16::from_le_bytes([iwram[a + 2], iwram[a + 3]]);
-                    let c = ((hi as u32) << 16 | lo as u32).wrapping_add(1);
-                    iwram[a] = c as u8;
-                    iwram[a + 1] = (c >> 8) as u8;
-                    iwram[a + 2] = (c >> 16) as u8;
-                    iwram[a + 3] = (c >> 24) as u8;
-                }
-                mem.halt_pending = true;
+                self.begin_intr_wait(true, super::mem::Interrupt::VBLANK.bits(), mem);
             }
             0x06 => {
-                let r0 = self.r[0] as i32;
-                let r1 = self.r[1] as i32;
-                if r1 != 0 {
-                    let q = r0.wrapping_div(r1);
+                // Widened so i32::MIN / -1 cannot overflow; only a zero
+                // divisor fails
+                let r0 = self.r[0] as i32 as i64;
+                let r1 = self.r[1] as i32 as i64;
+                if let (Some(q), Some(rem)) = (r0.checked_div(r1), r0.checked_rem(r1)) {
                     self.r[0] = q as u32;
-                    self.r[1] = r0.wrapping_rem(r1) as u32;
-                    self.r[3] = q.wrapping_abs() as u32;
+                    self.r[1] = rem as u32;
+                    self.r[3] = (q as i32).wrapping_abs() as u32;
                 } else {
                     let v = if r0 >= 0 { 0x7FFFFFFF } else { 0x80000000 };
                     self.r[0] = v;
@@ -1725,13 +1969,12 @@ impl Cpu {
                 }
             }
             0x07 => {
-                let r0 = self.r[1] as i32;
-                let r1 = self.r[0] as i32;
-                if r1 != 0 {
-                    let q = r0.wrapping_div(r1);
+                let r0 = self.r[1] as i32 as i64;
+                let r1 = self.r[0] as i32 as i64;
+                if let (Some(q), Some(rem)) = (r0.checked_div(r1), r0.checked_rem(r1)) {
                     self.r[0] = q as u32;
-                    self.r[1] = r0.wrapping_rem(r1) as u32;
-                    self.r[3] = q.wrapping_abs() as u32;
+                    self.r[1] = rem as u32;
+                    self.r[3] = (q as i32).wrapping_abs() as u32;
                 }
             }
             0x08 => {
@@ -1755,28 +1998,16 @@ impl Cpu {
                     if is_32 {
                         let v = mem.read_word(src);
                         for i in 0..count {
-                            mem.write_word(dst + i * 4, v);
+                            mem.write_word(dst.wrapping_add(i << 2), v);
                         }
                     } else {
                         let v = mem.read_half(src);
                         for i in 0..count {
-                            mem.write_half(dst + i * 2, v);
+                            mem.write_half(dst.wrapping_add(i << 1), v);
                         }
                     }
                 } else {
-                    let mut s = src;
-                    let mut d = dst;
-                    for _ in 0..count {
-                        if is_32 {
-                            let v = mem.read_word(s);
-                            mem.write_word(d, v);
-                        } else {
-                            let v = mem.read_half(s);
-                            mem.write_half(d, v);
-                        }
-                        s += if is_32 { 4 } else { 2 };
-                        d += if is_32 { 4 } else { 2 };
-                    }
+                    mem.copy_block(src, dst, count, if is_32 { 4 } else { 2 });
                 }
             }
             0x0C => {
@@ -1788,17 +2019,10 @@ impl Cpu {
                 if fill {
                     let v = mem.read_word(src);
                     for i in 0..count {
-                        mem.write_word(dst + i * 4, v);
+                        mem.write_word(dst.wrapping_add(i << 2), v);
                     }
                 } else {
-                    let mut s = src;
-                    let mut d = dst;
-                    for _ in 0..count {
-                        let v = mem.read_word(s);
-                        mem.write_word(d, v);
-                        s += 4;
-                        d += 4;
-                    }
+                    mem.copy_block(src, dst, count, 4);
                 }
             }
             0x10 | 0x11 => {
@@ -1809,34 +2033,34 @@ impl Cpu {
                 let size = header >> 8;
                 if (header & 0xFF) != 0x10 { /* not LZ77 */
                 } else {
-                    let mut sp = src + 4;
+                    let mut sp = src.wrapping_add(4);
                     let mut dp = dst;
                     let mut written = 0u32;
                     while written < size {
                         let flags = mem.read_byte(sp);
-                        sp += 1;
+                        sp = sp.wrapping_add(1);
                         for bit in 0..8 {
                             if written >= size {
                                 break;
                             }
                             if (flags & (1 << bit)) != 0 {
                                 let b0 = mem.read_byte(sp) as u32;
-                                let b1 = mem.read_byte(sp + 1) as u32;
+                                let b1 = mem.read_byte(sp.wrapping_add(1)) as u32;
                                 let len = if (b0 >> 4) != 0 {
-                                    sp += 2;
-                                    (b0 >> 4) as usize + 3
+                                    sp = sp.wrapping_add(2);
+                                    ((b0 >> 4) as usize).wrapping_add(3)
                                 } else {
-                                    let b2 = mem.read_byte(sp + 2) as u32;
-                                    sp += 3;
-                                    b2 as usize + 3
+                                    let b2 = mem.read_byte(sp.wrapping_add(2)) as u32;
+                                    sp = sp.wrapping_add(3);
+                                    (b2 as usize).wrapping_add(3)
                                 };
-                                let disp = (((b0 & 0xF) as usize) << 8) | (b1 & 0xFF) as usize;
-                                let lb = dp as usize - disp - 1;
+                                let disp = ((b0 & 0xF) << 8) | b1;
+                                let lb = dp.wrapping_sub(disp).wrapping_sub(1);
                                 for i in 0..len {
-                                    let b = mem.read_byte((lb + i) as u32);
+                                    let b = mem.read_byte(lb.wrapping_add(i as u32));
                                     if vram {
                                         let aligned = dp & !1;
-                                        let shift = (dp & 1) * 8;
+                                        let shift = (dp & 1) << 3;
                                         let old = mem.read_half(aligned);
                                         mem.write_half(
                                             aligned,
@@ -1845,18 +2069,18 @@ impl Cpu {
                                     } else {
                                         mem.write_byte(dp, b);
                                     }
-                                    dp += 1;
-                                    written += 1;
+                                    dp = dp.wrapping_add(1);
+                                    written = written.wrapping_add(1);
                                     if written >= size {
                                         break;
                                     }
                                 }
                             } else {
                                 let b = mem.read_byte(sp);
-                                sp += 1;
+                                sp = sp.wrapping_add(1);
                                 if vram {
                                     let aligned = dp & !1;
-                                    let shift = (dp & 1) * 8;
+                                    let shift = (dp & 1) << 3;
                                     let old = mem.read_half(aligned);
                                     mem.write_half(
                                         aligned,
@@ -1865,8 +2089,8 @@ impl Cpu {
                                 } else {
                                     mem.write_byte(dp, b);
                                 }
-                                dp += 1;
-                                written += 1;
+                                dp = dp.wrapping_add(1);
+                                written = written.wrapping_add(1);
                             }
                         }
                     }
@@ -1883,20 +2107,25 @@ impl Cpu {
                 let size = header >> 8;
                 if (header & 0xFF) != 0x30 { /* not RL */
                 } else {
-                    let mut sp = src + 4;
+                    let mut sp = src.wrapping_add(4);
                     let mut dp = dst;
                     let mut written = 0u32;
                     while written < size {
                         let ctrl = mem.read_byte(sp) as u32;
-                        sp += 1;
-                        let len = (ctrl & 0x7F) as usize;
+                        sp = sp.wrapping_add(1);
+                        // Repeated runs are stored minus 3 and literal runs minus 1
+                        let len = ((ctrl & 0x7F) as usize).wrapping_add(if ctrl & 0x80 != 0 {
+                            3
+                        } else {
+                            1
+                        });
                         if (ctrl & 0x80) != 0 {
                             let val = mem.read_byte(sp);
-                            sp += 1;
+                            sp = sp.wrapping_add(1);
                             for _ in 0..len {
                                 if vram {
                                     let aligned = dp & !1;
-                                    let shift = (dp & 1) * 8;
+                                    let shift = (dp & 1) << 3;
                                     let old = mem.read_half(aligned);
                                     mem.write_half(
                                         aligned,
@@ -1905,8 +2134,8 @@ impl Cpu {
                                 } else {
                                     mem.write_byte(dp, val);
                                 }
-                                dp += 1;
-                                written += 1;
+                                dp = dp.wrapping_add(1);
+                                written = written.wrapping_add(1);
                                 if written >= size {
                                     break;
                                 }
@@ -1914,10 +2143,10 @@ impl Cpu {
                         } else {
                             for _ in 0..len {
                                 let val = mem.read_byte(sp);
-                                sp += 1;
+                                sp = sp.wrapping_add(1);
                                 if vram {
                                     let aligned = dp & !1;
-                                    let shift = (dp & 1) * 8;
+                                    let shift = (dp & 1) << 3;
                                     let old = mem.read_half(aligned);
                                     mem.write_half(
                                         aligned,
@@ -1926,8 +2155,8 @@ impl Cpu {
                                 } else {
                                     mem.write_byte(dp, val);
                                 }
-                                dp += 1;
-                                written += 1;
+                                dp = dp.wrapping_add(1);
+                                written = written.wrapping_add(1);
                                 if written >= size {
                                     break;
                                 }
@@ -1939,6 +2168,10 @@ impl Cpu {
                     self.r[3] = written;
                 }
             }
+            0x25 => {
+                // MultiBoot: no slave ever answers the link handshake
+                self.r[0] = 1;
+            }
             _ => {}
         }
 
@@ -1979,6 +2212,7 @@ impl Cpu {
 
         self.pipeline[1] = self.pipeline[2];
         self.pipeline_pc[1] = self.pipeline_pc[2];
+        mem.set_open_bus_latch(self.pipeline[1] | (self.pipeline[1] << 16));
 
         // Decode and execute
         let cycles = self.execute_thumb(opcode, mem, instruction_pc);
@@ -1996,106 +2230,73 @@ impl Cpu {
         cycles
     }
 
+    /// Decode by the ARM7TDMI Thumb format table, keyed on the top five bits.
+    /// Encodings the table leaves unused (including the ARMv5 BLX and BKPT
+    /// forms) take the undefined instruction exception.
     fn execute_thumb(&mut self, opcode: u16, mem: &mut super::Memory, instruction_pc: u32) -> u32 {
-        let category = (opcode >> 13) & 0x7;
-
-        match category {
-            0b000 => {
-                let op11 = (opcode >> 11) & 0x3;
-                if op11 <= 2 {
-                    // LSL (0), LSR (1), ASR (2)
-                    self.thumb_shift_register(opcode)
-                } else {
-                    // ADD/SUB register (3)
-                    self.thumb_add_sub_reg(opcode)
-                }
-            }
-            0b001 => self.thumb_data_proc_imm(opcode),
-            0b010 => {
-                if (opcode & 0xF800) == 0x4800 {
-                    self.thumb_load_pc_rel(opcode, mem, instruction_pc)
-                } else if (opcode & 0xFC00) == 0x4400 {
-                    self.thumb_hi_reg_ops(opcode, instruction_pc)
-                } else if (opcode & 0xFC00) == 0x4000 {
-                    self.thumb_data_proc_reg(opcode)
-                } else {
-                    let op = (opcode >> 9) & 0x7;
-                    match op {
-                        0b000 => self.thumb_str_reg_offset(opcode, mem),
-                        0b001 => self.thumb_strh_reg_offset(opcode, mem),
-                        0b010 => self.thumb_strb_reg_offset(opcode, mem),
-                        0b011 => self.thumb_ldrsb_reg_offset(opcode, mem),
-                        0b100 => self.thumb_ldr_reg_offset(opcode, mem),
-                        0b101 => self.thumb_ldrh_reg_offset(opcode, mem),
-                        0b110 => self.thumb_ldrb_reg_offset(opcode, mem),
-                        0b111 => self.thumb_ldrsh_reg_offset(opcode, mem),
-                        _ => 1,
-                    }
-                }
-            }
-            0b011 => {
-                // Category 3: Load/store word/byte with IMMEDIATE offset
+        match opcode >> 11 {
+            // Format 1: move shifted register (LSL, LSR, ASR)
+            0b00000..=0b00010 => self.thumb_shift_register(opcode),
+            // Format 2: add/subtract
+            0b00011 => self.thumb_add_sub_reg(opcode),
+            // Format 3: move/compare/add/subtract immediate
+            0b00100..=0b00111 => self.thumb_data_proc_imm(opcode),
+            // Format 4: ALU operations; format 5: hi register operations/BX
+            0b01000 if opcode & 0x0400 == 0 => self.thumb_data_proc_reg(opcode),
+            0b01000 => self.thumb_hi_reg_ops(opcode, instruction_pc),
+            // Format 6: PC-relative load
+            0b01001 => self.thumb_load_pc_rel(opcode, mem, instruction_pc),
+            // Formats 7 and 8: load/store with register offset, sign-extended
+            0b01010 | 0b01011 => match (opcode >> 9) & 0x7 {
+                0b000 => self.thumb_str_reg_offset(opcode, mem),
+                0b001 => self.thumb_strh_reg_offset(opcode, mem),
+                0b010 => self.thumb_strb_reg_offset(opcode, mem),
+                0b011 => self.thumb_ldrsb_reg_offset(opcode, mem),
+                0b100 => self.thumb_ldr_reg_offset(opcode, mem),
+                0b101 => self.thumb_ldrh_reg_offset(opcode, mem),
+                0b110 => self.thumb_ldrb_reg_offset(opcode, mem),
+                _ => self.thumb_ldrsh_reg_offset(opcode, mem),
+            },
+            // Format 9: load/store word/byte with immediate offset
+            0b01100..=0b01111 => {
                 let load = (opcode >> 11) & 1 != 0;
                 self.thumb_load_store_word_byte(opcode, mem, load)
             }
-            0b100 => {
-                // Category 4: Load/store sign-extended, halfword, sp-relative
-                let op = (opcode >> 11) & 0x3;
-                match op {
-                    0b00 => self.thumb_load_store_halfword(opcode, mem, false),
-                    0b01 => self.thumb_load_store_halfword(opcode, mem, true),
-                    0b10 => self.thumb_load_store_sp_rel(opcode, mem, false),
-                    0b11 => self.thumb_load_store_sp_rel(opcode, mem, true),
-                    _ => 1,
-                }
-            }
-            0b101 => {
-                // Category 5: Load address, add offset to SP, push/pop
-                if (opcode & 0xF000) == 0xA000 {
-                    self.thumb_load_addr(opcode, instruction_pc)
-                } else if (opcode & 0xFF00) == 0xB000 {
-                    self.thumb_add_sp(opcode)
-                } else if (opcode & 0xFE00) == 0xB400 {
-                    // PUSH (0xB400-0xB5FF)
-                    self.thumb_push_pop(opcode, mem, false)
-                } else if (opcode & 0xFE00) == 0xBC00 {
-                    // POP (0xBC00-0xBDFF)
-                    self.thumb_push_pop(opcode, mem, true)
-                } else {
-                    1
-                }
-            }
-            0b110 => {
-                // Category 6: LDMIA/STMIA, conditional branch, SWI
-                if (opcode & 0xF000) == 0xC000 {
-                    // LDMIA/STMIA
-                    let is_load = (opcode >> 11) & 1 != 0;
-                    self.thumb_load_store_multiple(opcode, mem, is_load)
-                } else if (opcode & 0xF000) == 0xD000 {
-                    if (opcode & 0xFF00) == 0xDF00 {
-                        self.thumb_software_interrupt(opcode, mem, instruction_pc)
-                    } else {
-                        self.thumb_branch_cond(opcode, instruction_pc)
-                    }
-                } else {
-                    self.thumb_branch(opcode, instruction_pc)
-                }
-            }
-            0b111 => {
-                let top5 = (opcode >> 11) & 0x1F;
-                if top5 == 0b11100 {
-                    self.thumb_branch(opcode, instruction_pc)
-                } else if top5 == 0b11110 || top5 == 0b11111 {
-                    if (opcode & 0xF800) == 0xF000 {
-                        self.thumb_bl_prefix(opcode, instruction_pc)
-                    } else {
-                        self.thumb_bl_suffix(opcode, instruction_pc)
-                    }
-                } else {
-                    1
-                }
-            }
-            _ => 1,
+            // Format 10: load/store halfword
+            0b10000 => self.thumb_load_store_halfword(opcode, mem, false),
+            0b10001 => self.thumb_load_store_halfword(opcode, mem, true),
+            // Format 11: SP-relative load/store
+            0b10010 => self.thumb_load_store_sp_rel(opcode, mem, false),
+            0b10011 => self.thumb_load_store_sp_rel(opcode, mem, true),
+            // Format 12: load address
+            0b10100 | 0b10101 => self.thumb_load_addr(opcode, instruction_pc),
+            // Format 13: add offset to SP; format 14: push/pop; ARMv5 BKPT
+            0b10110 | 0b10111 => match (opcode >> 8) & 0xF {
+                0x0 => self.thumb_add_sp(opcode),
+                0x4 | 0x5 => self.thumb_push_pop(opcode, mem, false),
+                0xC | 0xD => self.thumb_push_pop(opcode, mem, true),
+                0xE => self.execute_bkpt(instruction_pc),
+                _ => self.enter_undefined(instruction_pc, opcode as u32),
+            },
+            // Format 15: multiple load/store
+            0b11000 | 0b11001 => {
+                let is_load = (opcode >> 11) & 1 != 0;
+                self.thumb_load_store_multiple(opcode, mem, is_load)
+            }
+            // Format 16: conditional branch, with condition 0xE undefined;
+            // format 17: SWI in the condition 0xF slot
+            0b11010 | 0b11011 => match (opcode >> 8) & 0xF {
+                0xE => self.enter_undefined(instruction_pc, opcode as u32),
+                0xF => self.thumb_software_interrupt(opcode, mem, instruction_pc),
+                _ => self.thumb_branch_cond(opcode, instruction_pc),
+            },
+            // Format 18: unconditional branch
+            0b11100 => self.thumb_branch(opcode, instruction_pc),
+            // Format 19: long branch with link
+            0b11110 => self.thumb_bl_prefix(opcode, instruction_pc),
+            0b11111 => self.thumb_bl_suffix(opcode, instruction_pc),
+            // 0b11101 is the ARMv5 BLX suffix
+            _ => self.enter_undefined(instruction_pc, opcode as u32),
         }
     }
 
@@ -2107,14 +2308,14 @@ impl Cpu {
         let rd = (opcode & 0x7) as usize;
         let offset = ((opcode >> 6) & 0x1F) as u32;
 
-        let mut result = self.r[rm];
+        let mut result = self.get_reg(rm);
 
         match op {
             0b00 => {
                 // LSL
                 let shift = offset.min(32);
                 self.set_flag_c(if shift != 0 {
-                    let bit = 32 - shift;
+                    let bit = 32u32.wrapping_sub(shift);
                     if bit <= 31 {
                         (result >> bit) & 1 != 0
                     } else {
@@ -2128,19 +2329,19 @@ impl Cpu {
             0b01 => {
                 // LSR #0 means LSR #32
                 let shift = if offset == 0 { 32 } else { offset };
-                self.set_flag_c((result >> (shift - 1)) & 1 != 0);
+                self.set_flag_c((result >> shift.wrapping_sub(1)) & 1 != 0);
                 result = if shift < 32 { result >> shift } else { 0 };
             }
             0b10 => {
                 // ASR #0 means ASR #32
                 let shift = if offset == 0 { 32 } else { offset };
-                self.set_flag_c((result as i32 >> (shift - 1)) & 1 != 0);
+                self.set_flag_c((result as i32 >> shift.wrapping_sub(1)) & 1 != 0);
                 result = ((result as i32) >> shift.min(31)) as u32;
             }
             _ => {}
         }
 
-        self.r[rd] = result;
+        self.set_reg(rd, result);
         self.set_flag_n((result as i32) < 0);
         self.set_flag_z(result == 0);
         self.r[15] = self.r[15].wrapping_add(2);
@@ -2154,16 +2355,16 @@ impl Cpu {
         let rn = ((opcode >> 3) & 0x7) as usize;
         let rd = (opcode & 0x7) as usize;
 
-        let rn_val = self.r[rn];
+        let rn_val = self.get_reg(rn);
         let operand = if i_bit {
             imm3_or_rm as u32
         } else {
-            self.r[imm3_or_rm]
+            self.get_reg(imm3_or_rm)
         };
 
         if !s_bit {
             let (result, overflow) = rn_val.overflowing_add(operand);
-            self.r[rd] = result;
+            self.set_reg(rd, result);
             self.set_flag_n((result as i32) < 0);
             self.set_flag_z(result == 0);
             self.set_flag_c(overflow);
@@ -2173,7 +2374,7 @@ impl Cpu {
             );
         } else {
             let (result, overflow) = rn_val.overflowing_sub(operand);
-            self.r[rd] = result;
+            self.set_reg(rd, result);
             self.set_flag_n((result as i32) < 0);
             self.set_flag_z(result == 0);
             self.set_flag_c(!overflow);
@@ -2193,13 +2394,13 @@ impl Cpu {
         let rd = (opcode & 0x7) as usize;
         let imm = ((opcode >> 3) & 0x7) as u32;
 
-        let rn_val = self.r[rn];
+        let rn_val = self.get_reg(rn);
 
         match op {
             0b00 => {
                 // ADD Rd, Rn, #imm
                 let (result, overflow) = rn_val.overflowing_add(imm);
-                self.r[rd] = result;
+                self.set_reg(rd, result);
                 self.set_flag_n((result as i32) < 0);
                 self.set_flag_z(result == 0);
                 self.set_flag_c(overflow);
@@ -2211,7 +2412,7 @@ impl Cpu {
             0b01 => {
                 // SUB Rd, Rn, #imm
                 let (result, overflow) = rn_val.overflowing_sub(imm);
-                self.r[rd] = result;
+                self.set_reg(rd, result);
                 self.set_flag_n((result as i32) < 0);
                 self.set_flag_z(result == 0);
                 self.set_flag_c(!overflow);
@@ -2221,7 +2422,7 @@ impl Cpu {
                 // ADD Rd, Rn, #imm (with Rn = imm3:Rd)
                 let rn_val = ((opcode >> 3) & 0x7 | (rd as u16 & 0x8)) as u32;
                 let (result, overflow) = rn_val.overflowing_add(imm);
-                self.r[rd] = result;
+                self.set_reg(rd, result);
                 self.set_flag_n((result as i32) < 0);
                 self.set_flag_z(result == 0);
                 self.set_flag_c(overflow);
@@ -2230,7 +2431,7 @@ impl Cpu {
                 // SUB Rd, Rn, #imm (with Rn = imm3:Rd)
                 let rn_val = ((opcode >> 3) & 0x7 | (rd as u16 & 0x8)) as u32;
                 let (result, overflow) = rn_val.overflowing_sub(imm);
-                self.r[rd] = result;
+                self.set_reg(rd, result);
                 self.set_flag_n((result as i32) < 0);
                 self.set_flag_z(result == 0);
                 self.set_flag_c(!overflow);
@@ -2247,12 +2448,12 @@ impl Cpu {
         let rd = ((opcode >> 8) & 0x7) as usize;
         let imm = (opcode & 0xFF) as u32;
 
-        let rd_val = self.r[rd];
+        let rd_val = self.get_reg(rd);
 
         match op {
             0b00 => {
                 // MOV Rd, #imm
-                self.r[rd] = imm;
+                self.set_reg(rd, imm);
                 self.set_flag_n((imm as i32) < 0);
                 self.set_flag_z(imm == 0);
             }
@@ -2267,7 +2468,7 @@ impl Cpu {
             0b10 => {
                 // ADD Rd, #imm
                 let (result, overflow) = rd_val.overflowing_add(imm);
-                self.r[rd] = result;
+                self.set_reg(rd, result);
                 self.set_flag_n((result as i32) < 0);
                 self.set_flag_z(result == 0);
                 self.set_flag_c(overflow);
@@ -2279,7 +2480,7 @@ impl Cpu {
             0b11 => {
                 // SUB Rd, #imm
                 let (result, overflow) = rd_val.overflowing_sub(imm);
-                self.r[rd] = result;
+                self.set_reg(rd, result);
                 self.set_flag_n((result as i32) < 0);
                 self.set_flag_z(result == 0);
                 self.set_flag_c(!overflow);
@@ -2297,21 +2498,21 @@ impl Cpu {
         let rms = ((opcode >> 3) & 0x7) as usize;
         let rds = (opcode & 0x7) as usize;
 
-        let rm_val = self.r[rms];
-        let rd_val = self.r[rds];
+        let rm_val = self.get_reg(rms);
+        let rd_val = self.get_reg(rds);
 
         match op {
             0x0 => {
                 // AND Rd, Rm
                 let result = rd_val & rm_val;
-                self.r[rds] = result;
+                self.set_reg(rds, result);
                 self.set_flag_n((result as i32) < 0);
                 self.set_flag_z(result == 0);
             }
             0x1 => {
                 // EOR Rd, Rm
                 let result = rd_val ^ rm_val;
-                self.r[rds] = result;
+                self.set_reg(rds, result);
                 self.set_flag_n((result as i32) < 0);
                 self.set_flag_z(result == 0);
             }
@@ -2319,7 +2520,7 @@ impl Cpu {
                 // LSL Rd, Rm
                 let shift = (rm_val & 0xFF).min(32);
                 self.set_flag_c(if shift != 0 {
-                    let bit = 32 - shift;
+                    let bit = 32u32.wrapping_sub(shift);
                     if bit <= 31 {
                         (rd_val >> bit) & 1 != 0
                     } else {
@@ -2329,7 +2530,7 @@ impl Cpu {
                     self.get_flag_c()
                 });
                 let result = if shift < 32 { rd_val << shift } else { 0 };
-                self.r[rds] = result;
+                self.set_reg(rds, result);
                 self.set_flag_n((result as i32) < 0);
                 self.set_flag_z(result == 0);
             }
@@ -2337,12 +2538,12 @@ impl Cpu {
                 // LSR Rd, Rm
                 let shift = (rm_val & 0xFF).min(32);
                 self.set_flag_c(if shift != 0 {
-                    (rd_val >> (shift - 1)) & 1 != 0
+                    (rd_val >> shift.wrapping_sub(1)) & 1 != 0
                 } else {
                     self.get_flag_c()
                 });
                 let result = if shift < 32 { rd_val >> shift } else { 0 };
-                self.r[rds] = result;
+                self.set_reg(rds, result);
                 self.set_flag_n((result as i32) < 0);
                 self.set_flag_z(result == 0);
             }
@@ -2350,12 +2551,12 @@ impl Cpu {
                 // ASR Rd, Rm
                 let shift = (rm_val & 0xFF).min(32);
                 self.set_flag_c(if shift != 0 {
-                    (rd_val as i32 >> (shift - 1)) & 1 != 0
+                    (rd_val as i32 >> shift.wrapping_sub(1)) & 1 != 0
                 } else {
                     self.get_flag_c()
                 });
                 let result = ((rd_val as i32) >> shift.min(31)) as u32;
-                self.r[rds] = result;
+                self.set_reg(rds, result);
                 self.set_flag_n((result as i32) < 0);
                 self.set_flag_z(result == 0);
             }
@@ -2364,7 +2565,7 @@ impl Cpu {
                 let c = if self.get_flag_c() { 1 } else { 0 };
                 let (result1, overflow1) = rd_val.overflowing_add(rm_val);
                 let (result, overflow2) = result1.overflowing_add(c);
-                self.r[rds] = result;
+                self.set_reg(rds, result);
                 self.set_flag_n((result as i32) < 0);
                 self.set_flag_z(result == 0);
                 self.set_flag_c(overflow1 || overflow2);
@@ -2376,21 +2577,25 @@ impl Cpu {
                 let not_c = if self.get_flag_c() { 0u32 } else { 1u32 };
                 let (result1, overflow1) = rd_val.overflowing_sub(rm_val);
                 let (result, overflow2) = result1.overflowing_sub(not_c);
-                self.r[rds] = result;
+                self.set_reg(rds, result);
                 self.set_flag_n((result as i32) < 0);
                 self.set_flag_z(result == 0);
                 self.set_flag_c(!(overflow1 || overflow2));
             }
             0x7 => {
-                // ROR Rd, Rm
-                let shift = (rm_val & 0xFF) % 32;
+                // ROR Rd, Rm; a nonzero multiple of 32 leaves Rd alone but
+                // still sets C from bit 31
+                let amount = rm_val & 0xFF;
+                let shift = amount % 32;
                 self.set_flag_c(if shift != 0 {
-                    (rd_val >> (shift - 1)) & 1 != 0
+                    (rd_val >> shift.wrapping_sub(1)) & 1 != 0
+                } else if amount != 0 {
+                    (rd_val >> 31) != 0
                 } else {
                     self.get_flag_c()
                 });
                 let result = rd_val.rotate_right(shift);
-                self.r[rds] = result;
+                self.set_reg(rds, result);
                 self.set_flag_n((result as i32) < 0);
                 self.set_flag_z(result == 0);
             }
@@ -2403,7 +2608,7 @@ impl Cpu {
             0x9 => {
                 // NEG Rd, Rm
                 let (result, overflow) = 0u32.overflowing_sub(rm_val);
-                self.r[rds] = result;
+                self.set_reg(rds, result);
                 self.set_flag_n((result as i32) < 0);
                 self.set_flag_z(result == 0);
                 self.set_flag_c(!overflow);
@@ -2427,28 +2632,30 @@ impl Cpu {
             0xC => {
                 // ORR Rd, Rm
                 let result = rd_val | rm_val;
-                self.r[rds] = result;
+                self.set_reg(rds, result);
                 self.set_flag_n((result as i32) < 0);
                 self.set_flag_z(result == 0);
             }
             0xD => {
-                // MUL Rd, Rm
+                // MUL Rd, Rm; the multiplier runs on the old Rd
                 let result = rd_val.wrapping_mul(rm_val);
-                self.r[rds] = result;
+                self.set_reg(rds, result);
                 self.set_flag_n((result as i32) < 0);
                 self.set_flag_z(result == 0);
+                self.r[15] = self.r[15].wrapping_add(2);
+                return Self::multiply_cycles(rd_val, true).wrapping_add(1);
             }
             0xE => {
                 // BIC Rd, Rm
                 let result = rd_val & !rm_val;
-                self.r[rds] = result;
+                self.set_reg(rds, result);
                 self.set_flag_n((result as i32) < 0);
                 self.set_flag_z(result == 0);
             }
             0xF => {
                 // MVN Rd, Rm
                 let result = !rm_val;
-                self.r[rds] = result;
+                self.set_reg(rds, result);
                 self.set_flag_n((result as i32) < 0);
                 self.set_flag_z(result == 0);
             }
@@ -2456,7 +2663,12 @@ impl Cpu {
         }
 
         self.r[15] = self.r[15].wrapping_add(2);
-        1
+        // LSL, LSR, ASR and ROR by register add an internal cycle
+        if matches!(op, 0x2 | 0x3 | 0x4 | 0x7) {
+            2
+        } else {
+            1
+        }
     }
 
     fn thumb_hi_reg_ops(&mut self, opcode: u16, instruction_pc: u32) -> u32 {
@@ -2470,18 +2682,18 @@ impl Cpu {
         let rs_val = if rs == 15 {
             (instruction_pc.wrapping_add(4)) | 1
         } else {
-            self.r[rs]
+            self.get_reg(rs)
         };
 
         match op {
             0b00 => {
-                let rd_val = self.r[rd];
+                let rd_val = self.get_reg(rd);
                 let (result, overflow) = rd_val.overflowing_add(rs_val);
                 if rd == 15 {
                     self.set_pc(result);
                     return 2;
                 }
-                self.r[rd] = result;
+                self.set_reg(rd, result);
                 if !hd && !hsr {
                     self.set_flag_n((result as i32) < 0);
                     self.set_flag_z(result == 0);
@@ -2493,7 +2705,7 @@ impl Cpu {
                 }
             }
             0b01 => {
-                let rd_val = self.r[rd];
+                let rd_val = self.get_reg(rd);
                 let (result, overflow) = rd_val.overflowing_sub(rs_val);
                 self.set_flag_n((result as i32) < 0);
                 self.set_flag_z(result == 0);
@@ -2505,7 +2717,7 @@ impl Cpu {
                     self.set_pc(rs_val);
                     return 2;
                 }
-                self.r[rd] = rs_val;
+                self.set_reg(rd, rs_val);
                 if !hd && !hsr {
                     self.set_flag_n((rs_val as i32) < 0);
                     self.set_flag_z(rs_val == 0);
@@ -2530,12 +2742,12 @@ impl Cpu {
         instruction_pc: u32,
     ) -> u32 {
         let rd = ((opcode >> 8) & 0x7) as usize;
-        let imm = ((opcode & 0xFF) * 4) as u32;
+        let imm = ((opcode & 0xFF) as u32) << 2;
 
-        let pc = (instruction_pc + 4) & !0x3;
+        let pc = instruction_pc.wrapping_add(4) & !0x3;
         let addr = pc.wrapping_add(imm);
 
-        self.r[rd] = mem.read_word(addr);
+        self.set_reg(rd, mem.read_word(addr));
         self.r[15] = self.r[15].wrapping_add(2);
         2
     }
@@ -2550,12 +2762,12 @@ impl Cpu {
         let rb = ((opcode >> 3) & 0x7) as usize;
         let rd = (opcode & 0x7) as usize;
 
-        let addr = self.r[rb].wrapping_add(self.r[ro]);
+        let addr = self.get_reg(rb).wrapping_add(self.get_reg(ro));
 
         if byte {
-            self.r[rd] = mem.read_byte(addr) as u32;
+            self.set_reg(rd, mem.read_byte(addr) as u32);
         } else {
-            self.r[rd] = mem.read_word(addr);
+            self.set_reg(rd, mem.read_word(addr));
         }
 
         self.r[15] = self.r[15].wrapping_add(2);
@@ -2566,8 +2778,8 @@ impl Cpu {
         let ro = ((opcode >> 6) & 0x7) as usize;
         let rb = ((opcode >> 3) & 0x7) as usize;
         let rd = (opcode & 0x7) as usize;
-        let addr = self.r[rb].wrapping_add(self.r[ro]);
-        mem.write_word(addr, self.r[rd]);
+        let addr = self.get_reg(rb).wrapping_add(self.get_reg(ro));
+        mem.write_word(addr, self.get_reg(rd));
         self.r[15] = self.r[15].wrapping_add(2);
         2
     }
@@ -2576,8 +2788,8 @@ impl Cpu {
         let ro = ((opcode >> 6) & 0x7) as usize;
         let rb = ((opcode >> 3) & 0x7) as usize;
         let rd = (opcode & 0x7) as usize;
-        let addr = self.r[rb].wrapping_add(self.r[ro]);
-        mem.write_byte(addr, self.r[rd] as u8);
+        let addr = self.get_reg(rb).wrapping_add(self.get_reg(ro));
+        mem.write_byte(addr, self.get_reg(rd) as u8);
         self.r[15] = self.r[15].wrapping_add(2);
         2
     }
@@ -2586,8 +2798,8 @@ impl Cpu {
         let ro = ((opcode >> 6) & 0x7) as usize;
         let rb = ((opcode >> 3) & 0x7) as usize;
         let rd = (opcode & 0x7) as usize;
-        let addr = self.r[rb].wrapping_add(self.r[ro]);
-        mem.write_half(addr, self.r[rd] as u16);
+        let addr = self.get_reg(rb).wrapping_add(self.get_reg(ro));
+        mem.write_half(addr, self.get_reg(rd) as u16);
         self.r[15] = self.r[15].wrapping_add(2);
         2
     }
@@ -2596,8 +2808,8 @@ impl Cpu {
         let ro = ((opcode >> 6) & 0x7) as usize;
         let rb = ((opcode >> 3) & 0x7) as usize;
         let rd = (opcode & 0x7) as usize;
-        let addr = self.r[rb].wrapping_add(self.r[ro]);
-        self.r[rd] = mem.read_word(addr);
+        let addr = self.get_reg(rb).wrapping_add(self.get_reg(ro));
+        self.set_reg(rd, mem.read_word(addr));
         self.r[15] = self.r[15].wrapping_add(2);
         2
     }
@@ -2606,8 +2818,8 @@ impl Cpu {
         let ro = ((opcode >> 6) & 0x7) as usize;
         let rb = ((opcode >> 3) & 0x7) as usize;
         let rd = (opcode & 0x7) as usize;
-        let addr = self.r[rb].wrapping_add(self.r[ro]);
-        self.r[rd] = mem.read_byte(addr) as u32;
+        let addr = self.get_reg(rb).wrapping_add(self.get_reg(ro));
+        self.set_reg(rd, mem.read_byte(addr) as u32);
         self.r[15] = self.r[15].wrapping_add(2);
         3
     }
@@ -2616,9 +2828,9 @@ impl Cpu {
         let ro = ((opcode >> 6) & 0x7) as usize;
         let rb = ((opcode >> 3) & 0x7) as usize;
         let rd = (opcode & 0x7) as usize;
-        let addr = self.r[rb].wrapping_add(self.r[ro]);
+        let addr = self.get_reg(rb).wrapping_add(self.get_reg(ro));
         let val = mem.read_byte(addr) as i8 as u32;
-        self.r[rd] = val;
+        self.set_reg(rd, val);
         self.r[15] = self.r[15].wrapping_add(2);
         3
     }
@@ -2627,9 +2839,14 @@ impl Cpu {
         let ro = ((opcode >> 6) & 0x7) as usize;
         let rb = ((opcode >> 3) & 0x7) as usize;
         let rd = (opcode & 0x7) as usize;
-        let addr = self.r[rb].wrapping_add(self.r[ro]);
-        let val = mem.read_half(addr) as i16 as u32;
-        self.r[rd] = val;
+        let addr = self.get_reg(rb).wrapping_add(self.get_reg(ro));
+        // A misaligned LDRSH loads the addressed byte and sign-extends it
+        let val = if addr & 1 != 0 {
+            mem.read_byte(addr) as i8 as u32
+        } else {
+            mem.read_half(addr) as i16 as u32
+        };
+        self.set_reg(rd, val);
         self.r[15] = self.r[15].wrapping_add(2);
         3
     }
@@ -2638,8 +2855,8 @@ impl Cpu {
         let ro = ((opcode >> 6) & 0x7) as usize;
         let rb = ((opcode >> 3) & 0x7) as usize;
         let rd = (opcode & 0x7) as usize;
-        let addr = self.r[rb].wrapping_add(self.r[ro]);
-        self.r[rd] = mem.read_half(addr) as u32;
+        let addr = self.get_reg(rb).wrapping_add(self.get_reg(ro));
+        self.set_reg(rd, mem.read_half_rotated(addr));
         self.r[15] = self.r[15].wrapping_add(2);
         3
     }
@@ -2654,12 +2871,12 @@ impl Cpu {
         let offset = if byte {
             ((opcode >> 6) & 0x1F) as u32
         } else {
-            (((opcode >> 6) & 0x1F) * 4) as u32
+            (((opcode >> 6) & 0x1F) as u32) << 2
         };
         let rb = ((opcode >> 3) & 0x7) as usize;
         let rd = (opcode & 0x7) as usize;
 
-        let addr = self.r[rb].wrapping_add(offset);
+        let addr = self.get_reg(rb).wrapping_add(offset);
 
         if load {
             let val = if byte {
@@ -2673,13 +2890,13 @@ impl Cpu {
                 // ARM architecture: loaded value should have bit 0 cleared
                 self.set_pc(val & 0xFFFFFFFE);
             } else {
-                self.r[rd] = val;
+                self.set_reg(rd, val);
             }
         } else {
             if byte {
-                mem.write_byte(addr, self.r[rd] as u8);
+                mem.write_byte(addr, self.get_reg(rd) as u8);
             } else {
-                mem.write_word(addr, self.r[rd]);
+                mem.write_word(addr, self.get_reg(rd));
             }
         }
 
@@ -2693,16 +2910,16 @@ impl Cpu {
         mem: &mut super::Memory,
         load: bool,
     ) -> u32 {
-        let offset = (((opcode >> 6) & 0x1F) * 2) as u32;
+        let offset = (((opcode >> 6) & 0x1F) as u32) << 1;
         let rb = ((opcode >> 3) & 0x7) as usize;
         let rd = (opcode & 0x7) as usize;
 
-        let addr = self.r[rb].wrapping_add(offset);
+        let addr = self.get_reg(rb).wrapping_add(offset);
 
         if load {
-            self.r[rd] = mem.read_half(addr) as u32;
+            self.set_reg(rd, mem.read_half_rotated(addr));
         } else {
-            mem.write_half(addr, self.r[rd] as u16);
+            mem.write_half(addr, self.get_reg(rd) as u16);
         }
 
         self.r[15] = self.r[15].wrapping_add(2);
@@ -2711,14 +2928,14 @@ impl Cpu {
 
     fn thumb_load_store_sp_rel(&mut self, opcode: u16, mem: &mut super::Memory, load: bool) -> u32 {
         let rd = ((opcode >> 8) & 0x7) as usize;
-        let offset = ((opcode & 0xFF) * 4) as u32;
+        let offset = ((opcode & 0xFF) as u32) << 2;
 
         let addr = self.r[13].wrapping_add(offset);
 
         if load {
-            self.r[rd] = mem.read_word(addr);
+            self.set_reg(rd, mem.read_word(addr));
         } else {
-            mem.write_word(addr, self.r[rd]);
+            mem.write_word(addr, self.get_reg(rd));
         }
 
         self.r[15] = self.r[15].wrapping_add(2);
@@ -2727,23 +2944,23 @@ impl Cpu {
 
     fn thumb_load_addr(&mut self, opcode: u16, instruction_pc: u32) -> u32 {
         let rd = ((opcode >> 8) & 0x7) as usize;
-        let offset = ((opcode & 0xFF) * 4) as u32;
+        let offset = ((opcode & 0xFF) as u32) << 2;
 
         let sp = ((opcode >> 11) & 1) != 0;
 
         let base = if sp {
             self.r[13]
         } else {
-            (instruction_pc + 4) & !0x3
+            instruction_pc.wrapping_add(4) & !0x3
         };
-        self.r[rd] = base.wrapping_add(offset);
+        self.set_reg(rd, base.wrapping_add(offset));
 
         self.r[15] = self.r[15].wrapping_add(2);
         1
     }
 
     fn thumb_add_sp(&mut self, opcode: u16) -> u32 {
-        let offset = (((opcode & 0x7F) * 4) as i32) as u32;
+        let offset = ((opcode & 0x7F) as u32) << 2;
         let sign = ((opcode >> 7) & 1) != 0;
 
         if sign {
@@ -2766,16 +2983,15 @@ impl Cpu {
             // POP (load from stack)
             for i in 0..8 {
                 if reg_list & (1 << i) != 0 {
-                    self.r[i] = mem.read_word(addr);
+                    self.set_reg(i, mem.read_word(addr));
                     addr = addr.wrapping_add(4);
                 }
             }
             if pc_lr {
                 let val = mem.read_word(addr);
-                // ARMv4T: POP {pc} does NOT interwork
-                // CPSR.T bit is unchanged - stay in current mode
-                self.r[15] = val;
-                self.pipeline_loaded = false;
+                // ARMv4T: POP {pc} does NOT interwork (that's ARMv5); only BX
+                // changes state. CPSR.T stays set and bit 0 is ignored.
+                self.set_pc(val);
                 addr = addr.wrapping_add(4);
             }
             self.r[13] = addr;
@@ -2787,7 +3003,7 @@ impl Cpu {
             for i in (0..8).rev() {
                 if reg_list & (1 << i) != 0 {
                     addr = addr.wrapping_sub(4);
-                    mem.write_word(addr, self.r[i]);
+                    mem.write_word(addr, self.get_reg(i));
                 }
             }
             self.r[13] = addr;
@@ -2796,7 +3012,9 @@ impl Cpu {
         if !(load && pc_lr) {
             self.r[15] = self.r[15].wrapping_add(2);
         }
-        (reg_list.count_ones() + if pc_lr { 1 } else { 0 }) as u32
+        reg_list
+            .count_ones()
+            .wrapping_add(if pc_lr { 1 } else { 0 })
     }
 
     fn thumb_load_store_multiple(
@@ -2808,27 +3026,27 @@ impl Cpu {
         let rb = ((opcode >> 8) & 0x7) as usize;
         let reg_list = opcode & 0xFF;
 
-        let mut addr = self.r[rb];
+        let mut addr = self.get_reg(rb);
 
         if load {
             for i in 0..8 {
                 if reg_list & (1 << i) != 0 {
-                    self.r[i] = mem.read_word(addr);
+                    self.set_reg(i, mem.read_word(addr));
                     addr = addr.wrapping_add(4);
                 }
             }
             if reg_list & (1 << rb) == 0 {
-                self.r[rb] = addr;
+                self.set_reg(rb, addr);
             }
         } else {
             for i in 0..8 {
                 if reg_list & (1 << i) != 0 {
-                    mem.write_word(addr, self.r[i]);
+                    mem.write_word(addr, self.get_reg(i));
                     addr = addr.wrapping_add(4);
                 }
             }
             if reg_list & (1 << rb) == 0 {
-                self.r[rb] = addr;
+                self.set_reg(rb, addr);
             }
         }
 
@@ -2838,7 +3056,7 @@ impl Cpu {
 
     fn thumb_branch_cond(&mut self, opcode: u16, instruction_pc: u32) -> u32 {
         let cond = ((opcode >> 8) & 0xF) as usize;
-        let offset = ((opcode as i8) as i32 * 2) as u32;
+        let offset = (((opcode as i8) as i32) << 1) as u32;
 
         if self.check_condition(cond) {
             let target = instruction_pc.wrapping_add(offset).wrapping_add(4);
@@ -2880,29 +3098,24 @@ impl Cpu {
     ) -> u32 {
         let swi_num = (opcode & 0xFF) as u32;
 
+        if self.try_semihost(swi_num, true, mem) {
+            self.r[15] = instruction_pc.wrapping_add(2);
+            self.pc_written = true;
+            self.pipeline_loaded = false;
+            return 2 + 2;
+        }
+
         if mem.swi_log_enabled && mem.swi_log.len() < 100_000 {
             mem.swi_log.push(swi_num);
         }
-        mem.thumb_swi_count += 1;
+        mem.thumb_swi_count = mem.thumb_swi_count.wrapping_add(1);
 
         if mem.use_real_bios {
-            if swi_num == 0x04 || swi_num == 0x05 {
-                mem.interrupt.ie |= super::mem::Interrupt::VBLANK;
-                mem.interrupt.if_raw &= !super::mem::Interrupt::VBLANK;
-                mem.interrupt.ime = true;
-
-                mem.halt_pending = true;
-                return 2 + 2;
-            }
-            let old_cpsr = self.cpsr;
-            let ret_addr = instruction_pc.wrapping_add(2);
-            self.set_mode(Mode::Supervisor);
-            self.banked_spsr[self.mode_index(Mode::Supervisor)] = old_cpsr;
-            self.r[14] = ret_addr;
-            self.cpsr |= 0x80;
-            self.set_thumb_mode(false);
-            self.set_pc(0x00000008);
-            return 2 + 2;
+            return self.enter_exception(
+                Mode::Supervisor,
+                0x0000_0008,
+                instruction_pc.wrapping_add(2),
+            );
         }
 
         let bios_return = match swi_num {
@@ -2913,8 +3126,9 @@ impl Cpu {
 
         match swi_num {
             0x00 => {
+                let entry = crate::multiboot::soft_reset_entry(mem.read_byte(0x0300_7FFA));
                 self.reset();
-                self.set_pc(0x08000000);
+                self.set_pc(entry);
                 return 2 + 2;
             }
             0x01 => {
@@ -2938,39 +3152,27 @@ impl Cpu {
                     mem.clear_io();
                 }
             }
-            0x02 | 0x03 => {
+            0x02 => {
                 mem.halt_pending = true;
             }
+            0x03 => {
+                mem.stop_pending = true;
+            }
             0x04 => {
-                mem.interrupt.ime = true;
-                mem.interrupt.ie |= super::mem::Interrupt::VBLANK;
-                mem.halt_pending = true;
+                self.begin_intr_wait(self.r[0] != 0, self.r[1] as u16, mem);
             }
             0x05 => {
-                mem.interrupt.ime = true;
-                mem.interrupt.ie |= super::mem::Interrupt::VBLANK;
-                // BIOS maintains VBlank counter at 0x03007FF8
-                {
-                    let iwram = mem.iwram_mut();
-                    let a = 0x7FF8;
-                    let lo = u16::from_le_bytes([iwram[a], iwram[a + 1]]);
-                    let hi = u16::from_le_bytes([iwram[a + 2], iwram[a + 3]]);
-                    let c = ((hi as u32) << 16 | lo as u32).wrapping_add(1);
-                    iwram[a] = c as u8;
-                    iwram[a + 1] = (c >> 8) as u8;
-                    iwram[a + 2] = (c >> 16) as u8;
-                    iwram[a + 3] = (c >> 24) as u8;
-                }
-                mem.halt_pending = true;
+                self.begin_intr_wait(true, super::mem::Interrupt::VBLANK.bits(), mem);
             }
             0x06 => {
-                let r0 = self.r[0] as i32;
-                let r1 = self.r[1] as i32;
-                if r1 != 0 {
-                    let q = r0.wrapping_div(r1);
+                // Widened so i32::MIN / -1 cannot overflow; only a zero
+                // divisor fails
+                let r0 = self.r[0] as i32 as i64;
+                let r1 = self.r[1] as i32 as i64;
+                if let (Some(q), Some(rem)) = (r0.checked_div(r1), r0.checked_rem(r1)) {
                     self.r[0] = q as u32;
-                    self.r[1] = r0.wrapping_rem(r1) as u32;
-                    self.r[3] = q.wrapping_abs() as u32;
+                    self.r[1] = rem as u32;
+                    self.r[3] = (q as i32).wrapping_abs() as u32;
                 } else {
                     let v = if r0 >= 0 { 0x7FFFFFFF } else { 0x80000000 };
                     self.r[0] = v;
@@ -2979,13 +3181,12 @@ impl Cpu {
                 }
             }
             0x07 => {
-                let r0 = self.r[1] as i32;
-                let r1 = self.r[0] as i32;
-                if r1 != 0 {
-                    let q = r0.wrapping_div(r1);
+                let r0 = self.r[1] as i32 as i64;
+                let r1 = self.r[0] as i32 as i64;
+                if let (Some(q), Some(rem)) = (r0.checked_div(r1), r0.checked_rem(r1)) {
                     self.r[0] = q as u32;
-                    self.r[1] = r0.wrapping_rem(r1) as u32;
-                    self.r[3] = q.wrapping_abs() as u32;
+                    self.r[1] = rem as u32;
+                    self.r[3] = (q as i32).wrapping_abs() as u32;
                 } else {
                     let v = if r0 >= 0 { 0x7FFFFFFF } else { 0x80000000 };
                     self.r[0] = v;
@@ -3010,28 +3211,16 @@ impl Cpu {
                     if is_32 {
                         let v = mem.read_word(src);
                         for i in 0..count {
-                            mem.write_word(dst + i * 4, v);
+                            mem.write_word(dst.wrapping_add(i << 2), v);
                         }
                     } else {
                         let v = mem.read_half(src);
                         for i in 0..count {
-                            mem.write_half(dst + i * 2, v);
+                            mem.write_half(dst.wrapping_add(i << 1), v);
                         }
                     }
                 } else {
-                    let mut s = src;
-                    let mut d = dst;
-                    for _ in 0..count {
-                        if is_32 {
-                            let v = mem.read_word(s);
-                            mem.write_word(d, v);
-                        } else {
-                            let v = mem.read_half(s);
-                            mem.write_half(d, v);
-                        }
-                        s += if is_32 { 4 } else { 2 };
-                        d += if is_32 { 4 } else { 2 };
-                    }
+                    mem.copy_block(src, dst, count, if is_32 { 4 } else { 2 });
                 }
             }
             0x0C => {
@@ -3043,17 +3232,10 @@ impl Cpu {
                 if fill {
                     let v = mem.read_word(src);
                     for i in 0..count {
-                        mem.write_word(dst + i * 4, v);
+                        mem.write_word(dst.wrapping_add(i << 2), v);
                     }
                 } else {
-                    let mut s = src;
-                    let mut d = dst;
-                    for _ in 0..count {
-                        let v = mem.read_word(s);
-                        mem.write_word(d, v);
-                        s += 4;
-                        d += 4;
-                    }
+                    mem.copy_block(src, dst, count, 4);
                 }
             }
             0x10 | 0x11 => {
@@ -3063,34 +3245,34 @@ impl Cpu {
                 let header = mem.read_word(src);
                 let size = header >> 8;
                 if (header & 0xFF) == 0x10 {
-                    let mut sp = src + 4;
+                    let mut sp = src.wrapping_add(4);
                     let mut dp = dst;
                     let mut written = 0u32;
                     while written < size {
                         let flags = mem.read_byte(sp);
-                        sp += 1;
+                        sp = sp.wrapping_add(1);
                         for bit in 0..8 {
                             if written >= size {
                                 break;
                             }
                             if (flags & (1 << bit)) != 0 {
                                 let b0 = mem.read_byte(sp) as u32;
-                                let b1 = mem.read_byte(sp + 1) as u32;
+                                let b1 = mem.read_byte(sp.wrapping_add(1)) as u32;
                                 let len = if (b0 >> 4) != 0 {
-                                    sp += 2;
-                                    (b0 >> 4) as usize + 3
+                                    sp = sp.wrapping_add(2);
+                                    ((b0 >> 4) as usize).wrapping_add(3)
                                 } else {
-                                    let b2 = mem.read_byte(sp + 2) as u32;
-                                    sp += 3;
-                                    b2 as usize + 3
+                                    let b2 = mem.read_byte(sp.wrapping_add(2)) as u32;
+                                    sp = sp.wrapping_add(3);
+                                    (b2 as usize).wrapping_add(3)
                                 };
-                                let disp = (((b0 & 0xF) as usize) << 8) | (b1 & 0xFF) as usize;
-                                let lb = dp as usize - disp - 1;
+                                let disp = ((b0 & 0xF) << 8) | b1;
+                                let lb = dp.wrapping_sub(disp).wrapping_sub(1);
                                 for i in 0..len {
-                                    let b = mem.read_byte((lb + i) as u32);
+                                    let b = mem.read_byte(lb.wrapping_add(i as u32));
                                     if vram {
                                         let aligned = dp & !1;
-                                        let shift = (dp & 1) * 8;
+                                        let shift = (dp & 1) << 3;
                                         let old = mem.read_half(aligned);
                                         mem.write_half(
                                             aligned,
@@ -3099,18 +3281,18 @@ impl Cpu {
                                     } else {
                                         mem.write_byte(dp, b);
                                     }
-                                    dp += 1;
-                                    written += 1;
+                                    dp = dp.wrapping_add(1);
+                                    written = written.wrapping_add(1);
                                     if written >= size {
                                         break;
                                     }
                                 }
                             } else {
                                 let b = mem.read_byte(sp);
-                                sp += 1;
+                                sp = sp.wrapping_add(1);
                                 if vram {
                                     let aligned = dp & !1;
-                                    let shift = (dp & 1) * 8;
+                                    let shift = (dp & 1) << 3;
                                     let old = mem.read_half(aligned);
                                     mem.write_half(
                                         aligned,
@@ -3119,8 +3301,8 @@ impl Cpu {
                                 } else {
                                     mem.write_byte(dp, b);
                                 }
-                                dp += 1;
-                                written += 1;
+                                dp = dp.wrapping_add(1);
+                                written = written.wrapping_add(1);
                             }
                         }
                     }
@@ -3136,20 +3318,25 @@ impl Cpu {
                 let header = mem.read_word(src);
                 let size = header >> 8;
                 if (header & 0xFF) == 0x30 {
-                    let mut sp = src + 4;
+                    let mut sp = src.wrapping_add(4);
                     let mut dp = dst;
                     let mut written = 0u32;
                     while written < size {
                         let ctrl = mem.read_byte(sp) as u32;
-                        sp += 1;
-                        let len = (ctrl & 0x7F) as usize;
+                        sp = sp.wrapping_add(1);
+                        // Repeated runs are stored minus 3 and literal runs minus 1
+                        let len = ((ctrl & 0x7F) as usize).wrapping_add(if ctrl & 0x80 != 0 {
+                            3
+                        } else {
+                            1
+                        });
                         if (ctrl & 0x80) != 0 {
                             let val = mem.read_byte(sp);
-                            sp += 1;
+                            sp = sp.wrapping_add(1);
                             for _ in 0..len {
                                 if vram {
                                     let aligned = dp & !1;
-                                    let shift = (dp & 1) * 8;
+                                    let shift = (dp & 1) << 3;
                                     let old = mem.read_half(aligned);
                                     mem.write_half(
                                         aligned,
@@ -3158,8 +3345,8 @@ impl Cpu {
                                 } else {
                                     mem.write_byte(dp, val);
                                 }
-                                dp += 1;
-                                written += 1;
+                                dp = dp.wrapping_add(1);
+                                written = written.wrapping_add(1);
                                 if written >= size {
                                     break;
                                 }
@@ -3167,10 +3354,10 @@ impl Cpu {
                         } else {
                             for _ in 0..len {
                                 let val = mem.read_byte(sp);
-                                sp += 1;
+                                sp = sp.wrapping_add(1);
                                 if vram {
                                     let aligned = dp & !1;
-                                    let shift = (dp & 1) * 8;
+                                    let shift = (dp & 1) << 3;
                                     let old = mem.read_half(aligned);
                                     mem.write_half(
                                         aligned,
@@ -3179,8 +3366,8 @@ impl Cpu {
                                 } else {
                                     mem.write_byte(dp, val);
                                 }
-                                dp += 1;
-                                written += 1;
+                                dp = dp.wrapping_add(1);
+                                written = written.wrapping_add(1);
                                 if written >= size {
                                     break;
                                 }
@@ -3192,6 +3379,10 @@ impl Cpu {
                     self.r[3] = written;
                 }
             }
+            0x25 => {
+                // MultiBoot: no slave ever answers the link handshake
+                self.r[0] = 1;
+            }
             _ => {}
         }
 
@@ -3237,7 +3428,7 @@ impl Cpu {
             offset = offset | (-0x400000_i32);
         }
 
-        self.r[14] = (instruction_pc + 2) | 1;
+        self.r[14] = instruction_pc.wrapping_add(2) | 1;
 
         let target = instruction_pc.wrapping_add(2).wrapping_add(offset as u32);
 
@@ -3252,6 +3443,40 @@ impl Cpu {
     }
 }
 
+impl Cpu {
+    pub(crate) fn save_state(&self, w: &mut StateWriter) {
+        w.write_u32s(&self.r);
+        self.bank.save_state(w);
+        w.write_u32(self.cpsr);
+        w.write_u32s(&self.pipeline);
+        w.write_u32s(&self.pipeline_pc);
+        w.write_bool(self.pipeline_loaded);
+        w.write_bool(self.pc_written);
+        w.write_bool(self.halted);
+        w.write_bool(self.stopped);
+        w.write_bool(self.intr_wait.is_some());
+        w.write_u16(self.intr_wait.unwrap_or(0));
+        w.write_u32(self.current_arm_pc);
+    }
+
+    pub(crate) fn load_state(&mut self, r: &mut StateReader) -> Result<(), StateError> {
+        r.read_u32s(&mut self.r)?;
+        self.bank.load_state(r)?;
+        self.cpsr = r.read_u32()?;
+        r.read_u32s(&mut self.pipeline)?;
+        r.read_u32s(&mut self.pipeline_pc)?;
+        self.pipeline_loaded = r.read_bool()?;
+        self.pc_written = r.read_bool()?;
+        self.halted = r.read_bool()?;
+        self.stopped = r.read_bool()?;
+        let waiting = r.read_bool()?;
+        let mask = r.read_u16()?;
+        self.intr_wait = waiting.then_some(mask);
+        self.current_arm_pc = r.read_u32()?;
+        Ok(())
+    }
+}
+
 impl std::fmt::Debug for Cpu {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Cpu")
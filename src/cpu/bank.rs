@@ -0,0 +1,138 @@
+//! Banked Register Storage
+//!
+//! ARM7TDMI modes share R0-R7 and PC but not the rest: FIQ has private
+//! R8-R14, the other exception modes private R13-R14, and every exception
+//! mode its own SPSR. User and System share one set. [`RegisterBank`] holds
+//! each mode's copies; the CPU keeps the current mode's registers live in
+//! its register file and exchanges them through [`RegisterBank::switch`], so
+//! the stored copies of the current mode are stale until the next switch.
+
+use super::Mode;
+use crate::savestate::{StateError, StateReader, StateWriter};
+
+/// Slot of a mode's R13, R14 and SPSR (FIQ, IRQ, SVC, ABT, UND, USR/SYS)
+fn slot(mode: Mode) -> usize {
+    match mode {
+        Mode::Fiq => 0,
+        Mode::Irq => 1,
+        Mode::Supervisor => 2,
+        Mode::Abort => 3,
+        Mode::Undefined => 4,
+        Mode::User | Mode::System => 5,
+    }
+}
+
+/// Per-mode copies of R8-R14 and SPSR
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegisterBank {
+    /// R8-R12 of every mode but FIQ
+    user_r8_12: [u32; 5],
+    fiq_r8_12: [u32; 5],
+    sp: [u32; 6],
+    lr: [u32; 6],
+    /// Unused for User/System, which have no SPSR
+    spsr: [u32; 6],
+}
+
+impl RegisterBank {
+    pub fn new() -> Self {
+        Self {
+            user_r8_12: [0; 5],
+            fiq_r8_12: [0; 5],
+            sp: [0; 6],
+            lr: [0; 6],
+            spsr: [0; 6],
+        }
+    }
+
+    /// True if register `n` is a banked register (R8-R14)
+    pub fn is_banked(n: usize) -> bool {
+        (8..=14).contains(&n)
+    }
+
+    /// True if modes `a` and `b` see the same copy of register `n`
+    pub fn shares(a: Mode, b: Mode, n: usize) -> bool {
+        match n {
+            8..=12 => (a == Mode::Fiq) == (b == Mode::Fiq),
+            13 | 14 => slot(a) == slot(b),
+            _ => true,
+        }
+    }
+
+    /// Stored copy of banked register `n` (8-14) for `mode`; any other `n`
+    /// reads 0
+    pub fn read(&self, mode: Mode, n: usize) -> u32 {
+        let stored = match n {
+            8..=12 if mode == Mode::Fiq => self.fiq_r8_12.get(n.wrapping_sub(8)),
+            8..=12 => self.user_r8_12.get(n.wrapping_sub(8)),
+            13 => self.sp.get(slot(mode)),
+            14 => self.lr.get(slot(mode)),
+            _ => None,
+        };
+        stored.copied().unwrap_or(0)
+    }
+
+    /// Store banked register `n` (8-14) for `mode`; any other `n` is ignored
+    pub fn write(&mut self, mode: Mode, n: usize, val: u32) {
+        let stored = match n {
+            8..=12 if mode == Mode::Fiq => self.fiq_r8_12.get_mut(n.wrapping_sub(8)),
+            8..=12 => self.user_r8_12.get_mut(n.wrapping_sub(8)),
+            13 => self.sp.get_mut(slot(mode)),
+            14 => self.lr.get_mut(slot(mode)),
+            _ => None,
+        };
+        if let Some(stored) = stored {
+            *stored = val;
+        }
+    }
+
+    /// SPSR of `mode`, or None for User and System
+    pub fn spsr(&self, mode: Mode) -> Option<u32> {
+        match mode {
+            Mode::User | Mode::System => None,
+            _ => self.spsr.get(slot(mode)).copied(),
+        }
+    }
+
+    /// Set the SPSR of `mode`; ignored for User and System
+    pub fn set_spsr(&mut self, mode: Mode, val: u32) {
+        if self.spsr(mode).is_some() {
+            if let Some(spsr) = self.spsr.get_mut(slot(mode)) {
+                *spsr = val;
+            }
+        }
+    }
+
+    /// Store the live R8-R14 of `from` and load those of `to` into `r`
+    pub fn switch(&mut self, from: Mode, to: Mode, r: &mut [u32; 16]) {
+        for (n, live) in r.iter_mut().enumerate().take(15).skip(8) {
+            if !Self::shares(from, to, n) {
+                self.write(from, n, *live);
+                *live = self.read(to, n);
+            }
+        }
+    }
+
+    pub(crate) fn save_state(&self, w: &mut StateWriter) {
+        w.write_u32s(&self.user_r8_12);
+        w.write_u32s(&self.fiq_r8_12);
+        w.write_u32s(&self.sp);
+        w.write_u32s(&self.lr);
+        w.write_u32s(&self.spsr);
+    }
+
+    pub(crate) fn load_state(&mut self, r: &mut StateReader) -> Result<(), StateError> {
+        r.read_u32s(&mut self.user_r8_12)?;
+        r.read_u32s(&mut self.fiq_r8_12)?;
+        r.read_u32s(&mut self.sp)?;
+        r.read_u32s(&mut self.lr)?;
+        r.read_u32s(&mut self.spsr)?;
+        Ok(())
+    }
+}
+
+impl Default for RegisterBank {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,373 @@
+//! ARM7TDMI Disassembler
+//!
+//! Turns single ARM or Thumb opcodes into readable assembly for debuggers and
+//! traces. Output follows GNU-style mnemonics; branch targets are absolute
+//! addresses computed from the instruction address.
+
+const COND: [&str; 16] = [
+    "eq", "ne", "cs", "cc", "mi", "pl", "vs", "vc", "hi", "ls", "ge", "lt", "gt", "le", "", "nv",
+];
+
+const DP_OPS: [&str; 16] = [
+    "and", "eor", "sub", "rsb", "add", "adc", "sbc", "rsc", "tst", "teq", "cmp", "cmn", "orr", "mov",
+    "bic", "mvn",
+];
+
+const SHIFTS: [&str; 4] = ["lsl", "lsr", "asr", "ror"];
+
+/// Entry `index` of a mnemonic table
+fn pick(table: &[&'static str], index: u32) -> &'static str {
+    table.get(index as usize).copied().unwrap_or("")
+}
+
+fn reg(r: u32) -> String {
+    match r & 0xF {
+        13 => "sp".to_string(),
+        14 => "lr".to_string(),
+        15 => "pc".to_string(),
+        n => format!("r{}", n),
+    }
+}
+
+fn reg_list(mask: u32) -> String {
+    let regs: Vec<String> = (0..16).filter(|i| mask & (1 << i) != 0).map(reg).collect();
+    format!("{{{}}}", regs.join(", "))
+}
+
+fn shifted_reg(op: u32) -> String {
+    let rm = reg(op);
+    let shift_type = (op >> 5) & 3;
+    if op & 0x10 != 0 {
+        return format!("{}, {} {}", rm, pick(&SHIFTS, shift_type), reg(op >> 8));
+    }
+
+    let amount = (op >> 7) & 0x1F;
+    match (shift_type, amount) {
+        (0, 0) => rm,
+        (3, 0) => format!("{}, rrx", rm),
+        (1, 0) | (2, 0) => format!("{}, {} #32", rm, pick(&SHIFTS, shift_type)),
+        _ => format!("{}, {} #{}", rm, pick(&SHIFTS, shift_type), amount),
+    }
+}
+
+/// Disassemble one ARM instruction located at `addr`
+pub fn disassemble_arm(addr: u32, op: u32) -> String {
+    let cond = pick(&COND, op >> 28);
+
+    if op & 0x0FFF_FFF0 == 0x012F_FF10 {
+        return format!("bx{} {}", cond, reg(op));
+    }
+
+    if op & 0xFFF0_00F0 == 0xE120_0070 {
+        return format!("bkpt #0x{:X}", ((op >> 4) & 0xFFF0) | (op & 0xF));
+    }
+
+    if op & 0x0FC0_00F0 == 0x0000_0090 {
+        let s = if op & (1 << 20) != 0 { "s" } else { "" };
+        let (rd, rn, rs, rm) = (reg(op >> 16), reg(op >> 12), reg(op >> 8), reg(op));
+        return if op & (1 << 21) != 0 {
+            format!("mla{}{} {}, {}, {}, {}", cond, s, rd, rm, rs, rn)
+        } else {
+            format!("mul{}{} {}, {}, {}", cond, s, rd, rm, rs)
+        };
+    }
+
+    if op & 0x0F80_00F0 == 0x0080_0090 {
+        let name = match (op >> 21) & 3 {
+            0 => "umull",
+            1 => "umlal",
+            2 => "smull",
+            _ => "smlal",
+        };
+        let s = if op & (1 << 20) != 0 { "s" } else { "" };
+        return format!(
+            "{}{}{} {}, {}, {}, {}",
+            name,
+            cond,
+            s,
+            reg(op >> 12),
+            reg(op >> 16),
+            reg(op),
+            reg(op >> 8)
+        );
+    }
+
+    if op & 0x0FB0_0FF0 == 0x0100_0090 {
+        let b = if op & (1 << 22) != 0 { "b" } else { "" };
+        return format!("swp{}{} {}, {}, [{}]", cond, b, reg(op >> 12), reg(op), reg(op >> 16));
+    }
+
+    if op & 0x0E00_0090 == 0x0000_0090 && (op >> 5) & 3 != 0 {
+        return arm_halfword(op, cond);
+    }
+
+    if op & 0x0FBF_0FFF == 0x010F_0000 {
+        let psr = if op & (1 << 22) != 0 { "spsr" } else { "cpsr" };
+        return format!("mrs{} {}, {}", cond, reg(op >> 12), psr);
+    }
+
+    if op & 0x0DB0_F000 == 0x0120_F000 {
+        let psr = if op & (1 << 22) != 0 { "spsr" } else { "cpsr" };
+        let mut fields = String::new();
+        for (bit, c) in [(16, 'c'), (17, 'x'), (18, 's'), (19, 'f')] {
+            if op & (1 << bit) != 0 {
+                fields.push(c);
+            }
+        }
+        let src = if op & (1 << 25) != 0 {
+            format!("#0x{:X}", (op & 0xFF).rotate_right(((op >> 8) & 0xF) << 1))
+        } else {
+            reg(op)
+        };
+        return format!("msr{} {}_{}, {}", cond, psr, fields, src);
+    }
+
+    match (op >> 25) & 7 {
+        0 | 1 => arm_data_processing(op, cond),
+        2 | 3 => {
+            if op & (1 << 25) != 0 && op & 0x10 != 0 {
+                return format!("undefined 0x{:08X}", op);
+            }
+            arm_single_transfer(op, cond)
+        }
+        4 => {
+            let name = if op & (1 << 20) != 0 { "ldm" } else { "stm" };
+            let mode = match (op >> 23) & 3 {
+                0 => "da",
+                1 => "ia",
+                2 => "db",
+                _ => "ib",
+            };
+            let wb = if op & (1 << 21) != 0 { "!" } else { "" };
+            let user = if op & (1 << 22) != 0 { "^" } else { "" };
+            format!("{}{}{} {}{}, {}{}", name, cond, mode, reg(op >> 16), wb, reg_list(op & 0xFFFF), user)
+        }
+        5 => {
+            let offset = (((op & 0x00FF_FFFF) << 8) as i32 >> 6) as u32;
+            let target = addr.wrapping_add(8).wrapping_add(offset);
+            let link = if op & (1 << 24) != 0 { "l" } else { "" };
+            format!("b{}{} 0x{:08X}", link, cond, target)
+        }
+        7 if op & (1 << 24) != 0 => format!("swi{} #0x{:X}", cond, op & 0x00FF_FFFF),
+        _ => format!("coproc{} 0x{:08X}", cond, op),
+    }
+}
+
+fn arm_data_processing(op: u32, cond: &str) -> String {
+    let opcode = (op >> 21) & 0xF;
+    let name = pick(&DP_OPS, opcode);
+    let set = op & (1 << 20) != 0;
+    let operand2 = if op & (1 << 25) != 0 {
+        format!("#0x{:X}", (op & 0xFF).rotate_right(((op >> 8) & 0xF) << 1))
+    } else {
+        shifted_reg(op)
+    };
+    let rd = reg(op >> 12);
+    let rn = reg(op >> 16);
+
+    match opcode {
+        // Compare ops always set flags and have no destination
+        8..=11 => format!("{}{} {}, {}", name, cond, rn, operand2),
+        13 | 15 => format!("{}{}{} {}, {}", name, cond, if set { "s" } else { "" }, rd, operand2),
+        _ => format!("{}{}{} {}, {}, {}", name, cond, if set { "s" } else { "" }, rd, rn, operand2),
+    }
+}
+
+fn arm_single_transfer(op: u32, cond: &str) -> String {
+    let load = op & (1 << 20) != 0;
+    let byte = if op & (1 << 22) != 0 { "b" } else { "" };
+    let pre = op & (1 << 24) != 0;
+    let up = op & (1 << 23) != 0;
+    let wb = op & (1 << 21) != 0;
+    let sign = if up { "" } else { "-" };
+
+    let offset = if op & (1 << 25) == 0 {
+        let imm = op & 0xFFF;
+        if imm == 0 {
+            None
+        } else {
+            Some(format!("#{}0x{:X}", sign, imm))
+        }
+    } else {
+        Some(format!("{}{}", sign, shifted_reg(op)))
+    };
+
+    let rn = reg(op >> 16);
+    let address = match (pre, offset) {
+        (true, Some(off)) => format!("[{}, {}]{}", rn, off, if wb { "!" } else { "" }),
+        (true, None) => format!("[{}]", rn),
+        (false, Some(off)) => format!("[{}], {}", rn, off),
+        (false, None) => format!("[{}]", rn),
+    };
+    let t = if !pre && wb { "t" } else { "" };
+    format!("{}{}{}{} {}, {}", if load { "ldr" } else { "str" }, cond, byte, t, reg(op >> 12), address)
+}
+
+fn arm_halfword(op: u32, cond: &str) -> String {
+    let load = op & (1 << 20) != 0;
+    let name = match ((op >> 5) & 3, load) {
+        (1, false) => "strh",
+        (1, true) => "ldrh",
+        (2, _) => "ldrsb",
+        _ => "ldrsh",
+    };
+    let pre = op & (1 << 24) != 0;
+    let sign = if op & (1 << 23) != 0 { "" } else { "-" };
+    let wb = op & (1 << 21) != 0;
+    let offset = if op & (1 << 22) != 0 {
+        let imm = ((op >> 4) & 0xF0) | (op & 0xF);
+        format!("#{}0x{:X}", sign, imm)
+    } else {
+        format!("{}{}", sign, reg(op))
+    };
+    let rn = reg(op >> 16);
+    let address = if pre {
+        format!("[{}, {}]{}", rn, offset, if wb { "!" } else { "" })
+    } else {
+        format!("[{}], {}", rn, offset)
+    };
+    format!("{}{} {}, {}", name, cond, reg(op >> 12), address)
+}
+
+/// Disassemble one Thumb instruction located at `addr`.
+/// BL is a two-halfword pair; each half is shown on its own.
+pub fn disassemble_thumb(addr: u32, op: u16) -> String {
+    let op = op as u32;
+    let rd = reg(op & 7);
+    let rs = reg((op >> 3) & 7);
+
+    match op >> 13 {
+        0 => {
+            if (op >> 11) & 3 == 3 {
+                let name = if op & (1 << 9) != 0 { "sub" } else { "add" };
+                let operand = if op & (1 << 10) != 0 {
+                    format!("#{}", (op >> 6) & 7)
+                } else {
+                    reg((op >> 6) & 7)
+                };
+                format!("{}s {}, {}, {}", name, rd, rs, operand)
+            } else {
+                let name = pick(&SHIFTS, (op >> 11) & 3);
+                let mut amount = (op >> 6) & 0x1F;
+                if amount == 0 && name != "lsl" {
+                    amount = 32;
+                }
+                format!("{}s {}, {}, #{}", name, rd, rs, amount)
+            }
+        }
+        1 => {
+            let name = pick(&["movs", "cmp", "adds", "subs"], (op >> 11) & 3);
+            format!("{} {}, #0x{:X}", name, reg((op >> 8) & 7), op & 0xFF)
+        }
+        2 => thumb_010(addr, op),
+        3 => {
+            let load = op & (1 << 11) != 0;
+            let byte = op & (1 << 12) != 0;
+            let imm = ((op >> 6) & 0x1F) << if byte { 0 } else { 2 };
+            let name = match (load, byte) {
+                (false, false) => "str",
+                (true, false) => "ldr",
+                (false, true) => "strb",
+                (true, true) => "ldrb",
+            };
+            format!("{} {}, [{}, #0x{:X}]", name, rd, rs, imm)
+        }
+        4 => {
+            let load = op & (1 << 11) != 0;
+            if op & (1 << 12) == 0 {
+                let name = if load { "ldrh" } else { "strh" };
+                format!("{} {}, [{}, #0x{:X}]", name, rd, rs, ((op >> 6) & 0x1F) << 1)
+            } else {
+                let name = if load { "ldr" } else { "str" };
+                format!("{} {}, [sp, #0x{:X}]", name, reg((op >> 8) & 7), (op & 0xFF) << 2)
+            }
+        }
+        5 => {
+            if op & (1 << 12) == 0 {
+                let base = if op & (1 << 11) != 0 { "sp" } else { "pc" };
+                format!("add {}, {}, #0x{:X}", reg((op >> 8) & 7), base, (op & 0xFF) << 2)
+            } else if (op >> 8) & 0xF == 0 {
+                let sign = if op & 0x80 != 0 { "-" } else { "" };
+                format!("add sp, #{}0x{:X}", sign, (op & 0x7F) << 2)
+            } else if (op >> 9) & 3 == 2 {
+                let mut mask = op & 0xFF;
+                let load = op & (1 << 11) != 0;
+                if op & (1 << 8) != 0 {
+                    mask |= if load { 1 << 15 } else { 1 << 14 };
+                }
+                format!("{} {}", if load { "pop" } else { "push" }, reg_list(mask))
+            } else if (op >> 8) & 0xFF == 0xBE {
+                format!("bkpt #0x{:X}", op & 0xFF)
+            } else {
+                format!("undefined 0x{:04X}", op)
+            }
+        }
+        6 => {
+            if op & (1 << 12) == 0 {
+                let name = if op & (1 << 11) != 0 { "ldmia" } else { "stmia" };
+                format!("{} {}!, {}", name, reg((op >> 8) & 7), reg_list(op & 0xFF))
+            } else {
+                match (op >> 8) & 0xF {
+                    0xF => format!("swi #0x{:X}", op & 0xFF),
+                    0xE => format!("undefined 0x{:04X}", op),
+                    cond => {
+                        let offset = ((op & 0xFF) as i8 as i32) << 1;
+                        let target = addr.wrapping_add(4).wrapping_add(offset as u32);
+                        format!("b{} 0x{:08X}", pick(&COND, cond), target)
+                    }
+                }
+            }
+        }
+        _ => match (op >> 11) & 3 {
+            0 => {
+                let offset = (((op & 0x7FF) << 21) as i32 >> 20) as u32;
+                format!("b 0x{:08X}", addr.wrapping_add(4).wrapping_add(offset))
+            }
+            1 => format!("blx.suffix #0x{:X}", (op & 0x7FF) << 1),
+            2 => {
+                let high = (((op & 0x7FF) << 21) as i32 >> 9) as u32;
+                format!("bl.prefix 0x{:08X}", addr.wrapping_add(4).wrapping_add(high))
+            }
+            _ => format!("bl.suffix #0x{:X}", (op & 0x7FF) << 1),
+        },
+    }
+}
+
+fn thumb_010(addr: u32, op: u32) -> String {
+    let rd = reg(op & 7);
+    let rs = reg((op >> 3) & 7);
+
+    if op & 0xFC00 == 0x4000 {
+        let names = [
+            "ands", "eors", "lsls", "lsrs", "asrs", "adcs", "sbcs", "rors", "tst", "negs", "cmp", "cmn", "orrs",
+            "muls", "bics", "mvns",
+        ];
+        let name = pick(&names, (op >> 6) & 0xF);
+        return format!("{} {}, {}", name, rd, rs);
+    }
+
+    if op & 0xFC00 == 0x4400 {
+        let hd = reg((op & 7) | ((op >> 4) & 8));
+        let hs = reg((op >> 3) & 0xF);
+        return match (op >> 8) & 3 {
+            0 => format!("add {}, {}", hd, hs),
+            1 => format!("cmp {}, {}", hd, hs),
+            2 => format!("mov {}, {}", hd, hs),
+            _ => format!("bx {}", hs),
+        };
+    }
+
+    if op & 0xF800 == 0x4800 {
+        let imm = (op & 0xFF) << 2;
+        let target = (addr.wrapping_add(4) & !2).wrapping_add(imm);
+        return format!("ldr {}, [pc, #0x{:X}] ; 0x{:08X}", reg((op >> 8) & 7), imm, target);
+    }
+
+    let ro = reg((op >> 6) & 7);
+    let name = if op & (1 << 9) == 0 {
+        pick(&["str", "strb", "ldr", "ldrb"], (op >> 10) & 3)
+    } else {
+        pick(&["strh", "ldsb", "ldrh", "ldsh"], (op >> 10) & 3)
+    };
+    format!("{} {}, [{}, {}]", name, rd, rs, ro)
+}
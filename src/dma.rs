@@ -4,6 +4,8 @@
 //! memory regions without CPU intervention.
 
 use crate::Memory;
+use crate::savestate::{StateError, StateReader, StateWriter};
+use crate::view::ByteViewMut;
 
 /// DMA transfer mode
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -39,6 +41,8 @@ pub struct Dma {
     current_src: u32,
     current_dst: u32,
     current_count: u32, // Use u32 to handle 0x10000 for DMA3
+    last_cycles: u32,
+    last_bytes: u32,
 }
 
 impl Dma {
@@ -60,6 +64,8 @@ impl Dma {
             current_src: 0,
             current_dst: 0,
             current_count: 0,
+            last_cycles: 0,
+            last_bytes: 0,
         }
     }
 
@@ -76,6 +82,8 @@ impl Dma {
         self.dst_increment = 0;
         self.trigger = DmaTransferMode::Immediate;
         self.irq = false;
+        self.last_cycles = 0;
+        self.last_bytes = 0;
     }
 
     pub fn get_control(&self) -> u16 {
@@ -108,7 +116,7 @@ impl Dma {
             _ => DmaTransferMode::Special,
         };
 
-        self.transfer_type = if (value & 0x0400) != 0 {
+        self.transfer_type = if (value & 0x0400) != 0 || self.is_sound_fifo() {
             DmaTransferType::Word
         } else {
             DmaTransferType::HalfWord
@@ -123,7 +131,10 @@ impl Dma {
             _ => 1,
         };
 
+        // Sound FIFO DMA always feeds the same FIFO register, so control 3
+        // (increment/reload) must not walk past it into the DMA registers
         self.dst_increment = match (value >> 5) & 0x3 {
+            _ if self.is_sound_fifo() => 0,
             0 => 1,
             1 => -1,
             2 => 0,
@@ -137,17 +148,31 @@ impl Dma {
         }
     }
 
-    fn start_transfer(&mut self) {
-        self.current_src = self.src_addr;
-        self.current_dst = self.dst_addr;
-        self.current_count = if self.count == 0 {
+    /// DMA1/DMA2 with Special timing refill the Direct Sound FIFOs: four
+    /// words per request into a fixed address, whatever the count, width
+    /// and destination control say
+    fn is_sound_fifo(&self) -> bool {
+        matches!(self.num, 1 | 2) && self.trigger == DmaTransferMode::Special
+    }
+
+    /// Units moved per transfer; a count of 0 means the channel maximum
+    fn unit_count(&self) -> u32 {
+        if self.is_sound_fifo() {
+            4
+        } else if self.count == 0 {
             match self.num {
                 3 => 0x10000,
                 _ => 0x4000,
             }
         } else {
             self.count as u32
-        };
+        }
+    }
+
+    fn start_transfer(&mut self) {
+        self.current_src = self.src_addr;
+        self.current_dst = self.dst_addr;
+        self.current_count = self.unit_count();
         self.active = true;
 
         // For immediate transfer mode, execute immediately
@@ -165,9 +190,8 @@ impl Dma {
     }
 
     pub fn writeback_control(&self, io: &mut [u8]) {
-        let base = 0xB0 + (self.num as usize * 12);
-        io[base + 10] = self.control as u8;
-        io[base + 11] = (self.control >> 8) as u8;
+        let cnt_h = 0xBAusize.wrapping_add((self.num as usize).wrapping_mul(12));
+        io.set_half(cnt_h, self.control);
     }
 
     pub fn is_active(&self) -> bool {
@@ -202,7 +226,21 @@ impl Dma {
         self.count = count;
     }
 
+    /// Bus cycles taken by the last `execute`: 2 internal cycles (4 for
+    /// ROM to ROM), then a read and a write per unit, nonsequential for the
+    /// first unit and sequential after, timed with the CPU's waitstates
+    pub fn last_transfer_cycles(&self) -> u32 {
+        self.last_cycles
+    }
+
+    /// Bytes moved by the last `execute`
+    pub fn last_transfer_bytes(&self) -> u32 {
+        self.last_bytes
+    }
+
     pub fn execute(&mut self, mem: &mut Memory) -> bool {
+        self.last_cycles = 0;
+        self.last_bytes = 0;
         if !self.active || !self.enabled {
             return false;
         }
@@ -223,11 +261,46 @@ impl Dma {
                 transfer_size,
             ));
         }
+        self.last_bytes = self.current_count.wrapping_mul(transfer_size);
         let dst_inc = self.dst_increment;
         let src_inc = self.src_increment;
 
+        let word = self.transfer_type == DmaTransferType::Word;
+        let in_rom = |addr: u32| (0x08..=0x0D).contains(&(addr >> 24));
+        self.last_cycles = if in_rom(self.current_src) && in_rom(self.current_dst) {
+            4
+        } else {
+            2
+        };
+        let mut sequential = false;
+
+        // Both addresses counting up: hand the whole block to the bus at once
+        if self.src_increment > 0 && self.dst_increment > 0 && self.current_count > 0 {
+            let n = self.current_count;
+            let (src, dst) = (self.current_src, self.current_dst);
+            let first = mem
+                .bus_cycles(src, false, word)
+                .wrapping_add(mem.bus_cycles(dst, false, word));
+            let rest = mem
+                .bus_cycles(src, true, word)
+                .wrapping_add(mem.bus_cycles(dst, true, word));
+            self.last_cycles = self
+                .last_cycles
+                .wrapping_add(first)
+                .wrapping_add(rest.wrapping_mul(n.wrapping_sub(1)));
+            mem.copy_block(src, dst, n, transfer_size);
+            self.current_src = src.wrapping_add(n.wrapping_mul(transfer_size));
+            self.current_dst = dst.wrapping_add(n.wrapping_mul(transfer_size));
+            self.current_count = 0;
+        }
+
         // Transfer data
         while self.current_count > 0 {
+            let cycles = mem
+                .bus_cycles(self.current_src, sequential, word)
+                .wrapping_add(mem.bus_cycles(self.current_dst, sequential, word));
+            self.last_cycles = self.last_cycles.wrapping_add(cycles);
+            sequential = true;
             match self.transfer_type {
                 DmaTransferType::HalfWord => {
                     let value = mem.read_half(self.current_src);
@@ -252,21 +325,17 @@ impl Dma {
                 self.current_dst = self.current_dst.wrapping_sub(transfer_size);
             }
 
-            self.current_count -= 1;
+            self.current_count = self.current_count.wrapping_sub(1);
         }
 
         // Check if DMA should repeat
         if self.repeat && self.trigger != DmaTransferMode::Immediate {
-            self.current_src = self.src_addr;
-            self.current_dst = self.dst_addr;
-            self.current_count = if self.count == 0 {
-                match self.num {
-                    3 => 0x10000,
-                    _ => 0x4000,
-                }
-            } else {
-                self.count as u32
-            };
+            // The source carries on where it stopped; only destination
+            // control 3 reloads the destination
+            if (self.control >> 5) & 0x3 == 3 {
+                self.current_dst = self.dst_addr;
+            }
+            self.current_count = self.unit_count();
             // Keep active for next trigger
         } else {
             self.active = false;
@@ -281,6 +350,36 @@ impl Dma {
     }
 }
 
+impl Dma {
+    pub(crate) fn save_state(&self, w: &mut StateWriter) {
+        w.write_u32(self.src_addr);
+        w.write_u32(self.dst_addr);
+        w.write_u16(self.count);
+        w.write_u16(self.control);
+        w.write_bool(self.enabled);
+        w.write_bool(self.active);
+        w.write_u32(self.current_src);
+        w.write_u32(self.current_dst);
+        w.write_u32(self.current_count);
+    }
+
+    pub(crate) fn load_state(&mut self, r: &mut StateReader) -> Result<(), StateError> {
+        self.src_addr = r.read_u32()?;
+        self.dst_addr = r.read_u32()?;
+        self.count = r.read_u16()?;
+        let control = r.read_u16()?;
+        // Decode the control fields, then restore the in-flight transfer as saved
+        self.enabled = true;
+        self.set_control(control);
+        self.enabled = r.read_bool()?;
+        self.active = r.read_bool()?;
+        self.current_src = r.read_u32()?;
+        self.current_dst = r.read_u32()?;
+        self.current_count = r.read_u32()?;
+        Ok(())
+    }
+}
+
 impl Default for Dma {
     fn default() -> Self {
         Self::new(0)
@@ -3,6 +3,8 @@
 //! EEPROM is accessed via a serial protocol through the highest ROM mirror address.
 //! Supports 512B (14-bit address) and 8KB (6-bit address) EEPROM sizes.
 
+use crate::view::ByteViewMut;
+
 /// EEPROM serial interface
 pub struct Eeprom {
     data: Vec<u8>,
@@ -69,17 +71,16 @@ impl Eeprom {
     pub fn serial_read(&mut self) -> u8 {
         match self.state {
             EepromState::ReadData => {
-                if self.read_offset < self.data.len() {
+                if let Some(byte) = self.data.get_mut(self.read_offset) {
                     // Read bits MSB first from current byte
-                    let byte = self.data[self.read_offset];
-                    let bit = (byte >> 7) & 1;
+                    let bit = (*byte >> 7) & 1;
                     // Shift the byte left
-                    self.data[self.read_offset] = byte << 1;
-                    self.bits_received += 1;
+                    *byte <<= 1;
+                    self.bits_received = self.bits_received.wrapping_add(1);
                     // After 8 bits, advance to next byte
                     if self.bits_received >= 8 {
                         self.bits_received = 0;
-                        self.read_offset += 1;
+                        self.read_offset = self.read_offset.wrapping_add(1);
                         // After reading all data, return to idle
                         if self.read_offset >= self.data.len() {
                             self.state = EepromState::Idle;
@@ -111,7 +112,7 @@ impl Eeprom {
             EepromState::Command => {
                 // Receive 2-bit command: 10=read, 01=write
                 self.shift_reg = (self.shift_reg << 1) | bit as u64;
-                self.bits_received += 1;
+                self.bits_received = self.bits_received.wrapping_add(1);
                 if self.bits_received >= 2 {
                     let cmd = (self.shift_reg & 0x3) as u8;
                     self.shift_reg = 0;
@@ -133,9 +134,11 @@ impl Eeprom {
             EepromState::Address => {
                 // Receive address bits
                 self.shift_reg = (self.shift_reg << 1) | bit as u64;
-                self.bits_received += 1;
+                self.bits_received = self.bits_received.wrapping_add(1);
                 if self.bits_received >= self.address_bits {
-                    let addr = (self.shift_reg as usize) % self.data.len();
+                    let addr = (self.shift_reg as usize)
+                        .checked_rem(self.data.len())
+                        .unwrap_or(0);
                     let cmd = (self.shift_reg >> self.address_bits) as u8;
                     self.shift_reg = 0;
                     self.bits_received = 0;
@@ -154,7 +157,7 @@ impl Eeprom {
             EepromState::WriteData => {
                 // Receive 8-byte blocks
                 self.shift_reg = (self.shift_reg << 1) | bit as u64;
-                self.bits_received += 1;
+                self.bits_received = self.bits_received.wrapping_add(1);
                 if self.bits_received >= 8 {
                     let byte = (self.shift_reg & 0xFF) as u8;
                     self.write_buffer.push(byte);
@@ -164,10 +167,11 @@ impl Eeprom {
                     // Write 8 bytes at a time
                     if self.write_buffer.len() >= 8 {
                         for (i, &byte) in self.write_buffer.iter().enumerate() {
-                            let addr = (self.write_offset + i) % self.data.len();
-                            self.data[addr] = byte;
+                            let addr = self.write_offset.wrapping_add(i);
+                            let addr = addr.checked_rem(self.data.len()).unwrap_or(0);
+                            self.data.set_byte(addr, byte);
                         }
-                        self.write_offset += 8;
+                        self.write_offset = self.write_offset.wrapping_add(8);
                         self.write_buffer.clear();
                         self.state = EepromState::Idle;
                     }
@@ -181,8 +185,7 @@ impl Eeprom {
 
     /// Load data from a save file
     pub fn load(&mut self, data: &[u8]) {
-        let len = data.len().min(self.data.len());
-        self.data[..len].copy_from_slice(&data[..len]);
+        self.data.set_bytes(0, data);
     }
 
     /// Get a reference to the raw data (for saving)
@@ -5,6 +5,8 @@
 
 use std::collections::VecDeque;
 
+use crate::view::{ByteView, ByteViewMut};
+
 /// Flash command state machine
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[allow(dead_code)]
@@ -73,7 +75,7 @@ impl Flash {
 
     /// Read a byte from flash
     pub fn read(&self, addr: u32) -> u8 {
-        let addr = addr as usize & (self.size - 1);
+        let addr = addr as usize & self.size.wrapping_sub(1);
 
         if self.id_mode {
             // Return chip ID bytes
@@ -94,11 +96,15 @@ impl Flash {
             }
         }
 
+        self.data.byte_at(self.offset(addr))
+    }
+
+    /// Index into `data` of `addr`, in the selected bank of a 128K chip
+    fn offset(&self, addr: usize) -> usize {
         if self.size == 0x20000 && self.bank == 1 {
-            // 128K flash bank 1
-            self.data[0x10000 + (addr & 0xFFFF)]
+            0x10000 | (addr & 0xFFFF)
         } else {
-            self.data[addr & (self.size - 1)]
+            addr & self.size.wrapping_sub(1)
         }
     }
 
@@ -168,9 +174,10 @@ impl Flash {
             }
             FlashState::EraseSector2 => {
                 if val == 0x30 {
-                    let sector_base = raw_addr & !0xFFF & (self.size - 1);
-                    for b in &mut self.data[sector_base..sector_base + 0x1000] {
-                        *b = 0xFF;
+                    let sector_base = raw_addr & !0xFFF & self.size.wrapping_sub(1);
+                    let sector = sector_base..sector_base.wrapping_add(0x1000);
+                    if let Some(sector) = self.data.get_mut(sector) {
+                        sector.fill(0xFF);
                     }
                 } else if val == 0x10 {
                     self.data.fill(0xFF);
@@ -182,12 +189,10 @@ impl Flash {
                     self.bank = val & 1;
                     self.pending_bank_select = false;
                 } else {
-                    let target = if self.size == 0x20000 && self.bank == 1 {
-                        0x10000 + (raw_addr & 0xFFFF)
-                    } else {
-                        raw_addr & (self.size - 1)
-                    };
-                    self.data[target] &= val;
+                    let target = self.offset(raw_addr);
+                    if let Some(b) = self.data.get_mut(target) {
+                        *b &= val;
+                    }
                 }
                 self.state = FlashState::Idle;
             }
@@ -215,7 +220,6 @@ impl Flash {
 
     /// Load data from a save file
     pub fn load(&mut self, data: &[u8]) {
-        let len = data.len().min(self.data.len());
-        self.data[..len].copy_from_slice(&data[..len]);
+        self.data.set_bytes(0, data);
     }
 }
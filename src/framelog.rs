@@ -0,0 +1,188 @@
+//! Frame Event Log (`frame-log` feature)
+//!
+//! Records what happened in each video frame at the level a user can see:
+//! when VBlank began, which DMA bursts ran, which interrupts the CPU
+//! serviced and how many audio samples came out. A frame runs from one
+//! VBlank to the next, the same span [`crate::Gba::audio_frame_sample_count`]
+//! counts, so uneven frame lengths or sample counts show up directly.
+//! [`FrameLog::to_json_lines`] writes the log in a form that can be attached
+//! to a stutter or desync report and diffed between emulator builds.
+
+use std::collections::VecDeque;
+use std::fmt::Write;
+
+use crate::Interrupt;
+
+/// Something that happened during a frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameEvent {
+    /// DMA `channel` moved `bytes` bytes, holding the bus for `cycles`
+    Dma {
+        channel: u8,
+        bytes: u32,
+        cycles: u32,
+    },
+    /// The CPU entered the IRQ handler with these interrupts pending
+    Irq(Interrupt),
+}
+
+/// A [`FrameEvent`] and when it happened
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoggedEvent {
+    /// Cycles since the frame's VBlank
+    pub offset: u32,
+    pub event: FrameEvent,
+}
+
+/// One VBlank-to-VBlank span
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameRecord {
+    /// Counts from 0 at the first VBlank after logging started
+    pub frame: u64,
+    /// Emulated cycle count when the frame's VBlank began
+    pub vblank_cycle: u64,
+    /// Cycles until the next VBlank; 280896 on a steady display
+    pub cycles: u32,
+    /// Stereo output samples produced during the frame
+    pub audio_samples: usize,
+    /// Events in the order they happened
+    pub events: Vec<LoggedEvent>,
+}
+
+impl FrameRecord {
+    fn new(frame: u64, vblank_cycle: u64) -> Self {
+        Self {
+            frame,
+            vblank_cycle,
+            cycles: 0,
+            audio_samples: 0,
+            events: Vec::new(),
+        }
+    }
+
+    /// Total bytes moved by DMA `channel`
+    pub fn dma_bytes(&self, channel: u8) -> u32 {
+        self.events
+            .iter()
+            .map(|e| match e.event {
+                FrameEvent::Dma {
+                    channel: c, bytes, ..
+                } if c == channel => bytes,
+                _ => 0,
+            })
+            .sum()
+    }
+
+    /// IRQ handler entries with `irq` among the pending interrupts
+    pub fn irq_count(&self, irq: Interrupt) -> usize {
+        self.events
+            .iter()
+            .filter(|e| matches!(e.event, FrameEvent::Irq(pending) if pending.intersects(irq)))
+            .count()
+    }
+}
+
+/// Ring of the most recent [`FrameRecord`]s
+#[derive(Debug, Clone)]
+pub struct FrameLog {
+    capacity: usize,
+    frames: VecDeque<FrameRecord>,
+    /// Frame in progress; `None` until the first VBlank after logging starts
+    current: Option<FrameRecord>,
+    next_frame: u64,
+}
+
+impl FrameLog {
+    /// Keep the last `capacity` complete frames (at least 1)
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            frames: VecDeque::new(),
+            current: None,
+            next_frame: 0,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Complete frames, oldest first
+    pub fn frames(&self) -> impl Iterator<Item = &FrameRecord> {
+        self.frames.iter()
+    }
+
+    /// Complete frame number `frame`, if still held
+    pub fn frame(&self, frame: u64) -> Option<&FrameRecord> {
+        let first = self.frames.front()?.frame;
+        self.frames.get(frame.checked_sub(first)? as usize)
+    }
+
+    /// Frames whose length differs from `cycles`, e.g. to find where the
+    /// display timing was disturbed
+    pub fn irregular_frames(&self, cycles: u32) -> impl Iterator<Item = &FrameRecord> {
+        self.frames.iter().filter(move |f| f.cycles != cycles)
+    }
+
+    /// Close the current frame at a VBlank that began at `cycle`, after
+    /// `audio_samples` samples, and open the next
+    pub(crate) fn vblank(&mut self, cycle: u64, audio_samples: usize) {
+        if let Some(mut frame) = self.current.take() {
+            frame.cycles = cycle.saturating_sub(frame.vblank_cycle) as u32;
+            frame.audio_samples = audio_samples;
+            if self.frames.len() == self.capacity {
+                self.frames.pop_front();
+            }
+            self.frames.push_back(frame);
+        }
+        self.current = Some(FrameRecord::new(self.next_frame, cycle));
+        self.next_frame = self.next_frame.wrapping_add(1);
+    }
+
+    pub(crate) fn record(&mut self, cycle: u64, event: FrameEvent) {
+        if let Some(frame) = &mut self.current {
+            let offset = cycle.saturating_sub(frame.vblank_cycle) as u32;
+            frame.events.push(LoggedEvent { offset, event });
+        }
+    }
+
+    /// One JSON object per complete frame, e.g.
+    /// `{"frame":3,"vblank_cycle":1123584,"cycles":280896,"audio_samples":548,"events":[{"offset":12,"dma":{"channel":1,"bytes":16,"cycles":42}},{"offset":80,"irq":1}]}`
+    /// where `irq` is the pending IE & IF mask
+    pub fn to_json_lines(&self) -> String {
+        let mut out = String::new();
+        for f in &self.frames {
+            let _ = write!(
+                out,
+                "{{\"frame\":{},\"vblank_cycle\":{},\"cycles\":{},\"audio_samples\":{},\"events\":[",
+                f.frame, f.vblank_cycle, f.cycles, f.audio_samples
+            );
+            for (i, e) in f.events.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                let _ = match e.event {
+                    FrameEvent::Dma {
+                        channel,
+                        bytes,
+                        cycles,
+                    } => write!(
+                        out,
+                        "{{\"offset\":{},\"dma\":{{\"channel\":{},\"bytes\":{},\"cycles\":{}}}}}",
+                        e.offset, channel, bytes, cycles
+                    ),
+                    FrameEvent::Irq(pending) => {
+                        write!(
+                            out,
+                            "{{\"offset\":{},\"irq\":{}}}",
+                            e.offset,
+                            pending.bits()
+                        )
+                    }
+                };
+            }
+            out.push_str("]}\n");
+        }
+        out
+    }
+}
@@ -0,0 +1,7 @@
+//! Frontend Utilities
+//!
+//! Helpers shared by the bundled GUI and any other frontend embedding the
+//! core: presentation math and pixel scaling that do not belong in the
+//! emulation itself.
+
+pub mod scale;
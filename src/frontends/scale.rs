@@ -0,0 +1,186 @@
+//! Screen Scaling
+//!
+//! Computes letterboxed destination rectangles and scales 0x00RRGGBB frames
+//! into window-sized buffers.
+
+/// Rectangle in destination pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// How the source is fitted into the destination
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FitMode {
+    /// Largest whole-number multiple, letterboxed
+    #[default]
+    Integer,
+    /// Largest size keeping the source aspect ratio, letterboxed
+    Aspect,
+    /// Fill the whole destination
+    Stretch,
+}
+
+/// Sampling filter used by [`scale_into`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Filter {
+    #[default]
+    Nearest,
+    /// Nearest-neighbour prescale followed by bilinear for the fractional rest
+    SharpBilinear,
+}
+
+/// Largest integer scale of the source that fits (at least 1)
+pub fn integer_scale(src_w: u32, src_h: u32, dst_w: u32, dst_h: u32) -> u32 {
+    let fit_w = dst_w.checked_div(src_w).unwrap_or(1);
+    let fit_h = dst_h.checked_div(src_h).unwrap_or(1);
+    fit_w.min(fit_h).max(1)
+}
+
+/// Destination rectangle for the source under the given fit mode
+pub fn fit(src_w: u32, src_h: u32, dst_w: u32, dst_h: u32, mode: FitMode) -> Rect {
+    if src_w == 0 || src_h == 0 {
+        return Rect { x: 0, y: 0, width: 0, height: 0 };
+    }
+
+    let (width, height) = match mode {
+        FitMode::Stretch => (dst_w, dst_h),
+        FitMode::Integer if dst_w >= src_w && dst_h >= src_h => {
+            let scale = integer_scale(src_w, src_h, dst_w, dst_h);
+            (src_w.wrapping_mul(scale), src_h.wrapping_mul(scale))
+        }
+        // Too small for 1x: fall back to aspect fitting
+        FitMode::Integer | FitMode::Aspect => {
+            let cross = |a: u32, b: u32| (a as u64).wrapping_mul(b as u64);
+            if cross(dst_w, src_h) <= cross(dst_h, src_w) {
+                (dst_w, mul_div(dst_w, src_h, src_w))
+            } else {
+                (mul_div(dst_h, src_w, src_h), dst_h)
+            }
+        }
+    };
+
+    Rect {
+        x: dst_w.saturating_sub(width) / 2,
+        y: dst_h.saturating_sub(height) / 2,
+        width,
+        height,
+    }
+}
+
+/// Copy a sub-rectangle of the source (e.g. to crop overscan or a region of interest)
+pub fn crop(src: &[u32], src_w: u32, region: Rect) -> Vec<u32> {
+    let mut out = Vec::with_capacity(region.width.wrapping_mul(region.height) as usize);
+    for y in region.y..region.y.wrapping_add(region.height) {
+        for x in region.x..region.x.wrapping_add(region.width) {
+            out.push(pixel_at(src, src_w, x, y));
+        }
+    }
+    out
+}
+
+/// Scale `src` into `dst`, clearing the letterbox bars to black.
+/// Returns the rectangle the image was drawn into.
+#[allow(clippy::too_many_arguments)]
+pub fn scale_into(
+    src: &[u32],
+    src_w: u32,
+    src_h: u32,
+    dst: &mut [u32],
+    dst_w: u32,
+    dst_h: u32,
+    mode: FitMode,
+    filter: Filter,
+) -> Rect {
+    let rect = fit(src_w, src_h, dst_w, dst_h, mode);
+    let Some(visible) = dst.get_mut(..dst_w.wrapping_mul(dst_h) as usize) else {
+        return rect;
+    };
+    if src.len() < src_w.wrapping_mul(src_h) as usize {
+        return rect;
+    }
+
+    visible.fill(0);
+    if rect.width == 0 || rect.height == 0 {
+        return rect;
+    }
+
+    let prescale_x = rect.width.checked_div(src_w).unwrap_or(1).max(1) as f32;
+    let prescale_y = rect.height.checked_div(src_h).unwrap_or(1).max(1) as f32;
+
+    for dy in 0..rect.height {
+        let y = rect.y.wrapping_add(dy);
+        for dx in 0..rect.width {
+            let x = rect.x.wrapping_add(dx);
+            let Some(out) = dst.get_mut(y.wrapping_mul(dst_w).wrapping_add(x) as usize) else {
+                continue;
+            };
+            *out = match filter {
+                Filter::Nearest => {
+                    let sx = mul_div(dx, src_w, rect.width);
+                    let sy = mul_div(dy, src_h, rect.height);
+                    pixel_at(src, src_w, sx, sy)
+                }
+                Filter::SharpBilinear => {
+                    let tx = sharp_coord(dx, rect.width, src_w, prescale_x);
+                    let ty = sharp_coord(dy, rect.height, src_h, prescale_y);
+                    sample_bilinear(src, src_w, src_h, tx, ty)
+                }
+            };
+        }
+    }
+
+    rect
+}
+
+/// Texel coordinate (texel centers at +0.5) with the sharp-bilinear remap
+fn sharp_coord(d: u32, dst_len: u32, src_len: u32, prescale: f32) -> f32 {
+    let texel = (d as f32 + 0.5) * src_len as f32 / dst_len as f32;
+    let floor = texel.floor();
+    let center_dist = texel - floor - 0.5;
+    let region = 0.5 - 0.5 / prescale;
+    let f = (center_dist - center_dist.clamp(-region, region)) * prescale + 0.5;
+    floor + f
+}
+
+fn sample_bilinear(src: &[u32], src_w: u32, src_h: u32, tx: f32, ty: f32) -> u32 {
+    let x = (tx - 0.5).max(0.0);
+    let y = (ty - 0.5).max(0.0);
+    let x0 = (x as u32).min(src_w.saturating_sub(1));
+    let y0 = (y as u32).min(src_h.saturating_sub(1));
+    let x1 = x0.saturating_add(1).min(src_w.saturating_sub(1));
+    let y1 = y0.saturating_add(1).min(src_h.saturating_sub(1));
+    let fx = x - x0 as f32;
+    let fy = y - y0 as f32;
+
+    let at = |px: u32, py: u32| pixel_at(src, src_w, px, py);
+    let top = lerp_rgb(at(x0, y0), at(x1, y0), fx);
+    let bottom = lerp_rgb(at(x0, y1), at(x1, y1), fx);
+    lerp_rgb(top, bottom, fy)
+}
+
+/// `a * b / c` without intermediate overflow; 0 when `c` is 0
+fn mul_div(a: u32, b: u32, c: u32) -> u32 {
+    let product = (a as u64).wrapping_mul(b as u64);
+    product.checked_div(c as u64).unwrap_or(0) as u32
+}
+
+/// Pixel (`x`, `y`) of a `width`-wide frame, black past its end
+fn pixel_at(src: &[u32], width: u32, x: u32, y: u32) -> u32 {
+    let at = y.wrapping_mul(width).wrapping_add(x);
+    src.get(at as usize).copied().unwrap_or(0)
+}
+
+fn lerp_rgb(a: u32, b: u32, t: f32) -> u32 {
+    let mut out = 0;
+    for shift in [0, 8, 16] {
+        let ca = ((a >> shift) & 0xFF) as f32;
+        let cb = ((b >> shift) & 0xFF) as f32;
+        let c = (ca + (cb - ca) * t + 0.5) as u32;
+        out |= c.min(255) << shift;
+    }
+    out
+}
@@ -0,0 +1,135 @@
+//! Game Boy Player
+//!
+//! The GameCube's Game Boy Player sits on the link port. Games that support
+//! its rumble detect it in two steps:
+//! 1. While the "Game Boy Player" boot logo is on screen, the player reports
+//!    all four directions held on every third frame, which no real keypad
+//!    can do. The logo is recognised by hashing its tile data.
+//! 2. The game then runs a fixed handshake over 32-bit normal-mode SIO and,
+//!    from the thirteenth transfer on, sends rumble commands in the low
+//!    byte: 0x22 starts the motor, 0x00 and 0x11 stop it.
+
+/// Host hook called with `true` when the motor starts and `false` when it stops
+pub(crate) type RumbleCallback = Box<dyn FnMut(bool) + Send>;
+
+/// Replies the player sends, one per transfer; the last repeats
+const TX_DATA: [u32; 13] = [
+    0x0000_494E, 0x0000_494E, 0xB6B1_494E, 0xB6B1_544E, 0xABB1_544E, 0xABB1_4E45, 0xB1BA_4E45,
+    0xB1BA_4F44, 0xB0BB_4F44, 0xB0BB_8002, 0x1000_0010, 0x2000_0013, 0x3000_0003,
+];
+
+/// MurmurHash3 of the logo tile data at 0x0600_4000-0x0600_7FFF, the
+/// fingerprint mGBA uses for the same check
+const LOGO_HASH: u32 = 0xEEDA_6963;
+const LOGO_TILES: std::ops::Range<usize> = 0x4000..0x8000;
+
+/// KEYINPUT with Up, Down, Left and Right held
+pub(crate) const SIGNATURE_KEYS: u16 = 0x030F;
+
+pub(crate) struct GameBoyPlayer {
+    /// The logo has been seen since the player was plugged in
+    detected: bool,
+    /// Frame counter modulo 3 while the logo is up; the signature shows on 2
+    inputs_posted: u8,
+    logo_on_screen: bool,
+    tx_position: usize,
+    rumble: RumbleCallback,
+    rumbling: bool,
+}
+
+impl GameBoyPlayer {
+    pub(crate) fn new(rumble: RumbleCallback) -> Self {
+        Self {
+            detected: false,
+            inputs_posted: 0,
+            logo_on_screen: false,
+            tx_position: 0,
+            rumble,
+            rumbling: false,
+        }
+    }
+
+    /// Back to undetected, as when the console restarts; a running motor stops
+    pub(crate) fn reset(&mut self) {
+        self.detected = false;
+        self.inputs_posted = 0;
+        self.logo_on_screen = false;
+        self.tx_position = 0;
+        if self.rumbling {
+            self.rumbling = false;
+            (self.rumble)(false);
+        }
+    }
+
+    pub(crate) fn detected(&self) -> bool {
+        self.detected
+    }
+
+    /// Called once per frame at VBlank with the current VRAM
+    pub(crate) fn frame(&mut self, vram: &[u8]) {
+        self.logo_on_screen = murmur3(vram.get(LOGO_TILES).unwrap_or(&[]), 0) == LOGO_HASH;
+        if self.detected {
+            if self.logo_on_screen {
+                self.inputs_posted = self.inputs_posted.wrapping_add(1) % 3;
+            }
+            self.tx_position = 0;
+        } else if self.logo_on_screen {
+            self.detected = true;
+            self.inputs_posted = 0;
+        }
+    }
+
+    /// KEYINPUT override for this frame, if the signature is showing
+    pub(crate) fn keyinput(&self) -> Option<u16> {
+        (self.logo_on_screen && self.inputs_posted == 2).then_some(SIGNATURE_KEYS)
+    }
+
+    /// One 32-bit transfer: takes the game's word and returns the reply
+    pub(crate) fn transfer(&mut self, rx: u32) -> u32 {
+        if self.tx_position >= 12 {
+            let rumbling = rx & 0x33 == 0x22;
+            if rumbling != self.rumbling {
+                self.rumbling = rumbling;
+                (self.rumble)(rumbling);
+            }
+        }
+        if self.tx_position > 16 {
+            self.tx_position = 0;
+        }
+        let tx = TX_DATA
+            .get(self.tx_position)
+            .copied()
+            .unwrap_or(TX_DATA[12]);
+        self.tx_position = self.tx_position.wrapping_add(1);
+        tx
+    }
+}
+
+/// MurmurHash3 (x86, 32-bit)
+fn murmur3(data: &[u8], seed: u32) -> u32 {
+    const C1: u32 = 0xCC9E_2D51;
+    const C2: u32 = 0x1B87_3593;
+    let mix = |k: u32| k.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2);
+
+    let mut h = seed;
+    let mut blocks = data.chunks_exact(4);
+    for block in &mut blocks {
+        let k = block.iter().rev().fold(0u32, |k, &b| (k << 8) | b as u32);
+        h = (h ^ mix(k)).rotate_left(13).wrapping_mul(5).wrapping_add(0xE654_6B64);
+    }
+    let tail = blocks.remainder();
+    if !tail.is_empty() {
+        let k = tail
+            .iter()
+            .rev()
+            .fold(0u32, |k, &b| (k << 8) | b as u32);
+        h ^= mix(k);
+    }
+
+    h ^= data.len() as u32;
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x85EB_CA6B);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0xC2B2_AE35);
+    h ^ (h >> 16)
+}
@@ -0,0 +1,294 @@
+//! Foreign Save State Import
+//!
+//! Best-effort loaders for save states written by mGBA (`.ss0`-`.ss9`, raw or
+//! embedded in a PNG screenshot) and VBA-M (`.sgm`, gzip-compressed). Only the
+//! CPU registers, RAM, VRAM/OAM/palette and I/O registers are carried over;
+//! timer counters, audio state and in-flight DMA progress are rebuilt from
+//! the imported I/O registers, so a game may glitch for a frame after import.
+
+use std::io::Read;
+
+use flate2::read::{GzDecoder, ZlibDecoder};
+
+use crate::savestate::StateError;
+use crate::view::ByteView;
+use crate::Gba;
+
+/// Size of mGBA's `GBASerializedState`
+const MGBA_STATE_SIZE: usize = 0x61000;
+const PNG_SIGNATURE: &[u8; 8] = b"\x89PNG\r\n\x1a\n";
+
+/// Oldest VBA-M state version whose memory layout is understood
+const VBA_MIN_VERSION: u32 = 5;
+/// VBA-M's `saveGameStruct`: 77 halfword registers followed by timing,
+/// flag and DMA variables
+const VBA_STRUCT_SIZE: usize = 267;
+
+/// Emulator-neutral register and memory image
+struct ForeignState<'a> {
+    regs: [u32; 16],
+    cpsr: u32,
+    /// FIQ, IRQ, SVC, ABT, UND, SYS order
+    banked_sp: [u32; 6],
+    banked_lr: [u32; 6],
+    banked_spsr: [u32; 6],
+    other_r8_12: [u32; 5],
+    title: &'a [u8],
+    iwram: &'a [u8],
+    wram: &'a [u8],
+    io: &'a [u8],
+    palette: &'a [u8],
+    vram: &'a [u8],
+    oam: &'a [u8],
+}
+
+/// Our banked register slot (FIQ, IRQ, SVC, ABT, UND, SYS) for a CPSR mode
+fn bank_index(cpsr: u32) -> usize {
+    match cpsr & 0x1F {
+        0x11 => 0,
+        0x12 => 1,
+        0x13 => 2,
+        0x17 => 3,
+        0x1B => 4,
+        _ => 5,
+    }
+}
+
+fn word(data: &[u8], offset: usize) -> Result<u32, StateError> {
+    Ok(block(data, offset, 4)?.word_at(0))
+}
+
+/// Word `index` of the word table starting at `base`
+fn table_word(data: &[u8], base: usize, index: usize) -> Result<u32, StateError> {
+    word(data, base.wrapping_add(index << 2))
+}
+
+fn block(data: &[u8], offset: usize, len: usize) -> Result<&[u8], StateError> {
+    let end = offset.checked_add(len).ok_or(StateError::Truncated)?;
+    data.get(offset..end).ok_or(StateError::Truncated)
+}
+
+/// Pull the zlib-compressed `gbAs` chunk out of an mGBA PNG state
+fn png_state_chunk(data: &[u8]) -> Result<Vec<u8>, StateError> {
+    let mut pos = PNG_SIGNATURE.len();
+    while let Some(&[l0, l1, l2, l3, ref kind @ ..]) = data.get(pos..pos.saturating_add(8)) {
+        let len = u32::from_be_bytes([l0, l1, l2, l3]) as usize;
+        let body = block(data, pos.saturating_add(8), len)?;
+        if kind == b"gbAs" {
+            let mut out = Vec::with_capacity(MGBA_STATE_SIZE);
+            ZlibDecoder::new(body)
+                .read_to_end(&mut out)
+                .map_err(|_| StateError::Truncated)?;
+            return Ok(out);
+        }
+        pos = pos.saturating_add(12).saturating_add(len);
+    }
+    Err(StateError::BadMagic)
+}
+
+fn parse_mgba(data: &[u8]) -> Result<ForeignState<'_>, StateError> {
+    let version = word(data, 0)?;
+    if version & 0xFF00_0000 != 0x0100_0000 {
+        return Err(StateError::BadMagic);
+    }
+    if data.len() < MGBA_STATE_SIZE {
+        return Err(StateError::Truncated);
+    }
+
+    let mut regs = [0u32; 16];
+    for (i, r) in regs.iter_mut().enumerate() {
+        *r = table_word(data, 0x20, i)?;
+    }
+    let cpsr = word(data, 0x60)?;
+    // mGBA keeps PC one instruction ahead of the next one to execute
+    regs[15] = regs[15].wrapping_sub(if cpsr & (1 << 5) != 0 { 2 } else { 4 });
+
+    // mGBA banks: 0 user/system, 1 FIQ, 2 IRQ, 3 SVC, 4 ABT, 5 UND;
+    // each holds SP, LR, then R8-R12 (banks 0 and 1 only)
+    let banked =
+        |bank: usize, reg: usize| table_word(data, 0x70, bank.wrapping_mul(7).wrapping_add(reg));
+    let mut banked_sp = [0u32; 6];
+    let mut banked_lr = [0u32; 6];
+    let mut banked_spsr = [0u32; 6];
+    let ours = banked_sp
+        .iter_mut()
+        .zip(&mut banked_lr)
+        .zip(&mut banked_spsr);
+    for (((sp, lr), spsr), bank) in ours.zip([1, 2, 3, 4, 5, 0]) {
+        *sp = banked(bank, 0)?;
+        *lr = banked(bank, 1)?;
+        *spsr = table_word(data, 0x118, bank)?;
+    }
+    if let Some(spsr) = banked_spsr.get_mut(bank_index(cpsr)) {
+        *spsr = word(data, 0x64)?;
+    }
+    let inactive_bank = if cpsr & 0x1F == 0x11 { 0 } else { 1 };
+    let mut other_r8_12 = [0u32; 5];
+    for (i, r) in other_r8_12.iter_mut().enumerate() {
+        *r = banked(inactive_bank, i.wrapping_add(2))?;
+    }
+
+    Ok(ForeignState {
+        regs,
+        cpsr,
+        banked_sp,
+        banked_lr,
+        banked_spsr,
+        other_r8_12,
+        title: block(data, 0x10, 12)?,
+        io: block(data, 0x400, 0x400)?,
+        palette: block(data, 0x800, 0x400)?,
+        oam: block(data, 0xC00, 0x400)?,
+        vram: block(data, 0x1000, 0x18000)?,
+        iwram: block(data, 0x19000, 0x8000)?,
+        wram: block(data, 0x21000, 0x40000)?,
+    })
+}
+
+fn parse_vba(data: &[u8]) -> Result<ForeignState<'_>, StateError> {
+    let version = word(data, 0)?;
+    if version == 0 || version > 0xFF {
+        return Err(StateError::BadMagic);
+    }
+    if version < VBA_MIN_VERSION {
+        return Err(StateError::UnsupportedVersion(version));
+    }
+
+    // reg[45]: R0-R15, CPSR, SPSR, then banked registers by fixed slot
+    let reg = |n: usize| table_word(data, 24, n);
+    let mut regs = [0u32; 16];
+    for (i, r) in regs.iter_mut().enumerate() {
+        *r = reg(i)?;
+    }
+
+    let vars = 24 + 45 * 4;
+    let &[n, c, z, v, arm_state, irq_enable] = block(data, vars + 249, 6)? else {
+        return Err(StateError::Truncated);
+    };
+    let next_pc = word(data, vars + 255)?;
+    let mut cpsr = reg(16)? & !0xF000_00A0;
+    for (set, bit) in [n, c, z, v]
+        .into_iter()
+        .zip([1 << 31, 1 << 29, 1 << 30, 1 << 28])
+    {
+        if set != 0 {
+            cpsr |= bit;
+        }
+    }
+    if arm_state == 0 {
+        cpsr |= 1 << 5;
+    }
+    if irq_enable == 0 {
+        cpsr |= 1 << 7;
+    }
+    regs[15] = next_pc;
+
+    // FIQ, IRQ, SVC, ABT, UND slots as (SP, LR, SPSR); USR has no SPSR
+    let slots = [
+        (42, 43, 44),
+        (18, 19, 20),
+        (28, 29, 30),
+        (31, 32, 33),
+        (34, 35, 36),
+    ];
+    let mut banked_sp = [reg(26)?; 6];
+    let mut banked_lr = [reg(27)?; 6];
+    let mut banked_spsr = [0u32; 6];
+    let ours = banked_sp
+        .iter_mut()
+        .zip(&mut banked_lr)
+        .zip(&mut banked_spsr);
+    for (((sp, lr), spsr), &(sp_reg, lr_reg, spsr_reg)) in ours.zip(&slots) {
+        *sp = reg(sp_reg)?;
+        *lr = reg(lr_reg)?;
+        *spsr = reg(spsr_reg)?;
+    }
+    if let Some(spsr) = banked_spsr.get_mut(bank_index(cpsr)) {
+        *spsr = reg(17)?;
+    }
+    let mut other_r8_12 = [0u32; 5];
+    for (i, r) in other_r8_12.iter_mut().enumerate() {
+        *r = reg(i.wrapping_add(37))?;
+    }
+
+    let mut pos = vars + VBA_STRUCT_SIZE;
+    if version >= 6 {
+        pos = pos.saturating_add(8); // stopState, IRQTicks
+    }
+    let mut take = |len: usize| {
+        let b = block(data, pos, len);
+        pos = pos.saturating_add(len);
+        b
+    };
+    let iwram = take(0x8000)?;
+    let palette = take(0x400)?;
+    let wram = take(0x40000)?;
+    let vram = take(0x20000)?;
+    let oam = take(0x400)?;
+    take(4 * 241 * 162)?; // screen buffer
+    let io = take(0x400)?;
+
+    Ok(ForeignState {
+        regs,
+        cpsr,
+        banked_sp,
+        banked_lr,
+        banked_spsr,
+        other_r8_12,
+        title: block(data, 4, 12)?,
+        iwram,
+        wram,
+        io,
+        palette,
+        vram,
+        oam,
+    })
+}
+
+impl Gba {
+    /// Import an mGBA save state (raw or PNG-embedded) for the loaded ROM
+    pub fn import_mgba_state(&mut self, data: &[u8]) -> Result<(), StateError> {
+        if data.starts_with(PNG_SIGNATURE) {
+            let raw = png_state_chunk(data)?;
+            return self.apply_foreign_state(parse_mgba(&raw)?);
+        }
+        self.apply_foreign_state(parse_mgba(data)?)
+    }
+
+    /// Import a VBA-M `.sgm` save state for the loaded ROM
+    pub fn import_vba_state(&mut self, data: &[u8]) -> Result<(), StateError> {
+        let mut raw = Vec::new();
+        GzDecoder::new(data)
+            .read_to_end(&mut raw)
+            .map_err(|_| StateError::BadMagic)?;
+        self.apply_foreign_state(parse_vba(&raw)?)
+    }
+
+    fn apply_foreign_state(&mut self, state: ForeignState) -> Result<(), StateError> {
+        let rom = self.mem.rom();
+        let title = state.title.get(..12);
+        if rom.get(0xA0..0xAC).is_some_and(|ours| Some(ours) != title) {
+            return Err(StateError::RomMismatch);
+        }
+
+        self.cpu.import_registers(
+            state.regs,
+            state.cpsr,
+            state.banked_sp,
+            state.banked_lr,
+            state.banked_spsr,
+            state.other_r8_12,
+        );
+        self.mem.import_regions(
+            state.iwram,
+            state.wram,
+            state.io,
+            state.palette,
+            state.vram,
+            state.oam,
+        );
+        self.sync_io_to_components();
+        self.sync_ppu_full();
+        Ok(())
+    }
+}
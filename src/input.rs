@@ -8,6 +8,8 @@
 
 use bitflags::bitflags;
 
+use crate::savestate::{StateError, StateReader, StateWriter};
+
 bitflags! {
     /// Keypad state bits
     #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -29,6 +31,9 @@ bitflags! {
 pub struct Input {
     keys: KeyState,
     keys_changed: KeyState,
+    /// Keys the CPU sees while frame latching is on
+    latched: KeyState,
+    frame_latch: bool,
 }
 
 impl Input {
@@ -36,12 +41,33 @@ impl Input {
         Self {
             keys: KeyState::all(), // All keys "released" (active low)
             keys_changed: KeyState::empty(),
+            latched: KeyState::all(),
+            frame_latch: false,
         }
     }
 
     pub fn reset(&mut self) {
         self.keys = KeyState::all();
         self.keys_changed = KeyState::empty();
+        self.latched = KeyState::all();
+    }
+
+    /// Latch input once per frame instead of propagating every key change
+    /// immediately. While on, KEYINPUT only changes when [`Input::latch`]
+    /// runs (at VBlank), so a replay sees the same keys on the same frame no
+    /// matter when the frontend polled the host.
+    pub fn set_frame_latch(&mut self, enabled: bool) {
+        self.frame_latch = enabled;
+        self.latched = self.keys;
+    }
+
+    pub fn frame_latch(&self) -> bool {
+        self.frame_latch
+    }
+
+    /// Make the current keys visible to the CPU
+    pub fn latch(&mut self) {
+        self.latched = self.keys;
     }
 
     /// Check if a key is pressed
@@ -52,7 +78,7 @@ impl Input {
     /// Press a key
     pub fn press_key(&mut self, key: KeyState) {
         if self.keys.contains(key) {
-            self.keys -= key;
+            self.keys.remove(key);
             self.keys_changed |= key;
         }
     }
@@ -65,12 +91,45 @@ impl Input {
         }
     }
 
+    /// Set all ten keys at once from an active-high mask in KEYINPUT bit
+    /// order: a set bit is a pressed key, bits 10-15 are ignored
+    pub fn set_all_keys(&mut self, mask: u16) {
+        let keys = KeyState::from_bits_truncate(!mask);
+        self.keys_changed |= self.keys ^ keys;
+        self.keys = keys;
+    }
+
+    /// Pressed keys as an active-high mask in KEYINPUT bit order, the
+    /// inverse of [`Input::set_all_keys`]
+    pub fn pressed_keys(&self) -> u16 {
+        !self.keys.bits() & KeyState::all().bits()
+    }
+
     /// Get current key state as register value
     /// GBA key input is active-low: 0 = pressed, 1 = released
     pub fn get_key_register(&self) -> u16 {
         // Bits 0-9: Key states (active low)
         // Bits 10-15: Always set to 1
-        (self.keys.bits() & 0x03FF) | 0xFC00
+        let keys = if self.frame_latch {
+            self.latched
+        } else {
+            self.keys
+        };
+        (keys.bits() & 0x03FF) | 0xFC00
+    }
+}
+
+impl Input {
+    pub(crate) fn save_state(&self, w: &mut StateWriter) {
+        w.write_u16(self.keys.bits());
+        w.write_u16(self.latched.bits());
+    }
+
+    pub(crate) fn load_state(&mut self, r: &mut StateReader) -> Result<(), StateError> {
+        self.keys = KeyState::from_bits_truncate(r.read_u16()?);
+        self.latched = KeyState::from_bits_truncate(r.read_u16()?);
+        self.keys_changed = KeyState::empty();
+        Ok(())
     }
 }
 
@@ -0,0 +1,534 @@
+//! Memory-Mapped I/O Register Descriptions
+//!
+//! Names and bitfield layouts for the registers at 0x0400_0000-0x0400_03FF
+//! (per GBATEK), so debuggers and diagnostics can show decoded values
+//! instead of hand-decoding raw halfwords.
+
+use std::fmt;
+
+/// One bitfield inside an I/O register
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IoField {
+    pub name: &'static str,
+    pub shift: u8,
+    pub width: u8,
+}
+
+impl IoField {
+    /// Extract this field from a raw register value
+    pub fn extract(&self, value: u32) -> u32 {
+        let mask = if self.width >= 32 {
+            u32::MAX
+        } else {
+            (1u32 << self.width).wrapping_sub(1)
+        };
+        (value >> self.shift) & mask
+    }
+}
+
+/// Static description of an I/O register
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IoRegInfo {
+    pub name: &'static str,
+    /// Offset from 0x0400_0000
+    pub offset: u32,
+    /// Width in bytes (1, 2 or 4)
+    pub size: u8,
+    pub fields: &'static [IoField],
+}
+
+impl IoRegInfo {
+    /// Absolute bus address of the register
+    pub fn addr(&self) -> u32 {
+        0x0400_0000u32.wrapping_add(self.offset)
+    }
+
+    /// Split a raw value into (field name, field value) pairs
+    pub fn decode(&self, value: u32) -> Vec<(&'static str, u32)> {
+        self.fields
+            .iter()
+            .map(|f| (f.name, f.extract(value)))
+            .collect()
+    }
+
+    /// Read the register's current value from a raw I/O byte array
+    pub fn read(&self, io: &[u8]) -> u32 {
+        let start = self.offset as usize;
+        io.get(start..start.wrapping_add(self.size as usize))
+            .map(|bytes| {
+                bytes
+                    .iter()
+                    .rev()
+                    .fold(0u32, |acc, &b| (acc << 8) | b as u32)
+            })
+            .unwrap_or(0)
+    }
+}
+
+/// A register's description together with a value read from it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IoRegValue {
+    pub info: IoRegInfo,
+    pub value: u32,
+}
+
+impl IoRegValue {
+    /// Each field of the register with its decoded value
+    pub fn fields(&self) -> impl Iterator<Item = (IoField, u32)> + '_ {
+        self.info.fields.iter().map(|f| (*f, f.extract(self.value)))
+    }
+
+    /// Decoded value of the field called `name`
+    pub fn field(&self, name: &str) -> Option<u32> {
+        self.fields()
+            .find(|(f, _)| f.name == name)
+            .map(|(_, value)| value)
+    }
+}
+
+impl fmt::Display for IoRegValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let info = &self.info;
+        write!(f, "{} {:08X} = {:X}", info.name, info.addr(), self.value)?;
+        for (i, (field, value)) in self.fields().enumerate() {
+            let sep = if i == 0 { ": " } else { ", " };
+            write!(f, "{}{}={}", sep, field.name, value)?;
+        }
+        Ok(())
+    }
+}
+
+const fn field(name: &'static str, shift: u8, width: u8) -> IoField {
+    IoField { name, shift, width }
+}
+
+const fn reg(name: &'static str, offset: u32, size: u8, fields: &'static [IoField]) -> IoRegInfo {
+    IoRegInfo {
+        name,
+        offset,
+        size,
+        fields,
+    }
+}
+
+const DISPCNT: &[IoField] = &[
+    field("BG Mode", 0, 3),
+    field("CGB Mode", 3, 1),
+    field("Display Frame Select", 4, 1),
+    field("H-Blank Interval Free", 5, 1),
+    field("OBJ Character VRAM Mapping", 6, 1),
+    field("Forced Blank", 7, 1),
+    field("Screen Display BG0", 8, 1),
+    field("Screen Display BG1", 9, 1),
+    field("Screen Display BG2", 10, 1),
+    field("Screen Display BG3", 11, 1),
+    field("Screen Display OBJ", 12, 1),
+    field("Window 0 Display", 13, 1),
+    field("Window 1 Display", 14, 1),
+    field("OBJ Window Display", 15, 1),
+];
+
+const DISPSTAT: &[IoField] = &[
+    field("V-Blank flag", 0, 1),
+    field("H-Blank flag", 1, 1),
+    field("V-Counter flag", 2, 1),
+    field("V-Blank IRQ Enable", 3, 1),
+    field("H-Blank IRQ Enable", 4, 1),
+    field("V-Counter IRQ Enable", 5, 1),
+    field("V-Count Setting", 8, 8),
+];
+
+const VCOUNT: &[IoField] = &[field("Current Scanline", 0, 8)];
+
+const BGCNT: &[IoField] = &[
+    field("BG Priority", 0, 2),
+    field("Character Base Block", 2, 2),
+    field("Mosaic", 6, 1),
+    field("Colors/Palettes", 7, 1),
+    field("Screen Base Block", 8, 5),
+    field("Display Area Overflow", 13, 1),
+    field("Screen Size", 14, 2),
+];
+
+const BGOFS: &[IoField] = &[field("Offset", 0, 9)];
+
+const BGP: &[IoField] = &[
+    field("Fraction", 0, 8),
+    field("Integer", 8, 7),
+    field("Sign", 15, 1),
+];
+
+const BGREF: &[IoField] = &[
+    field("Fraction", 0, 8),
+    field("Integer", 8, 19),
+    field("Sign", 27, 1),
+];
+
+const WINH: &[IoField] = &[
+    field("X2 (right, exclusive)", 0, 8),
+    field("X1 (left)", 8, 8),
+];
+
+const WINV: &[IoField] = &[
+    field("Y2 (bottom, exclusive)", 0, 8),
+    field("Y1 (top)", 8, 8),
+];
+
+const WININ: &[IoField] = &[
+    field("Window 0 BG0-3", 0, 4),
+    field("Window 0 OBJ", 4, 1),
+    field("Window 0 Color Effect", 5, 1),
+    field("Window 1 BG0-3", 8, 4),
+    field("Window 1 OBJ", 12, 1),
+    field("Window 1 Color Effect", 13, 1),
+];
+
+const WINOUT: &[IoField] = &[
+    field("Outside BG0-3", 0, 4),
+    field("Outside OBJ", 4, 1),
+    field("Outside Color Effect", 5, 1),
+    field("OBJ Window BG0-3", 8, 4),
+    field("OBJ Window OBJ", 12, 1),
+    field("OBJ Window Color Effect", 13, 1),
+];
+
+const MOSAIC: &[IoField] = &[
+    field("BG Mosaic H-Size", 0, 4),
+    field("BG Mosaic V-Size", 4, 4),
+    field("OBJ Mosaic H-Size", 8, 4),
+    field("OBJ Mosaic V-Size", 12, 4),
+];
+
+const BLDCNT: &[IoField] = &[
+    field("1st Target BG0", 0, 1),
+    field("1st Target BG1", 1, 1),
+    field("1st Target BG2", 2, 1),
+    field("1st Target BG3", 3, 1),
+    field("1st Target OBJ", 4, 1),
+    field("1st Target BD", 5, 1),
+    field("Color Special Effect", 6, 2),
+    field("2nd Target BG0", 8, 1),
+    field("2nd Target BG1", 9, 1),
+    field("2nd Target BG2", 10, 1),
+    field("2nd Target BG3", 11, 1),
+    field("2nd Target OBJ", 12, 1),
+    field("2nd Target BD", 13, 1),
+];
+
+const BLDALPHA: &[IoField] = &[
+    field("EVA Coefficient", 0, 5),
+    field("EVB Coefficient", 8, 5),
+];
+
+const BLDY: &[IoField] = &[field("EVY Coefficient", 0, 5)];
+
+const SOUND1CNT_L: &[IoField] = &[
+    field("Sweep Shift", 0, 3),
+    field("Sweep Direction", 3, 1),
+    field("Sweep Time", 4, 3),
+];
+
+const SOUNDDUTY: &[IoField] = &[
+    field("Sound Length", 0, 6),
+    field("Wave Pattern Duty", 6, 2),
+    field("Envelope Step Time", 8, 3),
+    field("Envelope Direction", 11, 1),
+    field("Initial Volume", 12, 4),
+];
+
+const SOUNDFREQ: &[IoField] = &[
+    field("Frequency", 0, 11),
+    field("Length Flag", 14, 1),
+    field("Initial", 15, 1),
+];
+
+const SOUND3CNT_L: &[IoField] = &[
+    field("Wave RAM Dimension", 5, 1),
+    field("Wave RAM Bank", 6, 1),
+    field("Sound Channel 3 Off", 7, 1),
+];
+
+const SOUND3CNT_H: &[IoField] = &[
+    field("Sound Length", 0, 8),
+    field("Sound Volume", 13, 2),
+    field("Force Volume", 15, 1),
+];
+
+const SOUND4CNT_L: &[IoField] = &[
+    field("Sound Length", 0, 6),
+    field("Envelope Step Time", 8, 3),
+    field("Envelope Direction", 11, 1),
+    field("Initial Volume", 12, 4),
+];
+
+const SOUND4CNT_H: &[IoField] = &[
+    field("Dividing Ratio", 0, 3),
+    field("Counter Step/Width", 3, 1),
+    field("Shift Clock Frequency", 4, 4),
+    field("Length Flag", 14, 1),
+    field("Initial", 15, 1),
+];
+
+const SOUNDCNT_L: &[IoField] = &[
+    field("Master Volume Right", 0, 3),
+    field("Master Volume Left", 4, 3),
+    field("Sound 1-4 Enable Right", 8, 4),
+    field("Sound 1-4 Enable Left", 12, 4),
+];
+
+const SOUNDCNT_H: &[IoField] = &[
+    field("Sound 1-4 Volume", 0, 2),
+    field("DMA Sound A Volume", 2, 1),
+    field("DMA Sound B Volume", 3, 1),
+    field("DMA Sound A Enable Right", 8, 1),
+    field("DMA Sound A Enable Left", 9, 1),
+    field("DMA Sound A Timer Select", 10, 1),
+    field("DMA Sound A Reset FIFO", 11, 1),
+    field("DMA Sound B Enable Right", 12, 1),
+    field("DMA Sound B Enable Left", 13, 1),
+    field("DMA Sound B Timer Select", 14, 1),
+    field("DMA Sound B Reset FIFO", 15, 1),
+];
+
+const SOUNDCNT_X: &[IoField] = &[
+    field("Sound 1 ON flag", 0, 1),
+    field("Sound 2 ON flag", 1, 1),
+    field("Sound 3 ON flag", 2, 1),
+    field("Sound 4 ON flag", 3, 1),
+    field("PSG/FIFO Master Enable", 7, 1),
+];
+
+const SOUNDBIAS: &[IoField] = &[
+    field("Bias Level", 1, 9),
+    field("Amplitude Resolution", 14, 2),
+];
+
+const RAW16: &[IoField] = &[field("Value", 0, 16)];
+
+const RAW32: &[IoField] = &[field("Value", 0, 32)];
+
+const DMAADDR: &[IoField] = &[field("Address", 0, 28)];
+
+const DMACNT_L: &[IoField] = &[field("Word Count", 0, 16)];
+
+const DMACNT_H: &[IoField] = &[
+    field("Dest Addr Control", 5, 2),
+    field("Source Addr Control", 7, 2),
+    field("DMA Repeat", 9, 1),
+    field("DMA Transfer Type", 10, 1),
+    field("Game Pak DRQ", 11, 1),
+    field("DMA Start Timing", 12, 2),
+    field("IRQ upon end of Word Count", 14, 1),
+    field("DMA Enable", 15, 1),
+];
+
+const TMCNT_L: &[IoField] = &[field("Counter/Reload", 0, 16)];
+
+const TMCNT_H: &[IoField] = &[
+    field("Prescaler Selection", 0, 2),
+    field("Count-up Timing", 2, 1),
+    field("Timer IRQ Enable", 6, 1),
+    field("Timer Start/Stop", 7, 1),
+];
+
+const SIOCNT: &[IoField] = &[
+    field("Baud Rate / Shift Clock", 0, 2),
+    field("SI Terminal", 2, 1),
+    field("SD Terminal", 3, 1),
+    field("Error / Start Bit", 6, 1),
+    field("Start/Busy", 7, 1),
+    field("Transfer Length", 12, 1),
+    field("Communication Mode", 13, 2),
+    field("IRQ Enable", 14, 1),
+];
+
+const KEYINPUT: &[IoField] = &[
+    field("Button A", 0, 1),
+    field("Button B", 1, 1),
+    field("Select", 2, 1),
+    field("Start", 3, 1),
+    field("Right", 4, 1),
+    field("Left", 5, 1),
+    field("Up", 6, 1),
+    field("Down", 7, 1),
+    field("Button R", 8, 1),
+    field("Button L", 9, 1),
+];
+
+const KEYCNT: &[IoField] = &[
+    field("Button Mask", 0, 10),
+    field("IRQ Enable", 14, 1),
+    field("IRQ Condition", 15, 1),
+];
+
+const RCNT: &[IoField] = &[
+    field("SC/SD/SI/SO Data", 0, 4),
+    field("SC/SD/SI/SO Direction", 4, 4),
+    field("SI Interrupt Enable", 8, 1),
+    field("Mode", 14, 2),
+];
+
+const IRQ: &[IoField] = &[
+    field("LCD V-Blank", 0, 1),
+    field("LCD H-Blank", 1, 1),
+    field("LCD V-Counter Match", 2, 1),
+    field("Timer 0 Overflow", 3, 1),
+    field("Timer 1 Overflow", 4, 1),
+    field("Timer 2 Overflow", 5, 1),
+    field("Timer 3 Overflow", 6, 1),
+    field("Serial Communication", 7, 1),
+    field("DMA 0", 8, 1),
+    field("DMA 1", 9, 1),
+    field("DMA 2", 10, 1),
+    field("DMA 3", 11, 1),
+    field("Keypad", 12, 1),
+    field("Game Pak", 13, 1),
+];
+
+const WAITCNT: &[IoField] = &[
+    field("SRAM Wait Control", 0, 2),
+    field("Wait State 0 First Access", 2, 2),
+    field("Wait State 0 Second Access", 4, 1),
+    field("Wait State 1 First Access", 5, 2),
+    field("Wait State 1 Second Access", 7, 1),
+    field("Wait State 2 First Access", 8, 2),
+    field("Wait State 2 Second Access", 10, 1),
+    field("PHI Terminal Output", 11, 2),
+    field("Game Pak Prefetch Buffer", 14, 1),
+    field("Game Pak Type Flag", 15, 1),
+];
+
+const IME: &[IoField] = &[field("Master Enable", 0, 1)];
+
+const POSTFLG: &[IoField] = &[field("First Boot Flag", 0, 1)];
+
+const HALTCNT: &[IoField] = &[field("Stop Mode", 7, 1)];
+
+/// Every documented register, sorted by offset
+pub static REGISTERS: &[IoRegInfo] = &[
+    reg("DISPCNT", 0x000, 2, DISPCNT),
+    reg("GREENSWAP", 0x002, 2, RAW16),
+    reg("DISPSTAT", 0x004, 2, DISPSTAT),
+    reg("VCOUNT", 0x006, 2, VCOUNT),
+    reg("BG0CNT", 0x008, 2, BGCNT),
+    reg("BG1CNT", 0x00A, 2, BGCNT),
+    reg("BG2CNT", 0x00C, 2, BGCNT),
+    reg("BG3CNT", 0x00E, 2, BGCNT),
+    reg("BG0HOFS", 0x010, 2, BGOFS),
+    reg("BG0VOFS", 0x012, 2, BGOFS),
+    reg("BG1HOFS", 0x014, 2, BGOFS),
+    reg("BG1VOFS", 0x016, 2, BGOFS),
+    reg("BG2HOFS", 0x018, 2, BGOFS),
+    reg("BG2VOFS", 0x01A, 2, BGOFS),
+    reg("BG3HOFS", 0x01C, 2, BGOFS),
+    reg("BG3VOFS", 0x01E, 2, BGOFS),
+    reg("BG2PA", 0x020, 2, BGP),
+    reg("BG2PB", 0x022, 2, BGP),
+    reg("BG2PC", 0x024, 2, BGP),
+    reg("BG2PD", 0x026, 2, BGP),
+    reg("BG2X", 0x028, 4, BGREF),
+    reg("BG2Y", 0x02C, 4, BGREF),
+    reg("BG3PA", 0x030, 2, BGP),
+    reg("BG3PB", 0x032, 2, BGP),
+    reg("BG3PC", 0x034, 2, BGP),
+    reg("BG3PD", 0x036, 2, BGP),
+    reg("BG3X", 0x038, 4, BGREF),
+    reg("BG3Y", 0x03C, 4, BGREF),
+    reg("WIN0H", 0x040, 2, WINH),
+    reg("WIN1H", 0x042, 2, WINH),
+    reg("WIN0V", 0x044, 2, WINV),
+    reg("WIN1V", 0x046, 2, WINV),
+    reg("WININ", 0x048, 2, WININ),
+    reg("WINOUT", 0x04A, 2, WINOUT),
+    reg("MOSAIC", 0x04C, 2, MOSAIC),
+    reg("BLDCNT", 0x050, 2, BLDCNT),
+    reg("BLDALPHA", 0x052, 2, BLDALPHA),
+    reg("BLDY", 0x054, 2, BLDY),
+    reg("SOUND1CNT_L", 0x060, 2, SOUND1CNT_L),
+    reg("SOUND1CNT_H", 0x062, 2, SOUNDDUTY),
+    reg("SOUND1CNT_X", 0x064, 2, SOUNDFREQ),
+    reg("SOUND2CNT_L", 0x068, 2, SOUNDDUTY),
+    reg("SOUND2CNT_H", 0x06C, 2, SOUNDFREQ),
+    reg("SOUND3CNT_L", 0x070, 2, SOUND3CNT_L),
+    reg("SOUND3CNT_H", 0x072, 2, SOUND3CNT_H),
+    reg("SOUND3CNT_X", 0x074, 2, SOUNDFREQ),
+    reg("SOUND4CNT_L", 0x078, 2, SOUND4CNT_L),
+    reg("SOUND4CNT_H", 0x07C, 2, SOUND4CNT_H),
+    reg("SOUNDCNT_L", 0x080, 2, SOUNDCNT_L),
+    reg("SOUNDCNT_H", 0x082, 2, SOUNDCNT_H),
+    reg("SOUNDCNT_X", 0x084, 2, SOUNDCNT_X),
+    reg("SOUNDBIAS", 0x088, 2, SOUNDBIAS),
+    reg("WAVE_RAM0", 0x090, 2, RAW16),
+    reg("WAVE_RAM1", 0x092, 2, RAW16),
+    reg("WAVE_RAM2", 0x094, 2, RAW16),
+    reg("WAVE_RAM3", 0x096, 2, RAW16),
+    reg("WAVE_RAM4", 0x098, 2, RAW16),
+    reg("WAVE_RAM5", 0x09A, 2, RAW16),
+    reg("WAVE_RAM6", 0x09C, 2, RAW16),
+    reg("WAVE_RAM7", 0x09E, 2, RAW16),
+    reg("FIFO_A", 0x0A0, 4, RAW32),
+    reg("FIFO_B", 0x0A4, 4, RAW32),
+    reg("DMA0SAD", 0x0B0, 4, DMAADDR),
+    reg("DMA0DAD", 0x0B4, 4, DMAADDR),
+    reg("DMA0CNT_L", 0x0B8, 2, DMACNT_L),
+    reg("DMA0CNT_H", 0x0BA, 2, DMACNT_H),
+    reg("DMA1SAD", 0x0BC, 4, DMAADDR),
+    reg("DMA1DAD", 0x0C0, 4, DMAADDR),
+    reg("DMA1CNT_L", 0x0C4, 2, DMACNT_L),
+    reg("DMA1CNT_H", 0x0C6, 2, DMACNT_H),
+    reg("DMA2SAD", 0x0C8, 4, DMAADDR),
+    reg("DMA2DAD", 0x0CC, 4, DMAADDR),
+    reg("DMA2CNT_L", 0x0D0, 2, DMACNT_L),
+    reg("DMA2CNT_H", 0x0D2, 2, DMACNT_H),
+    reg("DMA3SAD", 0x0D4, 4, DMAADDR),
+    reg("DMA3DAD", 0x0D8, 4, DMAADDR),
+    reg("DMA3CNT_L", 0x0DC, 2, DMACNT_L),
+    reg("DMA3CNT_H", 0x0DE, 2, DMACNT_H),
+    reg("TM0CNT_L", 0x100, 2, TMCNT_L),
+    reg("TM0CNT_H", 0x102, 2, TMCNT_H),
+    reg("TM1CNT_L", 0x104, 2, TMCNT_L),
+    reg("TM1CNT_H", 0x106, 2, TMCNT_H),
+    reg("TM2CNT_L", 0x108, 2, TMCNT_L),
+    reg("TM2CNT_H", 0x10A, 2, TMCNT_H),
+    reg("TM3CNT_L", 0x10C, 2, TMCNT_L),
+    reg("TM3CNT_H", 0x10E, 2, TMCNT_H),
+    reg("SIODATA32", 0x120, 4, RAW32),
+    reg("SIOMULTI2", 0x124, 2, RAW16),
+    reg("SIOMULTI3", 0x126, 2, RAW16),
+    reg("SIOCNT", 0x128, 2, SIOCNT),
+    reg("SIODATA8", 0x12A, 2, RAW16),
+    reg("KEYINPUT", 0x130, 2, KEYINPUT),
+    reg("KEYCNT", 0x132, 2, KEYCNT),
+    reg("RCNT", 0x134, 2, RCNT),
+    reg("JOYCNT", 0x140, 2, RAW16),
+    reg("JOY_RECV", 0x150, 4, RAW32),
+    reg("JOY_TRANS", 0x154, 4, RAW32),
+    reg("JOYSTAT", 0x158, 2, RAW16),
+    reg("IE", 0x200, 2, IRQ),
+    reg("IF", 0x202, 2, IRQ),
+    reg("WAITCNT", 0x204, 2, WAITCNT),
+    reg("IME", 0x208, 2, IME),
+    reg("POSTFLG", 0x300, 1, POSTFLG),
+    reg("HALTCNT", 0x301, 1, HALTCNT),
+];
+
+/// Look up the register covering the bus address `addr` (0x0400_0000-0x0400_03FF)
+pub fn describe(addr: u32) -> Option<IoRegInfo> {
+    describe_offset(addr.checked_sub(0x0400_0000)?)
+}
+
+/// Look up the register covering `offset` into I/O space
+pub fn describe_offset(offset: u32) -> Option<IoRegInfo> {
+    REGISTERS
+        .iter()
+        .find(|r| offset.wrapping_sub(r.offset) < r.size as u32)
+        .copied()
+}
+
+/// Look up a register by its GBATEK name (case-insensitive)
+pub fn by_name(name: &str) -> Option<IoRegInfo> {
+    REGISTERS
+        .iter()
+        .find(|r| r.name.eq_ignore_ascii_case(name))
+        .copied()
+}
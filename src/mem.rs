@@ -5,17 +5,58 @@
 //!
 //! Memory Map:
 //! - 0x0000_0000 - 0x0000_3FFF: BIOS (16KB)
-//! - 0x0200_0000 - 0x0203_FFFF: WRAM-B (256KB)
-//! - 0x0300_0000 - 0x0300_7FFF: IWRAM (32KB)
+//! - 0x0200_0000 - 0x0203_FFFF: WRAM-B (256KB, mirrored up to 0x02FF_FFFF)
+//! - 0x0300_0000 - 0x0300_7FFF: IWRAM (32KB, mirrored up to 0x03FF_FFFF)
 //! - 0x0400_0000 - 0x0400_03FE: IO Registers
 //! - 0x0500_0000 - 0x0500_03FF: Palette RAM (1KB)
 //! - 0x0600_0000 - 0x0601_7FFF: VRAM (96KB)
 //! - 0x0700_0000 - 0x0700_03FF: OAM (1KB)
 //! - 0x0800_0000 - 0x0DFF_FFFF: ROM (max 32MB)
 
+use std::collections::VecDeque;
+
 use bitflags::bitflags;
 
+#[cfg(feature = "stats")]
+mod heatmap;
+mod iomap;
+mod waitcnt;
+
+use crate::gbp::GameBoyPlayer;
+use crate::ppu::AffineParams;
+use crate::render::MemView;
+use crate::semihost::Semihost;
+use crate::sio::{self, GpioPort};
 use crate::{Eeprom, Flash};
+use crate::savestate::{StateError, StateReader, StateWriter};
+use crate::view::{ByteView, ByteViewMut};
+
+#[cfg(feature = "stats")]
+pub use heatmap::{AccessHeatmap, PageStats, RegionStats, HEATMAP_PAGE_SIZE};
+pub use waitcnt::{PhiOutput, WaitControl};
+
+pub(crate) use iomap::peek_registers;
+
+/// Entries kept in the video write log before further writes are dropped
+const VIDEO_WRITE_LOG_LIMIT: usize = 1 << 20;
+
+/// Game pak address space per waitstate region; larger ROMs are truncated
+const MAX_ROM_SIZE: usize = 0x200_0000;
+
+/// SOUNDCNT_X offset; bit 7 is the PSG/FIFO master power
+const SOUNDCNT_X: usize = 0x084;
+
+/// PSG registers (SOUND1CNT_L through SOUNDCNT_L) cleared and locked while sound is off
+const PSG_REGS: std::ops::RangeInclusive<usize> = 0x060..=0x081;
+
+/// Bits still writable while sound is off: the PSG length counters
+fn psg_powered_off_mask(offset: usize) -> u8 {
+    match offset {
+        0x062 | 0x068 | 0x078 => 0x3F, // square 1/2 and noise length
+        0x072 => 0xFF,                 // wave length
+        _ => 0,
+    }
+}
 
 /// Cartridge save type
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -49,6 +90,28 @@ bitflags! {
     }
 }
 
+/// What happened to an interrupt in an [`IrqEvent`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IrqEventKind {
+    /// A source raised its IF bit
+    Requested,
+    /// The CPU entered the IRQ handler for these pending interrupts
+    Dispatched,
+    /// The game cleared these IF bits by writing 1s
+    Acknowledged,
+}
+
+/// One entry of the interrupt history, see [`crate::Gba::irq_trace`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IrqEvent {
+    pub kind: IrqEventKind,
+    pub irq: Interrupt,
+    /// Emulated cycle count when the event was recorded
+    pub cycle: u64,
+    /// Next instruction the CPU was about to run (the interrupted PC for dispatches)
+    pub pc: u32,
+}
+
 /// GBA Interrupt Controller (embedded in Memory for IO register handling)
 pub struct InterruptController {
     /// Interrupt Enable register (0x0400_0200)
@@ -68,6 +131,12 @@ pub struct InterruptController {
 
     /// Whether we're currently in an interrupt handler
     pub in_interrupt: bool,
+
+    /// Most recent events, oldest first; empty while `history_len` is 0
+    history: VecDeque<IrqEvent>,
+    history_len: usize,
+    /// (cycle, pc) stamped on new history events, kept current by the Gba
+    clock: (u64, u32),
 }
 
 impl InterruptController {
@@ -79,6 +148,9 @@ impl InterruptController {
             if_processed: Interrupt::empty(),
             ime: false,
             in_interrupt: false,
+            history: VecDeque::new(),
+            history_len: 0,
+            clock: (0, 0),
         }
     }
 
@@ -89,11 +161,52 @@ impl InterruptController {
         self.if_processed = Interrupt::empty();
         self.ime = false;
         self.in_interrupt = false;
+        self.history.clear();
+    }
+
+    /// Keep the last `len` requests, dispatches and acknowledgements; 0 turns
+    /// the history off and drops it
+    pub fn set_history_len(&mut self, len: usize) {
+        self.history_len = len;
+        while self.history.len() > len {
+            self.history.pop_front();
+        }
+    }
+
+    pub fn history_enabled(&self) -> bool {
+        self.history_len > 0
+    }
+
+    /// Recorded events, oldest first
+    pub fn history(&self) -> impl Iterator<Item = &IrqEvent> {
+        self.history.iter()
+    }
+
+    /// Set the cycle and PC attached to events recorded from now on
+    pub(crate) fn set_clock(&mut self, cycle: u64, pc: u32) {
+        self.clock = (cycle, pc);
+    }
+
+    fn record(&mut self, kind: IrqEventKind, irq: Interrupt) {
+        if self.history_len == 0 || irq.is_empty() {
+            return;
+        }
+        if self.history.len() == self.history_len {
+            self.history.pop_front();
+        }
+        let (cycle, pc) = self.clock;
+        self.history.push_back(IrqEvent {
+            kind,
+            irq,
+            cycle,
+            pc,
+        });
     }
 
     /// Request an interrupt
     pub fn request(&mut self, interrupt: Interrupt) {
         self.if_raw |= interrupt;
+        self.record(IrqEventKind::Requested, interrupt);
     }
 
     /// Get pending interrupt (considering IE and IME)
@@ -116,17 +229,15 @@ impl InterruptController {
 
     /// Acknowledge an interrupt (clears IF bit)
     pub fn acknowledge(&mut self, interrupt: Interrupt) {
+        self.record(IrqEventKind::Acknowledged, self.if_raw & interrupt);
         self.if_raw &= !interrupt;
         self.if_processed &= !interrupt;
     }
 
-    /// Check if we should take an interrupt (IME must be set)
+    /// Check if the controller is asserting IRQ: IME set and an enabled
+    /// interrupt pending. The CPU takes it only while CPSR's I bit is clear.
     pub fn should_take_interrupt(&self) -> bool {
-        if !self.ime || self.in_interrupt {
-            return false;
-        }
-
-        !(self.ie & self.if_raw).is_empty()
+        self.ime && !(self.ie & self.if_raw).is_empty()
     }
 
     /// Check if HALT condition is met (IF & IE != 0, regardless of IME)
@@ -137,45 +248,92 @@ impl InterruptController {
         !(self.ie & self.if_raw).is_empty()
     }
 
-    /// Enter interrupt handler
+    /// STOP halts every clock, so only the keypad, cartridge and serial
+    /// interrupts, raised by external pins, can end it
+    pub fn should_wake_from_stop(&self) -> bool {
+        let external = Interrupt::KEYPAD | Interrupt::GAMEPAK | Interrupt::SERIAL;
+        !(self.ie & self.if_raw & external).is_empty()
+    }
+
+    /// Enter interrupt handler. IME is left alone, as on hardware; the CPU
+    /// masks further IRQs through CPSR until the handler returns.
     pub fn enter_interrupt(&mut self) {
+        self.record(IrqEventKind::Dispatched, self.ie & self.if_raw);
         self.in_interrupt = true;
-        self.ime = false; // IME is cleared on interrupt entry
     }
 
     /// Exit interrupt handler
     pub fn exit_interrupt(&mut self) {
         self.in_interrupt = false;
-        self.ime = true; // IME is restored on interrupt exit
     }
 
-    /// Read IO register
+    /// Read IE (0x200), IF (0x202) or IME (0x208); `offset` is from
+    /// 0x0400_0000 as in the memory map
     pub fn read_register(&self, offset: usize) -> u16 {
         match offset {
-            0x000 => self.ie.bits(),
-            0x002 => self.if_raw.bits(),
             0x200 => self.ie.bits(),
+            0x202 => self.if_raw.bits(),
             0x208 => self.ime as u16,
             _ => 0,
         }
     }
 
-    /// Write IO register
+    /// Set IE (0x200), IF (0x202) or IME (0x208) to `val` as stored, with
+    /// no write-1-to-clear on IF and no history entry
+    pub(crate) fn poke_register(&mut self, offset: usize, val: u16) {
+        match offset {
+            0x200 => self.ie = Interrupt::from_bits_truncate(val),
+            0x202 => {
+                self.if_raw = Interrupt::from_bits_truncate(val);
+                self.if_processed &= self.if_raw;
+            }
+            0x208 => self.ime = val & 1 != 0,
+            _ => {}
+        }
+    }
+
+    /// Write IE (0x200), IF (0x202) or IME (0x208)
     pub fn write_register(&mut self, offset: usize, val: u16) {
         match offset {
-            0x000 => self.ie = Interrupt::from_bits_truncate(val),
-            0x002 => {
+            0x200 => self.ie = Interrupt::from_bits_truncate(val),
+            0x202 => {
                 // IF - writing 1 clears the bit, writing 0 has no effect
+                let cleared = self.if_raw & Interrupt::from_bits_truncate(val);
+                self.record(IrqEventKind::Acknowledged, cleared);
                 self.if_raw &= !(Interrupt::from_bits_truncate(val));
                 self.if_processed &= !(Interrupt::from_bits_truncate(val));
             }
-            0x200 => self.ie = Interrupt::from_bits_truncate(val),
-            0x208 => self.ime = val != 0,
+            0x208 => self.ime = val & 1 != 0,
             _ => {}
         }
     }
 }
 
+impl InterruptController {
+    pub(crate) fn save_state(&self, w: &mut StateWriter) {
+        w.write_u16s(&[
+            self.ie.bits(),
+            self.ie_fp.bits(),
+            self.if_raw.bits(),
+            self.if_processed.bits(),
+        ]);
+        w.write_bool(self.ime);
+        w.write_bool(self.in_interrupt);
+    }
+
+    pub(crate) fn load_state(&mut self, r: &mut StateReader) -> Result<(), StateError> {
+        let mut regs = [0u16; 4];
+        r.read_u16s(&mut regs)?;
+        self.ie = Interrupt::from_bits_retain(regs[0]);
+        self.ie_fp = Interrupt::from_bits_retain(regs[1]);
+        self.if_raw = Interrupt::from_bits_retain(regs[2]);
+        self.if_processed = Interrupt::from_bits_retain(regs[3]);
+        self.ime = r.read_bool()?;
+        self.in_interrupt = r.read_bool()?;
+        Ok(())
+    }
+}
+
 impl Default for InterruptController {
     fn default() -> Self {
         Self::new()
@@ -192,6 +350,12 @@ pub struct Memory {
     bios_read_return: u32,
 
     pub use_real_bios: bool,
+    /// Host file I/O for semihosting SWIs; None while disabled
+    pub semihost: Option<Semihost>,
+    /// Game Boy Player on the link port; None when not plugged in
+    pub(crate) gbp: Option<GameBoyPlayer>,
+    /// Peripheral answering RCNT general-purpose mode; None when unplugged
+    pub(crate) sio_gpio: Option<GpioPort>,
     pub intrwait_flag_addr: u32,
     pub intrwait_active: bool,
 
@@ -218,6 +382,8 @@ pub struct Memory {
 
     // ROM (max 32MB) - mirrored across different waitstate regions
     rom: Vec<u8>,
+    // Whether ROM repeats past its end instead of reading the address pattern
+    rom_mirroring: bool,
 
     // Waitstate configuration
     waitcnt: u16,
@@ -228,13 +394,27 @@ pub struct Memory {
     // HALT state - set when writing to HALTCNT (0x0400_0301)
     pub halt_pending: bool,
 
+    /// STOP requested by HALTCNT bit 7 or the Stop SWI
+    pub stop_pending: bool,
+
+    /// Bytes written to FIFO A (0) / FIFO B (1), waiting to be moved into
+    /// the APU
+    pub(crate) fifo_writes: Vec<(u8, u8)>,
+
     // Dirty flags for lazy synchronization
     pub vram_dirty: bool,
     pub oam_dirty: bool,
     pub palette_dirty: bool,
     pub io_ppu_dirty: bool,
     pub io_timer_dirty: bool,
+    /// Timers whose TMxCNT_L was written since the last sync, one bit each;
+    /// between writes those IO bytes mirror the running counter instead
+    pub io_timer_reload_dirty: u8,
     pub io_dma_dirty: bool,
+    /// SOUNDCNT_L/H/X were written since the last APU sync
+    pub io_sound_dirty: bool,
+    /// BG2 / BG3 reference point registers were written since the last PPU sync
+    pub bg_ref_dirty: [bool; 2],
     pub dma_active: bool,
 
     // Save type configuration and backends
@@ -282,36 +462,44 @@ pub struct Memory {
     pub dispcnt_write_log_enabled: bool,
     pub ie_ime_write_log: Vec<(u32, u32, u16)>, // (pc, addr, val)
     pub ie_ime_write_log_enabled: bool,
+    /// Byte writes to palette/VRAM/OAM as (addr, old, new), see [`Memory::take_video_writes`]
+    pub video_write_log: Vec<(u32, u8, u8)>,
+    pub video_write_log_enabled: bool,
+    /// Accuracy option: unmapped reads return the last prefetched opcode instead of 0
+    pub open_bus_enabled: bool,
+    open_bus_latch: u32,
+    /// Accuracy option: sequential cartridge reads take 1 cycle while the
+    /// WAITCNT prefetch buffer is on
+    pub prefetch_enabled: bool,
+    /// Accuracy option: DMA transfers stall the CPU for their bus cycles
+    pub dma_bus_timing: bool,
+    /// Accuracy option: CPU palette/VRAM/OAM accesses wait a cycle while
+    /// the PPU draws, and OAM is locked while the PPU reads it
+    pub video_access_timing: bool,
+    /// Cycles the CPU has waited on the video bus since the last
+    /// [`Memory::take_video_stall`]
+    video_stall: u32,
+    #[cfg(feature = "stats")]
+    heatmap: heatmap::HeatmapCounter,
 }
 
 impl Memory {
-    pub fn new() -> Self {
+    /// The built-in BIOS stub: a jump to the cartridge, `BX LR` at BIOS
+    /// routine entry points and an IRQ dispatcher at 0x18
+    pub(crate) fn builtin_bios() -> Vec<u8> {
         // Initialize BIOS with stub implementation
         // Fill with BX LR (0xE12FFF1E) - all bytes non-zero, so games
         // that read BIOS bytes as data won't get false zeros.
         // BX LR is safe because if code falls through, it just returns.
-        let mut bios = vec![0u8; 0x4000];
-
-        for i in (0..0x4000).step_by(4) {
-            bios[i] = 0x1E;
-            bios[i + 1] = 0xFF;
-            bios[i + 2] = 0x2F;
-            bios[i + 3] = 0xE1;
-        }
+        let mut bios = 0xE12FFF1E_u32.to_le_bytes().repeat(0x1000);
 
         // At BIOS entry point (0x00000000), jump to ROM at 0x08000000
         // LDR PC, [PC, #4] = 0xE59FF004 -> loads from 0x00+8+4=0x0C
         // Then at 0x0C: .word 0x08000000
-        bios[0] = 0x04;
-        bios[1] = 0xF0;
-        bios[2] = 0x9F;
-        bios[3] = 0xE5;
-        // 0x04-0x0B: filler (already BX LR from the loop)
+        bios.set_word(0, 0xE59FF004);
+        // 0x04-0x0B: filler (already BX LR from the fill)
         // 0x0C-0x0F: target address
-        bios[0x0C] = 0x00;
-        bios[0x0D] = 0x00;
-        bios[0x0E] = 0x00;
-        bios[0x0F] = 0x08;
+        bios.set_word(0x0C, 0x08000000);
 
         // At key BIOS entry points used by tests and games, put "BX LR" to return
         // BX LR in ARM: 0xE12FFF1E
@@ -333,11 +521,8 @@ impl Memory {
             0x3200, 0x3300, 0x3400, 0x3500, 0x3600, 0x3700, 0x3800, 0x3900, 0x3A00, 0x3B00, 0x3C00,
             0x3D00, 0x3E00, 0x3F00,
         ]
-        .iter()
         {
-            if *offset + 4 <= 0x4000 {
-                bios[*offset..(*offset + 4)].copy_from_slice(&bios_return);
-            }
+            bios.set_bytes(offset, &bios_return);
         }
 
         // Place BIOS stub handler at 0x013C that clears IF and returns
@@ -349,26 +534,11 @@ impl Memory {
         //   STRH R1, [R0]          ; clear VBlank in IF
         //   BX LR                  ; return to BIOS dispatcher (not exception return)
         //   .word 0x04000202       ; literal pool: IF register address
-        bios[0x013C] = 0x08;
-        bios[0x013D] = 0x00;
-        bios[0x013E] = 0x9F;
-        bios[0x013F] = 0xE5; // 0x013C: LDR R0, [PC, #8] -> loads from 0x014C
-        bios[0x0140] = 0x01;
-        bios[0x0141] = 0x10;
-        bios[0x0142] = 0xA0;
-        bios[0x0143] = 0xE3; // 0x0140: MOV R1, #1
-        bios[0x0144] = 0xB0;
-        bios[0x0145] = 0x10;
-        bios[0x0146] = 0xC0;
-        bios[0x0147] = 0xE1; // 0x0144: STRH R1, [R0]
-        bios[0x0148] = 0x1E;
-        bios[0x0149] = 0xFF;
-        bios[0x014A] = 0x2F;
-        bios[0x014B] = 0xE1; // 0x0148: BX LR
-        bios[0x014C] = 0x02;
-        bios[0x014D] = 0x02;
-        bios[0x014E] = 0x00;
-        bios[0x014F] = 0x04; // 0x014C: .word 0x04000202
+        bios.set_word(0x013C, 0xE59F0008); // 0x013C: LDR R0, [PC, #8] -> loads from 0x014C
+        bios.set_word(0x0140, 0xE3A01001); // 0x0140: MOV R1, #1
+        bios.set_word(0x0144, 0xE1C010B0); // 0x0144: STRH R1, [R0]
+        bios.set_word(0x0148, 0xE12FFF1E); // 0x0148: BX LR
+        bios.set_word(0x014C, 0x04000202); // 0x014C: .word 0x04000202
 
         // BIOS IRQ dispatcher at 0x0018 (ARM IRQ vector)
         // Calls the user handler at [0x03007FFC].
@@ -391,42 +561,47 @@ impl Memory {
             0x04, 0xF0, 0x5E, 0xE2, // 0x004C: SUBS PC, LR, #4
             0xFC, 0x7F, 0x00, 0x03, // 0x0050: .word 0x03007FFC
         ];
-        bios[0x18..0x18 + irq_handler.len()].copy_from_slice(&irq_handler);
-
-        // Set IRQ handler pointer at 0x03007FFC to point to BIOS stub
-        // Game initialization will overwrite this when it sets up its own handler.
-        let mut iwram = Box::new([0u8; 0x8000]);
-        iwram[0x7FFC] = 0x3C;
-        iwram[0x7FFD] = 0x01;
-        iwram[0x7FFE] = 0x00;
-        iwram[0x7FFF] = 0x00;
+        bios.set_bytes(0x18, &irq_handler);
+        bios
+    }
 
+    pub fn new() -> Self {
+        let bios = Self::builtin_bios();
         let mut io = Box::new([0u8; 0x400]);
-        io[0] = 0x80;
+        io.set_byte(0, 0x80);
 
         Self {
             bios,
             bios_read_return: 0xE129F000,
             use_real_bios: false,
+            semihost: None,
+            gbp: None,
+            sio_gpio: None,
             intrwait_flag_addr: 0,
             intrwait_active: false,
             wram: Box::new([0u8; 0x40000]),
-            iwram,
-            io: Box::new([0u8; 0x400]),
+            iwram: Self::default_iwram(),
+            io: Self::default_io(),
             palette: Box::new([0u8; 0x400]),
             vram: Box::new([0u8; 0x18000]),
             oam: Box::new([0u8; 0x400]),
             sram: Box::new([0xFFu8; 0x8000]),
             rom: Vec::new(),
+            rom_mirroring: false,
             waitcnt: 0x0000,
             interrupt: InterruptController::new(),
             halt_pending: false,
+            stop_pending: false,
+            fifo_writes: Vec::new(),
             vram_dirty: true,
             oam_dirty: true,
             palette_dirty: true,
             io_ppu_dirty: true,
             io_timer_dirty: true,
+            io_timer_reload_dirty: 0xF,
             io_dma_dirty: true,
+            io_sound_dirty: true,
+            bg_ref_dirty: [false; 2],
             dma_active: false,
             save_type: SaveType::None,
             flash: None,
@@ -470,25 +645,111 @@ impl Memory {
             dispcnt_write_log_enabled: false,
             ie_ime_write_log: Vec::new(),
             ie_ime_write_log_enabled: false,
+            video_write_log: Vec::new(),
+            video_write_log_enabled: false,
+            open_bus_enabled: false,
+            open_bus_latch: 0,
+            prefetch_enabled: false,
+            #[cfg(feature = "stats")]
+            heatmap: heatmap::HeatmapCounter::default(),
+            dma_bus_timing: false,
+            video_access_timing: false,
+            video_stall: 0,
         }
     }
 
+    /// Power-cycle with the cartridge still inserted: RAM, I/O and video
+    /// memory return to their boot contents, while BIOS, ROM and the save
+    /// chip's data are kept. See [`Memory::clear_backup`] for the latter.
     pub fn reset(&mut self) {
         self.wram.fill(0);
-        self.iwram.fill(0);
-        self.io.fill(0);
+        self.iwram = Self::default_iwram();
+        self.io = Self::default_io();
         self.palette.fill(0);
         self.vram.fill(0);
         self.oam.fill(0);
-        self.sram.fill(0);
         self.waitcnt = 0x0000;
+        self.bios_read_return = 0xE129F000;
+        self.intrwait_flag_addr = 0;
+        self.intrwait_active = false;
         self.interrupt.reset();
+        self.halt_pending = false;
+        self.stop_pending = false;
+        self.fifo_writes.clear();
+        self.open_bus_latch = 0;
+        self.dma_active = false;
+        self.video_stall = 0;
         if let Some(ref mut flash) = self.flash {
             flash.reset();
         }
         if let Some(ref mut eeprom) = self.eeprom {
             eeprom.reset();
         }
+        if let Some(gbp) = &mut self.gbp {
+            gbp.reset();
+        }
+        self.mark_all_dirty();
+    }
+
+    /// Let the Game Boy Player look at the frame that just finished
+    pub(crate) fn update_game_boy_player(&mut self) {
+        if let Some(gbp) = &mut self.gbp {
+            gbp.frame(&self.vram[..]);
+        }
+    }
+
+    /// Refresh RCNT's input pins from the general-purpose peripheral,
+    /// raising the serial IRQ if SI fell
+    pub(crate) fn poll_sio_gpio(&mut self) {
+        let rcnt = self.io.half_at(0x134);
+        let Some(port) = self.sio_gpio.as_mut().filter(|_| sio::is_gpio_mode(rcnt)) else {
+            return;
+        };
+        let (value, si_fell) = port.sample(rcnt);
+        self.io.set_byte(0x134, value as u8);
+        if si_fell {
+            self.interrupt.request(Interrupt::SERIAL);
+        }
+    }
+
+    /// Erase the save chip to its blank (0xFF) state, as a new cartridge
+    pub fn clear_backup(&mut self) {
+        self.sram.fill(0xFF);
+        self.set_save_type(self.save_type);
+    }
+
+    /// Force every component to resync from memory on the next step
+    fn mark_all_dirty(&mut self) {
+        self.vram_dirty = true;
+        self.oam_dirty = true;
+        self.palette_dirty = true;
+        self.io_ppu_dirty = true;
+        self.io_timer_dirty = true;
+        self.io_timer_reload_dirty = 0xF;
+        self.io_dma_dirty = true;
+        self.io_sound_dirty = true;
+        self.bg_ref_dirty = [true; 2];
+    }
+
+    /// I/O register contents once the BIOS hands over to the cartridge:
+    /// BG2/BG3 affine matrices are identity, SOUNDBIAS is centred and
+    /// POSTFLG records the completed boot
+    fn default_io() -> Box<[u8; 0x400]> {
+        let mut io = Box::new([0u8; 0x400]);
+        for offset in [0x20, 0x26, 0x30, 0x36] {
+            io.set_half(offset, 0x0100); // PA/PD = 1.0
+        }
+        io.set_half(0x88, 0x0200); // SOUNDBIAS = 0x0200
+        io.set_byte(0x300, 0x01);
+        io
+    }
+
+    /// IWRAM at boot: the IRQ handler pointer at 0x03007FFC points to the
+    /// BIOS stub until the game installs its own handler
+    fn default_iwram() -> Box<[u8; 0x8000]> {
+        let mut iwram = Box::new([0u8; 0x8000]);
+        iwram.set_word(0x7FFC, 0x0000_013C);
+        iwram
     }
 
     /// Clear EWRAM (0x02000000-0x0203FFFF)
@@ -499,7 +760,9 @@ impl Memory {
     /// Clear IWRAM (0x03000000-0x03007FFF, except top 8 bytes)
     pub fn clear_iwram(&mut self) {
         // Don't clear the top 8 bytes (0x03007FF8-0x03007FFF) - used for BIOS communication
-        self.iwram[..0x7FF8].fill(0);
+        if let Some(low) = self.iwram.get_mut(..0x7FF8) {
+            low.fill(0);
+        }
     }
 
     /// Clear Palette (0x05000000-0x050003FF)
@@ -524,10 +787,24 @@ impl Memory {
         self.io.fill(0);
     }
 
-    pub fn load_rom(&mut self, data: Vec<u8>) {
+    pub fn load_rom(&mut self, mut data: Vec<u8>) {
+        data.truncate(MAX_ROM_SIZE);
+        // Classic NES Series carts (game code F***) rely on their ROM repeating
+        self.rom_mirroring = data.get(0xAC) == Some(&b'F') && data.len().is_power_of_two();
         self.rom = data;
     }
 
+    /// Reads past the end of ROM repeat the ROM instead of returning
+    /// `(addr / 2) & 0xFFFF`, as on the small carts that mirror it.
+    /// Set automatically by [`Memory::load_rom`].
+    pub fn set_rom_mirroring(&mut self, enabled: bool) {
+        self.rom_mirroring = enabled;
+    }
+
+    pub fn rom_mirroring(&self) -> bool {
+        self.rom_mirroring
+    }
+
     /// Set the cartridge save type
     pub fn set_save_type(&mut self, save_type: SaveType) {
         self.save_type = save_type;
@@ -549,8 +826,9 @@ impl Memory {
 
     /// Load SRAM data from a save file
     pub fn load_sram(&mut self, data: &[u8]) {
-        let len = data.len().min(self.sram.len());
-        self.sram[..len].copy_from_slice(&data[..len]);
+        for (dst, &src) in self.sram.iter_mut().zip(data) {
+            *dst = src;
+        }
     }
 
     pub fn zero_sram(&mut self) {
@@ -566,11 +844,9 @@ impl Memory {
     }
 
     /// Load BIOS from a file
-    pub fn load_bios(&mut self, data: Vec<u8>) {
-        let mut bios_data = vec![0u8; 0x4000];
-        let len = data.len().min(0x4000);
-        bios_data[..len].copy_from_slice(&data[..len]);
-        self.bios = bios_data;
+    pub fn load_bios(&mut self, mut data: Vec<u8>) {
+        data.resize(0x4000, 0);
+        self.bios = data;
     }
 
     /// Check if BIOS is loaded (not all zeros)
@@ -583,13 +859,12 @@ impl Memory {
     }
 
     pub fn get_irq_handler(&self) -> u32 {
-        let off = 0x7FFC;
-        u32::from_le_bytes([
-            self.iwram[off],
-            self.iwram[off + 1],
-            self.iwram[off + 2],
-            self.iwram[off + 3],
-        ])
+        self.iwram.word_at(0x7FFC)
+    }
+
+    /// Value the bus holds after the CPU's last opcode prefetch
+    pub fn set_open_bus_latch(&mut self, val: u32) {
+        self.open_bus_latch = val;
     }
 
     pub fn get_bios_read_return(&self) -> u32 {
@@ -597,16 +872,9 @@ impl Memory {
     }
 
     pub fn bios_read_word(&self, offset: usize) -> u32 {
-        if offset + 4 <= self.bios.len() {
-            u32::from_le_bytes([
-                self.bios[offset],
-                self.bios[offset + 1],
-                self.bios[offset + 2],
-                self.bios[offset + 3],
-            ])
-        } else {
-            0
-        }
+        self.bios
+            .get(offset..offset.wrapping_add(4))
+            .map_or(0, |word| word.word_at(0))
     }
 
     pub fn reinstall_bios_returns(&mut self) {
@@ -625,20 +893,15 @@ impl Memory {
             0x2C00, 0x2D00, 0x2E00, 0x2F00, 0x3000, 0x3100, 0x3200, 0x3300, 0x3400, 0x3500, 0x3600,
             0x3700, 0x3800, 0x3900, 0x3A00, 0x3B00, 0x3C00, 0x3D00, 0x3E00, 0x3F00,
         ]
-        .iter()
         {
-            if *offset + 4 <= 0x4000 {
-                self.bios[*offset..(*offset + 4)].copy_from_slice(&bios_return);
-            }
+            self.bios.set_bytes(offset, &bios_return);
         }
 
         // BIOS function at 0x2000: simple BX LR return stub
         // Games call this via BX directly (not SWI) - must not corrupt stack
         // Previous STMFD/LDMFD stub was broken: BX LR returns before LDMFD pops,
         // leaving SP decremented by 4 bytes each call, corrupting caller's stack.
-        if 0x2004 <= 0x4000 {
-            self.bios[0x2000..0x2004].copy_from_slice(&bios_return);
-        }
+        self.bios.set_bytes(0x2000, &bios_return);
 
         // BIOS IRQ return stub at 0x3000:
         // When take_interrupt fires, it pushes the return address onto the
@@ -651,9 +914,7 @@ impl Memory {
             0x00, 0x40, 0xBD, 0xE8, // LDMIA SP!, {LR} = 0xE8BD4000 (LDMFD = pop)
             0x04, 0xF0, 0x5E, 0xE2, // SUBS PC, LR, #4 = 0xE25EF004
         ];
-        if 0x3008 <= 0x4000 {
-            self.bios[0x3000..0x3008].copy_from_slice(&irq_return_stub);
-        }
+        self.bios.set_bytes(0x3000, &irq_return_stub);
 
         // BIOS IRQ dispatch is installed in new() at 0x0018-0x004B
         // Do not overwrite it here
@@ -663,8 +924,8 @@ impl Memory {
     pub fn get_access_cycles(&self, addr: u32, _sequential: bool) -> u32 {
         match addr {
             0x0000_0000..=0x0000_3FFF => 2, // BIOS: always 2 cycles
-            0x0200_0000..=0x0203_FFFF => 3, // WRAM: always 3 cycles
-            0x0300_0000..=0x0300_7FFF => 1, // IWRAM: always 1 cycle
+            0x0200_0000..=0x02FF_FFFF => 3, // WRAM and mirrors: always 3 cycles
+            0x0300_0000..=0x03FF_FFFF => 1, // IWRAM and mirrors: always 1 cycle
             0x0400_0000..=0x0400_03FE => 1, // IO: always 1 cycle
             0x0500_0000..=0x0500_03FF => 1, // Palette: always 1 cycle
             0x0600_0000..=0x0601_7FFF => 1, // VRAM: always 1 cycle
@@ -672,118 +933,240 @@ impl Memory {
             0x0800_0000..=0x09FF_FFFF => self.get_rom_waitstates(0, _sequential),
             0x0A00_0000..=0x0BFF_FFFF => self.get_rom_waitstates(1, _sequential),
             0x0C00_0000..=0x0DFF_FFFF => self.get_rom_waitstates(2, _sequential),
+            0x0E00_0000..=0x0FFF_FFFF => self.waitcnt().sram.wrapping_add(1),
             _ => 1, // Unknown region
         }
     }
 
-    fn get_rom_waitstates(&self, region: usize, sequential: bool) -> u32 {
-        // Extract waitstate settings from WAITCNT register
-        let ws = if sequential {
-            match region {
-                0 => (self.waitcnt >> 2) & 0x3,
-                1 => (self.waitcnt >> 6) & 0x3,
-                2 => (self.waitcnt >> 10) & 0x3,
-                _ => 3,
-            }
+    /// One access of `word` width; 16-bit buses split words into two halves
+    pub(crate) fn bus_cycles(&self, addr: u32, sequential: bool, word: bool) -> u32 {
+        let first = self.get_access_cycles(addr, sequential);
+        let bus16 = matches!(addr >> 24, 0x02 | 0x05 | 0x06 | 0x08..=0x0D);
+        if word && bus16 {
+            first.wrapping_add(self.get_access_cycles(addr, true))
         } else {
-            match region {
-                0 => self.waitcnt & 0x3,
-                1 => (self.waitcnt >> 4) & 0x3,
-                2 => (self.waitcnt >> 8) & 0x3,
-                _ => 3,
-            }
-        };
+            first
+        }
+    }
 
-        // Convert waitstate to cycles (WS0=3, WS1=2, WS2=1, WS3=1? - varies)
-        // Simplified: default to 3 cycles
-        match ws {
-            0 => 3,
-            1 => 2,
-            2 => 1,
-            _ => 3,
+    fn get_rom_waitstates(&self, region: usize, sequential: bool) -> u32 {
+        let wait = self.waitcnt();
+        if sequential && self.prefetch_enabled && wait.prefetch {
+            return 1;
         }
+        wait.rom_waits(region, sequential).wrapping_add(1)
+    }
+
+    /// Decoded WAITCNT, as last written by the game
+    pub fn waitcnt(&self) -> WaitControl {
+        WaitControl::from_bits(self.waitcnt)
+    }
+
+    #[cfg(feature = "stats")]
+    fn count_access(&mut self, addr: u32, width: u32, write: bool) {
+        let sequential = self.heatmap.is_sequential(addr);
+        let cycles = self.bus_cycles(addr, sequential, width == 4);
+        self.heatmap.record(addr, width, write, cycles);
+    }
+
+    /// Bus accesses per memory area (and per page, if enabled) since the
+    /// last [`Memory::reset_access_heatmap`]
+    #[cfg(feature = "stats")]
+    pub fn access_heatmap(&self) -> AccessHeatmap {
+        self.heatmap.snapshot()
+    }
+
+    /// Also count accesses per [`HEATMAP_PAGE_SIZE`] page; turning this on
+    /// or off drops the page counts gathered so far
+    #[cfg(feature = "stats")]
+    pub fn set_page_heatmap(&mut self, enabled: bool) {
+        self.heatmap.set_pages(enabled);
+    }
+
+    #[cfg(feature = "stats")]
+    pub fn reset_access_heatmap(&mut self) {
+        self.heatmap.clear();
     }
 
     /// Map address to actual memory location
-    fn map_address(&self, addr: u32) -> (MemoryRegion, usize) {
+    pub(crate) fn map_address(addr: u32) -> (MemoryRegion, usize) {
         match addr {
             0x0000_0000..=0x0000_3FFF => (MemoryRegion::Bios, (addr - 0x0000_0000) as usize),
             // EWRAM (256KB) and its mirrors
             0x0200_0000..=0x02FF_FFFF => {
-                let offset = ((addr - 0x0200_0000) & 0x3_FFFF) as usize; // Mask to 256KB
+                let offset = (addr.wrapping_sub(0x0200_0000) & 0x3_FFFF) as usize; // Mask to 256KB
                 (MemoryRegion::Wram, offset)
             }
             // IWRAM (32KB) and its mirrors
             0x0300_0000..=0x03FF_FFFF => {
-                let offset = ((addr - 0x0300_0000) & 0x7FFF) as usize; // Mask to 32KB
+                let offset = (addr.wrapping_sub(0x0300_0000) & 0x7FFF) as usize; // Mask to 32KB
                 (MemoryRegion::Iwram, offset)
             }
-            0x0400_0000..=0x0400_03FE => (MemoryRegion::Io, (addr - 0x0400_0000) as usize),
-            // Palette (1KB) and its mirrors (every 0x400 bytes)
-            0x0500_0000..=0x050F_FFFF => {
-                (MemoryRegion::Palette, ((addr - 0x0500_0000) % 0x400) as usize)
+            0x0400_0000..=0x0400_03FE => {
+                (MemoryRegion::Io, addr.wrapping_sub(0x0400_0000) as usize)
             }
+            // Palette (1KB) and its mirrors (every 0x400 bytes)
+            0x0500_0000..=0x050F_FFFF => (
+                MemoryRegion::Palette,
+                (addr.wrapping_sub(0x0500_0000) % 0x400) as usize,
+            ),
             // VRAM (96KB) and its mirrors (128KB period)
             // 0x00000-0x0FFFF: BG VRAM (64KB)
             // 0x10000-0x17FFF: OBJ VRAM (32KB)
             // 0x18000-0x1FFFF: mirrors 0x10000-0x17FFF (OBJ)
             // Pattern repeats every 128KB (0x20000)
             0x0600_0000..=0x060F_FFFF => {
-                let raw = (addr - 0x0600_0000) % 0x2_0000;
-                let offset = if raw >= 0x1_8000 { raw - 0x8000 } else { raw };
+                let raw = addr.wrapping_sub(0x0600_0000) % 0x2_0000;
+                let offset = if raw >= 0x1_8000 {
+                    raw.wrapping_sub(0x8000)
+                } else {
+                    raw
+                };
                 (MemoryRegion::Vram, offset as usize)
             }
             // OAM (1KB) and its mirrors
-            0x0700_0000..=0x0700_03FF => (MemoryRegion::Oam, (addr - 0x0700_0000) as usize),
+            0x0700_0000..=0x0700_03FF => {
+                (MemoryRegion::Oam, addr.wrapping_sub(0x0700_0000) as usize)
+            }
             0x0700_0400..=0x070F_FFFF => {
-                let offset = ((addr - 0x0700_0000) & 0x3FF) as usize; // Mask to 1KB
+                let offset = (addr.wrapping_sub(0x0700_0000) & 0x3FF) as usize; // Mask to 1KB
                 (MemoryRegion::Oam, offset)
             }
             // SRAM (32KB) and its mirrors
             0x0E00_0000..=0x0FFF_FFFF => {
-                let offset = ((addr - 0x0E00_0000) & 0xFFFF) as usize;
+                let offset = (addr.wrapping_sub(0x0E00_0000) & 0xFFFF) as usize;
                 (MemoryRegion::Sram, offset)
             }
             // ROM WS0 (0x08000000-0x09FFFFFF)
             0x0800_0000..=0x09FF_FFFF => {
-                let offset = (addr - 0x0800_0000) as usize;
+                let offset = addr.wrapping_sub(0x0800_0000) as usize;
                 (MemoryRegion::Rom, offset)
             }
             // ROM WS1 (0x0A000000-0x0BFFFFFF)
             0x0A00_0000..=0x0BFF_FFFF => {
-                let offset = (addr - 0x0A00_0000) as usize;
+                let offset = addr.wrapping_sub(0x0A00_0000) as usize;
                 (MemoryRegion::Rom, offset)
             }
             // ROM WS2 (0x0C000000-0x0DFFFFFF)
             0x0C00_0000..=0x0DFF_FFFF => {
-                let offset = (addr - 0x0C00_0000) as usize;
+                let offset = addr.wrapping_sub(0x0C00_0000) as usize;
                 (MemoryRegion::Rom, offset)
             }
             _ => (MemoryRegion::Unknown, 0),
         }
     }
 
+    /// Byte at `addr` as the CPU would read it, but with none of a bus
+    /// read's side effects: no I/O read handlers (SIO pin polling, trace
+    /// logs), no EEPROM serial shift and no access statistics. For debugger
+    /// views and RAM search. EEPROM space reads as 0xFF.
+    pub fn peek_byte(&self, addr: u32) -> u8 {
+        let (region, offset) = Self::map_address(addr);
+        match region {
+            MemoryRegion::Bios => self.bios.byte_at(offset),
+            MemoryRegion::Io => iomap::peek(self, offset),
+            MemoryRegion::Sram => match self.save_type {
+                SaveType::Sram | SaveType::None => self.sram.byte_at(offset & 0x7FFF),
+                SaveType::Flash64K | SaveType::Flash128K => {
+                    self.flash.as_ref().map_or(0xFF, |f| f.read(offset as u32))
+                }
+                _ => 0xFF,
+            },
+            MemoryRegion::Rom if self.is_eeprom_access(addr) => 0xFF,
+            MemoryRegion::Rom => self.rom_byte(addr, offset),
+            MemoryRegion::Unknown => self.open_bus_byte(addr),
+            _ => self.region_bytes(region).byte_at(offset),
+        }
+    }
+
+    /// Halfword at `addr` (force-aligned) without side effects, see [`Memory::peek_byte`]
+    pub fn peek_half(&self, addr: u32) -> u16 {
+        if Self::on_backup_bus(addr) {
+            let b = self.peek_byte(addr);
+            return u16::from_le_bytes([b, b]);
+        }
+        let aligned = addr & !1;
+        u16::from_le_bytes([self.peek_byte(aligned), self.peek_byte(aligned.wrapping_add(1))])
+    }
+
+    /// Word at `addr` (force-aligned) without side effects, see [`Memory::peek_byte`]
+    pub fn peek_word(&self, addr: u32) -> u32 {
+        let aligned = addr & !3;
+        self.peek_half(aligned) as u32 | (self.peek_half(aligned.wrapping_add(2)) as u32) << 16
+    }
+
+    /// Store a byte in the backing buffer behind `addr`, bypassing everything
+    /// a bus write does: I/O write handlers (IF acknowledge, FIFO pushes,
+    /// transfer starts, write masks), the VRAM/palette byte-write rule, write
+    /// logs and read-only protection, so BIOS and ROM can be patched too.
+    /// Flash, EEPROM and unmapped space ignore pokes. Components that cache
+    /// registers still pick up the new value.
+    pub fn poke_byte(&mut self, addr: u32, val: u8) {
+        let (region, offset) = Self::map_address(addr);
+        let slot = match region {
+            MemoryRegion::Bios => self.bios.get_mut(offset),
+            MemoryRegion::Io => {
+                iomap::poke(self, offset, val);
+                None
+            }
+            MemoryRegion::Sram => match self.save_type {
+                SaveType::Sram | SaveType::None => self.sram.get_mut(offset & 0x7FFF),
+                _ => None,
+            },
+            MemoryRegion::Rom => self.rom.get_mut(offset),
+            _ => self.region_bytes_mut(region).get_mut(offset),
+        };
+        if let Some(byte) = slot {
+            *byte = val;
+        }
+        self.mark_dirty(addr);
+    }
+
+    /// Little-endian [`Memory::poke_byte`] of a force-aligned halfword
+    pub fn poke_half(&mut self, addr: u32, val: u16) {
+        let aligned = addr & !1;
+        let [low, high] = val.to_le_bytes();
+        self.poke_byte(aligned, low);
+        self.poke_byte(aligned.wrapping_add(1), high);
+    }
+
+    /// Little-endian [`Memory::poke_byte`] of a force-aligned word
+    pub fn poke_word(&mut self, addr: u32, val: u32) {
+        let aligned = addr & !3;
+        self.poke_half(aligned, val as u16);
+        self.poke_half(aligned.wrapping_add(2), (val >> 16) as u16);
+    }
+
     /// Read a byte from memory
     pub fn read_byte(&mut self, addr: u32) -> u8 {
-        let (region, offset) = self.map_address(addr);
+        #[cfg(feature = "stats")]
+        self.count_access(addr, 1, false);
+        if self.video_access_refused(addr) {
+            return 0;
+        }
+        self.read_byte_internal(addr)
+    }
+
+    fn read_byte_internal(&mut self, addr: u32) -> u8 {
+        let (region, offset) = Self::map_address(addr);
 
         match region {
-            MemoryRegion::Bios => self.bios[offset],
+            MemoryRegion::Bios => self.bios.byte_at(offset),
             MemoryRegion::Wram => {
                 if self.input_reads_enabled && self.input_reads.len() < 100_000 {
                     if addr >= 0x02008CF8 && addr < 0x02008D10 {
                         self.input_reads.push((addr, self.vram_log_pc));
                     }
                 }
-                self.wram[offset]
+                self.wram.byte_at(offset)
             }
-            MemoryRegion::Iwram => self.iwram[offset],
+            MemoryRegion::Iwram => self.iwram.byte_at(offset),
             MemoryRegion::Io => self.read_io(addr),
-            MemoryRegion::Palette => self.palette[offset],
-            MemoryRegion::Vram => self.vram[offset],
-            MemoryRegion::Oam => self.oam[offset],
+            MemoryRegion::Palette => self.palette.byte_at(offset),
+            MemoryRegion::Vram => self.vram.byte_at(offset),
+            MemoryRegion::Oam => self.oam.byte_at(offset),
             MemoryRegion::Sram => match self.save_type {
-                SaveType::Sram | SaveType::None => self.sram[offset & 0x7FFF],
+                SaveType::Sram | SaveType::None => self.sram.byte_at(offset & 0x7FFF),
                 SaveType::Flash64K | SaveType::Flash128K => {
                     self.flash.as_ref().map_or(0xFF, |f| f.read(offset as u32))
                 }
@@ -793,21 +1176,58 @@ impl Memory {
                 if self.is_eeprom_access(addr) {
                     return self.eeprom.as_mut().map_or(0xFF, |e| e.serial_read());
                 }
-                if self.rom.is_empty() {
-                    0
-                } else if offset < self.rom.len() {
-                    self.rom[offset]
-                } else {
-                    ((addr >> 1) >> (8 * (addr & 1))) as u8
-                }
+                self.rom_byte(addr, offset)
             }
-            MemoryRegion::Unknown => 0,
+            MemoryRegion::Unknown => self.open_bus_byte(addr),
+        }
+    }
+
+    fn rom_byte(&self, addr: u32, offset: usize) -> u8 {
+        if self.rom.is_empty() {
+            0
+        } else if offset < self.rom.len() {
+            self.rom.byte_at(offset)
+        } else if self.rom_mirroring {
+            self.rom
+                .byte_at(offset.checked_rem(self.rom.len()).unwrap_or(0))
+        } else {
+            // Nothing drives the bus, so the cart's address latch reads back
+            ((addr >> 1) >> ((addr & 1) << 3)) as u8
+        }
+    }
+
+    fn open_bus_byte(&self, addr: u32) -> u8 {
+        if self.open_bus_enabled {
+            (self.open_bus_latch >> ((addr & 3) << 3)) as u8
+        } else {
+            0
         }
     }
 
+    /// Record a palette/VRAM/OAM byte write in the video write log
+    #[inline]
+    fn log_video_write(&mut self, region: MemoryRegion, offset: usize, val: u8) {
+        if !self.video_write_log_enabled || self.video_write_log.len() >= VIDEO_WRITE_LOG_LIMIT {
+            return;
+        }
+        let (base, old) = match region {
+            MemoryRegion::Palette => (0x0500_0000u32, self.palette.byte_at(offset)),
+            MemoryRegion::Vram => (0x0600_0000, self.vram.byte_at(offset)),
+            MemoryRegion::Oam => (0x0700_0000, self.oam.byte_at(offset)),
+            _ => return,
+        };
+        self.video_write_log
+            .push((base.wrapping_add(offset as u32), old, val));
+    }
+
+    /// Drain the video write log; addresses are canonical (mirrors resolved)
+    pub fn take_video_writes(&mut self) -> Vec<(u32, u8, u8)> {
+        std::mem::take(&mut self.video_write_log)
+    }
+
     /// Write a byte to memory (internal, used by write_word)
     fn write_byte_internal(&mut self, addr: u32, val: u8) {
-        let (region, offset) = self.map_address(addr);
+        let (region, offset) = Self::map_address(addr);
 
         match region {
             MemoryRegion::Bios => {
@@ -848,7 +1268,7 @@ impl Memory {
                         self.decomp_writes.push((addr, pc, val));
                     }
                 }
-                self.wram[offset] = val
+                self.wram.set_byte(offset, val)
             }
             MemoryRegion::Iwram => {
                 if self.iwram_write_log_enabled && self.iwram_write_log.len() < 500_000 {
@@ -859,7 +1279,7 @@ impl Memory {
                 if offset >= 0x7FF8 && offset <= 0x7FFB && self.iwram_write_log.len() < 500_000 {
                     self.iwram_write_log.push((addr, self.vram_log_pc, val));
                 }
-                self.iwram[offset] = val;
+                self.iwram.set_byte(offset, val);
             }
             MemoryRegion::Io => self.write_io(addr, val),
             MemoryRegion::Palette => {
@@ -871,17 +1291,22 @@ impl Memory {
                         self.dma_active,
                     ));
                 }
-                self.palette[offset] = val
+                self.log_video_write(region, offset, val);
+                self.palette.set_byte(offset, val)
             }
             MemoryRegion::Vram => {
                 if self.vram_log_enabled && self.vram_write_log.len() < 100_000 {
                     self.vram_write_log.push((addr, self.vram_log_pc, val));
                 }
-                self.vram[offset] = val
+                self.log_video_write(region, offset, val);
+                self.vram.set_byte(offset, val)
+            }
+            MemoryRegion::Oam => {
+                self.log_video_write(region, offset, val);
+                self.oam.set_byte(offset, val)
             }
-            MemoryRegion::Oam => self.oam[offset] = val,
             MemoryRegion::Sram => match self.save_type {
-                SaveType::Sram | SaveType::None => self.sram[offset & 0x7FFF] = val,
+                SaveType::Sram | SaveType::None => self.sram.set_byte(offset & 0x7FFF, val),
                 SaveType::Flash64K | SaveType::Flash128K => {
                     self.flash.as_mut().map(|f| f.write(offset as u32, val));
                 }
@@ -897,21 +1322,42 @@ impl Memory {
             MemoryRegion::Unknown => {}
         }
 
-        // Set dirty flags based on written address
+        self.mark_dirty(addr);
+    }
+
+    /// Flag the component state a write to `addr` invalidates
+    #[inline]
+    fn mark_dirty(&mut self, addr: u32) {
         match addr {
             0x06000000..=0x06017FFF => self.vram_dirty = true,
             0x07000000..=0x070003FF => self.oam_dirty = true,
             0x05000000..=0x050003FF => self.palette_dirty = true,
             0x04000000..=0x04000055 => self.io_ppu_dirty = true,
-            0x04000100..=0x0400010F => self.io_timer_dirty = true,
+            0x04000100..=0x0400010F => {
+                self.io_timer_dirty = true;
+                if addr & 2 == 0 {
+                    self.io_timer_reload_dirty |= 1 << ((addr >> 2) & 3);
+                }
+            }
             0x040000B0..=0x040000DF => self.io_dma_dirty = true,
+            0x04000080..=0x04000085 => self.io_sound_dirty = true,
+            _ => {}
+        }
+        match addr {
+            0x04000028..=0x0400002F => self.bg_ref_dirty[0] = true,
+            0x04000038..=0x0400003F => self.bg_ref_dirty[1] = true,
             _ => {}
         }
     }
 
     /// Write a byte to memory (public, handles OAM and VRAM byte-write restrictions)
     pub fn write_byte(&mut self, addr: u32, val: u8) {
-        let (region, offset) = self.map_address(addr);
+        #[cfg(feature = "stats")]
+        self.count_access(addr, 1, true);
+        if self.video_access_refused(addr) {
+            return;
+        }
+        let (region, offset) = Self::map_address(addr);
 
         // OAM ignores byte writes (only accepts 16-bit or 32-bit aligned writes)
         if region == MemoryRegion::Oam {
@@ -923,8 +1369,9 @@ impl Memory {
         if region == MemoryRegion::Vram {
             let half_offset = offset & !1; // Align to halfword boundary
             let half_val = ((val as u16) << 8) | (val as u16); // Duplicate byte
-            self.vram[half_offset] = (half_val & 0xFF) as u8;
-            self.vram[half_offset + 1] = ((half_val >> 8) & 0xFF) as u8;
+            self.log_video_write(region, half_offset, val);
+            self.log_video_write(region, half_offset | 1, val);
+            self.vram.set_half(half_offset, half_val);
             return;
         }
 
@@ -932,225 +1379,388 @@ impl Memory {
         if region == MemoryRegion::Palette {
             let half_offset = offset & !1; // Align to halfword boundary
             let half_val = ((val as u16) << 8) | (val as u16); // Duplicate byte
-            self.palette[half_offset] = (half_val & 0xFF) as u8;
-            self.palette[half_offset + 1] = ((half_val >> 8) & 0xFF) as u8;
+            self.log_video_write(region, half_offset, val);
+            self.log_video_write(region, half_offset | 1, val);
+            self.palette.set_half(half_offset, half_val);
             return;
         }
 
         self.write_byte_internal(addr, val);
     }
 
-    /// Read a halfword (16-bit) from memory
+    /// Charge a CPU access to palette, VRAM or OAM against the PPU, with
+    /// video access timing on. Drawing a visible line holds the video bus,
+    /// so the access waits a cycle. OAM is read for sprites while drawing
+    /// and during HBlank unless DISPCNT's H-Blank Interval Free bit is set;
+    /// then the access is refused. VBlank and forced blank leave the bus
+    /// free. Returns true when the access is refused.
+    #[inline]
+    fn video_access_refused(&mut self, addr: u32) -> bool {
+        if !(0x05..=0x07).contains(&(addr >> 24)) || !self.video_bus_contended() {
+            return false;
+        }
+        let dispcnt = self.io.half_at(0);
+        let hblank = self.io.byte_at(4) & 2 != 0;
+        if !hblank {
+            self.video_stall = self.video_stall.wrapping_add(1);
+        }
+        addr >> 24 == 0x07 && !(hblank && dispcnt & 0x20 != 0)
+    }
+
+    /// True while CPU accesses to palette, VRAM and OAM go through
+    /// [`Memory::video_access_refused`]: the option is on, no DMA is running
+    /// and the PPU is neither in VBlank nor force-blanked
+    #[inline]
+    fn video_bus_contended(&self) -> bool {
+        let dispcnt = self.io.half_at(0);
+        let vblank = self.io.byte_at(4) & 1 != 0;
+        self.video_access_timing && !self.dma_active && dispcnt & 0x80 == 0 && !vblank
+    }
+
+    /// Cycles CPU accesses have waited on the video bus since the last call
+    pub(crate) fn take_video_stall(&mut self) -> u32 {
+        std::mem::take(&mut self.video_stall)
+    }
+
+    /// SRAM/Flash sit on an 8-bit bus: wider reads return the addressed byte
+    /// repeated in every lane, and wider writes store only the byte lane
+    /// selected by the low address bits.
+    #[inline]
+    fn on_backup_bus(addr: u32) -> bool {
+        (0x0E00_0000..0x1000_0000).contains(&addr)
+    }
+
+    /// Read a halfword (16-bit) from memory; the address is force-aligned
     pub fn read_half(&mut self, addr: u32) -> u16 {
-        if addr >= 0x0E00_0000 && addr < 0x1000_0000 {
-            let b = self.read_byte(addr);
+        #[cfg(feature = "stats")]
+        self.count_access(addr, 2, false);
+        if self.video_access_refused(addr) {
+            return 0;
+        }
+        if Self::on_backup_bus(addr) {
+            let b = self.read_byte_internal(addr);
             return u16::from_le_bytes([b, b]);
         }
         let aligned = addr & !1;
-        let low = self.read_byte(aligned);
-        let high = self.read_byte(aligned.wrapping_add(1));
+        let low = self.read_byte_internal(aligned);
+        let high = self.read_byte_internal(aligned.wrapping_add(1));
         u16::from_le_bytes([low, high])
     }
 
+    /// LDRH semantics: read the aligned halfword, rotated right by 8 when
+    /// `addr` is odd
     pub fn read_half_rotated(&mut self, addr: u32) -> u32 {
-        if addr >= 0x0E00_0000 && addr < 0x1000_0000 {
-            let b = self.read_byte(addr) as u32;
+        #[cfg(feature = "stats")]
+        self.count_access(addr, 2, false);
+        if self.video_access_refused(addr) {
+            return 0;
+        }
+        if Self::on_backup_bus(addr) {
+            let b = self.read_byte_internal(addr) as u32;
             return b | (b << 8);
         }
         let aligned = addr & !1;
-        let low = self.read_byte(aligned) as u32;
-        let high = self.read_byte(aligned.wrapping_add(1)) as u32;
+        let low = self.read_byte_internal(aligned) as u32;
+        let high = self.read_byte_internal(aligned.wrapping_add(1)) as u32;
         let val = low | (high << 8);
-        let rotate = ((addr & 1) * 8) as u32;
+        let rotate = (addr & 1) << 3;
         val.rotate_right(rotate)
     }
 
-    /// Write a halfword (16-bit) to memory
+    /// Write a halfword (16-bit) to memory; the address is force-aligned
     pub fn write_half(&mut self, addr: u32, val: u16) {
-        if addr >= 0x0E00_0000 && addr < 0x1000_0000 {
+        #[cfg(feature = "stats")]
+        self.count_access(addr, 2, true);
+        if self.video_access_refused(addr) {
+            return;
+        }
+        if Self::on_backup_bus(addr) {
             let byte_index = (addr & 1) as usize;
-            let byte_val = val.to_le_bytes()[byte_index];
+            let byte_val = val.to_le_bytes().byte_at(byte_index);
             self.write_byte_internal(addr, byte_val);
             return;
         }
         let addr = addr & !1;
-        let bytes = val.to_le_bytes();
-        self.write_byte_internal(addr, bytes[0]);
-        self.write_byte_internal(addr + 1, bytes[1]);
+        let [low, high] = val.to_le_bytes();
+        self.write_byte_internal(addr, low);
+        self.write_byte_internal(addr | 1, high);
     }
 
     /// Read a word from memory (optimized fast path for ROM/IWRAM)
     #[inline(always)]
     pub fn read_word_fast(&mut self, addr: u32) -> u32 {
+        #[cfg(feature = "stats")]
+        self.count_access(addr, 4, false);
         match addr {
             // ROM WS0 - most common for instruction fetch
             0x0800_0000..=0x09FF_FFFF => {
-                let offset = (addr - 0x0800_0000) as usize;
-                if offset + 3 < self.rom.len() {
+                let offset = (addr & 0x01FF_FFFF) as usize;
+                if offset.wrapping_add(3) < self.rom.len() {
                     unsafe {
                         let ptr = self.rom.as_ptr().add(offset);
                         u32::from_le_bytes([*ptr, *ptr.add(1), *ptr.add(2), *ptr.add(3)])
                     }
                 } else {
-                    self.read_word(addr)
+                    self.read_word_internal(addr)
                 }
             }
             // IWRAM - fast access for stack
             0x0300_0000..=0x03FF_FFFF => {
-                let offset = (addr - 0x0300_0000) as usize;
-                if offset + 3 < self.iwram.len() {
+                let offset = (addr & 0x7FFF) as usize;
+                if offset.wrapping_add(3) < self.iwram.len() {
                     unsafe {
                         let ptr = self.iwram.as_ptr().add(offset);
                         u32::from_le_bytes([*ptr, *ptr.add(1), *ptr.add(2), *ptr.add(3)])
                     }
                 } else {
-                    self.read_word(addr)
+                    self.read_word_internal(addr)
                 }
             }
             // WRAM
             0x0200_0000..=0x02FF_FFFF => {
-                let offset = (addr - 0x0200_0000) as usize;
-                if offset + 3 < self.wram.len() {
+                let offset = (addr & 0x3_FFFF) as usize;
+                if offset.wrapping_add(3) < self.wram.len() {
                     unsafe {
                         let ptr = self.wram.as_ptr().add(offset);
                         u32::from_le_bytes([*ptr, *ptr.add(1), *ptr.add(2), *ptr.add(3)])
                     }
                 } else {
-                    self.read_word(addr)
+                    self.read_word_internal(addr)
                 }
             }
-            _ => self.read_word(addr),
+            _ if self.video_access_refused(addr) => 0,
+            _ => self.read_word_internal(addr),
         }
     }
 
     /// Read a word (32-bit) from memory
     pub fn read_word(&mut self, addr: u32) -> u32 {
-        if addr >= 0x0E00_0000 && addr < 0x1000_0000 {
-            let b = self.read_byte(addr) as u32;
+        #[cfg(feature = "stats")]
+        self.count_access(addr, 4, false);
+        if self.video_access_refused(addr) {
+            return 0;
+        }
+        self.read_word_internal(addr)
+    }
+
+    fn read_word_internal(&mut self, addr: u32) -> u32 {
+        if Self::on_backup_bus(addr) {
+            let b = self.read_byte_internal(addr) as u32;
             return b | (b << 8) | (b << 16) | (b << 24);
         }
         if addr & 3 != 0 {
             // Unaligned read - rotate
-            let aligned = addr & !3;
-            let low = self.read_half(aligned) as u32;
-            let high = self.read_half(aligned.wrapping_add(2)) as u32;
-            let val = low | (high << 16);
-            val.rotate_right(8 * (addr & 3) as u32)
+            let val = self.read_word_internal(addr & !3);
+            val.rotate_right((addr & 3) << 3)
         } else {
-            let b0 = self.read_byte(addr) as u32;
-            let b1 = self.read_byte(addr.wrapping_add(1)) as u32;
-            let b2 = self.read_byte(addr.wrapping_add(2)) as u32;
-            let b3 = self.read_byte(addr.wrapping_add(3)) as u32;
+            let b0 = self.read_byte_internal(addr) as u32;
+            let b1 = self.read_byte_internal(addr.wrapping_add(1)) as u32;
+            let b2 = self.read_byte_internal(addr.wrapping_add(2)) as u32;
+            let b3 = self.read_byte_internal(addr.wrapping_add(3)) as u32;
             b0 | (b1 << 8) | (b2 << 16) | (b3 << 24)
         }
     }
 
-    /// Write a word (32-bit) to memory
+    /// Write a word (32-bit) to memory; the address is force-aligned
     pub fn write_word(&mut self, addr: u32, val: u32) {
-        if addr >= 0x0E00_0000 && addr < 0x1000_0000 {
+        #[cfg(feature = "stats")]
+        self.count_access(addr, 4, true);
+        if self.video_access_refused(addr) {
+            return;
+        }
+        if Self::on_backup_bus(addr) {
             let byte_index = (addr & 3) as usize;
-            let byte_val = val.to_le_bytes()[byte_index];
+            let byte_val = val.to_le_bytes().byte_at(byte_index);
             self.write_byte_internal(addr, byte_val);
             return;
         }
         let addr = addr & !3;
-        let bytes = val.to_le_bytes();
-        for i in 0..4usize {
-            self.write_byte_internal(addr.wrapping_add(i as u32), bytes[i]);
+        for (lane, byte) in (0..4).zip(val.to_le_bytes()) {
+            self.write_byte_internal(addr | lane, byte);
         }
     }
 
-    /// Read from IO register
-    fn read_io(&mut self, addr: u32) -> u8 {
-        let offset = (addr - 0x0400_0000) as usize;
-
-        // Handle interrupt registers (IE is at 0x0400_0200, not 0x0400_0000!)
-        let (int_offset, byte_index) = match addr {
-            0x0400_0200 | 0x0400_0201 => (Some(0x200), (addr & 1) as usize), // IE
-            0x0400_0202 | 0x0400_0203 => (Some(0x002), (addr & 1) as usize), // IF
-            0x0400_0208 => (Some(0x208), 0),                                 // IME
-            _ => (None, 0),
+    /// Copy `len` units of `width` bytes (2 or 4) from `src` to `dst`, both
+    /// incrementing and force-aligned to `width`, with the same result as a
+    /// loop of `read_half`/`write_half` (or word) calls. Copies that stay
+    /// inside plain RAM, VRAM, palette, OAM or ROM are done as slice copies.
+    pub fn copy_block(&mut self, src: u32, dst: u32, len: u32, width: u32) {
+        let src = src & !width.wrapping_sub(1);
+        let dst = dst & !width.wrapping_sub(1);
+        // The heatmap counts each unit, so `stats` builds take the slow path
+        if cfg!(feature = "stats") || !self.copy_block_fast(src, dst, len, width) {
+            for i in 0..len {
+                let step = i.wrapping_mul(width);
+                let (s, d) = (src.wrapping_add(step), dst.wrapping_add(step));
+                if width == 4 {
+                    let v = self.read_word(s);
+                    self.write_word(d, v);
+                } else {
+                    let v = self.read_half(s);
+                    self.write_half(d, v);
+                }
+            }
+        }
+    }
+
+    /// Slice-copy path of [`Memory::copy_block`]; false if the copy needs the
+    /// per-element path (IO, backup chips, mirror wrap, forward overlap,
+    /// logging, video bus contention)
+    fn copy_block_fast(&mut self, src: u32, dst: u32, len: u32, width: u32) -> bool {
+        let bytes = (len as usize).saturating_mul(width as usize);
+        if bytes == 0 || self.block_copy_hooks_active() {
+            return bytes == 0;
+        }
+        let (Some((src_region, s)), Some((dst_region, d))) =
+            (self.contiguous(src, bytes), self.contiguous(dst, bytes))
+        else {
+            return false;
         };
+        let (src_end, dst_end) = (s.saturating_add(bytes), d.saturating_add(bytes));
+        let readable = matches!(
+            src_region,
+            MemoryRegion::Wram
+                | MemoryRegion::Iwram
+                | MemoryRegion::Palette
+                | MemoryRegion::Vram
+                | MemoryRegion::Oam
+                | MemoryRegion::Rom
+        );
+        let writable = matches!(
+            dst_region,
+            MemoryRegion::Wram
+                | MemoryRegion::Iwram
+                | MemoryRegion::Palette
+                | MemoryRegion::Vram
+                | MemoryRegion::Oam
+        );
+        let rom_ok = src_region != MemoryRegion::Rom
+            || (src_end <= self.rom.len()
+                && !self.is_eeprom_access(src.wrapping_add(bytes as u32).wrapping_sub(1)));
+        if !readable || !writable || !rom_ok {
+            return false;
+        }
+        // The PPU's bus stalls and refuses accesses per unit while it draws
+        let video = |region| {
+            matches!(
+                region,
+                MemoryRegion::Palette | MemoryRegion::Vram | MemoryRegion::Oam
+            )
+        };
+        if (video(src_region) || video(dst_region)) && self.video_bus_contended() {
+            return false;
+        }
+        // An element-wise forward copy into a later overlapping range repeats
+        // the leading elements, which a memmove would not
+        if src_region == dst_region && d > s && d < src_end {
+            return false;
+        }
+        // Writes the debug hooks in write_byte_internal always record
+        let overlaps = |lo: usize, hi: usize| d <= hi && dst_end > lo;
+        if (dst_region == MemoryRegion::Wram && overlaps(0x50, 0x53))
+            || (dst_region == MemoryRegion::Iwram && overlaps(0x7FF8, 0x7FFB))
+        {
+            return false;
+        }
 
-        if let Some(ioff) = int_offset {
-            let val = self.interrupt.read_register(ioff);
-            return if ioff == 0x208 {
-                val as u8
-            } else {
-                (val >> (8 * byte_index as u32)) as u8
+        if src_region == dst_region {
+            let region = self.region_bytes_mut(dst_region);
+            if src_end.max(dst_end) > region.len() {
+                return false;
+            }
+            region.copy_within(s..src_end, d);
+        } else {
+            let Some(data) = self
+                .region_bytes(src_region)
+                .get(s..src_end)
+                .map(<[u8]>::to_vec)
+            else {
+                return false;
+            };
+            let Some(out) = self.region_bytes_mut(dst_region).get_mut(d..dst_end) else {
+                return false;
             };
+            out.copy_from_slice(&data);
         }
+        match dst_region {
+            MemoryRegion::Vram => self.vram_dirty = true,
+            MemoryRegion::Oam => self.oam_dirty = true,
+            MemoryRegion::Palette => self.palette_dirty = true,
+            _ => {}
+        }
+        true
+    }
 
-        match offset {
-            0x000 => self.io[offset], // DISPCNT
-            0x004 => self.io[offset], // DISPSTAT
-            0x006 => self.io[offset], // VCOUNT (would be updated by PPU)
-            0x130 | 0x131 => {
-                let v = self.io[offset];
-                if self.keyinput_read_trace_enabled && self.keyinput_read_pcs.len() < 1000 {
-                    self.keyinput_read_pcs.push(self.vram_log_pc);
-                }
-                v
-            }
-            0x132 | 0x133 => self.io[offset], // KEYCNT
-            _ => self.io[offset],
+    /// Debug logging that sees individual reads or writes
+    fn block_copy_hooks_active(&self) -> bool {
+        self.ewram_write_limit.is_some()
+            || self.vram_log_enabled
+            || self.ewram_tile_log_enabled
+            || self.palette_log_enabled
+            || self.iwram_write_log_enabled
+            || self.ewram_range_log_enabled
+            || self.decomp_writes_enabled
+            || self.timer_writes_enabled
+            || self.input_reads_enabled
+            || self.video_write_log_enabled
+    }
+
+    /// Region and start offset if `addr..addr + bytes` maps to one unbroken
+    /// run of a backing buffer
+    fn contiguous(&self, addr: u32, bytes: usize) -> Option<(MemoryRegion, usize)> {
+        let last = addr.checked_add((bytes as u32).wrapping_sub(1))?;
+        let (region, start) = Self::map_address(addr);
+        let (end_region, end) = Self::map_address(last);
+        let contiguous = end.wrapping_sub(start) == bytes.wrapping_sub(1);
+        (region == end_region && contiguous).then_some((region, start))
+    }
+
+    fn region_bytes(&self, region: MemoryRegion) -> &[u8] {
+        match region {
+            MemoryRegion::Wram => &self.wram[..],
+            MemoryRegion::Iwram => &self.iwram[..],
+            MemoryRegion::Palette => &self.palette[..],
+            MemoryRegion::Vram => &self.vram[..],
+            MemoryRegion::Oam => &self.oam[..],
+            MemoryRegion::Rom => &self.rom[..],
+            _ => &[],
         }
     }
 
-    /// Write to IO register
-    fn write_io(&mut self, addr: u32, val: u8) {
-        let offset = (addr - 0x0400_0000) as usize;
-
-        // Handle interrupt registers (IE is at 0x0400_0200, not 0x0400_0000!)
-        let (int_offset, byte_index) = match addr {
-            0x0400_0200 | 0x0400_0201 => (Some(0x200), (addr & 1) as usize), // IE
-            0x0400_0202 | 0x0400_0203 => (Some(0x002), (addr & 1) as usize), // IF
-            0x0400_0208 => (Some(0x208), 0),                                 // IME
-            _ => (None, 0),
-        };
+    fn region_bytes_mut(&mut self, region: MemoryRegion) -> &mut [u8] {
+        match region {
+            MemoryRegion::Wram => &mut self.wram[..],
+            MemoryRegion::Iwram => &mut self.iwram[..],
+            MemoryRegion::Palette => &mut self.palette[..],
+            MemoryRegion::Vram => &mut self.vram[..],
+            MemoryRegion::Oam => &mut self.oam[..],
+            _ => &mut [],
+        }
+    }
 
-        if let Some(ioff) = int_offset {
-            let current = self.interrupt.read_register(ioff);
-            let new_val = if ioff == 0x208 {
-                val as u16
-            } else {
-                let shift = 8 * byte_index as u32;
-                let mask = 0xFF << shift;
-                let current_cleared = current & !mask;
-                current_cleared | ((val as u16) << shift)
-            };
-            if self.ie_ime_write_log_enabled && self.ie_ime_write_log.len() < 10_000 {
-                self.ie_ime_write_log
-                    .push((self.vram_log_pc, addr, new_val));
-            }
-            self.interrupt.write_register(ioff, new_val);
-            return;
+    /// Read from IO register
+    fn read_io(&mut self, addr: u32) -> u8 {
+        let offset = addr.wrapping_sub(0x0400_0000) as usize;
+        match iomap::lookup(offset) {
+            Some(reg) => (reg.read)(self, offset) & reg.read_mask_byte(offset),
+            None => self.io.byte_at(offset),
         }
+    }
 
-        match offset {
-            0x204 => {
-                // WAITCNT - only some bits are writable
-                self.waitcnt = u16::from_le_bytes([val, self.io[offset + 1]]);
-            }
-            0x301 => {
-                // HALTCNT - halt the CPU
-                // Writing 0 to bit 0 enters HALT mode
-                self.halt_pending = true;
-                self.io[offset] = val;
-            }
-            0x000..=0x003 => {
-                if self.dispcnt_write_log_enabled && self.dispcnt_write_log.len() < 10_000 {
-                    self.dispcnt_write_log
-                        .push((self.vram_log_pc, offset as u8, val));
-                }
-                self.io[offset] = val;
-            }
-            _ => {
-                self.io[offset] = val;
-            }
+    /// Write to IO register
+    fn write_io(&mut self, addr: u32, val: u8) {
+        let offset = addr.wrapping_sub(0x0400_0000) as usize;
+        match iomap::lookup(offset) {
+            Some(reg) => (reg.write)(self, offset, val),
+            None => self.io.set_byte(offset, val),
         }
     }
 
+    /// SOUNDCNT_X master enable; while clear the PSG registers read as 0 and ignore writes
+    pub fn sound_powered(&self) -> bool {
+        self.io.byte_at(SOUNDCNT_X) & 0x80 != 0
+    }
+
     /// Read a palette color entry (16-bit RGB555)
     /// pal_num: 0 for BG palette, 1 for OBJ palette
     /// index: color index (0-255)
@@ -1160,17 +1770,13 @@ impl Memory {
         // OBJ palette: 0x0500_0200 - 0x0500_03FF (512 bytes, 256 colors)
         let offset = if pal_num == 0 {
             // BG palette
-            (index as usize * 2) & 0x3FF
+            ((index as usize) << 1) & 0x3FF
         } else {
             // OBJ palette (offset by 0x200)
-            0x200 + ((index as usize * 2) & 0x1FF)
+            0x200 | (((index as usize) << 1) & 0x1FF)
         };
 
-        if offset + 1 < self.palette.len() {
-            u16::from_le_bytes([self.palette[offset], self.palette[offset + 1]])
-        } else {
-            0
-        }
+        self.palette.half_at(offset)
     }
 
     /// Get a reference to the palette RAM
@@ -1178,6 +1784,22 @@ impl Memory {
         &self.palette
     }
 
+    /// Read-only view of BIOS, RAM, I/O, palette, VRAM, OAM and ROM for
+    /// renderers, debug views and RAM search; see [`MemView`]
+    pub fn view(&self) -> MemView<'_> {
+        MemView {
+            palette: &self.palette,
+            bios: &self.bios,
+            wram: &self.wram[..],
+            iwram: &self.iwram[..],
+            io: &self.io[..],
+            vram: &self.vram[..],
+            oam: &self.oam[..],
+            rom: &self.rom,
+            interrupt: Some(&self.interrupt),
+        }
+    }
+
     /// Get a mutable reference to BIOS data (for font embedding)
     pub fn bios_mut(&mut self) -> &mut Vec<u8> {
         &mut self.bios
@@ -1188,6 +1810,10 @@ impl Memory {
         &self.wram[..]
     }
 
+    pub fn wram_mut(&mut self) -> &mut [u8] {
+        &mut self.wram[..]
+    }
+
     /// Get a reference to VRAM data
     pub fn vram(&self) -> &[u8] {
         &self.vram[..]
@@ -1210,6 +1836,18 @@ impl Memory {
         &self.oam[..]
     }
 
+    /// OBJ affine parameter group `group` (0-31)
+    pub fn oam_affine(&self, group: usize) -> AffineParams {
+        AffineParams::read(&self.oam[..], group)
+    }
+
+    /// Replace OBJ affine parameter group `group` (0-31), as a game's
+    /// ObjAffineSet would; sprite attributes sharing the words are kept
+    pub fn set_oam_affine(&mut self, group: usize, params: AffineParams) {
+        params.write(&mut self.oam[..], group);
+        self.oam_dirty = true;
+    }
+
     /// Get a reference to IO register data
     pub fn io(&self) -> &[u8] {
         &self.io[..]
@@ -1220,22 +1858,143 @@ impl Memory {
         &mut self.io[..]
     }
 
-    /// Check if address is in interrupt register range (0x0400_0000 - 0x0400_0208)
+    /// Check if address is a byte of IE, IF or IME (including IME's unused upper half)
     pub fn is_interrupt_register(addr: u32) -> bool {
-        matches!(addr, 0x0400_0000..=0x0400_0208)
+        Self::get_interrupt_register_offset(addr).is_some()
     }
 
-    /// Get interrupt register offset (returns 0xFFFF if not in range)
+    /// [`InterruptController::read_register`] offset of the register
+    /// holding `addr`, or None outside IE, IF and IME
     pub fn get_interrupt_register_offset(addr: u32) -> Option<usize> {
         match addr {
-            0x0400_0000 | 0x0400_0001 => Some(0x000), // IE
-            0x0400_0002 | 0x0400_0003 => Some(0x002), // IF
-            0x0400_0208 => Some(0x208),               // IME
+            0x0400_0200 | 0x0400_0201 => Some(0x200), // IE
+            0x0400_0202 | 0x0400_0203 => Some(0x202), // IF
+            0x0400_0208..=0x0400_020B => Some(0x208), // IME
             _ => None,
         }
     }
 }
 
+impl Memory {
+    /// Overwrite RAM and I/O with raw images taken from another emulator's
+    /// save state. Shorter images leave the rest of a region untouched.
+    #[cfg(feature = "import")]
+    pub(crate) fn import_regions(
+        &mut self,
+        iwram: &[u8],
+        wram: &[u8],
+        io: &[u8],
+        palette: &[u8],
+        vram: &[u8],
+        oam: &[u8],
+    ) {
+        fn copy(dst: &mut [u8], src: &[u8]) {
+            for (d, &s) in dst.iter_mut().zip(src) {
+                *d = s;
+            }
+        }
+        copy(&mut self.iwram[..], iwram);
+        copy(&mut self.wram[..], wram);
+        copy(&mut self.io[..], io);
+        copy(&mut self.palette[..], palette);
+        copy(&mut self.vram[..], vram);
+        copy(&mut self.oam[..], oam);
+
+        let half = |off: usize| self.io.half_at(off);
+        self.waitcnt = half(0x204) & waitcnt::WRITABLE;
+        self.interrupt.ie = Interrupt::from_bits_retain(half(0x200));
+        self.interrupt.if_raw = Interrupt::from_bits_retain(half(0x202));
+        self.interrupt.ime = half(0x208) & 1 != 0;
+        self.io_timer_dirty = true;
+        self.io_timer_reload_dirty = 0xF;
+        self.io_dma_dirty = true;
+        self.io_sound_dirty = true;
+        self.bg_ref_dirty = [true; 2];
+    }
+
+    pub(crate) fn save_state(&self, w: &mut StateWriter) {
+        w.write_u32(self.bios_read_return);
+        w.write_bool(self.use_real_bios);
+        w.write_u32(self.intrwait_flag_addr);
+        w.write_bool(self.intrwait_active);
+        w.write_bytes(&self.wram[..]);
+        w.write_bytes(&self.iwram[..]);
+        w.write_bytes(&self.io[..]);
+        w.write_bytes(&self.palette[..]);
+        w.write_bytes(&self.vram[..]);
+        w.write_bytes(&self.oam[..]);
+        w.write_bytes(&self.sram[..]);
+        w.write_u16(self.waitcnt);
+        self.interrupt.save_state(w);
+        w.write_bool(self.halt_pending);
+        w.write_bool(self.stop_pending);
+
+        w.write_u8(match self.save_type {
+            SaveType::None => 0,
+            SaveType::Sram => 1,
+            SaveType::Flash64K => 2,
+            SaveType::Flash128K => 3,
+            SaveType::Eeprom512B => 4,
+            SaveType::Eeprom8K => 5,
+        });
+        let backup: &[u8] = match (&self.flash, &self.eeprom) {
+            (Some(flash), _) => flash.data(),
+            (None, Some(eeprom)) => eeprom.data(),
+            (None, None) => &[],
+        };
+        w.write_bytes(backup);
+    }
+
+    pub(crate) fn load_state(&mut self, r: &mut StateReader) -> Result<(), StateError> {
+        self.bios_read_return = r.read_u32()?;
+        self.use_real_bios = r.read_bool()?;
+        self.intrwait_flag_addr = r.read_u32()?;
+        self.intrwait_active = r.read_bool()?;
+        r.read_bytes_into(&mut self.wram[..])?;
+        r.read_bytes_into(&mut self.iwram[..])?;
+        r.read_bytes_into(&mut self.io[..])?;
+        r.read_bytes_into(&mut self.palette[..])?;
+        r.read_bytes_into(&mut self.vram[..])?;
+        r.read_bytes_into(&mut self.oam[..])?;
+        r.read_bytes_into(&mut self.sram[..])?;
+        self.waitcnt = r.read_u16()?;
+        self.interrupt.load_state(r)?;
+        self.halt_pending = r.read_bool()?;
+        self.stop_pending = r.read_bool()?;
+
+        let save_type = match r.read_u8()? {
+            1 => SaveType::Sram,
+            2 => SaveType::Flash64K,
+            3 => SaveType::Flash128K,
+            4 => SaveType::Eeprom512B,
+            5 => SaveType::Eeprom8K,
+            _ => SaveType::None,
+        };
+        self.set_save_type(save_type);
+        let backup = r.read_bytes()?;
+        if let Some(ref mut flash) = self.flash {
+            flash.load(backup);
+        }
+        if let Some(ref mut eeprom) = self.eeprom {
+            eeprom.load(backup);
+        }
+
+        // The PPU resyncs from the restored RAM; timers, DMA and the APU carry
+        // their own state
+        self.vram_dirty = true;
+        self.oam_dirty = true;
+        self.palette_dirty = true;
+        self.io_ppu_dirty = true;
+        self.io_timer_dirty = false;
+        self.io_timer_reload_dirty = 0;
+        self.io_dma_dirty = false;
+        self.io_sound_dirty = false;
+        self.bg_ref_dirty = [false; 2];
+        self.dma_active = false;
+        Ok(())
+    }
+}
+
 impl Default for Memory {
     fn default() -> Self {
         Self::new()
@@ -1243,7 +2002,7 @@ impl Default for Memory {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum MemoryRegion {
+pub(crate) enum MemoryRegion {
     Bios,
     Wram,
     Iwram,
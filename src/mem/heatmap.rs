@@ -0,0 +1,127 @@
+//! Memory Access Heatmap (`stats` feature)
+//!
+//! Counts CPU and DMA bus accesses per memory area (the top address byte of
+//! the GBA map) and, when asked, per 4KB page. Each access is also charged
+//! the cycles the bus timing table gives it, so a profile shows both how
+//! often a game touches slow EWRAM or cartridge space and how much emulated
+//! time those accesses cost under the current WAITCNT settings. An access
+//! counts once whatever its width.
+
+use std::collections::HashMap;
+
+/// Page size for [`AccessHeatmap::pages`]
+pub const HEATMAP_PAGE_SIZE: u32 = 0x1000;
+
+const AREA_NAMES: [&str; 16] = [
+    "BIOS", "Unmapped", "EWRAM", "IWRAM", "I/O", "Palette", "VRAM", "OAM", "ROM WS0", "ROM WS0",
+    "ROM WS1", "ROM WS1", "ROM WS2", "ROM WS2", "SRAM", "SRAM",
+];
+
+/// Accesses to one 16MB area of the memory map
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegionStats {
+    pub name: &'static str,
+    /// First address of the area, e.g. 0x0200_0000 for EWRAM
+    pub base: u32,
+    pub reads: u64,
+    pub writes: u64,
+    /// Bus cycles charged to these accesses, wait states included
+    pub cycles: u64,
+}
+
+/// Accesses to one [`HEATMAP_PAGE_SIZE`] page
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageStats {
+    pub addr: u32,
+    pub reads: u64,
+    pub writes: u64,
+}
+
+/// Snapshot returned by [`crate::Memory::access_heatmap`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccessHeatmap {
+    /// Areas with at least one access, by address
+    pub regions: Vec<RegionStats>,
+    /// Pages with at least one access, by address; empty unless page
+    /// counting was turned on
+    pub pages: Vec<PageStats>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Counts {
+    reads: u64,
+    writes: u64,
+    cycles: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct HeatmapCounter {
+    areas: [Counts; 16],
+    pages: Option<HashMap<u32, (u64, u64)>>,
+    /// Address just past the previous access, for sequential timing
+    next: u32,
+}
+
+impl HeatmapCounter {
+    /// Whether an access at `addr` continues the previous one
+    pub(crate) fn is_sequential(&self, addr: u32) -> bool {
+        addr == self.next
+    }
+
+    pub(crate) fn record(&mut self, addr: u32, width: u32, write: bool, cycles: u32) {
+        let Some(area) = self.areas.get_mut(((addr >> 24) & 0xF) as usize) else {
+            return;
+        };
+        if write {
+            area.writes = area.writes.wrapping_add(1);
+        } else {
+            area.reads = area.reads.wrapping_add(1);
+        }
+        area.cycles = area.cycles.wrapping_add(cycles as u64);
+        if let Some(pages) = &mut self.pages {
+            let page = pages.entry(addr & !(HEATMAP_PAGE_SIZE - 1)).or_default();
+            if write {
+                page.1 = page.1.wrapping_add(1);
+            } else {
+                page.0 = page.0.wrapping_add(1);
+            }
+        }
+        self.next = addr.wrapping_add(width);
+    }
+
+    pub(crate) fn set_pages(&mut self, enabled: bool) {
+        self.pages = enabled.then(HashMap::new);
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.areas = Default::default();
+        if let Some(pages) = &mut self.pages {
+            pages.clear();
+        }
+    }
+
+    pub(crate) fn snapshot(&self) -> AccessHeatmap {
+        let regions = self
+            .areas
+            .iter()
+            .zip(AREA_NAMES)
+            .enumerate()
+            .filter(|(_, (c, _))| c.reads != 0 || c.writes != 0)
+            .map(|(i, (c, name))| RegionStats {
+                name,
+                base: (i as u32) << 24,
+                reads: c.reads,
+                writes: c.writes,
+                cycles: c.cycles,
+            })
+            .collect();
+        let mut pages: Vec<PageStats> = self
+            .pages
+            .iter()
+            .flatten()
+            .map(|(&addr, &(reads, writes))| PageStats { addr, reads, writes })
+            .collect();
+        pages.sort_by_key(|p| p.addr);
+        AccessHeatmap { regions, pages }
+    }
+}
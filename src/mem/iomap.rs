@@ -0,0 +1,289 @@
+//! Table-Driven I/O Register Dispatch
+//!
+//! [`IO_MAP`] lists the registers whose CPU reads or writes do more than
+//! touch the raw I/O byte array: side effects, read-only or write-only bits,
+//! debug logging. Offsets missing from the table behave as plain storage.
+//! A 1KB index built at compile time maps each byte offset to its entry, so
+//! dispatch is one table lookup per access.
+
+use crate::view::{ByteView, ByteViewMut};
+
+use super::{psg_powered_off_mask, sio, waitcnt, Interrupt, Memory, PSG_REGS, SOUNDCNT_X};
+
+/// Byte read: (memory, offset from 0x0400_0000)
+type IoRead = fn(&mut Memory, usize) -> u8;
+
+/// Byte write: (memory, offset from 0x0400_0000, value)
+type IoWrite = fn(&mut Memory, usize, u8);
+
+/// One register's handlers
+pub(super) struct IoHandler {
+    /// Offset from 0x0400_0000
+    pub offset: usize,
+    /// Width in bytes; unused bytes after a register can be folded in
+    pub size: usize,
+    pub read: IoRead,
+    pub write: IoWrite,
+    /// Bits that read back; the rest read as 0
+    pub read_mask: u32,
+}
+
+impl IoHandler {
+    /// `read_mask` lane for the byte at `offset`
+    pub fn read_mask_byte(&self, offset: usize) -> u8 {
+        let lane = offset.wrapping_sub(self.offset);
+        self.read_mask.to_le_bytes().byte_at(lane)
+    }
+}
+
+macro_rules! io_map {
+    ($($_name:ident @ $offset:literal, $size:literal => $read:ident, $write:ident, $mask:literal;)*) => {
+        pub(super) const IO_MAP: &[IoHandler] = &[$(
+            IoHandler {
+                offset: $offset,
+                size: $size,
+                read: $read,
+                write: $write,
+                read_mask: $mask,
+            },
+        )*];
+    };
+}
+
+io_map! {
+    DISPCNT     @ 0x000, 2 => read_plain, write_dispcnt, 0xFFFF;
+    VCOUNT      @ 0x006, 2 => read_plain, write_ignored, 0x00FF;
+    SOUND1CNT_L @ 0x060, 2 => read_plain, write_psg, 0x007F;
+    SOUND1CNT_H @ 0x062, 2 => read_plain, write_psg, 0xFFC0;
+    SOUND1CNT_X @ 0x064, 4 => read_plain, write_psg, 0x4000;
+    SOUND2CNT_L @ 0x068, 4 => read_plain, write_psg, 0xFFC0;
+    SOUND2CNT_H @ 0x06C, 4 => read_plain, write_psg, 0x4000;
+    SOUND3CNT_L @ 0x070, 2 => read_plain, write_psg, 0x00E0;
+    SOUND3CNT_H @ 0x072, 2 => read_plain, write_psg, 0xE000;
+    SOUND3CNT_X @ 0x074, 4 => read_plain, write_psg, 0x4000;
+    SOUND4CNT_L @ 0x078, 4 => read_plain, write_psg, 0xFF00;
+    SOUND4CNT_H @ 0x07C, 4 => read_plain, write_psg, 0x40FF;
+    SOUNDCNT_L  @ 0x080, 2 => read_plain, write_psg, 0xFF77;
+    SOUNDCNT_H  @ 0x082, 2 => read_plain, write_plain, 0x770F;
+    SOUNDCNT_X  @ 0x084, 4 => read_plain, write_soundcnt_x, 0x008F;
+    FIFO_A      @ 0x0A0, 4 => read_plain, write_fifo, 0x0000;
+    FIFO_B      @ 0x0A4, 4 => read_plain, write_fifo, 0x0000;
+    SIOCNT      @ 0x128, 2 => read_plain, write_siocnt, 0xFFFF;
+    KEYINPUT    @ 0x130, 2 => read_keyinput, write_ignored, 0x03FF;
+    RCNT        @ 0x134, 2 => read_rcnt, write_rcnt, 0xC1FF;
+    IE          @ 0x200, 2 => read_irq, write_irq, 0xFFFF;
+    IF          @ 0x202, 2 => read_irq, write_irq, 0xFFFF;
+    WAITCNT     @ 0x204, 2 => read_plain, write_waitcnt, 0x5FFF;
+    IME         @ 0x208, 4 => read_irq, write_irq, 0x0001;
+    HALTCNT     @ 0x301, 1 => read_plain, write_haltcnt, 0xFF;
+}
+
+/// [`IO_INDEX`] entry for byte offsets without a handler
+const NONE: u8 = u8::MAX;
+
+/// IO_MAP entry for every byte offset, or NONE
+static IO_INDEX: [u8; 0x400] = build_index();
+
+// Only evaluated at compile time, where a bad offset fails the build
+#[allow(clippy::arithmetic_side_effects, clippy::indexing_slicing)]
+const fn build_index() -> [u8; 0x400] {
+    let mut index = [NONE; 0x400];
+    let mut i = 0;
+    while i < IO_MAP.len() {
+        let mut offset = IO_MAP[i].offset;
+        while offset < IO_MAP[i].offset + IO_MAP[i].size {
+            assert!(index[offset] == NONE, "overlapping IO_MAP entries");
+            index[offset] = i as u8;
+            offset += 1;
+        }
+        i += 1;
+    }
+    index
+}
+
+/// Handler covering the byte at `offset`, if it has one
+#[inline]
+pub(super) fn lookup(offset: usize) -> Option<&'static IoHandler> {
+    match IO_INDEX.get(offset) {
+        Some(&i) if i != NONE => IO_MAP.get(i as usize),
+        _ => None,
+    }
+}
+
+fn read_plain(mem: &mut Memory, offset: usize) -> u8 {
+    mem.io.byte_at(offset)
+}
+
+fn write_plain(mem: &mut Memory, offset: usize, val: u8) {
+    mem.io.set_byte(offset, val);
+}
+
+/// Read-only registers, refreshed by their component through `io_mut`
+fn write_ignored(_mem: &mut Memory, _offset: usize, _val: u8) {}
+
+fn write_dispcnt(mem: &mut Memory, offset: usize, val: u8) {
+    if mem.dispcnt_write_log_enabled && mem.dispcnt_write_log.len() < 10_000 {
+        mem.dispcnt_write_log
+            .push((mem.vram_log_pc, offset as u8, val));
+    }
+    mem.io.set_byte(offset, val);
+}
+
+/// PSG registers are locked while SOUNDCNT_X is off, except for the lengths
+fn write_psg(mem: &mut Memory, offset: usize, val: u8) {
+    debug_assert!(PSG_REGS.contains(&offset));
+    let mask = if mem.sound_powered() {
+        0xFF
+    } else {
+        psg_powered_off_mask(offset)
+    };
+    let old = mem.io.byte_at(offset);
+    mem.io.set_byte(offset, (old & !mask) | (val & mask));
+}
+
+fn write_soundcnt_x(mem: &mut Memory, offset: usize, val: u8) {
+    if offset != SOUNDCNT_X {
+        return;
+    }
+    // Only the master enable is writable; bits 0-3 are channel status
+    if val & 0x80 == 0 {
+        if let Some(regs) = mem.io.get_mut(PSG_REGS) {
+            regs.fill(0);
+        }
+        mem.io.set_byte(offset, 0);
+    } else {
+        let status = mem.io.byte_at(offset) & 0x0F;
+        mem.io.set_byte(offset, status | 0x80);
+    }
+}
+
+/// FIFO bytes are queued for the APU; the registers are write-only
+fn write_fifo(mem: &mut Memory, offset: usize, val: u8) {
+    if mem.sound_powered() {
+        let fifo = offset.wrapping_sub(0x0A0) / 4;
+        mem.fifo_writes.push((fifo as u8, val));
+    }
+}
+
+fn read_keyinput(mem: &mut Memory, offset: usize) -> u8 {
+    if mem.keyinput_read_trace_enabled && mem.keyinput_read_pcs.len() < 1000 {
+        mem.keyinput_read_pcs.push(mem.vram_log_pc);
+    }
+    match mem.gbp.as_ref().and_then(|gbp| gbp.keyinput()) {
+        Some(keys) => keys.to_le_bytes().byte_at(offset.wrapping_sub(0x130)),
+        None => mem.io.byte_at(offset),
+    }
+}
+
+/// Starting a transfer (bit 7) in 32-bit normal mode with a Game Boy
+/// Player attached completes it at once: SIODATA32 is swapped for the
+/// player's reply, the start bit clears and the serial IRQ fires if enabled.
+/// Without a partner the start bit stays set, as with no cable.
+fn write_siocnt(mem: &mut Memory, offset: usize, val: u8) {
+    mem.io.set_byte(offset, val);
+    let siocnt = mem.io.half_at(0x128);
+    let normal_32 = siocnt & 0x1080 == 0x1080 && mem.io.byte_at(0x135) & 0x80 == 0;
+    let Some(gbp) = mem.gbp.as_mut().filter(|_| normal_32) else {
+        return;
+    };
+    let tx = gbp.transfer(mem.io.word_at(0x120));
+    mem.io.set_word(0x120, tx);
+    mem.io.set_half(0x128, siocnt & !0x80);
+    if siocnt & 0x4000 != 0 {
+        mem.interrupt.request(Interrupt::SERIAL);
+    }
+}
+
+/// In general-purpose mode the input pins show the peripheral's levels
+fn read_rcnt(mem: &mut Memory, offset: usize) -> u8 {
+    if offset == 0x134 {
+        mem.poll_sio_gpio();
+    }
+    mem.io.byte_at(offset)
+}
+
+fn write_rcnt(mem: &mut Memory, offset: usize, val: u8) {
+    mem.io.set_byte(offset, val);
+    let rcnt = mem.io.half_at(0x134);
+    if let Some(port) = mem.sio_gpio.as_mut().filter(|_| sio::is_gpio_mode(rcnt)) {
+        port.write(rcnt);
+    }
+}
+
+/// Interrupt controller register and the shift of the byte lane for an
+/// IE/IF/IME offset; None for the unused upper half of IME
+fn irq_register(offset: usize) -> Option<(usize, u32)> {
+    match offset {
+        0x200..=0x203 | 0x208 | 0x209 => Some((offset & !1, (offset as u32 & 1) << 3)),
+        _ => None,
+    }
+}
+
+fn read_irq(mem: &mut Memory, offset: usize) -> u8 {
+    irq_register(offset).map_or(0, |(reg, shift)| {
+        (mem.interrupt.read_register(reg) >> shift) as u8
+    })
+}
+
+/// Byte as a CPU read would return it, without running the read handler
+pub(super) fn peek(mem: &Memory, offset: usize) -> u8 {
+    peek_registers(&mem.io[..], |reg| mem.interrupt.read_register(reg), offset)
+}
+
+/// [`peek`] over borrowed register bytes, with IE, IF and IME taken from
+/// `irq` since they live in the interrupt controller
+pub(crate) fn peek_registers(io: &[u8], irq: impl Fn(usize) -> u16, offset: usize) -> u8 {
+    let val = match irq_register(offset) {
+        Some((reg, shift)) => (irq(reg) >> shift) as u8,
+        None => io.byte_at(offset),
+    };
+    lookup(offset).map_or(val, |reg| val & reg.read_mask_byte(offset))
+}
+
+/// Store the raw register byte without running the write handler; IE, IF
+/// and IME are set in the interrupt controller as given
+pub(super) fn poke(mem: &mut Memory, offset: usize, val: u8) {
+    match irq_register(offset) {
+        Some((reg, shift)) => {
+            let current = mem.interrupt.read_register(reg);
+            let new_val = (current & !(0xFF << shift)) | ((val as u16) << shift);
+            mem.interrupt.poke_register(reg, new_val);
+        }
+        None => mem.io.set_byte(offset, val),
+    }
+}
+
+fn write_irq(mem: &mut Memory, offset: usize, val: u8) {
+    let Some((reg, shift)) = irq_register(offset) else {
+        return;
+    };
+    // IF clears the bits written as 1, so the other byte must not be merged in
+    let current = if reg == 0x202 {
+        0
+    } else {
+        mem.interrupt.read_register(reg)
+    };
+    let new_val = (current & !(0xFF << shift)) | ((val as u16) << shift);
+    if mem.ie_ime_write_log_enabled && mem.ie_ime_write_log.len() < 10_000 {
+        mem.ie_ime_write_log
+            .push((mem.vram_log_pc, 0x0400_0000 | offset as u32, new_val));
+    }
+    mem.interrupt.write_register(reg, new_val);
+}
+
+fn write_waitcnt(mem: &mut Memory, offset: usize, val: u8) {
+    mem.io.set_byte(offset, val);
+    let raw = mem.io.half_at(0x204) & waitcnt::WRITABLE;
+    mem.io.set_half(0x204, raw);
+    mem.waitcnt = raw;
+}
+
+fn write_haltcnt(mem: &mut Memory, offset: usize, val: u8) {
+    // Bit 7 clear enters HALT, set enters STOP
+    if val & 0x80 != 0 {
+        mem.stop_pending = true;
+    } else {
+        mem.halt_pending = true;
+    }
+    mem.io.set_byte(offset, val);
+}
@@ -0,0 +1,73 @@
+//! Game Pak Waitstate Control (WAITCNT, 0x0400_0204)
+//!
+//! Bit layout per GBATEK:
+//! - 0-1:   SRAM wait (4, 3, 2, 8 cycles)
+//! - 2-3:   WS0 first access (4, 3, 2, 8)
+//! - 4:     WS0 second access (2, 1)
+//! - 5-6:   WS1 first access (4, 3, 2, 8)
+//! - 7:     WS1 second access (4, 1)
+//! - 8-9:   WS2 first access (4, 3, 2, 8)
+//! - 10:    WS2 second access (8, 1)
+//! - 11-12: PHI terminal output
+//! - 14:    Game Pak prefetch buffer
+//! - 15:    Game Pak type (read-only, 0 = GBA cartridge)
+
+/// Bits the CPU can write; bit 13 is unused and bit 15 is read-only
+pub(super) const WRITABLE: u16 = 0x5FFF;
+
+const FIRST_ACCESS: [u32; 4] = [4, 3, 2, 8];
+
+/// Clock driven onto the cartridge PHI pin
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PhiOutput {
+    Disabled,
+    Mhz4,
+    Mhz8,
+    Mhz16,
+}
+
+/// Decoded WAITCNT; wait counts are extra cycles on top of the access itself
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WaitControl {
+    pub sram: u32,
+    /// First (nonsequential) access waits for WS0, WS1 and WS2
+    pub first: [u32; 3],
+    /// Second (sequential) access waits for WS0, WS1 and WS2
+    pub second: [u32; 3],
+    pub phi: PhiOutput,
+    pub prefetch: bool,
+    /// Game Pak type flag: set for a CGB cartridge, never for GBA ones
+    pub cgb_pak: bool,
+}
+
+impl WaitControl {
+    pub fn from_bits(bits: u16) -> Self {
+        let field =
+            |shift: u16, width: u16| ((bits >> shift) & (1u16 << width).wrapping_sub(1)) as usize;
+        let first = |shift: u16| FIRST_ACCESS.get(field(shift, 2)).copied().unwrap_or(8);
+        let second = |shift: u16, slow: u32| if field(shift, 1) != 0 { 1 } else { slow };
+        Self {
+            sram: first(0),
+            first: [first(2), first(5), first(8)],
+            second: [second(4, 2), second(7, 4), second(10, 8)],
+            phi: match field(11, 2) {
+                0 => PhiOutput::Disabled,
+                1 => PhiOutput::Mhz4,
+                2 => PhiOutput::Mhz8,
+                _ => PhiOutput::Mhz16,
+            },
+            prefetch: field(14, 1) != 0,
+            cgb_pak: field(15, 1) != 0,
+        }
+    }
+
+    /// Wait cycles for one access to Game Pak wait state region `ws` (0-2)
+    pub fn rom_waits(&self, ws: usize, sequential: bool) -> u32 {
+        let waits = if sequential {
+            &self.second
+        } else {
+            &self.first
+        };
+        waits.get(ws).copied().unwrap_or(0)
+    }
+}
@@ -0,0 +1,58 @@
+//! Multiboot Images
+//!
+//! A multiboot program is sent over the link cable by a master GBA running
+//! SWI 0x25 and runs from EWRAM with no cartridge inserted. Small demos and
+//! homebrew are often built this way (`.mb` files). The transfer itself is
+//! not emulated: [`crate::Gba::load_multiboot`] places the image where the
+//! slave's BIOS would have left it and starts at its first instruction,
+//! which, as on a cartridge, branches over the header.
+//!
+//! The slave BIOS also records how the image arrived in header bytes 0xC4
+//! (boot mode) and 0xC5 (slave number), and sets the flag at 0x03007FFA so
+//! that SoftReset returns to EWRAM rather than to the empty cartridge slot.
+
+use crate::view::ByteViewMut;
+use crate::Memory;
+
+/// Load address and entry point of a multiboot image
+pub const MULTIBOOT_BASE: u32 = 0x0200_0000;
+/// Largest image the transfer can deliver: all of EWRAM
+pub const MULTIBOOT_MAX_SIZE: usize = 0x4_0000;
+
+/// Header byte the BIOS fills with the transfer mode
+const BOOT_MODE: usize = 0xC4;
+/// Multi-play transfer, the mode the GBA's own master code uses
+const BOOT_MODE_MULTIPLAY: u8 = 3;
+/// Header byte the BIOS fills with this console's position on the link
+const SLAVE_ID: usize = 0xC5;
+
+/// IWRAM offset of the flag SoftReset checks (0x03007FFA)
+const RESET_TO_EWRAM: usize = 0x7FFA;
+
+/// Whether `path` follows a multiboot naming convention: `.mb`, or the
+/// `_mb.gba` devkitARM produces
+pub fn is_multiboot_path(path: &str) -> bool {
+    let path = path.to_ascii_lowercase();
+    path.ends_with(".mb") || path.ends_with("_mb.gba")
+}
+
+/// Copy `image` to the start of EWRAM and fill in what the slave BIOS
+/// leaves behind after a successful transfer
+pub(crate) fn install(image: &[u8], mem: &mut Memory) {
+    let wram = mem.wram_mut();
+    wram.set_bytes(0, image);
+    if image.len() > SLAVE_ID {
+        wram.set_byte(BOOT_MODE, BOOT_MODE_MULTIPLAY);
+        wram.set_byte(SLAVE_ID, 1);
+    }
+    mem.iwram_mut().set_byte(RESET_TO_EWRAM, 1);
+}
+
+/// Where SoftReset (SWI 0x00) jumps, given the flag at 0x03007FFA
+pub(crate) fn soft_reset_entry(flag: u8) -> u32 {
+    if flag != 0 {
+        MULTIBOOT_BASE
+    } else {
+        0x0800_0000
+    }
+}
@@ -6,8 +6,17 @@
 //! - Sprite (OBJ) rendering
 //! - Special effects (mosaic, alpha blending, windowing)
 
+pub mod debug;
+pub mod export;
+
+use std::ops::Range;
+use std::sync::atomic::{AtomicU32, Ordering};
+
 use bitflags::bitflags;
 
+use crate::savestate::{StateError, StateReader, StateWriter};
+use crate::view::{ByteView, ByteViewMut};
+
 bitflags! {
     /// Display control flags (DISPCNT) - GBATEK bit positions
     #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -37,7 +46,424 @@ bitflags! {
     }
 }
 
+bitflags! {
+    /// Display status flags (DISPSTAT); bits 8-15 hold the VCount setting
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct DisplayStatus: u16 {
+        const VBLANK = 1 << 0;
+        const HBLANK = 1 << 1;
+        const VCOUNT_MATCH = 1 << 2;
+        const VBLANK_IRQ = 1 << 3;
+        const HBLANK_IRQ = 1 << 4;
+        const VCOUNT_IRQ = 1 << 5;
+    }
+}
+
+/// Offsets from 0x0400_0000 of the registers [`Ppu::write_register`] and
+/// [`Ppu::read_register`] handle, DISPCNT through BLDY
+pub const PPU_REGISTERS: Range<usize> = 0x00..0x56;
+
+/// DISPCNT decoded into named fields
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DisplayControlView {
+    /// Video mode 0-5 (bits 0-2)
+    pub mode: u8,
+    /// Bitmap frame shown in modes 4 and 5 (bit 4)
+    pub frame_select: bool,
+    /// OAM may be accessed during HBlank (bit 5)
+    pub hblank_free: bool,
+    /// One-dimensional OBJ tile mapping (bit 6)
+    pub obj_1d: bool,
+    pub forced_blank: bool,
+    /// BG0-BG3 enables (bits 8-11)
+    pub bg_enabled: [bool; 4],
+    pub obj_enabled: bool,
+    pub win0_enabled: bool,
+    pub win1_enabled: bool,
+    pub obj_win_enabled: bool,
+}
+
+impl DisplayControlView {
+    pub fn from_bits(val: u16) -> Self {
+        let bit = |n: u16| val & (1 << n) != 0;
+        Self {
+            mode: (val & 0x7) as u8,
+            frame_select: bit(4),
+            hblank_free: bit(5),
+            obj_1d: bit(6),
+            forced_blank: bit(7),
+            bg_enabled: [bit(8), bit(9), bit(10), bit(11)],
+            obj_enabled: bit(12),
+            win0_enabled: bit(13),
+            win1_enabled: bit(14),
+            obj_win_enabled: bit(15),
+        }
+    }
+
+    pub fn bits(&self) -> u16 {
+        let flags = [
+            (4, self.frame_select),
+            (5, self.hblank_free),
+            (6, self.obj_1d),
+            (7, self.forced_blank),
+            (8, self.bg_enabled[0]),
+            (9, self.bg_enabled[1]),
+            (10, self.bg_enabled[2]),
+            (11, self.bg_enabled[3]),
+            (12, self.obj_enabled),
+            (13, self.win0_enabled),
+            (14, self.win1_enabled),
+            (15, self.obj_win_enabled),
+        ];
+        flags
+            .iter()
+            .filter(|(_, on)| *on)
+            .fold(self.mode as u16 & 0x7, |acc, (n, _)| acc | (1 << n))
+    }
+}
+
+/// A 16KB BG tile data block, 0-3, as selected by BGCNT bits 2-3
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CharBlock(u8);
+
+impl CharBlock {
+    pub const SIZE: usize = 0x4000;
+
+    /// Block `index`; like the register field, only the low 2 bits count
+    pub const fn new(index: u8) -> Self {
+        Self(index & 0x3)
+    }
+
+    /// Block containing VRAM offset `addr`
+    pub const fn from_addr(addr: usize) -> Self {
+        Self::new((addr / Self::SIZE) as u8)
+    }
+
+    pub const fn index(self) -> u8 {
+        self.0
+    }
+
+    /// VRAM offset of the block's first byte
+    pub const fn addr(self) -> usize {
+        (self.0 as usize).wrapping_mul(Self::SIZE)
+    }
+}
+
+/// A 2KB BG map block, 0-31, as selected by BGCNT bits 8-12
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ScreenBlock(u8);
+
+impl ScreenBlock {
+    pub const SIZE: usize = 0x800;
+
+    /// Block `index`; like the register field, only the low 5 bits count
+    pub const fn new(index: u8) -> Self {
+        Self(index & 0x1F)
+    }
+
+    /// Block containing VRAM offset `addr`
+    pub const fn from_addr(addr: usize) -> Self {
+        Self::new((addr / Self::SIZE) as u8)
+    }
+
+    pub const fn index(self) -> u8 {
+        self.0
+    }
+
+    /// VRAM offset of the block's first byte
+    pub const fn addr(self) -> usize {
+        (self.0 as usize).wrapping_mul(Self::SIZE)
+    }
+}
+
+/// BGxCNT decoded into named fields
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BgControl {
+    /// Drawing priority 0-3, 0 on top (bits 0-1)
+    pub priority: u8,
+    /// Tile data block (bits 2-3)
+    pub char_base: CharBlock,
+    pub mosaic: bool,
+    /// 256-color tiles (bit 7); 16-color tiles when false
+    pub bpp8: bool,
+    /// Map block (bits 8-12)
+    pub screen_base: ScreenBlock,
+    /// Affine BGs wrap around instead of going transparent (bit 13)
+    pub wraparound: bool,
+    /// Screen size 0-3 (bits 14-15); see [`BgControl::text_size`] and
+    /// [`BgControl::affine_size`]
+    pub size: u8,
+}
+
+impl BgControl {
+    pub fn from_bits(val: u16) -> Self {
+        Self {
+            priority: (val & 0x3) as u8,
+            char_base: CharBlock::new((val >> 2) as u8),
+            mosaic: val & (1 << 6) != 0,
+            bpp8: val & (1 << 7) != 0,
+            screen_base: ScreenBlock::new((val >> 8) as u8),
+            wraparound: val & (1 << 13) != 0,
+            size: ((val >> 14) & 0x3) as u8,
+        }
+    }
+
+    pub fn bits(&self) -> u16 {
+        (self.priority as u16 & 0x3)
+            | (self.char_base.index() as u16) << 2
+            | (self.mosaic as u16) << 6
+            | (self.bpp8 as u16) << 7
+            | (self.screen_base.index() as u16) << 8
+            | (self.wraparound as u16) << 13
+            | (self.size as u16 & 0x3) << 14
+    }
+
+    /// Bits per pixel of the tiles: 4 or 8
+    pub fn bpp(&self) -> u8 {
+        if self.bpp8 {
+            8
+        } else {
+            4
+        }
+    }
+
+    /// VRAM offset of the tile data
+    pub fn char_base_addr(&self) -> usize {
+        self.char_base.addr()
+    }
+
+    /// VRAM offset of the map
+    pub fn screen_base_addr(&self) -> usize {
+        self.screen_base.addr()
+    }
+
+    /// Map size in pixels when drawn as a text BG
+    pub fn text_size(&self) -> (u32, u32) {
+        match self.size & 0x3 {
+            0 => (256, 256),
+            1 => (512, 256),
+            2 => (256, 512),
+            _ => (512, 512),
+        }
+    }
+
+    /// Map size in pixels (square) when drawn as an affine BG
+    pub fn affine_size(&self) -> u32 {
+        128 << (self.size & 0x3)
+    }
+}
+
+/// A single PPU layer, in BG0-BG3, OBJ, backdrop order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Layer {
+    Bg0,
+    Bg1,
+    Bg2,
+    Bg3,
+    Obj,
+    Backdrop,
+}
+
+impl Layer {
+    pub const ALL: [Layer; 6] = [
+        Layer::Bg0,
+        Layer::Bg1,
+        Layer::Bg2,
+        Layer::Bg3,
+        Layer::Obj,
+        Layer::Backdrop,
+    ];
+}
+
+const LAYER_PIXELS: usize = 240 * 160;
+
+/// Per-layer output of the frame before windowing, priority sorting and
+/// blending, as captured while [`Ppu::set_layer_capture`] is on.
+///
+/// Pixels are `None` where the layer is transparent. Palette indices are
+/// 0-255 for BG colors and 256-511 for OBJ colors; direct-color bitmap
+/// pixels (modes 3 and 5) have a color but no index.
+#[derive(Clone)]
+pub struct LayerBuffers {
+    rgb555: Vec<Option<u16>>,
+    indexed: Vec<Option<u16>>,
+}
+
+impl LayerBuffers {
+    const LEN: usize = Layer::ALL.len() * LAYER_PIXELS;
+
+    fn new() -> Self {
+        Self {
+            rgb555: vec![None; Self::LEN],
+            indexed: vec![None; Self::LEN],
+        }
+    }
+
+    /// 240x160 RGB555 colors of `layer`
+    pub fn rgb555(&self, layer: Layer) -> &[Option<u16>] {
+        let mut layers = self.rgb555.chunks_exact(LAYER_PIXELS);
+        layers.nth(layer as usize).unwrap_or_default()
+    }
+
+    /// 240x160 palette indices of `layer`
+    pub fn indexed(&self, layer: Layer) -> &[Option<u16>] {
+        let mut layers = self.indexed.chunks_exact(LAYER_PIXELS);
+        layers.nth(layer as usize).unwrap_or_default()
+    }
+
+    pub(crate) fn set(
+        &mut self,
+        layer: Layer,
+        x: usize,
+        y: usize,
+        pixel: Option<(u16, Option<u16>)>,
+    ) {
+        let i = (layer as usize)
+            .wrapping_mul(LAYER_PIXELS)
+            .wrapping_add(y.wrapping_mul(240))
+            .wrapping_add(x);
+        if let Some(color) = self.rgb555.get_mut(i) {
+            *color = pixel.map(|(color, _)| color);
+        }
+        if let Some(index) = self.indexed.get_mut(i) {
+            *index = pixel.and_then(|(_, index)| index);
+        }
+    }
+}
+
+/// Scene complexity of one frame, as collected while
+/// [`Ppu::set_frame_stats`] is on. Sprites count once per scanline they
+/// cover, since the OBJ cycle budget is spent line by line.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FrameStats {
+    /// Sprite lines drawn
+    pub sprites_rendered: u32,
+    /// Sprite lines skipped because the line's OBJ cycle budget ran out;
+    /// always 0 unless [`Ppu::set_sprite_cycle_limit`] is on
+    pub sprites_dropped: u32,
+    /// Screen pixels won by each layer, indexed by `Layer as usize`
+    pub layer_pixels: [u32; 6],
+    /// Pixels that went through alpha blending or a brightness change
+    pub blends: u32,
+}
+
+/// Number of OBJ affine parameter groups in OAM
+pub const OAM_AFFINE_GROUPS: usize = 32;
+
+/// One OBJ affine parameter group: the 8.8 fixed-point matrix mapping
+/// screen offsets from a sprite's centre to texture offsets. OAM interleaves
+/// the groups with sprite attributes; group n's PA, PB, PC and PD are the
+/// unused fourth halfwords of sprites 4n to 4n+3.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AffineParams {
+    pub pa: i16,
+    pub pb: i16,
+    pub pc: i16,
+    pub pd: i16,
+}
+
+impl AffineParams {
+    /// No rotation, 1:1 scale
+    pub const IDENTITY: Self = Self {
+        pa: 0x100,
+        pb: 0,
+        pc: 0,
+        pd: 0x100,
+    };
+
+    /// OAM offset of the group's PA; PB, PC and PD follow every 8 bytes
+    fn offset(group: usize) -> usize {
+        (group << 5) | 6
+    }
+
+    /// Read `group` from OAM; groups past the last read as the identity
+    pub(crate) fn read(oam: &[u8], group: usize) -> Self {
+        if group >= OAM_AFFINE_GROUPS {
+            return Self::IDENTITY;
+        }
+        let at = |i: usize| oam.half_at(Self::offset(group).wrapping_add(i << 3)) as i16;
+        Self {
+            pa: at(0),
+            pb: at(1),
+            pc: at(2),
+            pd: at(3),
+        }
+    }
+
+    /// Store into `group` in OAM, leaving the sprite attributes around it
+    pub(crate) fn write(&self, oam: &mut [u8], group: usize) {
+        if group >= OAM_AFFINE_GROUPS {
+            return;
+        }
+        for (i, value) in [self.pa, self.pb, self.pc, self.pd].into_iter().enumerate() {
+            oam.set_half(Self::offset(group).wrapping_add(i << 3), value as u16);
+        }
+    }
+
+    /// Texture offset for screen offset (`x`, `y`), both from the centre
+    pub fn transform(&self, x: i32, y: i32) -> (i32, i32) {
+        (
+            (self.pa as i32)
+                .wrapping_mul(x)
+                .wrapping_add((self.pb as i32).wrapping_mul(y))
+                >> 8,
+            (self.pc as i32)
+                .wrapping_mul(x)
+                .wrapping_add((self.pd as i32).wrapping_mul(y))
+                >> 8,
+        )
+    }
+}
+
+/// (line, OAM entries drawn) for the last line evaluated, in an atomic so
+/// a shared `&Ppu` can be rendered from another thread
+#[derive(Debug)]
+struct ObjCutoffCache(AtomicU32);
+
+impl ObjCutoffCache {
+    const EMPTY: u32 = u32::MAX;
+
+    fn new() -> Self {
+        Self(AtomicU32::new(Self::EMPTY))
+    }
+
+    fn get(&self) -> Option<(u16, u8)> {
+        let packed = self.0.load(Ordering::Relaxed);
+        (packed != Self::EMPTY).then_some(((packed >> 8) as u16, packed as u8))
+    }
+
+    fn set(&self, entry: Option<(u16, u8)>) {
+        let packed = match entry {
+            Some((line, count)) => (line as u32) << 8 | count as u32,
+            None => Self::EMPTY,
+        };
+        self.0.store(packed, Ordering::Relaxed);
+    }
+}
+
+impl Clone for ObjCutoffCache {
+    fn clone(&self) -> Self {
+        Self(AtomicU32::new(self.0.load(Ordering::Relaxed)))
+    }
+}
+
+/// Registers a game can rewrite between scanlines that change how a line
+/// is drawn, recorded per line for [`crate::PpuFrameState`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct LineRegisters {
+    dispcnt: DisplayControl,
+    bgcnt: [u16; 4],
+    bg_hofs: [u16; 4],
+    bg_vofs: [u16; 4],
+    bg_affine: [[u32; 4]; 2],
+    bg_mosaic: u16,
+    obj_mosaic: u16,
+    windows: [u16; 6],
+    blend: [u16; 3],
+}
+
 /// GBA Picture Processing Unit
+#[derive(Clone)]
 pub struct Ppu {
     // Display control
     dispcnt: DisplayControl,
@@ -60,6 +486,13 @@ pub struct Ppu {
     bg_vofs: [u16; 4],
     bg_affine: [[u32; 4]; 2], // For BG2 and BG3
 
+    // BG2/BG3 reference point (20.8 fixed point): the BGxX/BGxY register values,
+    // the internal accumulators stepped by PB/PD after each line, and the value
+    // each visible line was drawn with
+    bg_ref: [[i32; 2]; 2],
+    bg_ref_internal: [[i32; 2]; 2],
+    bg_ref_lines: Box<[[[i32; 2]; 2]; 160]>,
+
     // Mosaic settings
     pub bg_mosaic: u16,
     pub obj_mosaic: u16,
@@ -83,6 +516,24 @@ pub struct Ppu {
 
     // Sprite data (simplified OAM storage)
     oam: Box<[u8; 0x400]>, // 1KB OAM
+
+    // Accuracy option: emulate the OBJ-vs-BG priority quirk (kept across reset)
+    obj_priority_bug: bool,
+
+    // Accuracy option: drop sprites past the per-line OBJ cycle budget (kept across reset)
+    sprite_cycle_limit: bool,
+    // (line, OAM entries drawn) for the last line evaluated; cleared on OAM/DISPCNT changes
+    obj_cutoff_cache: ObjCutoffCache,
+
+    // Debug: pre-composite layer output, filled by the Gba each visible line
+    pub(crate) layer_buffers: Option<Box<LayerBuffers>>,
+
+    // Debug: scene statistics for the frame being drawn (None while off) and the last finished one
+    stats_acc: Option<FrameStats>,
+    frame_stats: FrameStats,
+
+    // Debug: DISPCNT BG0-3/OBJ enable bits (8-12) masked out when rendering
+    hidden_layers: u16,
 }
 
 impl Ppu {
@@ -97,6 +548,9 @@ impl Ppu {
             bg_hofs: [0; 4],
             bg_vofs: [0; 4],
             bg_affine: [[0x100, 0, 0, 0x100], [0x100, 0, 0, 0x100]], // Identity matrices
+            bg_ref: [[0; 2]; 2],
+            bg_ref_internal: [[0; 2]; 2],
+            bg_ref_lines: Box::new([[[0; 2]; 2]; 160]),
             bg_mosaic: 0,
             obj_mosaic: 0,
             win0_h: 0,
@@ -110,10 +564,18 @@ impl Ppu {
             bldy: 0,
             vram: Box::new([0; 0x18000]),
             oam: Box::new([0; 0x400]),
+            obj_priority_bug: false,
+            sprite_cycle_limit: false,
+            obj_cutoff_cache: ObjCutoffCache::new(),
+            layer_buffers: None,
+            stats_acc: None,
+            frame_stats: FrameStats::default(),
+            hidden_layers: 0,
         }
     }
 
     pub fn reset(&mut self) {
+        self.obj_cutoff_cache.set(None);
         self.dispcnt = DisplayControl::empty();
         self.display_enabled = false;
         self.dispstat = 0;
@@ -123,6 +585,9 @@ impl Ppu {
         self.bg_hofs = [0; 4];
         self.bg_vofs = [0; 4];
         self.bg_affine = [[0x100, 0, 0, 0x100], [0x100, 0, 0, 0x100]];
+        self.bg_ref = [[0; 2]; 2];
+        self.bg_ref_internal = [[0; 2]; 2];
+        self.bg_ref_lines.fill([[0; 2]; 2]);
         self.bg_mosaic = 0;
         self.obj_mosaic = 0;
         self.win0_h = 0;
@@ -138,11 +603,135 @@ impl Ppu {
         self.oam.fill(0);
     }
 
+    /// Write the 16-bit register at `offset` from 0x0400_0000 as a CPU store
+    /// would; this is how the bus hands register writes to the PPU. DISPSTAT
+    /// keeps its status flags, VCOUNT is read-only, and a BG2/BG3 reference
+    /// point half reloads that coordinate's internal accumulator.
+    pub fn write_register(&mut self, offset: usize, val: u16) {
+        let offset = offset & !1;
+        match offset {
+            0x00 => self.set_dispcnt(val),
+            0x04 => self.set_dispstat(val),
+            0x08..=0x0E => self.set_bgcnt((offset >> 1) & 3, val),
+            0x10..=0x1E => {
+                let bg = (offset >> 2) & 3;
+                if offset & 2 == 0 {
+                    self.set_bg_hofs(bg, val & 0x1FF);
+                } else {
+                    self.set_bg_vofs(bg, val & 0x1FF);
+                }
+            }
+            0x20..=0x3E => {
+                // BG2 or BG3
+                let bg = (offset >> 4) & 1;
+                match offset & 0xF {
+                    reg @ 0x0..=0x6 => {
+                        let params = self.bg_affine.get_mut(bg);
+                        if let Some(param) = params.and_then(|p| p.get_mut(reg >> 1)) {
+                            *param = val as i16 as i32 as u32;
+                        }
+                    }
+                    reg => {
+                        let axis = (reg >> 2) & 1;
+                        let (Some(latched), Some(internal)) = (
+                            self.bg_ref.get_mut(bg).and_then(|r| r.get_mut(axis)),
+                            self.bg_ref_internal
+                                .get_mut(bg)
+                                .and_then(|r| r.get_mut(axis)),
+                        ) else {
+                            return;
+                        };
+                        let raw = *latched as u32;
+                        let raw = if reg & 2 == 0 {
+                            (raw & !0xFFFF) | val as u32
+                        } else {
+                            (raw & 0xFFFF) | (val as u32) << 16
+                        };
+                        // 28-bit signed 20.8 fixed point
+                        let point = ((raw as i32) << 4) >> 4;
+                        *latched = point;
+                        *internal = point;
+                    }
+                }
+            }
+            0x40 => self.win0_h = val,
+            0x42 => self.win1_h = val,
+            0x44 => self.win0_v = val,
+            0x46 => self.win1_v = val,
+            0x48 => self.winin = val,
+            0x4A => self.winout = val,
+            0x4C => {
+                self.bg_mosaic = val & 0xFF;
+                self.obj_mosaic = val >> 8;
+            }
+            0x50 => self.bldcnt = val,
+            0x52 => self.bldalpha = val,
+            0x54 => self.bldy = val,
+            _ => {}
+        }
+    }
+
+    /// The 16-bit register at `offset` from 0x0400_0000 as the PPU holds
+    /// it, including the current status flags and scanline; registers the
+    /// hardware makes write-only return the value last written
+    pub fn read_register(&self, offset: usize) -> u16 {
+        let offset = offset & !1;
+        match offset {
+            0x00 => self.get_dispcnt(),
+            0x04 => self.get_dispstat(),
+            0x06 => self.vcount,
+            0x08..=0x0E => self.bgcnt.get((offset >> 1) & 3).copied().unwrap_or(0),
+            0x10..=0x1E => {
+                let bg = (offset >> 2) & 3;
+                let scroll = if offset & 2 == 0 {
+                    &self.bg_hofs
+                } else {
+                    &self.bg_vofs
+                };
+                scroll.get(bg).copied().unwrap_or(0)
+            }
+            0x20..=0x3E => {
+                let bg = (offset >> 4) & 1;
+                match offset & 0xF {
+                    reg @ 0x0..=0x6 => {
+                        let params = self.bg_affine.get(bg);
+                        params
+                            .and_then(|p| p.get(reg >> 1))
+                            .map_or(0, |&p| p as u16)
+                    }
+                    reg => {
+                        let point = self.bg_ref.get(bg).and_then(|r| r.get((reg >> 2) & 1));
+                        let raw = point.map_or(0, |&p| p as u32) & 0x0FFF_FFFF;
+                        if reg & 2 == 0 {
+                            raw as u16
+                        } else {
+                            (raw >> 16) as u16
+                        }
+                    }
+                }
+            }
+            0x40 => self.win0_h,
+            0x42 => self.win1_h,
+            0x44 => self.win0_v,
+            0x46 => self.win1_v,
+            0x48 => self.winin,
+            0x4A => self.winout,
+            0x4C => (self.bg_mosaic & 0xFF) | (self.obj_mosaic & 0xFF) << 8,
+            0x50 => self.bldcnt,
+            0x52 => self.bldalpha,
+            0x54 => self.bldy,
+            _ => 0,
+        }
+    }
+
     /// Sync VRAM data from Memory system
     /// This must be called before rendering to get the latest VRAM state
+    #[doc(hidden)]
     pub fn sync_vram(&mut self, vram_data: &[u8]) {
         let len = self.vram.len().min(vram_data.len());
-        self.vram[..len].copy_from_slice(&vram_data[..len]);
+        if let (Some(dst), Some(src)) = (self.vram.get_mut(..len), vram_data.get(..len)) {
+            dst.copy_from_slice(src);
+        }
     }
 
     /// Get a reference to VRAM (for reading by GUI)
@@ -171,6 +760,7 @@ impl Ppu {
     /// Set the full DISPCNT value from memory
     pub fn set_dispcnt(&mut self, val: u16) {
         self.dispcnt = DisplayControl::from_bits_truncate(val);
+        self.obj_cutoff_cache.set(None);
     }
 
     /// Get the full DISPCNT value
@@ -178,6 +768,48 @@ impl Ppu {
         self.dispcnt.bits()
     }
 
+    /// DISPCNT as named fields
+    pub fn display_control(&self) -> DisplayControlView {
+        DisplayControlView::from_bits(self.get_dispcnt())
+    }
+
+    pub fn set_display_control(&mut self, control: DisplayControlView) {
+        self.set_dispcnt(control.bits());
+    }
+
+    /// DISPCNT as seen by the renderer, with debug-hidden layers disabled
+    pub fn render_dispcnt(&self) -> u16 {
+        self.dispcnt.bits() & !self.hidden_layers
+    }
+
+    /// Hide or show a layer when rendering without touching DISPCNT itself.
+    /// The backdrop cannot be hidden.
+    pub fn set_layer_enabled(&mut self, layer: Layer, enabled: bool) {
+        let bit = match layer {
+            Layer::Backdrop => return,
+            _ => 0x100 << layer as u16,
+        };
+        if enabled {
+            self.hidden_layers &= !bit;
+        } else {
+            self.hidden_layers |= bit;
+        }
+    }
+
+    /// False if `layer` is hidden by [`Ppu::set_layer_enabled`]
+    pub fn layer_enabled(&self, layer: Layer) -> bool {
+        layer == Layer::Backdrop || self.hidden_layers & (0x100 << layer as u16) == 0
+    }
+
+    /// OBJ character mapping: true for 1D (linear), false for 2D (32-tile rows)
+    pub fn is_obj_1d_mapping(&self) -> bool {
+        self.dispcnt.contains(DisplayControl::OBJ_VRAM_1D)
+    }
+
+    pub fn set_obj_1d_mapping(&mut self, enabled: bool) {
+        self.dispcnt.set(DisplayControl::OBJ_VRAM_1D, enabled);
+    }
+
     pub fn get_width(&self) -> u16 {
         match self.get_display_mode() {
             3 | 4 => 240,
@@ -198,6 +830,7 @@ impl Ppu {
         self.vcount
     }
 
+    #[doc(hidden)]
     pub fn set_vcount(&mut self, count: u16) {
         self.vcount = count;
     }
@@ -206,6 +839,7 @@ impl Ppu {
         self.hcounter
     }
 
+    #[doc(hidden)]
     pub fn set_hcounter(&mut self, count: u32) {
         self.hcounter = count;
     }
@@ -218,6 +852,16 @@ impl Ppu {
         self.hcounter >= 960 // Visible pixels: 240 * 4 = 960 cycles
     }
 
+    /// Cycles until HBlank starts or the line ends, whichever is next.
+    /// Stepping at most this far sees every HBlank and line edge.
+    pub fn cycles_to_next_edge(&self) -> u32 {
+        if self.hcounter < 960 {
+            960u32.wrapping_sub(self.hcounter)
+        } else {
+            1232u32.saturating_sub(self.hcounter).max(1)
+        }
+    }
+
     // Display status (DISPSTAT)
     pub fn get_dispstat(&self) -> u16 {
         let mut stat = self.dispstat;
@@ -233,15 +877,28 @@ impl Ppu {
         } else {
             stat &= !0x0002;
         }
-        // Bit 2: VCount match flag (not implemented for now)
+        // Bit 2: VCount match flag (set while VCOUNT equals the setting)
+        if self.is_vcount_match() {
+            stat |= 0x0004;
+        } else {
+            stat &= !0x0004;
+        }
         stat
     }
 
     pub fn set_dispstat(&mut self, val: u16) {
-        // Only bits 8-15 are writable (VCount setting)
         // Bits 0-2 are status flags, read-only
-        // Bits 3-7 are interrupt enables, writable
-        self.dispstat = val;
+        // Bits 3-7 are interrupt enables, bits 8-15 the VCount setting
+        self.dispstat = val & !0x0007;
+    }
+
+    /// VCount setting (LYC) from DISPSTAT bits 8-15
+    pub fn vcount_setting(&self) -> u16 {
+        self.dispstat >> 8
+    }
+
+    pub fn is_vcount_match(&self) -> bool {
+        self.vcount == self.vcount_setting()
     }
 
     // Background control
@@ -250,14 +907,14 @@ impl Ppu {
             return false;
         }
         self.dispcnt
-            .contains(DisplayControl::from_bits_truncate(1 << (8 + bg)))
+            .contains(DisplayControl::from_bits_truncate(0x100 << bg))
     }
 
     pub fn set_bg_enabled(&mut self, bg: usize, enabled: bool) {
         if bg > 3 {
             return;
         }
-        let flag = DisplayControl::from_bits_truncate(1 << (8 + bg));
+        let flag = DisplayControl::from_bits_truncate(0x100 << bg);
         if enabled {
             self.dispcnt |= flag;
         } else {
@@ -266,159 +923,208 @@ impl Ppu {
     }
 
     pub fn get_bg_priority(&self, bg: usize) -> u16 {
-        if bg > 3 {
-            return 0;
-        }
-        self.bgcnt[bg] & 0x3
+        self.get_bgcnt(bg) & 0x3
     }
 
     pub fn set_bg_priority(&mut self, bg: usize, priority: u16) {
-        if bg > 3 {
-            return;
+        if let Some(cnt) = self.bgcnt.get_mut(bg) {
+            *cnt = (*cnt & !0x3) | (priority & 0x3);
         }
-        self.bgcnt[bg] = (self.bgcnt[bg] & !0x3) | (priority & 0x3);
     }
 
+    /// Tile data block of `bg` (BGCNT bits 2-3)
+    pub fn bg_char_block(&self, bg: usize) -> CharBlock {
+        self.bg_control(bg).char_base
+    }
+
+    pub fn set_bg_char_block(&mut self, bg: usize, block: CharBlock) {
+        let control = self.bg_control(bg);
+        self.set_bg_control(
+            bg,
+            BgControl {
+                char_base: block,
+                ..control
+            },
+        );
+    }
+
+    /// Map block of `bg` (BGCNT bits 8-12)
+    pub fn bg_screen_block(&self, bg: usize) -> ScreenBlock {
+        self.bg_control(bg).screen_base
+    }
+
+    pub fn set_bg_screen_block(&mut self, bg: usize, block: ScreenBlock) {
+        let control = self.bg_control(bg);
+        self.set_bg_control(
+            bg,
+            BgControl {
+                screen_base: block,
+                ..control
+            },
+        );
+    }
+
+    /// VRAM offset of `bg`'s tile data: 0x0000, 0x4000, 0x8000 or 0xC000
     pub fn get_bg_tile_base(&self, bg: usize) -> u16 {
-        if bg > 3 {
-            return 0;
-        }
-        // BG tile base is in 16KB units (bits 2-3 of BGCNT)
-        // 0 = 0x0000, 1 = 0x4000, 2 = 0x8000, 3 = 0xC000
-        ((self.bgcnt[bg] >> 2) & 0x3) * 0x4000
+        self.bg_char_block(bg).addr() as u16
     }
 
+    /// Point `bg`'s tile data at VRAM offset `base`, rounded down to a
+    /// 16KB block
     pub fn set_bg_tile_base(&mut self, bg: usize, base: u16) {
-        if bg > 3 {
-            return;
-        }
-        self.bgcnt[bg] = (self.bgcnt[bg] & !0xC) | ((base & 0x60) << 2);
+        self.set_bg_char_block(bg, CharBlock::from_addr(base as usize));
     }
 
+    /// VRAM offset of `bg`'s map, a multiple of 0x800
     pub fn get_bg_map_base(&self, bg: usize) -> u16 {
-        if bg > 3 {
-            return 0;
-        }
-        // BG map base is in 2KB units (bits 8-12 of BGCNT)
-        // Each unit is 0x800 bytes
-        ((self.bgcnt[bg] >> 8) & 0x1F) * 0x800
+        self.bg_screen_block(bg).addr() as u16
     }
 
+    /// Point `bg`'s map at VRAM offset `base`, rounded down to a 2KB block
     pub fn set_bg_map_base(&mut self, bg: usize, base: u16) {
-        if bg > 3 {
-            return;
-        }
-        self.bgcnt[bg] = (self.bgcnt[bg] & !0x1F00) | ((base & 0x1F) << 8);
+        self.set_bg_screen_block(bg, ScreenBlock::from_addr(base as usize));
     }
 
     /// Get the BG control register value
     pub fn get_bgcnt(&self, bg: usize) -> u16 {
-        if bg > 3 {
-            return 0;
-        }
-        self.bgcnt[bg]
+        self.bgcnt.get(bg).copied().unwrap_or(0)
     }
 
     /// Set the BG control register value
     pub fn set_bgcnt(&mut self, bg: usize, val: u16) {
-        if bg > 3 {
-            return;
+        if let Some(cnt) = self.bgcnt.get_mut(bg) {
+            *cnt = val;
         }
-        self.bgcnt[bg] = val;
+    }
+
+    /// BGxCNT of `bg` as named fields
+    pub fn bg_control(&self, bg: usize) -> BgControl {
+        BgControl::from_bits(self.get_bgcnt(bg))
+    }
+
+    pub fn set_bg_control(&mut self, bg: usize, control: BgControl) {
+        self.set_bgcnt(bg, control.bits());
     }
 
     /// Get background horizontal offset
     pub fn get_bg_hofs(&self, bg: usize) -> u16 {
-        if bg > 3 {
-            return 0;
-        }
-        self.bg_hofs[bg]
+        self.bg_hofs.get(bg).copied().unwrap_or(0)
     }
 
     /// Set background horizontal offset
     pub fn set_bg_hofs(&mut self, bg: usize, val: u16) {
-        if bg > 3 {
-            return;
+        if let Some(hofs) = self.bg_hofs.get_mut(bg) {
+            *hofs = val & 0x1FF; // 9 bits (0-511)
         }
-        self.bg_hofs[bg] = val & 0x1FF; // 9 bits (0-511)
     }
 
     /// Get background vertical offset
     pub fn get_bg_vofs(&self, bg: usize) -> u16 {
-        if bg > 3 {
-            return 0;
-        }
-        self.bg_vofs[bg]
+        self.bg_vofs.get(bg).copied().unwrap_or(0)
     }
 
     /// Set background vertical offset
     pub fn set_bg_vofs(&mut self, bg: usize, val: u16) {
-        if bg > 3 {
-            return;
+        if let Some(vofs) = self.bg_vofs.get_mut(bg) {
+            *vofs = val & 0x1FF; // 9 bits (0-511)
         }
-        self.bg_vofs[bg] = val & 0x1FF; // 9 bits (0-511)
     }
 
     // Affine background
+    /// PA-PD (`param` 0-3) of BG2 or BG3; other backgrounds have none
+    fn bg_affine_param(&self, bg: usize, param: usize) -> Option<u32> {
+        self.bg_affine.get(bg.checked_sub(2)?)?.get(param).copied()
+    }
+
+    fn bg_affine_param_mut(&mut self, bg: usize, param: usize) -> Option<&mut u32> {
+        self.bg_affine.get_mut(bg.checked_sub(2)?)?.get_mut(param)
+    }
+
     pub fn set_bg_affine_a(&mut self, bg: usize, val: u32) {
-        if bg == 2 || bg == 3 {
-            self.bg_affine[bg - 2][0] = val;
+        if let Some(param) = self.bg_affine_param_mut(bg, 0) {
+            *param = val;
         }
     }
 
     pub fn get_bg_affine_a(&self, bg: usize) -> u32 {
-        if bg == 2 || bg == 3 {
-            self.bg_affine[bg - 2][0]
-        } else {
-            0x100
-        }
+        self.bg_affine_param(bg, 0).unwrap_or(0x100)
     }
 
     pub fn set_bg_affine_b(&mut self, bg: usize, val: u32) {
-        if bg == 2 || bg == 3 {
-            self.bg_affine[bg - 2][1] = val;
+        if let Some(param) = self.bg_affine_param_mut(bg, 1) {
+            *param = val;
         }
     }
 
     pub fn get_bg_affine_b(&self, bg: usize) -> u32 {
-        if bg == 2 || bg == 3 {
-            self.bg_affine[bg - 2][1]
-        } else {
-            0
-        }
+        self.bg_affine_param(bg, 1).unwrap_or(0)
     }
 
     pub fn set_bg_affine_c(&mut self, bg: usize, val: u32) {
-        if bg == 2 || bg == 3 {
-            self.bg_affine[bg - 2][2] = val;
+        if let Some(param) = self.bg_affine_param_mut(bg, 2) {
+            *param = val;
         }
     }
 
     pub fn get_bg_affine_c(&self, bg: usize) -> u32 {
-        if bg == 2 || bg == 3 {
-            self.bg_affine[bg - 2][2]
-        } else {
-            0
-        }
+        self.bg_affine_param(bg, 2).unwrap_or(0)
     }
 
     pub fn set_bg_affine_d(&mut self, bg: usize, val: u32) {
-        if bg == 2 || bg == 3 {
-            self.bg_affine[bg - 2][3] = val;
+        if let Some(param) = self.bg_affine_param_mut(bg, 3) {
+            *param = val;
         }
     }
 
     pub fn get_bg_affine_d(&self, bg: usize) -> u32 {
-        if bg == 2 || bg == 3 {
-            self.bg_affine[bg - 2][3]
-        } else {
-            0
+        self.bg_affine_param(bg, 3).unwrap_or(0)
+    }
+
+    /// Write BGxX/BGxY (bg 2 or 3). Like hardware, this also reloads the
+    /// internal reference point immediately, so mid-frame writes apply from
+    /// the next line drawn.
+    pub fn set_bg_reference(&mut self, bg: usize, x: i32, y: i32) {
+        let Some(slot) = bg.checked_sub(2) else {
+            return;
+        };
+        if let Some(point) = self.bg_ref.get_mut(slot) {
+            *point = [x, y];
+        }
+        if let Some(point) = self.bg_ref_internal.get_mut(slot) {
+            *point = [x, y];
+        }
+    }
+
+    /// BGxX/BGxY register values
+    pub fn get_bg_reference(&self, bg: usize) -> (i32, i32) {
+        let point = bg.checked_sub(2).and_then(|slot| self.bg_ref.get(slot));
+        point.map_or((0, 0), |&[x, y]| (x, y))
+    }
+
+    /// Reference point the given visible line was drawn with
+    pub fn get_bg_reference_for_line(&self, bg: usize, line: u16) -> (i32, i32) {
+        let point = self
+            .bg_ref_lines
+            .get(line as usize)
+            .and_then(|points| points.get(bg.checked_sub(2)?));
+        point.map_or((0, 0), |&[x, y]| (x, y))
+    }
+
+    /// Record the internal reference points for a drawn line, then step them by PB/PD
+    fn latch_bg_reference_line(&mut self, line: u16) {
+        let Some(latched) = self.bg_ref_lines.get_mut(line as usize) else {
+            return;
+        };
+        *latched = self.bg_ref_internal;
+        for (internal, params) in self.bg_ref_internal.iter_mut().zip(&self.bg_affine) {
+            internal[0] = internal[0].wrapping_add(params[1] as i32);
+            internal[1] = internal[1].wrapping_add(params[3] as i32);
         }
     }
 
     // Mosaic
     pub fn get_bg_mosaic_h(&self) -> u16 {
-        (self.bg_mosaic & 0xF) + 1
+        (self.bg_mosaic & 0xF).wrapping_add(1)
     }
 
     /// Get BG mosaic horizontal block size (raw, 0-15)
@@ -432,11 +1138,11 @@ impl Ppu {
     }
 
     pub fn set_bg_mosaic_h(&mut self, val: u16) {
-        self.bg_mosaic = (self.bg_mosaic & !0xF) | (val - 1).min(0xF);
+        self.bg_mosaic = (self.bg_mosaic & !0xF) | val.wrapping_sub(1).min(0xF);
     }
 
     pub fn get_bg_mosaic_v(&self) -> u16 {
-        ((self.bg_mosaic >> 4) & 0xF) + 1
+        ((self.bg_mosaic >> 4) & 0xF).wrapping_add(1)
     }
 
     /// Apply BG mosaic to pixel coordinates
@@ -446,29 +1152,35 @@ impl Ppu {
         if mh == 0 && mv == 0 {
             return (x, y);
         }
-        let bx = if mh > 0 { (x / (mh + 1)) * (mh + 1) } else { x };
-        let by = if mv > 0 { (y / (mv + 1)) * (mv + 1) } else { y };
-        (bx, by)
+        (
+            Self::mosaic_block_start(x, mh.wrapping_add(1)),
+            Self::mosaic_block_start(y, mv.wrapping_add(1)),
+        )
+    }
+
+    /// First coordinate of the `block`-pixel mosaic block holding `v`
+    fn mosaic_block_start(v: u16, block: u16) -> u16 {
+        v.wrapping_sub(v.checked_rem(block).unwrap_or(0))
     }
 
     pub fn set_bg_mosaic_v(&mut self, val: u16) {
-        self.bg_mosaic = (self.bg_mosaic & !0xF0) | ((val - 1).min(0xF) << 4);
+        self.bg_mosaic = (self.bg_mosaic & !0xF0) | (val.wrapping_sub(1).min(0xF) << 4);
     }
 
     pub fn get_obj_mosaic_h(&self) -> u16 {
-        (self.obj_mosaic & 0xF) + 1
+        (self.obj_mosaic & 0xF).wrapping_add(1)
     }
 
     pub fn set_obj_mosaic_h(&mut self, val: u16) {
-        self.obj_mosaic = (self.obj_mosaic & !0xF) | (val - 1).min(0xF);
+        self.obj_mosaic = (self.obj_mosaic & !0xF) | val.wrapping_sub(1).min(0xF);
     }
 
     pub fn get_obj_mosaic_v(&self) -> u16 {
-        ((self.obj_mosaic >> 4) & 0xF) + 1
+        ((self.obj_mosaic >> 4) & 0xF).wrapping_add(1)
     }
 
     pub fn set_obj_mosaic_v(&mut self, val: u16) {
-        self.obj_mosaic = (self.obj_mosaic & !0xF0) | ((val - 1).min(0xF) << 4);
+        self.obj_mosaic = (self.obj_mosaic & !0xF0) | (val.wrapping_sub(1).min(0xF) << 4);
     }
 
     // Window
@@ -498,10 +1210,10 @@ impl Ppu {
 
         // Check WIN0
         if win0_en {
-            let left = (self.win0_h & 0xFF) as u16;
-            let right = ((self.win0_h >> 8) & 0xFF) as u16;
-            let top = (self.win0_v & 0xFF) as u16;
-            let bottom = ((self.win0_v >> 8) & 0xFF) as u16;
+            let left = (self.win0_h >> 8) & 0xFF;
+            let right = self.win0_h & 0xFF;
+            let top = (self.win0_v >> 8) & 0xFF;
+            let bottom = self.win0_v & 0xFF;
 
             if x >= left && x < right && y >= top && y < bottom {
                 // Inside WIN0: use WININ low byte
@@ -511,10 +1223,10 @@ impl Ppu {
 
         // Check WIN1
         if win1_en {
-            let left = (self.win1_h & 0xFF) as u16;
-            let right = ((self.win1_h >> 8) & 0xFF) as u16;
-            let top = (self.win1_v & 0xFF) as u16;
-            let bottom = ((self.win1_v >> 8) & 0xFF) as u16;
+            let left = (self.win1_h >> 8) & 0xFF;
+            let right = self.win1_h & 0xFF;
+            let top = (self.win1_v >> 8) & 0xFF;
+            let bottom = self.win1_v & 0xFF;
 
             if x >= left && x < right && y >= top && y < bottom {
                 // Inside WIN1: use WININ high byte
@@ -522,6 +1234,11 @@ impl Ppu {
             }
         }
 
+        // Check OBJ window (opaque pixels of attr0 mode 10 sprites)
+        if obj_win_en && self.obj_window_contains(x, y) {
+            return (self.winout >> 8) & 0x1F;
+        }
+
         // Outside all windows: use WINOUT
         self.winout & 0x1F
     }
@@ -534,36 +1251,37 @@ impl Ppu {
         }
     }
 
+    // WINxH/WINxV hold the left/top edge in the high byte, right/bottom in the low
     pub fn get_window1_left(&self) -> u16 {
-        self.win1_h & 0xFF
+        (self.win1_h >> 8) & 0xFF
     }
 
     pub fn set_window1_left(&mut self, val: u16) {
-        self.win1_h = (self.win1_h & !0xFF) | (val & 0xFF);
+        self.win1_h = (self.win1_h & !0xFF00) | ((val & 0xFF) << 8);
     }
 
     pub fn get_window1_right(&self) -> u16 {
-        (self.win1_h >> 8) & 0xFF
+        self.win1_h & 0xFF
     }
 
     pub fn set_window1_right(&mut self, val: u16) {
-        self.win1_h = (self.win1_h & !0xFF00) | ((val & 0xFF) << 8);
+        self.win1_h = (self.win1_h & !0xFF) | (val & 0xFF);
     }
 
     pub fn get_window1_top(&self) -> u16 {
-        self.win1_v & 0xFF
+        (self.win1_v >> 8) & 0xFF
     }
 
     pub fn set_window1_top(&mut self, val: u16) {
-        self.win1_v = (self.win1_v & !0xFF) | (val & 0xFF);
+        self.win1_v = (self.win1_v & !0xFF00) | ((val & 0xFF) << 8);
     }
 
     pub fn get_window1_bottom(&self) -> u16 {
-        (self.win1_v >> 8) & 0xFF
+        self.win1_v & 0xFF
     }
 
     pub fn set_window1_bottom(&mut self, val: u16) {
-        self.win1_v = (self.win1_v & !0xFF00) | ((val & 0xFF) << 8);
+        self.win1_v = (self.win1_v & !0xFF) | (val & 0xFF);
     }
 
     pub fn is_window1_bg_enabled(&self, bg: usize) -> bool {
@@ -655,7 +1373,7 @@ impl Ppu {
         if layer > 1 {
             return false;
         }
-        self.bldcnt & (1 << (4 + layer)) != 0
+        self.bldcnt & (0x10 << layer) != 0
     }
 
     pub fn set_blend_target_obj(&mut self, layer: usize, enabled: bool) {
@@ -663,9 +1381,9 @@ impl Ppu {
             return;
         }
         if enabled {
-            self.bldcnt |= 1 << (4 + layer);
+            self.bldcnt |= 0x10 << layer;
         } else {
-            self.bldcnt &= !(1 << (4 + layer));
+            self.bldcnt &= !(0x10 << layer);
         }
     }
 
@@ -718,54 +1436,60 @@ impl Ppu {
     }
 
     // Mode 3: 16-bit bitmap (240x160)
+    #[doc(hidden)]
     pub fn set_pixel_mode3(&mut self, x: u16, y: u16, color: u16) {
         // Mode 3: 240x160, 16-bit color
         // VRAM base: 0x0600_0000
         // Each pixel is 2 bytes
         if x < 240 && y < 160 {
-            let offset = ((y as usize * 240 + x as usize) * 2) as usize;
-            let bytes = color.to_le_bytes();
-            self.vram[offset] = bytes[0];
-            self.vram[offset + 1] = bytes[1];
+            let offset = (y as usize).wrapping_mul(240).wrapping_add(x as usize) << 1;
+            self.vram.set_half(offset, color);
         }
     }
 
+    #[doc(hidden)]
     pub fn get_pixel_mode3(&self, x: u16, y: u16) -> u16 {
         // Mode 3: 240x160, 16-bit color
         if x < 240 && y < 160 {
-            let offset = ((y as usize * 240 + x as usize) * 2) as usize;
-            u16::from_le_bytes([self.vram[offset], self.vram[offset + 1]])
+            let offset = (y as usize).wrapping_mul(240).wrapping_add(x as usize) << 1;
+            self.vram.half_at(offset)
         } else {
             0
         }
     }
 
     // Mode 4: 8-bit paletted bitmap (240x160)
+    #[doc(hidden)]
     pub fn set_pixel_mode4(&mut self, x: u16, y: u16, index: u8) {
         // Mode 4: 240x160, 8-bit palette index
         // Uses page switching for double buffering
         // Page 0: 0x0600_0000, Page 1: 0x0600_A000
         // Each pixel is 1 byte
         if x < 240 && y < 160 {
-            let offset = (y as usize * 240 + x as usize) as usize;
-            self.vram[offset] = index;
+            let offset = (y as usize).wrapping_mul(240).wrapping_add(x as usize);
+            self.vram.set_byte(offset, index);
         }
     }
 
+    #[doc(hidden)]
     pub fn get_pixel_mode4(&self, x: u16, y: u16) -> u8 {
         // Mode 4: 240x160, 8-bit palette index
         if x < 240 && y < 160 {
-            let offset = (y as usize * 240 + x as usize) as usize;
-            self.vram[offset]
+            let offset = (y as usize).wrapping_mul(240).wrapping_add(x as usize);
+            self.vram.byte_at(offset)
         } else {
             0
         }
     }
 
     // Sprite/OAM handling
+    #[doc(hidden)]
     pub fn sync_oam(&mut self, oam_data: &[u8]) {
         let len = self.oam.len().min(oam_data.len());
-        self.oam[..len].copy_from_slice(&oam_data[..len]);
+        if let (Some(dst), Some(src)) = (self.oam.get_mut(..len), oam_data.get(..len)) {
+            dst.copy_from_slice(src);
+        }
+        self.obj_cutoff_cache.set(None);
     }
 
     pub fn oam(&self) -> &[u8] {
@@ -774,12 +1498,8 @@ impl Ppu {
 
     /// Get OAM attribute word for sprite (3 words = 6 bytes each)
     fn oam_attr(&self, sprite: usize, attr: usize) -> u16 {
-        let offset = sprite * 8 + attr * 2;
-        if offset + 1 < self.oam.len() {
-            u16::from_le_bytes([self.oam[offset], self.oam[offset + 1]])
-        } else {
-            0
-        }
+        let offset = (sprite << 3) | (attr << 1);
+        self.oam.half_at(offset)
     }
 
     /// Get sprite shape (0=square, 1=horizontal, 2=vertical) from attr0 bits 14-15
@@ -804,9 +1524,8 @@ impl Ppu {
             [[8, 16], [8, 32], [16, 32], [32, 64]], // shape 2 (vertical)
             [[8, 8], [16, 16], [32, 32], [64, 64]], // shape 3 (prohibited)
         ];
-        let w = DIMENSIONS[shape][size][0];
-        let h = DIMENSIONS[shape][size][1];
-        (w, h)
+        let dimensions = DIMENSIONS.get(shape).and_then(|sizes| sizes.get(size));
+        dimensions.map_or((8, 8), |&[w, h]| (w, h))
     }
 
     /// Check if sprite is double-sized (attr0 bit 9)
@@ -836,100 +1555,348 @@ impl Ppu {
     pub fn sprite_x(&self, sprite: usize) -> i32 {
         let x = (self.oam_attr(sprite, 1) & 0x1FF) as i32;
         if x >= 256 {
-            x - 512
+            x.wrapping_sub(512)
         } else {
             x
         }
     }
 
-    /// Get sprite tile number (10-bit from attr2 bits 0-9)
-    pub fn sprite_tile(&self, sprite: usize) -> u16 {
-        self.oam_attr(sprite, 2) & 0x3FF
+    /// Get sprite tile number (10-bit from attr2 bits 0-9)
+    pub fn sprite_tile(&self, sprite: usize) -> u16 {
+        self.oam_attr(sprite, 2) & 0x3FF
+    }
+
+    /// Get sprite priority (2-bit from attr2 bits 10-11)
+    pub fn sprite_priority(&self, sprite: usize) -> u16 {
+        (self.oam_attr(sprite, 2) >> 10) & 0x3
+    }
+
+    /// Get sprite palette number (4-bit from attr2 bits 12-15, only for 16-color mode)
+    pub fn sprite_palette(&self, sprite: usize) -> u16 {
+        (self.oam_attr(sprite, 2) >> 12) & 0xF
+    }
+
+    /// Check if sprite uses 256-color mode (attr0 bit 13 = 0 for 16-color, 1 for 256-color)
+    pub fn sprite_is_256color(&self, sprite: usize) -> bool {
+        (self.oam_attr(sprite, 0) & 0x2000) != 0
+    }
+
+    /// Check if sprite uses horizontal flip (attr1 bit 12, only for non-affine)
+    pub fn sprite_flip_h(&self, sprite: usize) -> bool {
+        (self.oam_attr(sprite, 1) & 0x1000) != 0
+    }
+
+    /// Check if sprite uses vertical flip (attr1 bit 13, only for non-affine)
+    pub fn sprite_flip_v(&self, sprite: usize) -> bool {
+        (self.oam_attr(sprite, 1) & 0x2000) != 0
+    }
+
+    /// Check if sprite is affine (rotation/scaling) mode (attr0 bit 12)
+    /// attr0 bit 12: 0 = non-affine, 1 = affine
+    pub fn sprite_is_affine(&self, sprite: usize) -> bool {
+        (self.oam_attr(sprite, 0) & 0x0100) != 0
+    }
+
+    /// Get affine parameter group index (0-31) from attr0 bits 9-13
+    /// Each affine parameter group is 4 halfwords (PA, PB, PC, PD)
+    /// 32 groups share space with 128 sprites in OAM
+    pub fn sprite_rotation_param(&self, sprite: usize) -> usize {
+        let attr1 = self.oam_attr(sprite, 1);
+        ((attr1 >> 9) & 0x1F) as usize
+    }
+
+    /// Affine parameter group `group` (0-31) as last synced from OAM
+    pub fn sprite_affine(&self, group: usize) -> AffineParams {
+        AffineParams::read(&self.oam[..], group)
+    }
+
+    /// Get affine rotation parameter PA
+    pub fn sprite_affine_pa(&self, group: usize) -> i16 {
+        self.sprite_affine(group).pa
+    }
+
+    /// Get affine rotation parameter PB
+    pub fn sprite_affine_pb(&self, group: usize) -> i16 {
+        self.sprite_affine(group).pb
+    }
+
+    /// Get affine rotation parameter PC
+    pub fn sprite_affine_pc(&self, group: usize) -> i16 {
+        self.sprite_affine(group).pc
+    }
+
+    /// Get affine rotation parameter PD
+    pub fn sprite_affine_pd(&self, group: usize) -> i16 {
+        self.sprite_affine(group).pd
+    }
+
+    /// Check if sprite is a sprite-type window mask (attr0 bits 10-11 == 10)
+    pub fn sprite_is_window(&self, sprite: usize) -> bool {
+        let mode = (self.oam_attr(sprite, 0) >> 10) & 0x3;
+        mode == 0b10
+    }
+
+    /// Check if sprite is semi-transparent (attr0 bits 10-11 == 01)
+    pub fn sprite_is_semi_transparent(&self, sprite: usize) -> bool {
+        (self.oam_attr(sprite, 0) >> 10) & 0x3 == 0b01
+    }
+
+    /// OBJ palette index (0-255) of the sprite's pixel at screen (x, y),
+    /// or None if the sprite is hidden, doesn't cover the pixel, or it is transparent
+    pub fn sprite_pixel_index(&self, sprite: usize, x: u16, y: u16) -> Option<u16> {
+        let is_affine = self.sprite_is_affine(sprite);
+        let double_size = self.sprite_double_size(sprite);
+        if !is_affine && double_size {
+            return None;
+        }
+
+        let (w, h) = self.sprite_dimensions(sprite);
+        let (render_w, render_h) = if is_affine && double_size {
+            (w << 1, h << 1)
+        } else {
+            (w, h)
+        };
+
+        let dx = (x as i32).wrapping_sub(self.sprite_x(sprite));
+        let dy = (y as i32).wrapping_sub(self.sprite_y(sprite));
+        if dx < 0 || dx >= render_w as i32 || dy < 0 || dy >= render_h as i32 {
+            return None;
+        }
+
+        let is_256 = self.sprite_is_256color(sprite);
+        let tile_num = self.sprite_tile(sprite);
+        if !Self::obj_tile_usable(tile_num, self.get_display_mode()) {
+            return None;
+        }
+        let palette = self.sprite_palette(sprite);
+
+        let (px, py) = if is_affine {
+            let params = self.sprite_affine(self.sprite_rotation_param(sprite));
+            let cx = render_w as i32 / 2;
+            let cy = render_h as i32 / 2;
+            let (tx, ty) = params.transform(dx.wrapping_sub(cx), dy.wrapping_sub(cy));
+            let tx = tx.wrapping_add(w as i32 / 2);
+            let ty = ty.wrapping_add(h as i32 / 2);
+            if tx < 0 || tx >= w as i32 || ty < 0 || ty >= h as i32 {
+                return None;
+            }
+            (tx as u16, ty as u16)
+        } else {
+            let mut px = dx as u16;
+            let mut py = dy as u16;
+            if self.sprite_flip_h(sprite) {
+                px = w.wrapping_sub(1).wrapping_sub(px);
+            }
+            if self.sprite_flip_v(sprite) {
+                py = h.wrapping_sub(1).wrapping_sub(py);
+            }
+            (px, py)
+        };
+
+        let tile_x = px / 8;
+        let tile_y = py / 8;
+        let actual_tile =
+            Self::obj_tile_index(tile_num, tile_x, tile_y, w, is_256, self.is_obj_1d_mapping());
+        let color_index =
+            self.get_obj_tile_pixel(actual_tile, (px % 8) as u8, (py % 8) as u8, palette, is_256);
+        if color_index == 0 {
+            return None;
+        }
+
+        Some(if is_256 {
+            color_index as u16
+        } else {
+            (palette << 4) | color_index as u16
+        })
+    }
+
+    /// Emulate the hardware OBJ priority quirk. When enabled, the lowest-numbered
+    /// opaque sprite claims a pixel even if a later sprite has a better priority,
+    /// so a low-priority sprite behind a BG can mask a high-priority one.
+    /// When disabled, the best priority wins and OAM order only breaks ties.
+    pub fn set_obj_priority_bug(&mut self, enabled: bool) {
+        self.obj_priority_bug = enabled;
+    }
+
+    pub fn obj_priority_bug(&self) -> bool {
+        self.obj_priority_bug
+    }
+
+    /// Limit OBJ rendering to the hardware cycle budget per scanline
+    /// (1210 cycles, or 954 with DISPCNT "H-Blank Interval Free"). Sprites
+    /// that don't fit in the budget are not drawn on that line.
+    pub fn set_sprite_cycle_limit(&mut self, enabled: bool) {
+        self.sprite_cycle_limit = enabled;
+        self.obj_cutoff_cache.set(None);
+    }
+
+    pub fn sprite_cycle_limit(&self) -> bool {
+        self.sprite_cycle_limit
     }
 
-    /// Get sprite priority (2-bit from attr2 bits 10-11)
-    pub fn sprite_priority(&self, sprite: usize) -> u16 {
-        (self.oam_attr(sprite, 2) >> 10) & 0x3
+    /// Start or stop recording per-layer output for [`Ppu::layer_buffers`]
+    pub fn set_layer_capture(&mut self, enabled: bool) {
+        if enabled != self.layer_buffers.is_some() {
+            self.layer_buffers = enabled.then(|| Box::new(LayerBuffers::new()));
+        }
     }
 
-    /// Get sprite palette number (4-bit from attr2 bits 12-15, only for 16-color mode)
-    pub fn sprite_palette(&self, sprite: usize) -> u16 {
-        (self.oam_attr(sprite, 2) >> 12) & 0xF
+    pub fn layer_capture(&self) -> bool {
+        self.layer_buffers.is_some()
     }
 
-    /// Check if sprite uses 256-color mode (attr0 bit 13 = 0 for 16-color, 1 for 256-color)
-    pub fn sprite_is_256color(&self, sprite: usize) -> bool {
-        (self.oam_attr(sprite, 0) & 0x2000) != 0
+    /// Layer output of the most recently drawn lines, if capture is on
+    pub fn layer_buffers(&self) -> Option<&LayerBuffers> {
+        self.layer_buffers.as_deref()
     }
 
-    /// Check if sprite uses horizontal flip (attr1 bit 12, only for non-affine)
-    pub fn sprite_flip_h(&self, sprite: usize) -> bool {
-        (self.oam_attr(sprite, 1) & 0x1000) != 0
+    /// Start or stop collecting [`Ppu::frame_stats`]
+    pub fn set_frame_stats(&mut self, enabled: bool) {
+        if enabled != self.stats_acc.is_some() {
+            self.stats_acc = enabled.then(FrameStats::default);
+            self.frame_stats = FrameStats::default();
+        }
     }
 
-    /// Check if sprite uses vertical flip (attr1 bit 13, only for non-affine)
-    pub fn sprite_flip_v(&self, sprite: usize) -> bool {
-        (self.oam_attr(sprite, 1) & 0x2000) != 0
+    pub fn frame_stats_enabled(&self) -> bool {
+        self.stats_acc.is_some()
     }
 
-    /// Check if sprite is affine (rotation/scaling) mode (attr0 bit 12)
-    /// attr0 bit 12: 0 = non-affine, 1 = affine
-    pub fn sprite_is_affine(&self, sprite: usize) -> bool {
-        (self.oam_attr(sprite, 0) & 0x0100) != 0
+    /// Counts for the last frame that finished while collection was on;
+    /// all zero until then
+    pub fn frame_stats(&self) -> FrameStats {
+        self.frame_stats
     }
 
-    /// Get affine parameter group index (0-31) from attr0 bits 9-13
-    /// Each affine parameter group is 4 halfwords (PA, PB, PC, PD)
-    /// 32 groups share space with 128 sprites in OAM
-    pub fn sprite_rotation_param(&self, sprite: usize) -> usize {
-        let attr1 = self.oam_attr(sprite, 1);
-        ((attr1 >> 9) & 0x1F) as usize
+    /// Add the sprites covering line `y` to the frame in progress
+    pub(crate) fn record_line_sprites(&mut self, y: u16) {
+        if self.stats_acc.is_none() || self.render_dispcnt() & DisplayControl::OBJ.bits() == 0 {
+            return;
+        }
+        let drawn = self.obj_sprites_on_line(y);
+        let (mut rendered, mut dropped) = (0u32, 0u32);
+        for sprite in (0..128).filter(|&s| self.sprite_is_enabled(s)) {
+            let (_, h) = self.sprite_dimensions(sprite);
+            let h = if self.sprite_double_size(sprite) {
+                h << 1
+            } else {
+                h
+            };
+            let dy = (y as i32).wrapping_sub(Self::sprite_y_from_attr(self.oam_attr(sprite, 0)));
+            if dy < 0 || dy >= h as i32 {
+                continue;
+            }
+            if sprite < drawn {
+                rendered = rendered.wrapping_add(1);
+            } else {
+                dropped = dropped.wrapping_add(1);
+            }
+        }
+        if let Some(stats) = &mut self.stats_acc {
+            stats.sprites_rendered = stats.sprites_rendered.wrapping_add(rendered);
+            stats.sprites_dropped = stats.sprites_dropped.wrapping_add(dropped);
+        }
     }
 
-    /// Get affine rotation parameter PA (4 halfwords per group, at OAM offset group*16+3)
-    pub fn sprite_affine_pa(&self, group: usize) -> i16 {
-        let offset = group * 16 + 6;
-        if offset + 1 < self.oam.len() {
-            i16::from_le_bytes([self.oam[offset], self.oam[offset + 1]])
-        } else {
-            0x100
+    /// Add one composited pixel to the frame in progress
+    pub(crate) fn record_pixel(&mut self, pixel: &debug::PixelBreakdown) {
+        if let Some(stats) = &mut self.stats_acc {
+            if let Some(count) = stats.layer_pixels.get_mut(pixel.layer as usize) {
+                *count = count.wrapping_add(1);
+            }
+            if pixel.blend != debug::BlendEffect::None {
+                stats.blends = stats.blends.wrapping_add(1);
+            }
         }
     }
 
-    /// Get affine rotation parameter PB
-    pub fn sprite_affine_pb(&self, group: usize) -> i16 {
-        let offset = group * 16 + 14;
-        if offset + 1 < self.oam.len() {
-            i16::from_le_bytes([self.oam[offset], self.oam[offset + 1]])
+    /// Number of OAM entries evaluated on line `y` before the OBJ cycle
+    /// budget runs out. A regular sprite costs its width in cycles, an
+    /// affine one 10 + 2 * its (possibly doubled) width.
+    pub fn obj_line_cutoff(oam: &[u8; 0x400], dispcnt: u16, y: u16) -> usize {
+        let budget = if dispcnt & DisplayControl::HBLANK_FREE.bits() != 0 {
+            954
         } else {
-            0
+            1210
+        };
+        let mut used = 0u32;
+        for sprite in 0..128 {
+            let attr0 = Self::oam_attr_from_data(oam, sprite, 0);
+            let affine = attr0 & 0x0100 != 0;
+            let double = attr0 & 0x0200 != 0;
+            if !affine && double {
+                continue;
+            }
+            let attr1 = Self::oam_attr_from_data(oam, sprite, 1);
+            let (w, h) =
+                Self::sprite_dimensions_from_shape_size((attr0 >> 14) & 0x3, (attr1 >> 14) & 0x3);
+            let (w, h) = if affine && double {
+                (w << 1, h << 1)
+            } else {
+                (w, h)
+            };
+            let dy = (y as i32).wrapping_sub(Self::sprite_y_from_attr(attr0));
+            if dy < 0 || dy >= h as i32 {
+                continue;
+            }
+            let cost = if affine {
+                ((w as u32) << 1).wrapping_add(10)
+            } else {
+                w as u32
+            };
+            used = used.wrapping_add(cost);
+            if used > budget {
+                return sprite;
+            }
         }
+        128
     }
 
-    /// Get affine rotation parameter PC
-    pub fn sprite_affine_pc(&self, group: usize) -> i16 {
-        let offset = group * 16 + 22;
-        if offset + 1 < self.oam.len() {
-            i16::from_le_bytes([self.oam[offset], self.oam[offset + 1]])
-        } else {
-            0
+    /// OAM entries drawn on line `y`, honoring the sprite cycle limit setting
+    fn obj_sprites_on_line(&self, y: u16) -> usize {
+        if !self.sprite_cycle_limit {
+            return 128;
+        }
+        if let Some((line, count)) = self.obj_cutoff_cache.get() {
+            if line == y {
+                return count as usize;
+            }
         }
+        let count = Self::obj_line_cutoff(&self.oam, self.dispcnt.bits(), y);
+        self.obj_cutoff_cache.set(Some((y, count as u8)));
+        count
     }
 
-    /// Get affine rotation parameter PD
-    pub fn sprite_affine_pd(&self, group: usize) -> i16 {
-        let offset = group * 16 + 30;
-        if offset + 1 < self.oam.len() {
-            i16::from_le_bytes([self.oam[offset], self.oam[offset + 1]])
-        } else {
-            0x100
+    /// Select the OBJ pixel at (x, y) among non-window sprites, honoring
+    /// the priority quirk setting. Returns (sprite, palette index).
+    pub fn top_sprite_at(&self, x: u16, y: u16) -> Option<(usize, u16)> {
+        let mut best: Option<(usize, u16, u16)> = None;
+        for sprite in 0..self.obj_sprites_on_line(y) {
+            if !self.sprite_is_enabled(sprite) || self.sprite_is_window(sprite) {
+                continue;
+            }
+            let Some(index) = self.sprite_pixel_index(sprite, x, y) else {
+                continue;
+            };
+            if self.obj_priority_bug {
+                return Some((sprite, index));
+            }
+            let prio = self.sprite_priority(sprite);
+            if best.is_none_or(|(_, _, p)| prio < p) {
+                best = Some((sprite, index, prio));
+                if prio == 0 {
+                    break;
+                }
+            }
         }
+        best.map(|(sprite, index, _)| (sprite, index))
     }
 
-    /// Check if sprite is a sprite-type window mask (attr0 bits 14-15 == 10)
-    pub fn sprite_is_window(&self, sprite: usize) -> bool {
-        let mode = (self.oam_attr(sprite, 0) >> 10) & 0x3;
-        mode == 0b10
+    /// Check if any OBJ window sprite has an opaque pixel at (x, y)
+    pub fn obj_window_contains(&self, x: u16, y: u16) -> bool {
+        (0..self.obj_sprites_on_line(y)).any(|sprite| {
+            self.sprite_is_window(sprite) && self.sprite_pixel_index(sprite, x, y).is_some()
+        })
     }
 
     /// Apply OBJ mosaic to pixel coordinates
@@ -939,8 +1906,7 @@ impl Ppu {
         if mv == 0 {
             return sprite_dy;
         }
-        let block_h = mv + 1;
-        let base_y = (scanline / block_h) * block_h;
+        let base_y = Self::mosaic_block_start(scanline, mv.wrapping_add(1));
         let snapped_dy = base_y % 256;
         snapped_dy
     }
@@ -951,8 +1917,36 @@ impl Ppu {
         if mh == 0 {
             return sprite_dx;
         }
-        let block_w = mh + 1;
-        (sprite_dx / block_w) * block_w
+        Self::mosaic_block_start(sprite_dx, mh.wrapping_add(1))
+    }
+
+    /// Tile number (in 32-byte units) of the 8x8 cell at (tile_x, tile_y) inside a sprite.
+    /// 1D mapping stores a sprite's rows back to back; 2D mapping treats OBJ VRAM
+    /// as a 32x32 tile sheet. 256-color cells take two 32-byte units.
+    pub fn obj_tile_index(
+        base_tile: u16,
+        tile_x: u16,
+        tile_y: u16,
+        width: u16,
+        is_256color: bool,
+        one_dimensional: bool,
+    ) -> u16 {
+        let cell = if is_256color { 2 } else { 1 };
+        let row_stride = if one_dimensional {
+            (width / 8).wrapping_mul(cell)
+        } else {
+            32
+        };
+        let cell_offset = tile_y
+            .wrapping_mul(row_stride)
+            .wrapping_add(tile_x.wrapping_mul(cell));
+        base_tile.wrapping_add(cell_offset) & 0x3FF
+    }
+
+    /// In bitmap modes (3-5) the frame buffer covers the lower 16KB of OBJ VRAM,
+    /// so sprites whose base tile is below 512 are not displayed.
+    pub fn obj_tile_usable(base_tile: u16, mode: u8) -> bool {
+        mode < 3 || base_tile >= 512
     }
 
     /// Get a pixel from an OBJ tile
@@ -969,30 +1963,23 @@ impl Ppu {
         _palette_num: u16,
         is_256color: bool,
     ) -> u8 {
-        let obj_base = 0x10000; // OBJ tiles start at VRAM offset 0x10000
+        let obj_base: usize = 0x10000; // OBJ tiles start at VRAM offset 0x10000
         if is_256color {
             // 8bpp: tile_num already accounts for 2x size via caller's *2 multiplier
-            let tile_offset = obj_base + (tile_num as usize * 32);
-            let pixel_offset = tile_offset + (y as usize * 8) + (x as usize);
-            if pixel_offset < self.vram.len() {
-                self.vram[pixel_offset]
-            } else {
-                0
-            }
+            let tile_offset = obj_base.wrapping_add((tile_num as usize) << 5);
+            let pixel_offset = tile_offset
+                .wrapping_add((y as usize) << 3)
+                .wrapping_add(x as usize);
+            self.vram.byte_at(pixel_offset)
         } else {
             // 4bpp: each tile is 32 bytes
-            let tile_offset = obj_base + (tile_num as usize * 32);
-            let row_offset = tile_offset + (y as usize * 4);
-            let byte_offset = row_offset + (x as usize / 2);
-            if byte_offset < self.vram.len() {
-                let byte = self.vram[byte_offset];
-                if x % 2 == 0 {
-                    byte & 0x0F
-                } else {
-                    (byte >> 4) & 0x0F
-                }
+            let tile_offset = obj_base.wrapping_add((tile_num as usize) << 5);
+            let row_offset = tile_offset.wrapping_add((y as usize) << 2);
+            let byte = self.vram.byte_at(row_offset.wrapping_add(x as usize / 2));
+            if x % 2 == 0 {
+                byte & 0x0F
             } else {
-                0
+                (byte >> 4) & 0x0F
             }
         }
     }
@@ -1001,16 +1988,13 @@ impl Ppu {
 
     /// Read a 16-bit value from VRAM at the given offset
     fn read_vram_half(&self, offset: usize) -> u16 {
-        if offset + 1 < self.vram.len() {
-            u16::from_le_bytes([self.vram[offset], self.vram[offset + 1]])
-        } else {
-            0
-        }
+        self.vram.half_at(offset)
     }
 
     /// Get palette color (RGB555) for the given palette index
     /// pal_num: 0 for BG palette, 1 for OBJ palette
     /// index: color index (0-255)
+    #[doc(hidden)]
     pub fn get_palette_color(&self, _pal_num: usize, _index: u16) -> u16 {
         // Palette is stored in Memory, not PPU
         // For now, we'll need to get this from Memory
@@ -1036,25 +2020,24 @@ impl Ppu {
         flip_v: bool,
     ) -> u8 {
         // Each 4bpp tile is 32 bytes (8x8 pixels, 4 bits per pixel)
-        let tile_offset = tile_base + (tile_num as usize * 32);
+        let tile_offset = tile_base.wrapping_add((tile_num as usize) << 5);
 
-        // Handle flipping
-        let x = if flip_h { 7 - x } else { x };
-        let y = if flip_v { 7 - y } else { y };
+        // Handle flipping; coordinates outside the tile wrap instead of underflowing
+        let x = if flip_h { (x & 7) ^ 7 } else { x & 7 };
+        let y = if flip_v { (y & 7) ^ 7 } else { y & 7 };
 
         // Each row is 4 bytes (8 pixels at 4 bits each)
-        let row_offset = tile_offset + (y as usize * 4);
+        let row_offset = tile_offset.wrapping_add((y as usize) << 2);
 
         // Each pixel is 4 bits (nibble)
-        let pixel_nibble = if x % 2 == 0 {
+        let byte = self.vram.byte_at(row_offset.wrapping_add(x as usize / 2));
+        if x % 2 == 0 {
             // Low nibble
-            (self.vram[row_offset + (x as usize / 2)]) & 0x0F
+            byte & 0x0F
         } else {
             // High nibble
-            (self.vram[row_offset + (x as usize / 2)]) >> 4
-        };
-
-        pixel_nibble
+            byte >> 4
+        }
     }
 
     /// Get tile pixel for 8bpp tile (mode 4 bitmap, or mode 2/4 BG with 256-color)
@@ -1073,16 +2056,18 @@ impl Ppu {
         flip_v: bool,
     ) -> u8 {
         // Each 8bpp tile is 64 bytes (8x8 pixels, 8 bits per pixel)
-        let tile_offset = tile_base + (tile_num as usize * 64);
+        let tile_offset = tile_base.wrapping_add((tile_num as usize) << 6);
 
-        // Handle flipping
-        let x = if flip_h { 7 - x } else { x };
-        let y = if flip_v { 7 - y } else { y };
+        // Handle flipping; coordinates outside the tile wrap instead of underflowing
+        let x = if flip_h { (x & 7) ^ 7 } else { x & 7 };
+        let y = if flip_v { (y & 7) ^ 7 } else { y & 7 };
 
         // Each row is 8 bytes
-        let pixel_offset = tile_offset + (y as usize * 8) + (x as usize);
+        let pixel_offset = tile_offset
+            .wrapping_add((y as usize) << 3)
+            .wrapping_add(x as usize);
 
-        self.vram[pixel_offset]
+        self.vram.byte_at(pixel_offset)
     }
 
     /// Get screen entry (tile map entry) for text backgrounds
@@ -1098,15 +2083,20 @@ impl Ppu {
         width: u16,
         height: u16,
     ) -> u16 {
-        let screen_x = x % width;
-        let screen_y = y % height;
+        let screen_x = x.checked_rem(width).unwrap_or(0);
+        let screen_y = y.checked_rem(height).unwrap_or(0);
         let num_blocks_x = width / 32;
         let block_x = (screen_x / 32) as usize;
         let block_y = (screen_y / 32) as usize;
         let local_x = (screen_x % 32) as usize;
         let local_y = (screen_y % 32) as usize;
-        let block_num = block_y * (num_blocks_x as usize) + block_x;
-        let entry_offset = screen_base + block_num * 0x800 + (local_y * 32 + local_x) * 2;
+        let block_num = block_y
+            .wrapping_mul(num_blocks_x as usize)
+            .wrapping_add(block_x);
+        let entry = (local_y << 5 | local_x) << 1;
+        let entry_offset = screen_base
+            .wrapping_add(block_num << 11)
+            .wrapping_add(entry);
 
         self.read_vram_half(entry_offset)
     }
@@ -1124,12 +2114,12 @@ impl Ppu {
 
     /// Step the PPU forward by given number of cycles
     pub fn step(&mut self, cycles: u32) {
-        self.hcounter += cycles;
+        self.hcounter = self.hcounter.wrapping_add(cycles);
 
         // Check for HBlank
         if self.hcounter >= 1232 {
-            self.hcounter -= 1232;
-            self.vcount += 1;
+            self.hcounter = self.hcounter.wrapping_sub(1232);
+            self.vcount = self.vcount.wrapping_add(1);
 
             // Check for VBlank
             if self.vcount >= 228 {
@@ -1145,13 +2135,13 @@ impl Ppu {
         let old_vcount = self.vcount;
         let old_hblank = self.is_in_hblank();
 
-        self.hcounter += cycles;
+        self.hcounter = self.hcounter.wrapping_add(cycles);
 
         let mut vblank_start = false;
 
         if self.hcounter >= 1232 {
-            self.hcounter -= 1232;
-            self.vcount += 1;
+            self.hcounter = self.hcounter.wrapping_sub(1232);
+            self.vcount = self.vcount.wrapping_add(1);
 
             // Check for VBlank
             if self.vcount >= 228 {
@@ -1161,17 +2151,71 @@ impl Ppu {
             // VBlank starts when we transition from scanline 159 to 160
             if old_vcount == 159 && self.vcount == 160 {
                 vblank_start = true;
+                // Internal reference points reload from BGxX/BGxY for the next frame
+                self.bg_ref_internal = self.bg_ref;
+                if let Some(stats) = &mut self.stats_acc {
+                    self.frame_stats = std::mem::take(stats);
+                }
             }
         }
 
         // HBlank starts when hcounter crosses 960 (visible pixels end)
         let new_hblank = self.is_in_hblank();
         let hblank_start = !old_hblank && new_hblank;
+        if hblank_start {
+            self.latch_bg_reference_line(self.vcount);
+        }
 
         (vblank_start, hblank_start)
     }
 }
 
+impl Ppu {
+    /// Register state the current line is drawn with; the affine reference
+    /// point is kept per line already
+    pub(crate) fn line_registers(&self) -> LineRegisters {
+        LineRegisters {
+            dispcnt: self.dispcnt,
+            bgcnt: self.bgcnt,
+            bg_hofs: self.bg_hofs,
+            bg_vofs: self.bg_vofs,
+            bg_affine: self.bg_affine,
+            bg_mosaic: self.bg_mosaic,
+            obj_mosaic: self.obj_mosaic,
+            windows: [
+                self.win0_h,
+                self.win0_v,
+                self.win1_h,
+                self.win1_v,
+                self.winin,
+                self.winout,
+            ],
+            blend: [self.bldcnt, self.bldalpha, self.bldy],
+        }
+    }
+
+    /// Put back registers saved by [`Ppu::line_registers`]
+    pub(crate) fn set_line_registers(&mut self, regs: &LineRegisters) {
+        self.dispcnt = regs.dispcnt;
+        self.bgcnt = regs.bgcnt;
+        self.bg_hofs = regs.bg_hofs;
+        self.bg_vofs = regs.bg_vofs;
+        self.bg_affine = regs.bg_affine;
+        self.bg_mosaic = regs.bg_mosaic;
+        self.obj_mosaic = regs.obj_mosaic;
+        [
+            self.win0_h,
+            self.win0_v,
+            self.win1_h,
+            self.win1_v,
+            self.winin,
+            self.winout,
+        ] = regs.windows;
+        [self.bldcnt, self.bldalpha, self.bldy] = regs.blend;
+        self.obj_cutoff_cache.set(None);
+    }
+}
+
 /// Snapshot of PPU state for parallel rendering
 /// Contains all data needed to render a frame without accessing the original PPU
 #[derive(Clone)]
@@ -1188,6 +2232,9 @@ pub struct PpuSnapshot {
     pub bg_mosaic: u16,
     pub obj_mosaic: u16,
     pub vcount: u16,
+    pub obj_priority_bug: bool,
+    /// OAM entries drawn on each line (128 unless the sprite cycle limit is on)
+    pub obj_line_sprites: [u8; 160],
 }
 
 impl Ppu {
@@ -1196,7 +2243,7 @@ impl Ppu {
         PpuSnapshot {
             vram: self.vram.clone(),
             oam: self.oam.clone(),
-            dispcnt: self.dispcnt.bits(),
+            dispcnt: self.render_dispcnt(),
             bgcnt: self.bgcnt,
             bg_hofs: self.bg_hofs,
             bg_vofs: self.bg_vofs,
@@ -1206,6 +2253,8 @@ impl Ppu {
             bg_mosaic: self.bg_mosaic,
             obj_mosaic: self.obj_mosaic,
             vcount: self.vcount,
+            obj_priority_bug: self.obj_priority_bug,
+            obj_line_sprites: std::array::from_fn(|y| self.obj_sprites_on_line(y as u16) as u8),
         }
     }
 
@@ -1221,67 +2270,60 @@ impl Ppu {
         }
 
         let mode = (snapshot.dispcnt & 0x7) as u8;
-        let width = 240usize;
         let y = scanline as usize;
 
         // Collect 15-bit colors first, then batch convert to 32-bit ARGB
         let mut colors_15bit = [0u16; 240];
 
-        for x in 0..width {
-            colors_15bit[x] = match mode {
+        for (x, color) in colors_15bit.iter_mut().enumerate() {
+            let pixel = y.wrapping_mul(240).wrapping_add(x);
+            *color = match mode {
+                // BG2 disabled in a bitmap mode shows only the backdrop
+                3..=5 if snapshot.dispcnt & DisplayControl::BG2.bits() == 0 => palette.half_at(0),
                 0 | 1 | 2 => {
                     // Tile modes - render all BG layers and composite
                     Self::render_tile_pixel_composited(snapshot, x as u16, y as u16, palette)
                 }
                 3 => {
                     // Mode 3: 16-bit bitmap (240x160)
-                    let offset = (y * 240 + x) * 2;
-                    if offset + 1 < snapshot.vram.len() {
-                        u16::from_le_bytes([snapshot.vram[offset], snapshot.vram[offset + 1]])
-                    } else {
-                        0
-                    }
+                    snapshot.vram.half_at(pixel << 1)
                 }
                 4 => {
                     // Mode 4: 8-bit paletted bitmap (240x160, double buffered)
-                    let page = if snapshot.dispcnt & (1 << 4) != 0 {
+                    let page: usize = if snapshot.dispcnt & (1 << 4) != 0 {
                         0xA000
                     } else {
                         0
                     };
-                    let offset = page + y * 240 + x;
-                    if offset < snapshot.vram.len() {
-                        let idx = snapshot.vram[offset] as usize;
-                        let pal_offset = idx * 2;
-                        if pal_offset + 1 < palette.len() {
-                            u16::from_le_bytes([palette[pal_offset], palette[pal_offset + 1]])
-                        } else {
-                            0
-                        }
-                    } else {
-                        0
-                    }
+                    let idx = snapshot.vram.byte_at(page.wrapping_add(pixel)) as usize;
+                    palette.half_at(idx << 1)
                 }
                 5 => {
                     // Mode 5: 16-bit bitmap (160x128, double buffered)
-                    let page = if snapshot.dispcnt & (1 << 4) != 0 {
+                    let page: usize = if snapshot.dispcnt & (1 << 4) != 0 {
                         0xA000
                     } else {
                         0
                     };
                     if x < 160 && y < 128 {
-                        let offset = page + (y * 160 + x) * 2;
-                        if offset + 1 < snapshot.vram.len() {
-                            u16::from_le_bytes([snapshot.vram[offset], snapshot.vram[offset + 1]])
-                        } else {
-                            0
-                        }
+                        let offset = page.wrapping_add(y.wrapping_mul(160).wrapping_add(x) << 1);
+                        snapshot.vram.half_at(offset)
                     } else {
                         0
                     }
                 }
                 _ => 0,
             };
+
+            // Bitmap modes: sprites draw over the BG2 frame buffer
+            if (3..=5).contains(&mode) && snapshot.dispcnt & (1 << 12) != 0 {
+                let bg2_priority = snapshot.bgcnt[2] & 0x3;
+                let sprite_color =
+                    Self::render_sprite_pixel(snapshot, x as u16, y as u16, palette, bg2_priority);
+                if sprite_color != 0 {
+                    *color = sprite_color;
+                }
+            }
         }
 
         // Batch convert 15-bit to 32-bit ARGB
@@ -1297,24 +2339,20 @@ impl Ppu {
     ) -> u16 {
         // Collect enabled backgrounds with their priorities
         let mut bg_layers: [(u8, u16); 4] = [(0, 0); 4]; // (bg_idx, priority)
-        let mut layer_count = 0;
-
-        for bg in 0..4 {
-            if snapshot.dispcnt & (1 << (8 + bg)) != 0 {
-                let priority = snapshot.bgcnt[bg] & 0x3;
-                bg_layers[layer_count] = (bg as u8, priority);
-                layer_count += 1;
-            }
+        for (bg, (layer, &bgcnt)) in bg_layers.iter_mut().zip(&snapshot.bgcnt).enumerate() {
+            // Disabled layers get priority 4 and sort after the enabled ones
+            let enabled = snapshot.dispcnt & (0x100 << bg) != 0;
+            *layer = (bg as u8, if enabled { bgcnt & 0x3 } else { 4 });
         }
 
         // Sort by priority (lower number = higher priority)
-        bg_layers[..layer_count].sort_by_key(|&(_, pri)| pri);
+        bg_layers.sort_by_key(|&(_, pri)| pri);
 
         // Render each layer and composite
         let mut bg_color = 0u16;
         let mut bg_priority = 4u16; // Start with lowest priority
 
-        for &(bg_idx, priority) in &bg_layers[..layer_count] {
+        for &(bg_idx, priority) in bg_layers.iter().take_while(|&&(_, pri)| pri < 4) {
             let color = Self::render_bg_pixel(snapshot, bg_idx as usize, x, y, palette);
             if color != 0 {
                 bg_color = color;
@@ -1336,11 +2374,7 @@ impl Ppu {
             bg_color
         } else {
             // All layers transparent, return backdrop color (palette[0])
-            if palette.len() >= 2 {
-                u16::from_le_bytes([palette[0], palette[1]])
-            } else {
-                0
-            }
+            palette.half_at(0)
         }
     }
 
@@ -1352,9 +2386,11 @@ impl Ppu {
         palette: &[u8; 0x400],
         max_priority: u16,
     ) -> u16 {
-        let obj_tile_base = 0x10000;
+        let obj_tile_base: usize = 0x10000;
+        let mut best: Option<(u16, u16)> = None; // (priority, color)
+        let sprites = snapshot.obj_line_sprites.get(y as usize).map_or(128, |&n| n as u16);
 
-        for sprite in 0..128u16 {
+        for sprite in 0..sprites {
             let attr0 = Self::oam_attr_from_data(&snapshot.oam, sprite as usize, 0);
             let obj_mode = (attr0 >> 10) & 0x3;
             if obj_mode == 0b10 {
@@ -1369,7 +2405,7 @@ impl Ppu {
             let attr2 = Self::oam_attr_from_data(&snapshot.oam, sprite as usize, 2);
             let sprite_priority = (attr2 >> 10) & 0x3;
 
-            if sprite_priority > max_priority {
+            if sprite_priority > max_priority && !snapshot.obj_priority_bug {
                 continue;
             }
 
@@ -1380,30 +2416,50 @@ impl Ppu {
             let shape = (attr0 >> 14) & 0x3;
             let size = (attr1 >> 14) & 0x3;
             let (width, height) = Self::sprite_dimensions_from_shape_size(shape, size);
+            let (render_w, render_h) = if rot_scale && attr0 & 0x0200 != 0 {
+                ((width as i32) << 1, (height as i32) << 1)
+            } else {
+                (width as i32, height as i32)
+            };
 
-            let dx = x as i32 - sx;
-            let dy = y as i32 - sy;
+            let dx = (x as i32).wrapping_sub(sx);
+            let dy = (y as i32).wrapping_sub(sy);
 
-            if dx < 0 || dx >= width as i32 || dy < 0 || dy >= height as i32 {
+            if dx < 0 || dx >= render_w || dy < 0 || dy >= render_h {
                 continue;
             }
 
             let tile_num = attr2 & 0x3FF;
+            if !Self::obj_tile_usable(tile_num, (snapshot.dispcnt & 0x7) as u8) {
+                continue;
+            }
             let palette_num = (attr2 >> 12) & 0xF;
             let is_256color = (attr0 & 0x2000) != 0;
 
-            let flip_h = (attr1 & 0x1000) != 0;
-            let flip_v = (attr1 & 0x2000) != 0;
-
-            let mut px = dx as u16;
-            let mut py = dy as u16;
-
-            if flip_h {
-                px = width - 1 - px;
-            }
-            if flip_v {
-                py = height - 1 - py;
-            }
+            let (px, py) = if rot_scale {
+                let group = ((attr1 >> 9) & 0x1F) as usize;
+                let params = AffineParams::read(&snapshot.oam[..], group);
+                let (tx, ty) =
+                    params.transform(dx.wrapping_sub(render_w / 2), dy.wrapping_sub(render_h / 2));
+                let (tx, ty) = (
+                    tx.wrapping_add(width as i32 / 2),
+                    ty.wrapping_add(height as i32 / 2),
+                );
+                if tx < 0 || tx >= width as i32 || ty < 0 || ty >= height as i32 {
+                    continue;
+                }
+                (tx as u16, ty as u16)
+            } else {
+                let mut px = dx as u16;
+                let mut py = dy as u16;
+                if attr1 & 0x1000 != 0 {
+                    px = width.wrapping_sub(1).wrapping_sub(px);
+                }
+                if attr1 & 0x2000 != 0 {
+                    py = height.wrapping_sub(1).wrapping_sub(py);
+                }
+                (px, py)
+            };
 
             // Calculate tile number based on position
             let tile_x = px / 8;
@@ -1411,82 +2467,61 @@ impl Ppu {
             let pixel_x = (px % 8) as u8;
             let pixel_y = (py % 8) as u8;
 
-            // For 256-color sprites, tiles are laid out differently
-            let actual_tile = if is_256color {
-                // 256-color: tiles are 8x8 but stored linearly
-                let tiles_per_row = width / 8;
-                tile_num + tile_y * tiles_per_row + tile_x
-            } else {
-                // 16-color: tiles are stored in 2D blocks
-                tile_num + tile_y * (width / 8) + tile_x
-            };
+            let one_dimensional = snapshot.dispcnt & DisplayControl::OBJ_VRAM_1D.bits() != 0;
+            let actual_tile =
+                Self::obj_tile_index(tile_num, tile_x, tile_y, width, is_256color, one_dimensional);
 
             // Get pixel color
             let color_idx = if is_256color {
                 // 8bpp
-                let tile_offset = obj_tile_base + (actual_tile as usize * 64);
-                let pixel_offset = tile_offset + (pixel_y as usize * 8) + (pixel_x as usize);
-                if pixel_offset < snapshot.vram.len() {
-                    snapshot.vram[pixel_offset] as usize
-                } else {
-                    0
-                }
+                let tile_offset = obj_tile_base.wrapping_add((actual_tile as usize) << 5);
+                let pixel_offset = tile_offset | (pixel_y as usize) << 3 | pixel_x as usize;
+                snapshot.vram.byte_at(pixel_offset) as usize
             } else {
                 // 4bpp
-                let tile_offset = obj_tile_base + (actual_tile as usize * 32);
-                let row_offset = tile_offset + (pixel_y as usize * 4);
+                let tile_offset = obj_tile_base.wrapping_add((actual_tile as usize) << 5);
+                let row_offset = tile_offset | (pixel_y as usize) << 2;
+                let byte = snapshot.vram.byte_at(row_offset | (pixel_x as usize / 2));
                 let nibble = if pixel_x % 2 == 0 {
-                    if row_offset + (pixel_x as usize / 2) < snapshot.vram.len() {
-                        snapshot.vram[row_offset + (pixel_x as usize / 2)] & 0x0F
-                    } else {
-                        0
-                    }
+                    byte & 0x0F
                 } else {
-                    if row_offset + (pixel_x as usize / 2) < snapshot.vram.len() {
-                        snapshot.vram[row_offset + (pixel_x as usize / 2)] >> 4
-                    } else {
-                        0
-                    }
+                    byte >> 4
                 };
                 if nibble == 0 {
                     continue; // Transparent
                 }
-                (palette_num as usize * 16 + nibble as usize) & 0xFF
+                ((palette_num as usize) << 4 | nibble as usize) & 0xFF
             };
 
             // Look up color in OBJ palette (starts at 0x200 in palette RAM)
-            let pal_offset = if is_256color {
-                0x200 + color_idx * 2
-            } else {
-                (0x200 + color_idx * 2) & 0x3FF
-            };
+            let pal_offset = 0x200 | color_idx << 1;
 
-            if pal_offset + 1 < palette.len() {
-                let color = u16::from_le_bytes([palette[pal_offset], palette[pal_offset + 1]]);
-                if color != 0 {
-                    return color;
+            let color = palette.half_at(pal_offset);
+            if color != 0 {
+                if snapshot.obj_priority_bug {
+                    // First opaque sprite claims the pixel; BG may still cover it
+                    return if sprite_priority > max_priority { 0 } else { color };
+                }
+                if best.is_none_or(|(p, _)| sprite_priority < p) {
+                    best = Some((sprite_priority, color));
                 }
             }
         }
 
-        0
+        best.map_or(0, |(_, color)| color)
     }
 
     /// Helper to read OAM attribute
     fn oam_attr_from_data(oam: &[u8; 0x400], sprite: usize, attr: usize) -> u16 {
-        let offset = sprite * 8 + attr * 2;
-        if offset + 1 < oam.len() {
-            u16::from_le_bytes([oam[offset], oam[offset + 1]])
-        } else {
-            0
-        }
+        let offset = (sprite << 3) | (attr << 1);
+        oam.half_at(offset)
     }
 
     /// Get sprite Y position from attr0
     fn sprite_y_from_attr(attr0: u16) -> i32 {
         let y = (attr0 & 0xFF) as i32;
         if y >= 160 {
-            y - 256
+            y.wrapping_sub(256)
         } else {
             y
         }
@@ -1496,7 +2531,7 @@ impl Ppu {
     fn sprite_x_from_attr(attr1: u16) -> i32 {
         let x = (attr1 & 0x1FF) as i32;
         if x >= 240 {
-            x - 512
+            x.wrapping_sub(512)
         } else {
             x
         }
@@ -1510,9 +2545,10 @@ impl Ppu {
             [[8, 16], [8, 32], [16, 32], [32, 64]], // shape 2 (vertical)
             [[8, 8], [16, 16], [32, 32], [64, 64]], // shape 3 (prohibited)
         ];
-        let w = DIMENSIONS[shape as usize][size as usize][0];
-        let h = DIMENSIONS[shape as usize][size as usize][1];
-        (w, h)
+        let dimensions = DIMENSIONS
+            .get(shape as usize)
+            .and_then(|sizes| sizes.get(size as usize));
+        dimensions.map_or((8, 8), |&[w, h]| (w, h))
     }
 
     /// Render a pixel from a specific background layer
@@ -1523,13 +2559,13 @@ impl Ppu {
         y: u16,
         palette: &[u8; 0x400],
     ) -> u16 {
-        let bgcnt = snapshot.bgcnt[bg_idx];
-        let hofs = snapshot.bg_hofs[bg_idx];
-        let vofs = snapshot.bg_vofs[bg_idx];
+        let bgcnt = snapshot.bgcnt.get(bg_idx).copied().unwrap_or(0);
+        let hofs = snapshot.bg_hofs.get(bg_idx).copied().unwrap_or(0);
+        let vofs = snapshot.bg_vofs.get(bg_idx).copied().unwrap_or(0);
 
         // Calculate tile map dimensions based on BG size
         let bg_size = bgcnt & 0x3;
-        let (map_width, map_height) = match bg_size {
+        let (map_width, map_height): (u16, u16) = match bg_size {
             0 => (256, 256), // 32x32 tiles
             1 => (512, 256), // 64x32 tiles
             2 => (256, 512), // 32x64 tiles
@@ -1538,8 +2574,8 @@ impl Ppu {
         };
 
         // Calculate pixel position with scrolling
-        let px = (x.wrapping_add(hofs)) % map_width;
-        let py = (y.wrapping_add(vofs)) % map_height;
+        let px = x.wrapping_add(hofs) & map_width.wrapping_sub(1);
+        let py = y.wrapping_add(vofs) & map_height.wrapping_sub(1);
 
         // Calculate tile position
         let tile_x = px / 8;
@@ -1548,8 +2584,8 @@ impl Ppu {
         let pixel_in_tile_y = (py % 8) as u8;
 
         // Get tile base and map base from BGCNT
-        let char_base = ((bgcnt >> 2) & 0x3) as usize * 0x4000;
-        let screen_base = ((bgcnt >> 8) & 0x1F) as usize * 0x800;
+        let char_base = (((bgcnt >> 2) & 0x3) as usize) << 14;
+        let screen_base = (((bgcnt >> 8) & 0x1F) as usize) << 11;
 
         let tiles_per_row = map_width / 8;
         let num_blocks_x = (tiles_per_row / 32) as usize;
@@ -1557,14 +2593,13 @@ impl Ppu {
         let block_y = (tile_y / 32) as usize;
         let local_x = (tile_x % 32) as usize;
         let local_y = (tile_y % 32) as usize;
-        let block_num = block_y * num_blocks_x + block_x;
-        let entry_offset = screen_base + block_num * 0x800 + (local_y * 32 + local_x) * 2;
+        let block_num = block_y.wrapping_mul(num_blocks_x).wrapping_add(block_x);
+        let entry = (local_y << 5 | local_x) << 1;
+        let entry_offset = screen_base
+            .wrapping_add(block_num << 11)
+            .wrapping_add(entry);
 
-        if entry_offset + 1 >= snapshot.vram.len() {
-            return 0;
-        }
-        let entry =
-            u16::from_le_bytes([snapshot.vram[entry_offset], snapshot.vram[entry_offset + 1]]);
+        let entry = snapshot.vram.half_at(entry_offset);
 
         let tile_num = entry & 0x3FF;
         let flip_h = (entry & 0x400) != 0;
@@ -1575,63 +2610,48 @@ impl Ppu {
 
         let color_idx = if is_8bpp {
             // 8bpp: 256 colors, no palette selection
-            let tile_offset = char_base + (tile_num as usize * 64);
+            let tile_offset = char_base.wrapping_add((tile_num as usize) << 6);
             let fx = if flip_h {
-                7 - pixel_in_tile_x
+                pixel_in_tile_x ^ 7
             } else {
                 pixel_in_tile_x
             };
             let fy = if flip_v {
-                7 - pixel_in_tile_y
+                pixel_in_tile_y ^ 7
             } else {
                 pixel_in_tile_y
             };
-            let pixel_offset = tile_offset + (fy as usize * 8) + (fx as usize);
-            if pixel_offset < snapshot.vram.len() {
-                snapshot.vram[pixel_offset] as usize
-            } else {
-                0
-            }
+            let pixel_offset = tile_offset | (fy as usize) << 3 | fx as usize;
+            snapshot.vram.byte_at(pixel_offset) as usize
         } else {
             // 4bpp: 16 colors per palette
-            let tile_offset = char_base + (tile_num as usize * 32);
+            let tile_offset = char_base.wrapping_add((tile_num as usize) << 5);
             let fx = if flip_h {
-                7 - pixel_in_tile_x
+                pixel_in_tile_x ^ 7
             } else {
                 pixel_in_tile_x
             };
             let fy = if flip_v {
-                7 - pixel_in_tile_y
+                pixel_in_tile_y ^ 7
             } else {
                 pixel_in_tile_y
             };
-            let row_offset = tile_offset + (fy as usize * 4);
+            let row_offset = tile_offset | (fy as usize) << 2;
+            let byte = snapshot.vram.byte_at(row_offset | (fx as usize / 2));
             let nibble = if fx % 2 == 0 {
-                if row_offset + (fx as usize / 2) < snapshot.vram.len() {
-                    snapshot.vram[row_offset + (fx as usize / 2)] & 0x0F
-                } else {
-                    0
-                }
+                byte & 0x0F
             } else {
-                if row_offset + (fx as usize / 2) < snapshot.vram.len() {
-                    snapshot.vram[row_offset + (fx as usize / 2)] >> 4
-                } else {
-                    0
-                }
+                byte >> 4
             };
             if nibble == 0 {
                 return 0; // Transparent
             }
-            (palette_num as usize * 16 + nibble as usize) & 0xFF
+            ((palette_num as usize) << 4 | nibble as usize) & 0xFF
         };
 
         // Look up color in palette
-        let pal_offset = color_idx * 2;
-        if pal_offset + 1 < palette.len() {
-            u16::from_le_bytes([palette[pal_offset], palette[pal_offset + 1]])
-        } else {
-            0
-        }
+        let pal_offset = color_idx << 1;
+        palette.half_at(pal_offset)
     }
 
     /// Convert 15-bit RGB colors to 32-bit ARGB using SIMD when available
@@ -1651,12 +2671,12 @@ impl Ppu {
     /// Scalar fallback for color conversion
     #[inline(always)]
     fn convert_colors_15bit_to_argb_scalar(colors: &[u16; 240], framebuffer: &mut [u32]) {
-        for i in 0..240 {
-            let color = colors[i] as u32;
-            let r = ((color & 0x1F) * 255 / 31) << 16;
-            let g = (((color >> 5) & 0x1F) * 255 / 31) << 8;
-            let b = ((color >> 10) & 0x1F) * 255 / 31;
-            framebuffer[i] = r | g | b;
+        for (out, &color) in framebuffer.iter_mut().zip(colors) {
+            let color = color as u32;
+            let r = ((color & 0x1F).wrapping_mul(255) / 31) << 16;
+            let g = (((color >> 5) & 0x1F).wrapping_mul(255) / 31) << 8;
+            let b = ((color >> 10) & 0x1F).wrapping_mul(255) / 31;
+            *out = r | g | b;
         }
     }
 
@@ -1666,12 +2686,11 @@ impl Ppu {
     unsafe fn convert_colors_15bit_to_argb_neon(colors: &[u16; 240], framebuffer: &mut [u32]) {
         use std::arch::aarch64::*;
 
-        // Process 8 pixels at a time (NEON can do 8x u16 -> 8x u32)
-        let mut i = 0;
-        while i + 8 <= 240 {
+        // Process 8 pixels at a time (NEON can do 8x u16 -> 8x u32); 240 is a
+        // multiple of 8, so there is no tail
+        for (src, dst) in colors.chunks_exact(8).zip(framebuffer.chunks_exact_mut(8)) {
             // Load 8 u16 colors
-            let colors_ptr = colors.as_ptr().add(i);
-            let c16 = vld1q_u16(colors_ptr);
+            let c16 = vld1q_u16(src.as_ptr());
 
             // Extract R, G, B components (5 bits each)
             let r16 = vandq_u16(c16, vdupq_n_u16(0x1F));
@@ -1709,155 +2728,73 @@ impl Ppu {
             );
 
             // Store results
-            let fb_ptr = framebuffer.as_mut_ptr().add(i);
+            let fb_ptr = dst.as_mut_ptr();
             vst1q_u32(fb_ptr, argb_lo);
             vst1q_u32(fb_ptr.add(4), argb_hi);
-
-            i += 8;
-        }
-
-        // Handle remaining pixels with scalar
-        while i < 240 {
-            let color = colors[i] as u32;
-            let r = ((color & 0x1F) * 255 / 31) << 16;
-            let g = (((color >> 5) & 0x1F) * 255 / 31) << 8;
-            let b = ((color >> 10) & 0x1F) * 255 / 31;
-            framebuffer[i] = r | g | b;
-            i += 1;
         }
     }
+}
 
-    fn render_tile_pixel_from_snapshot(
-        snapshot: &PpuSnapshot,
-        x: u16,
-        y: u16,
-        palette: &[u8; 0x400],
-    ) -> u16 {
-        // Render BG0 (simplified - just render the first enabled background)
-        let mode = (snapshot.dispcnt & 0x7) as u8;
-
-        // Find the first enabled background
-        let bg_idx = if snapshot.dispcnt & (1 << 8) != 0 {
-            0
-        } else if snapshot.dispcnt & (1 << 9) != 0 {
-            1
-        } else if snapshot.dispcnt & (1 << 10) != 0 {
-            2
-        } else if snapshot.dispcnt & (1 << 11) != 0 {
-            3
-        } else {
-            return 0;
-        }; // No background enabled
-
-        let bgcnt = snapshot.bgcnt[bg_idx];
-        let hofs = snapshot.bg_hofs[bg_idx];
-        let vofs = snapshot.bg_vofs[bg_idx];
-
-        // Calculate tile map dimensions based on BG size
-        let bg_size = bgcnt & 0x3;
-        let (map_width, map_height) = match bg_size {
-            0 => (256, 256), // 32x32 tiles
-            1 => (512, 256), // 64x32 tiles
-            2 => (256, 512), // 32x64 tiles
-            3 => (512, 512), // 64x64 tiles
-            _ => (256, 256),
-        };
-
-        // Calculate pixel position with scrolling
-        let px = (x.wrapping_add(hofs)) % map_width;
-        let py = (y.wrapping_add(vofs)) % map_height;
-
-        // Calculate tile position
-        let tile_x = px / 8;
-        let tile_y = py / 8;
-        let pixel_in_tile_x = (px % 8) as u8;
-        let pixel_in_tile_y = (py % 8) as u8;
-
-        // Get tile base and map base from BGCNT
-        let char_base = ((bgcnt >> 2) & 0x3) as usize * 0x4000;
-        let screen_base = ((bgcnt >> 8) & 0x1F) as usize * 0x800;
-
-        let tiles_per_row = map_width / 8;
-        let num_blocks_x = (tiles_per_row / 32) as usize;
-        let block_x = (tile_x / 32) as usize;
-        let block_y = (tile_y / 32) as usize;
-        let local_x = (tile_x % 32) as usize;
-        let local_y = (tile_y % 32) as usize;
-        let block_num = block_y * num_blocks_x + block_x;
-        let entry_offset = screen_base + block_num * 0x800 + (local_y * 32 + local_x) * 2;
-
-        if entry_offset + 1 >= snapshot.vram.len() {
-            return 0;
-        }
-        let entry =
-            u16::from_le_bytes([snapshot.vram[entry_offset], snapshot.vram[entry_offset + 1]]);
-
-        let tile_num = entry & 0x3FF;
-        let flip_h = (entry & 0x400) != 0;
-        let flip_v = (entry & 0x800) != 0;
-        let palette_num = (entry >> 12) & 0xF;
-
-        let is_8bpp = (bgcnt >> 7) & 1 != 0;
-
-        let color_idx = if is_8bpp {
-            // 8bpp: 256 colors, no palette selection
-            let tile_offset = char_base + (tile_num as usize * 64);
-            let fx = if flip_h {
-                7 - pixel_in_tile_x
-            } else {
-                pixel_in_tile_x
-            };
-            let fy = if flip_v {
-                7 - pixel_in_tile_y
-            } else {
-                pixel_in_tile_y
-            };
-            let pixel_offset = tile_offset + (fy as usize * 8) + (fx as usize);
-            if pixel_offset < snapshot.vram.len() {
-                snapshot.vram[pixel_offset] as usize
-            } else {
-                0
+impl Ppu {
+    pub(crate) fn save_state(&self, w: &mut StateWriter) {
+        w.write_u16(self.dispcnt.bits());
+        w.write_bool(self.display_enabled);
+        w.write_u16(self.dispstat);
+        w.write_u16(self.vcount);
+        w.write_u32(self.hcounter);
+        w.write_u16s(&self.bgcnt);
+        w.write_u16s(&self.bg_hofs);
+        w.write_u16s(&self.bg_vofs);
+        for params in &self.bg_affine {
+            w.write_u32s(params);
+        }
+        for point in self.bg_ref.iter().chain(&self.bg_ref_internal) {
+            w.write_u32s(&point.map(|v| v as u32));
+        }
+        for line in self.bg_ref_lines.iter() {
+            for point in line {
+                w.write_u32s(&point.map(|v| v as u32));
             }
-        } else {
-            // 4bpp: 16 colors per palette
-            let tile_offset = char_base + (tile_num as usize * 32);
-            let fx = if flip_h {
-                7 - pixel_in_tile_x
-            } else {
-                pixel_in_tile_x
-            };
-            let fy = if flip_v {
-                7 - pixel_in_tile_y
-            } else {
-                pixel_in_tile_y
-            };
-            let row_offset = tile_offset + (fy as usize * 4);
-            let nibble = if fx % 2 == 0 {
-                if row_offset + (fx as usize / 2) < snapshot.vram.len() {
-                    snapshot.vram[row_offset + (fx as usize / 2)] & 0x0F
-                } else {
-                    0
-                }
-            } else {
-                if row_offset + (fx as usize / 2) < snapshot.vram.len() {
-                    snapshot.vram[row_offset + (fx as usize / 2)] >> 4
-                } else {
-                    0
-                }
-            };
-            if nibble == 0 {
-                return 0; // Transparent
+        }
+        w.write_u16s(&[self.bg_mosaic, self.obj_mosaic]);
+        w.write_u16s(&[self.win0_h, self.win0_v, self.win1_h, self.win1_v, self.winin, self.winout]);
+        w.write_u16s(&[self.bldcnt, self.bldalpha, self.bldy]);
+        w.write_bytes(&self.vram[..]);
+        w.write_bytes(&self.oam[..]);
+    }
+
+    pub(crate) fn load_state(&mut self, r: &mut StateReader) -> Result<(), StateError> {
+        self.obj_cutoff_cache.set(None);
+        self.dispcnt = DisplayControl::from_bits_retain(r.read_u16()?);
+        self.display_enabled = r.read_bool()?;
+        self.dispstat = r.read_u16()?;
+        self.vcount = r.read_u16()?;
+        self.hcounter = r.read_u32()?;
+        r.read_u16s(&mut self.bgcnt)?;
+        r.read_u16s(&mut self.bg_hofs)?;
+        r.read_u16s(&mut self.bg_vofs)?;
+        for params in &mut self.bg_affine {
+            r.read_u32s(params)?;
+        }
+        for point in self.bg_ref.iter_mut().chain(self.bg_ref_internal.iter_mut()) {
+            *point = [r.read_u32()? as i32, r.read_u32()? as i32];
+        }
+        for line in self.bg_ref_lines.iter_mut() {
+            for point in line.iter_mut() {
+                *point = [r.read_u32()? as i32, r.read_u32()? as i32];
             }
-            (palette_num as usize * 16 + nibble as usize) & 0xFF
-        };
-
-        // Look up color in palette
-        let pal_offset = color_idx * 2;
-        if pal_offset + 1 < palette.len() {
-            u16::from_le_bytes([palette[pal_offset], palette[pal_offset + 1]])
-        } else {
-            0
         }
+        self.bg_mosaic = r.read_u16()?;
+        self.obj_mosaic = r.read_u16()?;
+        let mut win = [0u16; 6];
+        r.read_u16s(&mut win)?;
+        [self.win0_h, self.win0_v, self.win1_h, self.win1_v, self.winin, self.winout] = win;
+        let mut blend = [0u16; 3];
+        r.read_u16s(&mut blend)?;
+        [self.bldcnt, self.bldalpha, self.bldy] = blend;
+        r.read_bytes_into(&mut self.vram[..])?;
+        r.read_bytes_into(&mut self.oam[..])?;
+        Ok(())
     }
 }
 
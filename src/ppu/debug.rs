@@ -0,0 +1,156 @@
+//! Frame Snapshot Diffing
+//!
+//! Captures the video memory a frame was drawn from and compares two
+//! captures at the granularity a VRAM viewer shows: tiles, BG map entries
+//! and palette colors. A golden-image test that starts failing can report
+//! which of these changed instead of only "pixels differ".
+//!
+//! [`PixelBreakdown`] is the per-pixel counterpart, returned by
+//! [`crate::Gba::render_debug_pixel`].
+
+use super::{BgControl, Layer, Ppu};
+use crate::view::{ByteView, ByteViewMut};
+
+/// Bytes per 4bpp tile, the unit tile changes are reported in
+const TILE_BYTES: usize = 32;
+
+/// Video memory and the registers needed to interpret it, at one instant
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameSnapshot {
+    pub vram: Box<[u8; 0x18000]>,
+    pub palette: Box<[u8; 0x400]>,
+    pub dispcnt: u16,
+    pub bgcnt: [u16; 4],
+}
+
+impl FrameSnapshot {
+    /// Capture the PPU's current VRAM and registers plus `palette`
+    pub fn capture(ppu: &Ppu, palette: &[u8; 0x400]) -> Self {
+        let mut vram = Box::new([0u8; 0x18000]);
+        vram.set_bytes(0, ppu.vram());
+        Self {
+            vram,
+            palette: Box::new(*palette),
+            dispcnt: ppu.get_dispcnt(),
+            bgcnt: std::array::from_fn(|bg| ppu.get_bgcnt(bg)),
+        }
+    }
+
+    /// VRAM byte range and entry size of each enabled BG's map
+    fn bg_maps(&self) -> Vec<(usize, std::ops::Range<usize>, usize)> {
+        let (text, affine): (&[usize], &[usize]) = match self.dispcnt & 0x7 {
+            0 => (&[0, 1, 2, 3], &[]),
+            1 => (&[0, 1], &[2]),
+            2 => (&[], &[2, 3]),
+            _ => (&[], &[]),
+        };
+        let map = |bg: usize, affine: bool| {
+            let cnt = BgControl::from_bits(self.bgcnt.get(bg).copied().unwrap_or(0));
+            let base = cnt.screen_base_addr();
+            let (len, entry) = if affine {
+                let tiles = cnt.affine_size() as usize / 8;
+                (tiles.wrapping_mul(tiles), 1)
+            } else {
+                let (w, h) = cnt.text_size();
+                (((w / 8).wrapping_mul(h / 8) as usize).wrapping_mul(2), 2)
+            };
+            (bg, base..base.wrapping_add(len), entry)
+        };
+        let text = text.iter().map(|&bg| map(bg, false));
+        let affine = affine.iter().map(|&bg| map(bg, true));
+        text.chain(affine)
+            .filter(|(bg, _, _)| self.dispcnt & (0x100 << bg) != 0)
+            .collect()
+    }
+}
+
+/// What changed between two [`FrameSnapshot`]s
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FrameDiff {
+    /// Changed 32-byte tile slots (VRAM offset / 32), outside the BG maps
+    pub tiles: Vec<usize>,
+    /// Changed map entries as (BG, entry index within its map), for the BGs
+    /// enabled in the newer snapshot
+    pub map_entries: Vec<(usize, usize)>,
+    /// Changed palette colors, 0-255 BG and 256-511 OBJ
+    pub palette: Vec<usize>,
+}
+
+impl FrameDiff {
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty() && self.map_entries.is_empty() && self.palette.is_empty()
+    }
+}
+
+/// Compare two snapshots. BG maps are located with `next`'s registers.
+pub fn diff_frames(prev: &FrameSnapshot, next: &FrameSnapshot) -> FrameDiff {
+    let mut diff = FrameDiff::default();
+    let maps = next.bg_maps();
+
+    for (bg, range, entry) in &maps {
+        for (i, offset) in range.clone().step_by(*entry).enumerate() {
+            let changed = (offset..offset.wrapping_add(*entry))
+                .any(|o| prev.vram.byte_at(o) != next.vram.byte_at(o));
+            if changed {
+                diff.map_entries.push((*bg, i));
+            }
+        }
+    }
+
+    let in_map = |offset: usize| maps.iter().any(|(_, range, _)| range.contains(&offset));
+    for (slot, (a, b)) in prev
+        .vram
+        .chunks(TILE_BYTES)
+        .zip(next.vram.chunks(TILE_BYTES))
+        .enumerate()
+    {
+        let start = slot.wrapping_mul(TILE_BYTES);
+        let changed = a
+            .iter()
+            .zip(b)
+            .enumerate()
+            .any(|(i, (x, y))| x != y && !in_map(start.wrapping_add(i)));
+        if changed {
+            diff.tiles.push(slot);
+        }
+    }
+
+    diff.palette = (0..0x200)
+        .filter(|&i: &usize| prev.palette.half_at(i << 1) != next.palette.half_at(i << 1))
+        .collect();
+    diff
+}
+
+/// Color special effect applied to a composited pixel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendEffect {
+    None,
+    /// Alpha blend with the layer below, coefficients 0-16
+    Alpha { eva: u8, evb: u8 },
+    /// Brightness increase, coefficient 0-16
+    Brighten(u8),
+    /// Brightness decrease, coefficient 0-16
+    Darken(u8),
+}
+
+/// How one screen pixel was composited
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PixelBreakdown {
+    /// Final RGB555 color
+    pub color: u16,
+    /// Layer that won the pixel
+    pub layer: Layer,
+    /// The winning layer's color before effects
+    pub layer_color: u16,
+    /// Palette entry of the winning layer (0-255 BG, 256-511 OBJ); None for
+    /// direct-color bitmap pixels
+    pub palette_index: Option<u16>,
+    /// Priority of the winning layer; 4 for the backdrop
+    pub priority: u8,
+    /// Next visible layer beneath, the second target of an alpha blend
+    pub below: Layer,
+    pub below_color: u16,
+    pub blend: BlendEffect,
+    /// Window visibility at the pixel: bits 0-3 BG0-BG3, bit 4 OBJ
+    pub window_mask: u16,
+}
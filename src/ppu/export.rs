@@ -0,0 +1,181 @@
+//! Tileset and Tilemap Export
+//!
+//! Decodes VRAM into 8-bit indexed images for inspection outside the
+//! emulator: raw character blocks as a tile sheet, and whole BG maps as the
+//! game laid them out (ignoring scroll, windows and effects).
+
+use super::Ppu;
+use crate::view::ByteView;
+use crate::ColorCorrection;
+
+/// Tiles per row in a tileset sheet
+const SHEET_TILES_PER_ROW: usize = 16;
+
+/// Size of one character block
+const CHAR_BLOCK_SIZE: usize = 0x4000;
+
+/// 8-bit indexed image plus the 256-color RGB555 palette it indexes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexedImage {
+    pub width: usize,
+    pub height: usize,
+    /// Row-major palette indices; 0 is transparent on hardware
+    pub pixels: Vec<u8>,
+    pub palette: [u16; 256],
+}
+
+impl IndexedImage {
+    fn new(width: usize, height: usize, palette: [u16; 256]) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0; width.saturating_mul(height)],
+            palette,
+        }
+    }
+
+    /// Palette index at (x, y)
+    pub fn get(&self, x: usize, y: usize) -> u8 {
+        self.pixels.get(self.index(x, y)).copied().unwrap_or(0)
+    }
+
+    fn set(&mut self, x: usize, y: usize, index: u8) {
+        let at = self.index(x, y);
+        if let Some(pixel) = self.pixels.get_mut(at) {
+            *pixel = index;
+        }
+    }
+
+    fn index(&self, x: usize, y: usize) -> usize {
+        y.wrapping_mul(self.width).wrapping_add(x)
+    }
+
+    /// Expand to 0x00RRGGBB pixels
+    pub fn to_xrgb8888(&self) -> Vec<u32> {
+        self.pixels
+            .iter()
+            .map(|&i| {
+                let color = self.palette.get(i as usize).copied().unwrap_or(0);
+                ColorCorrection::None.convert(color)
+            })
+            .collect()
+    }
+}
+
+/// The BG (`obj == false`) or OBJ half of palette RAM as 256 colors
+fn palette_half(palette: &[u8; 0x400], obj: bool) -> [u16; 256] {
+    let base = if obj { 0x200 } else { 0 };
+    std::array::from_fn(|i| palette.half_at(base | i << 1))
+}
+
+/// Decode one 16 KB character block as a sheet 16 tiles wide.
+///
+/// `char_base` counts 16 KB blocks like BGxCNT: 0-3 hold BG tiles, 4-5 OBJ
+/// tiles. `bpp` is 4 or 8. 4bpp pixels keep their raw 0-15 index, so the
+/// sheet shows palette bank 0 of the matching (BG or OBJ) palette.
+pub fn dump_tileset(ppu: &Ppu, palette: &[u8; 0x400], char_base: usize, bpp: u8) -> IndexedImage {
+    let base = char_base.wrapping_mul(CHAR_BLOCK_SIZE);
+    let (tile_bytes, tiles) = if bpp == 8 {
+        (64, CHAR_BLOCK_SIZE / 64)
+    } else {
+        (32, CHAR_BLOCK_SIZE / 32)
+    };
+    let width = SHEET_TILES_PER_ROW * 8;
+    let height = (tiles / SHEET_TILES_PER_ROW).wrapping_mul(8);
+    let mut image = IndexedImage::new(width, height, palette_half(palette, char_base >= 4));
+
+    for tile in 0..tiles {
+        let (sheet_x, sheet_y) = (
+            (tile % SHEET_TILES_PER_ROW).wrapping_mul(8),
+            (tile / SHEET_TILES_PER_ROW).wrapping_mul(8),
+        );
+        let start = base.wrapping_add(tile.wrapping_mul(tile_bytes));
+        for py in 0..8 {
+            for px in 0..8 {
+                let index = if bpp == 8 {
+                    ppu.get_tile_pixel_8bpp(start, 0, px as u8, py as u8, false, false)
+                } else {
+                    ppu.get_tile_pixel_4bpp(start, 0, px as u8, py as u8, 0, false, false)
+                };
+                image.set(sheet_x.wrapping_add(px), sheet_y.wrapping_add(py), index);
+            }
+        }
+    }
+    image
+}
+
+/// Decode the full map of `bg` using its current BGxCNT and display mode.
+///
+/// Text BGs produce 256-512 pixel maps with tile flips and 4bpp palette
+/// banks applied (index = bank * 16 + color); affine BGs produce their
+/// 128-1024 pixel square map.
+pub fn dump_tilemap(ppu: &Ppu, palette: &[u8; 0x400], bg: usize) -> IndexedImage {
+    let bg = bg.min(3);
+    let bgcnt = ppu.get_bgcnt(bg);
+    let mode = ppu.get_display_mode();
+    let size_bits = (bgcnt >> 14) & 0x3;
+    let tile_base = ppu.get_bg_tile_base(bg) as usize;
+    let map_base = ppu.get_bg_map_base(bg) as usize;
+    let palette = palette_half(palette, false);
+
+    let affine = (mode == 1 && bg == 2) || (mode == 2 && bg >= 2);
+    if affine {
+        let size = 128usize << size_bits;
+        let mut image = IndexedImage::new(size, size, palette);
+        for y in 0..size {
+            for x in 0..size {
+                let row = (y / 8).wrapping_mul(size / 8);
+                let entry = map_base.wrapping_add(row).wrapping_add(x / 8);
+                let tile = ppu.vram().byte_at(entry) as u16;
+                let index = ppu.get_tile_pixel_8bpp(
+                    tile_base,
+                    tile,
+                    (x % 8) as u8,
+                    (y % 8) as u8,
+                    false,
+                    false,
+                );
+                image.set(x, y, index);
+            }
+        }
+        return image;
+    }
+
+    let (width, height) = match size_bits {
+        0 => (256, 256),
+        1 => (512, 256),
+        2 => (256, 512),
+        _ => (512, 512),
+    };
+    let is_8bpp = bgcnt & 0x80 != 0;
+    let mut image = IndexedImage::new(width, height, palette);
+    for ty in 0..height / 8 {
+        for tx in 0..width / 8 {
+            let entry = ppu.get_screen_entry(
+                map_base,
+                tx as u16,
+                ty as u16,
+                size_bits,
+                (width / 8) as u16,
+                (height / 8) as u16,
+            );
+            let (tile, flip_h, flip_v, bank, _) = Ppu::parse_screen_entry(entry);
+            for py in 0..8 {
+                for px in 0..8 {
+                    let (x, y) = (px as u8, py as u8);
+                    let index = if is_8bpp {
+                        ppu.get_tile_pixel_8bpp(tile_base, tile, x, y, flip_h, flip_v)
+                    } else {
+                        match ppu.get_tile_pixel_4bpp(tile_base, tile, x, y, bank, flip_h, flip_v) {
+                            0 => 0,
+                            color => (bank as u8) << 4 | color,
+                        }
+                    };
+                    let (sheet_x, sheet_y) = (tx.wrapping_mul(8), ty.wrapping_mul(8));
+                    image.set(sheet_x.wrapping_add(px), sheet_y.wrapping_add(py), index);
+                }
+            }
+        }
+    }
+    image
+}
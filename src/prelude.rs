@@ -0,0 +1,16 @@
+//! Types a frontend needs to load a game, run it and show its output:
+//!
+//! ```
+//! use rgba::prelude::*;
+//!
+//! let mut gba = Gba::new();
+//! gba.run_frame();
+//! ```
+//!
+//! Debugger, profiler and hardware-level types stay at the crate root.
+
+pub use crate::{
+    AccuracyProfile, AudioOutput, AudioPitch, AudioQuality, BootMode, ColorCorrection, Gba,
+    HostClock, KeyState, Layer, NullRenderer, PpuFrameState, Renderer, SaveType, SlotInfo,
+    SoftwareRenderer, StateError, StepResult, SwapMode, SwiHandling, Throttle,
+};
@@ -0,0 +1,160 @@
+//! Sampling Profiler
+//!
+//! Records the PC of the running instruction once every N emulated cycles
+//! into a histogram. [`Profiler::report`] folds the histogram into functions
+//! from a caller-supplied symbol table, and PCs outside every symbol into
+//! fixed-size address ranges, so a homebrew developer can see where frame
+//! time goes without an external tool.
+
+use std::collections::HashMap;
+
+/// Address range size used for samples that hit no symbol
+pub const UNSYMBOLIZED_RANGE: u32 = 0x100;
+
+/// A named code range, e.g. a function from a linker map or ELF symbol table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    pub start: u32,
+    /// Size in bytes; 0 extends the symbol to the next one's start
+    pub size: u32,
+}
+
+impl Symbol {
+    pub fn new(name: impl Into<String>, start: u32, size: u32) -> Self {
+        Self {
+            name: name.into(),
+            start,
+            size,
+        }
+    }
+}
+
+/// Samples attributed to one symbol or address range
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProfileEntry {
+    /// Symbol name, or `XXXXXXXX-XXXXXXXX` for an unsymbolized range
+    pub name: String,
+    pub start: u32,
+    pub samples: u64,
+    /// Share of all samples, 0-100
+    pub percent: f64,
+}
+
+/// Aggregated profile, busiest entry first
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProfileReport {
+    pub interval: u32,
+    pub total_samples: u64,
+    pub entries: Vec<ProfileEntry>,
+}
+
+/// PC histogram sampled at a fixed cycle interval
+#[derive(Debug, Clone)]
+pub struct Profiler {
+    interval: u32,
+    /// Cycles left until the next sample
+    countdown: u32,
+    samples: HashMap<u32, u64>,
+    total: u64,
+}
+
+impl Profiler {
+    /// Sample every `interval` cycles (at least 1)
+    pub fn new(interval: u32) -> Self {
+        let interval = interval.max(1);
+        Self {
+            interval,
+            countdown: interval,
+            samples: HashMap::new(),
+            total: 0,
+        }
+    }
+
+    pub fn interval(&self) -> u32 {
+        self.interval
+    }
+
+    pub fn total_samples(&self) -> u64 {
+        self.total
+    }
+
+    /// Raw histogram: PC -> sample count
+    pub fn samples(&self) -> &HashMap<u32, u64> {
+        &self.samples
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+        self.total = 0;
+        self.countdown = self.interval;
+    }
+
+    /// Account `cycles` spent executing at `pc`
+    pub fn record(&mut self, pc: u32, cycles: u32) {
+        let mut cycles = cycles;
+        while cycles >= self.countdown {
+            cycles = cycles.wrapping_sub(self.countdown);
+            self.countdown = self.interval;
+            let samples = self.samples.entry(pc).or_insert(0);
+            *samples = samples.wrapping_add(1);
+            self.total = self.total.wrapping_add(1);
+        }
+        self.countdown = self.countdown.wrapping_sub(cycles);
+    }
+
+    /// Aggregate the histogram by `symbols`, falling back to
+    /// [`UNSYMBOLIZED_RANGE`]-byte address ranges
+    pub fn report(&self, symbols: &[Symbol]) -> ProfileReport {
+        let mut sorted: Vec<&Symbol> = symbols.iter().collect();
+        sorted.sort_by_key(|s| s.start);
+
+        let mut buckets: HashMap<(u32, Option<usize>), u64> = HashMap::new();
+        for (&pc, &count) in &self.samples {
+            let key = match symbol_at(&sorted, pc) {
+                Some((i, symbol)) => (symbol.start, Some(i)),
+                None => (pc & !(UNSYMBOLIZED_RANGE - 1), None),
+            };
+            let bucket = buckets.entry(key).or_insert(0);
+            *bucket = bucket.wrapping_add(count);
+        }
+
+        let total = self.total.max(1) as f64;
+        let mut entries: Vec<ProfileEntry> = buckets
+            .into_iter()
+            .map(|((start, symbol), samples)| ProfileEntry {
+                name: match symbol {
+                    Some(i) => sorted.get(i).map(|s| s.name.clone()).unwrap_or_default(),
+                    None => format!(
+                        "{:08X}-{:08X}",
+                        start,
+                        start.wrapping_add(UNSYMBOLIZED_RANGE - 1)
+                    ),
+                },
+                start,
+                samples,
+                percent: samples as f64 * 100.0 / total,
+            })
+            .collect();
+        entries.sort_by(|a, b| b.samples.cmp(&a.samples).then(a.start.cmp(&b.start)));
+
+        ProfileReport {
+            interval: self.interval,
+            total_samples: self.total,
+            entries,
+        }
+    }
+}
+
+/// Index in start-sorted `symbols` of the symbol containing `pc`
+fn symbol_at<'a>(symbols: &[&'a Symbol], pc: u32) -> Option<(usize, &'a Symbol)> {
+    let i = symbols.partition_point(|s| s.start <= pc).checked_sub(1)?;
+    let symbol = *symbols.get(i)?;
+    let end = match symbol.size {
+        0 => symbols
+            .get(i.wrapping_add(1))
+            .map_or(u32::MAX, |next| next.start),
+        size => symbol.start.saturating_add(size),
+    };
+    (pc < end).then_some((i, symbol))
+}
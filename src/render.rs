@@ -0,0 +1,739 @@
+//! Pluggable PPU Render Backends
+//!
+//! The core loop hands each visible scanline to a [`Renderer`] as its HBlank
+//! begins, with the live [`PpuState`] and a read-only [`MemView`], so
+//! per-line scroll, affine, window and palette changes reach every backend.
+//! The built-in [`SoftwareRenderer`] is the default and also draws the core's
+//! own framebuffer; [`NullRenderer`] discards output for headless tests and
+//! benchmarks.
+//!
+//! A [`PpuFrameState`] owns its copy of a finished frame behind `Arc`s,
+//! including the registers and palette each line was drawn with, so it can
+//! be handed to another thread and rendered while emulation runs on.
+
+use std::sync::Arc;
+
+use crate::color::ColorCorrection;
+use crate::mem::{peek_registers, InterruptController, Memory, MemoryRegion};
+use crate::ppu::{debug, Layer, LineRegisters, Ppu};
+use crate::view::ByteView;
+
+/// Screen width in pixels
+pub const SCREEN_WIDTH: usize = 240;
+/// Screen height in pixels
+pub const SCREEN_HEIGHT: usize = 160;
+
+/// PPU registers and VRAM/OAM handed to renderers
+pub type PpuState = Ppu;
+
+/// Read-only view of guest memory, from [`Memory::view`] or a renderer's
+/// palette alone
+///
+/// Only shared borrows are held, so a view is `Send + Sync` and can be read
+/// from another thread while the [`Memory`] it came from stays untouched.
+/// Reads never run I/O handlers, so they cannot disturb the machine the way
+/// a bus read can. Regions a view was not given read as 0.
+#[derive(Clone, Copy)]
+pub struct MemView<'a> {
+    pub palette: &'a [u8; 0x400],
+    pub(crate) bios: &'a [u8],
+    pub(crate) wram: &'a [u8],
+    pub(crate) iwram: &'a [u8],
+    pub(crate) io: &'a [u8],
+    pub(crate) vram: &'a [u8],
+    pub(crate) oam: &'a [u8],
+    pub(crate) rom: &'a [u8],
+    pub(crate) interrupt: Option<&'a InterruptController>,
+}
+
+impl<'a> MemView<'a> {
+    /// View of `palette` only, as handed to renderers of a detached frame
+    pub fn new(palette: &'a [u8; 0x400]) -> Self {
+        Self {
+            palette,
+            bios: &[],
+            wram: &[],
+            iwram: &[],
+            io: &[],
+            vram: &[],
+            oam: &[],
+            rom: &[],
+            interrupt: None,
+        }
+    }
+
+    pub fn from_memory(mem: &'a Memory) -> Self {
+        mem.view()
+    }
+
+    /// RGB555 palette entry (0-255 BG, 256-511 OBJ); 0 for out-of-range indices
+    pub fn color(&self, index: usize) -> u16 {
+        self.palette.half_at(index.wrapping_mul(2))
+    }
+
+    pub fn wram(&self) -> &'a [u8] {
+        self.wram
+    }
+
+    pub fn iwram(&self) -> &'a [u8] {
+        self.iwram
+    }
+
+    pub fn vram(&self) -> &'a [u8] {
+        self.vram
+    }
+
+    pub fn oam(&self) -> &'a [u8] {
+        self.oam
+    }
+
+    pub fn rom(&self) -> &'a [u8] {
+        self.rom
+    }
+
+    /// Byte at `addr` with the bus's mirroring, as [`Memory::peek_byte`]
+    /// reads it; save memory, open bus and ROM past its end read as 0
+    pub fn read_byte(&self, addr: u32) -> u8 {
+        let (region, offset) = Memory::map_address(addr);
+        match region {
+            MemoryRegion::Bios => self.bios.byte_at(offset),
+            MemoryRegion::Wram => self.wram.byte_at(offset),
+            MemoryRegion::Iwram => self.iwram.byte_at(offset),
+            MemoryRegion::Io => peek_registers(
+                self.io,
+                |reg| self.interrupt.map_or(0, |irq| irq.read_register(reg)),
+                offset,
+            ),
+            MemoryRegion::Palette => self.palette.byte_at(offset),
+            MemoryRegion::Vram => self.vram.byte_at(offset),
+            MemoryRegion::Oam => self.oam.byte_at(offset),
+            MemoryRegion::Rom => self.rom.byte_at(offset),
+            MemoryRegion::Sram | MemoryRegion::Unknown => 0,
+        }
+    }
+
+    /// Halfword at `addr` (force-aligned), see [`MemView::read_byte`]
+    pub fn read_half(&self, addr: u32) -> u16 {
+        let aligned = addr & !1;
+        u16::from_le_bytes([
+            self.read_byte(aligned),
+            self.read_byte(aligned.wrapping_add(1)),
+        ])
+    }
+
+    /// Word at `addr` (force-aligned), see [`MemView::read_byte`]
+    pub fn read_word(&self, addr: u32) -> u32 {
+        let aligned = addr & !3;
+        self.read_half(aligned) as u32 | (self.read_half(aligned.wrapping_add(2)) as u32) << 16
+    }
+
+    /// Copy `len` bytes starting at `addr`, for callers that need to keep
+    /// them after the view is gone
+    pub fn copy_bytes(&self, addr: u32, len: usize) -> Vec<u8> {
+        (0..len as u32)
+            .map(|i| self.read_byte(addr.wrapping_add(i)))
+            .collect()
+    }
+}
+
+/// Registers and palette one line of a [`PpuFrameState`] was drawn with
+#[derive(Clone)]
+struct LineState {
+    regs: LineRegisters,
+    palette: Arc<[u8; 0x400]>,
+}
+
+/// Owned PPU state and palette of a frame; cloning only bumps reference counts
+#[derive(Clone)]
+pub struct PpuFrameState {
+    pub ppu: Arc<PpuState>,
+    pub palette: Arc<[u8; 0x400]>,
+    /// Per-line state recorded while the frame ran; empty for a frame
+    /// captured at one instant
+    lines: Arc<[Option<LineState>]>,
+}
+
+impl PpuFrameState {
+    pub fn new(ppu: PpuState, palette: &[u8; 0x400]) -> Self {
+        Self {
+            ppu: Arc::new(ppu),
+            palette: Arc::new(*palette),
+            lines: Arc::new([]),
+        }
+    }
+
+    pub fn view(&self) -> MemView<'_> {
+        MemView::new(&self.palette)
+    }
+
+    /// Drive the renderer over every visible scanline of this frame, each
+    /// with the registers and palette it had when the frame ran. VRAM and
+    /// OAM are the end-of-frame contents.
+    pub fn render(&self, renderer: &mut dyn Renderer) {
+        let mut line_ppu: Option<Ppu> = None;
+        for line in 0..SCREEN_HEIGHT as u16 {
+            match self.lines.get(line as usize).and_then(Option::as_ref) {
+                Some(state) => {
+                    let ppu = line_ppu.get_or_insert_with(|| (*self.ppu).clone());
+                    ppu.set_line_registers(&state.regs);
+                    renderer.render_scanline(line, ppu, &MemView::new(&state.palette));
+                }
+                None => renderer.render_scanline(line, &self.ppu, &self.view()),
+            }
+        }
+    }
+}
+
+/// Renderer that keeps each line's registers and palette for a [`PpuFrameState`]
+pub(crate) struct FrameRecorder {
+    lines: Vec<Option<LineState>>,
+}
+
+impl FrameRecorder {
+    pub(crate) fn new() -> Self {
+        Self {
+            lines: vec![None; SCREEN_HEIGHT],
+        }
+    }
+
+    /// Frame state with `ppu` and `palette` as the end-of-frame contents
+    pub(crate) fn finish(self, ppu: PpuState, palette: &[u8; 0x400]) -> PpuFrameState {
+        PpuFrameState {
+            lines: self.lines.into(),
+            ..PpuFrameState::new(ppu, palette)
+        }
+    }
+}
+
+impl Renderer for FrameRecorder {
+    fn render_scanline(&mut self, line: u16, state: &PpuState, mem: &MemView) {
+        let y = line as usize;
+        if y >= SCREEN_HEIGHT {
+            return;
+        }
+        // Lines mostly share a palette, so reuse the previous line's copy
+        let previous = y.checked_sub(1).and_then(|p| self.lines.get(p)?.as_ref());
+        let palette = match previous {
+            Some(prev) if *prev.palette == *mem.palette => prev.palette.clone(),
+            _ => Arc::new(*mem.palette),
+        };
+        if let Some(slot) = self.lines.get_mut(y) {
+            *slot = Some(LineState {
+                regs: state.line_registers(),
+                palette,
+            });
+        }
+    }
+}
+
+/// Render backend driven once per visible scanline
+pub trait Renderer {
+    fn render_scanline(&mut self, line: u16, state: &PpuState, mem: &MemView);
+}
+
+/// Default CPU renderer producing a 240x160 0x00RRGGBB framebuffer with
+/// windows, blending, affine BGs and sprites, converted through a
+/// [`ColorCorrection`] lookup table
+pub struct SoftwareRenderer {
+    framebuffer: Vec<u32>,
+    color_correction: ColorCorrection,
+    lut: Vec<u32>,
+}
+
+impl SoftwareRenderer {
+    pub fn new() -> Self {
+        Self::with_color_correction(ColorCorrection::None)
+    }
+
+    pub fn with_color_correction(mode: ColorCorrection) -> Self {
+        Self {
+            framebuffer: vec![0; SCREEN_WIDTH * SCREEN_HEIGHT],
+            color_correction: mode,
+            lut: mode.build_lut(),
+        }
+    }
+
+    /// Selects the LCD model emulated when converting to RGB
+    pub fn set_color_correction(&mut self, mode: ColorCorrection) {
+        if mode != self.color_correction {
+            self.color_correction = mode;
+            self.lut = mode.build_lut();
+        }
+    }
+
+    pub fn color_correction(&self) -> ColorCorrection {
+        self.color_correction
+    }
+
+    pub fn framebuffer(&self) -> &[u32] {
+        &self.framebuffer
+    }
+
+    pub fn framebuffer_mut(&mut self) -> &mut [u32] {
+        &mut self.framebuffer
+    }
+
+    /// Draw `line` of `state` into the 240-pixel `row` instead of the framebuffer
+    pub(crate) fn draw_line(
+        &self,
+        line: u16,
+        state: &PpuState,
+        palette: &[u8; 0x400],
+        row: &mut [u32],
+    ) {
+        draw_line(&self.lut, line, state, palette, row);
+    }
+}
+
+impl Default for SoftwareRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Renderer for SoftwareRenderer {
+    fn render_scanline(&mut self, line: u16, state: &PpuState, mem: &MemView) {
+        let y = line as usize;
+        if y >= SCREEN_HEIGHT {
+            return;
+        }
+        let Some(row) = self.framebuffer.chunks_exact_mut(SCREEN_WIDTH).nth(y) else {
+            return;
+        };
+        draw_line(&self.lut, line, state, mem.palette, row);
+    }
+}
+
+fn draw_line(lut: &[u32], line: u16, state: &PpuState, palette: &[u8; 0x400], row: &mut [u32]) {
+    let rgb = |color: u16| lut.get((color & 0x7FFF) as usize).copied().unwrap_or(0);
+    if state.get_dispcnt() & 0x80 != 0 {
+        // Forced blank shows white
+        row.fill(rgb(0x7FFF));
+        return;
+    }
+    let compositor = Compositor {
+        ppu: state,
+        palette,
+    };
+    for (x, out) in row.iter_mut().enumerate() {
+        let color = compositor.pixel(x as u16, line).color;
+        *out = rgb(color);
+    }
+}
+
+/// Renderer that ignores every scanline
+#[derive(Default)]
+pub struct NullRenderer;
+
+impl Renderer for NullRenderer {
+    fn render_scanline(&mut self, _line: u16, _state: &PpuState, _mem: &MemView) {}
+}
+
+/// One layer's candidate for a pixel during compositing
+#[derive(Debug, Clone, Copy)]
+struct PixelSource {
+    layer: Layer,
+    color: u16,
+    palette_index: Option<u16>,
+    /// 0-3; the backdrop sorts below every layer at 4
+    priority: u8,
+}
+
+/// RGB555 compositor shared by [`SoftwareRenderer`] and the debugger: BG
+/// and OBJ priorities, windows, blending, affine BGs and mosaic, read from
+/// a PPU and a palette
+#[derive(Clone, Copy)]
+pub(crate) struct Compositor<'a> {
+    pub ppu: &'a Ppu,
+    pub palette: &'a [u8; 0x400],
+}
+
+impl Compositor<'_> {
+    /// RGB555 color `index` (0-255) of the BG (`pal_num` 0) or OBJ palette
+    pub(crate) fn palette_color(&self, pal_num: usize, index: u16) -> u16 {
+        let offset = if pal_num == 0 {
+            ((index as usize) << 1) & 0x3FF
+        } else {
+            0x200 | (((index as usize) << 1) & 0x1FF)
+        };
+        self.palette.half_at(offset)
+    }
+
+    /// Composite the pixel at (x, y) and report how it came about: the
+    /// winning layer, its palette index, the layer beneath it and the blend
+    /// effect applied
+    pub(crate) fn pixel(&self, x: u16, y: u16) -> debug::PixelBreakdown {
+        let ppu = self.ppu;
+        let mode = ppu.get_display_mode();
+        let dispcnt = ppu.render_dispcnt();
+        let backdrop = PixelSource {
+            layer: Layer::Backdrop,
+            color: self.palette_color(0, 0),
+            palette_index: Some(0),
+            priority: 4,
+        };
+        let finish = |top: PixelSource, below: PixelSource, color, blend, window_mask| {
+            debug::PixelBreakdown {
+                color,
+                layer: top.layer,
+                layer_color: top.color,
+                palette_index: top.palette_index,
+                priority: top.priority,
+                below: below.layer,
+                below_color: below.color,
+                blend,
+                window_mask,
+            }
+        };
+
+        if (3..=5).contains(&mode) {
+            // Sprites draw over the BG2 frame buffer
+            let bitmap = (dispcnt & (1 << 10) != 0)
+                .then(|| self.bitmap_pixel(mode, x, y))
+                .flatten();
+            let bg2 = match bitmap {
+                Some((color, palette_index)) => PixelSource {
+                    layer: Layer::Bg2,
+                    color,
+                    palette_index,
+                    priority: ppu.get_bg_priority(2) as u8,
+                },
+                // BG2 disabled, or outside the mode 5 frame, shows the backdrop
+                None => backdrop,
+            };
+            let obj = if dispcnt & (1 << 12) != 0 {
+                self.obj_source(x, y)
+                    .filter(|(obj, _)| obj.priority as u16 <= ppu.get_bg_priority(2))
+            } else {
+                None
+            };
+            return match obj {
+                Some((obj, _)) => finish(obj, bg2, obj.color, debug::BlendEffect::None, 0x1F),
+                None => finish(bg2, backdrop, bg2.color, debug::BlendEffect::None, 0x1F),
+            };
+        }
+        if mode > 5 {
+            return finish(backdrop, backdrop, 0, debug::BlendEffect::None, 0x1F);
+        }
+
+        let win_vis = ppu.get_window_visibility(x, y);
+        let mut first = backdrop;
+        let mut second = backdrop;
+        let mut first_semi = false;
+
+        for (bg, &layer) in Layer::ALL.iter().enumerate().take(4) {
+            if dispcnt & (0x100 << bg) != 0 && (win_vis & (1 << bg)) != 0 {
+                let priority = ppu.get_bg_priority(bg) as u8;
+                if priority >= first.priority {
+                    continue;
+                }
+                if let Some(index) = self.bg_index(mode, bg, x, y) {
+                    second = first;
+                    first = PixelSource {
+                        layer,
+                        color: self.palette_color(0, index),
+                        palette_index: Some(index),
+                        priority,
+                    };
+                }
+            }
+        }
+
+        if dispcnt & (1 << 12) != 0 && (win_vis & (1 << 4)) != 0 {
+            if let Some((obj, semi)) = self.obj_source(x, y) {
+                if obj.priority <= first.priority {
+                    second = first;
+                    first = obj;
+                    first_semi = semi;
+                }
+            }
+        }
+
+        if first_semi {
+            if let Some((color, blend)) =
+                self.blend_semi_transparent_obj(first.color, second.color, second.layer)
+            {
+                return finish(first, second, color, blend, win_vis);
+            }
+        }
+
+        if first.layer == Layer::Backdrop {
+            return finish(
+                first,
+                second,
+                first.color,
+                debug::BlendEffect::None,
+                win_vis,
+            );
+        }
+        let (color, blend) = self.apply_pixel_blending(first.color, second.color, first.layer);
+        finish(first, second, color, blend, win_vis)
+    }
+
+    /// Topmost non-window sprite pixel as (color, priority)
+    pub(crate) fn sprite_pixel(&self, x: u16, y: u16) -> Option<(u16, u8)> {
+        self.obj_source(x, y)
+            .map(|(obj, _)| (obj.color, obj.priority))
+    }
+
+    /// Topmost non-window sprite pixel and whether it is semi-transparent
+    fn obj_source(&self, x: u16, y: u16) -> Option<(PixelSource, bool)> {
+        let ppu = self.ppu;
+        let (sprite, pal_index) = ppu.top_sprite_at(x, y)?;
+        let source = PixelSource {
+            layer: Layer::Obj,
+            color: self.palette_color(1, pal_index),
+            palette_index: Some(pal_index.wrapping_add(256)),
+            priority: ppu.sprite_priority(sprite) as u8,
+        };
+        Some((source, ppu.sprite_is_semi_transparent(sprite)))
+    }
+
+    /// BG2 frame buffer pixel in bitmap modes 3-5 as (color, palette index);
+    /// only mode 4 is paletted. None outside mode 5's 160x128 frame.
+    pub(crate) fn bitmap_pixel(&self, mode: u8, x: u16, y: u16) -> Option<(u16, Option<u16>)> {
+        let page_base: usize = if (self.ppu.get_dispcnt() & 0x10) != 0 {
+            0xA000
+        } else {
+            0x0000
+        };
+        let vram = self.ppu.vram();
+        match mode {
+            3 => {
+                let pixel = (y as usize).wrapping_mul(240).wrapping_add(x as usize);
+                Some((vram.half_at(pixel << 1), None))
+            }
+            4 => {
+                let pixel = (y as usize).wrapping_mul(240).wrapping_add(x as usize);
+                let index = vram.byte_at(page_base.wrapping_add(pixel)) as u16;
+                Some((self.palette_color(0, index), Some(index)))
+            }
+            _ if x < 160 && y < 128 => {
+                let pixel = (y as usize).wrapping_mul(160).wrapping_add(x as usize);
+                Some((vram.half_at(page_base.wrapping_add(pixel << 1)), None))
+            }
+            _ => None,
+        }
+    }
+
+    /// BG palette index (0-255) at (x, y) for a tile-mode BG; None if transparent
+    pub(crate) fn bg_index(&self, mode: u8, bg: usize, x: u16, y: u16) -> Option<u16> {
+        let ppu = self.ppu;
+        let is_affine = (mode == 1 && bg == 2) || (mode == 2 && (bg == 2 || bg == 3));
+        if is_affine {
+            return self.affine_bg_index(bg, x, y);
+        }
+
+        let bgcnt = ppu.get_bgcnt(bg);
+        let bg_size = (bgcnt >> 14) & 0x3;
+
+        let (width, height) = match bg_size {
+            0 => (256u16, 256u16),
+            1 => (512u16, 256u16),
+            2 => (256u16, 512u16),
+            _ => (512u16, 512u16),
+        };
+
+        let hofs = ppu.get_bg_hofs(bg);
+        let vofs = ppu.get_bg_vofs(bg);
+        let (bg_x, bg_y) = (
+            x.wrapping_add(hofs) & width.wrapping_sub(1),
+            y.wrapping_add(vofs) & height.wrapping_sub(1),
+        );
+
+        let (bg_x, bg_y) = ppu.apply_bg_mosaic(bg_x, bg_y);
+        let tile_x = bg_x / 8;
+        let tile_y = bg_y / 8;
+        let pixel_x = bg_x % 8;
+        let pixel_y = bg_y % 8;
+        let screen_base = ppu.get_bg_map_base(bg) as usize;
+        let entry =
+            ppu.get_screen_entry(screen_base, tile_x, tile_y, bg_size, width / 8, height / 8);
+        let (tile_num, flip_h, flip_v, palette_num, _) = Ppu::parse_screen_entry(entry);
+        let is_8bpp = (bgcnt & 0x80) != 0;
+        let tile_base = ppu.get_bg_tile_base(bg) as usize;
+
+        let color_index = if is_8bpp {
+            ppu.get_tile_pixel_8bpp(
+                tile_base,
+                tile_num,
+                pixel_x as u8,
+                pixel_y as u8,
+                flip_h,
+                flip_v,
+            )
+        } else {
+            ppu.get_tile_pixel_4bpp(
+                tile_base,
+                tile_num,
+                pixel_x as u8,
+                pixel_y as u8,
+                palette_num,
+                flip_h,
+                flip_v,
+            )
+        };
+
+        if color_index != 0 {
+            let pal_index = if is_8bpp {
+                color_index as u16
+            } else {
+                (palette_num << 4) | color_index as u16
+            };
+            Some(pal_index)
+        } else {
+            None
+        }
+    }
+
+    /// Sample an affine (rotation/scaling) BG: square 128-1024 pixel map of 8-bit
+    /// tile numbers, 8bpp tiles. BGxCNT bit 13 chooses wraparound or transparency
+    /// for coordinates outside the map.
+    fn affine_bg_index(&self, bg: usize, x: u16, y: u16) -> Option<u16> {
+        let ppu = self.ppu;
+        let bgcnt = ppu.get_bgcnt(bg);
+        let size = 128i32 << ((bgcnt >> 14) & 0x3);
+        let wraparound = bgcnt & (1 << 13) != 0;
+
+        // The per-line reference point already includes the PB/PD steps
+        let pa = ppu.get_bg_affine_a(bg) as i32;
+        let pc = ppu.get_bg_affine_c(bg) as i32;
+        let (ref_x, ref_y) = ppu.get_bg_reference_for_line(bg, y);
+        let mut tx = pa.wrapping_mul(x as i32).wrapping_add(ref_x) >> 8;
+        let mut ty = pc.wrapping_mul(x as i32).wrapping_add(ref_y) >> 8;
+
+        if wraparound {
+            tx = tx.rem_euclid(size);
+            ty = ty.rem_euclid(size);
+        } else if tx < 0 || tx >= size || ty < 0 || ty >= size {
+            return None;
+        }
+
+        let (tx, ty) = ppu.apply_bg_mosaic(tx as u16, ty as u16);
+        let tiles_per_row = (size / 8) as usize;
+        let screen_base = ppu.get_bg_map_base(bg) as usize;
+        let row = (ty as usize / 8).wrapping_mul(tiles_per_row);
+        let entry_offset = screen_base.wrapping_add(row).wrapping_add(tx as usize / 8);
+        let tile_num = ppu.vram().byte_at(entry_offset) as u16;
+        let tile_base = ppu.get_bg_tile_base(bg) as usize;
+        let color_index = ppu.get_tile_pixel_8bpp(
+            tile_base,
+            tile_num,
+            (tx % 8) as u8,
+            (ty % 8) as u8,
+            false,
+            false,
+        );
+
+        if color_index != 0 {
+            Some(color_index as u16)
+        } else {
+            None
+        }
+    }
+
+    /// Semi-transparent OBJs alpha-blend with a second target directly behind them,
+    /// regardless of the BLDCNT effect and first-target bits. None if nothing to blend with.
+    fn blend_semi_transparent_obj(
+        &self,
+        obj: u16,
+        below: u16,
+        below_layer: Layer,
+    ) -> Option<(u16, debug::BlendEffect)> {
+        let ppu = self.ppu;
+        let target_bit = match below_layer {
+            Layer::Bg0 | Layer::Bg1 | Layer::Bg2 | Layer::Bg3 => below_layer as usize | 8,
+            Layer::Backdrop => 13,
+            Layer::Obj => return None,
+        };
+        if ppu.get_blend_control() & (1 << target_bit) == 0 {
+            return None;
+        }
+        let eva = (ppu.get_blend_alpha() & 0x1F).min(16) as u32;
+        let evb = ((ppu.get_blend_alpha() >> 8) & 0x1F).min(16) as u32;
+        let blend = debug::BlendEffect::Alpha {
+            eva: eva as u8,
+            evb: evb as u8,
+        };
+        Some((blend_alpha(obj, below, eva, evb), blend))
+    }
+
+    fn apply_pixel_blending(
+        &self,
+        first: u16,
+        second: u16,
+        first_layer: Layer,
+    ) -> (u16, debug::BlendEffect) {
+        let ppu = self.ppu;
+        let bldcnt = ppu.get_blend_control();
+        let blend_mode = ppu.get_blend_mode();
+        let unblended = (first, debug::BlendEffect::None);
+
+        if blend_mode == 0 {
+            return unblended;
+        }
+
+        let is_first_target = match first_layer {
+            Layer::Backdrop => false,
+            layer => (bldcnt & (1 << layer as usize)) != 0,
+        };
+
+        if !is_first_target {
+            return unblended;
+        }
+
+        match blend_mode {
+            1 => {
+                let eva = (ppu.get_blend_alpha() & 0x1F).min(16) as u32;
+                let evb = ((ppu.get_blend_alpha() >> 8) & 0x1F).min(16) as u32;
+                let is_second_target = (bldcnt & (0x1F << 8)) != 0;
+                let blended_second = if is_second_target { second } else { 0 };
+                let blend = debug::BlendEffect::Alpha {
+                    eva: eva as u8,
+                    evb: evb as u8,
+                };
+                (blend_alpha(first, blended_second, eva, evb), blend)
+            }
+            2 => {
+                let ey = (ppu.get_blend_brightness() & 0x1F).min(16) as u32;
+                (
+                    blend_brightness_up(first, ey),
+                    debug::BlendEffect::Brighten(ey as u8),
+                )
+            }
+            3 => {
+                let ey = (ppu.get_blend_brightness() & 0x1F).min(16) as u32;
+                (
+                    blend_brightness_down(first, ey),
+                    debug::BlendEffect::Darken(ey as u8),
+                )
+            }
+            _ => unblended,
+        }
+    }
+}
+
+pub(crate) fn blend_alpha(c1: u16, c2: u16, eva: u32, evb: u32) -> u16 {
+    map_channels(c1, c2, |a, b| {
+        eva.wrapping_mul(a).wrapping_add(evb.wrapping_mul(b)) >> 4
+    })
+}
+
+pub(crate) fn blend_brightness_up(c: u16, ey: u32) -> u16 {
+    map_channels(c, 0, |a, _| {
+        a.wrapping_add(31u32.wrapping_sub(a).wrapping_mul(ey) / 16)
+    })
+}
+
+pub(crate) fn blend_brightness_down(c: u16, ey: u32) -> u16 {
+    map_channels(c, 0, |a, _| a.wrapping_mul(16u32.wrapping_sub(ey)) >> 4)
+}
+
+/// Combine the 5-bit R, G and B of two RGB555 colors with `f`, clamping each
+/// result to 31
+fn map_channels(c1: u16, c2: u16, f: impl Fn(u32, u32) -> u32) -> u16 {
+    [0, 5, 10].into_iter().fold(0, |out, shift| {
+        let (a, b) = ((c1 >> shift) & 0x1F, (c2 >> shift) & 0x1F);
+        out | ((f(a as u32, b as u32).min(31) as u16) << shift)
+    })
+}
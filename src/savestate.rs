@@ -0,0 +1,296 @@
+//! Save States
+//!
+//! A save state is a flat little-endian byte stream: an 8-byte magic, a
+//! format version, the core version that wrote it, a short ROM identity
+//! block, then every component in a fixed order. Each component writes and reads its own fields through
+//! [`StateWriter`] / [`StateReader`], so adding a field only touches the
+//! component that owns it (and bumps [`STATE_VERSION`]).
+
+use std::fmt;
+
+/// Leading bytes of every save state
+pub const STATE_MAGIC: &[u8; 8] = b"RGBASTAT";
+
+/// Current save state layout version
+pub const STATE_VERSION: u32 = 12;
+
+/// Reasons a save state could not be restored
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StateError {
+    /// The data does not start with [`STATE_MAGIC`]
+    BadMagic,
+    /// The state was written by an incompatible layout version
+    UnsupportedVersion(u32),
+    /// The data ended before every component was read
+    Truncated,
+    /// The state belongs to a different ROM
+    RomMismatch,
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StateError::BadMagic => write!(f, "not an rgba save state"),
+            StateError::UnsupportedVersion(v) => write!(f, "unsupported save state version {}", v),
+            StateError::Truncated => write!(f, "save state is truncated"),
+            StateError::RomMismatch => write!(f, "save state was created for a different ROM"),
+        }
+    }
+}
+
+impl std::error::Error for StateError {}
+
+/// Leading fields of a save state, readable without a matching ROM
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateHeader {
+    /// Layout version; always [`STATE_VERSION`] once parsed
+    pub version: u32,
+    /// [`crate::build_info::VERSION`] of the core that wrote the state
+    pub core_version: String,
+}
+
+impl StateHeader {
+    /// Read the header at the start of `data`
+    pub fn parse(data: &[u8]) -> Result<Self, StateError> {
+        Self::read(&mut StateReader::new(data))
+    }
+
+    pub(crate) fn write(w: &mut StateWriter) {
+        for &b in STATE_MAGIC {
+            w.write_u8(b);
+        }
+        w.write_u32(STATE_VERSION);
+        w.write_bytes(crate::build_info::VERSION.as_bytes());
+    }
+
+    /// Read the header, rejecting other formats and layout versions
+    pub(crate) fn read(r: &mut StateReader) -> Result<Self, StateError> {
+        for &b in STATE_MAGIC {
+            if r.read_u8().map_err(|_| StateError::BadMagic)? != b {
+                return Err(StateError::BadMagic);
+            }
+        }
+        let version = r.read_u32()?;
+        if version != STATE_VERSION {
+            return Err(StateError::UnsupportedVersion(version));
+        }
+        let core_version = String::from_utf8_lossy(r.read_bytes()?).into_owned();
+        Ok(Self {
+            version,
+            core_version,
+        })
+    }
+}
+
+/// Leading bytes of a save state slot file
+pub const SLOT_MAGIC: &[u8; 8] = b"RGBASLOT";
+
+/// Slot thumbnails are the screen at half size
+pub const THUMBNAIL_WIDTH: usize = 120;
+pub const THUMBNAIL_HEIGHT: usize = 80;
+
+/// What a slot picker shows for a saved slot. A slot file is
+/// [`SLOT_MAGIC`], this metadata, then the save state itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlotInfo {
+    pub slot: u32,
+    /// Seconds since the Unix epoch when the slot was saved
+    pub timestamp: u64,
+    /// [`THUMBNAIL_WIDTH`] x [`THUMBNAIL_HEIGHT`] 0x00RRGGBB pixels of the
+    /// screen at the time
+    pub thumbnail: Vec<u32>,
+}
+
+impl SlotInfo {
+    /// Average each 2x2 block of a 240x160 0x00RRGGBB frame
+    pub(crate) fn thumbnail_of(frame: &[u32]) -> Vec<u32> {
+        let mut thumbnail = Vec::with_capacity(THUMBNAIL_WIDTH * THUMBNAIL_HEIGHT);
+        for rows in frame.chunks_exact(2 * 240).take(THUMBNAIL_HEIGHT) {
+            let (top, bottom) = rows.split_at(240);
+            for (top, bottom) in top.chunks_exact(2).zip(bottom.chunks_exact(2)) {
+                let channel = |shift: u32| {
+                    let block = top.iter().chain(bottom);
+                    let sum: u32 = block.map(|&px| (px >> shift) & 0xFF).sum();
+                    (sum / 4) << shift
+                };
+                thumbnail.push(channel(16) | channel(8) | channel(0));
+            }
+        }
+        thumbnail
+    }
+
+    /// Slot file contents: this metadata followed by `state`
+    pub(crate) fn encode(&self, state: &[u8]) -> Vec<u8> {
+        let mut w = StateWriter::new();
+        for &b in SLOT_MAGIC {
+            w.write_u8(b);
+        }
+        w.write_u64(self.timestamp);
+        w.write_u32s(&self.thumbnail);
+        w.write_bytes(state);
+        w.into_inner()
+    }
+
+    /// Split a slot file into its metadata and save state
+    pub(crate) fn decode(slot: u32, data: &[u8]) -> Result<(Self, &[u8]), StateError> {
+        let mut r = StateReader::new(data);
+        for &b in SLOT_MAGIC {
+            if r.read_u8().map_err(|_| StateError::BadMagic)? != b {
+                return Err(StateError::BadMagic);
+            }
+        }
+        let timestamp = r.read_u64()?;
+        let mut thumbnail = vec![0; THUMBNAIL_WIDTH * THUMBNAIL_HEIGHT];
+        r.read_u32s(&mut thumbnail)?;
+        let state = r.read_bytes()?;
+        let info = Self {
+            slot,
+            timestamp,
+            thumbnail,
+        };
+        Ok((info, state))
+    }
+}
+
+/// Append-only encoder used by components to serialize themselves
+pub struct StateWriter {
+    buf: Vec<u8>,
+}
+
+impl StateWriter {
+    pub fn new() -> Self {
+        Self { buf: Vec::new() }
+    }
+
+    pub fn into_inner(self) -> Vec<u8> {
+        self.buf
+    }
+
+    pub fn write_u8(&mut self, val: u8) {
+        self.buf.push(val);
+    }
+
+    pub fn write_bool(&mut self, val: bool) {
+        self.buf.push(val as u8);
+    }
+
+    pub fn write_u16(&mut self, val: u16) {
+        self.buf.extend_from_slice(&val.to_le_bytes());
+    }
+
+    pub fn write_u32(&mut self, val: u32) {
+        self.buf.extend_from_slice(&val.to_le_bytes());
+    }
+
+    pub fn write_u64(&mut self, val: u64) {
+        self.buf.extend_from_slice(&val.to_le_bytes());
+    }
+
+    pub fn write_i16(&mut self, val: i16) {
+        self.buf.extend_from_slice(&val.to_le_bytes());
+    }
+
+    pub fn write_u16s(&mut self, vals: &[u16]) {
+        for &v in vals {
+            self.write_u16(v);
+        }
+    }
+
+    pub fn write_u32s(&mut self, vals: &[u32]) {
+        for &v in vals {
+            self.write_u32(v);
+        }
+    }
+
+    /// Length-prefixed byte block
+    pub fn write_bytes(&mut self, data: &[u8]) {
+        self.write_u32(data.len() as u32);
+        self.buf.extend_from_slice(data);
+    }
+}
+
+impl Default for StateWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Cursor over a save state; every read fails with [`StateError::Truncated`] past the end
+pub struct StateReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> StateReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], StateError> {
+        let end = self.pos.checked_add(len).ok_or(StateError::Truncated)?;
+        let slice = self.data.get(self.pos..end).ok_or(StateError::Truncated)?;
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn take_array<const N: usize>(&mut self) -> Result<[u8; N], StateError> {
+        self.take(N)?.try_into().map_err(|_| StateError::Truncated)
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, StateError> {
+        let [b] = self.take_array()?;
+        Ok(b)
+    }
+
+    pub fn read_bool(&mut self) -> Result<bool, StateError> {
+        Ok(self.read_u8()? != 0)
+    }
+
+    pub fn read_u16(&mut self) -> Result<u16, StateError> {
+        Ok(u16::from_le_bytes(self.take_array()?))
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32, StateError> {
+        Ok(u32::from_le_bytes(self.take_array()?))
+    }
+
+    pub fn read_u64(&mut self) -> Result<u64, StateError> {
+        let lo = self.read_u32()?;
+        let hi = self.read_u32()?;
+        Ok((hi as u64) << 32 | lo as u64)
+    }
+
+    pub fn read_i16(&mut self) -> Result<i16, StateError> {
+        Ok(self.read_u16()? as i16)
+    }
+
+    pub fn read_u16s(&mut self, out: &mut [u16]) -> Result<(), StateError> {
+        for v in out.iter_mut() {
+            *v = self.read_u16()?;
+        }
+        Ok(())
+    }
+
+    pub fn read_u32s(&mut self, out: &mut [u32]) -> Result<(), StateError> {
+        for v in out.iter_mut() {
+            *v = self.read_u32()?;
+        }
+        Ok(())
+    }
+
+    /// Length-prefixed byte block
+    pub fn read_bytes(&mut self) -> Result<&'a [u8], StateError> {
+        let len = self.read_u32()? as usize;
+        self.take(len)
+    }
+
+    /// Length-prefixed block copied into a fixed-size buffer; sizes must match
+    pub fn read_bytes_into(&mut self, out: &mut [u8]) -> Result<(), StateError> {
+        let data = self.read_bytes()?;
+        if data.len() != out.len() {
+            return Err(StateError::Truncated);
+        }
+        out.copy_from_slice(data);
+        Ok(())
+    }
+}
@@ -0,0 +1,215 @@
+//! Semihosting Host File I/O
+//!
+//! An opt-in subset of the ARM semihosting protocol so homebrew self-tests
+//! can write results to the host for CI to inspect. The ROM issues
+//! `SWI 0x123456` (ARM) or `SWI 0xAB` (Thumb) with the operation in r0 and a
+//! pointer to its argument words in r1; the result comes back in r0.
+//!
+//! Supported operations: SYS_OPEN, SYS_CLOSE, SYS_WRITEC, SYS_WRITE0,
+//! SYS_WRITE, SYS_READ, SYS_FLEN and SYS_EXIT. File names are resolved
+//! inside one sandbox directory; absolute paths and `..` are refused. The
+//! special name `:tt` is the console, which is captured in memory.
+
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+
+use crate::Memory;
+
+/// ARM SWI comment field that requests a semihosting call
+pub const SEMIHOST_SWI_ARM: u32 = 0x12_3456;
+
+/// Thumb SWI comment field that requests a semihosting call
+pub const SEMIHOST_SWI_THUMB: u32 = 0xAB;
+
+const SYS_OPEN: u32 = 0x01;
+const SYS_CLOSE: u32 = 0x02;
+const SYS_WRITEC: u32 = 0x03;
+const SYS_WRITE0: u32 = 0x04;
+const SYS_WRITE: u32 = 0x05;
+const SYS_READ: u32 = 0x06;
+const SYS_FLEN: u32 = 0x0C;
+const SYS_EXIT: u32 = 0x18;
+
+/// Returned in r0 when an operation fails
+const FAILURE: u32 = u32::MAX;
+
+/// Handle of the `:tt` console
+const CONSOLE: u32 = 0;
+
+/// Longest file name or NUL-terminated string read from guest memory
+const MAX_STRING: u32 = 0x1000;
+
+/// Semihosting state: the sandbox, open files and captured console output
+#[derive(Debug)]
+pub struct Semihost {
+    root: PathBuf,
+    /// Open files; handle `n` is `files[n - 1]`
+    files: Vec<Option<File>>,
+    console: Vec<u8>,
+    exit_code: Option<u32>,
+}
+
+impl Semihost {
+    /// Serve file operations from inside `root`
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            files: Vec::new(),
+            console: Vec::new(),
+            exit_code: None,
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Everything written to the `:tt` console
+    pub fn console(&self) -> &[u8] {
+        &self.console
+    }
+
+    /// Reason code passed to SYS_EXIT, once the ROM has called it
+    pub fn exit_code(&self) -> Option<u32> {
+        self.exit_code
+    }
+
+    /// Perform operation `op` with the argument block at `arg`; returns r0
+    pub(crate) fn call(&mut self, op: u32, arg: u32, mem: &mut Memory) -> u32 {
+        let word = |mem: &mut Memory, i: u32| mem.read_word(arg.wrapping_add(i.wrapping_mul(4)));
+        match op {
+            SYS_OPEN => {
+                let (name, mode, len) = (word(mem, 0), word(mem, 1), word(mem, 2));
+                let name = read_bytes(mem, name, len.min(MAX_STRING));
+                self.open(&String::from_utf8_lossy(&name), mode)
+            }
+            SYS_CLOSE => {
+                let handle = word(mem, 0);
+                match self.file_slot(handle) {
+                    Some(slot) => {
+                        *slot = None;
+                        0
+                    }
+                    None if handle == CONSOLE => 0,
+                    None => FAILURE,
+                }
+            }
+            SYS_WRITEC => {
+                let c = mem.read_byte(arg);
+                self.console.push(c);
+                0
+            }
+            SYS_WRITE0 => {
+                let s = read_c_string(mem, arg);
+                self.console.extend_from_slice(&s);
+                0
+            }
+            SYS_WRITE => {
+                let (handle, ptr, len) = (word(mem, 0), word(mem, 1), word(mem, 2));
+                let data = read_bytes(mem, ptr, len);
+                if handle == CONSOLE {
+                    self.console.extend_from_slice(&data);
+                    return 0;
+                }
+                match self.file(handle) {
+                    Some(file) => match file.write_all(&data) {
+                        Ok(()) => 0,
+                        Err(_) => len,
+                    },
+                    None => len,
+                }
+            }
+            SYS_READ => {
+                let (handle, ptr, len) = (word(mem, 0), word(mem, 1), word(mem, 2));
+                let Some(file) = self.file(handle) else {
+                    return len;
+                };
+                let mut data = Vec::new();
+                if file.take(len as u64).read_to_end(&mut data).is_err() {
+                    return len;
+                }
+                for (i, &b) in data.iter().enumerate() {
+                    mem.write_byte(ptr.wrapping_add(i as u32), b);
+                }
+                len.wrapping_sub(data.len() as u32)
+            }
+            SYS_FLEN => {
+                let handle = word(mem, 0);
+                let len = self
+                    .file(handle)
+                    .and_then(|f| f.metadata().ok())
+                    .map(|m| m.len());
+                match len {
+                    Some(len) if len < FAILURE as u64 => len as u32,
+                    _ => FAILURE,
+                }
+            }
+            SYS_EXIT => {
+                self.exit_code = Some(arg);
+                0
+            }
+            _ => FAILURE,
+        }
+    }
+
+    /// SYS_OPEN: `mode` 0-3 read, 4-7 write (truncate), 8-11 append; the `+`
+    /// variants (odd pairs) also allow the other direction
+    fn open(&mut self, name: &str, mode: u32) -> u32 {
+        if name == ":tt" {
+            return CONSOLE;
+        }
+        let Some(path) = self.sandboxed(name) else {
+            return FAILURE;
+        };
+        let plus = mode & 2 != 0;
+        let mut options = OpenOptions::new();
+        match mode >> 2 {
+            0 => options.read(true).write(plus),
+            1 => options.write(true).create(true).truncate(true).read(plus),
+            2 => options.append(true).create(true).read(plus),
+            _ => return FAILURE,
+        };
+        match options.open(path) {
+            Ok(mut file) => {
+                if mode >> 2 == 2 {
+                    let _ = file.seek(SeekFrom::End(0));
+                }
+                self.files.push(Some(file));
+                self.files.len() as u32
+            }
+            Err(_) => FAILURE,
+        }
+    }
+
+    /// `name` joined to the sandbox root, if it stays inside it
+    fn sandboxed(&self, name: &str) -> Option<PathBuf> {
+        let relative = Path::new(name);
+        let inside = relative
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+        (inside && !name.is_empty()).then(|| self.root.join(relative))
+    }
+
+    fn file_slot(&mut self, handle: u32) -> Option<&mut Option<File>> {
+        let index = (handle as usize).checked_sub(1)?;
+        self.files.get_mut(index).filter(|slot| slot.is_some())
+    }
+
+    fn file(&mut self, handle: u32) -> Option<&mut File> {
+        self.file_slot(handle)?.as_mut()
+    }
+}
+
+fn read_bytes(mem: &mut Memory, addr: u32, len: u32) -> Vec<u8> {
+    (0..len)
+        .map(|i| mem.read_byte(addr.wrapping_add(i)))
+        .collect()
+}
+
+fn read_c_string(mem: &mut Memory, addr: u32) -> Vec<u8> {
+    (0..MAX_STRING)
+        .map(|i| mem.read_byte(addr.wrapping_add(i)))
+        .take_while(|&b| b != 0)
+        .collect()
+}
@@ -0,0 +1,96 @@
+//! Serial Port General-Purpose Mode
+//!
+//! With RCNT bits 14-15 set to 2, the link port's four pins stop being a
+//! serial interface and become plain I/O lines the game drives or samples
+//! one bit at a time. RCNT bits 0-3 are the pin levels (SC, SD, SI, SO),
+//! bits 4-7 their directions (1 = output) and bit 8 requests a serial
+//! interrupt when SI falls from high to low.
+//!
+//! A [`SioGpio`] implementation stands in for whatever is plugged into the
+//! port: it is told whenever the game changes a pin level or direction and
+//! supplies the levels on the pins the GBA leaves as inputs.
+
+/// RCNT pin bits, also used in [`SioPins`]
+pub const SIO_SC: u8 = 1 << 0;
+pub const SIO_SD: u8 = 1 << 1;
+pub const SIO_SI: u8 = 1 << 2;
+pub const SIO_SO: u8 = 1 << 3;
+
+const PINS: u16 = 0x000F;
+const SI_IRQ: u16 = 1 << 8;
+
+/// State of the link port pins as the game last set them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SioPins {
+    /// Levels written to RCNT bits 0-3; only meaningful for output pins
+    pub levels: u8,
+    /// Pins the GBA drives, from RCNT bits 4-7
+    pub outputs: u8,
+}
+
+impl SioPins {
+    fn from_rcnt(rcnt: u16) -> Self {
+        Self {
+            levels: (rcnt & PINS) as u8,
+            outputs: ((rcnt >> 4) & PINS) as u8,
+        }
+    }
+
+    /// Whether the GBA drives `pin` high
+    pub fn is_high(&self, pin: u8) -> bool {
+        self.outputs & self.levels & pin != 0
+    }
+}
+
+/// Peripheral on the link port in general-purpose mode
+pub trait SioGpio: Send {
+    /// The game changed a pin level or direction in general-purpose mode
+    fn write(&mut self, pins: SioPins);
+
+    /// Levels the peripheral drives, as RCNT bits 0-3. Bits for pins the
+    /// GBA drives itself are ignored.
+    fn read(&mut self) -> u8;
+}
+
+/// Whether RCNT selects general-purpose mode
+pub(crate) fn is_gpio_mode(rcnt: u16) -> bool {
+    rcnt >> 14 == 2
+}
+
+/// The attached peripheral, the pins last reported to it and the SI level
+/// last sampled from it
+pub(crate) struct GpioPort {
+    handler: Box<dyn SioGpio>,
+    written: Option<SioPins>,
+    si_high: bool,
+}
+
+impl GpioPort {
+    pub(crate) fn new(handler: Box<dyn SioGpio>) -> Self {
+        Self {
+            handler,
+            written: None,
+            si_high: true,
+        }
+    }
+
+    pub(crate) fn write(&mut self, rcnt: u16) {
+        let pins = SioPins::from_rcnt(rcnt);
+        if self.written != Some(pins) {
+            self.written = Some(pins);
+            self.handler.write(pins);
+        }
+    }
+
+    /// RCNT with the input pins replaced by the peripheral's levels, and
+    /// whether SI fell with its interrupt enabled
+    pub(crate) fn sample(&mut self, rcnt: u16) -> (u16, bool) {
+        let outputs = (rcnt >> 4) & PINS;
+        let levels = self.handler.read() as u16 & PINS;
+        let value = (rcnt & !(PINS & !outputs)) | (levels & !outputs);
+        let si_high = value & SIO_SI as u16 != 0;
+        let fell = self.si_high && !si_high && rcnt & SI_IRQ != 0;
+        self.si_high = si_high;
+        (value, fell)
+    }
+}
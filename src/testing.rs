@@ -0,0 +1,227 @@
+//! Test ROM Runner
+//!
+//! Runs a test ROM until it reports a result and returns a [`TestReport`].
+//! Each [`TestProtocol`] describes how a suite signals completion:
+//!
+//! - [`TestProtocol::R12Zero`]: jsmolka's gba-tests park the CPU in a `b .`
+//!   loop and leave the number of the first failing test in r12 (0 = pass).
+//! - [`TestProtocol::AgbAges`]: screen-based suites such as the AGB aging
+//!   cartridge finish on a static result screen, which is compared against
+//!   a reference frame.
+//! - [`TestProtocol::Custom`]: a caller-supplied check run after every frame.
+//!
+//! [`find_divergence`] compares two machines fed the same input, e.g. one
+//! per accuracy profile, and reports the first frame their states differ.
+
+use std::path::Path;
+
+use crate::{Gba, KeyState};
+
+/// Frames run before a test is reported as timed out (~10 seconds)
+pub const DEFAULT_FRAME_LIMIT: u32 = 600;
+
+/// Frames the screen must stay unchanged before an AGS result is read
+const STABLE_FRAMES: u32 = 60;
+
+/// Per-frame completion check for [`TestProtocol::Custom`]
+pub type TestCheck = Box<dyn FnMut(&mut Gba) -> Option<TestOutcome>>;
+
+/// How a test ROM signals that it finished, and whether it passed
+pub enum TestProtocol {
+    /// Finished once the CPU idles on a branch-to-self; passes if r12 is 0
+    R12Zero,
+    /// Finished once the screen is static; passes if it matches the
+    /// reference 240x160 0x00RRGGBB frame
+    AgbAges(Vec<u32>),
+    /// Called after every frame; returns the outcome once the test finished
+    Custom(TestCheck),
+}
+
+/// Result of a test ROM run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TestOutcome {
+    Pass,
+    /// The ROM reported a failure, with a short description
+    Fail(String),
+    /// No result within the frame limit
+    Timeout,
+}
+
+/// Outcome plus the machine state at the end of the run
+#[derive(Debug, Clone)]
+pub struct TestReport {
+    pub outcome: TestOutcome,
+    pub frames: u32,
+    pub cycles: u64,
+    pub registers: [u32; 16],
+    pub pc: u32,
+    /// Final screen as 240x160 0x00RRGGBB pixels
+    pub frame: Vec<u32>,
+}
+
+impl TestReport {
+    pub fn passed(&self) -> bool {
+        self.outcome == TestOutcome::Pass
+    }
+}
+
+/// True if the next instruction is an ARM `b .` or Thumb `b .`
+fn at_idle_loop(gba: &mut Gba) -> bool {
+    let pc = gba.cpu().next_instruction_pc();
+    if gba.cpu().is_thumb_mode() {
+        gba.mem.read_half(pc) == 0xE7FE
+    } else {
+        gba.mem.read_word(pc) & 0x0FFF_FFFF == 0x0AFF_FFFE
+    }
+}
+
+/// Load a ROM from disk and run it under `protocol` for up to
+/// [`DEFAULT_FRAME_LIMIT`] frames
+pub fn run_test_rom(
+    path: impl AsRef<Path>,
+    protocol: TestProtocol,
+) -> Result<TestReport, Box<dyn std::error::Error>> {
+    let path = path.as_ref().to_string_lossy();
+    let mut gba = Gba::new();
+    gba.load_rom_path_patched(&path)?;
+    Ok(run_test(&mut gba, protocol, DEFAULT_FRAME_LIMIT))
+}
+
+/// Run an already loaded machine under `protocol` for up to `max_frames` frames
+pub fn run_test(gba: &mut Gba, mut protocol: TestProtocol, max_frames: u32) -> TestReport {
+    let mut frame = vec![0u32; 240 * 160];
+    let mut last_frame = Vec::new();
+    let mut stable = 0;
+    let mut outcome = TestOutcome::Timeout;
+    let mut frames = 0;
+
+    while frames < max_frames {
+        gba.run_frame();
+        frames += 1;
+
+        let result = match protocol {
+            TestProtocol::R12Zero => at_idle_loop(gba).then(|| match gba.cpu_reg(12) {
+                0 => TestOutcome::Pass,
+                n => TestOutcome::Fail(format!("test {} failed", n)),
+            }),
+            TestProtocol::AgbAges(ref expected) => {
+                gba.framebuffer_xrgb8888(&mut frame);
+                stable = if frame == last_frame { stable + 1 } else { 0 };
+                last_frame.clone_from(&frame);
+                (stable >= STABLE_FRAMES).then(|| {
+                    let diff = frame.iter().zip(expected).filter(|(a, b)| a != b).count();
+                    if diff == 0 && expected.len() == frame.len() {
+                        TestOutcome::Pass
+                    } else {
+                        TestOutcome::Fail(format!("{} pixels differ from the reference", diff))
+                    }
+                })
+            }
+            TestProtocol::Custom(ref mut check) => check(gba),
+        };
+        if let Some(result) = result {
+            outcome = result;
+            break;
+        }
+    }
+
+    gba.framebuffer_xrgb8888(&mut frame);
+    TestReport {
+        outcome,
+        frames,
+        cycles: gba.cycles_elapsed(),
+        registers: gba.cpu().registers(),
+        pc: gba.cpu().next_instruction_pc(),
+        frame,
+    }
+}
+
+/// Frames run between digest comparisons in [`find_divergence`]
+const DIVERGENCE_CHUNK: usize = 32;
+
+/// First frame at which two machines' [`Gba::state_digest`]s differed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Divergence {
+    /// Frames run when the digests first differed; 0 if they differed at
+    /// the start
+    pub frame: usize,
+    pub digest_a: u64,
+    pub digest_b: u64,
+}
+
+/// Hold exactly the keys in `pressed` for the next frame
+fn apply_keys(gba: &mut Gba, pressed: KeyState) {
+    for key in KeyState::all().iter() {
+        if pressed.contains(key) {
+            gba.input.press_key(key);
+        } else {
+            gba.input.release_key(key);
+        }
+    }
+}
+
+/// Run `frames` frames of `inputs` starting at frame `from`
+fn run_inputs(gba: &mut Gba, inputs: &[KeyState], from: usize, frames: usize) {
+    for &pressed in &inputs[from..from + frames] {
+        apply_keys(gba, pressed);
+        gba.run_frame();
+    }
+}
+
+/// Run `a` and `b` one frame per entry of `inputs` (the keys held during
+/// that frame) and return the first frame after which their states differ.
+///
+/// Digests are compared every few frames; once a chunk diverges both
+/// machines are rewound with save states and the chunk is bisected. On
+/// return both machines sit at the reported frame, or at the end of the
+/// input if they never diverged.
+pub fn find_divergence(a: &mut Gba, b: &mut Gba, inputs: &[KeyState]) -> Option<Divergence> {
+    let divergence = |frame: usize, a: &Gba, b: &Gba| {
+        let (digest_a, digest_b) = (a.state_digest(), b.state_digest());
+        (digest_a != digest_b).then_some(Divergence {
+            frame,
+            digest_a,
+            digest_b,
+        })
+    };
+    if let Some(d) = divergence(0, a, b) {
+        return Some(d);
+    }
+
+    let mut start = 0;
+    while start < inputs.len() {
+        let len = DIVERGENCE_CHUNK.min(inputs.len() - start);
+        let checkpoint = (a.save_state(), b.save_state());
+        run_inputs(a, inputs, start, len);
+        run_inputs(b, inputs, start, len);
+        if divergence(start + len, a, b).is_none() {
+            start += len;
+            continue;
+        }
+
+        // Bisect for the smallest n in 1..=len whose digests differ
+        // A state the same machine just saved always loads back
+        let rewind = |gba: &mut Gba, state: &[u8]| {
+            let _ = gba.load_state(state);
+        };
+        let (mut lo, mut hi) = (1, len);
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            rewind(a, &checkpoint.0);
+            rewind(b, &checkpoint.1);
+            run_inputs(a, inputs, start, mid);
+            run_inputs(b, inputs, start, mid);
+            if divergence(start + mid, a, b).is_some() {
+                hi = mid;
+            } else {
+                lo = mid + 1;
+            }
+        }
+        rewind(a, &checkpoint.0);
+        rewind(b, &checkpoint.1);
+        run_inputs(a, inputs, start, lo);
+        run_inputs(b, inputs, start, lo);
+        return divergence(start + lo, a, b);
+    }
+    None
+}
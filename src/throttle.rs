@@ -0,0 +1,99 @@
+//! Real-Time Throttling
+//!
+//! [`Throttle`] paces emulation against the wall clock using the emulated
+//! cycle count from [`crate::Gba::cycles_elapsed`]. It sleeps for most of the
+//! remaining time and busy-waits the last stretch, since OS sleeps routinely
+//! overshoot by a millisecond or more.
+
+use std::time::{Duration, Instant};
+
+/// ARM7TDMI clock rate in Hz
+pub const CPU_FREQUENCY: u64 = 16_777_216;
+
+/// CPU cycles in one video frame, 228 lines of 1232 cycles
+pub const CYCLES_PER_FRAME: u64 = 280_896;
+
+/// Time left to the deadline that is spent spinning instead of sleeping
+const SPIN_MARGIN: Duration = Duration::from_micros(1500);
+
+/// Falling further behind than this re-anchors instead of racing to catch up
+const MAX_LAG: Duration = Duration::from_millis(100);
+
+/// Keeps emulated time aligned with real time at a speed multiplier
+pub struct Throttle {
+    speed: f64,
+    anchor_time: Instant,
+    anchor_cycles: u64,
+}
+
+impl Throttle {
+    /// `speed` is the emulation rate relative to real hardware (1.0 = 100%).
+    /// Zero, negative or non-finite speeds disable throttling.
+    pub fn new(speed: f64) -> Self {
+        Self {
+            speed,
+            anchor_time: Instant::now(),
+            anchor_cycles: 0,
+        }
+    }
+
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    /// Change the multiplier; pacing restarts from `cycles`
+    pub fn set_speed(&mut self, speed: f64, cycles: u64) {
+        self.speed = speed;
+        self.reset(cycles);
+    }
+
+    /// Restart pacing from `cycles` now, e.g. after a pause or a reset
+    pub fn reset(&mut self, cycles: u64) {
+        self.anchor_time = Instant::now();
+        self.anchor_cycles = cycles;
+    }
+
+    fn is_limited(&self) -> bool {
+        self.speed.is_finite() && self.speed > 0.0
+    }
+
+    /// Wall-clock time after the anchor at which `cycles` should be reached
+    pub fn target_time(&self, cycles: u64) -> Duration {
+        if !self.is_limited() {
+            return Duration::ZERO;
+        }
+        let emulated = cycles.saturating_sub(self.anchor_cycles) as f64;
+        Duration::from_secs_f64(emulated / (CPU_FREQUENCY as f64 * self.speed))
+    }
+
+    /// Block until real time catches up with `cycles`. Returns the time spent waiting.
+    pub fn wait(&mut self, cycles: u64) -> Duration {
+        if !self.is_limited() {
+            return Duration::ZERO;
+        }
+        let target = self.target_time(cycles);
+        let elapsed = self.anchor_time.elapsed();
+        if elapsed >= target {
+            if elapsed.saturating_sub(target) > MAX_LAG {
+                self.reset(cycles);
+            }
+            return Duration::ZERO;
+        }
+
+        let start = Instant::now();
+        let remaining = target.saturating_sub(elapsed);
+        if remaining > SPIN_MARGIN {
+            std::thread::sleep(remaining.saturating_sub(SPIN_MARGIN));
+        }
+        while self.anchor_time.elapsed() < target {
+            std::hint::spin_loop();
+        }
+        start.elapsed()
+    }
+}
+
+impl Default for Throttle {
+    fn default() -> Self {
+        Self::new(1.0)
+    }
+}
@@ -3,6 +3,8 @@
 //! The GBA has 4 timers that can operate independently or cascade.
 //! Each timer can trigger DMA or interrupts on overflow.
 
+use crate::savestate::{StateError, StateReader, StateWriter};
+
 /// GBA Timer
 pub struct Timer {
     num: u8,
@@ -10,6 +12,8 @@ pub struct Timer {
     reload: u16,
     control: u16,
     prescaler_shift: u8,
+    /// CPU cycles accumulated toward the next prescaled tick
+    prescaler_cycles: u32,
     enabled: bool,
     count_up: bool,
     irq: bool,
@@ -24,6 +28,7 @@ impl Timer {
             reload: 0,
             control: 0,
             prescaler_shift: 0,
+            prescaler_cycles: 0,
             enabled: false,
             count_up: false,
             irq: false,
@@ -39,6 +44,7 @@ impl Timer {
         self.count_up = false;
         self.irq = false;
         self.prescaler_shift = 0;
+        self.prescaler_cycles = 0;
         self.overflow_pending = false;
     }
 
@@ -54,30 +60,33 @@ impl Timer {
             return;
         }
 
-        // Apply prescaler
-        let _prescaler = 1 << self.prescaler_shift;
-        let scaled_cycles = cycles >> self.prescaler_shift;
-
-        if scaled_cycles == 0 {
-            return;
-        }
-
-        // Check if overflow will occur
-        let old_counter = self.counter;
-        let (new_counter, overflow) = self.counter.overflowing_add(scaled_cycles as u16);
-
-        if overflow || (new_counter < old_counter && scaled_cycles > 0) {
-            // Timer overflowed
+        // Apply prescaler, carrying partial ticks over to the next step
+        self.prescaler_cycles = self.prescaler_cycles.wrapping_add(cycles);
+        let mut ticks = self.prescaler_cycles >> self.prescaler_shift;
+        self.prescaler_cycles &= (1u32 << self.prescaler_shift).wrapping_sub(1);
+
+        while ticks > 0 {
+            let to_overflow = 0x1_0000u32.wrapping_sub(self.counter as u32);
+            if ticks < to_overflow {
+                self.counter = self.counter.wrapping_add(ticks as u16);
+                break;
+            }
+            ticks = ticks.wrapping_sub(to_overflow);
             self.counter = self.reload;
-            let remaining =
-                (scaled_cycles as u16).wrapping_sub(0xFFFF_u16.wrapping_sub(old_counter));
-            self.counter = self.counter.wrapping_add(remaining);
             self.overflow_pending = true;
-        } else {
-            self.counter = new_counter;
         }
     }
 
+    /// CPU cycles until the next overflow, or `None` while stopped or cascading.
+    /// Stepping by at most this many cycles overflows at most once.
+    pub fn cycles_until_overflow(&self) -> Option<u32> {
+        if !self.enabled || (self.count_up && self.num > 0) {
+            return None;
+        }
+        let ticks = 0x1_0000u32.wrapping_sub(self.counter as u32);
+        Some((ticks << self.prescaler_shift).wrapping_sub(self.prescaler_cycles))
+    }
+
     /// Trigger count-up timing (called when previous timer overflows)
     pub fn trigger_count_up(&mut self) {
         if !self.enabled || !self.count_up {
@@ -120,6 +129,7 @@ impl Timer {
         self.enabled = (value & 0x80) != 0;
         if self.enabled && !was_enabled {
             self.counter = self.reload;
+            self.prescaler_cycles = 0;
             self.overflow_pending = false;
         }
     }
@@ -155,6 +165,31 @@ impl Timer {
     }
 }
 
+impl Timer {
+    pub(crate) fn save_state(&self, w: &mut StateWriter) {
+        w.write_u16(self.counter);
+        w.write_u16(self.reload);
+        w.write_u16(self.control);
+        w.write_bool(self.overflow_pending);
+        w.write_u32(self.prescaler_cycles);
+    }
+
+    pub(crate) fn load_state(&mut self, r: &mut StateReader) -> Result<(), StateError> {
+        let counter = r.read_u16()?;
+        self.reload = r.read_u16()?;
+        let control = r.read_u16()?;
+        // Decode prescaler/enable bits without the enable-edge reload
+        self.enabled = false;
+        self.set_control(control & !0x80);
+        self.enabled = control & 0x80 != 0;
+        self.control = control;
+        self.counter = counter;
+        self.overflow_pending = r.read_bool()?;
+        self.prescaler_cycles = r.read_u32()?;
+        Ok(())
+    }
+}
+
 impl Default for Timer {
     fn default() -> Self {
         Self::new(0)
@@ -0,0 +1,83 @@
+//! Bounds-Checked Memory Reads
+//!
+//! VRAM, OAM and palette offsets are built from guest-controlled registers,
+//! map entries and sprite attributes, so a corrupt or hostile ROM can point
+//! them anywhere. [`ByteView`] reads return 0 past the end of the buffer
+//! instead of panicking, and [`ByteViewMut`] writes there are dropped;
+//! garbage in draws garbage rather than crashing.
+
+/// Little-endian reads that yield 0 for any byte outside the slice
+pub trait ByteView {
+    fn byte_at(&self, offset: usize) -> u8;
+
+    fn half_at(&self, offset: usize) -> u16 {
+        u16::from_le_bytes([self.byte_at(offset), self.byte_at(offset.wrapping_add(1))])
+    }
+
+    fn word_at(&self, offset: usize) -> u32 {
+        self.half_at(offset) as u32 | (self.half_at(offset.wrapping_add(2)) as u32) << 16
+    }
+}
+
+impl ByteView for [u8] {
+    #[inline]
+    fn byte_at(&self, offset: usize) -> u8 {
+        self.get(offset).copied().unwrap_or(0)
+    }
+
+    #[inline]
+    fn half_at(&self, offset: usize) -> u16 {
+        match self.get(offset..offset.wrapping_add(2)) {
+            Some(&[lo, hi]) => u16::from_le_bytes([lo, hi]),
+            _ => u16::from_le_bytes([self.byte_at(offset), 0]),
+        }
+    }
+}
+
+impl<const N: usize> ByteView for [u8; N] {
+    #[inline]
+    fn byte_at(&self, offset: usize) -> u8 {
+        self.as_slice().byte_at(offset)
+    }
+
+    #[inline]
+    fn half_at(&self, offset: usize) -> u16 {
+        self.as_slice().half_at(offset)
+    }
+}
+
+/// Little-endian writes that drop any byte outside the slice
+pub trait ByteViewMut {
+    fn set_byte(&mut self, offset: usize, val: u8);
+
+    /// Copy `bytes` to `offset`, keeping only the part that fits
+    fn set_bytes(&mut self, offset: usize, bytes: &[u8]) {
+        for (i, &b) in bytes.iter().enumerate() {
+            self.set_byte(offset.wrapping_add(i), b);
+        }
+    }
+
+    fn set_half(&mut self, offset: usize, val: u16) {
+        self.set_bytes(offset, &val.to_le_bytes());
+    }
+
+    fn set_word(&mut self, offset: usize, val: u32) {
+        self.set_bytes(offset, &val.to_le_bytes());
+    }
+}
+
+impl ByteViewMut for [u8] {
+    #[inline]
+    fn set_byte(&mut self, offset: usize, val: u8) {
+        if let Some(b) = self.get_mut(offset) {
+            *b = val;
+        }
+    }
+}
+
+impl<const N: usize> ByteViewMut for [u8; N] {
+    #[inline]
+    fn set_byte(&mut self, offset: usize, val: u8) {
+        self.as_mut_slice().set_byte(offset, val);
+    }
+}
@@ -0,0 +1,85 @@
+//! Watch Expressions
+//!
+//! Frontends pin addresses they want to follow, such as a player's HP or
+//! an RNG seed, and read the decoded values back once per frame instead of
+//! peeking memory themselves. Values are refreshed at the start of VBlank,
+//! when games have finished their frame's logic, and are read without bus
+//! side effects.
+
+use std::fmt;
+
+use crate::Memory;
+
+/// Size of a watched value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Width {
+    Byte,
+    Half,
+    Word,
+}
+
+impl Width {
+    pub fn bytes(self) -> u32 {
+        match self {
+            Width::Byte => 1,
+            Width::Half => 2,
+            Width::Word => 4,
+        }
+    }
+}
+
+/// How a watched value's bits are interpreted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Signedness {
+    #[default]
+    Unsigned,
+    Signed,
+}
+
+/// A pinned address and its value at the last refresh
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchValue {
+    pub label: String,
+    pub addr: u32,
+    pub width: Width,
+    pub signedness: Signedness,
+    pub value: i64,
+    /// The value differs from the one before the last refresh
+    pub changed: bool,
+}
+
+impl WatchValue {
+    pub(crate) fn new(addr: u32, width: Width, signedness: Signedness, label: String) -> Self {
+        Self {
+            label,
+            addr,
+            width,
+            signedness,
+            value: 0,
+            changed: false,
+        }
+    }
+
+    /// Re-read the value from `mem`, little-endian and without alignment
+    pub(crate) fn refresh(&mut self, mem: &Memory) {
+        let mut bytes = [0u8; 4];
+        for (i, b) in (0..self.width.bytes()).zip(bytes.iter_mut()) {
+            *b = mem.peek_byte(self.addr.wrapping_add(i));
+        }
+        let raw = u32::from_le_bytes(bytes);
+        let value = match (self.signedness, self.width) {
+            (Signedness::Unsigned, _) => raw as i64,
+            (Signedness::Signed, Width::Byte) => raw as u8 as i8 as i64,
+            (Signedness::Signed, Width::Half) => raw as u16 as i16 as i64,
+            (Signedness::Signed, Width::Word) => raw as i32 as i64,
+        };
+        self.changed = value != self.value;
+        self.value = value;
+    }
+}
+
+impl fmt::Display for WatchValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} @ {:08X}: {}", self.label, self.addr, self.value)
+    }
+}
@@ -2,7 +2,10 @@
 //!
 //! These tests describe the expected behavior of the GBA's audio system.
 
-use rgba::Apu;
+use rgba::{
+    Apu, AudioOutput, AudioPitch, AudioQuality, Channel, FifoStats, Gba, CYCLES_PER_SAMPLE,
+    FIFO_CAPACITY, NATIVE_SAMPLE_RATE, SCOPE_LEN,
+};
 
 /// Scenario: APU initializes in silent state
 #[test]
@@ -23,3 +26,470 @@ fn apu_reset_clears_all_state() {
     // All channels should be silent
     // All registers should be at default values
 }
+
+/// Scenario: Channel 1 sweep leaving subtract mode mutes the channel under the quirk
+#[test]
+fn sweep_negate_then_add_disables_channel_with_quirk() {
+    for quirk in [true, false] {
+        // Given: channel 1 sweeping down every sweep tick by freq >> 1
+        let mut apu = Apu::new();
+        apu.set_master_enabled(true);
+        let ch1 = apu.get_square1();
+        ch1.set_sweep_negate_quirk(quirk);
+        ch1.set_frequency(0x400);
+        ch1.write_sweep(0x19);
+        ch1.trigger();
+
+        // When: the frame sequencer reaches its first sweep step
+        apu.step(32768 * 3);
+
+        // Then: the frequency has been lowered
+        assert_eq!(apu.square1().frequency(), 0x200, "sweep should subtract");
+
+        // When: the sweep is switched to addition after that subtraction
+        apu.get_square1().write_sweep(0x11);
+
+        // Then: only the quirk silences the channel
+        assert_eq!(
+            apu.square1().is_enabled(),
+            !quirk,
+            "quirk {} channel state",
+            quirk
+        );
+    }
+}
+
+/// Scenario: Powering the APU off resets the PSG channels
+#[test]
+fn master_power_off_resets_psg_channels() {
+    // Given: a triggered channel 1 with a sweep configured
+    let mut apu = Apu::new();
+    apu.set_master_enabled(true);
+    let ch1 = apu.get_square1();
+    ch1.set_frequency(0x400);
+    ch1.write_sweep(0x19);
+    ch1.trigger();
+
+    // When: the master enable is cleared
+    apu.set_master_enabled(false);
+
+    // Then: the channel is silent and its registers are back to zero
+    assert_eq!(apu.square1().is_enabled(), false, "channel 1 disabled");
+    assert_eq!(apu.square1().frequency(), 0, "frequency cleared");
+}
+
+/// Scenario: Every resampler quality passes a constant level through unchanged
+#[test]
+fn resampler_qualities_preserve_a_constant_level() {
+    for quality in AudioQuality::ALL {
+        // Given: an output stage converting to 44.1 kHz
+        let mut audio = AudioOutput::new(44100, quality);
+
+        // When: one second of a constant native signal is fed in
+        for _ in 0..NATIVE_SAMPLE_RATE {
+            audio.step(CYCLES_PER_SAMPLE, 1000, -500);
+        }
+        let mut out = Vec::new();
+        audio.drain_into(&mut out);
+
+        // Then: about 44100 stereo samples come out (less the kernel delay),
+        // settled at the input level
+        let frames = out.len() / 2;
+        assert!(
+            (44080..=44100).contains(&frames),
+            "{:?} produced {} samples",
+            quality,
+            frames
+        );
+        assert_eq!(&out[out.len() - 2..], &[1000, -500], "{:?} level", quality);
+    }
+}
+
+/// Scenario: The per-frame sample count matches the output rate
+#[test]
+fn audio_frame_sample_count_follows_output_rate() {
+    // Given: a console producing 48 kHz output
+    let mut gba = Gba::new();
+    gba.set_audio_sample_rate(48000);
+    gba.set_audio_quality(AudioQuality::Sinc);
+
+    // When: a few frames run
+    for _ in 0..3 {
+        gba.run_frame();
+    }
+
+    // Then: one frame yields 48000 * 280896 / 16777216 (about 803.6) samples
+    let count = gba.audio_frame_sample_count();
+    assert!((803..=804).contains(&count), "samples per frame: {}", count);
+
+    // And: draining hands out interleaved stereo samples
+    let mut out = Vec::new();
+    gba.drain_audio(&mut out);
+    assert_eq!(out.len() % 2, 0, "interleaved stereo");
+    let buffered = out.len() / 2;
+    assert!(buffered > 2 * 803, "buffered {} samples", buffered);
+}
+
+/// Scenario: SOUNDCNT_L and SOUNDCNT_H written by the game route each channel
+#[test]
+fn soundcnt_registers_route_channels_to_each_speaker() {
+    // Given: a console with the sound circuit powered
+    let mut gba = Gba::new();
+    gba.write_byte(0x0400_0084, 0x80);
+
+    // When: channel 1 goes left only, channel 2 right only, channel 4 both,
+    // Direct Sound A right only and Direct Sound B left only
+    gba.write_half(0x0400_0080, 0x9A77);
+    gba.write_half(0x0400_0082, 0x2100);
+    gba.step();
+
+    // Then: the APU's routing matches the registers
+    let expected = [
+        (true, false),
+        (false, true),
+        (false, false),
+        (true, true),
+        (false, true),
+        (true, false),
+    ];
+    for (ch, &routing) in expected.iter().enumerate() {
+        assert_eq!(gba.apu.channel_routing(ch), routing, "channel {}", ch);
+    }
+}
+
+/// Scenario: A tone routed only to the left leaves the right speaker silent
+#[test]
+fn left_only_direct_sound_produces_no_right_output() {
+    // Given: Direct Sound A at full volume, enabled on the left only
+    let mut gba = Gba::new();
+    gba.write_byte(0x0400_0084, 0x80);
+    gba.write_half(0x0400_0082, 0x0204);
+    gba.step();
+
+    // When: the channel plays a non-zero sample
+    let ds_a = gba.apu.get_ds_a();
+    ds_a.write_fifo(0x4040_4040);
+    ds_a.read_sample();
+    gba.apu.step(4);
+
+    // Then: only the left output carries it
+    assert_ne!(gba.apu.get_output_left(), 0, "left output");
+    assert_eq!(gba.apu.get_output_right(), 0, "right output");
+}
+
+/// Scenario: The SOUNDCNT_H FIFO reset bit empties the FIFO and reads back as 0
+#[test]
+fn soundcnt_h_fifo_reset_clears_the_fifo() {
+    // Given: Direct Sound A playing a sample on both speakers
+    let mut gba = Gba::new();
+    gba.write_byte(0x0400_0084, 0x80);
+    gba.write_half(0x0400_0082, 0x0304);
+    gba.step();
+    let ds_a = gba.apu.get_ds_a();
+    ds_a.write_fifo(0x4040_4040);
+    ds_a.read_sample();
+
+    // When: the FIFO A reset bit is written
+    gba.write_half(0x0400_0082, 0x0B04);
+    gba.step();
+    gba.apu.step(4);
+
+    // Then: the channel is silent and the reset bit does not stick
+    assert_eq!(gba.apu.get_output_left(), 0, "left output after reset");
+    assert_eq!(gba.apu.get_ds_a().read_sample(), 0, "FIFO empty");
+    assert_eq!(gba.read_byte(0x0400_0083) & 0x08, 0, "reset bit reads 0");
+}
+
+/// Scenario: Writing more than 8 words to a FIFO drops the excess and counts it
+#[test]
+fn direct_sound_fifo_overrun_drops_bytes() {
+    // Given: a powered sound circuit with an empty FIFO A
+    let mut gba = Gba::new();
+    gba.write_byte(0x0400_0084, 0x80);
+
+    // When: the game writes nine words before any timer drains it
+    for i in 0..9u32 {
+        gba.write_word(0x0400_00A0, 0x0101_0101 * i);
+    }
+    gba.step();
+
+    // Then: the FIFO is full and the last word's bytes were dropped
+    assert_eq!(gba.apu.get_ds_a().fifo_len(), FIFO_CAPACITY, "FIFO full");
+    assert_eq!(gba.fifo_stats()[0].overruns, 4, "dropped bytes");
+    assert_eq!(
+        gba.fifo_stats()[1],
+        FifoStats::default(),
+        "FIFO B untouched"
+    );
+}
+
+/// Scenario: A timer draining an empty FIFO replays the last sample and counts underruns
+#[test]
+fn direct_sound_fifo_underrun_holds_last_sample() {
+    // Given: FIFO A on timer 0 at full volume, holding one word of samples
+    let mut gba = Gba::new();
+    gba.write_byte(0x0400_0084, 0x80);
+    gba.write_half(0x0400_0082, 0x0304);
+    gba.write_word(0x0400_00A0, 0x4010_2030);
+
+    // When: timer 0 overflows every 256 cycles for well over four samples
+    gba.write_half(0x0400_0100, 0xFF00);
+    gba.write_half(0x0400_0102, 0x0080);
+    for _ in 0..3000 {
+        gba.step();
+    }
+
+    // Then: the FIFO ran dry, and the channel keeps its last sample (0x40)
+    let stats = gba.fifo_stats()[0];
+    assert!(stats.underruns > 0, "underruns: {:?}", stats);
+    assert_eq!(gba.apu.get_ds_a().fifo_len(), 0, "FIFO drained");
+    assert_eq!(gba.apu.get_ds_a().get_output(), 0x4000, "held sample");
+}
+
+/// Scenario: A half-empty FIFO pulls the next words from its sound DMA
+#[test]
+fn direct_sound_fifo_refills_from_sound_dma() {
+    // Given: a primed FIFO A on timer 0, fed by DMA1 in sound FIFO mode
+    // from EWRAM
+    let mut gba = Gba::new();
+    gba.write_byte(0x0400_0084, 0x80);
+    gba.write_half(0x0400_0082, 0x0304);
+    for _ in 0..8 {
+        gba.write_word(0x0400_00A0, 0x2222_2222);
+    }
+    for i in 0..256u32 {
+        gba.write_word(0x0200_0000 + i * 4, 0x1111_1111);
+    }
+    gba.write_word(0x0400_00BC, 0x0200_0000);
+    gba.write_word(0x0400_00C0, 0x0400_00A0);
+    gba.write_half(0x0400_00C4, 4);
+    gba.write_half(0x0400_00C6, 0xB640);
+
+    // When: timer 0 clocks samples out for a while
+    gba.write_half(0x0400_0100, 0xFF00);
+    gba.write_half(0x0400_0102, 0x0080);
+    while gba.cycles_elapsed() < 40_000 {
+        gba.step();
+    }
+
+    // Then: DMA data is playing, and nothing underran or overflowed
+    assert_eq!(gba.apu.get_ds_a().get_output(), 0x1100, "sample from DMA");
+    assert_eq!(gba.fifo_stats()[0], FifoStats::default(), "FIFO A stats");
+    assert!(
+        gba.apu.get_ds_a().fifo_len() > 0,
+        "FIFO still holds samples"
+    );
+}
+
+/// Scenario: Sound DMA keeps feeding the same FIFO whatever its dest control says
+#[test]
+fn sound_dma_destination_stays_on_the_fifo() {
+    // Given: FIFO A fed by DMA1 with dest control 3 (increment/reload),
+    // 16-bit width and a count of 0, streaming a rising ramp from EWRAM
+    let mut gba = Gba::new();
+    gba.write_byte(0x0400_0084, 0x80);
+    gba.write_half(0x0400_0082, 0x0304);
+    for _ in 0..8 {
+        gba.write_word(0x0400_00A0, 0);
+    }
+    for i in 0..256u32 {
+        gba.write_word(0x0200_0000 + i * 4, i * 0x0101_0101);
+    }
+    gba.write_word(0x0400_00BC, 0x0200_0000);
+    gba.write_word(0x0400_00C0, 0x0400_00A0);
+    gba.write_half(0x0400_00C4, 0);
+    gba.write_half(0x0400_00C6, 0xB260);
+
+    // When: timer 0 clocks samples out for a while
+    gba.write_half(0x0400_0100, 0xFF00);
+    gba.write_half(0x0400_0102, 0x0080);
+    while gba.cycles_elapsed() < 40_000 {
+        gba.step();
+    }
+
+    // Then: only FIFO A received data, four words per request
+    assert_eq!(gba.fifo_stats()[0], FifoStats::default(), "FIFO A stats");
+    assert_eq!(gba.apu.get_ds_b().fifo_len(), 0, "FIFO B untouched");
+    assert_eq!(gba.dma[0].get_src_addr(), 0, "DMA0 registers untouched");
+
+    // And: the source advanced through the ramp between requests
+    assert!(gba.apu.get_ds_a().get_output() >> 8 > 4, "later ramp samples play");
+}
+
+/// Scenario: The oscilloscope ring returns each channel's recent outputs in order
+#[test]
+fn recent_samples_returns_per_channel_history() {
+    // Given: a powered APU with Direct Sound A at full volume
+    let mut apu = Apu::new();
+    apu.set_master_enabled(true);
+    apu.write_soundcnt_h(0x0004);
+    apu.get_ds_a().write_fifo(0x7F00_8040);
+
+    // When: each sample plays for one 32768 Hz sample period
+    for _ in 0..4 {
+        apu.get_ds_a().read_sample();
+        apu.step(CYCLES_PER_SAMPLE);
+    }
+
+    // Then: the scope holds them oldest first, and idle channels stay flat
+    assert_eq!(
+        apu.recent_samples(Channel::DirectSoundA, 4),
+        vec![0x4000, -0x8000, 0, 0x7F00],
+        "Direct Sound A history"
+    );
+    assert_eq!(
+        apu.recent_samples(Channel::Square1, 4),
+        vec![0; 4],
+        "square 1 history"
+    );
+    assert_eq!(
+        apu.recent_samples(Channel::Noise, usize::MAX).len(),
+        SCOPE_LEN,
+        "request clamped to the ring size"
+    );
+}
+
+/// Program that streams the table at 0x0800_0100 through DMA1 into FIFO A,
+/// one byte per timer 0 overflow at 16384 Hz, then spins
+const SQUARE_WAVE_PROGRAM: [u32; 19] = [
+    0xE3A00301, // mov r0, #0x04000000
+    0xE3A01080, // mov r1, #0x80
+    0xE1C018B4, // strh r1, [r0, #0x84]   ; SOUNDCNT_X: master enable
+    0xE3A01C0B, // mov r1, #0x0B00
+    0xE3811004, // orr r1, r1, #4
+    0xE1C018B2, // strh r1, [r0, #0x82]   ; SOUNDCNT_H: FIFO A full volume, both sides, timer 0
+    0xE28F10E0, // add r1, pc, #0xE0      ; r1 = 0x08000100
+    0xE58010BC, // str r1, [r0, #0xBC]    ; DMA1SAD
+    0xE28010A0, // add r1, r0, #0xA0
+    0xE58010C0, // str r1, [r0, #0xC0]    ; DMA1DAD = FIFO A
+    0xE3A01CB6, // mov r1, #0xB600
+    0xE3811040, // orr r1, r1, #0x40
+    0xE1C01CB6, // strh r1, [r0, #0xC6]   ; DMA1CNT_H: enable, sound FIFO, repeat, 32-bit
+    0xE2802C01, // add r2, r0, #0x100
+    0xE3A01CFC, // mov r1, #0xFC00
+    0xE1C210B0, // strh r1, [r2]          ; TM0CNT_L: reload 0xFC00, 1024 cycles per sample
+    0xE3A01080, // mov r1, #0x80
+    0xE1C210B2, // strh r1, [r2, #2]      ; TM0CNT_H: start
+    0xEAFFFFFE, // b .
+];
+
+/// Samples per half period of the streamed square wave
+const SQUARE_HALF_PERIOD: usize = 32;
+
+fn square_wave_rom() -> Vec<u8> {
+    let mut rom = vec![0u8; 0x100];
+    for (i, word) in SQUARE_WAVE_PROGRAM.iter().enumerate() {
+        rom[i * 4..i * 4 + 4].copy_from_slice(&word.to_le_bytes());
+    }
+    // Enough table for the DMA never to run off the end during the test
+    rom.extend((0..0x2000).map(|i| {
+        if (i / SQUARE_HALF_PERIOD) % 2 == 0 {
+            0x40u8
+        } else {
+            0xC0
+        }
+    }));
+    rom
+}
+
+/// Scenario: A ROM streaming a square wave through DMA1 and timer 0 plays it at pitch
+#[test]
+fn fifo_dma_square_wave_plays_at_expected_frequency() {
+    // Given: a ROM feeding a 64-sample square wave into FIFO A at 16384 Hz,
+    // which should sound at 16384 / 64 = 256 Hz
+    let mut gba = Gba::new();
+    gba.set_audio_sample_rate(NATIVE_SAMPLE_RATE);
+    gba.load_rom(square_wave_rom());
+
+    // When: it runs for several frames, skipping the first while it starts up
+    gba.run_frame();
+    let mut discard = Vec::new();
+    gba.drain_audio(&mut discard);
+    for _ in 0..4 {
+        gba.run_frame();
+    }
+    let mut samples = Vec::new();
+    gba.drain_audio(&mut samples);
+
+    // Then: the left channel rises through its midpoint once per period
+    let left: Vec<i32> = samples.iter().step_by(2).map(|&s| s as i32).collect();
+    let (lo, hi) = (*left.iter().min().unwrap(), *left.iter().max().unwrap());
+    assert!(hi - lo > 1000, "audible swing: {}..{}", lo, hi);
+    let mid = (lo + hi) / 2;
+    let rises: Vec<usize> = left
+        .windows(2)
+        .enumerate()
+        .filter(|(_, w)| w[0] < mid && w[1] >= mid)
+        .map(|(i, _)| i)
+        .collect();
+    assert!(rises.len() >= 10, "enough periods to measure: {}", rises.len());
+    let period = (rises[rises.len() - 1] - rises[0]) as f64 / (rises.len() - 1) as f64;
+    let frequency = NATIVE_SAMPLE_RATE as f64 / period;
+    assert!(
+        (frequency - 256.0).abs() < 2.0,
+        "square wave at {:.1} Hz",
+        frequency
+    );
+}
+
+/// Scenario: Fast-forward fits the sound to wall time, pitched up or not
+#[test]
+fn speed_scales_audio_with_or_without_pitch() {
+    for (pitch, period) in [(AudioPitch::Scaled, 64), (AudioPitch::Corrected, 128)] {
+        // Given: the square wave ROM, a 128-sample period at the native rate,
+        // fast-forwarded to 200%
+        let mut gba = Gba::new();
+        gba.set_audio_sample_rate(NATIVE_SAMPLE_RATE);
+        gba.set_audio_pitch(pitch);
+        gba.set_speed(2.0);
+        gba.load_rom(square_wave_rom());
+
+        // When: it runs ten frames after starting up
+        gba.run_frame();
+        gba.drain_audio(&mut Vec::new());
+        for _ in 0..10 {
+            gba.run_frame();
+        }
+        let mut samples = Vec::new();
+        gba.drain_audio(&mut samples);
+
+        // Then: the frames yield half the usual output, within one 20 ms
+        // grain still waiting to be stretched, for half the time
+        let expected = 10.0 * NATIVE_SAMPLE_RATE as f64 / 59.7275 / 2.0;
+        let produced = (samples.len() / 2) as f64;
+        assert!(
+            (produced - expected).abs() < NATIVE_SAMPLE_RATE as f64 / 50.0 / 2.0,
+            "{:?}: {} samples in ten frames",
+            pitch,
+            produced
+        );
+        let ms = gba.frame_duration().as_secs_f64() * 1000.0;
+        assert!((ms - 8.37).abs() < 0.01, "{:?}: {:.3} ms per frame", pitch, ms);
+
+        // And: the wave's period shows whether the pitch followed the speed
+        let left: Vec<i32> = samples.iter().step_by(2).map(|&s| s as i32).collect();
+        let mid = (left.iter().min().unwrap() + left.iter().max().unwrap()) / 2;
+        let rises: Vec<usize> = left
+            .windows(2)
+            .enumerate()
+            .filter(|(_, w)| w[0] < mid && w[1] >= mid)
+            .map(|(i, _)| i)
+            .collect();
+        let mut spacing: Vec<usize> = rises.windows(2).map(|w| w[1] - w[0]).collect();
+        spacing.sort_unstable();
+        let median = spacing[spacing.len() / 2];
+        assert!(
+            median.abs_diff(period) <= 1,
+            "{:?}: period of {} samples",
+            pitch,
+            median
+        );
+    }
+
+    // And: requests outside 25%-400% are clamped
+    let mut gba = Gba::new();
+    gba.set_speed(10.0);
+    assert_eq!(gba.speed(), 4.0, "fastest");
+    gba.set_speed(0.0);
+    assert_eq!(gba.speed(), 0.25, "slowest");
+}
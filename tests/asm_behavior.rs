@@ -0,0 +1,134 @@
+//! Behavior Driven Development tests for the runtime assembler
+//!
+//! These tests describe turning assembly source into code the emulator runs.
+
+use rgba::{asm, disassemble_arm, disassemble_thumb, Gba};
+
+/// Scenario: Disassembler output assembles back to the same ARM opcode
+#[test]
+fn arm_instructions_round_trip_through_the_disassembler() {
+    // Given: instructions as the disassembler prints them
+    let lines = [
+        "add r0, r1, r2",
+        "subne r3, r4, #0x3FC",
+        "movs r0, r1, lsr #32",
+        "orr r0, r1, r2, ror r3",
+        "mlas r0, r1, r2, r3",
+        "umull r0, r1, r2, r3",
+        "ldr r0, [r1, #-0x4]!",
+        "ldreqb r0, [r1]",
+        "strb r0, [r1, r2, lsl #2]",
+        "strh r0, [r1], -r2",
+        "ldrsb r0, [r1, #0xFF]",
+        "stmdb sp!, {r4, lr}",
+        "ldmia sp, {r0, pc}^",
+        "swpb r0, r1, [r2]",
+        "bx lr",
+        "swi #0x50000",
+        "msr cpsr_f, #0xF0000000",
+        "bleq 0x07FFFFF0",
+    ];
+
+    for text in lines {
+        // When: each is assembled at the start of ROM
+        let code = asm::assemble(0x0800_0000, text).unwrap();
+        let op = u32::from_le_bytes(code[..4].try_into().unwrap());
+
+        // Then: disassembling the opcode gives the same text
+        assert_eq!(disassemble_arm(0x0800_0000, op), text, "{:08X}", op);
+    }
+    let add = asm::assemble(0, "add r0, r1, r2").unwrap();
+    assert_eq!(add, 0xE081_0002u32.to_le_bytes(), "add r0, r1, r2");
+}
+
+/// Scenario: Disassembler output assembles back to the same Thumb opcode
+#[test]
+fn thumb_instructions_round_trip_through_the_disassembler() {
+    // Given: Thumb instructions as the disassembler prints them
+    let lines = [
+        "lsls r0, r1, #2",
+        "subs r0, r1, #7",
+        "adds r2, #0x80",
+        "negs r0, r1",
+        "mov r8, r0",
+        "cmp r0, r9",
+        "ldsb r0, [r1, r2]",
+        "strh r0, [r1, #0x3E]",
+        "ldr r0, [sp, #0x3FC]",
+        "add r0, pc, #0x10",
+        "add sp, #-0x10",
+        "pop {r4, pc}",
+        "ldmia r7!, {r0, r7}",
+        "beq 0x08000010",
+        "b 0x07FFFF00",
+    ];
+
+    for text in lines {
+        // When: each is assembled in Thumb state
+        let code = asm::assemble(0x0800_0000, &format!(".thumb\n{}", text)).unwrap();
+        let op = u16::from_le_bytes([code[0], code[1]]);
+
+        // Then: disassembling the opcode gives the same text
+        assert_eq!(disassemble_thumb(0x0800_0000, op), text, "{:04X}", op);
+    }
+    let bl = asm::assemble(0x0800_0000, ".thumb\nbl 0x08001000").unwrap();
+    assert_eq!(bl, [0x00, 0xF0, 0xFE, 0xFF], "BL is a pair of halfwords");
+}
+
+/// Scenario: Labels, literals and a switch to Thumb run as written
+#[test]
+fn assembled_program_runs_on_the_emulator() {
+    // Given: a program that stores a literal, enters Thumb and counts to ten
+    let rom = asm::assemble(
+        0x0800_0000,
+        "
+            ldr r0, =0x03000000
+            ldr r1, =0x12345678
+            str r1, [r0]
+            ldr r2, =count + 1      @ Thumb entry
+            bx r2
+            .pool
+
+            .thumb
+        count:
+            movs r3, #0
+        loop:
+            adds r3, #1
+            cmp r3, #10
+            bne loop
+            ldr r4, [r0]
+        done:
+            b done
+        ",
+    )
+    .unwrap();
+
+    // When: it runs for a frame
+    let mut gba = Gba::new();
+    gba.load_rom(rom);
+    gba.run_frame();
+
+    // Then: every part did its job
+    assert_eq!(gba.cpu.get_reg(3), 10, "Thumb loop counted to ten");
+    assert_eq!(gba.cpu.get_reg(4), 0x1234_5678, "Literal stored and read back");
+    assert!(gba.cpu.is_thumb_mode(), "BX switched to Thumb");
+}
+
+/// Scenario: Mistakes are reported with their line
+#[test]
+fn errors_name_the_offending_line() {
+    // Given / When: sources with an unencodable immediate and a missing label
+    let immediate = asm::assemble(0, "mov r0, #0x101").unwrap_err();
+    let label = asm::assemble(0, "nop\n  b nowhere").unwrap_err();
+    let unknown = asm::assemble(0, ".thumb\n\nmla r0, r1, r2, r3").unwrap_err();
+
+    // Then: each error points at the line and says what is wrong
+    assert_eq!(immediate.line, 1, "Immediate error line");
+    assert!(immediate.message.contains("cannot be encoded"), "{}", immediate);
+    assert_eq!(label.to_string(), "line 2: unknown label `nowhere`", "Label error");
+    assert_eq!(unknown.line, 3, "No MLA in Thumb");
+
+    // And: immediates with an inverted or negated form still assemble
+    let mvn = asm::assemble(0, "mov r0, #0xFFFFFF00").unwrap();
+    assert_eq!(mvn, 0xE3E0_00FFu32.to_le_bytes(), "mov becomes mvn");
+}
@@ -4,7 +4,8 @@
 //! following BDD principles: tests describe behavior in a readable,
 //! declarative manner.
 
-use rgba::{Cpu, Memory};
+use rgba::{asm, Cpu, Memory, Mode, RegisterBank, UndefinedInstruction};
+use std::sync::{Arc, Mutex};
 
 /// Scenario: CPU initializes in a known state
 #[test]
@@ -143,6 +144,76 @@ fn arm_memory_instructions_read_and_write_memory() {
     );
 }
 
+/// Scenario: Halfword transfers follow every P/U/W/I addressing combination
+#[test]
+fn halfword_transfers_cover_addressing_mode_3() {
+    const BASE: u32 = 0x0200_0100;
+    let at = |delta: i32| BASE.wrapping_add(delta as u32);
+
+    for bits in 0..16u32 {
+        let (pre, up, imm, wb) = (bits & 8 != 0, bits & 4 != 0, bits & 2 != 0, bits & 1 != 0);
+        let offset = if imm { 4 } else { 2 }; // #4, or r2 = 4
+        let mode = (pre as u32) << 24 | (up as u32) << 23 | (imm as u32) << 22 | (wb as u32) << 21;
+        let moved = if up { at(4) } else { at(-4) };
+        let addr = if pre { moved } else { BASE };
+        let rn_after = if !pre || wb { moved } else { BASE };
+        let case = format!("P={} U={} I={} W={}", pre as u8, up as u8, imm as u8, wb as u8);
+
+        for load in [false, true] {
+            // Given: r0 as base, r2 = 4 and distinct halfwords around it
+            let mut cpu = Cpu::new();
+            let mut mem = Memory::new();
+            let opcode = 0xE000_10B0 | mode | (load as u32) << 20 | offset;
+            mem.load_rom(opcode.to_le_bytes().to_vec());
+            for (delta, val) in [(-4, 0x1111u16), (0, 0x2222), (4, 0x3333)] {
+                mem.write_half(at(delta), val);
+            }
+            cpu.set_reg(0, BASE);
+            cpu.set_reg(1, 0xBEEF);
+            cpu.set_reg(2, 4);
+            cpu.set_pc(0x0800_0000);
+
+            // When: LDRH/STRH r1 runs
+            cpu.step(&mut mem);
+
+            // Then: the right address was used and the base written back
+            // exactly for post-indexing or W
+            let name = if load { "LDRH" } else { "STRH" };
+            if load {
+                assert_eq!(cpu.get_reg(1), mem.read_half(addr) as u32, "{} {}", name, case);
+            } else {
+                assert_eq!(mem.read_half(addr), 0xBEEF, "{} {}", name, case);
+            }
+            assert_eq!(cpu.get_reg(0), rn_after, "Rn after {} {}", name, case);
+        }
+    }
+
+    // And: signed loads subtract a register offset too, and an odd
+    // immediate shift on a data processing op is not mistaken for one
+    let program = asm::assemble(
+        0x0800_0000,
+        "ldrsh r3, [r0, -r2]
+         ldrsb r4, [r0], -r2
+         subs r5, r2, r2, asr #3",
+    )
+    .unwrap();
+    let mut cpu = Cpu::new();
+    let mut mem = Memory::new();
+    mem.load_rom(program);
+    mem.write_half(at(-4), 0x8001);
+    mem.write_half(at(0), 0x0080);
+    cpu.set_reg(0, BASE);
+    cpu.set_reg(2, 4);
+    cpu.set_pc(0x0800_0000);
+    cpu.step(&mut mem);
+    assert_eq!(cpu.get_reg(3), 0xFFFF_8001, "LDRSH with a negative register offset");
+    cpu.step(&mut mem);
+    assert_eq!(cpu.get_reg(4), 0xFFFF_FF80, "LDRSB post-indexed");
+    assert_eq!(cpu.get_reg(0), at(-4), "Post-index subtracted Rm");
+    cpu.step(&mut mem);
+    assert_eq!(cpu.get_reg(5), 4, "SUBS with ASR #3 is data processing");
+}
+
 /// Scenario: CPU correctly handles arithmetic flags
 #[test]
 fn cpu_sets_arithmetic_flags_based_on_operations() {
@@ -170,24 +241,161 @@ fn cpu_sets_arithmetic_flags_based_on_operations() {
     assert_eq!(cpu.get_reg(0), 0, "Result should wrap to zero");
 }
 
+/// Scenario: Flag-setting logical operations take C from the barrel shifter
+#[test]
+fn logical_operations_set_carry_from_shifter() {
+    // Given: C set, and operands whose shifted-out bit differs from the
+    // bits above it
+    let mut cpu = Cpu::new();
+    let mut mem = Memory::new();
+    let program = asm::assemble(
+        0x0800_0000,
+        "
+            movs r0, r1, lsl #2
+            mvns r4, #0x80000000
+            tst r1, r5, lsr r6
+            eors r7, r1, #1
+            ands r2, r1, r3, ror #4
+        ",
+    )
+    .unwrap();
+    mem.load_rom(program);
+    cpu.set_reg(1, 0x8000_0000);
+    cpu.set_reg(3, 0x0000_0008);
+    cpu.set_reg(5, 0x7FFF_FFFF);
+    cpu.set_reg(6, 32);
+    cpu.set_pc(0x0800_0000);
+    cpu.set_flag_c(true);
+
+    // When/Then: each instruction leaves C as its shifter operand dictates
+    cpu.step(&mut mem);
+    assert_eq!(cpu.get_reg(0), 0, "MOVS LSL #2 shifts bit 31 out");
+    assert!(!cpu.get_flag_c(), "LSL #2 carries out bit 30 only");
+    assert!(cpu.get_flag_z(), "MOVS sets Z for a zero result");
+
+    cpu.step(&mut mem);
+    assert_eq!(cpu.get_reg(4), 0x7FFF_FFFF, "MVNS of a rotated immediate");
+    assert!(cpu.get_flag_c(), "Rotated immediate carries out its bit 31");
+
+    cpu.step(&mut mem);
+    assert!(!cpu.get_flag_c(), "LSR by 32 carries out bit 31");
+    assert!(cpu.get_flag_z(), "TST of disjoint bits sets Z");
+
+    cpu.step(&mut mem);
+    assert!(!cpu.get_flag_c(), "Unrotated immediate leaves C alone");
+
+    cpu.step(&mut mem);
+    assert_eq!(cpu.get_reg(2), 0x8000_0000, "ANDS with ROR #4");
+    assert!(cpu.get_flag_c(), "ROR carries out the new bit 31");
+    assert!(cpu.get_flag_n(), "ANDS sets N from the result");
+
+    // And: Thumb ROR by a multiple of 32 keeps the value but sets C from bit 31
+    let (mut cpu, mut mem) = thumb_cpu(&[0x41C8]); // ROR r0, r1
+    cpu.set_reg(0, 0x8000_0001);
+    cpu.set_reg(1, 32);
+    cpu.step(&mut mem);
+    assert_eq!(cpu.get_reg(0), 0x8000_0001, "ROR by 32 leaves the value");
+    assert!(cpu.get_flag_c(), "ROR by 32 carries out bit 31");
+}
+
+/// Scenario: Shifts by register handle amounts of 0, 32 and over, and RRX
+#[test]
+fn register_specified_shifts_cover_edge_amounts() {
+    // (instruction, Rm, Rs, C before, result, C after)
+    let cases = [
+        ("movs r0, r1, lsl r2", 0x8000_0001, 0, true, 0x8000_0001, true),
+        ("movs r0, r1, lsl r2", 0x0000_0003, 32, false, 0, true),
+        ("movs r0, r1, lsl r2", 0xFFFF_FFFF, 33, true, 0, false),
+        ("movs r0, r1, lsl r2", 0x0000_0001, 0x101, true, 2, false),
+        ("movs r0, r1, lsr r2", 0x8000_0000, 32, false, 0, true),
+        ("movs r0, r1, lsr r2", 0xFFFF_FFFF, 40, true, 0, false),
+        ("movs r0, r1, asr r2", 0x8000_0000, 40, false, 0xFFFF_FFFF, true),
+        ("movs r0, r1, asr r2", 0x4000_0000, 32, true, 0, false),
+        ("movs r0, r1, ror r2", 0x8000_0001, 32, false, 0x8000_0001, true),
+        ("movs r0, r1, ror r2", 0x0000_0001, 33, false, 0x8000_0000, true),
+        ("movs r0, r1, rrx", 0x0000_0003, 0, true, 0x8000_0001, true),
+        ("movs r0, r1, rrx", 0x0000_0002, 0, false, 0x0000_0001, false),
+    ];
+
+    for (source, rm, rs, carry_in, result, carry_out) in cases {
+        // Given: the operands and carry in
+        let mut cpu = Cpu::new();
+        let mut mem = Memory::new();
+        mem.load_rom(asm::assemble(0x0800_0000, source).unwrap());
+        cpu.set_reg(1, rm);
+        cpu.set_reg(2, rs);
+        cpu.set_flag_c(carry_in);
+        cpu.set_pc(0x0800_0000);
+
+        // When: the instruction runs
+        cpu.step(&mut mem);
+
+        // Then: the shifter produced the value and carry-out
+        assert_eq!(cpu.get_reg(0), result, "{} with {:#x} by {}", source, rm, rs);
+        assert_eq!(cpu.get_flag_c(), carry_out, "{} carry with {:#x} by {}", source, rm, rs);
+    }
+
+    // And: a register shift costs an internal cycle and reads PC 12 ahead
+    let mut cpu = Cpu::new();
+    let mut mem = Memory::new();
+    let program = asm::assemble(0x0800_0000, "mov r0, pc, lsl r2\nmov r3, r1, lsl #1").unwrap();
+    mem.load_rom(program);
+    cpu.set_pc(0x0800_0000);
+    assert_eq!(cpu.step(&mut mem), 2, "Shift by register: 1S + 1I");
+    assert_eq!(cpu.get_reg(0), 0x0800_000C, "PC as Rm reads 12 ahead");
+    assert_eq!(cpu.step(&mut mem), 1, "Shift by immediate: 1S");
+
+    // And: the Thumb shift-by-register ALU ops take the same extra cycle
+    let (mut cpu, mut mem) = thumb_cpu(&[0x4088, 0x4008]); // LSL r0, r1; AND r0, r1
+    assert_eq!(cpu.step(&mut mem), 2, "Thumb LSL by register: 1S + 1I");
+    assert_eq!(cpu.step(&mut mem), 1, "Thumb AND: 1S");
+}
+
 /// Scenario: CPU handles multiply instructions
 #[test]
 fn cpu_multiply_instructions_perform_correct_calculations() {
+    // Given: a program of multiplies over small, negative and large operands
     let mut cpu = Cpu::new();
     let mut mem = Memory::new();
-
-    // Given: Registers with multiplicands
+    let program = asm::assemble(
+        0x0800_0000,
+        "
+            mul r2, r0, r1
+            mla r3, r0, r1, r2
+            muls r4, r0, r5
+            mul r6, r0, r7
+            umull r8, r9, r0, r7
+            smlal r8, r9, r0, r5
+        ",
+    )
+    .unwrap();
+    mem.load_rom(program);
     cpu.set_reg(0, 100);
     cpu.set_reg(1, 25);
-    cpu.set_reg(2, 0); // Will be destination
+    cpu.set_reg(5, -3i32 as u32);
+    cpu.set_reg(7, 0x0100_0000);
     cpu.set_pc(0x0800_0000);
 
-    // When: MUL R2, R0, R1 (0xE000_0291) - Multiply R0 * R1, store in R2
-    mem.write_word(0x0800_0000, 0xE000_0291);
-    cpu.step(&mut mem);
+    // When: each instruction runs
+    let cycles: Vec<u32> = (0..6).map(|_| cpu.step(&mut mem)).collect();
+
+    // Then: results and flags follow the operands
+    assert_eq!(cpu.get_reg(2), 2500, "MUL: R0 * R1");
+    assert_eq!(cpu.get_reg(3), 5000, "MLA: R0 * R1 + R2");
+    assert_eq!(cpu.get_reg(4), -300i32 as u32, "MULS with a negative operand");
+    assert!(cpu.get_flag_n(), "MULS sets N for a negative product");
+    assert!(!cpu.get_flag_z(), "MULS clears Z for a non-zero product");
+    assert_eq!(cpu.get_reg(6), 0x6400_0000, "MUL keeps the low 32 bits");
+    let product = ((cpu.get_reg(9) as u64) << 32) | cpu.get_reg(8) as u64;
+    assert_eq!(product, 100 * 0x0100_0000 - 300, "UMULL then SMLAL accumulate 64 bits");
 
-    // Then: Result should be product (not implemented yet, so we skip)
-    // assert_eq!(cpu.get_reg(2), 2500, "R2 should equal R0 * R1");
+    // And: the multiplier stops early on small or sign-extended operands
+    assert_eq!(cycles[0], 2, "MUL by one byte: 1S + 1I");
+    assert_eq!(cycles[1], 3, "MLA adds an I cycle");
+    assert_eq!(cycles[2], 2, "Negative operand with all-ones top bytes");
+    assert_eq!(cycles[3], 5, "Operand using the top byte: 1S + 4I");
+    assert_eq!(cycles[4], 6, "UMULL adds an I cycle");
+    assert_eq!(cycles[5], 4, "SMLAL by a small negative: 1S + 3I");
 }
 
 /// Scenario: CPU mode switching works
@@ -239,3 +447,727 @@ fn cpu_reset_clears_state_and_prepares_for_execution() {
     assert_eq!(cpu.is_thumb_mode(), false, "Should be in ARM mode");
     assert_eq!(cpu.get_flag_c(), false, "Flags should be clear");
 }
+
+/// Scenario: Misaligned Thumb stores force-align and halfword loads rotate
+#[test]
+fn thumb_misaligned_accesses_follow_alignment_rules() {
+    let mut cpu = Cpu::new();
+    let mut mem = Memory::new();
+
+    // Given: an odd base address and a ROM of halfword/word accesses through it
+    let program: [u16; 4] = [
+        0x8001, // STRH r1, [r0]
+        0x8802, // LDRH r2, [r0]
+        0x5F03, // LDRSH r3, [r0, r4]
+        0x6001, // STR r1, [r0]
+    ];
+    let mut rom = vec![0u8; 0x400];
+    for (i, op) in program.iter().enumerate() {
+        rom[i * 2..i * 2 + 2].copy_from_slice(&op.to_le_bytes());
+    }
+    mem.load_rom(rom);
+    cpu.set_reg(0, 0x0200_0001);
+    cpu.set_reg(1, 0x1234_8765);
+    cpu.set_reg(4, 0);
+    cpu.set_thumb_mode(true);
+    cpu.set_pc(0x0800_0000);
+
+    // When: STRH runs
+    cpu.step(&mut mem);
+
+    // Then: it wrote the aligned halfword
+    assert_eq!(mem.read_half(0x0200_0000), 0x8765, "STRH force-aligns");
+
+    // When: LDRH and LDRSH read back through the odd address
+    cpu.step(&mut mem);
+    cpu.step(&mut mem);
+
+    // Then: LDRH rotates and LDRSH sign-extends the addressed byte
+    assert_eq!(cpu.get_reg(2), 0x6500_0087, "LDRH rotates by 8");
+    assert_eq!(cpu.get_reg(3), 0xFFFF_FF87, "LDRSH loads a signed byte");
+
+    // When: STR runs
+    cpu.step(&mut mem);
+
+    // Then: the whole word went to the aligned address
+    assert_eq!(mem.read_word(0x0200_0000), 0x1234_8765, "STR force-aligns");
+}
+
+/// Scenario: Thumb POP {PC} stays in Thumb state on ARMv4T; only BX interworks
+#[test]
+fn thumb_pop_pc_does_not_interwork() {
+    let mut cpu = Cpu::new();
+    let mut mem = Memory::new();
+
+    // Given: POP {PC} at 0x08000000, then MOV r0, #7 and BX r1 at 0x08000100
+    let mut rom = vec![0u8; 0x400];
+    rom[0..2].copy_from_slice(&0xBD00u16.to_le_bytes()); // POP {pc}
+    rom[0x100..0x102].copy_from_slice(&0x2007u16.to_le_bytes()); // MOV r0, #7
+    rom[0x102..0x104].copy_from_slice(&0x4708u16.to_le_bytes()); // BX r1
+    mem.load_rom(rom);
+    cpu.set_reg(1, 0x0800_0200);
+    cpu.set_reg(13, 0x0300_1000);
+    cpu.set_thumb_mode(true);
+    cpu.set_pc(0x0800_0000);
+
+    // When: the popped return address has bit 0 set, as a v5 epilogue would expect
+    mem.write_word(0x0300_1000, 0x0800_0101);
+    cpu.step(&mut mem);
+
+    // Then: bit 0 is ignored rather than selecting a state
+    assert_eq!(cpu.is_thumb_mode(), true, "POP {{PC}} stays in Thumb");
+    assert_eq!(
+        cpu.get_pc(),
+        0x0800_0100,
+        "Bit 0 is cleared from the target"
+    );
+    assert_eq!(
+        cpu.get_reg(13),
+        0x0300_1004,
+        "SP moves past the popped word"
+    );
+
+    // When: execution continues at the target
+    cpu.step(&mut mem);
+
+    // Then: it is decoded as Thumb
+    assert_eq!(cpu.get_reg(0), 7, "MOV r0, #7 ran as a Thumb instruction");
+
+    // When: POP {PC} loads an even address, which v5 would treat as ARM
+    cpu.set_reg(13, 0x0300_1000);
+    mem.write_word(0x0300_1000, 0x0800_0100);
+    cpu.set_pc(0x0800_0000);
+    cpu.step(&mut mem);
+
+    // Then: the CPU is still in Thumb state
+    assert_eq!(
+        cpu.is_thumb_mode(),
+        true,
+        "Even target does not switch to ARM"
+    );
+    assert_eq!(cpu.get_pc(), 0x0800_0100, "PC is the popped address");
+
+    // When: BX runs with an even target
+    cpu.step(&mut mem);
+    cpu.step(&mut mem);
+
+    // Then: BX switches to ARM
+    assert_eq!(cpu.is_thumb_mode(), false, "BX interworks");
+    assert_eq!(cpu.get_pc(), 0x0800_0200, "BX target");
+}
+
+/// Thumb CPU about to run `ops` from 0x08000100, with SP at 0x02000100
+fn thumb_cpu(ops: &[u16]) -> (Cpu, Memory) {
+    let mut cpu = Cpu::new();
+    let mut mem = Memory::new();
+    let mut rom = vec![0u8; 0x400];
+    for (i, op) in ops.iter().enumerate() {
+        rom[0x100 + i * 2..0x102 + i * 2].copy_from_slice(&op.to_le_bytes());
+    }
+    rom[0x108..0x10C].copy_from_slice(&0xCAFE_F00Du32.to_le_bytes());
+    mem.load_rom(rom);
+    cpu.set_reg(13, 0x0200_0100);
+    cpu.set_thumb_mode(true);
+    cpu.set_pc(0x0800_0100);
+    (cpu, mem)
+}
+
+/// Scenario: Every Thumb format decodes to its own operation
+#[test]
+fn thumb_formats_decode_by_prefix() {
+    // Given/When: one instruction of each format runs; Then: it has that format's effect
+    let (mut cpu, mut mem) = thumb_cpu(&[0x0088]); // LSL r0, r1, #2
+    cpu.set_reg(1, 3);
+    cpu.step(&mut mem);
+    assert_eq!(cpu.get_reg(0), 12, "Format 1: shift by immediate");
+
+    let (mut cpu, mut mem) = thumb_cpu(&[0x1888]); // ADD r0, r1, r2
+    cpu.set_reg(1, 3);
+    cpu.set_reg(2, 4);
+    cpu.step(&mut mem);
+    assert_eq!(cpu.get_reg(0), 7, "Format 2: add register");
+
+    let (mut cpu, mut mem) = thumb_cpu(&[0x2005]); // MOV r0, #5
+    cpu.step(&mut mem);
+    assert_eq!(cpu.get_reg(0), 5, "Format 3: move immediate");
+
+    let (mut cpu, mut mem) = thumb_cpu(&[0x4050]); // EOR r0, r2
+    cpu.set_reg(0, 6);
+    cpu.set_reg(2, 3);
+    cpu.step(&mut mem);
+    assert_eq!(cpu.get_reg(0), 5, "Format 4: ALU operation");
+
+    let (mut cpu, mut mem) = thumb_cpu(&[0x4688]); // MOV r8, r1
+    cpu.set_reg(1, 9);
+    cpu.step(&mut mem);
+    assert_eq!(cpu.get_reg(8), 9, "Format 5: hi register move");
+
+    let (mut cpu, mut mem) = thumb_cpu(&[0x4801]); // LDR r0, [pc, #4]
+    cpu.step(&mut mem);
+    assert_eq!(cpu.get_reg(0), 0xCAFE_F00D, "Format 6: PC-relative load");
+
+    let (mut cpu, mut mem) = thumb_cpu(&[0x5088]); // STR r0, [r1, r2]
+    cpu.set_reg(0, 0xAB);
+    cpu.set_reg(1, 0x0200_0000);
+    cpu.set_reg(2, 4);
+    cpu.step(&mut mem);
+    assert_eq!(
+        mem.read_word(0x0200_0004),
+        0xAB,
+        "Format 7: register offset store"
+    );
+
+    let (mut cpu, mut mem) = thumb_cpu(&[0x5E88]); // LDSH r0, [r1, r2]
+    mem.write_half(0x0200_0004, 0x8000);
+    cpu.set_reg(1, 0x0200_0000);
+    cpu.set_reg(2, 4);
+    cpu.step(&mut mem);
+    assert_eq!(
+        cpu.get_reg(0),
+        0xFFFF_8000,
+        "Format 8: sign-extended halfword"
+    );
+
+    let (mut cpu, mut mem) = thumb_cpu(&[0x6048]); // STR r0, [r1, #4]
+    cpu.set_reg(0, 0x1234);
+    cpu.set_reg(1, 0x0200_0000);
+    cpu.step(&mut mem);
+    assert_eq!(
+        mem.read_word(0x0200_0004),
+        0x1234,
+        "Format 9: immediate offset store"
+    );
+
+    let (mut cpu, mut mem) = thumb_cpu(&[0x8848]); // LDRH r0, [r1, #2]
+    mem.write_half(0x0200_0002, 0xBEEF);
+    cpu.set_reg(1, 0x0200_0000);
+    cpu.step(&mut mem);
+    assert_eq!(cpu.get_reg(0), 0xBEEF, "Format 10: halfword load");
+
+    let (mut cpu, mut mem) = thumb_cpu(&[0x9801]); // LDR r0, [sp, #4]
+    mem.write_word(0x0200_0104, 0x5555);
+    cpu.step(&mut mem);
+    assert_eq!(cpu.get_reg(0), 0x5555, "Format 11: SP-relative load");
+
+    let (mut cpu, mut mem) = thumb_cpu(&[0xA001]); // ADD r0, pc, #4
+    cpu.step(&mut mem);
+    assert_eq!(cpu.get_reg(0), 0x0800_0108, "Format 12: load address");
+
+    let (mut cpu, mut mem) = thumb_cpu(&[0xB082]); // SUB sp, #8
+    cpu.step(&mut mem);
+    assert_eq!(cpu.get_reg(13), 0x0200_00F8, "Format 13: add offset to SP");
+
+    let (mut cpu, mut mem) = thumb_cpu(&[0xB401, 0xBC02]); // PUSH {r0}; POP {r1}
+    cpu.set_reg(0, 0x77);
+    cpu.step(&mut mem);
+    cpu.step(&mut mem);
+    assert_eq!(cpu.get_reg(1), 0x77, "Format 14: push/pop");
+    assert_eq!(cpu.get_reg(13), 0x0200_0100, "Format 14: SP restored");
+
+    let (mut cpu, mut mem) = thumb_cpu(&[0xC105]); // STMIA r1!, {r0, r2}
+    cpu.set_reg(0, 1);
+    cpu.set_reg(1, 0x0200_0000);
+    cpu.set_reg(2, 2);
+    cpu.step(&mut mem);
+    assert_eq!(mem.read_word(0x0200_0004), 2, "Format 15: multiple store");
+    assert_eq!(cpu.get_reg(1), 0x0200_0008, "Format 15: writeback");
+
+    let (mut cpu, mut mem) = thumb_cpu(&[0xD0FC]); // BEQ -8
+    cpu.set_flag_z(true);
+    cpu.step(&mut mem);
+    assert_eq!(
+        cpu.get_pc(),
+        0x0800_00FC,
+        "Format 16: taken backward branch"
+    );
+
+    let (mut cpu, mut mem) = thumb_cpu(&[0xD1FC, 0x2001]); // BNE -8; MOV r0, #1
+    cpu.set_flag_z(true);
+    cpu.step(&mut mem);
+    cpu.step(&mut mem);
+    assert_eq!(cpu.get_reg(0), 1, "Format 16: not taken falls through");
+
+    let (mut cpu, mut mem) = thumb_cpu(&[0xDF06]); // SWI 0x06 (Div)
+    cpu.set_reg(0, 7);
+    cpu.set_reg(1, 2);
+    cpu.step(&mut mem);
+    assert_eq!(cpu.get_reg(0), 3, "Format 17: SWI, not a branch");
+
+    let (mut cpu, mut mem) = thumb_cpu(&[0xE7FC]); // B -8
+    cpu.step(&mut mem);
+    assert_eq!(cpu.get_pc(), 0x0800_00FC, "Format 18: unconditional branch");
+
+    let (mut cpu, mut mem) = thumb_cpu(&[0xF000, 0xF802]); // BL +4
+    cpu.step(&mut mem);
+    cpu.step(&mut mem);
+    assert_eq!(cpu.get_pc(), 0x0800_0108, "Format 19: long branch");
+    assert_eq!(
+        cpu.get_reg(14),
+        0x0800_0105,
+        "Format 19: LR holds the Thumb return"
+    );
+}
+
+/// Scenario: Thumb encodings outside the ARMv4T format table are undefined
+#[test]
+fn thumb_unused_encodings_take_undefined_exception() {
+    // Given: BEQ-space condition 0xE, the v5 BLX suffix, and an unused misc slot
+    for op in [0xDE00u16, 0xE800, 0xB100] {
+        let (mut cpu, mut mem) = thumb_cpu(&[op]);
+
+        // When: the opcode runs
+        cpu.step(&mut mem);
+
+        // Then: the CPU enters Undefined mode at vector 0x04 in ARM state
+        assert_eq!(cpu.get_mode(), Mode::Undefined, "{:04X} is undefined", op);
+        assert_eq!(cpu.get_pc(), 0x0000_0004, "{:04X} jumps to the vector", op);
+        assert_eq!(cpu.is_thumb_mode(), false, "{:04X} enters ARM state", op);
+        assert_eq!(
+            cpu.get_reg(14),
+            0x0800_0102,
+            "{:04X} LR is the next Thumb opcode",
+            op
+        );
+    }
+}
+
+/// Scenario: Undefined and coprocessor ARM encodings trap to vector 0x04
+#[test]
+fn arm_undefined_encodings_take_undefined_exception() {
+    // Given: the undefined space, MCR, LDC and CDP, with a hook logging them
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    for op in [0xE600_0010u32, 0xEE01_0F10, 0xED90_0000, 0xEE00_0000] {
+        let mut cpu = Cpu::new();
+        let mut mem = Memory::new();
+        mem.load_rom(op.to_le_bytes().to_vec());
+        mem.poke_word(0x0000_0004, 0xE14F_0000); // MRS r0, SPSR at the vector
+        cpu.set_pc(0x0800_0000);
+        cpu.set_flag_c(true);
+        let cpsr = cpu.get_cpsr();
+        let log = Arc::clone(&seen);
+        cpu.set_undefined_hook(move |undef| log.lock().unwrap().push(undef));
+
+        // When: the opcode runs
+        cpu.step(&mut mem);
+
+        // Then: the CPU enters Undefined mode with the old CPSR saved
+        assert_eq!(cpu.get_mode(), Mode::Undefined, "{:08X} is undefined", op);
+        assert_eq!(cpu.get_pc(), 0x0000_0004, "{:08X} jumps to the vector", op);
+        assert_eq!(cpu.get_reg(14), 0x0800_0004, "{:08X} LR is the next opcode", op);
+        assert_ne!(cpu.get_cpsr() & 0x80, 0, "{:08X} IRQs are masked", op);
+        cpu.step(&mut mem);
+        assert_eq!(cpu.get_reg(0), cpsr, "{:08X} SPSR holds the old CPSR", op);
+    }
+
+    // And: the hook saw each opcode where it ran
+    let seen = seen.lock().unwrap();
+    assert_eq!(seen.len(), 4, "Hook called once per trap");
+    assert_eq!(
+        seen[1],
+        UndefinedInstruction {
+            address: 0x0800_0000,
+            opcode: 0xEE01_0F10,
+            thumb: false,
+        },
+        "MCR reported"
+    );
+
+    // And: an undefined opcode whose condition fails is skipped
+    let mut cpu = Cpu::new();
+    let mut mem = Memory::new();
+    mem.load_rom(0x0600_0010u32.to_le_bytes().to_vec()); // EQ with Z clear
+    cpu.set_pc(0x0800_0000);
+    cpu.step(&mut mem);
+    assert_eq!(cpu.get_mode(), Mode::System, "Condition failed, no trap");
+}
+
+/// Scenario: A Thumb BKPT without a debugger raises a prefetch abort
+#[test]
+fn thumb_bkpt_takes_prefetch_abort() {
+    // Given: BKPT #1 in Thumb state
+    let (mut cpu, mut mem) = thumb_cpu(&[0xBE01]);
+
+    // When: it runs
+    cpu.step(&mut mem);
+
+    // Then: the CPU enters Abort mode at vector 0x0C with LR = BKPT + 4
+    assert_eq!(cpu.get_mode(), Mode::Abort, "Abort mode");
+    assert_eq!(cpu.get_pc(), 0x0000_000C, "Prefetch abort vector");
+    assert_eq!(cpu.is_thumb_mode(), false, "ARM state");
+    assert_eq!(cpu.get_reg(14), 0x0800_0104, "LR is the BKPT address + 4");
+    assert_eq!(cpu.take_bkpt_hit(), None, "No debugger saw it");
+
+    // Given: a debugger trapping BKPT
+    let (mut cpu, mut mem) = thumb_cpu(&[0xBE01, 0x2001]); // BKPT #1; MOV r0, #1
+    cpu.set_bkpt_trap(true);
+
+    // When: it runs
+    cpu.step(&mut mem);
+
+    // Then: the hit is reported and execution continues in Thumb state
+    assert_eq!(cpu.take_bkpt_hit(), Some(0x0800_0100), "Hit reported once");
+    assert_eq!(cpu.take_bkpt_hit(), None, "Hit is consumed");
+    cpu.step(&mut mem);
+    assert_eq!(cpu.get_reg(0), 1, "Next instruction runs");
+}
+
+/// Scenario: LDM/STM with the S bit transfer user registers from IRQ mode
+#[test]
+fn block_transfer_s_bit_uses_user_bank() {
+    let mut cpu = Cpu::new();
+    let mut mem = Memory::new();
+
+    // Given: distinct user and IRQ stack pointers and link registers
+    let program: [u32; 2] = [
+        0xE8C0_6000, // STMIA r0, {sp, lr}^
+        0xE8D1_6000, // LDMIA r1, {sp, lr}^
+    ];
+    let mut rom = vec![0u8; 0x400];
+    for (i, op) in program.iter().enumerate() {
+        rom[i * 4..i * 4 + 4].copy_from_slice(&op.to_le_bytes());
+    }
+    mem.load_rom(rom);
+    cpu.set_mode(Mode::System);
+    cpu.set_sp(0x0300_7F00);
+    cpu.set_lr(0x0800_1234);
+    cpu.set_mode(Mode::Irq);
+    cpu.set_sp(0x0300_7FA0);
+    cpu.set_lr(0x0800_AAAA);
+    cpu.set_reg(0, 0x0200_0000);
+    cpu.set_reg(1, 0x0200_0100);
+    mem.write_word(0x0200_0100, 0x0300_7E00);
+    mem.write_word(0x0200_0104, 0x0800_5678);
+    cpu.set_pc(0x0800_0000);
+
+    // When: STM^ runs in IRQ mode
+    cpu.step(&mut mem);
+
+    // Then: the user SP and LR were stored
+    assert_eq!(mem.read_word(0x0200_0000), 0x0300_7F00, "User SP stored");
+    assert_eq!(mem.read_word(0x0200_0004), 0x0800_1234, "User LR stored");
+
+    // When: LDM^ runs in IRQ mode
+    cpu.step(&mut mem);
+
+    // Then: only the user bank changed
+    assert_eq!(cpu.get_sp(), 0x0300_7FA0, "IRQ SP untouched");
+    assert_eq!(cpu.get_lr(), 0x0800_AAAA, "IRQ LR untouched");
+    assert_eq!(cpu.banked_reg(Mode::User, 13), 0x0300_7E00, "User SP loaded");
+    cpu.set_mode(Mode::System);
+    assert_eq!(cpu.get_sp(), 0x0300_7E00, "System mode sees the loaded SP");
+    assert_eq!(cpu.get_lr(), 0x0800_5678, "System mode sees the loaded LR");
+}
+
+/// ARM CPU about to run `program` from 0x08000000 in `mode`, with `spsr`
+/// already loaded by an MSR
+fn arm_cpu_with_spsr(mode: Mode, spsr: u32, program: &[u32]) -> (Cpu, Memory) {
+    let mut cpu = Cpu::new();
+    let mut mem = Memory::new();
+    let mut rom = vec![0u8; 0x400];
+    rom[0..4].copy_from_slice(&0xE16F_F000u32.to_le_bytes()); // MSR SPSR_fsxc, r0
+    for (i, op) in program.iter().enumerate() {
+        rom[4 + i * 4..8 + i * 4].copy_from_slice(&op.to_le_bytes());
+    }
+    mem.load_rom(rom);
+    cpu.set_mode(mode);
+    cpu.set_reg(0, spsr);
+    cpu.set_pc(0x0800_0000);
+    cpu.step(&mut mem);
+    (cpu, mem)
+}
+
+/// Scenario: SUBS PC, LR and MOVS PC, LR return from an IRQ into Thumb code
+#[test]
+fn exception_return_restores_cpsr_from_spsr() {
+    for (op, lr, name) in [
+        (0xE25E_F004, 0x0800_0205, "SUBS PC, LR, #4"),
+        (0xE1B0_F00E, 0x0800_0201, "MOVS PC, LR"),
+    ] {
+        // Given: IRQ mode with its own SP, an SPSR for Thumb System mode with
+        // Z and C set, and LR pointing back at the interrupted code
+        let (mut cpu, mut mem) = arm_cpu_with_spsr(Mode::Irq, 0x6000_003F, &[op]);
+        cpu.set_mode(Mode::System);
+        cpu.set_sp(0x0300_7F00);
+        cpu.set_mode(Mode::Irq);
+        cpu.set_sp(0x0300_7FA0);
+        cpu.set_lr(lr);
+
+        // When: the handler returns
+        cpu.step(&mut mem);
+
+        // Then: CPSR is the saved one, banks switched and Thumb resumes
+        assert_eq!(
+            cpu.get_cpsr(),
+            0x6000_003F,
+            "{}: CPSR copied from SPSR",
+            name
+        );
+        assert_eq!(
+            cpu.get_mode(),
+            Mode::System,
+            "{}: back in System mode",
+            name
+        );
+        assert_eq!(
+            cpu.get_sp(),
+            0x0300_7F00,
+            "{}: System SP visible again",
+            name
+        );
+        assert_eq!(cpu.is_thumb_mode(), true, "{}: Thumb state restored", name);
+        assert_eq!(
+            cpu.are_interrupts_enabled(),
+            true,
+            "{}: IRQs unmasked",
+            name
+        );
+        assert_eq!(
+            cpu.next_instruction_pc(),
+            0x0800_0200,
+            "{}: resumes halfword aligned",
+            name
+        );
+    }
+}
+
+/// Scenario: LDM with PC and the S bit returns from an IRQ into Thumb code
+#[test]
+fn ldm_with_pc_and_s_bit_returns_from_exception() {
+    // Given: IRQ mode with SPSR for Thumb System mode, and a handler frame
+    // of r0 and the interrupted (Thumb) address on the IRQ stack
+    let (mut cpu, mut mem) = arm_cpu_with_spsr(
+        Mode::Irq,
+        0x2000_003F,
+        &[0xE8FD_8001], // LDMFD sp!, {r0, pc}^
+    );
+    cpu.set_mode(Mode::System);
+    cpu.set_sp(0x0300_7F00);
+    cpu.set_mode(Mode::Irq);
+    cpu.set_sp(0x0300_7FA0);
+    mem.write_word(0x0300_7FA0, 0x1234_5678);
+    mem.write_word(0x0300_7FA4, 0x0800_0201);
+
+    // When: the handler returns
+    cpu.step(&mut mem);
+
+    // Then: registers came from the IRQ stack and CPSR from SPSR
+    assert_eq!(cpu.get_reg(0), 0x1234_5678, "r0 restored");
+    assert_eq!(cpu.get_cpsr(), 0x2000_003F, "CPSR copied from SPSR");
+    assert_eq!(cpu.get_mode(), Mode::System, "Back in System mode");
+    assert_eq!(cpu.get_sp(), 0x0300_7F00, "System SP visible again");
+    cpu.set_mode(Mode::Irq);
+    assert_eq!(cpu.get_sp(), 0x0300_7FA8, "Writeback went to the IRQ SP");
+    cpu.set_mode(Mode::System);
+
+    // And: Thumb execution resumes at the halfword-aligned address
+    assert!(cpu.is_thumb_mode(), "Thumb state restored");
+    assert_eq!(cpu.next_instruction_pc(), 0x0800_0200, "Resumes halfword aligned");
+    cpu.step(&mut mem);
+    assert_eq!(cpu.next_instruction_pc(), 0x0800_0202, "Steps by halfwords");
+}
+
+/// Scenario: An exception return takes its flags from SPSR, not the ALU
+#[test]
+fn flag_setting_pc_write_keeps_spsr_flags() {
+    // Given: Supervisor mode with an SPSR for ARM System mode, flags clear
+    let (mut cpu, mut mem) = arm_cpu_with_spsr(
+        Mode::Supervisor,
+        0x0000_001F,
+        &[0xE09E_F001], // ADDS PC, LR, r1
+    );
+    cpu.set_lr(0x0800_0300);
+    cpu.set_reg(1, 0xFFFF_FF00);
+
+    // When: an ADDS that carries out writes PC
+    cpu.step(&mut mem);
+
+    // Then: the flags are the SPSR's and execution continues in ARM state
+    assert_eq!(cpu.get_cpsr(), 0x0000_001F, "No carry leaks into CPSR");
+    assert_eq!(cpu.get_mode(), Mode::System, "Back in System mode");
+    assert_eq!(
+        cpu.next_instruction_pc(),
+        0x0800_0200,
+        "Branched to the sum"
+    );
+}
+
+/// Scenario: ARMv5 BLX immediate traps as an undefined instruction
+#[test]
+fn arm_blx_immediate_takes_undefined_exception() {
+    let mut cpu = Cpu::new();
+    let mut mem = Memory::new();
+
+    // Given: a ROM starting with BLX #+0x40 (0xFA) in System mode
+    let mut rom = vec![0u8; 0x400];
+    rom[0..4].copy_from_slice(&0xFA00_0010u32.to_le_bytes());
+    mem.load_rom(rom);
+    cpu.set_mode(Mode::System);
+    cpu.set_pc(0x0800_0000);
+
+    // When: it executes
+    cpu.step(&mut mem);
+
+    // Then: the CPU entered the undefined vector instead of branching
+    assert_eq!(cpu.get_mode(), Mode::Undefined, "Undefined mode");
+    assert_eq!(cpu.next_instruction_pc(), 0x0000_0004, "Undefined vector");
+    assert_eq!(cpu.get_lr(), 0x0800_0004, "LR points past the opcode");
+    assert_eq!(cpu.is_thumb_mode(), false, "ARM state");
+    assert_eq!(cpu.are_interrupts_enabled(), false, "IRQs masked");
+}
+
+const MODES: [Mode; 7] = [
+    Mode::User,
+    Mode::Fiq,
+    Mode::Irq,
+    Mode::Supervisor,
+    Mode::Abort,
+    Mode::Undefined,
+    Mode::System,
+];
+
+/// Mode whose copy of register `n` `mode` sees
+fn register_owner(mode: Mode, n: usize) -> Mode {
+    match (mode, n) {
+        (Mode::Fiq, 8..=14) => Mode::Fiq,
+        (Mode::User | Mode::System, _) | (_, 0..=12) => Mode::User,
+        _ => mode,
+    }
+}
+
+fn register_tag(mode: Mode, n: usize) -> u32 {
+    (mode as u32) << 8 | n as u32
+}
+
+/// Scenario: Every mode keeps its own R8-R14 across any mode transition
+#[test]
+fn banked_registers_survive_every_mode_transition() {
+    let mut cpu = Cpu::new();
+
+    // Given: each mode's private copies hold a value naming their owner
+    for mode in MODES {
+        for n in 8..=14 {
+            if register_owner(mode, n) == mode {
+                cpu.set_banked_reg(mode, n, register_tag(mode, n));
+            }
+        }
+    }
+
+    for from in MODES {
+        for to in MODES {
+            // When: the CPU moves from one mode to another
+            cpu.set_mode(from);
+            cpu.set_mode(to);
+
+            // Then: the live registers are the new mode's, and every other
+            // mode's copies are untouched
+            for n in 8..=14 {
+                let expected = register_tag(register_owner(to, n), n);
+                assert_eq!(cpu.get_reg(n), expected, "r{} live after {:?} -> {:?}", n, from, to);
+                for mode in MODES {
+                    let expected = register_tag(register_owner(mode, n), n);
+                    assert_eq!(cpu.banked_reg(mode, n), expected, "{:?} r{}", mode, n);
+                }
+            }
+        }
+    }
+}
+
+/// Scenario: The register bank stores an SPSR only for exception modes
+#[test]
+fn register_bank_keeps_spsr_per_exception_mode() {
+    let mut bank = RegisterBank::new();
+
+    // When: every mode's SPSR is written
+    for mode in MODES {
+        bank.set_spsr(mode, mode as u32);
+    }
+
+    // Then: exception modes kept theirs, User and System have none
+    for mode in MODES {
+        let expected = match mode {
+            Mode::User | Mode::System => None,
+            _ => Some(mode as u32),
+        };
+        assert_eq!(bank.spsr(mode), expected, "{:?} SPSR", mode);
+    }
+    assert!(RegisterBank::shares(Mode::User, Mode::System, 13), "USR/SYS share SP");
+    assert!(!RegisterBank::shares(Mode::Irq, Mode::Fiq, 8), "FIQ banks R8");
+}
+
+/// Scenario: HLE BIOS calls with out-of-range arguments wrap instead of panicking
+#[test]
+fn hle_bios_calls_wrap_out_of_range_arguments() {
+    // Given: CpuFastSet filling four words from the top of the address space
+    let (mut cpu, mut mem) = thumb_cpu(&[0xDF0C]);
+    mem.write_word(0x0200_0000, 0x1234_5678);
+    cpu.set_reg(0, 0x0200_0000);
+    cpu.set_reg(1, 0xFFFF_FFF8);
+    cpu.set_reg(2, 1 << 24 | 4);
+
+    // When: the call runs
+    cpu.step(&mut mem);
+
+    // Then: the destination wraps to address 0 and the call returns
+    assert_eq!(cpu.get_pc(), 0x0800_0102, "CpuFastSet returned");
+
+    // Given: LZ77 data whose first back-reference reaches below address 0
+    let (mut cpu, mut mem) = thumb_cpu(&[0xDF11]);
+    mem.write_word(0x0200_0000, 4 << 8 | 0x10);
+    mem.write_byte(0x0200_0004, 0xFF);
+    mem.write_byte(0x0200_0005, 0x1F);
+    mem.write_byte(0x0200_0006, 0xFF);
+    cpu.set_reg(0, 0x0200_0000);
+    cpu.set_reg(1, 0x10);
+
+    // When: it is decompressed
+    cpu.step(&mut mem);
+
+    // Then: the window wraps around and all four bytes are produced
+    assert_eq!(cpu.get_reg(1), 0x14, "destination advanced by the size");
+    assert_eq!(cpu.get_reg(3), 4, "bytes written");
+
+    // Given: RL data with a one-byte literal run and a three-byte repeat,
+    // both stored with a zero length field
+    let (mut cpu, mut mem) = thumb_cpu(&[0xDF14]);
+    mem.write_word(0x0200_0000, 4 << 8 | 0x30);
+    for (i, b) in [0x00, 0xAB, 0x80, 0xCD].into_iter().enumerate() {
+        mem.write_byte(0x0200_0004 + i as u32, b);
+    }
+    cpu.set_reg(0, 0x0200_0000);
+    cpu.set_reg(1, 0x0600_0000);
+
+    // When: it is decompressed to VRAM
+    cpu.step(&mut mem);
+
+    // Then: the stored lengths are biased by 1 and 3
+    assert_eq!(mem.read_word(0x0600_0000), 0xCDCD_CDAB, "RL output");
+}
+
+/// Scenario: IntrWait honours its discard argument and wait mask
+#[test]
+fn intr_wait_checks_the_bios_flags_for_its_mask() {
+    // Given: IntrCheck already holds a Timer 0 flag
+    let (mut cpu, mut mem) = thumb_cpu(&[0xDF04, 0xDF04]);
+    mem.write_half(0x0300_7FF8, 0x0008);
+
+    // When: IntrWait keeps old flags and waits for Timer 0
+    cpu.set_reg(0, 0);
+    cpu.set_reg(1, 0x0008);
+    cpu.step(&mut mem);
+
+    // Then: it returns at once and consumes the flag
+    assert!(!cpu.is_halted(), "Old flag satisfied the wait");
+    assert_eq!(cpu.intr_wait_mask(), None, "Wait finished");
+    assert_eq!(mem.read_half(0x0300_7FF8), 0, "Flag cleared");
+
+    // When: it discards old flags and waits for VBlank or HCount
+    mem.write_half(0x0300_7FF8, 0x0001);
+    cpu.set_reg(0, 1);
+    cpu.set_reg(1, 0x0005);
+    cpu.step(&mut mem);
+
+    // Then: it halts until a new flag in the mask shows up
+    assert!(cpu.is_halted(), "Halted for a new interrupt");
+    assert_eq!(cpu.intr_wait_mask(), Some(0x0005), "Waiting on the mask");
+    assert_eq!(mem.read_half(0x0300_7FF8), 0, "Old VBlank flag discarded");
+}
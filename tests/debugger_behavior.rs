@@ -0,0 +1,431 @@
+//! Behavior Driven Development tests for the GBA debugger APIs
+//!
+//! These tests describe breakpoints, stepping and disassembly.
+
+use rgba::{
+    disassemble_arm, disassemble_thumb, io, BasicBlock, CallKind, Gba, Interrupt, IrqEventKind,
+    Mode, Signedness, StepResult, Symbol, Width,
+};
+
+fn write_bytes(gba: &mut Gba, addr: u32, bytes: &[u8]) {
+    for (i, &b) in bytes.iter().enumerate() {
+        gba.mem.write_byte(addr + i as u32, b);
+    }
+}
+
+fn rom_from_words(words: &[u32]) -> Vec<u8> {
+    let mut rom = vec![0u8; 0x200];
+    for (i, word) in words.iter().enumerate() {
+        rom[i * 4..i * 4 + 4].copy_from_slice(&word.to_le_bytes());
+    }
+    rom
+}
+
+/// Scenario: ARM instructions disassemble to readable mnemonics
+#[test]
+fn arm_instructions_disassemble() {
+    assert_eq!(disassemble_arm(0x0800_0000, 0xE3A01403), "mov r1, #0x3000000", "MOV immediate");
+    assert_eq!(disassemble_arm(0x0800_0000, 0xE2800001), "add r0, r0, #0x1", "ADD immediate");
+    assert_eq!(disassemble_arm(0x0800_0000, 0xE5810000), "str r0, [r1]", "STR without offset");
+    assert_eq!(disassemble_arm(0x0800_000C, 0xEAFFFFFC), "b 0x08000004", "Backward branch target");
+    assert_eq!(disassemble_arm(0x0800_0000, 0xE12FFF1E), "bx lr", "BX");
+    assert_eq!(disassemble_arm(0x0800_0000, 0xE92D4010), "stmdb sp!, {r4, lr}", "Block store");
+    assert_eq!(disassemble_arm(0x0800_0000, 0x1AFFFFFE), "bne 0x08000000", "Conditional branch");
+    assert_eq!(disassemble_arm(0x0800_0000, 0xEF000005), "swi #0x5", "SWI");
+    assert_eq!(disassemble_arm(0x0800_0000, 0xE1200172), "bkpt #0x12", "BKPT");
+}
+
+/// Scenario: Thumb instructions disassemble to readable mnemonics
+#[test]
+fn thumb_instructions_disassemble() {
+    assert_eq!(disassemble_thumb(0x0800_0000, 0x2005), "movs r0, #0x5", "MOV immediate");
+    assert_eq!(disassemble_thumb(0x0800_0000, 0xB510), "push {r4, lr}", "PUSH with LR");
+    assert_eq!(disassemble_thumb(0x0800_0000, 0xBD10), "pop {r4, pc}", "POP with PC");
+    assert_eq!(disassemble_thumb(0x0800_0000, 0xDF05), "swi #0x5", "SWI");
+    assert_eq!(disassemble_thumb(0x0800_0004, 0xD0FC), "beq 0x08000000", "Conditional branch");
+    assert_eq!(disassemble_thumb(0x0800_0000, 0x4770), "bx lr", "BX");
+}
+
+/// Scenario: Execution stops at a breakpoint and can resume past it
+#[test]
+fn breakpoint_stops_and_resumes() {
+    // Given a counting loop with a breakpoint on the store
+    let mut gba = Gba::new();
+    gba.load_rom(rom_from_words(&[0xE3A01403, 0xE2800001, 0xE5810000, 0xEAFFFFFC]));
+    gba.add_breakpoint(0x0800_0008);
+
+    // When running
+    let hit = gba.run_until_breakpoint(10_000);
+
+    // Then execution stops before the store with one increment done
+    assert_eq!(hit, Some(0x0800_0008), "Should stop at the breakpoint");
+    assert_eq!(gba.cpu().next_instruction_pc(), 0x0800_0008, "Next PC should be the breakpoint");
+    assert_eq!(gba.cpu_reg(0), 1, "One iteration should have run");
+
+    // When resuming
+    let hit = gba.run_until_breakpoint(10_000);
+
+    // Then the loop runs once more and stops again
+    assert_eq!(hit, Some(0x0800_0008), "Should stop at the breakpoint again");
+    assert_eq!(gba.cpu_reg(0), 2, "Second iteration should have run");
+
+    // When the breakpoint is removed the budget runs out instead
+    assert!(gba.remove_breakpoint(0x0800_0008), "Breakpoint should be removed");
+    assert_eq!(gba.run_until_breakpoint(1_000), None, "No breakpoint should be hit");
+}
+
+/// Scenario: A BKPT in the ROM stops an attached debugger and aborts otherwise
+#[test]
+fn bkpt_traps_to_debugger_or_aborts() {
+    // mov r0, #1; bkpt #0; mov r0, #2; b .
+    let rom = rom_from_words(&[0xE3A00001, 0xE1200070, 0xE3A00002, 0xEAFFFFFE]);
+
+    // Given a debugger is attached
+    let mut gba = Gba::new();
+    gba.load_rom(rom.clone());
+    gba.set_debugger_attached(true);
+
+    // When running
+    let hit = gba.run_until_breakpoint(10_000);
+
+    // Then execution stops at the BKPT without entering an exception
+    assert_eq!(hit, Some(0x0800_0004), "Should stop at the BKPT");
+    assert_eq!(gba.cpu_reg(0), 1, "Code before the BKPT should have run");
+    assert_ne!(gba.cpu().get_mode(), Mode::Abort, "No abort with a debugger");
+
+    // When stepping on, the next instruction runs
+    assert!(matches!(gba.step_debug(), StepResult::Stepped(_)), "Resume past the BKPT");
+    assert_eq!(gba.cpu_reg(0), 2, "Instruction after the BKPT should run");
+
+    // Given no debugger
+    let mut gba = Gba::new();
+    gba.load_rom(rom);
+
+    // When the BKPT executes
+    gba.step_debug();
+    assert_eq!(gba.step_debug(), StepResult::Stepped(3), "BKPT is not reported");
+
+    // Then the CPU takes the prefetch abort
+    assert_eq!(gba.cpu().get_mode(), Mode::Abort, "Abort mode");
+    assert_eq!(gba.cpu().next_instruction_pc(), 0x0000_000C, "Prefetch abort vector");
+    assert_eq!(gba.cpu().get_reg(14), 0x0800_0008, "LR is the BKPT address + 4");
+}
+
+/// Scenario: Executed code is reported as basic blocks with run counts
+#[test]
+fn block_trace_counts_basic_blocks() {
+    // Given a counting loop with block tracing on
+    let mut gba = Gba::new();
+    gba.load_rom(rom_from_words(&[0xE3A01403, 0xE2800001, 0xE5810000, 0xEAFFFFFC]));
+    gba.set_block_trace(true);
+
+    // When the setup and three full loop iterations run, plus the start of a fourth
+    for _ in 0..13 {
+        gba.step();
+    }
+
+    // Then the entry block ran once and the loop body twice more to its branch
+    let block = |start, end, times| BasicBlock { start, end, times };
+    assert_eq!(
+        gba.take_block_trace(),
+        vec![block(0x0800_0000, 0x0800_000C, 1), block(0x0800_0004, 0x0800_000C, 2)],
+        "Blocks end at the backward branch"
+    );
+    assert_eq!(gba.take_block_trace(), vec![], "Taking clears the trace");
+
+    // When the in-flight iteration finishes
+    gba.step();
+    assert_eq!(gba.take_block_trace(), vec![block(0x0800_0004, 0x0800_000C, 1)], "Open block reported once closed");
+
+    // When tracing is off nothing is recorded
+    gba.set_block_trace(false);
+    for _ in 0..8 {
+        gba.step();
+    }
+    assert_eq!(gba.take_block_trace(), vec![], "Tracing off");
+}
+
+/// Scenario: Call tracing shows a backtrace at a breakpoint
+#[test]
+fn call_stack_tracks_nested_calls_and_returns() {
+    // Given: main calls f, which calls g, with call tracing on
+    let mut gba = Gba::new();
+    gba.load_rom(rom_from_words(&[
+        0xEB00_0002, // 00 main: bl f
+        0xEAFF_FFFE, // 04       b .
+        0,
+        0,
+        0xE92D_4000, // 10 f:    push {lr}
+        0xEB00_0001, // 14       bl g
+        0xE8BD_8000, // 18       pop {pc}
+        0,
+        0xE3A0_0001, // 20 g:    mov r0, #1
+        0xE12F_FF1E, // 24       bx lr
+    ]));
+    gba.trace_calls(true);
+
+    // When: execution stops inside g
+    gba.add_breakpoint(0x0800_0020);
+    gba.run_until_breakpoint(10_000);
+
+    // Then: the backtrace lists main -> f -> g
+    let stack = gba.call_stack();
+    assert_eq!(stack.len(), 2, "Two active calls");
+    assert_eq!(stack[0].kind, CallKind::Call, "Plain call");
+    assert_eq!(stack[0].call_site, 0x0800_0000, "main called f");
+    assert_eq!(stack[0].target, 0x0800_0010, "f entry");
+    assert_eq!(stack[1].call_site, 0x0800_0014, "f called g");
+    assert_eq!(stack[1].return_addr, 0x0800_0018, "g returns into f");
+
+    // When: both calls return (BX LR, then POP {PC})
+    gba.remove_breakpoint(0x0800_0020);
+    gba.add_breakpoint(0x0800_0004);
+    gba.run_until_breakpoint(10_000);
+
+    // Then: the stack is empty again
+    assert!(gba.call_stack().is_empty(), "All frames unwound");
+}
+
+/// Scenario: I/O registers are named and decoded from their current value
+#[test]
+fn io_registers_are_described() {
+    // Given DISPCNT set to mode 3 with BG2 enabled
+    let mut gba = Gba::new();
+    gba.write_half(0x0400_0000, 0x0403);
+
+    // When describing the register
+    let dispcnt = gba.io_register(0x0400_0000).expect("DISPCNT known");
+
+    // Then its name and fields are decoded
+    assert_eq!(dispcnt.info.name, "DISPCNT", "Register name");
+    assert_eq!(dispcnt.value, 0x0403, "Current value");
+    assert_eq!(dispcnt.field("BG Mode"), Some(3), "Mode field");
+    assert_eq!(dispcnt.field("Screen Display BG2"), Some(1), "BG2 enable");
+    assert_eq!(dispcnt.field("Forced Blank"), Some(0), "Forced blank");
+    let text = dispcnt.to_string();
+    assert!(text.starts_with("DISPCNT 04000000 = 403: BG"), "Display");
+
+    // And lookups work by offset, address, inner byte and name
+    let dma0 = io::describe_offset(0x0B8).map(|r| r.name);
+    assert_eq!(dma0, Some("DMA0CNT_L"), "Offset lookup");
+    assert_eq!(io::describe(0x0B8), None, "Offsets are not addresses");
+    assert_eq!(io::describe(0x0400_0022).map(|r| r.name), Some("BG2PB"), "Address lookup");
+    assert_eq!(io::describe(0x0400_002A).map(|r| r.name), Some("BG2X"), "Inner byte of a word register");
+    assert_eq!(io::by_name("waitcnt").map(|r| r.addr()), Some(0x0400_0204), "Name lookup");
+    assert_eq!(io::describe(0x0400_0400), None, "Unmapped offsets are unknown");
+}
+
+/// Scenario: Video memory writes are logged with old and new values
+#[test]
+fn video_writes_are_logged() {
+    // Given a machine with the video write log enabled
+    let mut gba = Gba::new();
+    gba.write_half(0x0500_0002, 0x1234);
+    gba.set_video_write_log(true);
+
+    // When writing palette, VRAM (through a mirror) and OAM
+    gba.write_half(0x0500_0002, 0x7FFF);
+    gba.write_byte(0x0601_8001, 0xAB);
+    gba.write_word(0x0700_0000, 0x0000_0001);
+    gba.write_word(0x0300_0000, 0xFFFF_FFFF);
+
+    // Then each byte is logged once with its canonical address
+    let writes = gba.take_video_writes();
+    assert_eq!(
+        &writes[..4],
+        &[(0x0500_0002, 0x34, 0xFF), (0x0500_0003, 0x12, 0x7F), (0x0601_0000, 0x00, 0xAB), (0x0601_0001, 0x00, 0xAB)],
+        "Palette and mirrored VRAM byte writes"
+    );
+    assert_eq!(writes[4], (0x0700_0000, 0x00, 0x01), "OAM write");
+    assert_eq!(writes.len(), 8, "IWRAM writes should not be logged");
+
+    // And taking drains the log
+    assert!(gba.take_video_writes().is_empty(), "Log should be drained");
+    gba.set_video_write_log(false);
+    gba.write_half(0x0500_0000, 1);
+    assert!(gba.take_video_writes().is_empty(), "Disabled log should stay empty");
+}
+
+/// Scenario: Interrupt requests, dispatches and acknowledgements are traced
+#[test]
+fn irq_trace_records_request_dispatch_and_ack() {
+    // Given a program that enables the VBlank IRQ and spins, with tracing on
+    let mut gba = Gba::new();
+    gba.load_rom(rom_from_words(&[
+        0xE3A00301, // mov r0, #0x04000000
+        0xE3A01001, // mov r1, #1
+        0xE2802C02, // add r2, r0, #0x200
+        0xE1C210B0, // strh r1, [r2]      ; IE = VBlank
+        0xE5821008, // str r1, [r2, #8]   ; IME = 1
+        0xE3A03008, // mov r3, #8
+        0xE1C030B4, // strh r3, [r0, #4]  ; DISPSTAT VBlank IRQ
+        0xEAFFFFFE, // b .
+    ]));
+    gba.set_irq_trace(1024);
+
+    // When a frame runs
+    gba.run_frame();
+
+    // Then the VBlank request and its dispatch from the spin loop are recorded
+    let trace = gba.irq_trace();
+    assert!(trace.len() <= 1024, "History is bounded");
+    let dispatch = trace
+        .iter()
+        .find(|e| e.kind == IrqEventKind::Dispatched)
+        .expect("VBlank should be dispatched");
+    assert_eq!(dispatch.irq, Interrupt::VBLANK, "Only VBlank is enabled");
+    assert_eq!(dispatch.pc, 0x0800_001C, "Interrupted in the spin loop");
+    assert!(
+        trace
+            .iter()
+            .any(|e| e.kind == IrqEventKind::Requested && e.irq == Interrupt::VBLANK && e.cycle <= dispatch.cycle),
+        "Request precedes the dispatch"
+    );
+
+    // When the game acknowledges VBlank
+    gba.mem.interrupt.request(Interrupt::VBLANK);
+    gba.mem.write_half(0x0400_0202, 0x0001);
+
+    // Then the acknowledgement is the latest event
+    let last = *gba.irq_trace().last().unwrap();
+    assert_eq!(last.kind, IrqEventKind::Acknowledged, "IF write acknowledges");
+    assert_eq!(last.irq, Interrupt::VBLANK, "VBlank acknowledged");
+}
+
+/// Scenario: The sampling profiler attributes time to symbols and ranges
+#[test]
+fn profiler_reports_samples_by_symbol() {
+    // Given: a ROM that jumps from `main` into a spin loop at 0x08000100
+    let mut words = vec![0u32; 0x41];
+    words[0] = 0xEA00_003E; // b 0x08000100
+    words[0x40] = 0xEAFF_FFFE; // b .
+    let mut gba = Gba::new();
+    gba.load_rom(rom_from_words(&words));
+
+    // When: a frame runs with the profiler sampling every 64 cycles
+    gba.start_profiling(64);
+    gba.run_frame();
+
+    // Then: the spin loop dominates the profile under its symbol name
+    let symbols = [
+        Symbol::new("main", 0x0800_0000, 0x100),
+        Symbol::new("spin", 0x0800_0100, 4),
+    ];
+    let report = gba.profile_report(&symbols);
+    assert_eq!(report.total_samples, 280_896 / 64, "One sample per interval");
+    assert_eq!(report.entries[0].name, "spin", "Hottest symbol first");
+    assert!(report.entries[0].percent > 99.0, "Nearly all time spins");
+
+    // And: without a matching symbol the loop is reported by address range
+    let report = gba.profile_report(&symbols[..1]);
+    assert_eq!(report.entries[0].name, "08000100-080001FF", "Unsymbolized range");
+
+    // When: profiling stops
+    let profiler = gba.stop_profiling().expect("profiler was running");
+
+    // Then: the samples are handed back and nothing more is recorded
+    assert_eq!(profiler.total_samples(), report.total_samples, "Samples kept");
+    assert_eq!(gba.profile_report(&symbols).total_samples, 0, "Profiling off");
+}
+
+/// Scenario: A test ROM writes its result to a host file through semihosting
+#[test]
+fn semihosting_writes_host_files_inside_the_sandbox() {
+    // Given: a sandbox directory and a ROM that opens, writes and closes a
+    // file, then prints to the console
+    let root = std::env::temp_dir().join(format!("rgba-semihost-{}", std::process::id()));
+    std::fs::create_dir_all(&root).unwrap();
+    let mut gba = Gba::new();
+    gba.load_rom(rom_from_words(&[
+        0xE3A0_0001, // 00 mov r0, #1 (SYS_OPEN)
+        0xEF12_3456, // 04 swi 0x123456
+        0xE582_0000, // 08 str r0, [r2]
+        0xE1A0_1002, // 0C mov r1, r2
+        0xE3A0_0005, // 10 mov r0, #5 (SYS_WRITE)
+        0xEF12_3456, // 14 swi 0x123456
+        0xE1A0_4000, // 18 mov r4, r0
+        0xE3A0_0002, // 1C mov r0, #2 (SYS_CLOSE)
+        0xEF12_3456, // 20 swi 0x123456
+        0xE3A0_0004, // 24 mov r0, #4 (SYS_WRITE0)
+        0xE1A0_1003, // 28 mov r1, r3
+        0xEF12_3456, // 2C swi 0x123456
+        0xEAFF_FFFE, // 30 b .
+    ]));
+    for (i, word) in [0x0200_0100, 4, 7].into_iter().enumerate() {
+        gba.mem.write_word(0x0200_0000 + i as u32 * 4, word);
+    }
+    for (i, word) in [0, 0x0200_0200, 2].into_iter().enumerate() {
+        gba.mem.write_word(0x0200_0010 + i as u32 * 4, word);
+    }
+    write_bytes(&mut gba, 0x0200_0100, b"out.txt");
+    write_bytes(&mut gba, 0x0200_0200, b"ok");
+    write_bytes(&mut gba, 0x0200_0300, b"done\0");
+    gba.cpu.set_reg(1, 0x0200_0000);
+    gba.cpu.set_reg(2, 0x0200_0010);
+    gba.cpu.set_reg(3, 0x0200_0300);
+    gba.enable_semihosting(&root);
+
+    // When: the ROM runs to its final loop
+    gba.add_breakpoint(0x0800_0030);
+    gba.run_until_breakpoint(10_000);
+
+    // Then: the file holds the result and the console the message
+    assert_eq!(gba.cpu_reg(4), 0, "All bytes written");
+    assert_eq!(std::fs::read(root.join("out.txt")).unwrap(), b"ok", "Host file");
+    assert_eq!(gba.semihost().unwrap().console(), b"done", "Console output");
+
+    // When: the ROM tries to open a path outside the sandbox
+    write_bytes(&mut gba, 0x0200_0100, b"../x.txt");
+    gba.mem.write_word(0x0200_0008, 8);
+    gba.cpu.set_reg(1, 0x0200_0000);
+    gba.cpu.set_pc(0x0800_0000);
+    gba.add_breakpoint(0x0800_0008);
+    gba.run_until_breakpoint(10_000);
+
+    // Then: the open fails
+    assert_eq!(gba.cpu_reg(0), u32::MAX, "Escaping the sandbox is refused");
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+/// Scenario: Watch expressions follow pinned addresses once per frame
+#[test]
+fn watch_exprs_refresh_every_frame() {
+    // Given: an idle ROM and a halfword at 0x02000010 holding -5
+    let mut gba = Gba::new();
+    gba.load_rom(rom_from_words(&[0xEAFF_FFFE]));
+    gba.mem.write_half(0x0200_0010, 0xFFFB);
+
+    // When: it is watched both as a signed halfword and as an unsigned word
+    gba.add_watch_expr(0x0200_0010, Width::Half, Signedness::Signed, "hp");
+    gba.add_watch_expr(0x0200_0010, Width::Word, Signedness::Unsigned, "raw");
+
+    // Then: both decodings are available right away
+    let values = gba.watch_values();
+    assert_eq!(values[0].value, -5, "Signed halfword");
+    assert_eq!(values[1].value, 0xFFFB, "Unsigned word");
+    assert!(!values[0].changed, "A new watch has not changed");
+
+    // When: the value changes mid-frame
+    gba.mem.write_half(0x0200_0010, 100);
+
+    // Then: the watch keeps the last frame's value until the next VBlank
+    assert_eq!(gba.watch_values()[0].value, -5, "Not refreshed yet");
+    gba.run_frame();
+    assert_eq!(gba.watch_values()[0].value, 100, "Refreshed at VBlank");
+    assert!(gba.watch_values()[0].changed, "Change is flagged");
+    gba.run_frame();
+    assert!(
+        !gba.watch_values()[0].changed,
+        "Flag clears on a steady frame"
+    );
+
+    // And: watches can be removed
+    let removed = gba.remove_watch_expr(0).expect("watch 0 exists");
+    assert_eq!(removed.label, "hp", "Removed watch");
+    assert_eq!(gba.watch_values().len(), 1, "One watch left");
+    assert_eq!(
+        gba.watch_values()[0].to_string(),
+        "raw @ 02000010: 100",
+        "Display form"
+    );
+}
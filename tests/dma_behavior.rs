@@ -2,7 +2,7 @@
 //!
 //! These tests describe the expected behavior of the GBA's 4 DMA channels.
 
-use rgba::{Dma, Memory};
+use rgba::{AccuracyProfile, Dma, Gba, Memory};
 
 /// Scenario: DMA channel initializes correctly
 #[test]
@@ -60,3 +60,114 @@ fn dma_can_be_triggered_by_various_events() {
     dma.set_control(0x8000); // Enable + immediate trigger
     assert_eq!(dma.get_trigger() as u8, 0, "Should be in immediate trigger mode");
 }
+
+/// Scenario: DMA transfers are timed with the CPU's waitstates and stall the CPU
+#[test]
+fn dma_transfer_cycles_stall_the_cpu() {
+    // Given: a two-word transfer from EWRAM (16-bit bus, 3 cycles) to IWRAM
+    let mut dma = Dma::new(3);
+    let mut mem = Memory::new();
+    dma.set_src_addr(0x0200_0000);
+    dma.set_dst_addr(0x0300_0000);
+    dma.set_count(2);
+    dma.set_control(0x8400);
+
+    // When: it runs
+    dma.execute(&mut mem);
+
+    // Then: 2 internal cycles plus (3 + 3) + 1 per word
+    assert_eq!(dma.last_transfer_cycles(), 16, "Waitstate-timed transfer");
+
+    // Given: the same transfer started by the game with DMA bus timing on
+    let mut gba = Gba::new();
+    gba.set_accuracy(AccuracyProfile::Cycle);
+    gba.mem.write_word(0x0400_00D4, 0x0200_0000);
+    gba.mem.write_word(0x0400_00D8, 0x0300_0000);
+    gba.mem.write_half(0x0400_00DC, 2);
+    gba.mem.write_half(0x0400_00DE, 0x8400);
+    gba.step();
+
+    // When: the emulator steps again
+    let pc = gba.cpu().next_instruction_pc();
+    let cycles = gba.step();
+
+    // Then: the CPU sat out the transfer
+    assert_eq!(cycles, 16, "Step covers the DMA stall");
+    assert_eq!(gba.cpu().next_instruction_pc(), pc, "CPU did not execute");
+}
+
+/// DMA3 copying 0x800 words from ROM to EWRAM, restarted forever
+fn looping_dma_rom() -> Vec<u8> {
+    rgba::asm::assemble(
+        0x0800_0000,
+        "mov r0, #0x04000000\n\
+         add r0, r0, #0xD4\n\
+         mov r1, #0x08000000\n\
+         str r1, [r0]\n\
+         mov r2, #0x02000000\n\
+         str r2, [r0, #4]\n\
+         ldr r3, =0x84000800\n\
+         again: str r3, [r0, #8]\n\
+         b again",
+    )
+    .expect("program assembles")
+}
+
+/// Scenario: A DMA stall longer than a scanline still sees every HBlank
+#[test]
+fn long_dma_stalls_keep_every_hblank() {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    for per_scanline in [false, true] {
+        // Given: a game restarting a 0x800-word DMA with DMA bus timing on
+        let mut gba = Gba::new();
+        gba.set_accuracy(AccuracyProfile::Balanced);
+        gba.load_rom(looping_dma_rom());
+        let hblanks = Arc::new(AtomicU32::new(0));
+        let seen = Arc::clone(&hblanks);
+        gba.on_hblank(move |_| {
+            seen.fetch_add(1, Ordering::Relaxed);
+        });
+
+        // When: five frames run
+        if per_scanline {
+            gba.run_scanlines(5 * 228);
+        } else {
+            for _ in 0..5 {
+                gba.run_frame();
+            }
+        }
+
+        // Then: every line had its HBlank and the frames kept their length
+        let hblanks = hblanks.load(Ordering::Relaxed);
+        assert_eq!(hblanks, 5 * 228, "HBlanks (per scanline: {})", per_scanline);
+        let frames = gba.cycles_elapsed() as f64 / 280_896.0;
+        assert!((frames - 5.0).abs() < 0.01, "Frame length: {}", frames);
+    }
+}
+
+/// Scenario: A save state taken mid-stall resumes like an uninterrupted run
+#[test]
+fn dma_stall_survives_save_states() {
+    // Given: a machine that just started a long DMA
+    let mut gba = Gba::new();
+    gba.set_accuracy(AccuracyProfile::Balanced);
+    gba.load_rom(looping_dma_rom());
+    while gba.mem.read_word(0x0200_0000) == 0 {
+        gba.step();
+    }
+
+    // When: its state is loaded into a second machine and both run on
+    let state = gba.save_state();
+    let mut restored = Gba::new();
+    restored.set_accuracy(AccuracyProfile::Balanced);
+    restored.load_rom(looping_dma_rom());
+    restored.load_state(&state).expect("state loads");
+    let original: Vec<u32> = (0..1000).map(|_| gba.step()).collect();
+    let resumed: Vec<u32> = (0..1000).map(|_| restored.step()).collect();
+
+    // Then: both sit out the rest of the stall and end in the same place
+    assert_eq!(resumed, original, "Step cycles");
+    assert_eq!(restored.state_digest(), gba.state_digest(), "State digest");
+}
@@ -0,0 +1,150 @@
+//! Behavior Driven Development tests for the frontend utilities
+//!
+//! These tests describe the helpers frontends use to present GBA frames.
+
+use std::time::{Duration, Instant};
+
+use rgba::frontends::scale::{self, Filter, FitMode, Rect};
+use rgba::{AccuracyProfile, ColorCorrection, Gba, Throttle, CPU_FREQUENCY};
+#[cfg(feature = "config")]
+use rgba::{Config, ConfigError, KeyState};
+
+/// Scenario: Integer fitting letterboxes the screen at the largest whole scale
+#[test]
+fn integer_fit_letterboxes_at_whole_scale() {
+    // Given a 240x160 screen and an 800x600 window
+    // When fitted with integer scaling
+    let rect = scale::fit(240, 160, 800, 600, FitMode::Integer);
+
+    // Then the image is 3x and centered
+    assert_eq!(rect, Rect { x: 40, y: 60, width: 720, height: 480 }, "Should be 3x centered");
+}
+
+/// Scenario: Aspect fitting uses the full limiting dimension
+#[test]
+fn aspect_fit_preserves_ratio() {
+    let rect = scale::fit(240, 160, 800, 600, FitMode::Aspect);
+
+    assert_eq!(rect.width, 800, "Width should fill the window");
+    assert_eq!(rect.height, 533, "Height should keep the 3:2 ratio");
+    assert_eq!(rect.y, 33, "Image should be vertically centered");
+}
+
+/// Scenario: Nearest scaling duplicates pixels and clears the bars
+#[test]
+fn nearest_scaling_duplicates_pixels() {
+    // Given a 2x1 source and a 4x4 destination filled with garbage
+    let src = [0x00FF0000, 0x0000FF00];
+    let mut dst = [0xDEADBEEFu32; 16];
+
+    // When scaled with integer fitting
+    let rect = scale::scale_into(&src, 2, 1, &mut dst, 4, 4, FitMode::Integer, Filter::Nearest);
+
+    // Then the 2x image sits in the middle rows and the rest is black
+    assert_eq!(rect, Rect { x: 0, y: 1, width: 4, height: 2 }, "Should be 2x");
+    assert_eq!(&dst[0..4], &[0; 4], "Top bar should be cleared");
+    assert_eq!(&dst[4..8], &[0x00FF0000, 0x00FF0000, 0x0000FF00, 0x0000FF00], "Pixels should be doubled");
+    assert_eq!(&dst[12..16], &[0; 4], "Bottom bar should be cleared");
+}
+
+/// Scenario: Sharp bilinear keeps texel interiors solid
+#[test]
+fn sharp_bilinear_keeps_texel_centers_solid() {
+    let src = [0x00000000, 0x00FFFFFF];
+    let mut dst = [0u32; 8];
+
+    scale::scale_into(&src, 2, 1, &mut dst, 8, 1, FitMode::Stretch, Filter::SharpBilinear);
+
+    assert_eq!(dst[0], 0x00000000, "Left edge should stay black");
+    assert_eq!(dst[7], 0x00FFFFFF, "Right edge should stay white");
+}
+
+/// Scenario: The throttle paces emulated cycles against the wall clock
+#[test]
+fn throttle_paces_emulated_cycles() {
+    // Given a machine that ran one frame
+    let mut gba = Gba::new();
+    gba.run_frame();
+    assert!(gba.cycles_elapsed() >= 280_896, "A frame should take at least 280896 cycles");
+
+    // Then one second of emulated time maps to one real second at 100%
+    // and half a second at double speed
+    let mut throttle = Throttle::new(1.0);
+    assert_eq!(throttle.target_time(CPU_FREQUENCY), Duration::from_secs(1), "Full speed");
+    throttle.set_speed(2.0, 0);
+    assert_eq!(throttle.target_time(CPU_FREQUENCY), Duration::from_millis(500), "Double speed");
+
+    // When waiting for 20ms worth of cycles
+    throttle.set_speed(1.0, 0);
+    let start = Instant::now();
+    throttle.wait(CPU_FREQUENCY / 50);
+
+    // Then the call returns no earlier than the deadline
+    assert!(start.elapsed() >= Duration::from_millis(19), "Throttle should wait for real time");
+
+    // And an unlimited throttle never waits
+    throttle.set_speed(0.0, 0);
+    assert_eq!(throttle.wait(CPU_FREQUENCY), Duration::ZERO, "Unthrottled speed should not wait");
+
+    // And reset starts the count again
+    gba.reset();
+    assert_eq!(gba.cycles_elapsed(), 0, "Reset should clear the cycle count");
+}
+
+/// Scenario: The builder applies emulator settings to a new GBA
+#[test]
+fn builder_applies_settings() {
+    // Given settings a frontend picked
+    // When the GBA is built from them
+    let gba = Gba::builder()
+        .accuracy(AccuracyProfile::Balanced)
+        .color_correction(ColorCorrection::GbaLcd)
+        .audio_rate(44100)
+        .build()
+        .unwrap();
+
+    // Then the GBA runs with them
+    assert_eq!(gba.accuracy(), Some(AccuracyProfile::Balanced), "Accuracy profile");
+    assert_eq!(gba.color_correction(), ColorCorrection::GbaLcd, "Color correction");
+    assert_eq!(gba.audio_sample_rate(), 44100, "Audio rate");
+
+    // And a missing BIOS image is reported instead of ignored
+    let missing = Gba::builder().bios("/nonexistent/gba_bios.bin").build();
+    assert!(missing.is_err(), "BIOS load error surfaces");
+}
+
+/// Scenario: A settings file round-trips through TOML into a builder
+#[cfg(feature = "config")]
+#[test]
+fn config_round_trips_through_toml() {
+    // Given a partial settings file
+    let text = r#"
+        accuracy = "cycle"
+        color_correction = "ags101"
+
+        [keys]
+        A = "Space"
+    "#;
+
+    // When it is parsed
+    let config = Config::from_toml(text).unwrap();
+
+    // Then given fields are read and the rest keep their defaults
+    assert_eq!(config.accuracy, AccuracyProfile::Cycle, "Accuracy");
+    assert_eq!(config.color_correction, ColorCorrection::Ags101, "Color correction");
+    assert_eq!(config.audio_rate, Config::default().audio_rate, "Default audio rate");
+    assert_eq!(config.bindings(), vec![(KeyState::A, "Space")], "Only the listed key");
+
+    // And it survives a write and a read back, and builds the same GBA
+    let again = Config::from_toml(&config.to_toml().unwrap()).unwrap();
+    assert_eq!(again, config, "Round trip");
+    let gba = again.builder().build().unwrap();
+    assert_eq!(gba.accuracy(), Some(AccuracyProfile::Cycle), "Built with the profile");
+
+    // And unknown buttons are rejected
+    let unknown = Config::from_toml("[keys]\nTURBO = \"T\"");
+    assert!(
+        matches!(unknown, Err(ConfigError::UnknownButton(name)) if name == "TURBO"),
+        "Unknown button"
+    );
+}
@@ -2,7 +2,7 @@
 //!
 //! These tests describe the expected behavior of the GBA's keypad input.
 
-use rgba::{Input, KeyState};
+use rgba::{Gba, Input, KeyState};
 
 /// Scenario: Input initializes with no keys pressed
 #[test]
@@ -142,3 +142,67 @@ fn shoulder_buttons_work_correctly() {
     assert_eq!(input.is_key_pressed(KeyState::L), true);
     assert_eq!(input.is_key_pressed(KeyState::R), true);
 }
+
+/// Scenario: Frame latching holds KEYINPUT until the next VBlank
+#[test]
+fn frame_latch_updates_keyinput_at_vblank() {
+    // Given: a machine with frame latching on, stopped mid-frame
+    let mut rom = vec![0u8; 0x200];
+    rom[0..4].copy_from_slice(&0xEAFF_FFFEu32.to_le_bytes()); // b .
+    let mut gba = Gba::new();
+    gba.load_rom(rom);
+    gba.input_mut().set_frame_latch(true);
+    gba.run_scanlines(10);
+
+    // When: A is pressed mid-frame
+    gba.input_mut().press_key(KeyState::A);
+    gba.run_scanlines(10);
+
+    // Then: the CPU still sees A released
+    assert_eq!(gba.mem.read_half(0x0400_0130) & 0x3FF, 0x3FF, "A hidden until VBlank");
+
+    // When: the frame runs into VBlank
+    gba.run_scanlines(161 - 20);
+
+    // Then: A reads as pressed
+    assert_eq!(gba.mem.read_half(0x0400_0130) & 0x3FF, 0x3FE, "A latched at VBlank");
+
+    // When: latching is turned off and A released
+    gba.input_mut().set_frame_latch(false);
+    gba.input_mut().release_key(KeyState::A);
+    gba.run_scanline();
+
+    // Then: the release shows up immediately
+    assert_eq!(gba.mem.read_half(0x0400_0130) & 0x3FF, 0x3FF, "Immediate propagation");
+}
+
+/// Scenario: All keys are set at once from a KEYINPUT-ordered mask
+#[test]
+fn set_all_keys_applies_an_active_high_mask() {
+    // Given: B held down
+    let mut gba = Gba::new();
+    gba.input_mut().press_key(KeyState::B);
+
+    // When: the frame's keys are set as A + RIGHT + L, with junk in bits 10-15
+    let mask = (KeyState::A | KeyState::RIGHT | KeyState::L).bits() | 0xFC00;
+    gba.input_mut().set_all_keys(mask);
+
+    // Then: exactly those keys are down and the register is their inverse
+    let input = gba.input_mut();
+    assert!(input.is_key_pressed(KeyState::A), "A pressed");
+    assert!(input.is_key_pressed(KeyState::RIGHT), "RIGHT pressed");
+    assert!(input.is_key_pressed(KeyState::L), "L pressed");
+    assert!(!input.is_key_pressed(KeyState::B), "B released");
+    assert_eq!(input.pressed_keys(), 0x0211, "Mask reads back without bits 10-15");
+    assert_eq!(input.get_key_register(), !0x0211, "KEYINPUT is the active-low mask");
+
+    // And: the CPU sees the same bits
+    gba.run_scanline();
+    assert_eq!(gba.mem.read_half(0x0400_0130), !0x0211 & 0x3FF, "KEYINPUT register");
+
+    // When: the next frame's mask is empty
+    gba.input_mut().set_all_keys(0);
+
+    // Then: every key is released
+    assert_eq!(gba.input_mut().pressed_keys(), 0, "All keys released");
+}
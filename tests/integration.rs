@@ -2,7 +2,8 @@
 //!
 //! These tests verify that all components work together correctly.
 
-use rgba::{Gba, Cpu, Memory, Ppu};
+use rgba::testing::{self, TestOutcome, TestProtocol};
+use rgba::{Gba, Cpu, KeyState, Memory, Ppu, SaveType, SwapMode};
 
 /// Scenario: GBA system initializes correctly
 #[test]
@@ -39,6 +40,314 @@ fn gba_can_be_reset() {
     assert_eq!(gba.ppu.is_display_enabled(), false);
 }
 
+/// Scenario: Reset power-cycles with the cartridge inserted; hard reset also erases the save
+#[test]
+fn reset_keeps_cartridge_and_hard_reset_erases_save() {
+    // Given a running SRAM cartridge that has written a save byte and replaced the IRQ handler
+    let mut gba = Gba::new();
+    let mut rom = vec![0u8; 0x200];
+    rom[..4].copy_from_slice(&0xEAFF_FFFEu32.to_le_bytes());
+    gba.load_rom(rom);
+    gba.set_save_type(SaveType::Sram);
+    gba.write_byte(0x0E00_0000, 0x42);
+    gba.write_byte(0x0200_0000, 0x99);
+    gba.mem.write_word(0x0300_7FFC, 0x0300_1000);
+    gba.run_frame();
+
+    // When the console is reset
+    gba.reset();
+
+    // Then ROM and save data are kept but RAM and registers are back to the boot state
+    assert_eq!(gba.mem.read_word(0x0800_0000), 0xEAFF_FFFE, "ROM should stay loaded");
+    assert_eq!(gba.read_byte(0x0E00_0000), 0x42, "Save data should survive a reset");
+    assert_eq!(gba.read_byte(0x0200_0000), 0, "EWRAM should be cleared");
+    assert_eq!(gba.mem.read_word(0x0300_7FFC), 0x0000_013C, "IRQ handler should point at the BIOS stub again");
+    assert_eq!(gba.mem.read_byte(0x0400_0300), 1, "POSTFLG should report a completed boot");
+    assert_eq!(gba.mem.read_half(0x0400_0088), 0x0200, "SOUNDBIAS should hold its boot value");
+    assert_eq!(gba.cpu.get_pc(), 0x0800_0000, "PC should be at the cartridge entry point");
+    assert_eq!(gba.cpu.get_reg(13), 0x0300_7F00, "SP should be initialized");
+
+    // And a reset machine matches a freshly powered-on one with the same cartridge
+    let mut fresh = Gba::new();
+    let mut rom = vec![0u8; 0x200];
+    rom[..4].copy_from_slice(&0xEAFF_FFFEu32.to_le_bytes());
+    fresh.load_rom(rom);
+    assert_eq!(gba.state_digest(), fresh.state_digest(), "Reset should equal power-on");
+
+    // When the console is hard reset
+    gba.hard_reset();
+
+    // Then the save chip is blank
+    assert_eq!(gba.read_byte(0x0E00_0000), 0xFF, "Hard reset should erase the save");
+    assert_eq!(gba.save_type(), SaveType::Sram, "The save type should be kept");
+}
+
+/// Scenario: Swapping cartridges power-cycles into the new game, optionally keeping EWRAM
+#[test]
+fn swap_rom_boots_new_cartridge() {
+    for (mode, ewram) in [(SwapMode::HardReset, 0x00), (SwapMode::KeepRam, 0x99)] {
+        // Given a running SRAM game that left data in EWRAM and its save
+        let mut gba = Gba::new();
+        let mut rom = vec![0u8; 0x200];
+        rom[..4].copy_from_slice(&0xEAFF_FFFEu32.to_le_bytes());
+        gba.load_rom(rom);
+        gba.set_save_type(SaveType::Sram);
+        gba.write_byte(0x0E00_0000, 0x42);
+        gba.write_byte(0x0200_0000, 0x99);
+        gba.run_frame();
+
+        // When another cartridge is swapped in
+        let mut other = vec![0u8; 0x400];
+        other[..4].copy_from_slice(&0xE3A0_0001u32.to_le_bytes());
+        gba.swap_rom(other, mode);
+
+        // Then the new game boots from its entry point with a blank save
+        assert_eq!(gba.mem.rom().len(), 0x400, "{:?}: new ROM loaded", mode);
+        assert_eq!(gba.cpu.get_pc(), 0x0800_0000, "{:?}: at the entry point", mode);
+        assert_eq!(gba.read_byte(0x0E00_0000), 0xFF, "{:?}: save chip blank", mode);
+        assert_eq!(gba.save_type(), SaveType::Sram, "{:?}: save type kept", mode);
+
+        // And EWRAM is cleared or kept as asked
+        assert_eq!(gba.read_byte(0x0200_0000), ewram, "{:?}: EWRAM", mode);
+    }
+}
+
+/// Scenario: A multiboot image runs from EWRAM with no cartridge inserted
+#[test]
+fn multiboot_image_runs_from_ewram() {
+    // Given an image that branches over its header, sets r4, then asks the BIOS to multiboot a slave
+    let mut image = vec![0u8; 0x100];
+    let mut put = |at: usize, op: u32| image[at..at + 4].copy_from_slice(&op.to_le_bytes());
+    put(0x00, 0xEA00_002E); // B 0xC0
+    put(0xC0, 0xEA00_0002); // B 0xD0
+    put(0xD0, 0xE3A0_402A); // MOV R4, #0x2A
+    put(0xD4, 0xEF25_0000); // SWI 0x25
+    put(0xD8, 0xEAFF_FFFE); // B .
+    let mut gba = Gba::new();
+
+    // When it is loaded as a multiboot image and run
+    gba.load_multiboot(image);
+    gba.run_frame();
+
+    // Then it executed from EWRAM, with the header filled in as the BIOS would
+    assert!(gba.mem.rom().is_empty(), "No cartridge inserted");
+    assert_eq!(gba.cpu.get_reg(4), 0x2A, "Image code ran");
+    assert_eq!(gba.cpu.get_pc() >> 24, 0x02, "PC stays in EWRAM");
+    assert_eq!(gba.read_byte(0x0200_00C4), 3, "Boot mode: multi-play");
+    assert_eq!(gba.read_byte(0x0200_00C5), 1, "Slave number");
+    assert_eq!(gba.cpu.get_reg(0), 1, "MultiBoot fails without a slave");
+
+    // When the console is reset
+    gba.reset();
+
+    // Then the image boots again
+    assert_eq!(gba.cpu.get_pc(), 0x0200_0000, "PC at the image entry point");
+    assert_eq!(gba.mem.read_word(0x0200_00D0), 0xE3A0_402A, "Image reinstalled");
+
+    // When a cartridge is loaded instead
+    let mut rom = vec![0u8; 0x200];
+    rom[..4].copy_from_slice(&0xEAFF_FFFEu32.to_le_bytes());
+    gba.load_rom(rom);
+
+    // Then execution moves back to the cartridge
+    assert_eq!(gba.cpu.get_pc(), 0x0800_0000, "PC at the cartridge entry point");
+    assert_eq!(gba.read_byte(0x0200_00D0), 0, "EWRAM cleared");
+}
+
+/// Scenario: The replacement BIOS dispatches IRQs through 0x128 and the handler pointer
+#[cfg(feature = "replacement-bios")]
+#[test]
+fn replacement_bios_dispatches_irqs_through_handler_pointer() {
+    use rgba::BootMode;
+
+    // Given a ROM that installs an ARM VBlank handler and spins in System mode
+    let program: [u32; 23] = [
+        0xE3A00301, // 0x00: mov r0, #0x04000000
+        0xE28F1034, // 0x04: add r1, pc, #0x34   ; handler at 0x40
+        0xE5001004, // 0x08: str r1, [r0, #-4]   ; [0x03FFFFFC] = handler
+        0xE3A01008, // 0x0C: mov r1, #8
+        0xE1C010B4, // 0x10: strh r1, [r0, #4]   ; DISPSTAT VBlank IRQ
+        0xE3A01001, // 0x14: mov r1, #1
+        0xE2802C02, // 0x18: add r2, r0, #0x200
+        0xE1C210B0, // 0x1C: strh r1, [r2]       ; IE = VBlank
+        0xE1C210B8, // 0x20: strh r1, [r2, #8]   ; IME = 1
+        0xE321F01F, // 0x24: msr cpsr_c, #0x1F
+        0xE2855001, // 0x28: add r5, r5, #1
+        0xEAFFFFFD, // 0x2C: b 0x28
+        0, 0, 0, 0,
+        0xE3A00301, // 0x40: mov r0, #0x04000000
+        0xE2800C02, // 0x44: add r0, r0, #0x200
+        0xE3A01001, // 0x48: mov r1, #1
+        0xE1C010B2, // 0x4C: strh r1, [r0, #2]   ; acknowledge VBlank
+        0xE2844001, // 0x50: add r4, r4, #1
+        0xE12FFF1E, // 0x54: bx lr
+        0,
+    ];
+    let mut rom = vec![0u8; 0x200];
+    for (i, word) in program.iter().enumerate() {
+        rom[i * 4..i * 4 + 4].copy_from_slice(&word.to_le_bytes());
+    }
+    let mut gba = Gba::new();
+    gba.load_rom(rom);
+
+    // When booting with the replacement BIOS
+    gba.set_boot_mode(BootMode::ReplacementBios);
+    assert_eq!(gba.boot_mode(), BootMode::ReplacementBios, "Boot mode is kept");
+    assert!(gba.mem.read_word(0x0300_7FFC) < 0x4000, "A BIOS handler covers early IRQs");
+    gba.set_block_trace(true);
+    for _ in 0..3 {
+        gba.run_frame();
+    }
+
+    // Then each VBlank ran the game's handler and returned to the main loop
+    assert!(gba.cpu_reg(4) >= 2, "Handler ran every frame: {}", gba.cpu_reg(4));
+    assert!(gba.cpu_reg(5) > 1000, "Main loop keeps running");
+    assert_eq!(gba.cpu_get_cpsr() & 0x9F, 0x1F, "Back in System mode with IRQs on");
+    let blocks = gba.take_block_trace();
+    assert!(blocks.iter().any(|b| b.start == 0x0000_0018), "IRQ vector used");
+    assert!(blocks.iter().any(|b| b.start == 0x0000_0128), "Dispatcher at 0x128 used");
+    assert!(blocks.iter().any(|b| b.start == 0x0000_0138), "Handler returns through 0x138");
+
+    // When switching back to the built-in stubs
+    gba.set_boot_mode(BootMode::Builtin);
+
+    // Then the stub IRQ handler pointer is back
+    assert_eq!(gba.mem.read_word(0x0300_7FFC), 0x0000_013C, "Built-in stub handler");
+}
+
+/// ARM ROM that installs a VBlank handler at 0x03007FFC, then runs
+/// `cpsr_op` and spins counting in r5. The handler counts in r7, copies
+/// IME into r8 and acknowledges VBlank.
+fn vblank_handler_rom(cpsr_op: u32) -> Vec<u8> {
+    let program: [u32; 24] = [
+        0xE3A00301, // 0x00: mov r0, #0x04000000
+        0xE28F1034, // 0x04: add r1, pc, #0x34   ; handler at 0x40
+        0xE5001004, // 0x08: str r1, [r0, #-4]   ; [0x03FFFFFC] = handler
+        0xE3A01008, // 0x0C: mov r1, #8
+        0xE1C010B4, // 0x10: strh r1, [r0, #4]   ; DISPSTAT VBlank IRQ
+        0xE3A01001, // 0x14: mov r1, #1
+        0xE2802C02, // 0x18: add r2, r0, #0x200
+        0xE1C210B0, // 0x1C: strh r1, [r2]       ; IE = VBlank
+        0xE1C210B8, // 0x20: strh r1, [r2, #8]   ; IME = 1
+        cpsr_op,    // 0x24
+        0xE2855001, // 0x28: add r5, r5, #1
+        0xEAFFFFFD, // 0x2C: b 0x28
+        0, 0, 0, 0,
+        0xE3A00301, // 0x40: mov r0, #0x04000000
+        0xE2800C02, // 0x44: add r0, r0, #0x200
+        0xE1D020B8, // 0x48: ldrh r2, [r0, #8]   ; IME
+        0xE3A01001, // 0x4C: mov r1, #1
+        0xE1C010B2, // 0x50: strh r1, [r0, #2]   ; acknowledge VBlank
+        0xE2877001, // 0x54: add r7, r7, #1
+        0xE1A08002, // 0x58: mov r8, r2
+        0xE12FFF1E, // 0x5C: bx lr
+    ];
+    let mut rom = vec![0u8; 0x200];
+    for (i, word) in program.iter().enumerate() {
+        rom[i * 4..i * 4 + 4].copy_from_slice(&word.to_le_bytes());
+    }
+    rom
+}
+
+/// Scenario: IRQs reach the game's handler through the BIOS vector and 0x03007FFC
+#[test]
+fn irqs_dispatch_through_bios_to_game_handler() {
+    // Given a game that installs its VBlank handler and leaves IRQs unmasked
+    let mut gba = Gba::new();
+    gba.load_rom(vblank_handler_rom(0xE1A00000)); // nop
+    assert_eq!(gba.cpu_get_cpsr() & 0xFF, 0x1F, "Boot hands over in System mode, IRQs on");
+
+    // When frames run
+    for _ in 0..3 {
+        gba.run_frame();
+    }
+
+    // Then the handler ran once per VBlank and the main loop kept going
+    assert!(gba.cpu_reg(7) >= 2, "Handler ran every frame: {}", gba.cpu_reg(7));
+    assert!(gba.cpu_reg(5) > 1000, "Main loop resumes after each IRQ");
+    assert_eq!(gba.cpu_reg(8), 1, "IME is not cleared on IRQ entry");
+    assert_eq!(gba.cpu_get_cpsr() & 0xFF, 0x1F, "Handler returns to System mode");
+
+    // Given the same game with the CPSR I bit set
+    let mut gba = Gba::new();
+    gba.load_rom(vblank_handler_rom(0xE321F09F)); // msr cpsr_c, #0x9F
+    for _ in 0..3 {
+        gba.run_frame();
+    }
+
+    // Then the IRQ stays pending and the handler never runs
+    assert_eq!(gba.cpu_reg(7), 0, "Masked in CPSR");
+    assert_ne!(gba.mem.read_half(0x0400_0202) & 1, 0, "VBlank still pending in IF");
+}
+
+/// Scenario: The frame log records each frame's VBlank, DMA bursts, IRQs and audio output
+#[cfg(feature = "frame-log")]
+#[test]
+fn frame_log_records_per_frame_events() {
+    use rgba::{FrameEvent, Interrupt};
+
+    // Given the VBlank handler game with a repeating VBlank DMA3 of 16 words
+    let mut gba = Gba::new();
+    gba.load_rom(vblank_handler_rom(0xE1A00000)); // nop
+    gba.mem.write_word(0x0400_00D4, 0x0800_0000);
+    gba.mem.write_word(0x0400_00D8, 0x0200_0000);
+    gba.mem.write_half(0x0400_00DC, 16);
+    gba.mem.write_half(0x0400_00DE, 0x9600);
+
+    // When frames run with a three-frame log
+    gba.start_frame_log(3);
+    for _ in 0..6 {
+        gba.run_frame();
+    }
+
+    // Then only the last three complete frames are kept, numbered in order
+    let log = gba.frame_log().expect("Logging");
+    let frames: Vec<_> = log.frames().collect();
+    assert_eq!(frames.len(), 3, "Capacity bounds the log");
+    assert_eq!(frames[2].frame, frames[0].frame + 2, "Consecutive frames");
+    assert_eq!(log.frame(frames[1].frame), Some(frames[1]), "Lookup by number");
+
+    // And each frame shows one VBlank IRQ, the DMA burst and the samples emitted
+    for f in &frames {
+        let span = f.cycles as i64 - 280896;
+        assert!(span.abs() < 16, "Frame {} lasts a frame: {}", f.frame, f.cycles);
+        assert_eq!(f.irq_count(Interrupt::VBLANK), 1, "One VBlank IRQ in frame {}", f.frame);
+        assert_eq!(f.dma_bytes(3), 64, "DMA3 burst in frame {}", f.frame);
+        assert!(f.audio_samples > 0, "Audio output in frame {}", f.frame);
+        assert!(
+            matches!(f.events[0].event, FrameEvent::Dma { channel: 3, .. }),
+            "The VBlank DMA runs first: {:?}",
+            f.events
+        );
+    }
+    assert_eq!(
+        frames[1].vblank_cycle,
+        frames[0].vblank_cycle + frames[0].cycles as u64,
+        "Frames are contiguous"
+    );
+    assert_eq!(
+        frames[2].audio_samples,
+        gba.audio_frame_sample_count(),
+        "Sample count of the last complete frame"
+    );
+
+    // And the JSON lines export has one object per frame
+    let json = log.to_json_lines();
+    assert_eq!(json.lines().count(), 3, "One line per frame");
+    assert!(
+        json.lines().all(|l| l.contains("\"dma\":{\"channel\":3,\"bytes\":64")),
+        "DMA bursts exported: {}",
+        json
+    );
+
+    // When logging stops
+    let taken = gba.stop_frame_log();
+
+    // Then the log is handed over and nothing more is recorded
+    assert_eq!(taken.map(|l| l.frames().count()), Some(3), "Collected log returned");
+    assert!(gba.frame_log().is_none(), "Logging off");
+}
+
 /// Scenario: ROM can be loaded and executed
 #[test]
 fn rom_can_be_loaded() {
@@ -127,3 +436,331 @@ fn display_modes_can_be_changed() {
         assert_eq!(gba.ppu.is_display_enabled(), true);
     }
 }
+
+fn r12_rom(r12: u8) -> Vec<u8> {
+    let program: [u32; 2] = [
+        0xE3A0_C000 | r12 as u32, // mov r12, #r12
+        0xEAFF_FFFE,              // b .
+    ];
+    let mut rom = vec![0u8; 0x200];
+    for (i, word) in program.iter().enumerate() {
+        rom[i * 4..i * 4 + 4].copy_from_slice(&word.to_le_bytes());
+    }
+    rom
+}
+
+/// ROM copying KEYINPUT to IWRAM in a loop; `ignore_a` forces the A bit to
+/// "released"
+fn keyinput_copy_rom(ignore_a: bool) -> Vec<u8> {
+    let program: [u32; 7] = [
+        0xE3A0_0301, // mov r0, #0x04000000
+        0xE280_0E13, // add r0, r0, #0x130
+        0xE3A0_1403, // mov r1, #0x03000000
+        0xE1D0_20B0, // loop: ldrh r2, [r0]
+        if ignore_a { 0xE382_2001 } else { 0xE1A0_0000 }, // orr r2, r2, #1 / nop
+        0xE1C1_20B0, // strh r2, [r1]
+        0xEAFF_FFFB, // b loop
+    ];
+    let mut rom = vec![0u8; 0x200];
+    for (i, word) in program.iter().enumerate() {
+        rom[i * 4..i * 4 + 4].copy_from_slice(&word.to_le_bytes());
+    }
+    rom
+}
+
+/// Scenario: Divergence hunting finds the first frame two machines differ
+#[test]
+fn find_divergence_reports_first_differing_frame() {
+    // Given two builds that only differ in how they treat the A button
+    let mut a = Gba::new();
+    a.load_rom(keyinput_copy_rom(false));
+    let mut b = Gba::new();
+    b.load_rom(keyinput_copy_rom(true));
+
+    // When A is first held during frame 5 of 10
+    let mut inputs = vec![KeyState::empty(); 10];
+    inputs[4..].fill(KeyState::A);
+    let divergence = testing::find_divergence(&mut a, &mut b, &inputs).expect("machines should diverge");
+
+    // Then the divergence is pinned to that frame, with both machines stopped there
+    assert_eq!(divergence.frame, 5, "First differing frame");
+    assert_ne!(divergence.digest_a, divergence.digest_b, "Digests differ");
+    assert_eq!(a.state_digest(), divergence.digest_a, "Machine A left at the divergence");
+    assert_eq!(b.state_digest(), divergence.digest_b, "Machine B left at the divergence");
+
+    // And identical machines never diverge
+    let mut c = Gba::new();
+    c.load_rom(keyinput_copy_rom(false));
+    let mut d = Gba::new();
+    d.load_rom(keyinput_copy_rom(false));
+    assert_eq!(testing::find_divergence(&mut c, &mut d, &inputs), None, "Identical machines");
+}
+
+/// Scenario: The test ROM runner reports r12-based results
+#[test]
+fn test_rom_runner_reads_r12_protocol() {
+    // Given ROMs that finish with r12 = 0 and r12 = 3
+    let mut passing = Gba::new();
+    passing.load_rom(r12_rom(0));
+    let mut failing = Gba::new();
+    failing.load_rom(r12_rom(3));
+
+    // When they run under the r12 protocol
+    let pass = testing::run_test(&mut passing, TestProtocol::R12Zero, 10);
+    let fail = testing::run_test(&mut failing, TestProtocol::R12Zero, 10);
+
+    // Then the outcome and final state are reported
+    assert!(pass.passed(), "r12 = 0 should pass");
+    assert_eq!(pass.frames, 1, "Result should be read after the first frame");
+    assert_eq!(pass.pc, 0x0800_0004, "Report should hold the idle loop PC");
+    assert_eq!(fail.outcome, TestOutcome::Fail("test 3 failed".to_string()), "Failing test number");
+    assert_eq!(fail.registers[12], 3, "Report should hold r12");
+}
+
+/// Scenario: Custom checks and timeouts
+#[test]
+fn test_rom_runner_custom_check_and_timeout() {
+    // Given a ROM that never reaches a result
+    let mut gba = Gba::new();
+    gba.load_rom(r12_rom(0));
+    let check: testing::TestCheck = Box::new(|gba| (gba.cycles_elapsed() > 1_000_000).then_some(TestOutcome::Pass));
+
+    // When a custom check passes after a few frames
+    let report = testing::run_test(&mut gba, TestProtocol::Custom(check), 10);
+    assert!(report.passed(), "Custom check should pass");
+    assert_eq!(report.frames, 4, "Check should run after each frame");
+
+    // And a check that never finishes times out
+    let report = testing::run_test(&mut gba, TestProtocol::Custom(Box::new(|_| None)), 3);
+    assert_eq!(report.outcome, TestOutcome::Timeout, "Should time out");
+    assert_eq!(report.frames, 3, "Should stop at the frame limit");
+}
+
+/// Scenario: Screen-based suites compare the settled screen with a reference
+#[test]
+fn test_rom_runner_compares_static_screen() {
+    // Given a ROM that idles on a black screen
+    let mut gba = Gba::new();
+    gba.load_rom(r12_rom(0));
+
+    // When the reference is black, the run passes once the screen settles
+    let report = testing::run_test(&mut gba, TestProtocol::AgbAges(vec![0; 240 * 160]), 100);
+    assert!(report.passed(), "Matching screen should pass: {:?}", report.outcome);
+    assert_eq!(report.frames, 61, "Screen must be stable for 60 frames");
+
+    // When one reference pixel differs, the run fails
+    let mut reference = vec![0; 240 * 160];
+    reference[0] = 0x00FF_FFFF;
+    let report = testing::run_test(&mut gba, TestProtocol::AgbAges(reference), 100);
+    assert_eq!(
+        report.outcome,
+        TestOutcome::Fail("1 pixels differ from the reference".to_string()),
+        "Mismatch should be reported"
+    );
+}
+
+/// Scenario: External gba-tests ROMs pass when available
+///
+/// Set `RGBA_TEST_ROMS` to a directory containing jsmolka's gba-tests ROMs.
+#[test]
+fn external_gba_tests_pass_when_present() {
+    let Ok(dir) = std::env::var("RGBA_TEST_ROMS") else {
+        return;
+    };
+    for name in ["arm.gba", "thumb.gba", "memory.gba", "bios.gba"] {
+        let path = std::path::Path::new(&dir).join(name);
+        if !path.exists() {
+            continue;
+        }
+        let report = testing::run_test_rom(&path, TestProtocol::R12Zero).expect("ROM should load");
+        assert!(report.passed(), "{}: {:?}", name, report.outcome);
+    }
+}
+
+/// Scenario: With a BIOS file and LLE handling, SWIs run the BIOS's own code
+#[test]
+fn lle_swis_enter_the_bios_supervisor_vector() {
+    use rgba::SwiHandling;
+
+    // Given a BIOS whose SWI handler loads 0x42 into r0 and returns
+    let mut bios = vec![0u8; 0x4000];
+    for (addr, word) in [
+        (0x008, 0xEA00_003Cu32), // b 0x100
+        (0x100, 0xE3A0_0042),    // mov r0, #0x42
+        (0x104, 0xE1B0_F00E),    // movs pc, lr
+    ] {
+        bios[addr..addr + 4].copy_from_slice(&word.to_le_bytes());
+    }
+    let path = std::env::temp_dir().join(format!("rgba-lle-bios-{}.bin", std::process::id()));
+    std::fs::write(&path, &bios).unwrap();
+
+    // And a ROM that asks for Div (SWI 6) with a live LR
+    let rom = rgba::asm::assemble(
+        0x0800_0000,
+        "mov r0, #7
+         mov r1, #2
+         mov lr, #0x1200
+         swi #0x60000
+         loop: b loop",
+    )
+    .unwrap();
+    let mut gba = Gba::new();
+    gba.load_rom(rom);
+    gba.set_swi_handling(SwiHandling::Lle);
+    gba.load_bios_path(path.to_str().unwrap()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let mode = gba.cpu_get_cpsr() & 0x1F;
+
+    // When the SWI executes
+    for _ in 0..4 {
+        gba.step();
+    }
+
+    // Then it lands on the Supervisor vector with the caller's state saved
+    assert_eq!(gba.cpu.get_pc(), 0x0000_0008, "SWI vector");
+    assert_eq!(gba.cpu_get_cpsr() & 0xBF, 0x93, "Supervisor, IRQs off, ARM");
+    assert_eq!(gba.cpu_reg(14), 0x0800_0010, "LR_svc is the next instruction");
+
+    // And the BIOS code, not the emulated Div, produces the result
+    for _ in 0..4 {
+        gba.step();
+    }
+    assert_eq!(gba.cpu_reg(0), 0x42, "BIOS handler ran");
+    assert_eq!(gba.cpu_get_cpsr() & 0x1F, mode, "Caller's mode restored");
+    assert_eq!(gba.cpu_reg(14), 0x1200, "Caller's LR untouched");
+
+    // When switching back to HLE and resetting
+    gba.set_swi_handling(SwiHandling::Hle);
+    gba.reset();
+    for _ in 0..4 {
+        gba.step();
+    }
+
+    // Then Div is emulated without entering the BIOS
+    assert_eq!(gba.cpu_reg(0), 3, "7 / 2 emulated");
+    assert_eq!(gba.swi_handling(), SwiHandling::Hle, "Setting kept");
+}
+
+/// Scenario: VBlankIntrWait sleeps through other interrupts until the next VBlank
+#[test]
+fn vblank_intr_wait_sleeps_until_vblank() {
+    // Given a ROM taking VBlank and HBlank IRQs whose handler acknowledges
+    // them in IF and in the BIOS IntrCheck word, then loops on SWI 5
+    let rom = rgba::asm::assemble(
+        0x0800_0000,
+        "ldr r0, =0x03007FFC
+         ldr r1, =handler
+         str r1, [r0]
+         ldr r0, =0x04000000
+         mov r1, #0x18
+         strh r1, [r0, #4]
+         add r2, r0, #0x200
+         mov r1, #3
+         strh r1, [r2]
+         mov r1, #1
+         strh r1, [r2, #8]
+         loop: swi #0x50000
+         add r5, r5, #1
+         b loop
+         handler: ldr r0, =0x04000200
+         ldrh r1, [r0, #2]
+         ldrh r2, [r0]
+         and r1, r1, r2
+         strh r1, [r0, #2]
+         ldr r0, =0x03007FF8
+         ldrh r2, [r0]
+         orr r2, r2, r1
+         strh r2, [r0]
+         add r6, r6, #1
+         bx lr",
+    )
+    .unwrap();
+    let mut gba = Gba::new();
+    gba.load_rom(rom);
+
+    // When three frames run
+    for _ in 0..3 {
+        gba.run_frame();
+    }
+
+    // Then every HBlank IRQ was serviced, but the wait only ended per VBlank
+    assert!(gba.cpu_reg(6) > 3 * 200, "HBlank IRQs serviced: {}", gba.cpu_reg(6));
+    assert!((2..=3).contains(&gba.cpu_reg(5)), "One wake per frame: {}", gba.cpu_reg(5));
+    assert!(gba.cpu.is_halted(), "Asleep between interrupts");
+    assert_eq!(gba.cpu.intr_wait_mask(), Some(0x0001), "Waiting for VBlank");
+
+    // And a saved state keeps the wait
+    let state = gba.save_state();
+    let mut other = Gba::new();
+    other.load_rom(gba.mem.rom().to_vec());
+    other.load_state(&state).unwrap();
+    assert_eq!(other.cpu.intr_wait_mask(), Some(0x0001), "Wait restored");
+}
+
+/// Scenario: Stop freezes the machine until a keypad interrupt
+#[test]
+fn stop_freezes_the_clocks_until_a_keypad_interrupt() {
+    // Given a ROM with timer 0 running and a keypad IRQ on A enabled in IE
+    // (IME off) that calls the Stop SWI, then acknowledges IF and stops
+    // again through HALTCNT bit 7
+    let rom = rgba::asm::assemble(
+        0x0800_0000,
+        "ldr r0, =0x04000000
+         add r2, r0, #0x100
+         mov r1, #0x80
+         strh r1, [r2, #2]
+         ldr r1, =0x4001
+         strh r1, [r2, #0x32]
+         add r3, r0, #0x200
+         mov r1, #0x1000
+         strh r1, [r3]
+         swi #0x30000
+         add r5, r5, #1
+         strh r1, [r3, #2]
+         mov r1, #0x80
+         strb r1, [r3, #0x101]
+         add r5, r5, #1
+         end: b end",
+    )
+    .unwrap();
+    let mut gba = Gba::new();
+    gba.load_rom(rom);
+
+    // When a frame runs
+    gba.run_frame();
+
+    // Then the CPU is stopped and the PPU and timer no longer advance
+    assert!(gba.cpu.is_stopped(), "Stop SWI entered STOP");
+    let (vcount, timer) = (gba.ppu.get_vcount(), gba.mem.peek_half(0x0400_0100));
+    gba.run_frame();
+    assert!(gba.cpu.is_stopped(), "No display IRQ can wake it");
+    assert_eq!(gba.ppu.get_vcount(), vcount, "VCOUNT frozen");
+    assert_eq!(gba.mem.peek_half(0x0400_0100), timer, "Timer frozen");
+    assert_eq!(gba.cpu_reg(5), 0, "Nothing ran");
+
+    // When A is pressed
+    gba.input_mut().press_key(KeyState::A);
+    for _ in 0..100 {
+        if gba.cpu_reg(5) != 0 {
+            break;
+        }
+        gba.step();
+    }
+
+    // Then the keypad IRQ wakes it without IME
+    assert_eq!(gba.cpu_reg(5), 1, "Woke on the keypad IRQ");
+    assert!(!gba.cpu.is_stopped(), "Awake");
+
+    // When A is released and the ROM writes 0x80 to HALTCNT
+    gba.input_mut().release_key(KeyState::A);
+    gba.run_frame();
+
+    // Then it is stopped again, and a saved state keeps it stopped
+    assert!(gba.cpu.is_stopped(), "HALTCNT bit 7 entered STOP");
+    assert_eq!(gba.cpu_reg(5), 1, "Still asleep");
+    let state = gba.save_state();
+    let mut other = Gba::new();
+    other.load_rom(gba.mem.rom().to_vec());
+    other.load_state(&state).unwrap();
+    assert!(other.cpu.is_stopped(), "STOP restored");
+}
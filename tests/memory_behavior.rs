@@ -4,7 +4,10 @@
 //! including different memory regions with different access timings and
 //! characteristics.
 
-use rgba::Memory;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+use rgba::{Gba, Interrupt, MemView, Memory, PhiOutput, SioGpio, SioPins};
 
 /// Scenario: Memory system initializes with correct memory map
 #[test]
@@ -74,8 +77,9 @@ fn memory_has_correct_access_timings() {
     assert_eq!(mem.get_access_cycles(0x0700_0000, false), 1, "OAM should take 1 cycle");
     assert_eq!(mem.get_access_cycles(0x0700_0000, true), 1, "OAM should take 1 cycle");
 
-    // ROM WS0: 3 cycles (sequential), 3 cycles (non-sequential) - can be configured
-    assert_eq!(mem.get_access_cycles(0x0800_0000, false), 3, "ROM WS0 should take 3 cycles");
+    // ROM WS0: 5 cycles (non-sequential), 3 cycles (sequential) with WAITCNT at 0 - can be configured
+    assert_eq!(mem.get_access_cycles(0x0800_0000, false), 5, "ROM WS0 should take 5 cycles");
+    assert_eq!(mem.get_access_cycles(0x0800_0000, true), 3, "ROM WS0 should take 3 cycles");
 }
 
 /// Scenario: IO registers have correct read/write behavior
@@ -205,19 +209,26 @@ fn memory_reset_clears_all_regions() {
 /// Scenario: Waitstate configuration affects memory access timing
 #[test]
 fn waitstate_configuration_modifies_access_cycles() {
-    let mem = Memory::new();
-
-    // Given: Default ROM access is 3 cycles
-    assert_eq!(mem.get_access_cycles(0x0800_0000, false), 3);
-
-    // When: Waitstate register is configured for faster access
-    // 0x0400_0204: WAITCNT
-    // - WS0 non-sequential: 0 cycles (fastest)
-    // - WS0 sequential: 0 cycles
-    // mem.write_half(0x0400_0204, 0x0000); // All waitstates = 0
+    let mut mem = Memory::new();
 
-    // Then: ROM access should be faster
-    // assert_eq!(mem.get_access_cycles(0x0800_0000, false), 1);
+    // Given: Default ROM access is 4 + 1 cycles, SRAM 4 + 1
+    assert_eq!(mem.get_access_cycles(0x0800_0000, false), 5);
+    assert_eq!(mem.get_access_cycles(0x0E00_0000, false), 5);
+
+    // When: Waitstate register is configured the way most games do
+    // 0x0400_0204: WAITCNT = 0x4317
+    // - SRAM: 8 waits
+    // - WS0: 3 first, 1 second
+    // - WS1: 4 first, 4 second; WS2: 8 first, 8 second
+    // - Prefetch on
+    mem.write_half(0x0400_0204, 0x4317);
+
+    // Then: ROM WS0 access is faster and SRAM slower
+    assert_eq!(mem.get_access_cycles(0x0800_0000, false), 4);
+    assert_eq!(mem.get_access_cycles(0x0800_0000, true), 2);
+    assert_eq!(mem.get_access_cycles(0x0A00_0000, true), 5);
+    assert_eq!(mem.get_access_cycles(0x0C00_0000, true), 9);
+    assert_eq!(mem.get_access_cycles(0x0E00_0000, false), 9);
 }
 
 /// Scenario: Palette RAM stores color data correctly
@@ -280,3 +291,415 @@ fn oam_stores_sprite_attributes_correctly() {
     // And: Should support 128 sprites (max)
     // Last sprite at 0x0700_03FC
 }
+
+/// Scenario: Open bus reads are an opt-in accuracy option
+#[test]
+fn open_bus_reads_return_prefetched_opcode() {
+    // Given the CPU's last prefetch left an opcode on the bus
+    let mut mem = Memory::new();
+    mem.set_open_bus_latch(0xE3A0_1403);
+
+    // Then unmapped reads return 0 by default
+    assert_eq!(mem.read_word(0x1000_0000), 0, "Open bus should be off by default");
+
+    // When open bus is enabled
+    mem.open_bus_enabled = true;
+
+    // Then unmapped reads return the prefetched opcode
+    assert_eq!(mem.read_word(0x1000_0000), 0xE3A0_1403, "Word read of the open bus");
+    assert_eq!(mem.read_byte(0x1000_0003), 0xE3, "Byte read of the open bus");
+}
+
+/// Scenario: Turning sound off through SOUNDCNT_X clears and locks the PSG registers
+#[test]
+fn soundcnt_x_power_off_clears_psg_registers() {
+    // Given sound is powered and channel 1 is configured
+    let mut mem = Memory::new();
+    mem.write_half(0x0400_0084, 0x0080);
+    mem.write_half(0x0400_0060, 0x0019);
+    mem.write_half(0x0400_0062, 0xF080);
+    mem.write_half(0x0400_0080, 0x7777);
+    assert_eq!(mem.read_half(0x0400_0062), 0xF080, "Writable while powered");
+
+    // When the master enable is cleared
+    mem.write_half(0x0400_0084, 0x0000);
+
+    // Then every PSG register reads back as zero
+    assert!(!mem.sound_powered(), "Sound should be off");
+    assert_eq!(mem.read_half(0x0400_0060), 0, "SOUND1CNT_L cleared");
+    assert_eq!(mem.read_half(0x0400_0062), 0, "SOUND1CNT_H cleared");
+    assert_eq!(mem.read_half(0x0400_0080), 0, "SOUNDCNT_L cleared");
+
+    // And writes are ignored, except for the length counters
+    mem.write_half(0x0400_0060, 0x0019);
+    mem.write_half(0x0400_0062, 0xF0BF);
+    assert_eq!(mem.read_half(0x0400_0060), 0, "Sweep write ignored");
+    // (the length is write-only, so check the stored register bits)
+    assert_eq!(mem.io()[0x62], 0x3F, "Only the length is written");
+    assert_eq!(mem.io()[0x63], 0x00, "Envelope write ignored");
+
+    // When sound is powered again the registers accept writes
+    mem.write_half(0x0400_0084, 0x0080);
+    mem.write_half(0x0400_0060, 0x0019);
+    assert_eq!(mem.read_half(0x0400_0060), 0x0019, "Writable after power-on");
+}
+
+/// Scenario: Reads past the end of ROM return the address pattern unless the cart mirrors
+#[test]
+fn rom_reads_past_the_end_follow_cart_mirroring() {
+    // Given a 1KB ROM with no mirroring
+    let mut mem = Memory::new();
+    let mut rom = vec![0u8; 0x400];
+    rom[0] = 0x11;
+    rom[1] = 0x22;
+    mem.load_rom(rom.clone());
+    assert_eq!(mem.rom_mirroring(), false, "Regular carts don't mirror");
+
+    // Then reads past the end return (addr / 2) & 0xFFFF in every waitstate region
+    assert_eq!(mem.read_half(0x0800_0400), 0x0200, "Halfword past the end");
+    assert_eq!(mem.read_half(0x0A01_2344), 0x91A2, "WS1 pattern");
+    assert_eq!(mem.read_word(0x0C00_1000), 0x0801_0800, "Word is two pattern halves");
+
+    // When the cart is a Classic NES Series title
+    rom[0xAC] = b'F';
+    mem.load_rom(rom);
+
+    // Then the ROM repeats instead
+    assert_eq!(mem.rom_mirroring(), true, "Game code F*** mirrors");
+    assert_eq!(mem.read_half(0x0800_0400), 0x2211, "First mirror");
+    assert_eq!(mem.read_half(0x09FF_FC00), 0x2211, "Last mirror");
+}
+
+/// Scenario: The 8-bit SRAM bus repeats bytes on reads and keeps one byte lane on writes
+#[test]
+fn sram_wide_accesses_use_a_single_byte() {
+    // Given erased SRAM (0xFF) holding one known byte
+    let mut mem = Memory::new();
+    mem.write_byte(0x0E00_0010, 0x5A);
+
+    // Then halfword and word reads replicate the addressed byte
+    assert_eq!(mem.read_half(0x0E00_0010), 0x5A5A, "Halfword read repeats the byte");
+    assert_eq!(mem.read_word(0x0E00_0010), 0x5A5A_5A5A, "Word read repeats the byte");
+
+    // When wider values are written
+    mem.write_half(0x0E00_0021, 0x1234);
+    mem.write_word(0x0E00_0032, 0xAABB_CCDD);
+
+    // Then only the byte lane matching the address is stored
+    assert_eq!(mem.read_byte(0x0E00_0020), 0xFF, "Neighbouring byte untouched");
+    assert_eq!(mem.read_byte(0x0E00_0021), 0x12, "Odd halfword write stores the high byte");
+    assert_eq!(mem.read_byte(0x0E00_0030), 0xFF, "Aligned byte untouched");
+    assert_eq!(mem.read_byte(0x0E00_0032), 0xBB, "Word write stores lane 2");
+    assert_eq!(mem.read_byte(0x0E00_0033), 0xFF, "Only one byte written");
+}
+
+/// Scenario: EWRAM and IWRAM mirror across their whole 16MB blocks
+#[test]
+fn work_ram_mirrors_fill_the_whole_block() {
+    // Given an IRQ handler pointer written through the top-of-IWRAM mirror
+    let mut mem = Memory::new();
+    mem.write_word(0x03FF_FFFC, 0x0300_1234);
+
+    // Then it lands at the real BIOS IRQ vector
+    assert_eq!(mem.read_word(0x0300_7FFC), 0x0300_1234, "0x03FFFFFC mirrors 0x03007FFC");
+    assert_eq!(mem.read_word_fast(0x03FF_FFFC), 0x0300_1234, "Fast path sees the mirror");
+
+    // And EWRAM repeats every 256KB up to 0x02FFFFFF
+    mem.write_word(0x0200_0100, 0xCAFE_F00D);
+    assert_eq!(mem.read_word(0x02FC_0100), 0xCAFE_F00D, "Last EWRAM mirror");
+    assert_eq!(mem.read_word_fast(0x0284_0100), 0xCAFE_F00D, "Fast path EWRAM mirror");
+
+    // And mirrors keep their region's timing
+    assert_eq!(mem.get_access_cycles(0x02FC_0100, false), 3, "EWRAM mirror timing");
+    assert_eq!(mem.get_access_cycles(0x03FF_FFFC, false), 1, "IWRAM mirror timing");
+}
+
+/// Scenario: A memory view reads RAM and registers from another thread
+#[test]
+fn memory_view_reads_through_mirrors_without_side_effects() {
+    // Given EWRAM, IWRAM and VRAM data, an enabled IRQ and a write-only DMA register
+    let mut mem = Memory::new();
+    mem.write_word(0x0200_0100, 0xCAFE_F00D);
+    mem.write_half(0x0300_0010, 0x1234);
+    mem.write_half(0x0601_0000, 0x5678);
+    mem.write_half(0x0400_0200, Interrupt::VBLANK.bits());
+    mem.write_word(0x0400_00B0, 0x0200_0000);
+
+    // When a view is read on a worker thread
+    let view = mem.view();
+    let (ewram, iwram, obj, ie, dma_src, copy) = std::thread::scope(|s| {
+        s.spawn(|| {
+            (
+                view.read_word(0x02FC_0100),
+                view.read_half(0x03FF_8011),
+                view.read_half(0x0601_8000),
+                view.read_half(0x0400_0200),
+                view.read_word(0x0400_00B0),
+                view.copy_bytes(0x0200_0100, 4),
+            )
+        })
+        .join()
+        .unwrap()
+    });
+
+    // Then it follows the bus mirroring and matches a side-effect-free peek
+    assert_eq!(ewram, 0xCAFE_F00D, "Last EWRAM mirror");
+    assert_eq!(iwram, 0x1234, "IWRAM mirror, force-aligned");
+    assert_eq!(obj, 0x5678, "OBJ VRAM mirror");
+    assert_eq!(ie, Interrupt::VBLANK.bits(), "IE from the controller");
+    let peeked = mem.peek_word(0x0400_00B0);
+    assert_eq!(dma_src, peeked, "Write-only bits masked");
+    assert_eq!(copy, vec![0x0D, 0xF0, 0xFE, 0xCA], "Copied bytes");
+
+    // And a palette-only view leaves the other regions empty
+    let palette = [0x11; 0x400];
+    let detached = MemView::new(&palette);
+    assert_eq!(detached.read_half(0x0500_0000), 0x1111, "Palette readable");
+    assert_eq!(detached.read_word(0x0200_0000), 0, "Missing regions read 0");
+}
+
+/// Scenario: Block copies match an element-by-element copy
+#[test]
+fn copy_block_matches_per_element_copies() {
+    // Given a ROM with a recognisable pattern
+    let mut mem = Memory::new();
+    let rom: Vec<u8> = (0..0x400u32).map(|i| i as u8).collect();
+    mem.load_rom(rom);
+
+    // When a block of words is copied from ROM to VRAM
+    mem.copy_block(0x0800_0100, 0x0600_0000, 16, 4);
+
+    // Then VRAM holds the ROM bytes
+    assert_eq!(mem.read_word(0x0600_0000), 0x0302_0100, "First word copied");
+    assert_eq!(mem.read_word(0x0600_003C), 0x3F3E_3D3C, "Last word copied");
+    assert_eq!(mem.read_word(0x0600_0040), 0, "Nothing past the block");
+
+    // When halfwords are copied forward into an overlapping EWRAM range
+    mem.write_half(0x0200_0000, 0xAAAA);
+    mem.write_half(0x0200_0002, 0xBBBB);
+    mem.copy_block(0x0200_0000, 0x0200_0002, 3, 2);
+
+    // Then the first element repeats, as with a forward read/write loop
+    assert_eq!(mem.read_half(0x0200_0006), 0xAAAA, "Forward overlap smears");
+
+    // When the destination is an IO register
+    mem.copy_block(0x0800_0000, 0x0400_0000, 1, 2);
+
+    // Then the copy still goes through the register write path
+    assert_eq!(mem.read_half(0x0400_0000), 0x0100, "DISPCNT written");
+
+    // When a block is copied into OAM while the PPU draws with video access timing on
+    mem.video_access_timing = true;
+    mem.copy_block(0x0800_0100, 0x0700_0000, 4, 4);
+
+    // Then OAM refuses it, as it does element by element
+    assert_eq!(mem.peek_word(0x0700_0000), 0, "OAM locked while drawing");
+}
+
+/// Scenario: IO registers mask write-only and unused bits on reads
+#[test]
+fn io_register_reads_apply_read_masks() {
+    // Given sound is powered and channel 1 is fully written
+    let mut mem = Memory::new();
+    mem.write_half(0x0400_0084, 0x00FF);
+    mem.write_half(0x0400_0060, 0xFFFF);
+    mem.write_half(0x0400_0062, 0xFFFF);
+
+    // Then only the readable bits come back
+    assert_eq!(mem.read_half(0x0400_0060), 0x007F, "SOUND1CNT_L has 7 bits");
+    assert_eq!(mem.read_half(0x0400_0062), 0xFFC0, "Length is write-only");
+    assert_eq!(mem.read_half(0x0400_0084), 0x0080, "Status bits are not writable");
+
+    // And read-only registers ignore writes
+    mem.io_mut()[0x130] = 0xFF;
+    mem.write_half(0x0400_0130, 0x0000);
+    assert_eq!(mem.read_byte(0x0400_0130), 0xFF, "KEYINPUT is read-only");
+}
+
+/// Scenario: IE, IF and IME answer at their memory-mapped offsets, byte by byte
+#[test]
+fn interrupt_registers_follow_the_memory_map() {
+    let mut mem = Memory::new();
+
+    // Then every byte of 0x200-0x20B maps to IE, IF, IME or nothing
+    let expected = [
+        Some(0x200), Some(0x200), Some(0x202), Some(0x202),
+        None, None, None, None,
+        Some(0x208), Some(0x208), Some(0x208), Some(0x208),
+    ];
+    for (i, &offset) in expected.iter().enumerate() {
+        let addr = 0x0400_0200 + i as u32;
+        assert_eq!(Memory::get_interrupt_register_offset(addr), offset, "Offset of {:08X}", addr);
+        assert_eq!(Memory::is_interrupt_register(addr), offset.is_some(), "Range check of {:08X}", addr);
+    }
+    assert_eq!(Memory::get_interrupt_register_offset(0x0400_0000), None, "DISPCNT is not IE");
+
+    // When writing each IE byte, only the defined interrupt bits stick
+    mem.write_byte(0x0400_0200, 0xFF);
+    mem.write_byte(0x0400_0201, 0xFF);
+    assert_eq!(mem.read_byte(0x0400_0200), 0xFF, "IE low byte");
+    assert_eq!(mem.read_byte(0x0400_0201), 0x3F, "IE high byte");
+    assert_eq!(mem.interrupt.read_register(0x200), 0x3FFF, "Controller sees IE at 0x200");
+
+    // When interrupts are pending, each IF byte reads and acknowledges on its own
+    mem.interrupt.request(Interrupt::VBLANK | Interrupt::DMA0);
+    assert_eq!(mem.read_byte(0x0400_0202), 0x01, "IF low byte");
+    assert_eq!(mem.read_byte(0x0400_0203), 0x01, "IF high byte");
+    mem.write_byte(0x0400_0202, 0xFF);
+    assert_eq!(mem.read_byte(0x0400_0202), 0x00, "Low byte acknowledged");
+    assert_eq!(mem.read_byte(0x0400_0203), 0x01, "High byte still pending");
+    mem.write_byte(0x0400_0203, 0x01);
+    assert_eq!(mem.interrupt.read_register(0x202), 0, "Controller sees IF at 0x202");
+
+    // When writing IME, only bit 0 of its first byte counts
+    mem.write_byte(0x0400_0208, 0xFF);
+    assert_eq!(mem.read_byte(0x0400_0208), 0x01, "IME reads back bit 0");
+    for addr in 0x0400_0209..=0x0400_020B {
+        mem.write_byte(addr, 0x00);
+        assert_eq!(mem.read_byte(addr), 0x00, "Unused IME byte {:08X}", addr);
+    }
+    assert_eq!(mem.interrupt.read_register(0x208), 1, "Upper bytes do not clear IME");
+    mem.write_byte(0x0400_0209, 0xFF);
+    mem.write_byte(0x0400_0208, 0x00);
+    assert_eq!(mem.read_half(0x0400_0208), 0x0000, "IME off, upper byte ignored");
+    mem.write_byte(0x0400_020A, 0xFF);
+    assert_eq!(mem.read_word(0x0400_0208), 0, "Writes past bit 0 never enable IME");
+}
+
+/// Link port peripheral that counts how often the game samples its pins
+struct CountingPins(Arc<AtomicU32>);
+
+impl SioGpio for CountingPins {
+    fn write(&mut self, _pins: SioPins) {}
+
+    fn read(&mut self) -> u8 {
+        self.0.fetch_add(1, Ordering::Relaxed);
+        0x0F
+    }
+}
+
+/// Scenario: peek and poke reach memory without the bus side effects a debugger must not cause
+#[test]
+fn peek_and_poke_bypass_bus_side_effects() {
+    // Given: a ROM, a pending VBlank IRQ and a peripheral on the link port in GP mode
+    let mut gba = Gba::new();
+    gba.load_rom(vec![0x11, 0x22, 0x33, 0x44]);
+    let samples = Arc::new(AtomicU32::new(0));
+    gba.set_sio_gpio_handler(CountingPins(Arc::clone(&samples)));
+    gba.write_half(0x0400_0134, 0x8000);
+    gba.mem.interrupt.request(Interrupt::VBLANK);
+    let sampled = samples.load(Ordering::Relaxed);
+
+    // When: a debugger peeks at RCNT, IF, ROM and a write-only DMA register
+    // and disassembles RCNT as Thumb code
+    let rcnt = gba.peek_half(0x0400_0134);
+    let (op, _) = gba.disassemble_at(0x0400_0134, true);
+
+    // Then: values match what the game reads, but the peripheral is never sampled
+    assert_eq!(samples.load(Ordering::Relaxed), sampled, "Peek does not poll the pins");
+    assert_eq!(op, rcnt as u32, "Disassembly fetches the peeked value");
+    assert_eq!(gba.peek_half(0x0400_0202), 0x0001, "IF shows the pending VBlank");
+    assert_eq!(gba.peek_word(0x0800_0002), 0x4433_2211, "Word peeks force-align");
+    assert_eq!(gba.peek_word(0x0400_00D4), 0, "DMA3SAD is write-only");
+    gba.mem.read_half(0x0400_0134);
+    assert_eq!(samples.load(Ordering::Relaxed), sampled + 1, "A bus read does poll");
+    assert_eq!(rcnt & 0x8000, 0x8000, "RCNT mode bits");
+
+    // When: IF is poked with the pending bit and one more
+    gba.poke_half(0x0400_0202, 0x0003);
+
+    // Then: nothing is acknowledged; IF holds exactly the poked value
+    assert_eq!(
+        gba.mem.interrupt.if_raw,
+        Interrupt::VBLANK | Interrupt::HBLANK,
+        "Poke sets IF instead of clearing it"
+    );
+
+    // When: single bytes are poked into VRAM and ROM
+    gba.poke_byte(0x0600_0001, 0xAB);
+    gba.poke_word(0x0800_0000, 0xE12F_FF1E);
+
+    // Then: only the addressed bytes change and read-only ROM is patched
+    assert_eq!(gba.peek_half(0x0600_0000), 0xAB00, "No byte-write mirroring");
+    assert_eq!(gba.mem.read_word(0x0800_0000), 0xE12F_FF1E, "ROM patched");
+
+    // When: DISPCNT is poked and the machine steps
+    gba.poke_half(0x0400_0000, 0x0403);
+    gba.step();
+
+    // Then: the PPU picks up the new mode
+    assert_eq!(gba.ppu.get_dispcnt(), 0x0403, "Poked register reaches the PPU");
+}
+
+/// Scenario: WAITCNT keeps both bytes and decodes every field
+#[test]
+fn waitcnt_decodes_the_full_register() {
+    // Given: a fresh memory map, where WAITCNT is 0
+    let mut mem = Memory::new();
+    let wait = mem.waitcnt();
+    assert_eq!(wait.first, [4, 4, 4], "Default first access waits");
+    assert_eq!(wait.second, [2, 4, 8], "Default second access waits");
+    assert_eq!(wait.phi, PhiOutput::Disabled, "PHI off");
+
+    // When: the game writes the register a byte at a time, read-only bits included
+    mem.write_byte(0x0400_0204, 0xB6);
+    mem.write_byte(0x0400_0205, 0xFF);
+
+    // Then: the high byte is kept, minus the unused and read-only bits
+    assert_eq!(mem.read_half(0x0400_0204), 0x5FB6, "Bits 13 and 15 read as 0");
+    let wait = mem.waitcnt();
+    assert_eq!(wait.sram, 2, "SRAM wait");
+    assert_eq!(wait.first, [3, 3, 8], "First access waits");
+    assert_eq!(wait.second, [1, 1, 1], "Second access waits");
+    assert_eq!(wait.phi, PhiOutput::Mhz16, "PHI terminal output");
+    assert!(wait.prefetch, "Prefetch buffer enabled");
+    assert!(!wait.cgb_pak, "A GBA cartridge");
+}
+
+/// Scenario: the access heatmap counts bus accesses per area and page
+#[cfg(feature = "stats")]
+#[test]
+fn access_heatmap_counts_reads_and_writes() {
+    use rgba::{PageStats, RegionStats};
+
+    // Given: a memory map counting accesses per page too
+    let mut mem = Memory::new();
+    mem.set_page_heatmap(true);
+
+    // When: a word is written to EWRAM, two words read back, and IWRAM read once
+    mem.write_word(0x0200_0000, 0x1234_5678);
+    mem.read_word(0x0200_0000);
+    mem.read_word(0x0200_1004);
+    mem.read_byte(0x0300_0010);
+
+    // Then: each access counts once, EWRAM words costing two 3-cycle halves
+    let heatmap = mem.access_heatmap();
+    let ewram = RegionStats {
+        name: "EWRAM",
+        base: 0x0200_0000,
+        reads: 2,
+        writes: 1,
+        cycles: 18,
+    };
+    let iwram = RegionStats {
+        name: "IWRAM",
+        base: 0x0300_0000,
+        reads: 1,
+        writes: 0,
+        cycles: 1,
+    };
+    assert_eq!(heatmap.regions, vec![ewram, iwram], "Per-area counts");
+    let page = |addr, reads, writes| PageStats { addr, reads, writes };
+    assert_eq!(
+        heatmap.pages,
+        vec![page(0x0200_0000, 1, 1), page(0x0200_1000, 1, 0), page(0x0300_0000, 1, 0)],
+        "Per-page counts"
+    );
+
+    // When: the counts are reset
+    mem.reset_access_heatmap();
+
+    // Then: nothing is reported until the next access
+    assert!(mem.access_heatmap().regions.is_empty(), "Areas cleared");
+    assert!(mem.access_heatmap().pages.is_empty(), "Pages cleared");
+}
@@ -0,0 +1,1378 @@
+//! Behavior Driven Development tests for the GBA PPU
+//!
+//! These tests describe the expected behavior of the picture processing unit
+//! and the render backends that consume its state.
+
+use rgba::asm;
+use rgba::debug::{diff_frames, BlendEffect};
+use rgba::export::{dump_tilemap, dump_tileset};
+use rgba::{
+    AccuracyProfile, AccuracySettings, AffineParams, BgControl, ByteView, CharBlock, ColorCorrection,
+    DisplayControlView, Gba, Interrupt, Layer, MemView, NullRenderer, Ppu, Renderer, ScreenBlock,
+    SoftwareRenderer, VideoFormat,
+};
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+/// Scenario: The software renderer draws a mode 3 bitmap
+#[test]
+fn software_renderer_draws_mode3_bitmap() {
+    // Given a GBA in mode 3 with BG2 enabled and a red first pixel
+    let mut gba = Gba::new();
+    gba.write_half(0x0400_0000, 0x0403);
+    gba.write_half(0x0600_0000, 0x001F);
+
+    // When a frame is rendered through the software backend
+    let mut renderer = SoftwareRenderer::new();
+    gba.run_frame_with_renderer(&mut renderer);
+
+    // Then the framebuffer holds the converted color
+    assert_eq!(renderer.framebuffer()[0], 0x00FF0000, "First pixel should be red");
+    assert_eq!(renderer.framebuffer()[1], 0, "Second pixel should be black");
+}
+
+/// Scenario: Mode 5 shows the backdrop outside its 160x128 frame
+#[test]
+fn mode5_shows_backdrop_outside_its_frame() {
+    // Given mode 5 with VRAM filled white and a red backdrop
+    let mut gba = Gba::new();
+    gba.write_half(0x0400_0000, 0x0405);
+    gba.write_half(0x0500_0000, 0x001F);
+    for offset in (0..0x14000).step_by(2) {
+        gba.write_half(0x0600_0000 + offset, 0x7FFF);
+    }
+
+    // When the frame is drawn
+    let mut fb = vec![0u32; 240 * 160];
+    gba.framebuffer_xrgb8888(&mut fb);
+
+    // Then only the 160x128 frame shows VRAM
+    assert_eq!(fb[127 * 240 + 159], 0x00FFFFFF, "Last frame pixel");
+    assert_eq!(fb[10 * 240 + 200], 0x00FF0000, "Right of the frame");
+    assert_eq!(fb[128 * 240], 0x00FF0000, "Below the frame");
+}
+
+/// Scenario: A custom backend receives every visible scanline
+#[test]
+fn custom_renderer_receives_every_visible_scanline() {
+    struct CountingRenderer {
+        lines: Vec<u16>,
+    }
+
+    impl Renderer for CountingRenderer {
+        fn render_scanline(&mut self, line: u16, _state: &rgba::PpuState, _mem: &MemView) {
+            self.lines.push(line);
+        }
+    }
+
+    // Given a GBA and a renderer that records scanline numbers
+    let mut gba = Gba::new();
+    let mut renderer = CountingRenderer { lines: Vec::new() };
+
+    // When one frame is run
+    gba.run_frame_with_renderer(&mut renderer);
+
+    // Then lines 0..160 were delivered in order
+    assert_eq!(renderer.lines.len(), 160, "Every visible line should be rendered");
+    assert_eq!(renderer.lines[0], 0, "First line should be 0");
+    assert_eq!(renderer.lines[159], 159, "Last line should be 159");
+}
+
+/// Scenario: A frame state renders on another thread while emulation continues
+#[test]
+fn frame_state_renders_on_another_thread() {
+    // Given a mode 3 frame with a red first pixel
+    let mut gba = Gba::new();
+    gba.write_half(0x0400_0000, 0x0403);
+    gba.write_half(0x0600_0000, 0x001F);
+
+    // When the frame state is captured and the next frame turns the pixel blue
+    let state = gba.run_frame_state();
+    let worker = {
+        let state = state.clone();
+        std::thread::spawn(move || {
+            let mut renderer = SoftwareRenderer::new();
+            state.render(&mut renderer);
+            renderer.framebuffer()[0]
+        })
+    };
+    gba.write_half(0x0600_0000, 0x7C00);
+    gba.run_frame();
+
+    // Then the worker renders the captured frame, not the live one
+    assert_eq!(worker.join().unwrap(), 0x00FF0000, "Captured frame should still be red");
+    let mut renderer = SoftwareRenderer::new();
+    gba.frame_state().render(&mut renderer);
+    assert_eq!(renderer.framebuffer()[0], 0x000000FF, "Live frame should be blue");
+}
+
+/// Scenario: Scanline capture shows mid-frame palette changes line by line
+#[test]
+fn scanline_capture_shows_raster_changes() {
+    // Given an idle machine showing a red backdrop with scanline capture on
+    let mut gba = Gba::new();
+    gba.load_rom(idle_rom());
+    gba.write_half(0x0500_0000, 0x001F);
+    gba.set_scanline_capture(true);
+
+    // When the backdrop turns blue halfway down the frame
+    gba.run_scanlines(80);
+    gba.write_half(0x0500_0000, 0x7C00);
+    gba.run_scanlines(148);
+
+    // Then the capture keeps the color each line was drawn with
+    let capture = gba.scanline_capture().expect("capture should be enabled");
+    assert_eq!(capture[0], 0x00FF0000, "Top line should be red");
+    assert_eq!(capture[79 * 240], 0x00FF0000, "Line 79 should be red");
+    assert_eq!(capture[80 * 240], 0x000000FF, "Line 80 should be blue");
+    assert_eq!(capture[159 * 240 + 239], 0x000000FF, "Bottom line should be blue");
+
+    // And disabling capture releases the buffer
+    gba.set_scanline_capture(false);
+    assert!(gba.scanline_capture().is_none(), "Capture should be off");
+}
+
+/// Scenario: Render backends and frame states see raster effects per line
+#[test]
+fn renderers_and_frame_states_show_raster_changes() {
+    // Given a red backdrop that an HBlank handler turns blue after line 79
+    // and force-blanks after line 119
+    let mut gba = Gba::new();
+    gba.load_rom(idle_rom());
+    gba.write_half(0x0500_0000, 0x001F);
+    gba.on_hblank(|gba| match gba.ppu.get_vcount() {
+        79 => gba.write_half(0x0500_0000, 0x7C00),
+        119 => gba.write_half(0x0400_0000, 0x0080),
+        _ => {}
+    });
+
+    // When one frame goes to a backend and the next to a frame state
+    let mut renderer = SoftwareRenderer::new();
+    gba.run_frame_with_renderer(&mut renderer);
+    gba.write_half(0x0400_0000, 0x0000);
+    gba.write_half(0x0500_0000, 0x001F);
+    let mut deferred = SoftwareRenderer::new();
+    gba.run_frame_state().render(&mut deferred);
+
+    // Then both draw each line with the state it had at its HBlank
+    for fb in [renderer.framebuffer(), deferred.framebuffer()] {
+        assert_eq!(fb[79 * 240], 0x00FF0000, "Line 79 should be red");
+        assert_eq!(fb[80 * 240], 0x000000FF, "Line 80 should be blue");
+        assert_eq!(fb[119 * 240], 0x000000FF, "Line 119 should be blue");
+        assert_eq!(fb[120 * 240], 0x00FFFFFF, "Line 120 should be blank");
+    }
+}
+
+/// Capture sink the test can still read after handing it to the core
+#[derive(Clone, Default)]
+struct SharedSink(Arc<Mutex<Vec<u8>>>);
+
+impl SharedSink {
+    fn bytes(&self) -> Vec<u8> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+impl Write for SharedSink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Scenario: VBlank and HBlank hooks see the machine at the start of each blank
+#[test]
+fn blank_hooks_run_at_each_vblank_and_hblank() {
+    // Given: hooks recording the line each blank starts on, and a VBlank
+    // hook that pokes a counter into EWRAM like a cheat would
+    let mut gba = Gba::new();
+    gba.load_rom(idle_rom());
+    gba.write_half(0x0400_0004, 0x0008); // VBlank IRQ enable
+    let vblanks = Arc::new(Mutex::new(Vec::new()));
+    let hblanks = Arc::new(Mutex::new(Vec::new()));
+    let seen = vblanks.clone();
+    gba.on_vblank(move |gba| {
+        let requested = gba.mem.interrupt.if_raw.contains(Interrupt::VBLANK);
+        seen.lock().unwrap().push((gba.ppu.get_vcount(), requested));
+        let n = gba.read_byte(0x0200_0000);
+        gba.write_byte(0x0200_0000, n + 1);
+    });
+    let seen = hblanks.clone();
+    gba.on_hblank(move |gba| seen.lock().unwrap().push(gba.ppu.get_vcount()));
+
+    // When: two frames run one instruction at a time
+    gba.run_frame();
+    gba.run_frame();
+
+    // Then: each VBlank ran the hook once, on line 160, after its IRQ was
+    // requested
+    let calls = vblanks.lock().unwrap().clone();
+    assert_eq!(calls, vec![(160, true), (160, true)], "One call per VBlank");
+    assert_eq!(gba.read_byte(0x0200_0000), 2, "Hook wrote through the machine");
+
+    // And: every scanline's HBlank ran the other hook, in order
+    let lines = std::mem::take(&mut *hblanks.lock().unwrap());
+    assert_eq!(lines.len(), 2 * 228, "One call per scanline");
+    assert!(lines.windows(2).all(|w| w[1] == (w[0] + 1) % 228), "Lines in order");
+
+    // When: a frame runs scanline by scanline after the HBlank hook is
+    // cleared and the VBlank hook replaced
+    gba.clear_hblank_hook();
+    let seen = vblanks.clone();
+    gba.on_vblank(move |_| seen.lock().unwrap().push((0, false)));
+    gba.run_scanlines(228);
+
+    // Then: only the new VBlank hook ran
+    assert_eq!(vblanks.lock().unwrap().last(), Some(&(0, false)), "Replacement hook ran");
+    assert_eq!(vblanks.lock().unwrap().len(), 3, "Once per frame");
+    assert!(hblanks.lock().unwrap().is_empty(), "Cleared hook stays quiet");
+}
+
+/// Scenario: Video capture writes whole frames as displayed, with their audio
+#[test]
+fn video_capture_streams_frames_and_audio() {
+    // Given an idle machine showing a red backdrop, capturing raw RGB and audio
+    let mut gba = Gba::new();
+    gba.load_rom(idle_rom());
+    gba.write_half(0x0500_0000, 0x001F);
+    let (video, audio) = (SharedSink::default(), SharedSink::default());
+    gba.start_video_capture_with_audio(video.clone(), VideoFormat::RawRgb, audio.clone());
+
+    // When the frame in progress finishes, then the next turns blue halfway down
+    gba.run_scanlines(228);
+    assert!(video.bytes().is_empty(), "The frame capture started in is skipped");
+    gba.run_scanlines(80);
+    gba.write_half(0x0500_0000, 0x7C00);
+    gba.run_scanlines(148);
+
+    // Then one RGB24 frame was written with the color each line was drawn with
+    let frame = video.bytes();
+    assert_eq!(frame.len(), 240 * 160 * 3, "One 240x160 RGB24 frame");
+    assert_eq!(frame[..3], [0xFF, 0, 0], "Top line is red");
+    assert_eq!(frame[80 * 240 * 3..80 * 240 * 3 + 3], [0, 0, 0xFF], "Line 80 is blue");
+
+    // And the frame's audio went to its own sink without taking from drain_audio
+    let expected = gba.audio_frame_sample_count() * 4;
+    assert!(expected > 0, "The frame produced audio");
+    assert_eq!(audio.bytes().len(), expected, "Stereo s16le for that frame");
+    let mut drained = Vec::new();
+    gba.drain_audio(&mut drained);
+    assert!(!drained.is_empty(), "drain_audio still gets the output");
+
+    // And stopping reports the frames written and releases scanline capture
+    assert_eq!(gba.stop_video_capture().unwrap(), 1, "One frame captured");
+    assert!(gba.scanline_capture().is_none(), "Scanline capture is off again");
+
+    // When a Y4M capture records a frame
+    let y4m = SharedSink::default();
+    gba.start_video_capture(y4m.clone(), VideoFormat::Y4m);
+    gba.run_scanlines(228 * 2);
+
+    // Then the stream has a header and planar 4:4:4 BT.601 frames
+    let bytes = y4m.bytes();
+    let header = b"YUV4MPEG2 W240 H160 F262144:4389 Ip A1:1 C444\n";
+    assert_eq!(bytes[..header.len()], header[..], "Y4M stream header");
+    let frame = &bytes[header.len()..];
+    assert_eq!(frame.len(), 6 + 3 * 240 * 160, "One FRAME of three planes");
+    assert_eq!(frame[..6], b"FRAME\n"[..], "Frame marker");
+    assert_eq!(frame[6], 41, "Blue luma");
+    assert_eq!(frame[6 + 2 * 240 * 160], 110, "Blue Cr");
+    assert_eq!(gba.stop_video_capture().unwrap(), 1, "One Y4M frame");
+
+    // And a sink that fails has its error reported when capture stops
+    struct Broken;
+    impl Write for Broken {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+    gba.start_video_capture(Broken, VideoFormat::RawRgb);
+    gba.run_scanlines(228 * 2);
+    let err = gba.stop_video_capture().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::BrokenPipe, "Sink error surfaces");
+}
+
+/// Mode 0 with BG0 (one red tile) on top of BG1 (two green tiles) over a blue backdrop
+fn stacked_bgs() -> Gba {
+    let mut gba = Gba::new();
+    gba.load_rom(idle_rom());
+    gba.write_half(0x0500_0000, 0x7C00);
+    gba.write_half(0x0500_0002, 0x001F);
+    gba.write_half(0x0500_0004, 0x03E0);
+    for i in 0..16 {
+        gba.write_half(0x0600_0020 + i * 2, 0x1111);
+        gba.write_half(0x0600_0040 + i * 2, 0x2222);
+    }
+    gba.write_half(0x0600_F800, 1);
+    gba.write_half(0x0600_F000, 2);
+    gba.write_half(0x0600_F002, 2);
+    gba.write_half(0x0400_0008, 0x1F00);
+    gba.write_half(0x0400_000A, 0x1E00);
+    gba.write_half(0x0400_0000, 0x0300);
+    gba
+}
+
+/// Scenario: Layer capture exposes each layer before compositing
+#[test]
+fn layer_buffers_show_hidden_layers() {
+    // Given BG0 (red tile) on top of BG1 (green tiles) over a blue backdrop
+    let mut gba = stacked_bgs();
+
+    // When a frame is drawn with layer capture on
+    gba.ppu_mut().set_layer_capture(true);
+    gba.run_frame();
+
+    // Then BG0 covers the first tile on screen, but BG1 is still visible in its buffer
+    assert_eq!(gba.render_debug_pixel(0, 0).color, 0x001F, "BG0 should win the composite");
+    let layers = gba.ppu().layer_buffers().expect("capture should be enabled");
+    assert_eq!(layers.rgb555(Layer::Bg0)[0], Some(0x001F), "BG0 draws red");
+    assert_eq!(layers.rgb555(Layer::Bg1)[0], Some(0x03E0), "BG1 draws green underneath");
+    assert_eq!(layers.indexed(Layer::Bg1)[0], Some(2), "BG1 uses palette entry 2");
+    assert_eq!(layers.rgb555(Layer::Bg0)[8], None, "BG0 is transparent past its tile");
+    assert_eq!(layers.rgb555(Layer::Bg1)[8], Some(0x03E0), "BG1 covers two tiles");
+
+    // And disabled layers and the backdrop are reported too
+    assert_eq!(layers.rgb555(Layer::Bg2)[0], None, "BG2 is off");
+    assert_eq!(layers.rgb555(Layer::Obj)[0], None, "OBJ is off");
+    assert_eq!(
+        layers.rgb555(Layer::Backdrop)[159 * 240 + 239],
+        Some(0x7C00),
+        "Backdrop fills the screen"
+    );
+}
+
+/// Scenario: Hiding a layer affects rendering but not DISPCNT
+#[test]
+fn layer_toggle_hides_bg_without_touching_dispcnt() {
+    // Given BG0 drawn on top of BG1
+    let mut gba = stacked_bgs();
+    gba.run_frame();
+    assert_eq!(gba.render_debug_pixel(0, 0).color, 0x001F, "BG0 should be on top");
+
+    // When BG0 is hidden for debugging
+    gba.set_layer_enabled(Layer::Bg0, false);
+
+    // Then BG1 shows through in both render paths
+    assert!(!gba.layer_enabled(Layer::Bg0), "BG0 should report hidden");
+    assert_eq!(gba.render_debug_pixel(0, 0).color, 0x03E0, "BG1 should show through");
+    let mut renderer = SoftwareRenderer::new();
+    gba.run_frame_with_renderer(&mut renderer);
+    assert_eq!(renderer.framebuffer()[0], 0x0000FF00, "Snapshot path should hide BG0 too");
+
+    // And the game still sees its own DISPCNT value
+    assert_eq!(gba.mem.read_half(0x0400_0000), 0x0300, "DISPCNT should be unchanged");
+    assert_eq!(gba.ppu().get_dispcnt(), 0x0300, "PPU register copy should be unchanged");
+
+    // And showing the layer again restores the original picture
+    gba.set_layer_enabled(Layer::Bg0, true);
+    assert_eq!(gba.render_debug_pixel(0, 0).color, 0x001F, "BG0 should be back on top");
+}
+
+/// Scenario: Assets injected at runtime reach the PPU through the bus rules
+#[test]
+fn injected_assets_follow_bus_write_rules() {
+    // Given BG0 drawn on top of BG1
+    let mut gba = stacked_bgs();
+    gba.run_frame();
+
+    // When the red palette entry and BG0's tile are replaced on the fly
+    assert_eq!(gba.inject_palette(2, &0x7FFFu16.to_le_bytes()), 2, "Palette write");
+    assert_eq!(gba.inject_vram(0x20, &[0x22; 32]), 32, "Whole tile write");
+
+    // Then the PPU sees the new tile immediately, drawing with palette entry 2
+    assert_eq!(gba.ppu().vram()[0x20], 0x22, "PPU copy should be refreshed");
+    assert_eq!(gba.render_debug_pixel(0, 0).color, 0x03E0, "BG0 now uses color 2");
+
+    // And a trailing odd byte is mirrored into its halfword like a CPU byte store
+    gba.inject_vram(0x101, &[0xAB]);
+    assert_eq!(gba.mem.read_half(0x0600_0100), 0xABAB, "VRAM byte writes fill the halfword");
+
+    // And OAM drops unpaired bytes while data past the end is clipped
+    assert_eq!(gba.inject_oam(0x3FE, &[1, 2, 3, 4]), 2, "Only 2 bytes fit in OAM");
+    assert_eq!(gba.mem.read_half(0x0700_03FE), 0x0201, "Paired bytes land in OAM");
+    gba.inject_oam(0x11, &[0xCD]);
+    assert_eq!(gba.mem.read_half(0x0700_0010), 0, "OAM ignores byte writes");
+}
+
+/// Scenario: Tilesets and tilemaps export as indexed images
+#[test]
+fn tileset_and_tilemap_export_as_indexed_images() {
+    // Given BG1 showing two copies of tile 2 in a 256x256 map
+    let mut gba = stacked_bgs();
+    gba.sync_ppu_full();
+    let palette = *gba.mem.palette();
+
+    // When char block 0 is dumped as a 4bpp sheet
+    let sheet = dump_tileset(gba.ppu(), &palette, 0, 4);
+
+    // Then tiles are laid out 16 per row with their raw color indices
+    assert_eq!((sheet.width, sheet.height), (128, 256), "512 4bpp tiles fill 16x32 cells");
+    assert_eq!(sheet.get(0, 0), 0, "Tile 0 is empty");
+    assert_eq!(sheet.get(8, 0), 1, "Tile 1 uses color 1");
+    assert_eq!(sheet.get(23, 7), 2, "Tile 2 uses color 2");
+
+    // When BG1's map is dumped
+    let map = dump_tilemap(gba.ppu(), &palette, 1);
+
+    // Then the whole map is decoded with its palette
+    assert_eq!((map.width, map.height), (256, 256), "BG1 is a 256x256 text BG");
+    assert_eq!(map.get(0, 0), 2, "First map entry is tile 2");
+    assert_eq!(map.get(15, 7), 2, "Second map entry is tile 2");
+    assert_eq!(map.get(16, 0), 0, "The rest of the map is tile 0");
+    assert_eq!(map.to_xrgb8888()[0], 0x0000FF00, "Index 2 is green");
+}
+
+/// Scenario: Frame snapshot diffs name the tiles, map entries and colors that changed
+#[test]
+fn frame_diff_reports_changed_tiles_map_entries_and_colors() {
+    // Given a snapshot of two stacked BGs
+    let mut gba = stacked_bgs();
+    let before = gba.frame_snapshot();
+
+    // When a tile, a BG1 map entry and a palette color change
+    gba.write_half(0x0600_0060, 0x3333);
+    gba.write_half(0x0600_F004, 3);
+    gba.write_half(0x0500_0202, 0x7FFF);
+    let after = gba.frame_snapshot();
+    let diff = diff_frames(&before, &after);
+
+    // Then each change is reported at its own granularity
+    assert_eq!(diff.tiles, vec![3], "Tile 3 changed");
+    assert_eq!(diff.map_entries, vec![(1, 2)], "BG1 entry 2 changed");
+    assert_eq!(diff.palette, vec![257], "OBJ color 1 changed");
+    assert!(diff_frames(&after, &after).is_empty(), "Identical snapshots");
+}
+
+/// Scenario: The null renderer lets the core run headless
+#[test]
+fn null_renderer_runs_frame_without_output() {
+    let mut gba = Gba::new();
+    let mut renderer = NullRenderer;
+
+    gba.run_frame_with_renderer(&mut renderer);
+
+    assert_eq!(gba.ppu().get_vcount(), 0, "Frame should complete and wrap VCOUNT");
+}
+
+/// Scenario: Display interrupts are only requested while DISPSTAT enables them
+#[test]
+fn dispstat_enables_gate_display_interrupts() {
+    // Given: a machine idling with every DISPSTAT IRQ enable clear
+    let mut gba = Gba::new();
+    gba.load_rom(idle_rom());
+    let pending = |gba: &mut Gba| gba.mem.read_half(0x0400_0202);
+
+    // When: a whole frame runs
+    gba.run_frame();
+
+    // Then: neither VBlank nor HBlank was requested
+    assert_eq!(pending(&mut gba), 0, "No display IRQ without an enable");
+
+    // When: the VBlank enable is set mid-frame
+    gba.run_scanlines(80);
+    gba.write_half(0x0400_0004, 0x0008);
+    gba.run_scanlines(100);
+
+    // Then: only VBlank is pending
+    assert_eq!(pending(&mut gba), Interrupt::VBLANK.bits(), "VBlank requested");
+
+    // When: VBlank is swapped for a VCount match on line 100
+    gba.write_half(0x0400_0202, 0xFFFF);
+    gba.run_scanlines(228 - 180);
+    gba.write_half(0x0400_0004, 0x6420);
+    gba.run_scanlines(100);
+
+    // Then: VCount fires on line 100 and DISPSTAT shows the match flag
+    assert_eq!(gba.ppu().get_vcount(), 100, "On the matching line");
+    assert_eq!(pending(&mut gba), Interrupt::VCOUNT.bits(), "VCount requested");
+    assert_eq!(gba.mem.read_half(0x0400_0004) & 0x0004, 0x0004, "Match flag set");
+
+    // When: HBlank is enabled for one line, then disabled again
+    gba.write_half(0x0400_0202, 0xFFFF);
+    gba.write_half(0x0400_0004, 0x0010);
+    gba.run_scanline();
+    let with_hblank = pending(&mut gba);
+    gba.write_half(0x0400_0202, 0xFFFF);
+    gba.write_half(0x0400_0004, 0x0000);
+    gba.run_scanline();
+
+    // Then: HBlank was requested only while enabled
+    assert_eq!(with_hblank, Interrupt::HBLANK.bits(), "HBlank requested");
+    assert_eq!(pending(&mut gba), 0, "HBlank gated off again");
+}
+
+/// Scenario: RGBA8888 output honors the selected color correction
+#[test]
+fn framebuffer_rgba8888_applies_color_correction() {
+    // Given a mode 3 screen whose first pixel is pure red
+    let mut gba = Gba::new();
+    gba.write_half(0x0400_0000, 0x0403);
+    gba.write_half(0x0600_0000, 0x001F);
+    let mut out = vec![0u8; 240 * 160 * 4];
+
+    // When converted without correction
+    gba.framebuffer_rgba8888(&mut out);
+
+    // Then the raw color is expanded linearly
+    assert_eq!(&out[0..4], &[0xFF, 0x00, 0x00, 0xFF], "Uncorrected red should be saturated");
+
+    // When the original GBA LCD response is selected
+    gba.set_color_correction(ColorCorrection::GbaLcd);
+    gba.framebuffer_rgba8888(&mut out);
+
+    // Then red bleeds into the other channels and loses intensity
+    assert_eq!(gba.color_correction(), ColorCorrection::GbaLcd, "Mode should be stored");
+    assert!(out[0] < 0xFF, "Corrected red should be dimmer");
+    assert!(out[1] > 0 && out[2] > 0, "Corrected red should be desaturated");
+    assert_eq!(out[3], 0xFF, "Alpha should stay opaque");
+}
+
+/// Scenario: Every correction mode keeps black black
+#[test]
+fn color_correction_preserves_black() {
+    for mode in [
+        ColorCorrection::None,
+        ColorCorrection::GbaLcd,
+        ColorCorrection::Ags101,
+        ColorCorrection::Nds,
+    ] {
+        assert_eq!(mode.convert(0x0000), 0, "{:?} should map black to black", mode);
+    }
+}
+
+/// Scenario: The curve a GPU presenter uploads reproduces the CPU conversion
+#[test]
+fn color_curve_matches_convert() {
+    // Given white, the primaries, a gray and a mixed color
+    let colors = [0x7FFF, 0x001F, 0x03E0, 0x7C00, 0x4210, 0x1234];
+
+    for mode in [
+        ColorCorrection::GbaLcd,
+        ColorCorrection::Ags101,
+        ColorCorrection::Nds,
+    ] {
+        let curve = mode.curve();
+        for color in colors {
+            // When the shader's steps run on the uploaded levels and mix
+            let lin = [0, 5, 10].map(|shift| curve.levels[((color >> shift) & 0x1F) as usize]);
+            let shaded = curve
+                .mix
+                .iter()
+                .zip([16, 8, 0])
+                .fold(0u32, |out, (row, shift)| {
+                    let v = (row[0] * lin[0] + row[1] * lin[1] + row[2] * lin[2]) * curve.luminance;
+                    out | ((v.clamp(0.0, 1.0).powf(1.0 / 2.2) * 255.0 + 0.5) as u32) << shift
+                });
+
+            // Then the result matches the core's conversion
+            assert_eq!(shaded, mode.convert(color), "{:?} {:#06x}", mode, color);
+        }
+    }
+}
+
+/// ROM that spins on `b .`
+fn idle_rom() -> Vec<u8> {
+    let mut rom = vec![0u8; 0x200];
+    rom[0..4].copy_from_slice(&0xEAFF_FFFEu32.to_le_bytes());
+    rom
+}
+
+/// Write a 4bpp OBJ tile filled with one palette index
+fn fill_obj_tile(gba: &mut Gba, tile: u32, index: u8) {
+    let byte = index | (index << 4);
+    for i in 0..16 {
+        gba.write_half(0x0601_0000 + tile * 32 + i * 2, u16::from_le_bytes([byte, byte]));
+    }
+}
+
+/// Scenario: DISPCNT bit 6 selects 1D or 2D OBJ character mapping
+#[test]
+fn obj_character_mapping_follows_dispcnt() {
+    // Given a 16x16 sprite at the origin using tile 0, with tile 2 blue (next row in 1D)
+    // and tile 32 green (next row in 2D)
+    let mut gba = Gba::new();
+    gba.write_half(0x0500_0202, 0x001F);
+    gba.write_half(0x0500_0204, 0x03E0);
+    gba.write_half(0x0500_0206, 0x7C00);
+    fill_obj_tile(&mut gba, 1, 1);
+    fill_obj_tile(&mut gba, 2, 3);
+    fill_obj_tile(&mut gba, 32, 2);
+    gba.write_half(0x0700_0000, 0x0000);
+    gba.write_half(0x0700_0002, 0x4000);
+    gba.write_half(0x0700_0004, 0x0000);
+    let mut frame = vec![0u32; 240 * 160];
+
+    // When rendered with 2D mapping
+    gba.write_half(0x0400_0000, 0x1000);
+    gba.framebuffer_xrgb8888(&mut frame);
+
+    // Then the second tile row comes from 32 tiles further on
+    assert_eq!(frame[8], 0x00FF0000, "Top-right cell should be tile 1 in both modes");
+    assert_eq!(frame[8 * 240], 0x0000FF00, "2D mapping should use tile 32 for the second row");
+
+    // When rendered with 1D mapping
+    gba.write_half(0x0400_0000, 0x1040);
+    gba.framebuffer_xrgb8888(&mut frame);
+
+    // Then the second row directly follows the first
+    assert_eq!(frame[8], 0x00FF0000, "Top-right cell should be tile 1 in both modes");
+    assert_eq!(frame[8 * 240], 0x000000FF, "1D mapping should use tile 2 for the second row");
+
+    // And the scanline renderer agrees
+    let mut renderer = SoftwareRenderer::new();
+    gba.run_frame_with_renderer(&mut renderer);
+    assert_eq!(renderer.framebuffer()[8 * 240], 0x000000FF, "Snapshot renderer should honor 1D mapping");
+}
+
+/// Scenario: Bitmap modes only display sprites from the upper half of OBJ VRAM
+#[test]
+fn bitmap_modes_restrict_obj_tiles() {
+    // Given mode 3 with OBJ enabled, a red tile 0 and a green tile 512
+    let mut gba = Gba::new();
+    gba.write_half(0x0500_0202, 0x001F);
+    gba.write_half(0x0500_0204, 0x03E0);
+    fill_obj_tile(&mut gba, 0, 1);
+    fill_obj_tile(&mut gba, 512, 2);
+    gba.write_half(0x0400_0000, 0x1443);
+    let mut frame = vec![0u32; 240 * 160];
+
+    // When sprite 0 uses tile 0, which overlaps the frame buffer
+    gba.write_half(0x0700_0004, 0x0000);
+    gba.framebuffer_xrgb8888(&mut frame);
+
+    // Then the sprite is not displayed
+    assert_eq!(frame[0], 0, "Tiles below 512 should be hidden in bitmap modes");
+
+    // When it uses tile 512
+    gba.write_half(0x0700_0004, 0x0200);
+    gba.framebuffer_xrgb8888(&mut frame);
+
+    // Then it is drawn over the bitmap by both renderers
+    assert_eq!(frame[0], 0x0000FF00, "Tile 512 should be displayed");
+    let mut renderer = SoftwareRenderer::new();
+    gba.run_frame_with_renderer(&mut renderer);
+    assert_eq!(renderer.framebuffer()[0], 0x0000FF00, "Snapshot renderer should draw the sprite");
+}
+
+/// Scenario: Semi-transparent sprites always alpha-blend with the layer behind
+#[test]
+fn semi_transparent_obj_forces_alpha_blend() {
+    // Given a blue backdrop marked as second target, no BLDCNT effect, and EVA = EVB = 8
+    let mut gba = Gba::new();
+    gba.write_half(0x0500_0000, 0x7C00);
+    gba.write_half(0x0500_0202, 0x001F);
+    fill_obj_tile(&mut gba, 1, 1);
+    gba.write_half(0x0400_0050, 1 << 13);
+    gba.write_half(0x0400_0052, 0x0808);
+    gba.write_half(0x0400_0000, 0x1040);
+
+    // When a red sprite is drawn in semi-transparent mode
+    gba.write_half(0x0700_0000, 0x0400);
+    gba.write_half(0x0700_0004, 0x0001);
+    gba.sync_ppu_full();
+
+    // Then it is blended half and half with the backdrop
+    assert_eq!(gba.render_debug_pixel(0, 0).color, 0x3C0F, "Semi-transparent OBJ should blend");
+
+    // When the backdrop is no longer a second target
+    gba.write_half(0x0400_0050, 0);
+    gba.sync_ppu_full();
+
+    // Then the sprite is drawn opaque
+    assert_eq!(gba.render_debug_pixel(0, 0).color, 0x001F, "Without a second target there is no blend");
+}
+
+/// Scenario: The pixel breakdown explains which layer won and how it was blended
+#[test]
+fn render_debug_pixel_reports_layer_and_blend() {
+    // Given a semi-transparent red sprite over a blue backdrop marked as second target
+    let mut gba = Gba::new();
+    gba.write_half(0x0500_0000, 0x7C00);
+    gba.write_half(0x0500_0202, 0x001F);
+    fill_obj_tile(&mut gba, 1, 1);
+    gba.write_half(0x0400_0050, 1 << 13);
+    gba.write_half(0x0400_0052, 0x0808);
+    gba.write_half(0x0400_0000, 0x1040);
+    gba.write_half(0x0700_0000, 0x0400);
+    gba.write_half(0x0700_0004, 0x0001);
+    gba.sync_ppu_full();
+
+    // When the sprite's pixel and an uncovered pixel are inspected
+    let sprite = gba.render_debug_pixel(0, 0);
+    let backdrop = gba.render_debug_pixel(20, 0);
+
+    // Then the sprite pixel names OBJ palette entry 1 blended over the backdrop
+    assert_eq!(sprite.layer, Layer::Obj, "OBJ should win the pixel");
+    assert_eq!(
+        sprite.layer_color, 0x001F,
+        "The sprite color before blending is red"
+    );
+    assert_eq!(
+        sprite.palette_index,
+        Some(257),
+        "OBJ palette entry 1 is index 257"
+    );
+    assert_eq!(
+        sprite.below,
+        Layer::Backdrop,
+        "The backdrop is beneath the sprite"
+    );
+    assert_eq!(sprite.below_color, 0x7C00, "The backdrop is blue");
+    assert_eq!(
+        sprite.blend,
+        BlendEffect::Alpha { eva: 8, evb: 8 },
+        "Semi-transparent OBJ alpha-blends"
+    );
+    assert_eq!(sprite.color, 0x3C0F, "The final color is the blend");
+
+    // And the uncovered pixel is the unblended backdrop
+    assert_eq!(backdrop.layer, Layer::Backdrop, "Nothing covers x = 20");
+    assert_eq!(
+        backdrop.palette_index,
+        Some(0),
+        "The backdrop is BG palette entry 0"
+    );
+    assert_eq!(
+        backdrop.priority, 4,
+        "The backdrop sits below every priority"
+    );
+    assert_eq!(
+        backdrop.blend,
+        BlendEffect::None,
+        "No effect applies to the backdrop"
+    );
+    assert_eq!(backdrop.color, 0x7C00, "The backdrop keeps its color");
+}
+
+/// Scenario: OBJ window sprites mask other layers instead of being drawn
+#[test]
+fn obj_window_sprites_define_a_window() {
+    // Given a red sprite at x 0..8 and an OBJ window sprite at x 4..12,
+    // with OBJ shown only inside the OBJ window
+    let mut gba = Gba::new();
+    gba.write_half(0x0500_0000, 0x7C00);
+    gba.write_half(0x0500_0202, 0x001F);
+    fill_obj_tile(&mut gba, 1, 1);
+    gba.write_half(0x0700_0004, 0x0001);
+    gba.write_half(0x0700_0008, 0x0800);
+    gba.write_half(0x0700_000A, 0x0004);
+    gba.write_half(0x0700_000C, 0x0001);
+    gba.write_half(0x0400_004A, 0x1000);
+    gba.write_half(0x0400_0000, 0x9040);
+    gba.sync_ppu_full();
+
+    // Then the sprite only shows where the window sprite is opaque
+    assert_eq!(
+        gba.render_debug_pixel(5, 0).color,
+        0x001F,
+        "Sprite should show inside the OBJ window"
+    );
+    assert_eq!(
+        gba.render_debug_pixel(2, 0).color,
+        0x7C00,
+        "Sprite should be hidden outside the OBJ window"
+    );
+
+    // And the window sprite itself is never drawn
+    assert_eq!(
+        gba.render_debug_pixel(10, 0).color,
+        0x7C00,
+        "OBJ window sprites are not visible"
+    );
+}
+
+/// Scenario: OBJ affine groups are read and written as typed parameters
+#[test]
+fn oam_affine_groups_map_sprite_texture() {
+    // Given a 16x16 affine sprite using group 1, red on its left half and
+    // blue on its right, with sprite 4's attributes sharing group 1's words
+    let mut gba = Gba::new();
+    gba.write_half(0x0500_0202, 0x001F);
+    gba.write_half(0x0500_0206, 0x7C00);
+    for (tile, index) in [(1, 1), (2, 3), (3, 1), (4, 3)] {
+        fill_obj_tile(&mut gba, tile, index);
+    }
+    gba.write_half(0x0700_0000, 0x0100);
+    gba.write_half(0x0700_0002, 0x4200);
+    gba.write_half(0x0700_0004, 0x0001);
+    gba.write_half(0x0700_0020, 0x0200);
+    gba.write_half(0x0400_0000, 0x1040);
+
+    // When group 1 is set to a horizontal mirror
+    let mirror = AffineParams {
+        pa: -0x100,
+        pb: 0,
+        pc: 0,
+        pd: 0x100,
+    };
+    gba.mem.set_oam_affine(1, mirror);
+    gba.sync_ppu_full();
+
+    // Then its halfwords land in the fourth attribute of sprites 4 to 7
+    assert_eq!(gba.mem.read_half(0x0700_0026), 0xFF00, "PA in sprite 4");
+    assert_eq!(gba.mem.read_half(0x0700_003E), 0x0100, "PD in sprite 7");
+    assert_eq!(gba.mem.read_half(0x0700_0020), 0x0200, "Sprite 4 attr0 kept");
+    assert_eq!(gba.mem.oam_affine(1), mirror, "Memory reads the group back");
+    assert_eq!(gba.ppu.sprite_affine(1), mirror, "PPU sees the same group");
+    assert_eq!(gba.ppu.sprite_affine(0), AffineParams { pa: 0, pb: 0, pc: 0, pd: 0 }, "Group 0 untouched");
+
+    // And the sprite is drawn mirrored
+    assert_eq!(gba.render_debug_pixel(2, 4).color, 0x7C00, "Left edge shows the blue half");
+    assert_eq!(gba.render_debug_pixel(13, 4).color, 0x001F, "Right edge shows the red half");
+
+    // And the scanline renderer agrees
+    gba.load_rom(idle_rom());
+    gba.mem.set_oam_affine(1, mirror);
+    let mut renderer = SoftwareRenderer::new();
+    gba.run_frame_with_renderer(&mut renderer);
+    assert_eq!(renderer.framebuffer()[4 * 240 + 2], 0x000000FF, "Snapshot renderer mirrors too");
+}
+
+/// Scenario: Sprite and background priorities resolve like hardware
+#[test]
+fn obj_priority_tie_breaks_and_quirk() {
+    // Given a green BG0 at priority 1, a red priority-3 sprite 0 and a blue priority-0
+    // sprite 1, both at the origin
+    let mut gba = Gba::new();
+    gba.write_half(0x0500_0002, 0x03E0);
+    for i in 0..16 {
+        gba.write_half(0x0600_0000 + i * 2, 0x1111);
+    }
+    gba.write_half(0x0500_0202, 0x001F);
+    gba.write_half(0x0500_0204, 0x7C00);
+    fill_obj_tile(&mut gba, 1, 1);
+    fill_obj_tile(&mut gba, 2, 2);
+    gba.write_half(0x0700_0004, 0x0C01);
+    gba.write_half(0x0700_000C, 0x0002);
+    gba.write_half(0x0400_0008, 0x1F01);
+    gba.write_half(0x0400_0000, 0x1140);
+    gba.sync_ppu_full();
+
+    // Then the best-priority sprite is in front of the BG
+    assert_eq!(
+        gba.render_debug_pixel(0, 0).color,
+        0x7C00,
+        "Priority 0 sprite should win"
+    );
+
+    // When the blue sprite ties with the BG at priority 1
+    gba.write_half(0x0700_000C, 0x0402);
+    gba.sync_ppu_full();
+
+    // Then OBJ wins the tie
+    assert_eq!(
+        gba.render_debug_pixel(0, 0).color,
+        0x7C00,
+        "OBJ should win ties with BG"
+    );
+
+    // When both sprites share priority 0
+    gba.write_half(0x0700_0004, 0x0001);
+    gba.write_half(0x0700_000C, 0x0002);
+    gba.sync_ppu_full();
+
+    // Then the lower-numbered sprite is on top
+    assert_eq!(
+        gba.render_debug_pixel(0, 0).color,
+        0x001F,
+        "Sprite 0 should win among equals"
+    );
+
+    // When the hardware priority quirk is enabled and sprite 0 drops behind the BG
+    gba.write_half(0x0700_0004, 0x0C01);
+    gba.ppu.set_obj_priority_bug(true);
+    gba.sync_ppu_full();
+
+    // Then sprite 0 masks sprite 1 and the BG shows through
+    assert_eq!(
+        gba.render_debug_pixel(0, 0).color,
+        0x03E0,
+        "Low-priority sprite should mask the high one"
+    );
+    let mut renderer = SoftwareRenderer::new();
+    gba.run_frame_with_renderer(&mut renderer);
+    assert_eq!(
+        renderer.framebuffer()[0],
+        0x0000FF00,
+        "Snapshot renderer should emulate the quirk"
+    );
+
+    // And without the quirk the blue sprite is visible again
+    gba.ppu.set_obj_priority_bug(false);
+    gba.run_frame_with_renderer(&mut renderer);
+    assert_eq!(
+        renderer.framebuffer()[0],
+        0x000000FF,
+        "Snapshot renderer should pick the best priority"
+    );
+}
+
+/// Scenario: Affine backgrounds either wrap or go transparent outside the map
+#[test]
+fn affine_bg_overflow_selects_wraparound() {
+    // Given mode 2 with a 128x128 BG2 whose map entry (0, 0) is a red 8bpp tile,
+    // scrolled so the left edge of the screen samples x = 128
+    let mut gba = Gba::new();
+    gba.write_half(0x0500_0000, 0x7C00);
+    gba.write_half(0x0500_0002, 0x001F);
+    for i in 0..32 {
+        gba.write_half(0x0600_0040 + i * 2, 0x0101);
+    }
+    gba.write_half(0x0600_4000, 0x0001);
+    gba.write_word(0x0400_0028, 128 << 8);
+    gba.write_half(0x0400_0000, 0x0402);
+    gba.load_rom(idle_rom());
+    gba.run_frame();
+
+    // When the overflow bit is clear
+    gba.write_half(0x0400_000C, 0x0800);
+    gba.sync_ppu_full();
+
+    // Then out-of-range pixels are transparent
+    assert_eq!(
+        gba.render_debug_pixel(0, 0).color,
+        0x7C00,
+        "Outside the map should show the backdrop"
+    );
+
+    // When the overflow bit selects wraparound
+    gba.write_half(0x0400_000C, 0x2800);
+    gba.sync_ppu_full();
+
+    // Then the map repeats
+    assert_eq!(
+        gba.render_debug_pixel(0, 0).color,
+        0x001F,
+        "x = 128 should wrap to the first tile"
+    );
+    assert_eq!(
+        gba.render_debug_pixel(8, 0).color,
+        0x7C00,
+        "The next tile is empty"
+    );
+}
+
+/// Scenario: Mid-frame BG2Y writes reload the internal reference point
+#[test]
+fn affine_reference_point_latches_per_line() {
+    // Given a wrapping 128x128 affine BG2 whose first map row is red and second row green
+    let mut gba = Gba::new();
+    gba.load_rom(idle_rom());
+    gba.write_half(0x0500_0000, 0x7C00);
+    gba.write_half(0x0500_0002, 0x001F);
+    gba.write_half(0x0500_0004, 0x03E0);
+    for i in 0..32 {
+        gba.write_half(0x0600_0040 + i * 2, 0x0101);
+        gba.write_half(0x0600_0080 + i * 2, 0x0202);
+    }
+    for i in 0..8 {
+        gba.write_half(0x0600_4000 + i * 2, 0x0101);
+        gba.write_half(0x0600_4010 + i * 2, 0x0202);
+    }
+    gba.write_half(0x0400_000C, 0x2800);
+    gba.write_half(0x0400_0000, 0x0402);
+
+    // When BG2Y is rewritten to 8.0 just before line 80 is drawn
+    for line in 0..228 {
+        if line == 80 {
+            gba.write_word(0x0400_002C, 8 << 8);
+        }
+        gba.run_scanline();
+    }
+    gba.sync_ppu_full();
+
+    // Then earlier lines keep stepping from the old reference
+    assert_eq!(
+        gba.render_debug_pixel(0, 0).color,
+        0x001F,
+        "Line 0 samples map row 0"
+    );
+    assert_eq!(
+        gba.render_debug_pixel(0, 79).color,
+        0x7C00,
+        "Line 79 samples empty row 9"
+    );
+
+    // And line 80 restarts from the written value instead of adding 80 lines of PD
+    assert_eq!(
+        gba.render_debug_pixel(0, 80).color,
+        0x03E0,
+        "Line 80 samples row 1"
+    );
+    assert_eq!(
+        gba.render_debug_pixel(0, 88).color,
+        0x7C00,
+        "Line 88 samples empty row 2"
+    );
+
+    // When the next frame starts, the register value is reloaded at VBlank
+    for _ in 0..228 {
+        gba.run_scanline();
+    }
+    gba.sync_ppu_full();
+    assert_eq!(
+        gba.render_debug_pixel(0, 0).color,
+        0x03E0,
+        "Line 0 starts from BG2Y = 8.0"
+    );
+}
+
+/// Scenario: The sprite cycle limit drops sprites past the per-line budget
+#[test]
+fn accuracy_profile_enables_sprite_cycle_limit() {
+    // Given 18 transparent 64x64 sprites followed by a red one, all at the origin
+    let mut gba = Gba::new();
+    gba.write_half(0x0500_0202, 0x001F);
+    fill_obj_tile(&mut gba, 64, 1);
+    for sprite in 0..19 {
+        gba.write_half(0x0700_0002 + sprite * 8, 0xC000);
+    }
+    gba.write_half(0x0700_0004 + 18 * 8, 64);
+    gba.write_half(0x0400_0000, 0x1040);
+    gba.sync_ppu_full();
+
+    // Then the fast profile draws every sprite
+    assert_eq!(
+        gba.accuracy(),
+        Some(AccuracyProfile::Fast),
+        "Fast should be the default"
+    );
+    assert_eq!(
+        gba.render_debug_pixel(0, 0).color,
+        0x001F,
+        "Sprite 18 should be drawn"
+    );
+
+    // When the balanced profile enables the 1210-cycle budget
+    gba.set_accuracy(AccuracyProfile::Balanced);
+
+    // Then the 19th 64-pixel sprite no longer fits on the line
+    assert!(
+        gba.accuracy_settings().sprite_cycle_limit,
+        "Balanced should limit sprites"
+    );
+    assert_eq!(
+        gba.render_debug_pixel(0, 0).color,
+        0,
+        "Sprite 18 should be dropped"
+    );
+    let mut renderer = SoftwareRenderer::new();
+    gba.run_frame_with_renderer(&mut renderer);
+    assert_eq!(
+        renderer.framebuffer()[0],
+        0,
+        "Snapshot renderer should drop it too"
+    );
+
+    // And custom settings are not reported as a profile
+    gba.set_accuracy_settings(AccuracySettings {
+        prefetch: true,
+        ..AccuracySettings::default()
+    });
+    assert_eq!(gba.accuracy(), None, "Custom settings have no profile");
+    assert_eq!(
+        gba.render_debug_pixel(0, 0).color,
+        0x001F,
+        "Limit should be off again"
+    );
+}
+
+/// Scenario: CPU video memory accesses wait on the PPU while it draws
+#[test]
+fn video_access_timing_stalls_and_locks_oam() {
+    // Given a program touching palette RAM twice, run with and without the option
+    let rom = asm::assemble(
+        0x0800_0000,
+        "mov r0, #0x05000000\n\
+         mov r1, #0x1F\n\
+         strh r1, [r0]\n\
+         ldrh r3, [r0]\n\
+         spin: b spin",
+    )
+    .expect("program assembles");
+    let cycles = |settings: AccuracySettings| {
+        let mut gba = Gba::new();
+        gba.set_accuracy_settings(settings);
+        gba.load_rom(rom.clone());
+        (0..4).map(|_| gba.step()).collect::<Vec<u32>>()
+    };
+    let timed = AccuracySettings {
+        video_access_timing: true,
+        ..AccuracySettings::default()
+    };
+
+    // When the accesses land on a line being drawn
+    let base = cycles(AccuracySettings::default());
+    let stalled = cycles(timed);
+
+    // Then each palette access costs one more cycle, the rest is unchanged
+    assert_eq!(stalled[..2], base[..2], "Register moves are not delayed");
+    assert_eq!(stalled[2], base[2] + 1, "STRH to palette waits a cycle");
+    assert_eq!(stalled[3], base[3] + 1, "LDRH from palette waits a cycle");
+    assert!(
+        AccuracyProfile::Cycle.settings().video_access_timing,
+        "The cycle profile turns the option on"
+    );
+
+    // Given a machine drawing line 0
+    let mut gba = Gba::new();
+    gba.set_accuracy_settings(timed);
+    gba.load_rom(rom.clone());
+    gba.step();
+
+    // Then OAM ignores writes and reads back 0, while VRAM still answers
+    gba.mem.write_half(0x0700_0000, 0x1234);
+    assert_eq!(gba.peek_half(0x0700_0000), 0, "OAM write dropped while drawing");
+    gba.mem.write_half(0x0600_0000, 0x1234);
+    assert_eq!(gba.mem.read_half(0x0600_0000), 0x1234, "VRAM is only slowed");
+
+    // When HBlank of a visible line begins
+    while gba.mem.read_half(0x0400_0004) & 2 == 0 {
+        gba.step();
+    }
+
+    // Then OAM stays locked until DISPCNT frees it during HBlank
+    gba.mem.write_half(0x0700_0000, 0x1234);
+    assert_eq!(gba.peek_half(0x0700_0000), 0, "Sprites still being fetched");
+    gba.mem.write_half(0x0400_0000, 0x0020);
+    gba.mem.write_half(0x0700_0000, 0x1234);
+    assert_eq!(gba.mem.read_half(0x0700_0000), 0x1234, "H-Blank Interval Free");
+
+    // When VBlank begins
+    gba.mem.write_half(0x0400_0000, 0x0000);
+    while gba.mem.read_half(0x0400_0004) & 1 == 0 {
+        gba.step();
+    }
+
+    // Then OAM is free again
+    gba.mem.write_half(0x0700_0002, 0x5678);
+    assert_eq!(gba.mem.read_half(0x0700_0002), 0x5678, "OAM writable in VBlank");
+}
+
+/// Scenario: Frame stats count sprite lines, layer pixels and blends
+#[test]
+fn frame_stats_summarize_the_scene() {
+    // Given 20 64x64 sprites at the origin under the cycle limit, only the
+    // first with an opaque 8x8 corner, and OBJ brightened
+    let mut gba = Gba::new();
+    gba.load_rom(idle_rom());
+    gba.write_half(0x0500_0202, 0x001F);
+    fill_obj_tile(&mut gba, 64, 1);
+    for sprite in 0..128 {
+        let attr0 = if sprite < 20 { 0x0000 } else { 0x0200 };
+        gba.write_half(0x0700_0000 + sprite * 8, attr0);
+        gba.write_half(0x0700_0002 + sprite * 8, 0xC000);
+    }
+    gba.write_half(0x0700_0004, 64);
+    gba.write_half(0x0400_0000, 0x1040);
+    gba.write_half(0x0400_0050, 0x0090);
+    gba.write_half(0x0400_0054, 8);
+    gba.ppu_mut().set_sprite_cycle_limit(true);
+    gba.ppu_mut().set_frame_stats(true);
+
+    // When two frames run
+    gba.run_frame();
+    gba.run_frame();
+
+    // Then the last full frame is summarized
+    let stats = gba.ppu().frame_stats();
+    assert_eq!(stats.sprites_rendered, 18 * 64, "18 sprites fit each of 64 lines");
+    assert_eq!(stats.sprites_dropped, 2 * 64, "2 sprites over budget each line");
+    assert_eq!(stats.layer_pixels[Layer::Obj as usize], 64, "Opaque sprite corner");
+    assert_eq!(stats.layer_pixels[Layer::Backdrop as usize], 240 * 160 - 64, "Backdrop elsewhere");
+    assert_eq!(stats.layer_pixels[Layer::Bg0 as usize], 0, "BGs off");
+    assert_eq!(stats.blends, 64, "Every sprite pixel brightened");
+
+    // When collection is turned off
+    gba.ppu_mut().set_frame_stats(false);
+
+    // Then nothing is reported
+    assert_eq!(gba.ppu().frame_stats(), Default::default(), "Stats cleared");
+}
+
+/// Scenario: Out-of-range VRAM and palette reads come back as zero
+#[test]
+fn out_of_range_tile_reads_are_transparent() {
+    // Given a PPU whose VRAM is filled with opaque pixels
+    let mut ppu = Ppu::new();
+    ppu.sync_vram(&[0x11; 0x18000]);
+
+    // When a tile lookup runs past the end of VRAM or uses an out-of-tile coordinate
+    let past_end = ppu.get_tile_pixel_8bpp(0x1_7FC0, 1023, 0, 0, false, false);
+    let flipped = ppu.get_tile_pixel_4bpp(0, 0, 200, 9, 0, true, true);
+
+    // Then the read is transparent instead of panicking, and coordinates wrap
+    assert_eq!(past_end, 0, "Tile data past VRAM should read as 0");
+    assert_eq!(flipped, 1, "Coordinates should wrap within the tile");
+
+    // And checked views clip partial reads at the end of a buffer
+    let palette = [0xAA; 0x400];
+    let view = MemView::new(&palette);
+    assert_eq!(view.color(511), 0xAAAA, "Last OBJ color is in range");
+    assert_eq!(view.color(512), 0, "Colors past palette RAM read as 0");
+    assert_eq!(
+        palette[..].half_at(0x3FF),
+        0x00AA,
+        "Half read straddling the end keeps the low byte"
+    );
+}
+
+/// Scenario: BGCNT and DISPCNT decode into named fields and back
+#[test]
+fn typed_control_views_round_trip_raw_registers() {
+    // Given: BG1 with priority 2, tiles at 0x8000, 256 colors, map block 28
+    // and a 512x256 text map
+    let mut ppu = Ppu::new();
+    ppu.set_bgcnt(1, 0x5C8A);
+
+    // When: the register is read through the typed view
+    let bg1 = ppu.bg_control(1);
+
+    // Then: each field is decoded
+    assert_eq!(bg1.priority, 2, "priority");
+    assert_eq!(bg1.char_base_addr(), 0x8000, "tile data address");
+    assert_eq!(bg1.bpp(), 8, "bits per pixel");
+    assert_eq!(bg1.screen_base, ScreenBlock::new(28), "map block");
+    assert_eq!(bg1.screen_base_addr(), 0xE000, "map address");
+    assert_eq!(bg1.text_size(), (512, 256), "text map size");
+    assert!(!bg1.mosaic && !bg1.wraparound, "mosaic and wraparound off");
+
+    // When: a field is changed and written back
+    ppu.set_bg_control(1, BgControl { priority: 0, ..bg1 });
+
+    // Then: only that field's bits change
+    assert_eq!(ppu.get_bgcnt(1), 0x5C88, "raw BGCNT");
+
+    // When: DISPCNT selects mode 1 with BG0, BG2 and OBJ
+    ppu.set_dispcnt(0x1541);
+    let dispcnt = ppu.display_control();
+
+    // Then: its fields are decoded and re-encode to the same value
+    assert_eq!(dispcnt.mode, 1, "mode");
+    assert_eq!(dispcnt.bg_enabled, [true, false, true, false], "BG enables");
+    assert!(dispcnt.obj_enabled && dispcnt.obj_1d, "OBJ on, 1D mapping");
+    assert_eq!(dispcnt.bits(), 0x1541, "re-encoded DISPCNT");
+    ppu.set_display_control(DisplayControlView {
+        forced_blank: true,
+        ..dispcnt
+    });
+    assert_eq!(ppu.get_dispcnt(), 0x15C1, "forced blank set");
+}
+
+/// Scenario: Tile and map base setters round-trip every block
+#[test]
+fn bg_base_setters_round_trip_every_block() {
+    // Given: a BG whose other BGCNT fields are set
+    let mut ppu = Ppu::new();
+    ppu.set_bgcnt(2, 0xE0C3);
+
+    for block in 0..4u8 {
+        // When: the tile data block is set by index and by VRAM offset
+        ppu.set_bg_char_block(2, CharBlock::new(block));
+        let by_index = ppu.get_bg_tile_base(2);
+        ppu.set_bg_tile_base(2, block as u16 * 0x4000);
+
+        // Then: both select that block and leave the other fields alone
+        assert_eq!(
+            by_index,
+            block as u16 * 0x4000,
+            "char block {} by index",
+            block
+        );
+        assert_eq!(
+            ppu.bg_char_block(2).index(),
+            block,
+            "char block {} by offset",
+            block
+        );
+        assert_eq!(
+            ppu.get_bgcnt(2) & !0x000C,
+            0xE0C3,
+            "other fields, char block {}",
+            block
+        );
+    }
+
+    for block in 0..32u8 {
+        // When: the map block is set by index and by VRAM offset
+        ppu.set_bg_screen_block(2, ScreenBlock::new(block));
+        let by_index = ppu.get_bg_map_base(2);
+        ppu.set_bg_map_base(2, block as u16 * 0x800);
+
+        // Then: both select that block and leave the other fields alone
+        assert_eq!(
+            by_index,
+            block as u16 * 0x800,
+            "screen block {} by index",
+            block
+        );
+        assert_eq!(
+            ppu.bg_screen_block(2).index(),
+            block,
+            "screen block {} by offset",
+            block
+        );
+        assert_eq!(
+            ppu.get_bgcnt(2) & !0x1F0C,
+            0xE0C3,
+            "other fields, block {}",
+            block
+        );
+    }
+
+    // And: offsets inside a block round down to its start
+    ppu.set_bg_tile_base(2, 0x7FFF);
+    assert_eq!(ppu.get_bg_tile_base(2), 0x4000, "tile base rounded down");
+    assert_eq!(
+        CharBlock::new(5),
+        CharBlock::new(1),
+        "block index wraps like the register"
+    );
+}
+
+/// Scenario: Register writes reach the PPU the way the bus delivers them
+#[test]
+fn register_entry_points_follow_io_semantics() {
+    // Given: a PPU on line 5 whose registers are set as game code would
+    let mut ppu = Ppu::new();
+    ppu.set_vcount(5);
+    ppu.write_register(0x00, 0x2100); // BG0 and WIN0
+    ppu.write_register(0x04, 0xFF3F);
+    ppu.write_register(0x06, 99);
+    ppu.write_register(0x10, 0xFFFF);
+    ppu.write_register(0x20, 0xFF00);
+    ppu.write_register(0x28, 0x0100);
+    ppu.write_register(0x2A, 0x0FFF);
+    ppu.write_register(0x40, 0x1020); // WIN0H: x 16..32
+    ppu.write_register(0x44, 0x0818); // WIN0V: y 8..24
+    ppu.write_register(0x48, 0x0001);
+    ppu.write_register(0x4A, 0x0010);
+    ppu.write_register(0x4C, 0x3221);
+
+    // Then: read-only bits and registers keep the PPU's own values
+    assert_eq!(ppu.read_register(0x04), 0xFF38, "DISPSTAT status flags are not writable");
+    assert_eq!(ppu.read_register(0x06), 5, "VCOUNT ignores writes");
+    assert_eq!(ppu.read_register(0x10), 0x1FF, "BG0HOFS keeps 9 bits");
+
+    // And: BG2 parameters and reference points are sign-extended
+    assert_eq!(ppu.get_bg_affine_a(2), -256i32 as u32, "BG2PA is signed 8.8");
+    assert_eq!(ppu.get_bg_reference(2).0, 0xFFFF_0100u32 as i32, "BG2X is signed 28-bit");
+    assert_eq!(ppu.read_register(0x2A), 0x0FFF, "BG2X upper half reads back");
+
+    // And: WIN0H/WIN0V hold the left/top edge in the high byte
+    assert_eq!(ppu.get_window_visibility(20, 10), 0x01, "Inside WIN0 uses WININ");
+    assert_eq!(ppu.get_window_visibility(40, 10), 0x10, "Right of WIN0 uses WINOUT");
+    assert_eq!(ppu.get_window_visibility(20, 30), 0x10, "Below WIN0 uses WINOUT");
+
+    // And: MOSAIC splits into BG and OBJ block sizes
+    assert_eq!((ppu.get_bg_mosaic_h(), ppu.get_bg_mosaic_v()), (2, 3), "BG mosaic");
+    assert_eq!((ppu.get_obj_mosaic_h(), ppu.get_obj_mosaic_v()), (3, 4), "OBJ mosaic");
+    assert_eq!(ppu.read_register(0x4C), 0x3221, "MOSAIC reads back whole");
+}
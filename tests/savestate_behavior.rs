@@ -0,0 +1,272 @@
+//! Behavior Driven Development tests for GBA save states
+//!
+//! These tests describe capturing and restoring the full machine state.
+
+use rgba::{
+    asm, build_info, Gba, ManualClock, StateError, StateHeader, STATE_VERSION, THUMBNAIL_HEIGHT,
+    THUMBNAIL_WIDTH, VERSION,
+};
+
+/// ARM program: r1 = IWRAM; loop { r0 += 1; [r1] = r0 }
+fn counter_rom() -> Vec<u8> {
+    let mut rom = asm::assemble(
+        0x0800_0000,
+        "
+            mov r1, #0x03000000
+        loop:
+            add r0, r0, #1
+            str r0, [r1]
+            b loop
+        ",
+    )
+    .unwrap();
+    rom.resize(0x200, 0);
+    rom[0xA0..0xAC].copy_from_slice(b"SAVESTATE   ");
+    rom
+}
+
+/// Scenario: Loading a state rewinds CPU and memory
+#[test]
+fn load_state_rewinds_cpu_and_memory() {
+    // Given a running counter program
+    let mut gba = Gba::new();
+    gba.load_rom(counter_rom());
+    gba.run_frame();
+
+    // When the state is saved and emulation continues
+    let state = gba.save_state();
+    let saved_r0 = gba.cpu_reg(0);
+    let saved_pc = gba.cpu_pc();
+    gba.run_frame();
+    assert_ne!(gba.cpu_reg(0), saved_r0, "Counter should keep running");
+
+    // Then loading restores the snapshot exactly
+    gba.load_state(&state).expect("state should load");
+    assert_eq!(gba.cpu_reg(0), saved_r0, "r0 should be rewound");
+    assert_eq!(gba.cpu_pc(), saved_pc, "PC should be rewound");
+    assert_eq!(gba.mem_read_word(0x0300_0000), saved_r0, "IWRAM should be rewound");
+    assert_eq!(gba.save_state(), state, "Re-saving should reproduce the same bytes");
+}
+
+/// Scenario: Numbered slots keep states per game with a thumbnail and timestamp
+#[test]
+fn state_slots_are_kept_per_game_with_metadata() {
+    // Given a running game with a red backdrop and an empty state directory
+    let root = std::env::temp_dir().join(format!("rgba-slots-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    let mut rom = counter_rom();
+    rom[0xAC..0xB0].copy_from_slice(b"ASVE");
+    let mut gba = Gba::new();
+    gba.set_state_dir(&root);
+    gba.load_rom(rom);
+    gba.mem.write_half(0x0500_0000, 0x001F);
+    gba.run_frame();
+
+    // When slots 2 and 0 are saved at different points and emulation continues
+    gba.save_state_slot(2).expect("slot 2 should save");
+    let saved_r0 = gba.cpu_reg(0);
+    gba.run_frame();
+    gba.save_state_slot(0).expect("slot 0 should save");
+    gba.run_frame();
+
+    // Then both are stored under the game code with their metadata
+    assert_eq!(gba.game_code(), "ASVE", "Game code from the header");
+    assert!(root.join("ASVE").join("slot2.state").exists(), "Slot file under the game code");
+    let slots = gba.state_slots();
+    assert_eq!(slots.iter().map(|s| s.slot).collect::<Vec<_>>(), vec![0, 2], "Sorted by slot");
+    assert!(slots[1].timestamp > 0, "Timestamp recorded");
+    assert_eq!(slots[1].thumbnail.len(), THUMBNAIL_WIDTH * THUMBNAIL_HEIGHT, "Half-size thumbnail");
+    assert_eq!(slots[1].thumbnail[0], 0x00FF_0000, "Thumbnail shows the backdrop");
+
+    // And loading a slot restores its state
+    gba.load_state_slot(2).expect("slot 2 should load");
+    assert_eq!(gba.cpu_reg(0), saved_r0, "r0 rewound to slot 2");
+    assert!(gba.load_state_slot(5).is_err(), "Empty slot");
+
+    // And another game sees none of them
+    let mut other = Gba::new();
+    other.set_state_dir(&root);
+    other.load_rom(counter_rom());
+    assert_ne!(other.game_code(), "ASVE", "Headerless ROM keyed by hash");
+    assert!(other.state_slots().is_empty(), "No slots for another game");
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+/// Scenario: Restored emulation is deterministic
+#[test]
+fn restored_state_replays_identically() {
+    let mut gba = Gba::new();
+    gba.load_rom(counter_rom());
+    gba.run_frame();
+    let state = gba.save_state();
+
+    gba.run_frame();
+    let first = gba.save_state();
+
+    gba.load_state(&state).unwrap();
+    gba.run_frame();
+
+    assert_eq!(gba.save_state(), first, "Replaying from a state should be deterministic");
+}
+
+/// Scenario: Garbage and foreign states are rejected without side effects
+#[test]
+fn invalid_states_are_rejected() {
+    // Given a running machine
+    let mut gba = Gba::new();
+    gba.load_rom(counter_rom());
+    gba.run_frame();
+    let before = gba.save_state();
+
+    // When loading garbage
+    assert_eq!(gba.load_state(b"not a state"), Err(StateError::BadMagic), "Magic should be checked");
+
+    // When loading a truncated state
+    assert_eq!(
+        gba.load_state(&before[..before.len() / 2]),
+        Err(StateError::Truncated),
+        "Truncation should be detected"
+    );
+
+    // When loading a state made for another ROM
+    let mut other = Gba::new();
+    let mut rom = counter_rom();
+    rom[0xA0..0xAC].copy_from_slice(b"OTHER GAME  ");
+    other.load_rom(rom);
+    assert_eq!(gba.load_state(&other.save_state()), Err(StateError::RomMismatch), "ROM should be checked");
+
+    // Then the machine is untouched
+    assert_eq!(gba.save_state(), before, "Failed loads should not modify state");
+}
+
+/// Scenario: States record the core build that wrote them
+#[test]
+fn states_record_core_version() {
+    // Given the running build's description
+    let info = build_info();
+    assert_eq!(info.version, env!("CARGO_PKG_VERSION"), "Crate version");
+    assert_eq!(info.state_version, STATE_VERSION, "State layout version");
+    assert_eq!(info.has_feature("import"), cfg!(feature = "import"), "Feature list follows the build");
+    assert!(info.to_string().starts_with(&format!("rgba {}", VERSION)), "Display form leads with the version");
+
+    // When a state is saved
+    let mut gba = Gba::new();
+    gba.load_rom(counter_rom());
+    let state = gba.save_state();
+
+    // Then its header names this core
+    let header = StateHeader::parse(&state).expect("header should parse");
+    assert_eq!(header.version, STATE_VERSION, "Layout version");
+    assert_eq!(header.core_version, VERSION, "Core version");
+
+    // When the layout version is from another build
+    let mut old = state.clone();
+    old[8..12].copy_from_slice(&(STATE_VERSION - 1).to_le_bytes());
+
+    // Then both the header and the loader reject it
+    assert_eq!(StateHeader::parse(&old), Err(StateError::UnsupportedVersion(STATE_VERSION - 1)), "Header check");
+    assert_eq!(gba.load_state(&old), Err(StateError::UnsupportedVersion(STATE_VERSION - 1)), "Loader check");
+}
+
+/// Scenario: Restoring a state resumes the in-game clock where it was saved
+#[test]
+fn rtc_time_is_saved_with_the_state() {
+    // Given a machine seeded from a hand-driven host clock
+    let host = ManualClock::new(1_000_000);
+    let mut gba = Gba::new();
+    gba.load_rom(counter_rom());
+    gba.set_host_clock(&host);
+    assert_eq!(gba.rtc_time(), 1_000_000, "In-game time starts at the host time");
+
+    // When a second of emulation runs, the in-game clock follows it, not the host
+    for _ in 0..60 {
+        gba.run_frame();
+    }
+    assert_eq!(gba.rtc_time(), 1_000_001, "60 frames is just over one second");
+    let state = gba.save_state();
+
+    // When the host moves on a day and the state is restored
+    host.advance(86_400);
+    for _ in 0..120 {
+        gba.run_frame();
+    }
+    gba.load_state(&state).unwrap();
+
+    // Then the in-game clock is back at the saved time
+    assert_eq!(gba.rtc_time(), 1_000_001, "Loading should not jump the clock");
+
+    // And swapping the host clock re-seeds it
+    gba.set_host_clock(&ManualClock::new(5));
+    assert_eq!(gba.rtc_time(), 5, "New host clock sets the time");
+}
+
+/// Scenario: An mGBA state is imported into the running machine
+#[cfg(feature = "import")]
+#[test]
+fn mgba_state_is_imported() {
+    // Given a raw mGBA state in System mode with r0 = 42 and a value in IWRAM
+    let mut state = vec![0u8; 0x61000];
+    state[0..4].copy_from_slice(&0x0100_0007u32.to_le_bytes());
+    state[0x10..0x1C].copy_from_slice(b"SAVESTATE   ");
+    state[0x20..0x24].copy_from_slice(&42u32.to_le_bytes());
+    state[0x5C..0x60].copy_from_slice(&0x0800_0008u32.to_le_bytes());
+    state[0x60..0x64].copy_from_slice(&0x0000_001Fu32.to_le_bytes());
+    state[0x400..0x402].copy_from_slice(&0x0403u16.to_le_bytes());
+    state[0x19000..0x19004].copy_from_slice(&7u32.to_le_bytes());
+
+    // When importing it
+    let mut gba = Gba::new();
+    gba.load_rom(counter_rom());
+    gba.import_mgba_state(&state).expect("state should import");
+
+    // Then registers, memory and I/O are carried over
+    assert_eq!(gba.cpu_reg(0), 42, "r0 should be imported");
+    assert_eq!(gba.cpu().next_instruction_pc(), 0x0800_0004, "PC should point at the next instruction");
+    assert_eq!(gba.cpu_get_cpsr() & 0x1F, 0x1F, "Mode should be System");
+    assert_eq!(gba.mem_read_word(0x0300_0000), 7, "IWRAM should be imported");
+    assert_eq!(gba.ppu().get_dispcnt(), 0x0403, "DISPCNT should reach the PPU");
+
+    // And emulation resumes from the imported state
+    gba.run_frame();
+    assert!(gba.cpu_reg(0) > 42, "Counter should keep running");
+
+    // And states for other games are rejected
+    state[0x10..0x1C].copy_from_slice(b"OTHER GAME  ");
+    assert_eq!(gba.import_mgba_state(&state), Err(StateError::RomMismatch), "ROM should be checked");
+}
+
+/// Scenario: A gzip-compressed VBA-M state is imported
+#[cfg(feature = "import")]
+#[test]
+fn vba_state_is_imported() {
+    use std::io::Write;
+
+    // Given a version 10 VBA-M state in ARM System mode with r1 = 0x03000000
+    let mut state = vec![0u8; 24 + 45 * 4 + 267 + 8];
+    state[0..4].copy_from_slice(&10u32.to_le_bytes());
+    state[4..16].copy_from_slice(b"SAVESTATE   ");
+    state[28..32].copy_from_slice(&0x0300_0000u32.to_le_bytes());
+    state[88..92].copy_from_slice(&0x0000_001Fu32.to_le_bytes());
+    let vars = 24 + 45 * 4;
+    state[vars + 250] = 1; // C flag
+    state[vars + 253] = 1; // ARM state
+    state[vars + 255..vars + 259].copy_from_slice(&0x0800_0008u32.to_le_bytes());
+    let mut iwram = vec![0u8; 0x8000];
+    iwram[0..4].copy_from_slice(&9u32.to_le_bytes());
+    state.extend_from_slice(&iwram);
+    state.resize(state.len() + 0x400 + 0x40000 + 0x20000 + 0x400 + 4 * 241 * 162 + 0x400, 0);
+    let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+    gz.write_all(&state).unwrap();
+    let data = gz.finish().unwrap();
+
+    // When importing it
+    let mut gba = Gba::new();
+    gba.load_rom(counter_rom());
+    gba.import_vba_state(&data).expect("state should import");
+
+    // Then registers, flags and memory are carried over
+    assert_eq!(gba.cpu_reg(1), 0x0300_0000, "r1 should be imported");
+    assert_eq!(gba.cpu().next_instruction_pc(), 0x0800_0008, "PC should be VBA's next PC");
+    assert_eq!(gba.cpu_get_cpsr(), 0x2000_009F, "Flags and IRQ disable should be rebuilt");
+    assert_eq!(gba.mem_read_word(0x0300_0000), 9, "IWRAM should be imported");
+}
@@ -0,0 +1,137 @@
+//! Behavior Driven Development tests for the serial (link) port
+//!
+//! These tests describe what a game sees on the link port with accessories
+//! attached.
+
+use std::sync::{Arc, Mutex};
+
+use rgba::{Gba, Interrupt, SioGpio, SioPins, SIO_SC, SIO_SD, SIO_SI, SIO_SO};
+
+const SIODATA32: u32 = 0x0400_0120;
+const SIOCNT: u32 = 0x0400_0128;
+/// Normal mode, 32-bit, IRQ enabled, start
+const START_32_IRQ: u16 = 0x5080;
+
+fn transfer(gba: &mut Gba, word: u32) -> u32 {
+    gba.mem.write_word(SIODATA32, word);
+    gba.mem.write_half(SIOCNT, START_32_IRQ);
+    gba.mem.read_word(SIODATA32)
+}
+
+/// Scenario: a Game Boy Player answers the handshake and drives rumble
+#[test]
+fn game_boy_player_handshake_drives_rumble() {
+    // Given: a Game Boy Player plugged in, recording rumble changes
+    let mut gba = Gba::new();
+    let motor = Arc::new(Mutex::new(Vec::new()));
+    let log = Arc::clone(&motor);
+    gba.enable_game_boy_player(move |on| log.lock().unwrap().push(on));
+
+    // When: the game runs the handshake
+    let replies: Vec<u32> = (0..12).map(|_| transfer(&mut gba, 0x0000_494E)).collect();
+
+    // Then: each transfer completes at once with the player's reply
+    assert_eq!(replies[0], 0x0000_494E, "First reply");
+    assert_eq!(replies[9], 0xB0BB_8002, "Tenth reply");
+    assert_eq!(replies[11], 0x2000_0013, "Twelfth reply");
+    assert_eq!(gba.mem.read_half(SIOCNT) & 0x80, 0, "Start bit clears");
+    assert!(
+        gba.mem.interrupt.if_raw.contains(Interrupt::SERIAL),
+        "Serial IRQ requested"
+    );
+    assert!(motor.lock().unwrap().is_empty(), "No rumble during the handshake");
+
+    // When: the game sends start, start again, then stop
+    transfer(&mut gba, 0x4000_0022);
+    transfer(&mut gba, 0x4000_0022);
+    let reply = transfer(&mut gba, 0x4000_0000);
+
+    // Then: the callback sees each change of motor state once
+    assert_eq!(*motor.lock().unwrap(), vec![true, false], "Rumble on, then off");
+    assert_eq!(reply, 0x3000_0003, "Replies settle on the last word");
+
+    // And: without the logo on screen the keypad reads normally
+    gba.run_frame();
+    assert!(!gba.game_boy_player_detected(), "Logo never shown");
+    assert_eq!(gba.mem.read_half(0x0400_0130), 0x03FF, "No keys held");
+}
+
+/// Scenario: with nothing on the link port a transfer never completes
+#[test]
+fn transfer_without_partner_stays_busy() {
+    // Given: no accessory plugged in
+    let mut gba = Gba::new();
+
+    // When: the game starts a 32-bit transfer
+    let reply = transfer(&mut gba, 0x1234_5678);
+
+    // Then: the start bit stays set and no IRQ fires
+    assert_eq!(reply, 0x1234_5678, "SIODATA32 unchanged");
+    assert_ne!(gba.mem.read_half(SIOCNT) & 0x80, 0, "Still busy");
+    assert!(
+        !gba.mem.interrupt.if_raw.contains(Interrupt::SERIAL),
+        "No serial IRQ"
+    );
+}
+
+/// Peripheral that records pin changes and drives SI from a shared level
+struct Probe {
+    writes: Arc<Mutex<Vec<SioPins>>>,
+    input: Arc<Mutex<u8>>,
+}
+
+impl SioGpio for Probe {
+    fn write(&mut self, pins: SioPins) {
+        self.writes.lock().unwrap().push(pins);
+    }
+
+    fn read(&mut self) -> u8 {
+        *self.input.lock().unwrap()
+    }
+}
+
+const RCNT: u32 = 0x0400_0134;
+
+/// Scenario: general-purpose mode hands the pins to a user peripheral
+#[test]
+fn gpio_mode_exposes_pins_to_handler() {
+    // Given: a peripheral holding every input pin high
+    let mut gba = Gba::new();
+    let writes = Arc::new(Mutex::new(Vec::new()));
+    let input = Arc::new(Mutex::new(0x0F));
+    gba.set_sio_gpio_handler(Probe {
+        writes: Arc::clone(&writes),
+        input: Arc::clone(&input),
+    });
+
+    // When: the game selects GP mode driving SC and SD, then clocks SC low
+    gba.mem.write_half(RCNT, 0x8131);
+    gba.mem.write_half(RCNT, 0x8130);
+    gba.mem.write_half(RCNT, 0x8130);
+
+    // Then: the handler sees each change once
+    let pins = writes.lock().unwrap().clone();
+    assert_eq!(pins.len(), 2, "One call per change: {:?}", pins);
+    assert_eq!(pins[0], SioPins { levels: 0x1, outputs: SIO_SC | SIO_SD }, "SC high");
+    assert!(pins[0].is_high(SIO_SC), "SC driven high");
+    assert!(!pins[1].is_high(SIO_SC), "SC clocked low");
+
+    // And: inputs read the peripheral's levels while outputs keep the game's
+    let levels = gba.mem.read_half(RCNT) & 0xF;
+    assert_eq!(levels, (SIO_SI | SIO_SO) as u16, "SC, SD low; SI, SO high");
+    assert!(
+        !gba.mem.interrupt.if_raw.contains(Interrupt::SERIAL),
+        "SI has not fallen"
+    );
+
+    // When: the peripheral pulls SI low
+    *input.lock().unwrap() = SIO_SO;
+    gba.run_scanlines(1);
+
+    // Then: the SI interrupt fires and RCNT shows the new level
+    assert!(
+        gba.mem.interrupt.if_raw.contains(Interrupt::SERIAL),
+        "SI falling edge raises the serial IRQ"
+    );
+    assert_eq!(gba.mem.read_half(RCNT) & SIO_SI as u16, 0, "SI low");
+}
@@ -69,3 +69,25 @@ fn timers_can_cascade_for_counting() {
         assert!(timer1.get_counter() > 0, "Timer1 should increment when timer0 overflows");
     }
 }
+
+/// Scenario: Prescaled timers count cycles spread over many short steps
+#[test]
+fn prescaler_carries_partial_ticks_between_steps() {
+    // Given: timer 0 at F/64, one tick away from overflow
+    let mut timer = Timer::new(0);
+    timer.set_reload(0xFFFF);
+    timer.set_control(0x81);
+    assert_eq!(timer.cycles_until_overflow(), Some(64), "One tick of 64 cycles");
+
+    // When: it is stepped 4 cycles at a time, like short instructions
+    for _ in 0..15 {
+        timer.step(4);
+    }
+
+    // Then: nothing has happened until the 64th cycle
+    assert_eq!(timer.did_overflow(), false, "60 cycles is less than one tick");
+    assert_eq!(timer.cycles_until_overflow(), Some(4), "4 cycles left");
+    timer.step(4);
+    assert_eq!(timer.did_overflow(), true, "The 64th cycle overflows");
+    assert_eq!(timer.get_counter(), 0xFFFF, "Reloaded after overflow");
+}